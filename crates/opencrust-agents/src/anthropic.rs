@@ -4,16 +4,20 @@ use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use opencrust_common::{Error, Result};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 
+use crate::attachment_cache::AttachmentCache;
 use crate::providers::{
     ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart,
-    StreamEvent, Usage,
+    ResponseFormat, StreamEvent, Usage, document_fallback_text,
 };
 
 const DEFAULT_MODEL: &str = "claude-sonnet-4-5-20250929";
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
 const API_VERSION: &str = "2023-06-01";
+const FILES_API_BETA: &str = "files-api-2025-04-14";
+/// Smallest extended thinking budget the API accepts.
+const MIN_THINKING_BUDGET: u32 = 1024;
 
 /// Anthropic Claude LLM provider.
 pub struct AnthropicProvider {
@@ -22,6 +26,8 @@ pub struct AnthropicProvider {
     model: String,
     base_url: String,
     name: String,
+    upload_images: bool,
+    image_files: AttachmentCache,
 }
 
 impl AnthropicProvider {
@@ -36,6 +42,8 @@ impl AnthropicProvider {
             model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
             base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
             name: "anthropic".to_string(),
+            upload_images: false,
+            image_files: AttachmentCache::new(),
         }
     }
 
@@ -45,10 +53,97 @@ impl AnthropicProvider {
         self
     }
 
+    /// Upload inline images through the Files API and reference them by id,
+    /// so images kept in history are not re-sent as base64 on every turn.
+    pub fn with_image_uploads(mut self, enabled: bool) -> Self {
+        self.upload_images = enabled;
+        self
+    }
+
     fn endpoint(&self) -> String {
         format!("{}/v1/messages", self.base_url.trim_end_matches('/'))
     }
 
+    fn files_endpoint(&self) -> String {
+        format!("{}/v1/files", self.base_url.trim_end_matches('/'))
+    }
+
+    fn post(&self, url: String) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION);
+        if self.upload_images {
+            builder.header("anthropic-beta", FILES_API_BETA)
+        } else {
+            builder
+        }
+    }
+
+    /// Upload every inline image in `request` that is not cached yet. A failed
+    /// upload is logged and the image is sent inline instead.
+    async fn upload_new_images(&self, request: &LlmRequest) {
+        if !self.upload_images {
+            return;
+        }
+        for msg in &request.messages {
+            let MessagePart::Parts(blocks) = &msg.content else {
+                continue;
+            };
+            for block in blocks {
+                let ContentBlock::Image { url } = block else {
+                    continue;
+                };
+                let Some((media_type, data)) = parse_data_uri(url) else {
+                    continue;
+                };
+                let hash = AttachmentCache::content_hash(&data);
+                if self.image_files.get(&hash).is_some() {
+                    continue;
+                }
+                match self.upload_file(&media_type, &data).await {
+                    Ok(file_id) => self.image_files.insert(hash, file_id),
+                    Err(e) => warn!("anthropic image upload failed, sending inline: {e}"),
+                }
+            }
+        }
+    }
+
+    async fn upload_file(&self, media_type: &str, data: &str) -> Result<String> {
+        use base64::Engine;
+
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| Error::Agent(format!("invalid base64 image: {e}")))?;
+        let extension = media_type.rsplit('/').next().unwrap_or("bin");
+        let part = reqwest::multipart::Part::bytes(bytes)
+            .file_name(format!("image.{extension}"))
+            .mime_str(media_type)
+            .map_err(|e| Error::Agent(format!("invalid image media type: {e}")))?;
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        let response = self
+            .post(self.files_endpoint())
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("anthropic file upload failed: {e}")))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Agent(format!(
+                "anthropic files API error: status={status}, body={body}"
+            )));
+        }
+
+        let uploaded: AnthropicFile = response
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse anthropic file response: {e}")))?;
+        Ok(uploaded.id)
+    }
+
     fn build_request(&self, request: &LlmRequest) -> AnthropicRequest {
         let model = if request.model.is_empty() {
             self.model.clone()
@@ -60,10 +155,10 @@ impl AnthropicProvider {
             .messages
             .iter()
             .filter(|m| !matches!(m.role, ChatRole::System))
-            .map(to_anthropic_message)
+            .map(|m| to_anthropic_message(m, Some(&self.image_files)))
             .collect();
 
-        let tools: Vec<AnthropicTool> = request
+        let mut tools: Vec<AnthropicTool> = request
             .tools
             .iter()
             .map(|t| AnthropicTool {
@@ -73,13 +168,42 @@ impl AnthropicProvider {
             })
             .collect();
 
+        // Anthropic has no JSON mode: the schema becomes the only tool and the
+        // model is forced to call it, so its input is the structured answer.
+        let mut tool_choice = None;
+        if let Some(ResponseFormat::JsonSchema { name, schema }) = &request.response_format {
+            tools = vec![AnthropicTool {
+                name: name.clone(),
+                description: "Give the final answer in this format.".to_string(),
+                input_schema: schema.clone(),
+            }];
+            tool_choice = Some(serde_json::json!({ "type": "tool", "name": name }));
+        }
+
+        // Extended thinking can't be combined with a forced tool call. The
+        // budget comes on top of `max_tokens` so the answer keeps its share,
+        // and only the default temperature is allowed.
+        let mut max_tokens = request.max_tokens.unwrap_or(4096);
+        let mut temperature = request.temperature;
+        let thinking = match request.thinking {
+            Some(budget) if tool_choice.is_none() => {
+                let budget = budget.max(MIN_THINKING_BUDGET);
+                max_tokens = max_tokens.saturating_add(budget);
+                temperature = None;
+                Some(serde_json::json!({ "type": "enabled", "budget_tokens": budget }))
+            }
+            _ => None,
+        };
+
         AnthropicRequest {
             model,
-            max_tokens: request.max_tokens.unwrap_or(4096),
+            max_tokens,
             system: request.system.clone(),
             messages,
-            temperature: request.temperature,
+            temperature,
             tools: if tools.is_empty() { None } else { Some(tools) },
+            tool_choice,
+            thinking,
         }
     }
 }
@@ -94,18 +218,20 @@ impl LlmProvider for AnthropicProvider {
         Some(&self.model)
     }
 
+    fn supports_documents(&self, mime: &str) -> bool {
+        anthropic_supports_document(mime)
+    }
+
     #[instrument(skip(self, request), fields(model))]
     async fn complete(&self, request: &LlmRequest) -> Result<LlmResponse> {
+        self.upload_new_images(request).await;
         let body = self.build_request(request);
 
         tracing::Span::current().record("model", body.model.as_str());
         debug!("anthropic request: model={}", body.model);
 
         let response = self
-            .client
             .post(self.endpoint())
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", API_VERSION)
             .header("content-type", "application/json")
             .json(&body)
             .send()
@@ -114,10 +240,13 @@ impl LlmProvider for AnthropicProvider {
 
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Agent(format!(
-                "anthropic API error: status={status}, body={body}"
-            )));
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("anthropic API error: status={status}, body={body}"),
+            ));
         }
 
         let api_response: AnthropicResponse = response
@@ -125,7 +254,11 @@ impl LlmProvider for AnthropicProvider {
             .await
             .map_err(|e| Error::Agent(format!("failed to parse anthropic response: {e}")))?;
 
-        Ok(from_anthropic_response(api_response))
+        let response = from_anthropic_response(api_response);
+        if request.response_format.is_some() {
+            return Ok(structured_answer(response));
+        }
+        Ok(response)
     }
 
     #[instrument(skip(self, request), fields(model))]
@@ -133,6 +266,30 @@ impl LlmProvider for AnthropicProvider {
         &self,
         request: &LlmRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        // The forced tool call that carries a structured answer is not
+        // streamed; the finished answer is sent as a single text delta.
+        if request.response_format.is_some() {
+            let response = self.complete(request).await?;
+            let text: String = response
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let events = vec![
+                StreamEvent::TextDelta(text),
+                StreamEvent::MessageDelta {
+                    stop_reason: response.stop_reason,
+                    usage: response.usage,
+                },
+                StreamEvent::MessageStop,
+            ];
+            return Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))));
+        }
+
+        self.upload_new_images(request).await;
         let body = self.build_request(request);
         tracing::Span::current().record("model", body.model.as_str());
         debug!("anthropic streaming request: model={}", body.model);
@@ -142,10 +299,7 @@ impl LlmProvider for AnthropicProvider {
         body_value["stream"] = serde_json::Value::Bool(true);
 
         let response = self
-            .client
             .post(self.endpoint())
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", API_VERSION)
             .header("content-type", "application/json")
             .json(&body_value)
             .send()
@@ -154,10 +308,13 @@ impl LlmProvider for AnthropicProvider {
 
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Agent(format!(
-                "anthropic API error: status={status}, body={body}"
-            )));
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("anthropic API error: status={status}, body={body}"),
+            ));
         }
 
         let byte_stream: Pin<
@@ -225,7 +382,10 @@ impl LlmProvider for AnthropicProvider {
             system: None,
             max_tokens: Some(1),
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         match self.complete(&request).await {
@@ -251,6 +411,10 @@ struct AnthropicRequest {
     temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -273,6 +437,8 @@ enum AnthropicBlock {
     Text { text: String },
     #[serde(rename = "image")]
     Image { source: AnthropicImageSource },
+    #[serde(rename = "document")]
+    Document { source: AnthropicDocumentSource },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -283,15 +449,39 @@ enum AnthropicBlock {
     ToolResult {
         tool_use_id: String,
         content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
     },
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    /// Thinking flagged by safety systems, returned encrypted.
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct AnthropicImageSource {
-    #[serde(rename = "type")]
-    source_type: String,
-    media_type: String,
-    data: String,
+#[serde(tag = "type")]
+enum AnthropicImageSource {
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    #[serde(rename = "file")]
+    File { file_id: String },
+    #[serde(rename = "url")]
+    Url { url: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicFile {
+    id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum AnthropicDocumentSource {
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    #[serde(rename = "url")]
+    Url { url: String },
 }
 
 #[derive(Debug, Serialize)]
@@ -339,6 +529,8 @@ struct SseContentBlock {
     id: Option<String>,
     #[serde(default)]
     name: Option<String>,
+    #[serde(default)]
+    data: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -350,6 +542,10 @@ struct SseDelta {
     #[serde(default)]
     partial_json: Option<String>,
     #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
     stop_reason: Option<String>,
 }
 
@@ -360,14 +556,17 @@ fn parse_sse_data(data: &str) -> Option<StreamEvent> {
         "content_block_start" => {
             let block = parsed.content_block?;
             let index = parsed.index.unwrap_or(0);
-            if block.block_type == "tool_use" {
-                Some(StreamEvent::ToolUseStart {
+            match block.block_type.as_str() {
+                "tool_use" => Some(StreamEvent::ToolUseStart {
                     index,
                     id: block.id.unwrap_or_default(),
                     name: block.name.unwrap_or_default(),
-                })
-            } else {
-                None // text block starts don't need a separate event
+                }),
+                // Redacted thinking arrives whole, with no deltas.
+                "redacted_thinking" => Some(StreamEvent::RedactedThinking(
+                    block.data.unwrap_or_default(),
+                )),
+                _ => None, // text block starts don't need a separate event
             }
         }
         "content_block_delta" => {
@@ -377,6 +576,12 @@ fn parse_sse_data(data: &str) -> Option<StreamEvent> {
                 "input_json_delta" => Some(StreamEvent::InputJsonDelta(
                     delta.partial_json.unwrap_or_default(),
                 )),
+                "thinking_delta" => Some(StreamEvent::ThinkingDelta(
+                    delta.thinking.unwrap_or_default(),
+                )),
+                "signature_delta" => Some(StreamEvent::SignatureDelta(
+                    delta.signature.unwrap_or_default(),
+                )),
                 _ => None,
             }
         }
@@ -408,7 +613,55 @@ fn parse_data_uri(url: &str) -> Option<(String, String)> {
     Some((media_type.to_string(), data.to_string()))
 }
 
-fn to_anthropic_message(msg: &ChatMessage) -> AnthropicMessage {
+/// MIME types the Messages API accepts as `document` blocks.
+fn anthropic_supports_document(mime: &str) -> bool {
+    mime == "application/pdf"
+}
+
+fn to_anthropic_document(mime: &str, url: &str, text: Option<&str>) -> AnthropicBlock {
+    let source = if !anthropic_supports_document(mime) {
+        None
+    } else if let Some((media_type, data)) = parse_data_uri(url) {
+        Some(AnthropicDocumentSource::Base64 { media_type, data })
+    } else if url.starts_with("https://") {
+        Some(AnthropicDocumentSource::Url {
+            url: url.to_string(),
+        })
+    } else {
+        None
+    };
+    match source {
+        Some(source) => AnthropicBlock::Document { source },
+        None => AnthropicBlock::Text {
+            text: document_fallback_text(mime, text),
+        },
+    }
+}
+
+/// Image source for a `data:` URI: a previously uploaded file when `files`
+/// has one for this content, otherwise the inline base64 payload. `http(s)`
+/// URLs are passed for the API to fetch, as OpenAI's `image_url` does.
+fn to_anthropic_image(url: &str, files: Option<&AttachmentCache>) -> AnthropicBlock {
+    let Some((media_type, data)) = parse_data_uri(url) else {
+        if url.starts_with("https://") || url.starts_with("http://") {
+            return AnthropicBlock::Image {
+                source: AnthropicImageSource::Url {
+                    url: url.to_string(),
+                },
+            };
+        }
+        return AnthropicBlock::Text {
+            text: format!("[image: {url}]"),
+        };
+    };
+    let source = match files.and_then(|f| f.get(&AttachmentCache::content_hash(&data))) {
+        Some(file_id) => AnthropicImageSource::File { file_id },
+        None => AnthropicImageSource::Base64 { media_type, data },
+    };
+    AnthropicBlock::Image { source }
+}
+
+fn to_anthropic_message(msg: &ChatMessage, files: Option<&AttachmentCache>) -> AnthropicMessage {
     let role = match msg.role {
         ChatRole::User | ChatRole::Tool => "user",
         ChatRole::Assistant => "assistant",
@@ -430,21 +683,26 @@ fn to_anthropic_message(msg: &ChatMessage) -> AnthropicMessage {
                     ContentBlock::ToolResult {
                         tool_use_id,
                         content,
+                        is_error,
                     } => AnthropicBlock::ToolResult {
                         tool_use_id: tool_use_id.clone(),
                         content: content.clone(),
+                        is_error: *is_error,
+                    },
+                    ContentBlock::Thinking {
+                        thinking,
+                        signature,
+                    } => AnthropicBlock::Thinking {
+                        thinking: thinking.clone(),
+                        signature: signature.clone(),
                     },
-                    ContentBlock::Image { url } => parse_data_uri(url)
-                        .map(|(media_type, data)| AnthropicBlock::Image {
-                            source: AnthropicImageSource {
-                                source_type: "base64".to_string(),
-                                media_type,
-                                data,
-                            },
-                        })
-                        .unwrap_or_else(|| AnthropicBlock::Text {
-                            text: format!("[image: {url}]"),
-                        }),
+                    ContentBlock::RedactedThinking { data } => {
+                        AnthropicBlock::RedactedThinking { data: data.clone() }
+                    }
+                    ContentBlock::Image { url } => to_anthropic_image(url, files),
+                    ContentBlock::Document { mime, url, text } => {
+                        to_anthropic_document(mime, url, text.as_deref())
+                    }
                 })
                 .collect();
             AnthropicContent::Blocks(anthropic_blocks)
@@ -457,6 +715,19 @@ fn to_anthropic_message(msg: &ChatMessage) -> AnthropicMessage {
     }
 }
 
+/// Turn the forced tool call of a structured request into a JSON text answer.
+fn structured_answer(mut response: LlmResponse) -> LlmResponse {
+    let json = response.content.iter().find_map(|block| match block {
+        ContentBlock::ToolUse { input, .. } => Some(input.to_string()),
+        _ => None,
+    });
+    if let Some(text) = json {
+        response.content = vec![ContentBlock::Text { text }];
+        response.stop_reason = Some("end_turn".to_string());
+    }
+    response
+}
+
 fn from_anthropic_response(response: AnthropicResponse) -> LlmResponse {
     let content: Vec<ContentBlock> = response
         .content
@@ -469,16 +740,29 @@ fn from_anthropic_response(response: AnthropicResponse) -> LlmResponse {
                     text: "[image]".to_string(),
                 }
             }
+            AnthropicBlock::Document { .. } => ContentBlock::Text {
+                text: "[document]".to_string(),
+            },
             AnthropicBlock::ToolUse { id, name, input } => {
                 ContentBlock::ToolUse { id, name, input }
             }
             AnthropicBlock::ToolResult {
                 tool_use_id,
                 content,
+                is_error,
             } => ContentBlock::ToolResult {
                 tool_use_id,
                 content,
+                is_error,
             },
+            AnthropicBlock::Thinking {
+                thinking,
+                signature,
+            } => ContentBlock::Thinking {
+                thinking,
+                signature,
+            },
+            AnthropicBlock::RedactedThinking { data } => ContentBlock::RedactedThinking { data },
         })
         .collect();
 
@@ -510,7 +794,10 @@ mod tests {
             system: Some("You are helpful".to_string()),
             max_tokens: Some(1024),
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let anthropic_req = provider.build_request(&request);
@@ -529,7 +816,10 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: Some(0.7),
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let anthropic_req = provider.build_request(&request);
@@ -537,6 +827,55 @@ mod tests {
         assert_eq!(anthropic_req.temperature, Some(0.7));
     }
 
+    #[test]
+    fn json_schema_response_format_forces_the_schema_tool() {
+        let provider = AnthropicProvider::new("test-key", None, None);
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"],
+        });
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![crate::providers::ToolDefinition {
+                name: "bash".to_string(),
+                description: "Run a command".to_string(),
+                input_schema: serde_json::json!({ "type": "object" }),
+            }],
+            response_format: Some(ResponseFormat::json_schema("place", schema.clone())),
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(json["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(json["tools"][0]["name"], "place");
+        assert_eq!(json["tools"][0]["input_schema"], schema);
+        assert_eq!(
+            json["tool_choice"],
+            serde_json::json!({ "type": "tool", "name": "place" })
+        );
+
+        let answer = structured_answer(LlmResponse {
+            content: vec![ContentBlock::ToolUse {
+                id: "toolu_1".to_string(),
+                name: "place".to_string(),
+                input: serde_json::json!({ "city": "Oslo" }),
+            }],
+            model: DEFAULT_MODEL.to_string(),
+            usage: None,
+            stop_reason: Some("tool_use".to_string()),
+        });
+        assert!(
+            matches!(&answer.content[..], [ContentBlock::Text { text }] if text == r#"{"city":"Oslo"}"#)
+        );
+        assert_eq!(answer.stop_reason.as_deref(), Some("end_turn"));
+    }
+
     #[test]
     fn serializes_request_correctly() {
         let req = AnthropicRequest {
@@ -549,6 +888,8 @@ mod tests {
             }],
             temperature: None,
             tools: None,
+            tool_choice: None,
+            thinking: None,
         };
 
         let json = serde_json::to_value(&req).unwrap();
@@ -561,6 +902,104 @@ mod tests {
         assert!(json.get("tools").is_none());
     }
 
+    #[test]
+    fn thinking_budget_enables_extended_thinking() {
+        let provider = AnthropicProvider::new("test-key", None, None);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("hello".to_string()),
+            }],
+            system: None,
+            max_tokens: Some(2048),
+            temperature: Some(0.2),
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: Some(500),
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(json["thinking"]["type"], "enabled");
+        assert_eq!(json["thinking"]["budget_tokens"], MIN_THINKING_BUDGET);
+        assert_eq!(json["max_tokens"], 2048 + MIN_THINKING_BUDGET);
+        assert!(json.get("temperature").is_none());
+    }
+
+    #[test]
+    fn thinking_is_dropped_for_structured_output() {
+        let provider = AnthropicProvider::new("test-key", None, None);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: Some(ResponseFormat::json_schema(
+                "answer",
+                serde_json::json!({"type": "object"}),
+            )),
+            thinking: Some(4000),
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert!(json.get("thinking").is_none());
+        assert_eq!(json["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn thinking_blocks_round_trip() {
+        let json = r#"{
+            "content": [
+                {"type": "thinking", "thinking": "Check the weather first.", "signature": "sig=="},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "tool_use", "id": "t1", "name": "weather", "input": {}}
+            ],
+            "model": "claude-sonnet-4-5-20250929",
+            "stop_reason": "tool_use"
+        }"#;
+
+        let response = from_anthropic_response(serde_json::from_str(json).unwrap());
+        assert_eq!(response.content.len(), 3);
+        assert!(matches!(
+            &response.content[0],
+            ContentBlock::Thinking { thinking, signature }
+                if thinking == "Check the weather first." && signature == "sig=="
+        ));
+
+        let message = ChatMessage {
+            role: ChatRole::Assistant,
+            content: MessagePart::Parts(response.content),
+        };
+        let json = serde_json::to_value(to_anthropic_message(&message, None)).unwrap();
+        assert_eq!(json["content"][0]["type"], "thinking");
+        assert_eq!(json["content"][0]["signature"], "sig==");
+        assert_eq!(json["content"][1]["type"], "redacted_thinking");
+        assert_eq!(json["content"][1]["data"], "opaque");
+        assert_eq!(json["content"][2]["type"], "tool_use");
+    }
+
+    #[test]
+    fn parses_thinking_stream_deltas() {
+        let thinking = parse_sse_data(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Hmm"}}"#,
+        );
+        assert!(matches!(thinking, Some(StreamEvent::ThinkingDelta(t)) if t == "Hmm"));
+
+        let signature = parse_sse_data(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"abc"}}"#,
+        );
+        assert!(matches!(signature, Some(StreamEvent::SignatureDelta(s)) if s == "abc"));
+
+        let redacted = parse_sse_data(
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"redacted_thinking","data":"opaque"}}"#,
+        );
+        assert!(matches!(redacted, Some(StreamEvent::RedactedThinking(d)) if d == "opaque"));
+    }
+
     #[test]
     fn deserializes_text_response() {
         let json = r#"{
@@ -624,10 +1063,11 @@ mod tests {
             content: MessagePart::Parts(vec![ContentBlock::ToolResult {
                 tool_use_id: "toolu_123".to_string(),
                 content: "hello\n".to_string(),
+                is_error: false,
             }]),
         };
 
-        let anthropic_msg = to_anthropic_message(&msg);
+        let anthropic_msg = to_anthropic_message(&msg, None);
         assert_eq!(anthropic_msg.role, "user");
         match &anthropic_msg.content {
             AnthropicContent::Blocks(blocks) => {
@@ -636,6 +1076,7 @@ mod tests {
                     AnthropicBlock::ToolResult {
                         tool_use_id,
                         content,
+                        ..
                     } => {
                         assert_eq!(tool_use_id, "toolu_123");
                         assert_eq!(content, "hello\n");
@@ -647,6 +1088,95 @@ mod tests {
         }
     }
 
+    #[test]
+    fn serializes_tool_result_is_error() {
+        let tool_result = |is_error| ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Parts(vec![ContentBlock::ToolResult {
+                tool_use_id: "toolu_123".to_string(),
+                content: "error: file not found".to_string(),
+                is_error,
+            }]),
+        };
+
+        let failed = serde_json::to_value(to_anthropic_message(&tool_result(true), None)).unwrap();
+        assert_eq!(
+            failed["content"][0],
+            serde_json::json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_123",
+                "content": "error: file not found",
+                "is_error": true,
+            })
+        );
+
+        // Successful results omit the field entirely.
+        let ok = serde_json::to_value(to_anthropic_message(&tool_result(false), None)).unwrap();
+        assert!(ok["content"][0].get("is_error").is_none());
+    }
+
+    #[test]
+    fn serializes_pdf_document_natively() {
+        let message = |url: &str, mime: &str| ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Parts(vec![ContentBlock::Document {
+                mime: mime.to_string(),
+                url: url.to_string(),
+                text: Some("Quarterly report".to_string()),
+            }]),
+        };
+
+        let inline = serde_json::to_value(to_anthropic_message(
+            &message("data:application/pdf;base64,JVBERi0=", "application/pdf"),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(
+            inline["content"][0],
+            serde_json::json!({
+                "type": "document",
+                "source": {
+                    "type": "base64",
+                    "media_type": "application/pdf",
+                    "data": "JVBERi0=",
+                },
+            })
+        );
+
+        let linked = serde_json::to_value(to_anthropic_message(
+            &message("https://example.com/report.pdf", "application/pdf"),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(
+            linked["content"][0]["source"],
+            serde_json::json!({ "type": "url", "url": "https://example.com/report.pdf" })
+        );
+    }
+
+    #[test]
+    fn unsupported_document_falls_back_to_text() {
+        let provider = AnthropicProvider::new("key", None, None);
+        let mime = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+        assert!(provider.supports_documents("application/pdf"));
+        assert!(!provider.supports_documents(mime));
+
+        let message = ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Parts(vec![ContentBlock::Document {
+                mime: mime.to_string(),
+                url: "data:application/octet-stream;base64,UEsDBA==".to_string(),
+                text: Some("Meeting notes".to_string()),
+            }]),
+        };
+        let json = serde_json::to_value(to_anthropic_message(&message, None)).unwrap();
+        assert_eq!(json["content"][0]["type"], "text");
+        assert_eq!(
+            json["content"][0]["text"],
+            format!("[document ({mime})]\nMeeting notes")
+        );
+    }
+
     #[test]
     fn endpoint_strips_trailing_slash() {
         let provider =
@@ -663,6 +1193,7 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![ToolDefinition {
                 name: "bash".to_string(),
                 description: "Run a command".to_string(),
@@ -671,6 +1202,8 @@ mod tests {
                     "properties": {"command": {"type": "string"}}
                 }),
             }],
+            response_format: None,
+            thinking: None,
         };
 
         let anthropic_req = provider.build_request(&request);
@@ -678,4 +1211,119 @@ mod tests {
         assert_eq!(tools.len(), 1);
         assert_eq!(tools[0].name, "bash");
     }
+
+    fn image_message() -> ChatMessage {
+        ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Parts(vec![ContentBlock::Image {
+                url: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn openai_style_image_parts_become_image_blocks() {
+        let content: MessagePart = serde_json::from_value(serde_json::json!([
+            { "type": "text", "text": "What is this?" },
+            {
+                "type": "image_url",
+                "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=", "detail": "high" }
+            },
+            { "type": "image_url", "image_url": { "url": "https://example.com/cat.jpg" } },
+            { "type": "image", "url": "https://example.com/dog.jpg" }
+        ]))
+        .unwrap();
+        let msg = ChatMessage {
+            role: ChatRole::User,
+            content,
+        };
+
+        let json = serde_json::to_value(to_anthropic_message(&msg, None)).unwrap();
+        assert_eq!(json["content"][0]["type"], "text");
+        assert_eq!(
+            json["content"][1],
+            serde_json::json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo=" }
+            })
+        );
+        assert_eq!(
+            json["content"][2],
+            serde_json::json!({
+                "type": "image",
+                "source": { "type": "url", "url": "https://example.com/cat.jpg" }
+            })
+        );
+        assert_eq!(
+            json["content"][3]["source"]["url"],
+            "https://example.com/dog.jpg"
+        );
+    }
+
+    #[test]
+    fn cached_image_is_sent_as_file_reference() {
+        let files = AttachmentCache::new();
+        let inline =
+            serde_json::to_value(to_anthropic_message(&image_message(), Some(&files))).unwrap();
+        assert_eq!(inline["content"][0]["source"]["type"], "base64");
+
+        files.insert(AttachmentCache::content_hash("iVBORw0KGgo="), "file_abc");
+        let cached =
+            serde_json::to_value(to_anthropic_message(&image_message(), Some(&files))).unwrap();
+        assert_eq!(
+            cached["content"][0]["source"],
+            serde_json::json!({ "type": "file", "file_id": "file_abc" })
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_image_is_uploaded_once() {
+        use wiremock::matchers::{body_string_contains, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .and(header("anthropic-beta", FILES_API_BETA))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "file_abc" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_string_contains("file_abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{ "type": "text", "text": "a cat" }],
+                "model": "claude-test",
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 1, "output_tokens": 1 },
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let provider =
+            AnthropicProvider::new("key", None, Some(server.uri())).with_image_uploads(true);
+        let mut request = LlmRequest {
+            model: String::new(),
+            messages: vec![image_message()],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+        provider.complete(&request).await.unwrap();
+
+        request.messages.push(ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Text("and now?".to_string()),
+        });
+        provider.complete(&request).await.unwrap();
+        assert_eq!(provider.image_files.len(), 1);
+    }
 }
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Maps attachment content hashes to provider-side file references, so an
+/// image that stays in the conversation history is uploaded once and then
+/// referenced by id instead of being re-sent as base64 on every turn.
+#[derive(Debug, Default)]
+pub struct AttachmentCache {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl AttachmentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// SHA-256 of `data`, hex-encoded. Callers hash the base64 payload as-is
+    /// so a cache lookup never needs to decode the attachment.
+    pub fn content_hash(data: &str) -> String {
+        ring::digest::digest(&ring::digest::SHA256, data.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// The file reference previously stored for `hash`, if any.
+    pub fn get(&self, hash: &str) -> Option<String> {
+        self.entries.lock().ok()?.get(hash).cloned()
+    }
+
+    pub fn insert(&self, hash: impl Into<String>, file_ref: impl Into<String>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(hash.into(), file_ref.into());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_content_hashes_to_same_key() {
+        let a = AttachmentCache::content_hash("aGVsbG8=");
+        let b = AttachmentCache::content_hash("aGVsbG8=");
+        let c = AttachmentCache::content_hash("d29ybGQ=");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 64);
+    }
+
+    #[test]
+    fn stores_and_returns_file_refs() {
+        let cache = AttachmentCache::new();
+        let hash = AttachmentCache::content_hash("aGVsbG8=");
+        assert!(cache.get(&hash).is_none());
+
+        cache.insert(hash.clone(), "file_123");
+        assert_eq!(cache.get(&hash).as_deref(), Some("file_123"));
+        assert_eq!(cache.len(), 1);
+    }
+}
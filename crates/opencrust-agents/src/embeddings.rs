@@ -138,6 +138,141 @@ impl CohereEmbedResponse {
     }
 }
 
+/// OpenAI embeddings provider (`text-embedding-3-small`/`-large`). Also works
+/// with OpenAI-compatible embedding endpoints via `base_url`.
+pub struct OpenAiEmbeddingProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    /// Output size for `text-embedding-3` models; `None` keeps the model's native size.
+    dimensions: Option<usize>,
+}
+
+impl OpenAiEmbeddingProvider {
+    pub fn new(
+        api_key: impl Into<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+        dimensions: Option<usize>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.unwrap_or_else(|| "text-embedding-3-small".to_string()),
+            base_url: base_url.unwrap_or_else(|| "https://api.openai.com".to_string()),
+            dimensions,
+        }
+    }
+
+    /// `{base}/v1/embeddings`, without doubling a `/v1` already in `base_url`.
+    fn endpoint(&self) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        if base.ends_with("/v1") {
+            format!("{base}/embeddings")
+        } else {
+            format!("{base}/v1/embeddings")
+        }
+    }
+
+    fn build_request_body(&self, texts: &[String]) -> OpenAiEmbedRequest {
+        OpenAiEmbedRequest {
+            model: self.model.clone(),
+            input: texts.to_vec(),
+            encoding_format: "float".to_string(),
+            dimensions: self.dimensions,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddingProvider {
+    fn provider_id(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .client
+            .post(self.endpoint())
+            .bearer_auth(&self.api_key)
+            .json(&self.build_request_body(texts))
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("openai embed request failed: {e}")))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Agent(format!(
+                "openai embed request failed: status={status}, body={body}"
+            )));
+        }
+
+        let payload: OpenAiEmbedResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to decode openai embed response: {e}")))?;
+
+        payload.into_embeddings(texts.len())
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
+        let texts = vec![text.to_string()];
+        let mut embeddings = self.embed_documents(&texts).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| Error::Agent("openai returned no embeddings for query".into()))
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        Ok(self.embed_query("health check").await.is_ok())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenAiEmbedRequest {
+    model: String,
+    input: Vec<String>,
+    encoding_format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OpenAiEmbedResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedResponse {
+    /// Embeddings in input order. The API tags each one with its input index
+    /// rather than guaranteeing order.
+    fn into_embeddings(mut self, expected: usize) -> Result<Vec<Vec<f32>>> {
+        if self.data.len() != expected {
+            return Err(Error::Agent(format!(
+                "openai returned {} embeddings for {expected} inputs",
+                self.data.len()
+            )));
+        }
+        self.data.sort_by_key(|e| e.index);
+        Ok(self.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
 /// Ollama embeddings provider for local/offline embedding generation.
 pub struct OllamaEmbeddingProvider {
     client: reqwest::Client,
@@ -229,7 +364,7 @@ struct OllamaEmbedResponse {
 mod tests {
     use super::{
         CohereEmbedResponse, CohereEmbeddingProvider, EmbeddingProvider, OllamaEmbedResponse,
-        OllamaEmbeddingProvider,
+        OllamaEmbeddingProvider, OpenAiEmbedResponse, OpenAiEmbeddingProvider,
     };
 
     #[test]
@@ -274,6 +409,61 @@ mod tests {
         assert_eq!(provider.endpoint(), "https://api.cohere.com/v1/embed");
     }
 
+    // -- OpenAI tests --
+
+    #[test]
+    fn openai_request_includes_dimensions_only_when_set() {
+        let provider = OpenAiEmbeddingProvider::new("sk-test", None, None, None);
+        assert_eq!(provider.provider_id(), "openai");
+        assert_eq!(provider.model(), "text-embedding-3-small");
+        let body = serde_json::to_value(provider.build_request_body(&["hi".to_string()])).unwrap();
+        assert_eq!(body["input"], serde_json::json!(["hi"]));
+        assert!(body.get("dimensions").is_none());
+
+        let provider = OpenAiEmbeddingProvider::new(
+            "sk-test",
+            Some("text-embedding-3-large".into()),
+            None,
+            Some(256),
+        );
+        let body = serde_json::to_value(provider.build_request_body(&["hi".to_string()])).unwrap();
+        assert_eq!(body["model"], "text-embedding-3-large");
+        assert_eq!(body["dimensions"], 256);
+    }
+
+    #[test]
+    fn openai_endpoint_does_not_double_version() {
+        let provider = OpenAiEmbeddingProvider::new("sk-test", None, None, None);
+        assert_eq!(provider.endpoint(), "https://api.openai.com/v1/embeddings");
+        let provider = OpenAiEmbeddingProvider::new(
+            "sk-test",
+            None,
+            Some("http://localhost:8080/v1/".into()),
+            None,
+        );
+        assert_eq!(provider.endpoint(), "http://localhost:8080/v1/embeddings");
+    }
+
+    #[test]
+    fn openai_embeddings_are_returned_in_input_order() {
+        let payload: OpenAiEmbedResponse = serde_json::from_str(
+            r#"{
+                "object": "list",
+                "data": [
+                    {"object": "embedding", "index": 1, "embedding": [0.4, 0.5]},
+                    {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}
+                ],
+                "model": "text-embedding-3-small"
+            }"#,
+        )
+        .expect("json should parse");
+
+        let vectors = payload
+            .into_embeddings(2)
+            .expect("should contain embeddings");
+        assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.4, 0.5]]);
+    }
+
     // -- Ollama tests --
 
     #[test]
@@ -0,0 +1,1076 @@
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use opencrust_common::{Error, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+use crate::attachment_cache::AttachmentCache;
+use crate::providers::{
+    ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart,
+    StreamEvent, Usage, document_fallback_text,
+};
+
+const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Google Gemini provider using the native `generateContent` API.
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    name: String,
+    supports_tools: bool,
+    google_search: bool,
+    cache_ttl: Option<Duration>,
+    /// Context caches keyed by a hash of the model, system instruction and
+    /// tools. `None` marks a cache the API refused, so it is not retried
+    /// until the entry expires.
+    caches: Mutex<HashMap<String, (Option<String>, Instant)>>,
+}
+
+impl GeminiProvider {
+    pub fn new(
+        api_key: impl Into<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            name: "gemini".to_string(),
+            supports_tools: true,
+            google_search: false,
+            cache_ttl: None,
+            caches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Override the provider ID used for config-key-based lookups.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Whether to send tool definitions. See [`LlmProvider::supports_tools`].
+    pub fn with_supports_tools(mut self, supports_tools: bool) -> Self {
+        self.supports_tools = supports_tools;
+        self
+    }
+
+    /// Let the model ground its answers with Google Search. The sources it
+    /// used are listed after the answer.
+    pub fn with_google_search(mut self, enabled: bool) -> Self {
+        self.google_search = enabled;
+        self
+    }
+
+    /// Store the system instruction and tools in a context cache that lives
+    /// for `ttl`, so they are not billed as fresh input on every turn.
+    pub fn with_context_cache(mut self, ttl: Option<Duration>) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    fn model_for(&self, request: &LlmRequest) -> String {
+        let model = if request.model.is_empty() {
+            &self.model
+        } else {
+            &request.model
+        };
+        model.trim_start_matches("models/").to_string()
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.base_url.trim_end_matches('/'))
+    }
+
+    fn post(&self, url: String) -> reqwest::RequestBuilder {
+        self.client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .header("content-type", "application/json")
+    }
+
+    fn build_request(&self, request: &LlmRequest) -> GeminiRequest {
+        let mut tools = Vec::new();
+        if self.supports_tools && !request.tools.is_empty() {
+            tools.push(GeminiTool {
+                function_declarations: request
+                    .tools
+                    .iter()
+                    .map(|t| FunctionDeclaration {
+                        name: t.name.clone(),
+                        description: t.description.clone(),
+                        parameters_json_schema: t.input_schema.clone(),
+                    })
+                    .collect(),
+                google_search: None,
+            });
+        }
+        if self.google_search {
+            tools.push(GeminiTool {
+                function_declarations: Vec::new(),
+                google_search: Some(GoogleSearch {}),
+            });
+        }
+
+        GeminiRequest {
+            contents: to_gemini_contents(&request.messages),
+            system_instruction: request.system.as_ref().filter(|s| !s.is_empty()).map(|s| {
+                GeminiContent {
+                    role: None,
+                    parts: vec![GeminiPart::text(s.clone())],
+                }
+            }),
+            tools,
+            cached_content: None,
+            generation_config: GenerationConfig {
+                max_output_tokens: request.max_tokens,
+                temperature: request.temperature,
+                seed: request.seed,
+            },
+        }
+    }
+
+    /// Move the system instruction and tools of `body` into a context cache
+    /// when caching is on. Failures are logged and the request is sent as is.
+    async fn apply_context_cache(&self, model: &str, body: &mut GeminiRequest) {
+        let Some(ttl) = self.cache_ttl else {
+            return;
+        };
+        if body.system_instruction.is_none() && body.tools.is_empty() {
+            return;
+        }
+        let cache = CachedContentRequest {
+            model: format!("models/{model}"),
+            system_instruction: body.system_instruction.clone(),
+            tools: body.tools.clone(),
+            ttl: format!("{}s", ttl.as_secs()),
+        };
+        let key = AttachmentCache::content_hash(
+            &serde_json::to_string(&(&cache.model, &cache.system_instruction, &cache.tools))
+                .unwrap_or_default(),
+        );
+
+        let cached = self
+            .caches
+            .lock()
+            .ok()
+            .and_then(|caches| caches.get(&key).cloned())
+            .filter(|(_, expires)| *expires > Instant::now());
+        let name = match cached {
+            Some((name, _)) => name,
+            None => {
+                let name = match self.create_cache(&cache).await {
+                    Ok(name) => Some(name),
+                    Err(e) => {
+                        warn!("gemini context cache not created, sending inline: {e}");
+                        None
+                    }
+                };
+                // Renew a little before the API drops the cache.
+                let expires = Instant::now() + ttl.mul_f64(0.9);
+                if let Ok(mut caches) = self.caches.lock() {
+                    caches.retain(|_, (_, at)| *at > Instant::now());
+                    caches.insert(key, (name.clone(), expires));
+                }
+                name
+            }
+        };
+        if let Some(name) = name {
+            body.system_instruction = None;
+            body.tools.clear();
+            body.cached_content = Some(name);
+        }
+    }
+
+    async fn create_cache(&self, cache: &CachedContentRequest) -> Result<String> {
+        let response = self
+            .post(self.url("cachedContents"))
+            .json(cache)
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("gemini cache request failed: {e}")))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Agent(format!(
+                "gemini cache API error: status={status}, body={body}"
+            )));
+        }
+        let created: CachedContent = response
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse gemini cache response: {e}")))?;
+        Ok(created.name)
+    }
+
+    async fn send(
+        &self,
+        request: &LlmRequest,
+        stream: bool,
+    ) -> Result<(String, reqwest::Response)> {
+        let model = self.model_for(request);
+        let mut body = self.build_request(request);
+        self.apply_context_cache(&model, &mut body).await;
+
+        tracing::Span::current().record("model", model.as_str());
+        debug!("gemini request: model={model}, stream={stream}");
+
+        let url = if stream {
+            self.url(&format!("models/{model}:streamGenerateContent?alt=sse"))
+        } else {
+            self.url(&format!("models/{model}:generateContent"))
+        };
+        let response = self
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("gemini request failed: {e}")))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.text().await.unwrap_or_default();
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("gemini API error: status={status}, body={body}"),
+            ));
+        }
+        Ok((model, response))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn provider_id(&self) -> &str {
+        &self.name
+    }
+
+    fn configured_model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn supports_documents(&self, mime: &str) -> bool {
+        gemini_supports_document(mime)
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.supports_tools
+    }
+
+    async fn available_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(self.url("models?pageSize=1000"))
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("gemini models request failed: {e}")))?;
+        if !response.status().is_success() {
+            return Err(Error::Agent(format!(
+                "gemini models API error: status={}",
+                response.status()
+            )));
+        }
+        let list: ModelList = response
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse gemini models: {e}")))?;
+        Ok(list
+            .models
+            .into_iter()
+            .filter(|m| {
+                m.supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|m| m.name.trim_start_matches("models/").to_string())
+            .collect())
+    }
+
+    #[instrument(skip(self, request), fields(model))]
+    async fn complete(&self, request: &LlmRequest) -> Result<LlmResponse> {
+        let (model, response) = self.send(request, false).await?;
+        let api_response: GeminiResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse gemini response: {e}")))?;
+        Ok(from_gemini_response(api_response, &model))
+    }
+
+    #[instrument(skip(self, request), fields(model))]
+    async fn stream_complete(
+        &self,
+        request: &LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        let (_, response) = self.send(request, true).await?;
+
+        let byte_stream: Pin<
+            Box<dyn Stream<Item = std::result::Result<bytes::Bytes, reqwest::Error>> + Send>,
+        > = Box::pin(response.bytes_stream());
+
+        let event_stream = futures::stream::unfold(
+            (byte_stream, String::new(), StreamState::default()),
+            |(mut stream, mut buffer, mut state)| async move {
+                loop {
+                    if let Some(event) = state.pending.pop_front() {
+                        return Some((Ok(event), (stream, buffer, state)));
+                    }
+
+                    // Each SSE event carries one complete response chunk.
+                    if let Some(pos) = buffer.find("\n\n") {
+                        let event_str = buffer[..pos].to_string();
+                        buffer = buffer[pos + 2..].to_string();
+                        let data: String = event_str
+                            .lines()
+                            .filter_map(|line| line.strip_prefix("data:"))
+                            .map(str::trim_start)
+                            .collect();
+                        match serde_json::from_str::<GeminiResponse>(&data) {
+                            Ok(chunk) => state.push_chunk(chunk),
+                            Err(e) if !data.is_empty() => {
+                                debug!("skipping unparseable gemini stream event: {e}");
+                            }
+                            Err(_) => {}
+                        }
+                        continue;
+                    }
+
+                    match stream.next().await {
+                        Some(Ok(bytes)) => {
+                            buffer.push_str(&String::from_utf8_lossy(&bytes));
+                            if buffer.contains('\r') {
+                                buffer = buffer.replace("\r\n", "\n");
+                            }
+                        }
+                        Some(Err(e)) => {
+                            return Some((
+                                Err(Error::Agent(format!("stream read error: {e}"))),
+                                (stream, buffer, state),
+                            ));
+                        }
+                        None if !state.finished => {
+                            state.finished = true;
+                            state.finish();
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(event_stream))
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        let request = LlmRequest {
+            model: self.model.clone(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("ping".to_string()),
+            }],
+            system: None,
+            max_tokens: Some(1),
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        match self.complete(&request).await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                info!("gemini health check failed: {e}");
+                Ok(false)
+            }
+        }
+    }
+}
+
+// --- Gemini Wire Types (private) ---
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached_content: Option<String>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+/// One part of a message. The API accepts exactly one of the data fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inline_data: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+    /// Set on the model's thought summaries, which are not part of the answer.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    thought: bool,
+}
+
+impl GeminiPart {
+    fn text(text: String) -> Self {
+        Self {
+            text: Some(text),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    response: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    function_declarations: Vec<FunctionDeclaration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    google_search: Option<GoogleSearch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FunctionDeclaration {
+    name: String,
+    description: String,
+    parameters_json_schema: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GoogleSearch {}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedContentRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+    ttl: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CachedContent {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    model_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<GeminiContent>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    grounding_metadata: Option<GroundingMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroundingMetadata {
+    #[serde(default)]
+    grounding_chunks: Vec<GroundingChunk>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GroundingChunk {
+    #[serde(default)]
+    web: Option<WebSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSource {
+    uri: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelInfo {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+// --- Streaming ---
+
+/// Turns streamed response chunks into [`StreamEvent`]s. Function calls
+/// arrive whole, so each becomes a start, its arguments and a stop.
+#[derive(Default)]
+struct StreamState {
+    pending: VecDeque<StreamEvent>,
+    tool_index: usize,
+    sources: Vec<WebSource>,
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+    finished: bool,
+}
+
+impl StreamState {
+    fn push_chunk(&mut self, chunk: GeminiResponse) {
+        if let Some(u) = chunk.usage_metadata {
+            self.usage = Some(to_usage(u));
+        }
+        let Some(candidate) = chunk.candidates.into_iter().next() else {
+            return;
+        };
+        for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+            if part.thought {
+                continue;
+            }
+            if let Some(text) = part.text.filter(|t| !t.is_empty()) {
+                self.pending.push_back(StreamEvent::TextDelta(text));
+            }
+            if let Some(call) = part.function_call {
+                let index = self.tool_index;
+                self.tool_index += 1;
+                self.pending.push_back(StreamEvent::ToolUseStart {
+                    index,
+                    id: call.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                    name: call.name,
+                });
+                self.pending
+                    .push_back(StreamEvent::InputJsonDelta(call.args.to_string()));
+                self.pending
+                    .push_back(StreamEvent::ContentBlockStop { index });
+            }
+        }
+        if let Some(grounding) = candidate.grounding_metadata {
+            self.sources
+                .extend(grounding.grounding_chunks.into_iter().filter_map(|c| c.web));
+        }
+        if let Some(reason) = candidate.finish_reason {
+            self.stop_reason = Some(stop_reason(&reason, self.tool_index > 0));
+        }
+    }
+
+    /// Queue the closing events once the stream has ended.
+    fn finish(&mut self) {
+        if let Some(sources) = format_sources(&self.sources) {
+            self.pending.push_back(StreamEvent::TextDelta(sources));
+        }
+        self.pending.push_back(StreamEvent::MessageDelta {
+            stop_reason: self.stop_reason.take(),
+            usage: self.usage.take(),
+        });
+        self.pending.push_back(StreamEvent::MessageStop);
+    }
+}
+
+// --- Conversion Functions ---
+
+/// Parse a `data:` URI into (media_type, base64_data).
+fn parse_data_uri(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64").unwrap_or(meta);
+    Some((media_type.to_string(), data.to_string()))
+}
+
+/// MIME types Gemini reads natively as inline data.
+fn gemini_supports_document(mime: &str) -> bool {
+    matches!(
+        mime,
+        "application/pdf" | "text/plain" | "text/html" | "text/csv" | "text/markdown"
+    )
+}
+
+fn to_gemini_part(block: &ContentBlock, tool_names: &HashMap<String, String>) -> GeminiPart {
+    match block {
+        ContentBlock::Text { text } => GeminiPart::text(text.clone()),
+        ContentBlock::Image { url } => match parse_data_uri(url) {
+            Some((mime_type, data)) => GeminiPart {
+                inline_data: Some(Blob { mime_type, data }),
+                ..Default::default()
+            },
+            None => GeminiPart::text(format!("[image: {url}]")),
+        },
+        ContentBlock::Document { mime, url, text } => match parse_data_uri(url) {
+            Some((mime_type, data)) if gemini_supports_document(mime) => GeminiPart {
+                inline_data: Some(Blob { mime_type, data }),
+                ..Default::default()
+            },
+            _ => GeminiPart::text(document_fallback_text(mime, text.as_deref())),
+        },
+        ContentBlock::Thinking { thinking, .. } => GeminiPart {
+            text: Some(thinking.clone()),
+            thought: true,
+            ..Default::default()
+        },
+        // Skipped by `to_gemini_contents`; Gemini cannot read Anthropic's
+        // encrypted reasoning.
+        ContentBlock::RedactedThinking { .. } => GeminiPart::default(),
+        ContentBlock::ToolUse { id, name, input } => GeminiPart {
+            function_call: Some(FunctionCall {
+                id: Some(id.clone()),
+                name: name.clone(),
+                args: input.clone(),
+            }),
+            ..Default::default()
+        },
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => {
+            let key = if *is_error { "error" } else { "output" };
+            GeminiPart {
+                function_response: Some(FunctionResponse {
+                    id: Some(tool_use_id.clone()),
+                    name: tool_names.get(tool_use_id).cloned().unwrap_or_default(),
+                    response: serde_json::json!({ key: content }),
+                }),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Convert the conversation to Gemini contents. System messages are sent as
+/// the system instruction instead, and function responses name the function
+/// whose call they answer, as the API requires.
+fn to_gemini_contents(messages: &[ChatMessage]) -> Vec<GeminiContent> {
+    let mut tool_names = HashMap::new();
+    let mut contents = Vec::new();
+    for msg in messages {
+        let role = match msg.role {
+            ChatRole::System => continue,
+            ChatRole::User | ChatRole::Tool => "user",
+            ChatRole::Assistant => "model",
+        };
+        let parts: Vec<GeminiPart> = match &msg.content {
+            MessagePart::Text(text) if text.is_empty() => Vec::new(),
+            MessagePart::Text(text) => vec![GeminiPart::text(text.clone())],
+            MessagePart::Parts(blocks) => blocks
+                .iter()
+                .filter(|b| !matches!(b, ContentBlock::Text { text } if text.is_empty()))
+                .filter(|b| !matches!(b, ContentBlock::RedactedThinking { .. }))
+                .map(|block| {
+                    if let ContentBlock::ToolUse { id, name, .. } = block {
+                        tool_names.insert(id.clone(), name.clone());
+                    }
+                    to_gemini_part(block, &tool_names)
+                })
+                .collect(),
+        };
+        if !parts.is_empty() {
+            contents.push(GeminiContent {
+                role: Some(role.to_string()),
+                parts,
+            });
+        }
+    }
+    contents
+}
+
+/// Map Gemini's finish reason to the Anthropic-style stop reason used
+/// across providers.
+fn stop_reason(reason: &str, called_tools: bool) -> String {
+    match reason {
+        "STOP" if called_tools => "tool_use".to_string(),
+        "STOP" => "end_turn".to_string(),
+        "MAX_TOKENS" => "max_tokens".to_string(),
+        other => other.to_ascii_lowercase(),
+    }
+}
+
+fn to_usage(usage: UsageMetadata) -> Usage {
+    Usage {
+        input_tokens: usage.prompt_token_count,
+        output_tokens: usage.candidates_token_count,
+    }
+}
+
+/// The web pages a grounded answer was based on, as a list appended to it.
+fn format_sources(sources: &[WebSource]) -> Option<String> {
+    let mut seen = Vec::new();
+    let lines: Vec<String> = sources
+        .iter()
+        .filter(|s| {
+            let new = !seen.contains(&&s.uri);
+            seen.push(&s.uri);
+            new
+        })
+        .map(|s| match &s.title {
+            Some(title) => format!("- [{title}]({})", s.uri),
+            None => format!("- {}", s.uri),
+        })
+        .collect();
+    (!lines.is_empty()).then(|| format!("\n\nSources:\n{}", lines.join("\n")))
+}
+
+fn from_gemini_response(response: GeminiResponse, model: &str) -> LlmResponse {
+    let mut content = Vec::new();
+    let mut stop = None;
+    if let Some(candidate) = response.candidates.into_iter().next() {
+        for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+            if part.thought {
+                continue;
+            }
+            if let Some(text) = part.text.filter(|t| !t.is_empty()) {
+                content.push(ContentBlock::Text { text });
+            }
+            if let Some(call) = part.function_call {
+                content.push(ContentBlock::ToolUse {
+                    id: call.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                    name: call.name,
+                    input: call.args,
+                });
+            }
+        }
+        let sources = candidate
+            .grounding_metadata
+            .map(|g| {
+                g.grounding_chunks
+                    .into_iter()
+                    .filter_map(|c| c.web)
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        if let Some(sources) = format_sources(&sources) {
+            content.push(ContentBlock::Text { text: sources });
+        }
+        let called_tools = content
+            .iter()
+            .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+        stop = candidate
+            .finish_reason
+            .map(|r| stop_reason(&r, called_tools));
+    }
+
+    LlmResponse {
+        content,
+        model: response.model_version.unwrap_or_else(|| model.to_string()),
+        usage: response.usage_metadata.map(to_usage),
+        stop_reason: stop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ToolDefinition;
+
+    fn request(messages: Vec<ChatMessage>) -> LlmRequest {
+        LlmRequest {
+            model: String::new(),
+            messages,
+            system: Some("Be brief".to_string()),
+            max_tokens: Some(256),
+            temperature: Some(0.2),
+            seed: None,
+            tools: vec![ToolDefinition {
+                name: "get_weather".to_string(),
+                description: "Weather for a city".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "additionalProperties": false
+                }),
+            }],
+            response_format: None,
+            thinking: None,
+        }
+    }
+
+    #[test]
+    fn builds_native_request_with_system_instruction_and_tools() {
+        let provider = GeminiProvider::new("key", None, None).with_google_search(true);
+        let req = request(vec![ChatMessage {
+            role: ChatRole::User,
+            content: MessagePart::Parts(vec![
+                ContentBlock::Image {
+                    url: "data:image/png;base64,iVBORw0K".to_string(),
+                },
+                ContentBlock::Text {
+                    text: "what is this?".to_string(),
+                },
+            ]),
+        }]);
+
+        let json = serde_json::to_value(provider.build_request(&req)).unwrap();
+        assert_eq!(provider.model_for(&req), DEFAULT_MODEL);
+        assert_eq!(json["systemInstruction"]["parts"][0]["text"], "Be brief");
+        assert_eq!(json["contents"][0]["role"], "user");
+        assert_eq!(
+            json["contents"][0]["parts"][0]["inlineData"],
+            serde_json::json!({ "mimeType": "image/png", "data": "iVBORw0K" })
+        );
+        assert_eq!(json["contents"][0]["parts"][1]["text"], "what is this?");
+        let declaration = &json["tools"][0]["functionDeclarations"][0];
+        assert_eq!(declaration["name"], "get_weather");
+        assert_eq!(
+            declaration["parametersJsonSchema"]["additionalProperties"],
+            false
+        );
+        assert_eq!(json["tools"][1], serde_json::json!({ "googleSearch": {} }));
+        assert_eq!(json["generationConfig"]["maxOutputTokens"], 256);
+        assert!(json.get("cachedContent").is_none());
+    }
+
+    #[test]
+    fn tool_results_answer_the_named_function_call() {
+        let contents = to_gemini_contents(&[
+            ChatMessage {
+                role: ChatRole::System,
+                content: MessagePart::Text("ignored".to_string()),
+            },
+            ChatMessage {
+                role: ChatRole::Assistant,
+                content: MessagePart::Parts(vec![ContentBlock::ToolUse {
+                    id: "call-1".to_string(),
+                    name: "get_weather".to_string(),
+                    input: serde_json::json!({ "city": "Oslo" }),
+                }]),
+            },
+            ChatMessage {
+                role: ChatRole::Tool,
+                content: MessagePart::Parts(vec![ContentBlock::ToolResult {
+                    tool_use_id: "call-1".to_string(),
+                    content: "rain".to_string(),
+                    is_error: false,
+                }]),
+            },
+        ]);
+
+        let json = serde_json::to_value(&contents).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["role"], "model");
+        assert_eq!(json[0]["parts"][0]["functionCall"]["args"]["city"], "Oslo");
+        assert_eq!(json[1]["role"], "user");
+        assert_eq!(
+            json[1]["parts"][0]["functionResponse"],
+            serde_json::json!({
+                "id": "call-1",
+                "name": "get_weather",
+                "response": { "output": "rain" }
+            })
+        );
+    }
+
+    #[test]
+    fn documents_are_sent_inline_when_supported() {
+        let names = HashMap::new();
+        let pdf = to_gemini_part(
+            &ContentBlock::Document {
+                mime: "application/pdf".to_string(),
+                url: "data:application/pdf;base64,JVBERi0=".to_string(),
+                text: None,
+            },
+            &names,
+        );
+        assert_eq!(pdf.inline_data.unwrap().mime_type, "application/pdf");
+
+        let sheet = to_gemini_part(
+            &ContentBlock::Document {
+                mime: "application/vnd.ms-excel".to_string(),
+                url: "data:application/vnd.ms-excel;base64,AAAA".to_string(),
+                text: Some("a,b".to_string()),
+            },
+            &names,
+        );
+        assert!(sheet.inline_data.is_none());
+        assert!(sheet.text.unwrap().contains("a,b"));
+    }
+
+    #[test]
+    fn parses_text_function_calls_and_grounding() {
+        let json = r#"{
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "thinking...", "thought": true},
+                    {"text": "It is raining in Oslo."},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Bergen"}}}
+                ]},
+                "finishReason": "STOP",
+                "groundingMetadata": {"groundingChunks": [
+                    {"web": {"uri": "https://yr.no", "title": "Yr"}},
+                    {"web": {"uri": "https://yr.no", "title": "Yr"}}
+                ]}
+            }],
+            "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 7},
+            "modelVersion": "gemini-2.5-flash-001"
+        }"#;
+
+        let response = from_gemini_response(serde_json::from_str(json).unwrap(), "gemini");
+        assert_eq!(response.model, "gemini-2.5-flash-001");
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage.as_ref().unwrap().input_tokens, 12);
+        assert_eq!(response.usage.as_ref().unwrap().output_tokens, 7);
+        assert_eq!(response.content.len(), 3);
+        assert!(
+            matches!(&response.content[0], ContentBlock::Text { text } if text == "It is raining in Oslo.")
+        );
+        assert!(
+            matches!(&response.content[1], ContentBlock::ToolUse { name, input, .. } if name == "get_weather" && input["city"] == "Bergen")
+        );
+        assert!(
+            matches!(&response.content[2], ContentBlock::Text { text } if text == "\n\nSources:\n- [Yr](https://yr.no)")
+        );
+    }
+
+    #[test]
+    fn stream_chunks_become_events() {
+        let mut state = StreamState::default();
+        state.push_chunk(
+            serde_json::from_str(r#"{"candidates": [{"content": {"parts": [{"text": "Hel"}]}}]}"#)
+                .unwrap(),
+        );
+        state.push_chunk(
+            serde_json::from_str(
+                r#"{"candidates": [{"content": {"parts": [
+                    {"functionCall": {"id": "c1", "name": "get_weather", "args": {"city": "Oslo"}}}
+                ]}, "finishReason": "STOP"}],
+                "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 2}}"#,
+            )
+            .unwrap(),
+        );
+        state.finish();
+
+        let events: Vec<StreamEvent> = state.pending.into_iter().collect();
+        assert!(matches!(&events[0], StreamEvent::TextDelta(t) if t == "Hel"));
+        assert!(
+            matches!(&events[1], StreamEvent::ToolUseStart { index: 0, id, name } if id == "c1" && name == "get_weather")
+        );
+        assert!(matches!(&events[2], StreamEvent::InputJsonDelta(j) if j == r#"{"city":"Oslo"}"#));
+        assert!(matches!(
+            &events[3],
+            StreamEvent::ContentBlockStop { index: 0 }
+        ));
+        assert!(matches!(
+            &events[4],
+            StreamEvent::MessageDelta { stop_reason: Some(r), usage: Some(u) }
+                if r == "tool_use" && u.input_tokens == 3
+        ));
+        assert!(matches!(&events[5], StreamEvent::MessageStop));
+    }
+
+    #[tokio::test]
+    async fn context_cache_replaces_system_instruction_and_tools() {
+        let provider = GeminiProvider::new("key", None, Some("http://127.0.0.1:9".to_string()))
+            .with_context_cache(Some(Duration::from_secs(600)));
+        let req = request(Vec::new());
+
+        // The cache cannot be created against an unreachable server, so the
+        // request is sent inline and the failure is remembered.
+        let mut body = provider.build_request(&req);
+        provider.apply_context_cache(DEFAULT_MODEL, &mut body).await;
+        assert!(body.cached_content.is_none());
+        assert!(body.system_instruction.is_some());
+        assert_eq!(provider.caches.lock().unwrap().len(), 1);
+
+        let key = provider
+            .caches
+            .lock()
+            .unwrap()
+            .keys()
+            .next()
+            .unwrap()
+            .clone();
+        provider.caches.lock().unwrap().insert(
+            key,
+            (
+                Some("cachedContents/abc".to_string()),
+                Instant::now() + Duration::from_secs(60),
+            ),
+        );
+        let mut body = provider.build_request(&req);
+        provider.apply_context_cache(DEFAULT_MODEL, &mut body).await;
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["cachedContent"], "cachedContents/abc");
+        assert!(json.get("systemInstruction").is_none());
+        assert!(json.get("tools").is_none());
+    }
+}
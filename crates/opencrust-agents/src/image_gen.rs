@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use opencrust_common::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// Image generation can take well over a minute on busy or local backends.
+const IMAGE_TIMEOUT_SECS: u64 = 180;
+
+/// Size used when neither the request nor the provider config sets one.
+pub const DEFAULT_IMAGE_SIZE: &str = "1024x1024";
+
+/// One generated image.
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+    /// Prompt the provider actually used, when it rewrites prompts (DALL-E 3).
+    pub revised_prompt: Option<String>,
+}
+
+/// Backend that turns a text prompt into an image.
+#[async_trait]
+pub trait ImageProvider: Send + Sync {
+    fn provider_id(&self) -> &str;
+    /// Generate one image. `size` is `"<width>x<height>"`; `None` uses the
+    /// provider's default.
+    async fn generate(&self, prompt: &str, size: Option<&str>) -> Result<GeneratedImage>;
+}
+
+fn image_http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(IMAGE_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+}
+
+fn decode_base64_image(encoded: &str, provider: &str) -> Result<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| Error::Agent(format!("{provider} returned invalid base64 image: {e}")))
+}
+
+/// Parse `"<width>x<height>"`.
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (w, h) = size.trim().split_once(['x', 'X'])?;
+    let (w, h) = (w.trim().parse().ok()?, h.trim().parse().ok()?);
+    (w > 0 && h > 0).then_some((w, h))
+}
+
+async fn error_for_status(
+    response: reqwest::Response,
+    provider: &str,
+) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(Error::Agent(format!(
+        "{provider} image request failed: status={status}, body={body}"
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// OpenAI Images (dall-e-3, gpt-image-1, or any OpenAI-compatible endpoint)
+// ---------------------------------------------------------------------------
+
+/// OpenAI `/v1/images/generations`.
+pub struct OpenAiImageProvider {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    size: String,
+}
+
+impl OpenAiImageProvider {
+    pub fn new(
+        api_key: impl Into<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+        size: Option<String>,
+    ) -> Self {
+        Self {
+            client: image_http_client(),
+            api_key: api_key.into(),
+            model: model.unwrap_or_else(|| "dall-e-3".to_string()),
+            base_url: base_url.unwrap_or_else(|| "https://api.openai.com".to_string()),
+            size: size.unwrap_or_else(|| DEFAULT_IMAGE_SIZE.to_string()),
+        }
+    }
+
+    /// `{base}/v1/images/generations`, without doubling a `/v1` already in `base_url`.
+    fn endpoint(&self) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        if base.ends_with("/v1") {
+            format!("{base}/images/generations")
+        } else {
+            format!("{base}/v1/images/generations")
+        }
+    }
+
+    fn build_request_body(&self, prompt: &str, size: Option<&str>) -> OpenAiImageRequest {
+        OpenAiImageRequest {
+            model: self.model.clone(),
+            prompt: prompt.to_string(),
+            n: 1,
+            size: size.unwrap_or(&self.size).to_string(),
+            // gpt-image models always return base64 and reject the parameter.
+            response_format: (!self.model.starts_with("gpt-image")).then(|| "b64_json".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiImageRequest {
+    model: String,
+    prompt: String,
+    n: u32,
+    size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiImageResponse {
+    data: Vec<OpenAiImageData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiImageData {
+    b64_json: Option<String>,
+    revised_prompt: Option<String>,
+}
+
+#[async_trait]
+impl ImageProvider for OpenAiImageProvider {
+    fn provider_id(&self) -> &str {
+        "openai"
+    }
+
+    async fn generate(&self, prompt: &str, size: Option<&str>) -> Result<GeneratedImage> {
+        let response = self
+            .client
+            .post(self.endpoint())
+            .bearer_auth(&self.api_key)
+            .json(&self.build_request_body(prompt, size))
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("openai image request failed: {e}")))?;
+        let payload: OpenAiImageResponse = error_for_status(response, "openai")
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to decode openai image response: {e}")))?;
+
+        let image = payload
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Agent("openai returned no images".into()))?;
+        let encoded = image
+            .b64_json
+            .ok_or_else(|| Error::Agent("openai image response missing b64_json".into()))?;
+        Ok(GeneratedImage {
+            data: decode_base64_image(&encoded, "openai")?,
+            mime_type: "image/png".to_string(),
+            revised_prompt: image.revised_prompt,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Stability AI (Stable Image v2beta)
+// ---------------------------------------------------------------------------
+
+/// Aspect ratios the Stable Image API accepts, as `(label, width / height)`.
+const STABILITY_ASPECT_RATIOS: &[(&str, f64)] = &[
+    ("21:9", 21.0 / 9.0),
+    ("16:9", 16.0 / 9.0),
+    ("3:2", 3.0 / 2.0),
+    ("5:4", 5.0 / 4.0),
+    ("1:1", 1.0),
+    ("4:5", 4.0 / 5.0),
+    ("2:3", 2.0 / 3.0),
+    ("9:16", 9.0 / 16.0),
+    ("9:21", 9.0 / 21.0),
+];
+
+/// Stability AI `/v2beta/stable-image/generate/{model}`. Stability takes an
+/// aspect ratio rather than a size, so sizes map to the closest ratio.
+pub struct StabilityImageProvider {
+    client: reqwest::Client,
+    api_key: String,
+    /// `core`, `ultra` or `sd3`.
+    model: String,
+    base_url: String,
+    size: String,
+}
+
+impl StabilityImageProvider {
+    pub fn new(
+        api_key: impl Into<String>,
+        model: Option<String>,
+        base_url: Option<String>,
+        size: Option<String>,
+    ) -> Self {
+        Self {
+            client: image_http_client(),
+            api_key: api_key.into(),
+            model: model.unwrap_or_else(|| "core".to_string()),
+            base_url: base_url.unwrap_or_else(|| "https://api.stability.ai".to_string()),
+            size: size.unwrap_or_else(|| DEFAULT_IMAGE_SIZE.to_string()),
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        format!(
+            "{}/v2beta/stable-image/generate/{}",
+            self.base_url.trim_end_matches('/'),
+            self.model
+        )
+    }
+}
+
+/// The supported aspect ratio closest to `size`; `1:1` when it does not parse.
+fn stability_aspect_ratio(size: &str) -> &'static str {
+    let Some((w, h)) = parse_size(size) else {
+        return "1:1";
+    };
+    let ratio = f64::from(w) / f64::from(h);
+    STABILITY_ASPECT_RATIOS
+        .iter()
+        .min_by(|a, b| (a.1 - ratio).abs().total_cmp(&(b.1 - ratio).abs()))
+        .map(|(label, _)| *label)
+        .unwrap_or("1:1")
+}
+
+#[async_trait]
+impl ImageProvider for StabilityImageProvider {
+    fn provider_id(&self) -> &str {
+        "stability"
+    }
+
+    async fn generate(&self, prompt: &str, size: Option<&str>) -> Result<GeneratedImage> {
+        let form = reqwest::multipart::Form::new()
+            .text("prompt", prompt.to_string())
+            .text("output_format", "png")
+            .text(
+                "aspect_ratio",
+                stability_aspect_ratio(size.unwrap_or(&self.size)),
+            );
+        let response = self
+            .client
+            .post(self.endpoint())
+            .bearer_auth(&self.api_key)
+            .header("Accept", "image/*")
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("stability image request failed: {e}")))?;
+        let data = error_for_status(response, "stability")
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to read stability image: {e}")))?;
+        Ok(GeneratedImage {
+            data: data.to_vec(),
+            mime_type: "image/png".to_string(),
+            revised_prompt: None,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Stable Diffusion web UI (AUTOMATIC1111 / Forge, self-hosted)
+// ---------------------------------------------------------------------------
+
+/// A local Stable Diffusion web UI started with `--api`, via
+/// `/sdapi/v1/txt2img`. No API key needed.
+pub struct SdWebUiImageProvider {
+    client: reqwest::Client,
+    base_url: String,
+    size: String,
+    steps: u32,
+}
+
+impl SdWebUiImageProvider {
+    pub fn new(base_url: Option<String>, size: Option<String>, steps: Option<u32>) -> Self {
+        Self {
+            client: image_http_client(),
+            base_url: base_url.unwrap_or_else(|| "http://127.0.0.1:7860".to_string()),
+            size: size.unwrap_or_else(|| "512x512".to_string()),
+            steps: steps.unwrap_or(20),
+        }
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/sdapi/v1/txt2img", self.base_url.trim_end_matches('/'))
+    }
+
+    fn build_request_body(&self, prompt: &str, size: Option<&str>) -> serde_json::Value {
+        let (width, height) = parse_size(size.unwrap_or(&self.size)).unwrap_or((512, 512));
+        serde_json::json!({
+            "prompt": prompt,
+            "width": width,
+            "height": height,
+            "steps": self.steps,
+            "batch_size": 1,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SdWebUiResponse {
+    images: Vec<String>,
+}
+
+#[async_trait]
+impl ImageProvider for SdWebUiImageProvider {
+    fn provider_id(&self) -> &str {
+        "sdwebui"
+    }
+
+    async fn generate(&self, prompt: &str, size: Option<&str>) -> Result<GeneratedImage> {
+        let response = self
+            .client
+            .post(self.endpoint())
+            .json(&self.build_request_body(prompt, size))
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("sdwebui image request failed: {e}")))?;
+        let payload: SdWebUiResponse = error_for_status(response, "sdwebui")
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to decode sdwebui response: {e}")))?;
+        let encoded = payload
+            .images
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Agent("sdwebui returned no images".into()))?;
+        Ok(GeneratedImage {
+            data: decode_base64_image(&encoded, "sdwebui")?,
+            mime_type: "image/png".to_string(),
+            revised_prompt: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openai_omits_response_format_for_gpt_image_models() {
+        let provider = OpenAiImageProvider::new("sk-test", None, None, None);
+        assert_eq!(
+            provider.endpoint(),
+            "https://api.openai.com/v1/images/generations"
+        );
+        let body = serde_json::to_value(provider.build_request_body("a cat", None)).unwrap();
+        assert_eq!(body["model"], "dall-e-3");
+        assert_eq!(body["size"], DEFAULT_IMAGE_SIZE);
+        assert_eq!(body["response_format"], "b64_json");
+
+        let provider = OpenAiImageProvider::new("sk-test", Some("gpt-image-1".into()), None, None);
+        let body =
+            serde_json::to_value(provider.build_request_body("a cat", Some("1536x1024"))).unwrap();
+        assert_eq!(body["size"], "1536x1024");
+        assert!(body.get("response_format").is_none());
+    }
+
+    #[test]
+    fn stability_maps_sizes_to_nearest_aspect_ratio() {
+        assert_eq!(stability_aspect_ratio("1024x1024"), "1:1");
+        assert_eq!(stability_aspect_ratio("1792x1024"), "16:9");
+        assert_eq!(stability_aspect_ratio("1024x1536"), "2:3");
+        assert_eq!(stability_aspect_ratio("wide"), "1:1");
+    }
+
+    #[test]
+    fn sdwebui_request_uses_parsed_size() {
+        let provider = SdWebUiImageProvider::new(Some("http://gpu:7860/".into()), None, None);
+        assert_eq!(provider.endpoint(), "http://gpu:7860/sdapi/v1/txt2img");
+        let body = provider.build_request_body("a cat", Some("768x512"));
+        assert_eq!(body["width"], 768);
+        assert_eq!(body["height"], 512);
+        assert_eq!(body["steps"], 20);
+    }
+
+    #[tokio::test]
+    async fn openai_decodes_base64_image() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/images/generations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{"b64_json": "iVBORw==", "revised_prompt": "a tabby cat"}]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiImageProvider::new("sk-test", None, Some(server.uri()), None);
+        let image = provider.generate("a cat", None).await.unwrap();
+        assert_eq!(image.data, b"\x89PNG");
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.revised_prompt.as_deref(), Some("a tabby cat"));
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+/// A language detected in inbound user text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. `eng`, `spa`, `tha`.
+    pub code: &'static str,
+    /// English name of the language, e.g. `Spanish`.
+    pub name: &'static str,
+}
+
+/// Detect the language of `text`. Returns `None` when the text is too short
+/// or ambiguous for a reliable guess.
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() {
+        return None;
+    }
+    let lang = info.lang();
+    Some(DetectedLanguage {
+        code: lang.code(),
+        name: lang.eng_name(),
+    })
+}
+
+/// Build the system prompt instruction for the language of `text`.
+///
+/// A prompt configured for the detected language (keyed by ISO 639-3 code)
+/// takes precedence; otherwise a generic "respond in {lang}" instruction is used.
+pub fn language_instruction(text: &str, prompts: &HashMap<String, String>) -> Option<String> {
+    let detected = detect_language(text)?;
+    if let Some(prompt) = prompts
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(detected.code))
+        .map(|(_, prompt)| prompt)
+    {
+        return Some(prompt.clone());
+    }
+    Some(format!(
+        "The user is writing in {}. Respond in {} unless they ask for another language.",
+        detected.name, detected.name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_sample_languages() {
+        let samples = [
+            (
+                "Hello, could you help me write a short note to my landlord about the broken heater?",
+                "eng",
+            ),
+            (
+                "Hola, ¿podrías ayudarme a escribir una nota corta para mi casero sobre la calefacción rota?",
+                "spa",
+            ),
+            (
+                "Bonjour, pourriez-vous m'aider à écrire une courte lettre à mon propriétaire au sujet du chauffage?",
+                "fra",
+            ),
+            (
+                "Hallo, könntest du mir helfen, eine kurze Nachricht an meinen Vermieter wegen der kaputten Heizung zu schreiben?",
+                "deu",
+            ),
+            (
+                "สวัสดีครับ ช่วยเขียนข้อความสั้นๆ ถึงเจ้าของห้องเรื่องเครื่องทำความร้อนเสียหน่อยได้ไหม",
+                "tha",
+            ),
+        ];
+        for (text, code) in samples {
+            let detected = detect_language(text).expect("language should be detected");
+            assert_eq!(detected.code, code, "wrong language for {text:?}");
+        }
+    }
+
+    #[test]
+    fn short_ambiguous_text_is_not_detected() {
+        assert!(detect_language("ok").is_none());
+        assert!(detect_language("").is_none());
+    }
+
+    #[test]
+    fn generic_instruction_names_language() {
+        let instruction = language_instruction(
+            "Hola, ¿podrías ayudarme a escribir una nota corta para mi casero sobre la calefacción rota?",
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert!(instruction.contains("Respond in Spanish"));
+    }
+
+    #[test]
+    fn configured_prompt_overrides_generic_instruction() {
+        let mut prompts = HashMap::new();
+        prompts.insert(
+            "SPA".to_string(),
+            "Responde siempre en español.".to_string(),
+        );
+        let instruction = language_instruction(
+            "Hola, ¿podrías ayudarme a escribir una nota corta para mi casero sobre la calefacción rota?",
+            &prompts,
+        );
+        assert_eq!(instruction.as_deref(), Some("Responde siempre en español."));
+    }
+}
@@ -3,30 +3,49 @@ pub mod mcp;
 
 pub mod a2a;
 pub mod anthropic;
+pub mod attachment_cache;
 pub mod embeddings;
+pub mod gemini;
+pub mod image_gen;
+pub mod language;
+pub mod llamacpp;
 pub mod ollama;
 pub mod openai;
 pub mod providers;
+pub mod rate_limit;
 pub mod runtime;
 pub mod skill_suggester;
 pub mod tools;
+pub mod transcript;
 
 pub use anthropic::AnthropicProvider;
-pub use embeddings::{CohereEmbeddingProvider, EmbeddingProvider, OllamaEmbeddingProvider};
+pub use attachment_cache::AttachmentCache;
+pub use embeddings::{
+    CohereEmbeddingProvider, EmbeddingProvider, OllamaEmbeddingProvider, OpenAiEmbeddingProvider,
+};
+pub use gemini::GeminiProvider;
+pub use image_gen::{
+    GeneratedImage, ImageProvider, OpenAiImageProvider, SdWebUiImageProvider,
+    StabilityImageProvider,
+};
+pub use llamacpp::{LlamaCppMode, LlamaCppProvider};
 pub use ollama::OllamaProvider;
 pub use openai::OpenAiProvider;
 pub use providers::{
     ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart,
-    StreamEvent, ToolDefinition,
+    ResponseFormat, StreamEvent, ToolDefinition, document_fallback_text,
 };
-pub use runtime::AgentRuntime;
+pub use rate_limit::RateLimitRetry;
+pub use runtime::{AgentRuntime, SessionModel, ToolCallTrace, TurnTrace};
 pub use skill_suggester::{SkillSuggestion, suggest_from_trajectories};
 pub use tools::{
-    BashTool, CancelHeartbeat, CreateSkillTool, DocSearchTool, FilePatchTool, FileReadTool,
-    FileWriteTool, GoogleSearchTool, HandoffHandle, HandoffTool, ListDocumentsTool, ListHeartbeats,
-    MemoryTool, OutboundMessage, ScheduleHeartbeat, SearchFilesTool, SendMessageHandle,
-    SendMessageTool, Tool, ToolContext, ToolOutput, WebFetchTool, WebSearchTool,
+    AskUserTool, BashTool, CancelHeartbeat, CreateSkillTool, DocSearchTool, FilePatchTool,
+    FileReadTool, FileWriteTool, GoogleSearchTool, HandoffHandle, HandoffTool, ImageGenTool,
+    ListDocumentsTool, ListHeartbeats, MemoryTool, OutboundMessage, ScheduleHeartbeat,
+    ScheduleMessage, ScheduleMessageHandle, ScheduleTargetCheck, SearchFilesTool,
+    SendMessageHandle, SendMessageTool, Tool, ToolContext, ToolOutput, WebFetchTool, WebSearchTool,
 };
+pub use transcript::TranscriptWriter;
 
 #[cfg(feature = "mcp")]
 pub use mcp::{McpManager, McpPromptInfo, McpResourceInfo, McpResourceTool, McpToolInfo};
@@ -0,0 +1,672 @@
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use opencrust_common::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, instrument};
+
+use crate::openai::OpenAiProvider;
+use crate::providers::{
+    ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart, ResponseFormat,
+    StreamEvent, Usage, document_fallback_text,
+};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+/// llama-server serves a single model and ignores the requested name.
+const DEFAULT_MODEL: &str = "local";
+
+/// Which llama-server endpoint completions are sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlamaCppMode {
+    /// The OpenAI-compatible `/v1/chat/completions` endpoint, with tool use.
+    Chat,
+    /// The native `/completion` endpoint. The prompt is rendered with the
+    /// model's chat template via `/apply-template`, and requests can be
+    /// pinned to a slot so its prompt cache is reused across turns. No tools.
+    Completion,
+}
+
+/// Load state reported by llama-server's `/health` endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LlamaServerStatus {
+    /// The model is loaded and the server accepts requests.
+    Ready,
+    /// The server is up but still loading the model.
+    Loading,
+    /// The server is unreachable or reported an error.
+    Unavailable(String),
+}
+
+/// One processing slot of a llama-server instance, from `GET /slots`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LlamaSlot {
+    pub id: u32,
+    #[serde(default)]
+    pub is_processing: bool,
+    /// Context size of the slot, in tokens.
+    #[serde(default)]
+    pub n_ctx: u32,
+}
+
+/// Provider for llama.cpp's `llama-server`, for fully offline GGUF models.
+pub struct LlamaCppProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    name: String,
+    mode: LlamaCppMode,
+    slot: Option<u32>,
+    chat: OpenAiProvider,
+}
+
+impl LlamaCppProvider {
+    pub fn new(model: Option<String>, base_url: Option<String>, api_key: Option<String>) -> Self {
+        let base_url = base_url
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let model = model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+        let chat = OpenAiProvider::new(
+            api_key.clone().unwrap_or_default(),
+            Some(model.clone()),
+            Some(base_url.clone()),
+        )
+        .with_name("llamacpp");
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            api_key,
+            model,
+            name: "llamacpp".to_string(),
+            mode: LlamaCppMode::Chat,
+            slot: None,
+            chat,
+        }
+    }
+
+    /// Override the provider ID used for config-key-based lookups.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.chat = self.chat.with_name(name.clone());
+        self.name = name;
+        self
+    }
+
+    /// Declare whether the server accepts tools (llama-server needs `--jinja`).
+    /// Only applies in [`LlamaCppMode::Chat`].
+    pub fn with_supports_tools(mut self, supports_tools: bool) -> Self {
+        self.chat = self.chat.with_supports_tools(supports_tools);
+        self
+    }
+
+    pub fn with_mode(mut self, mode: LlamaCppMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Pin completions to slot `id` so its prompt cache is reused. Only
+    /// applies in [`LlamaCppMode::Completion`].
+    pub fn with_slot(mut self, id: Option<u32>) -> Self {
+        self.slot = id;
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{path}", self.base_url));
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    /// Send a request and turn a non-2xx status into an error.
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| Error::Agent(format!("llama.cpp request failed: {e}")))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = response.text().await.unwrap_or_default();
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("llama.cpp server error: status={status}, body={body}"),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Report whether the server has finished loading its model.
+    pub async fn server_status(&self) -> LlamaServerStatus {
+        let response = match self.request(reqwest::Method::GET, "/health").send().await {
+            Ok(response) => response,
+            Err(e) => return LlamaServerStatus::Unavailable(format!("unreachable: {e}")),
+        };
+        let status = response.status();
+        if status.is_success() {
+            return LlamaServerStatus::Ready;
+        }
+        let body: Value = response.json().await.unwrap_or_default();
+        let message = body["error"]["message"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("status {status}"));
+        if status == reqwest::StatusCode::SERVICE_UNAVAILABLE
+            && message.to_lowercase().contains("loading")
+        {
+            LlamaServerStatus::Loading
+        } else {
+            LlamaServerStatus::Unavailable(message)
+        }
+    }
+
+    /// List the server's slots. Requires the `/slots` endpoint to be enabled.
+    pub async fn slots(&self) -> Result<Vec<LlamaSlot>> {
+        self.send(self.request(reqwest::Method::GET, "/slots"))
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse llama.cpp slots: {e}")))
+    }
+
+    /// Clear the prompt cache of slot `id`.
+    pub async fn erase_slot(&self, id: u32) -> Result<()> {
+        self.slot_action(id, "erase", None).await
+    }
+
+    /// Save slot `id`'s prompt cache to `filename` in the server's
+    /// `--slot-save-path` directory.
+    pub async fn save_slot(&self, id: u32, filename: &str) -> Result<()> {
+        self.slot_action(id, "save", Some(filename)).await
+    }
+
+    /// Load a prompt cache saved with [`save_slot`](Self::save_slot) into slot `id`.
+    pub async fn restore_slot(&self, id: u32, filename: &str) -> Result<()> {
+        self.slot_action(id, "restore", Some(filename)).await
+    }
+
+    async fn slot_action(&self, id: u32, action: &str, filename: Option<&str>) -> Result<()> {
+        let mut builder = self.request(
+            reqwest::Method::POST,
+            &format!("/slots/{id}?action={action}"),
+        );
+        if let Some(filename) = filename {
+            builder = builder.json(&serde_json::json!({ "filename": filename }));
+        }
+        self.send(builder).await.map(|_| ())
+    }
+
+    /// Render the conversation into a prompt with the model's chat template.
+    async fn apply_template(&self, request: &LlmRequest) -> Result<String> {
+        let body = serde_json::json!({ "messages": template_messages(request) });
+        let rendered: TemplateResponse = self
+            .send(
+                self.request(reqwest::Method::POST, "/apply-template")
+                    .json(&body),
+            )
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse llama.cpp template: {e}")))?;
+        Ok(rendered.prompt)
+    }
+
+    async fn completion(&self, request: &LlmRequest, stream: bool) -> Result<reqwest::Response> {
+        let body = CompletionRequest {
+            prompt: self.apply_template(request).await?,
+            n_predict: request.max_tokens,
+            temperature: request.temperature,
+            seed: request.seed,
+            stream,
+            cache_prompt: true,
+            id_slot: self.slot,
+            json_schema: request
+                .response_format
+                .as_ref()
+                .map(|ResponseFormat::JsonSchema { schema, .. }| schema.clone()),
+        };
+        debug!("llama.cpp completion request: slot={:?}", self.slot);
+        self.send(
+            self.request(reqwest::Method::POST, "/completion")
+                .json(&body),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for LlamaCppProvider {
+    fn provider_id(&self) -> &str {
+        &self.name
+    }
+
+    fn configured_model(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.mode == LlamaCppMode::Chat && self.chat.supports_tools()
+    }
+
+    async fn available_models(&self) -> Result<Vec<String>> {
+        let models: ModelList = self
+            .send(self.request(reqwest::Method::GET, "/v1/models"))
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse llama.cpp models: {e}")))?;
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+
+    #[instrument(skip(self, request))]
+    async fn complete(&self, request: &LlmRequest) -> Result<LlmResponse> {
+        if self.mode == LlamaCppMode::Chat {
+            return self.chat.complete(request).await;
+        }
+        let response: CompletionChunk = self
+            .completion(request, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| Error::Agent(format!("failed to parse llama.cpp response: {e}")))?;
+        Ok(LlmResponse {
+            content: vec![ContentBlock::Text {
+                text: response.content.clone(),
+            }],
+            model: response.model.clone().unwrap_or_else(|| self.model.clone()),
+            usage: Some(response.usage()),
+            stop_reason: Some(response.stop_reason()),
+        })
+    }
+
+    #[instrument(skip(self, request))]
+    async fn stream_complete(
+        &self,
+        request: &LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        if self.mode == LlamaCppMode::Chat {
+            return self.chat.stream_complete(request).await;
+        }
+        let response = self.completion(request, true).await?;
+
+        let byte_stream: Pin<
+            Box<dyn Stream<Item = std::result::Result<bytes::Bytes, reqwest::Error>> + Send>,
+        > = Box::pin(response.bytes_stream());
+
+        let event_stream = futures::stream::unfold(
+            (byte_stream, String::new(), Vec::<StreamEvent>::new()),
+            |(mut stream, mut buffer, mut pending)| async move {
+                loop {
+                    if !pending.is_empty() {
+                        let event = pending.remove(0);
+                        return Some((Ok(event), (stream, buffer, pending)));
+                    }
+
+                    if let Some(pos) = buffer.find("\n\n") {
+                        let event_str = buffer[..pos].to_string();
+                        buffer = buffer[pos + 2..].to_string();
+                        let data: String = event_str
+                            .lines()
+                            .filter_map(|line| line.strip_prefix("data:"))
+                            .map(str::trim_start)
+                            .collect();
+                        match serde_json::from_str::<CompletionChunk>(&data) {
+                            Ok(chunk) => pending = chunk.into_events(),
+                            Err(e) if !data.is_empty() => {
+                                debug!("skipping unparseable llama.cpp stream event: {e}");
+                            }
+                            Err(_) => {}
+                        }
+                        continue;
+                    }
+
+                    match stream.next().await {
+                        Some(Ok(bytes)) => {
+                            buffer.push_str(&String::from_utf8_lossy(&bytes));
+                            if buffer.contains('\r') {
+                                buffer = buffer.replace("\r\n", "\n");
+                            }
+                        }
+                        Some(Err(e)) => {
+                            return Some((
+                                Err(Error::Agent(format!("stream read error: {e}"))),
+                                (stream, buffer, pending),
+                            ));
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(event_stream))
+    }
+
+    /// Healthy once the model is loaded. A server that is still loading is
+    /// reported as an error saying so, rather than as unreachable.
+    async fn health_check(&self) -> Result<bool> {
+        match self.server_status().await {
+            LlamaServerStatus::Ready => Ok(true),
+            LlamaServerStatus::Loading => Err(Error::Agent(format!(
+                "llama.cpp server at {} is still loading the model",
+                self.base_url
+            ))),
+            LlamaServerStatus::Unavailable(reason) => Err(Error::Agent(format!(
+                "llama.cpp server at {}: {reason}",
+                self.base_url
+            ))),
+        }
+    }
+}
+
+// --- llama-server Wire Types (private) ---
+
+#[derive(Debug, Serialize)]
+struct CompletionRequest {
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    stream: bool,
+    cache_prompt: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_slot: Option<u32>,
+    /// Grammar-constrains the output to this JSON Schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateResponse {
+    prompt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChunk {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    model: Option<String>,
+    /// Set on the final chunk of a stream (always set when not streaming).
+    #[serde(default)]
+    stop: bool,
+    /// Why generation ended: `eos`, `word` (stop string) or `limit`.
+    #[serde(default)]
+    stop_type: Option<String>,
+    #[serde(default)]
+    tokens_predicted: u32,
+    #[serde(default)]
+    tokens_evaluated: u32,
+}
+
+impl CompletionChunk {
+    fn usage(&self) -> Usage {
+        Usage {
+            input_tokens: self.tokens_evaluated,
+            output_tokens: self.tokens_predicted,
+        }
+    }
+
+    fn stop_reason(&self) -> String {
+        match self.stop_type.as_deref() {
+            Some("limit") => "max_tokens".to_string(),
+            _ => "end_turn".to_string(),
+        }
+    }
+
+    fn into_events(self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        if !self.content.is_empty() {
+            events.push(StreamEvent::TextDelta(self.content.clone()));
+        }
+        if self.stop {
+            events.push(StreamEvent::MessageDelta {
+                stop_reason: Some(self.stop_reason()),
+                usage: Some(self.usage()),
+            });
+            events.push(StreamEvent::MessageStop);
+        }
+        events
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Flatten the conversation into plain-text chat messages for
+/// `/apply-template`. Tool calls are dropped and tool results become user
+/// turns, since completion mode sends no tools.
+fn template_messages(request: &LlmRequest) -> Vec<Value> {
+    let mut messages = Vec::new();
+    if let Some(system) = request.system.as_deref().filter(|s| !s.is_empty()) {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    for message in &request.messages {
+        let role = match message.role {
+            ChatRole::System => "system",
+            ChatRole::User | ChatRole::Tool => "user",
+            ChatRole::Assistant => "assistant",
+        };
+        let content = match &message.content {
+            MessagePart::Text(text) => text.clone(),
+            MessagePart::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentBlock::Text { text } => Some(text.clone()),
+                    ContentBlock::Image { .. } => Some("[image]".to_string()),
+                    ContentBlock::Document { mime, text, .. } => {
+                        Some(document_fallback_text(mime, text.as_deref()))
+                    }
+                    ContentBlock::ToolResult { content, .. } => Some(content.clone()),
+                    ContentBlock::ToolUse { .. }
+                    | ContentBlock::Thinking { .. }
+                    | ContentBlock::RedactedThinking { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        if !content.is_empty() {
+            messages.push(serde_json::json!({ "role": role, "content": content }));
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ChatMessage;
+    use wiremock::matchers::{body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn request(text: &str) -> LlmRequest {
+        LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text(text.to_string()),
+            }],
+            system: Some("Be brief".to_string()),
+            max_tokens: Some(64),
+            temperature: None,
+            seed: None,
+            tools: Vec::new(),
+            response_format: None,
+            thinking: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn health_check_reports_model_loading() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(ResponseTemplate::new(503).set_body_json(serde_json::json!({
+                "error": { "code": 503, "message": "Loading model", "type": "unavailable_error" }
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = LlamaCppProvider::new(None, Some(server.uri()), None);
+        assert_eq!(provider.server_status().await, LlamaServerStatus::Loading);
+        let err = provider.health_check().await.unwrap_err();
+        assert!(err.to_string().contains("still loading the model"));
+
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/health"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "status": "ok" })),
+            )
+            .mount(&server)
+            .await;
+        assert!(provider.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn completion_mode_renders_template_and_pins_slot() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/apply-template"))
+            .and(body_partial_json(serde_json::json!({
+                "messages": [
+                    { "role": "system", "content": "Be brief" },
+                    { "role": "user", "content": "hello" },
+                ]
+            })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "prompt": "<s>hello</s>" })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/completion"))
+            .and(body_partial_json(serde_json::json!({
+                "prompt": "<s>hello</s>",
+                "n_predict": 64,
+                "cache_prompt": true,
+                "id_slot": 1,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": "Hi!",
+                "model": "qwen2.5-7b-instruct-q4_k_m.gguf",
+                "stop": true,
+                "stop_type": "eos",
+                "tokens_predicted": 3,
+                "tokens_evaluated": 12,
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = LlamaCppProvider::new(None, Some(server.uri()), None)
+            .with_mode(LlamaCppMode::Completion)
+            .with_slot(Some(1));
+        assert!(!provider.supports_tools());
+
+        let response = provider.complete(&request("hello")).await.unwrap();
+        assert!(matches!(&response.content[0], ContentBlock::Text { text } if text == "Hi!"));
+        assert_eq!(response.model, "qwen2.5-7b-instruct-q4_k_m.gguf");
+        assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(response.usage.unwrap().input_tokens, 12);
+    }
+
+    #[tokio::test]
+    async fn completion_mode_streams_text_deltas() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/apply-template"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "prompt": "p" })),
+            )
+            .mount(&server)
+            .await;
+        let body = concat!(
+            "data: {\"content\":\"Hel\",\"stop\":false}\n\n",
+            "data: {\"content\":\"lo\",\"stop\":false}\n\n",
+            "data: {\"content\":\"\",\"stop\":true,\"stop_type\":\"limit\",",
+            "\"tokens_predicted\":2,\"tokens_evaluated\":5}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/completion"))
+            .and(body_partial_json(serde_json::json!({ "stream": true })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = LlamaCppProvider::new(None, Some(server.uri()), None)
+            .with_mode(LlamaCppMode::Completion);
+        let events: Vec<StreamEvent> = provider
+            .stream_complete(&request("hi"))
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        let text: String = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::TextDelta(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello");
+        assert!(matches!(
+            &events[2],
+            StreamEvent::MessageDelta { stop_reason: Some(reason), .. } if reason == "max_tokens"
+        ));
+        assert!(matches!(events.last(), Some(StreamEvent::MessageStop)));
+    }
+
+    #[tokio::test]
+    async fn slot_actions_post_to_slot_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/slots/0"))
+            .and(query_param("action", "save"))
+            .and(body_partial_json(
+                serde_json::json!({ "filename": "chat.bin" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/slots"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": 0, "is_processing": false, "n_ctx": 4096 },
+                { "id": 1, "is_processing": true, "n_ctx": 4096 },
+            ])))
+            .mount(&server)
+            .await;
+
+        let provider = LlamaCppProvider::new(None, Some(server.uri()), None);
+        provider.save_slot(0, "chat.bin").await.unwrap();
+        let slots = provider.slots().await.unwrap();
+        assert_eq!(slots.len(), 2);
+        assert!(slots[1].is_processing);
+    }
+}
@@ -9,6 +9,7 @@ use tracing::info;
 
 use crate::providers::{
     ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart, Usage,
+    document_fallback_text,
 };
 
 const DEFAULT_MODEL: &str = "llama3.1";
@@ -80,12 +81,14 @@ impl OllamaProvider {
                                         }
                                     }));
                                 }
-                                ContentBlock::ToolResult {
-                                    tool_use_id: _,
-                                    content,
-                                } => {
+                                ContentBlock::ToolResult { content, .. } => {
                                     text_parts.push(content.clone());
                                 }
+                                ContentBlock::Document { mime, text, .. } => {
+                                    text_parts.push(document_fallback_text(mime, text.as_deref()));
+                                }
+                                ContentBlock::Thinking { .. }
+                                | ContentBlock::RedactedThinking { .. } => {}
                             }
                         }
 
@@ -462,7 +465,10 @@ mod tests {
             system: None,
             max_tokens: Some(100),
             temperature: Some(0.7),
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let body = provider.build_request_body(&req, false);
@@ -475,6 +481,42 @@ mod tests {
         assert_eq!(body["options"]["num_predict"], 100);
     }
 
+    #[test]
+    fn documents_are_sent_as_extracted_text() {
+        let provider = OllamaProvider::new(None, None);
+        assert!(!provider.supports_documents("application/pdf"));
+        let req = LlmRequest {
+            model: "llama3".to_string(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Parts(vec![
+                    ContentBlock::Document {
+                        mime: "application/pdf".to_string(),
+                        url: "data:application/pdf;base64,JVBERi0=".to_string(),
+                        text: None,
+                    },
+                    ContentBlock::Text {
+                        text: "Summarize".to_string(),
+                    },
+                ]),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        let body = provider.build_request_body(&req, false);
+        assert_eq!(
+            body["messages"][0]["content"],
+            "[document (application/pdf): no text could be extracted]\nSummarize"
+        );
+        assert!(body["messages"][0].get("images").is_none());
+    }
+
     async fn run_mock_server() -> (String, oneshot::Sender<()>) {
         let (tx, rx) = oneshot::channel::<()>();
 
@@ -555,7 +597,10 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let res = provider.complete(&req).await.unwrap();
@@ -578,7 +623,10 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let mut stream = provider.stream_complete(&req).await.unwrap();
@@ -608,7 +656,10 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools,
+            response_format: None,
+            thinking: None,
         };
 
         let body = provider.build_request_body(&req, false);
@@ -9,11 +9,13 @@ use tracing::{debug, info, instrument};
 
 use crate::providers::{
     ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart,
-    StreamEvent, Usage,
+    ResponseFormat, StreamEvent, Usage, document_fallback_text,
 };
 
 const DEFAULT_MODEL: &str = "gpt-4o";
 const DEFAULT_BASE_URL: &str = "https://api.openai.com";
+/// Azure OpenAI REST API version used when none is configured.
+pub const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
 
 /// OpenAI Chat Completions provider.
 /// Also works with OpenAI-compatible APIs (Azure, local models) via `base_url`.
@@ -23,6 +25,15 @@ pub struct OpenAiProvider {
     model: String,
     base_url: String,
     name: Option<String>,
+    extra_headers: Vec<(String, String)>,
+    supports_tools: bool,
+    azure: Option<AzureDeployment>,
+}
+
+/// Routing for an Azure OpenAI deployment.
+struct AzureDeployment {
+    deployment: String,
+    api_version: String,
 }
 
 impl OpenAiProvider {
@@ -37,6 +48,9 @@ impl OpenAiProvider {
             model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
             base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
             name: None,
+            extra_headers: Vec::new(),
+            supports_tools: true,
+            azure: None,
         }
     }
 
@@ -48,11 +62,150 @@ impl OpenAiProvider {
         self
     }
 
+    /// Add an HTTP header sent with every request.
+    /// Used by gateways that require attribution headers (e.g. OpenRouter's
+    /// `HTTP-Referer` and `X-Title`).
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Declare whether the endpoint accepts the `tools` field. Some
+    /// OpenAI-compatible servers reject any request that includes it.
+    pub fn with_supports_tools(mut self, supports_tools: bool) -> Self {
+        self.supports_tools = supports_tools;
+        self
+    }
+
+    /// Send requests to the Azure OpenAI `deployment` of the resource at
+    /// `base_url` (`https://<resource>.openai.azure.com`), authenticating
+    /// with an `api-key` header. `api_version` defaults to
+    /// [`AZURE_DEFAULT_API_VERSION`].
+    pub fn with_azure_deployment(
+        mut self,
+        deployment: impl Into<String>,
+        api_version: Option<String>,
+    ) -> Self {
+        self.azure = Some(AzureDeployment {
+            deployment: deployment.into(),
+            api_version: api_version.unwrap_or_else(|| AZURE_DEFAULT_API_VERSION.to_string()),
+        });
+        self
+    }
+
+    fn post(&self) -> reqwest::RequestBuilder {
+        let builder = self.client.post(self.endpoint());
+        let mut builder = match &self.azure {
+            Some(_) => builder.header("api-key", &self.api_key),
+            None => builder.header("authorization", format!("Bearer {}", self.api_key)),
+        }
+        .header("content-type", "application/json");
+        for (name, value) in &self.extra_headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        builder
+    }
+
+    /// Resolve the chat completions URL from `base_url`.
+    ///
+    /// - A base that already ends in `/chat/completions` is used verbatim.
+    /// - A base whose path contains a version segment (`/v1`, `/v1beta/openai`, ...)
+    ///   only gets `/chat/completions` appended, so `/v1` is never duplicated.
+    /// - Anything else (bare host or proxy prefix like `/api/openai`) gets
+    ///   `/v1/chat/completions` appended.
+    ///
+    /// Azure deployments use `{base}/openai/deployments/{deployment}/...`
+    /// with an `api-version` query parameter instead.
     fn endpoint(&self) -> String {
-        format!(
-            "{}/v1/chat/completions",
-            self.base_url.trim_end_matches('/')
-        )
+        let base = self.base_url.trim_end_matches('/');
+        if let Some(azure) = &self.azure {
+            let resource = base.trim_end_matches("/openai");
+            return format!(
+                "{resource}/openai/deployments/{}/chat/completions?api-version={}",
+                azure.deployment, azure.api_version
+            );
+        }
+        if base.ends_with("/chat/completions") {
+            return base.to_string();
+        }
+        if base_path_has_version(base) {
+            format!("{base}/chat/completions")
+        } else {
+            format!("{base}/v1/chat/completions")
+        }
+    }
+
+    /// Convert content blocks into one message. Images (and PDFs, where
+    /// supported) become typed content parts; otherwise the text is joined.
+    fn parts_message(&self, role: &str, blocks: &[ContentBlock]) -> OpenAiMessage {
+        let native_document =
+            |mime: &str, url: &str| self.supports_documents(mime) && url.starts_with("data:");
+        let has_media = blocks.iter().any(|b| match b {
+            ContentBlock::Image { .. } => true,
+            ContentBlock::Document { mime, url, .. } => native_document(mime, url),
+            _ => false,
+        });
+
+        if has_media {
+            let parts: Vec<OpenAiContentPart> = blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => {
+                        Some(OpenAiContentPart::Text { text: text.clone() })
+                    }
+                    ContentBlock::Image { url } => Some(OpenAiContentPart::ImageUrl {
+                        image_url: OpenAiImageUrl { url: url.clone() },
+                    }),
+                    ContentBlock::Document { mime, url, text } => {
+                        Some(if native_document(mime, url) {
+                            OpenAiContentPart::File {
+                                file: OpenAiFile {
+                                    filename: document_filename(mime),
+                                    file_data: url.clone(),
+                                },
+                            }
+                        } else {
+                            OpenAiContentPart::Text {
+                                text: document_fallback_text(mime, text.as_deref()),
+                            }
+                        })
+                    }
+                    _ => None,
+                })
+                .collect();
+            OpenAiMessage {
+                role: role.to_string(),
+                content: if parts.is_empty() {
+                    None
+                } else {
+                    Some(OpenAiContent::Parts(parts))
+                },
+                tool_calls: None,
+                tool_call_id: None,
+            }
+        } else {
+            let text: String = blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text } => Some(text.clone()),
+                    ContentBlock::Document { mime, text, .. } => {
+                        Some(document_fallback_text(mime, text.as_deref()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            OpenAiMessage {
+                role: role.to_string(),
+                content: if text.is_empty() {
+                    None
+                } else {
+                    Some(OpenAiContent::Text(text))
+                },
+                tool_calls: None,
+                tool_call_id: None,
+            }
+        }
     }
 
     fn build_request(&self, request: &LlmRequest) -> OpenAiRequest {
@@ -77,16 +230,20 @@ impl OpenAiProvider {
         // Convert chat messages
         for msg in &request.messages {
             match (&msg.role, &msg.content) {
-                // User messages with tool results expand to multiple "tool" messages
+                // User messages with tool results expand to multiple "tool" messages.
+                // Tool messages can only hold text, so any images or text sent
+                // alongside follow as a separate user message.
                 (ChatRole::User, MessagePart::Parts(blocks))
                     if blocks
                         .iter()
                         .any(|b| matches!(b, ContentBlock::ToolResult { .. })) =>
                 {
+                    let mut rest = Vec::new();
                     for block in blocks {
                         if let ContentBlock::ToolResult {
                             tool_use_id,
                             content,
+                            ..
                         } = block
                         {
                             messages.push(OpenAiMessage {
@@ -95,8 +252,13 @@ impl OpenAiProvider {
                                 tool_calls: None,
                                 tool_call_id: Some(tool_use_id.clone()),
                             });
+                        } else {
+                            rest.push(block.clone());
                         }
                     }
+                    if !rest.is_empty() {
+                        messages.push(self.parts_message("user", &rest));
+                    }
                 }
                 // Assistant messages with tool_use blocks
                 (ChatRole::Assistant, MessagePart::Parts(blocks)) => {
@@ -163,53 +325,7 @@ impl OpenAiProvider {
                         ChatRole::Tool => "tool",
                     };
 
-                    let has_images = blocks
-                        .iter()
-                        .any(|b| matches!(b, ContentBlock::Image { .. }));
-
-                    if has_images {
-                        let parts: Vec<OpenAiContentPart> = blocks
-                            .iter()
-                            .filter_map(|b| match b {
-                                ContentBlock::Text { text } => {
-                                    Some(OpenAiContentPart::Text { text: text.clone() })
-                                }
-                                ContentBlock::Image { url } => Some(OpenAiContentPart::ImageUrl {
-                                    image_url: OpenAiImageUrl { url: url.clone() },
-                                }),
-                                _ => None,
-                            })
-                            .collect();
-                        messages.push(OpenAiMessage {
-                            role: role_str.to_string(),
-                            content: if parts.is_empty() {
-                                None
-                            } else {
-                                Some(OpenAiContent::Parts(parts))
-                            },
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
-                    } else {
-                        let text: String = blocks
-                            .iter()
-                            .filter_map(|b| match b {
-                                ContentBlock::Text { text } => Some(text.as_str()),
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .join("\n");
-                        messages.push(OpenAiMessage {
-                            role: role_str.to_string(),
-                            content: if text.is_empty() {
-                                None
-                            } else {
-                                Some(OpenAiContent::Text(text))
-                            },
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
-                    }
+                    messages.push(self.parts_message(role_str, blocks));
                 }
             }
         }
@@ -217,6 +333,7 @@ impl OpenAiProvider {
         let tools: Vec<OpenAiTool> = request
             .tools
             .iter()
+            .filter(|_| self.supports_tools)
             .map(|t| OpenAiTool {
                 r#type: "function".to_string(),
                 function: OpenAiFunction {
@@ -233,12 +350,17 @@ impl OpenAiProvider {
             messages,
             max_tokens: request.max_tokens,
             temperature: request.temperature,
+            seed: request.seed,
             tools: if has_tools { Some(tools) } else { None },
             tool_choice: if has_tools {
                 Some("auto".to_string())
             } else {
                 None
             },
+            response_format: request
+                .response_format
+                .as_ref()
+                .map(to_openai_response_format),
         }
     }
 }
@@ -253,6 +375,16 @@ impl LlmProvider for OpenAiProvider {
         Some(&self.model)
     }
 
+    /// PDF file inputs are an OpenAI API feature; compatible endpoints
+    /// configured through `base_url` generally reject them.
+    fn supports_documents(&self, mime: &str) -> bool {
+        mime == "application/pdf" && self.base_url.trim_end_matches('/') == DEFAULT_BASE_URL
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.supports_tools
+    }
+
     #[instrument(skip(self, request), fields(model))]
     async fn complete(&self, request: &LlmRequest) -> Result<LlmResponse> {
         let body = self.build_request(request);
@@ -261,10 +393,7 @@ impl LlmProvider for OpenAiProvider {
         debug!("openai request: model={}", body.model);
 
         let response = self
-            .client
-            .post(self.endpoint())
-            .header("authorization", format!("Bearer {}", self.api_key))
-            .header("content-type", "application/json")
+            .post()
             .json(&body)
             .send()
             .await
@@ -272,10 +401,13 @@ impl LlmProvider for OpenAiProvider {
 
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Agent(format!(
-                "openai API error: status={status}, body={body}"
-            )));
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("openai API error: status={status}, body={body}"),
+            ));
         }
 
         let api_response: OpenAiResponse = response
@@ -304,10 +436,7 @@ impl LlmProvider for OpenAiProvider {
         body_value["stream_options"] = serde_json::json!({ "include_usage": true });
 
         let response = self
-            .client
-            .post(self.endpoint())
-            .header("authorization", format!("Bearer {}", self.api_key))
-            .header("content-type", "application/json")
+            .post()
             .json(&body_value)
             .send()
             .await
@@ -315,10 +444,13 @@ impl LlmProvider for OpenAiProvider {
 
         if !response.status().is_success() {
             let status = response.status();
+            let headers = response.headers().clone();
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Agent(format!(
-                "openai API error: status={status}, body={body}"
-            )));
+            return Err(crate::rate_limit::status_error(
+                status,
+                &headers,
+                format!("openai API error: status={status}, body={body}"),
+            ));
         }
 
         let byte_stream: Pin<
@@ -334,15 +466,8 @@ impl LlmProvider for OpenAiProvider {
                         let event_str = buffer[..pos].to_string();
                         buffer = buffer[pos + 2..].to_string();
 
-                        // Extract the data: line
-                        let mut data_line = None;
-                        for line in event_str.lines() {
-                            if let Some(d) = line.strip_prefix("data: ") {
-                                data_line = Some(d.to_string());
-                            }
-                        }
-
-                        if let Some(data) = data_line {
+                        // Comments, keep-alives and events without data are skipped
+                        if let Some(data) = sse_event_data(&event_str) {
                             // OpenAI sends "data: [DONE]" as the final event
                             if data == "[DONE]" {
                                 return Some((Ok(StreamEvent::MessageStop), (stream, buffer)));
@@ -371,6 +496,11 @@ impl LlmProvider for OpenAiProvider {
                     match stream.next().await {
                         Some(Ok(bytes)) => {
                             buffer.push_str(&String::from_utf8_lossy(&bytes));
+                            // Some servers delimit events with CRLF; a CRLF may also
+                            // straddle two chunks, so normalise the whole buffer.
+                            if buffer.contains('\r') {
+                                buffer = buffer.replace("\r\n", "\n");
+                            }
                         }
                         Some(Err(e)) => {
                             return Some((
@@ -397,7 +527,10 @@ impl LlmProvider for OpenAiProvider {
             system: None,
             max_tokens: Some(1),
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         match self.complete(&request).await {
@@ -421,9 +554,13 @@ struct OpenAiRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OpenAiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -453,6 +590,15 @@ enum OpenAiContentPart {
     Text { text: String },
     #[serde(rename = "image_url")]
     ImageUrl { image_url: OpenAiImageUrl },
+    #[serde(rename = "file")]
+    File { file: OpenAiFile },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct OpenAiFile {
+    filename: String,
+    /// Base64 `data:` URI.
+    file_data: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -573,6 +719,18 @@ impl serde::Serialize for SyntheticEvent {
                 map.serialize_entry("_synthetic", "input_json_delta")?;
                 map.serialize_entry("json", json)?;
             }
+            StreamEvent::ThinkingDelta(text) => {
+                map.serialize_entry("_synthetic", "thinking_delta")?;
+                map.serialize_entry("text", text)?;
+            }
+            StreamEvent::SignatureDelta(signature) => {
+                map.serialize_entry("_synthetic", "signature_delta")?;
+                map.serialize_entry("signature", signature)?;
+            }
+            StreamEvent::RedactedThinking(data) => {
+                map.serialize_entry("_synthetic", "redacted_thinking")?;
+                map.serialize_entry("data", data)?;
+            }
             StreamEvent::ContentBlockStop { index } => {
                 map.serialize_entry("_synthetic", "content_block_stop")?;
                 map.serialize_entry("index", index)?;
@@ -603,6 +761,15 @@ fn parse_synthetic_event(value: &serde_json::Value) -> Option<StreamEvent> {
         "input_json_delta" => Some(StreamEvent::InputJsonDelta(
             value.get("json")?.as_str()?.to_string(),
         )),
+        "thinking_delta" => Some(StreamEvent::ThinkingDelta(
+            value.get("text")?.as_str()?.to_string(),
+        )),
+        "signature_delta" => Some(StreamEvent::SignatureDelta(
+            value.get("signature")?.as_str()?.to_string(),
+        )),
+        "redacted_thinking" => Some(StreamEvent::RedactedThinking(
+            value.get("data")?.as_str()?.to_string(),
+        )),
         "content_block_stop" => Some(StreamEvent::ContentBlockStop {
             index: value.get("index")?.as_u64()? as usize,
         }),
@@ -618,6 +785,37 @@ fn parse_synthetic_event(value: &serde_json::Value) -> Option<StreamEvent> {
     }
 }
 
+/// The payload of one SSE event: its `data:` lines joined with newlines.
+///
+/// Comment lines (`: keep-alive`) and other fields (`event:`, `id:`,
+/// `retry:`) are ignored. Returns `None` when the event carries no data, as
+/// with blank keep-alives.
+fn sse_event_data(event: &str) -> Option<String> {
+    let mut data: Option<String> = None;
+    for line in event.lines() {
+        let Some(value) = line.strip_prefix("data:") else {
+            continue;
+        };
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match &mut data {
+            Some(existing) => {
+                existing.push('\n');
+                existing.push_str(value);
+            }
+            None => data = Some(value.to_string()),
+        }
+    }
+    data
+}
+
+fn to_openai_response_format(format: &ResponseFormat) -> serde_json::Value {
+    let ResponseFormat::JsonSchema { name, schema } = format;
+    serde_json::json!({
+        "type": "json_schema",
+        "json_schema": { "name": name, "schema": schema },
+    })
+}
+
 /// Parse an OpenAI streaming chunk into one or more StreamEvents.
 fn parse_stream_chunk(data: &str) -> Option<Vec<StreamEvent>> {
     let value: serde_json::Value = serde_json::from_str(data).ok()?;
@@ -693,6 +891,32 @@ fn parse_stream_chunk(data: &str) -> Option<Vec<StreamEvent>> {
     }
 }
 
+/// Filename sent with an inline file part; the API requires one.
+fn document_filename(mime: &str) -> String {
+    match mime.split_once('/') {
+        Some((_, ext)) if !ext.is_empty() => format!("document.{ext}"),
+        _ => "document".to_string(),
+    }
+}
+
+/// Returns true when the URL path contains an API version segment such as
+/// `v1`, `v2` or `v1beta`.
+fn base_path_has_version(base: &str) -> bool {
+    let path = match base.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map(|(_, p)| p).unwrap_or(""),
+        None => base,
+    };
+    path.split('/').any(|segment| {
+        segment
+            .strip_prefix('v')
+            .and_then(|rest| {
+                let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+                (digits > 0).then(|| &rest[digits..])
+            })
+            .is_some_and(|suffix| suffix.chars().all(|c| c.is_ascii_alphabetic()))
+    })
+}
+
 // --- Conversion ---
 
 fn from_openai_response(response: OpenAiResponse) -> LlmResponse {
@@ -763,7 +987,10 @@ mod tests {
             system: Some("You are helpful".to_string()),
             max_tokens: Some(1024),
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let openai_req = provider.build_request(&request);
@@ -778,6 +1005,81 @@ mod tests {
         assert!(openai_req.tools.is_none());
     }
 
+    #[test]
+    fn image_blocks_serialize_as_image_url_parts() {
+        let provider = OpenAiProvider::new("test-key", None, None);
+        // Both the native and the OpenAI-style shape deserialize to the same block.
+        let content: MessagePart = serde_json::from_value(serde_json::json!([
+            { "type": "text", "text": "Compare these" },
+            { "type": "image", "url": "data:image/png;base64,iVBORw0KGgo=" },
+            { "type": "image_url", "image_url": { "url": "https://example.com/cat.jpg" } }
+        ]))
+        .unwrap();
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content,
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"],
+            serde_json::json!([
+                { "type": "text", "text": "Compare these" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+                { "type": "image_url", "image_url": { "url": "https://example.com/cat.jpg" } }
+            ])
+        );
+    }
+
+    #[test]
+    fn images_next_to_tool_results_follow_as_user_message() {
+        let provider = OpenAiProvider::new("test-key", None, None);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Parts(vec![
+                    ContentBlock::ToolResult {
+                        tool_use_id: "call_1".to_string(),
+                        content: "screenshot taken".to_string(),
+                        is_error: false,
+                    },
+                    ContentBlock::Image {
+                        url: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+                    },
+                ]),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(json["messages"][0]["role"], "tool");
+        assert_eq!(json["messages"][0]["tool_call_id"], "call_1");
+        assert_eq!(json["messages"][1]["role"], "user");
+        assert_eq!(
+            json["messages"][1]["content"],
+            serde_json::json!([
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } }
+            ])
+        );
+    }
+
     #[test]
     fn serializes_request_correctly() {
         let req = OpenAiRequest {
@@ -790,8 +1092,10 @@ mod tests {
             }],
             max_tokens: Some(1024),
             temperature: None,
+            seed: None,
             tools: None,
             tool_choice: None,
+            response_format: None,
         };
 
         let json = serde_json::to_value(&req).unwrap();
@@ -800,7 +1104,9 @@ mod tests {
         assert_eq!(json["messages"][0]["role"], "user");
         assert_eq!(json["messages"][0]["content"], "Hello");
         assert!(json.get("temperature").is_none());
+        assert!(json.get("seed").is_none());
         assert!(json.get("tools").is_none());
+        assert!(json.get("response_format").is_none());
     }
 
     #[test]
@@ -890,13 +1196,17 @@ mod tests {
                     content: MessagePart::Parts(vec![ContentBlock::ToolResult {
                         tool_use_id: "call_123".to_string(),
                         content: "hi\n".to_string(),
+                        is_error: false,
                     }]),
                 },
             ],
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let openai_req = provider.build_request(&request);
@@ -915,6 +1225,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tool_result_error_flag_is_not_sent() {
+        let provider = OpenAiProvider::new("key", None, None);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Parts(vec![ContentBlock::ToolResult {
+                    tool_use_id: "call_123".to_string(),
+                    content: "error: boom".to_string(),
+                    is_error: true,
+                }]),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        let tool_msg = &json["messages"][0];
+        assert_eq!(tool_msg["role"], "tool");
+        assert_eq!(tool_msg["content"], "error: boom");
+        assert!(tool_msg.get("is_error").is_none());
+    }
+
+    fn document_request() -> LlmRequest {
+        LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Parts(vec![
+                    ContentBlock::Document {
+                        mime: "application/pdf".to_string(),
+                        url: "data:application/pdf;base64,JVBERi0=".to_string(),
+                        text: Some("Invoice 42".to_string()),
+                    },
+                    ContentBlock::Text {
+                        text: "What is the total?".to_string(),
+                    },
+                ]),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        }
+    }
+
+    #[test]
+    fn serializes_pdf_as_file_part_for_openai() {
+        let provider = OpenAiProvider::new("key", None, None);
+        assert!(provider.supports_documents("application/pdf"));
+
+        let json = serde_json::to_value(provider.build_request(&document_request())).unwrap();
+        let content = &json["messages"][0]["content"];
+        assert_eq!(
+            content[0],
+            serde_json::json!({
+                "type": "file",
+                "file": {
+                    "filename": "document.pdf",
+                    "file_data": "data:application/pdf;base64,JVBERi0=",
+                },
+            })
+        );
+        assert_eq!(content[1]["text"], "What is the total?");
+    }
+
+    #[test]
+    fn compatible_endpoints_get_document_text() {
+        let provider = OpenAiProvider::new(
+            "key",
+            None,
+            Some("https://api.groq.com/openai/v1".to_string()),
+        );
+        assert!(!provider.supports_documents("application/pdf"));
+
+        let json = serde_json::to_value(provider.build_request(&document_request())).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"],
+            "[document (application/pdf)]\nInvoice 42\nWhat is the total?"
+        );
+    }
+
     #[test]
     fn endpoint_strips_trailing_slash() {
         let provider =
@@ -925,6 +1326,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn endpoint_does_not_duplicate_v1() {
+        let provider = OpenAiProvider::new(
+            "key",
+            None,
+            Some("https://openrouter.ai/api/v1".to_string()),
+        );
+        assert_eq!(
+            provider.endpoint(),
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+
+        let provider =
+            OpenAiProvider::new("key", None, Some("https://api.ai71.ai/v1/".to_string()));
+        assert_eq!(
+            provider.endpoint(),
+            "https://api.ai71.ai/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn endpoint_keeps_versioned_prefix() {
+        let provider = OpenAiProvider::new(
+            "key",
+            None,
+            Some("https://generativelanguage.googleapis.com/v1beta/openai/".to_string()),
+        );
+        assert_eq!(
+            provider.endpoint(),
+            "https://generativelanguage.googleapis.com/v1beta/openai/chat/completions"
+        );
+    }
+
+    #[test]
+    fn endpoint_appends_v1_after_path_prefix() {
+        let provider = OpenAiProvider::new(
+            "key",
+            None,
+            Some("https://proxy.example.com/api/openai".to_string()),
+        );
+        assert_eq!(
+            provider.endpoint(),
+            "https://proxy.example.com/api/openai/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn endpoint_respects_full_completions_url() {
+        let provider = OpenAiProvider::new(
+            "key",
+            None,
+            Some("https://litellm.local/openai/deployments/chat/completions".to_string()),
+        );
+        assert_eq!(
+            provider.endpoint(),
+            "https://litellm.local/openai/deployments/chat/completions"
+        );
+    }
+
+    #[test]
+    fn endpoint_ignores_version_like_hostnames() {
+        let provider =
+            OpenAiProvider::new("key", None, Some("http://v1.example.com:8000".to_string()));
+        assert_eq!(
+            provider.endpoint(),
+            "http://v1.example.com:8000/v1/chat/completions"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_extra_headers() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/chat/completions"))
+            .and(header("authorization", "Bearer or-key"))
+            .and(header("HTTP-Referer", "https://example.com"))
+            .and(header("X-Title", "OpenCrust"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{
+                    "message": {"role": "assistant", "content": "hi"},
+                    "finish_reason": "stop"
+                }],
+                "model": "openrouter/auto"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new(
+            "or-key",
+            Some("openrouter/auto".to_string()),
+            Some(format!("{}/api/v1", server.uri())),
+        )
+        .with_name("openrouter")
+        .with_header("HTTP-Referer", "https://example.com")
+        .with_header("X-Title", "OpenCrust");
+
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("hello".to_string()),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+        let response = provider.complete(&request).await.unwrap();
+        assert_eq!(response.model, "openrouter/auto");
+        assert_eq!(provider.provider_id(), "openrouter");
+    }
+
+    #[tokio::test]
+    async fn azure_deployment_uses_api_key_header_and_versioned_url() {
+        use wiremock::matchers::{header, method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt4o-prod/chat/completions"))
+            .and(query_param("api-version", AZURE_DEFAULT_API_VERSION))
+            .and(header("api-key", "az-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{
+                    "message": {"role": "assistant", "content": "hi"},
+                    "finish_reason": "stop"
+                }],
+                "model": "gpt-4o"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new("az-key", None, Some(format!("{}/", server.uri())))
+            .with_azure_deployment("gpt4o-prod", None);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("hello".to_string()),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+        let response = provider.complete(&request).await.unwrap();
+        assert_eq!(response.model, "gpt-4o");
+        assert!(
+            provider
+                .post()
+                .build()
+                .unwrap()
+                .headers()
+                .get("authorization")
+                .is_none()
+        );
+
+        let pinned = OpenAiProvider::new(
+            "az-key",
+            None,
+            Some("https://res.openai.azure.com/openai".to_string()),
+        )
+        .with_azure_deployment("chat", Some("2025-01-01-preview".to_string()));
+        assert_eq!(
+            pinned.endpoint(),
+            "https://res.openai.azure.com/openai/deployments/chat/chat/completions?api-version=2025-01-01-preview"
+        );
+    }
+
     #[test]
     fn parses_text_stream_chunk() {
         let data = r#"{"id":"chatcmpl-abc","object":"chat.completion.chunk","model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Hello"},"finish_reason":null}]}"#;
@@ -981,6 +1562,7 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![ToolDefinition {
                 name: "bash".to_string(),
                 description: "Run a command".to_string(),
@@ -989,6 +1571,8 @@ mod tests {
                     "properties": {"command": {"type": "string"}}
                 }),
             }],
+            response_format: None,
+            thinking: None,
         };
 
         let openai_req = provider.build_request(&request);
@@ -1008,11 +1592,202 @@ mod tests {
             system: None,
             max_tokens: None,
             temperature: None,
+            seed: None,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
 
         let openai_req = provider.build_request(&request);
         assert!(openai_req.tools.is_none());
         assert!(openai_req.tool_choice.is_none());
     }
+
+    #[test]
+    fn request_omits_tools_when_unsupported() {
+        let provider = OpenAiProvider::new("test-key", None, None).with_supports_tools(false);
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![ToolDefinition {
+                name: "bash".to_string(),
+                description: "Run a command".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+            }],
+            response_format: None,
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert!(json.get("tools").is_none());
+        assert!(json.get("tool_choice").is_none());
+        assert!(!provider.supports_tools());
+    }
+
+    #[test]
+    fn request_includes_seed_when_set() {
+        let provider = OpenAiProvider::new("test-key", None, None);
+        let mut request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: Some(42),
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(json["seed"], 42);
+
+        request.seed = None;
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert!(json.get("seed").is_none());
+    }
+
+    #[test]
+    fn request_maps_json_schema_response_format() {
+        let provider = OpenAiProvider::new("test-key", None, None);
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"],
+        });
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: Some(ResponseFormat::json_schema("place", schema.clone())),
+            thinking: None,
+        };
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(json["response_format"]["type"], "json_schema");
+        assert_eq!(json["response_format"]["json_schema"]["name"], "place");
+        assert_eq!(json["response_format"]["json_schema"]["schema"], schema);
+    }
+
+    #[test]
+    fn sse_event_data_skips_comments_and_fields() {
+        assert_eq!(sse_event_data(": keep-alive"), None);
+        assert_eq!(sse_event_data(""), None);
+        assert_eq!(
+            sse_event_data("event: message\nid: 7\ndata: {\"a\":1}"),
+            Some("{\"a\":1}".to_string())
+        );
+        assert_eq!(sse_event_data("data:[DONE]"), Some("[DONE]".to_string()));
+        assert_eq!(
+            sse_event_data("data: {\"a\":\ndata: 1}"),
+            Some("{\"a\":\n1}".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_ignores_comments_and_keep_alives() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = concat!(
+            ": OPENROUTER PROCESSING\n\n",
+            "\n\n",
+            "event: ping\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\r\n\r\n",
+            ": keep-alive\n",
+            "data:{\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":null}]}\n\n",
+            "retry: 1000\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(body),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new("key", None, Some(server.uri()));
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("hello".to_string()),
+            }],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+        let events: Vec<StreamEvent> = provider
+            .stream_complete(&request)
+            .await
+            .unwrap()
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 4, "{events:?}");
+        assert!(matches!(&events[0], StreamEvent::TextDelta(t) if t == "Hel"));
+        assert!(matches!(&events[1], StreamEvent::TextDelta(t) if t == "lo"));
+        assert!(matches!(
+            &events[2],
+            StreamEvent::MessageDelta { stop_reason: Some(r), .. } if r == "end_turn"
+        ));
+        assert!(matches!(events[3], StreamEvent::MessageStop));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response_carries_retry_after() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "3")
+                    .set_body_string(r#"{"error":{"message":"Rate limit reached"}}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = OpenAiProvider::new("key", None, Some(server.uri()));
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        };
+        match provider.complete(&request).await {
+            Err(Error::RateLimited {
+                retry_after,
+                message,
+            }) => {
+                assert_eq!(retry_after, Some(std::time::Duration::from_secs(3)));
+                assert!(message.contains("status=429"));
+            }
+            other => panic!("expected a rate-limit error, got {other:?}"),
+        }
+    }
 }
@@ -36,6 +36,18 @@ pub trait LlmProvider: Send + Sync {
         Ok(Vec::new())
     }
 
+    /// Whether `ContentBlock::Document` blocks of this MIME type are sent to
+    /// the model natively. When false, the document's extracted text is sent.
+    fn supports_documents(&self, _mime: &str) -> bool {
+        false
+    }
+
+    /// Whether the endpoint accepts tool definitions. When false, the runtime
+    /// sends no tools and returns the model's text answer directly.
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
     /// Check if the provider is available and configured.
     async fn health_check(&self) -> Result<bool>;
 }
@@ -47,7 +59,105 @@ pub struct LlmRequest {
     pub system: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f64>,
+    /// Sampling seed for reproducible outputs. Only sent by providers that
+    /// support it (OpenAI-compatible); others ignore it.
+    #[serde(default)]
+    pub seed: Option<u64>,
     pub tools: Vec<ToolDefinition>,
+    /// Constrain the answer to JSON of a given shape. Honoured natively by
+    /// OpenAI-compatible providers (`response_format`) and Anthropic (a forced
+    /// tool call); others rely on the instruction in the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Token budget for extended thinking before the answer. Only honoured by
+    /// Anthropic; others ignore it. The model's reasoning comes back as
+    /// [`ContentBlock::Thinking`] blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<u32>,
+}
+
+/// Requested shape of a model's answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// A JSON value matching `schema`, a JSON Schema object. `name`
+    /// identifies the schema to the provider (letters, digits, `_` and `-`).
+    JsonSchema {
+        name: String,
+        schema: serde_json::Value,
+    },
+}
+
+impl ResponseFormat {
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self::JsonSchema {
+            name: name.into(),
+            schema,
+        }
+    }
+
+    /// Check `value` against the schema's `type`, `enum`, `required`,
+    /// `properties` and `items` keywords. Other keywords are not checked.
+    pub fn validate(&self, value: &serde_json::Value) -> std::result::Result<(), String> {
+        let Self::JsonSchema { schema, .. } = self;
+        validate_schema(schema, value, "$")
+    }
+}
+
+fn validate_schema(
+    schema: &serde_json::Value,
+    value: &serde_json::Value,
+    path: &str,
+) -> std::result::Result<(), String> {
+    use serde_json::Value;
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let matches = |t: &str| match t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !types.is_empty() && !types.iter().any(|t| matches(t)) {
+            return Err(format!("{path}: expected {}", types.join(" or ")));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum")
+        && !allowed.contains(value)
+    {
+        return Err(format!("{path}: {value} is not one of the allowed values"));
+    }
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(format!("{path}: missing required property '{key}'"));
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                if let Some(field) = object.get(key) {
+                    validate_schema(property, field, &format!("{path}.{key}"))?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(elements), Some(items)) = (value, schema.get("items")) {
+        for (i, element) in elements.iter().enumerate() {
+            validate_schema(items, element, &format!("{path}[{i}]"))?;
+        }
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,8 +187,27 @@ pub enum MessagePart {
 pub enum ContentBlock {
     #[serde(rename = "text")]
     Text { text: String },
-    #[serde(rename = "image")]
-    Image { url: String },
+    /// An image, serialized as `{"type": "image", "url": ...}`. OpenAI-style
+    /// `{"type": "image_url", "image_url": {"url": ...}}` parts deserialize to
+    /// this too, so either shape can be passed to any provider.
+    #[serde(rename = "image", alias = "image_url")]
+    Image {
+        /// `data:` URI with base64 content, or an `http(s)://` URL.
+        #[serde(alias = "image_url", deserialize_with = "deserialize_image_url")]
+        url: String,
+    },
+    /// A file passed to the model as-is, e.g. a PDF. Providers without native
+    /// support for `mime` send `text` instead (see [`document_fallback_text`]).
+    #[serde(rename = "document")]
+    Document {
+        /// MIME type, e.g. `application/pdf`.
+        mime: String,
+        /// `data:` URI with base64 content, or an `https://` URL.
+        url: String,
+        /// Text extracted from the document.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -89,7 +218,49 @@ pub enum ContentBlock {
     ToolResult {
         tool_use_id: String,
         content: String,
+        /// True when the tool failed; `content` then holds the error message.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
     },
+    /// The model's reasoning before its answer (extended thinking). Not part
+    /// of the reply; kept in the assistant message during a tool loop because
+    /// Anthropic requires it back alongside the tool calls.
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        /// Anthropic's signature over the block, checked when it is sent back.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        signature: String,
+    },
+    /// Reasoning Anthropic flagged and returned encrypted. Never shown; sent
+    /// back unchanged with the tool calls like [`ContentBlock::Thinking`].
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+}
+
+/// Accept an image URL as a plain string or as OpenAI's `{"url": ...}` object
+/// (any `detail` hint is dropped).
+fn deserialize_image_url<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ImageUrl {
+        Url(String),
+        Object { url: String },
+    }
+    Ok(match ImageUrl::deserialize(deserializer)? {
+        ImageUrl::Url(url) | ImageUrl::Object { url } => url,
+    })
+}
+
+/// Text sent in place of a document block the provider cannot pass natively.
+pub fn document_fallback_text(mime: &str, text: Option<&str>) -> String {
+    match text.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(text) => format!("[document ({mime})]\n{text}"),
+        None => format!("[document ({mime}): no text could be extracted]"),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -126,6 +297,12 @@ pub enum StreamEvent {
     },
     /// Partial JSON input for a tool use block.
     InputJsonDelta(String),
+    /// A chunk of the model's reasoning (extended thinking).
+    ThinkingDelta(String),
+    /// The signature that closes a thinking block.
+    SignatureDelta(String),
+    /// A whole redacted thinking block, with its encrypted data.
+    RedactedThinking(String),
     /// A content block finished.
     ContentBlockStop { index: usize },
     /// The message is finishing with metadata.
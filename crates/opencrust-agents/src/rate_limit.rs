@@ -0,0 +1,406 @@
+//! Provider rate limits: reading the wait a provider asks for on HTTP 429 and
+//! retrying after it.
+//!
+//! Providers turn a 429 into [`Error::RateLimited`] via [`status_error`], and
+//! the runtime wraps every registered provider in [`RateLimitRetry`], which
+//! sleeps for the requested duration (or an exponential backoff when none was
+//! given) before trying again.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::Stream;
+use opencrust_common::{Error, Result};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use tracing::warn;
+
+use crate::providers::{LlmProvider, LlmRequest, LlmResponse, StreamEvent};
+
+/// Retries after a rate-limit error before giving up.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// First backoff when the provider gives no wait; doubled on each retry.
+pub const DEFAULT_BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait honoured. Longer requested waits fail the call immediately
+/// rather than stalling the conversation.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Values above this in `x-ratelimit-reset` are Unix timestamps, not seconds.
+const EPOCH_THRESHOLD: f64 = 1_000_000_000.0;
+
+/// Build the error for a failed provider response: [`Error::RateLimited`]
+/// carrying the wait from `headers` for 429, [`Error::Agent`] otherwise.
+pub fn status_error(status: StatusCode, headers: &HeaderMap, message: String) -> Error {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        Error::RateLimited {
+            message,
+            retry_after: retry_after(headers),
+        }
+    } else {
+        Error::Agent(message)
+    }
+}
+
+/// How long the provider asked us to wait, from (in order of preference)
+/// `retry-after-ms`, `retry-after` (seconds or HTTP date), `x-ratelimit-reset`
+/// (seconds or Unix timestamp), or the longer of OpenAI's
+/// `x-ratelimit-reset-requests` / `x-ratelimit-reset-tokens` (`"1m30s"`).
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return seconds(ms / 1000.0);
+    }
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return seconds(secs);
+        }
+        if let Ok(at) = chrono::DateTime::parse_from_rfc2822(value) {
+            return until(at.timestamp() as f64);
+        }
+    }
+    if let Some(value) = header("x-ratelimit-reset").and_then(|v| v.parse::<f64>().ok()) {
+        return if value > EPOCH_THRESHOLD {
+            until(value)
+        } else {
+            seconds(value)
+        };
+    }
+    ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .into_iter()
+        .filter_map(|name| header(name).and_then(parse_go_duration))
+        .max()
+}
+
+fn seconds(secs: f64) -> Option<Duration> {
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+fn until(epoch_secs: f64) -> Option<Duration> {
+    let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+    seconds((epoch_secs - now).max(0.0))
+}
+
+/// Parse a Go-style duration such as `"6m0s"`, `"1.5s"` or `"20ms"`.
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[unit_len..];
+    }
+    seconds(total)
+}
+
+/// Wraps a provider and retries calls that fail with [`Error::RateLimited`].
+///
+/// Only the initial request of a stream is retried; errors mid-stream are
+/// passed through.
+pub struct RateLimitRetry {
+    inner: Arc<dyn LlmProvider>,
+    max_retries: u32,
+    base_backoff: Duration,
+    max_wait: Duration,
+}
+
+impl RateLimitRetry {
+    pub fn new(inner: Arc<dyn LlmProvider>) -> Self {
+        Self {
+            inner,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            max_wait: DEFAULT_MAX_WAIT,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// How long to wait before retry number `attempt` (0-based), or `None`
+    /// to give up and return `err`.
+    fn wait_for(&self, err: &Error, attempt: u32) -> Option<Duration> {
+        let Error::RateLimited { retry_after, .. } = err else {
+            return None;
+        };
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let wait = retry_after.unwrap_or_else(|| self.base_backoff * 2u32.saturating_pow(attempt));
+        if wait > self.max_wait {
+            warn!(
+                "{}: rate limited, provider asked to wait {}s (limit {}s); not retrying",
+                self.inner.provider_id(),
+                wait.as_secs(),
+                self.max_wait.as_secs()
+            );
+            return None;
+        }
+        warn!(
+            "{}: rate limited, retrying in {:.1}s (attempt {}/{})",
+            self.inner.provider_id(),
+            wait.as_secs_f64(),
+            attempt + 1,
+            self.max_retries
+        );
+        Some(wait)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RateLimitRetry {
+    fn provider_id(&self) -> &str {
+        self.inner.provider_id()
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> Result<LlmResponse> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.complete(request).await {
+                Err(e) => match self.wait_for(&e, attempt) {
+                    Some(wait) => tokio::time::sleep(wait).await,
+                    None => return Err(e),
+                },
+                ok => return ok,
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn stream_complete(
+        &self,
+        request: &LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.stream_complete(request).await {
+                Err(e) => match self.wait_for(&e, attempt) {
+                    Some(wait) => tokio::time::sleep(wait).await,
+                    None => return Err(e),
+                },
+                ok => return ok,
+            }
+            attempt += 1;
+        }
+    }
+
+    fn configured_model(&self) -> Option<&str> {
+        self.inner.configured_model()
+    }
+
+    async fn available_models(&self) -> Result<Vec<String>> {
+        self.inner.available_models().await
+    }
+
+    fn supports_documents(&self, mime: &str) -> bool {
+        self.inner.supports_documents(mime)
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ContentBlock;
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn parses_retry_after_headers() {
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "3")])),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_after(&headers(&[
+                ("retry-after-ms", "1500"),
+                ("retry-after", "9")
+            ])),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            retry_after(&headers(&[("x-ratelimit-reset", "12")])),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            retry_after(&headers(&[
+                ("x-ratelimit-reset-requests", "1m30s"),
+                ("x-ratelimit-reset-tokens", "250ms"),
+            ])),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")])), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn parses_http_date_and_epoch_resets() {
+        let at = chrono::Utc::now() + chrono::Duration::seconds(30);
+        let date = at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let wait = retry_after(&headers(&[("retry-after", &date)])).unwrap();
+        assert!((28..=30).contains(&wait.as_secs()), "{wait:?}");
+
+        let epoch = (at.timestamp()).to_string();
+        let wait = retry_after(&headers(&[("x-ratelimit-reset", &epoch)])).unwrap();
+        assert!((28..=30).contains(&wait.as_secs()), "{wait:?}");
+    }
+
+    #[test]
+    fn only_429_becomes_rate_limited() {
+        let h = headers(&[("retry-after", "3")]);
+        assert!(matches!(
+            status_error(StatusCode::TOO_MANY_REQUESTS, &h, "slow down".into()),
+            Error::RateLimited { retry_after: Some(d), .. } if d == Duration::from_secs(3)
+        ));
+        assert!(matches!(
+            status_error(StatusCode::INTERNAL_SERVER_ERROR, &h, "boom".into()),
+            Error::Agent(_)
+        ));
+    }
+
+    /// Fails with the queued errors, then succeeds.
+    struct FlakyProvider {
+        errors: Mutex<Vec<Error>>,
+        calls: Mutex<u32>,
+    }
+
+    impl FlakyProvider {
+        fn new(errors: Vec<Error>) -> Arc<Self> {
+            Arc::new(Self {
+                errors: Mutex::new(errors),
+                calls: Mutex::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        fn provider_id(&self) -> &str {
+            "flaky"
+        }
+
+        async fn complete(&self, _request: &LlmRequest) -> Result<LlmResponse> {
+            *self.calls.lock().unwrap() += 1;
+            let mut errors = self.errors.lock().unwrap();
+            if !errors.is_empty() {
+                return Err(errors.remove(0));
+            }
+            Ok(LlmResponse {
+                content: vec![ContentBlock::Text {
+                    text: "ok".to_string(),
+                }],
+                model: "flaky-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn rate_limited(retry_after: Option<Duration>) -> Error {
+        Error::RateLimited {
+            message: "429".to_string(),
+            retry_after,
+        }
+    }
+
+    fn request() -> LlmRequest {
+        LlmRequest {
+            model: String::new(),
+            messages: vec![],
+            system: None,
+            max_tokens: None,
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_retry_after_before_retrying() {
+        let inner = FlakyProvider::new(vec![rate_limited(Some(Duration::from_secs(3)))]);
+        let provider = RateLimitRetry::new(inner.clone());
+
+        let start = tokio::time::Instant::now();
+        provider.complete(&request()).await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+        assert_eq!(*inner.calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_exponentially_without_retry_after() {
+        let inner = FlakyProvider::new(vec![rate_limited(None), rate_limited(None)]);
+        let provider = RateLimitRetry::new(inner.clone());
+
+        let start = tokio::time::Instant::now();
+        provider.complete(&request()).await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_secs(1 + 2));
+        assert_eq!(*inner.calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_on_long_waits_and_other_errors() {
+        let inner = FlakyProvider::new(vec![rate_limited(Some(Duration::from_secs(600)))]);
+        let provider = RateLimitRetry::new(inner.clone());
+        assert!(matches!(
+            provider.complete(&request()).await,
+            Err(Error::RateLimited { .. })
+        ));
+        assert_eq!(*inner.calls.lock().unwrap(), 1);
+
+        let inner = FlakyProvider::new(vec![Error::Agent("bad request".into())]);
+        let provider = RateLimitRetry::new(inner.clone());
+        assert!(provider.complete(&request()).await.is_err());
+        assert_eq!(*inner.calls.lock().unwrap(), 1);
+
+        let inner = FlakyProvider::new((0..5).map(|_| rate_limited(None)).collect());
+        let provider = RateLimitRetry::new(inner.clone()).with_max_retries(2);
+        assert!(provider.complete(&request()).await.is_err());
+        assert_eq!(*inner.calls.lock().unwrap(), 3);
+    }
+}
@@ -7,20 +7,22 @@ use dashmap::DashMap;
 
 use futures::StreamExt;
 use futures::future::join_all;
-use opencrust_common::{Error, Result};
+use opencrust_common::{Error, MediaAttachment, Result};
 use opencrust_db::{
-    DocumentStore, MemoryEntry, MemoryProvider, MemoryRole, NewMemoryEntry, RecallQuery,
-    TrajectoryStore, TrajectorySummary,
+    DocumentStore, MemoryAttachment, MemoryEntry, MemoryProvider, MemoryRole, NewMemoryEntry,
+    RecallQuery, TrajectoryStore, TrajectorySummary,
 };
 use tokio::sync::mpsc;
 use tracing::{info, instrument, warn};
 
 use crate::embeddings::EmbeddingProvider;
 use crate::providers::{
-    ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, MessagePart, StreamEvent,
-    ToolDefinition,
+    ChatMessage, ChatRole, ContentBlock, LlmProvider, LlmRequest, LlmResponse, MessagePart,
+    ResponseFormat, StreamEvent, ToolDefinition, Usage,
 };
+use crate::tools::ask_user_tool::{ASK_USER_TOOL, ask_user_options};
 use crate::tools::{Tool, ToolContext, ToolOutput};
+use crate::transcript::TranscriptWriter;
 
 /// Maximum number of tool-use round-trips before the loop is forcibly stopped.
 const MAX_TOOL_ITERATIONS: usize = 10;
@@ -40,6 +42,12 @@ const TRAJECTORY_SUGGEST_COOLDOWN_SECS: u64 = 600;
 const SKILL_PRUNE_UNUSED_DAYS: u64 = 30;
 /// Maximum sessions compressed per `compress_old_trajectories` call to bound LLM cost.
 const COMPRESSION_BATCH_SIZE: usize = 20;
+/// Maximum characters of tool input/output kept per entry in a `TurnTrace`.
+const TRACE_SNIPPET_CHARS: usize = 500;
+/// Output token cap used when neither the provider nor the agent config sets one.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+/// Appended to a streamed reply when the provider stream fails after text was sent.
+const STREAM_INTERRUPTED_NOTE: &str = "[connection interrupted]";
 
 /// Default base system prompt when none is configured.
 const DEFAULT_BASE_SYSTEM_PROMPT: &str = "\
@@ -69,7 +77,20 @@ pub struct AgentRuntime {
     /// When non-empty, `skills_content` is unused and retrieval is semantic.
     skills_index: RwLock<Vec<IndexedSkill>>,
     skill_recall_limit: usize,
+    /// Directory scanned by `reload_skills`.
+    skills_source_dir: Option<PathBuf>,
+    /// Prompt block of the most recently indexed skills. `reload_skills` skips
+    /// re-indexing (and re-embedding) when a rescan produces the same block.
+    skills_fingerprint: Mutex<Option<String>>,
+    /// Renders template skills at injection time.
+    skill_template_engine: Arc<dyn opencrust_skills::TemplateEngine>,
     max_tokens: Option<u32>,
+    /// Per-provider output token caps, keyed by provider id. Override `max_tokens`.
+    provider_max_tokens: HashMap<String, u32>,
+    /// Sampling seed sent with every request, for reproducible outputs.
+    seed: Option<u64>,
+    /// Model aliases sessions can switch to, keyed by alias name.
+    model_aliases: HashMap<String, SessionModel>,
     max_context_tokens: Option<usize>,
     recall_limit: usize,
     summarization_enabled: bool,
@@ -85,10 +106,14 @@ pub struct AgentRuntime {
     session_dna_override: DashMap<String, String>,
     /// Per-session skills content override. When set, replaces global skill retrieval for that session.
     session_skills_override: DashMap<String, String>,
+    /// Per-session provider/model selection. Overrides the channel's provider and model.
+    session_models: DashMap<String, SessionModel>,
     /// When true, accumulate debug info (tool calls) per session.
     debug: bool,
     /// Debug info accumulated during message processing, keyed by session_id.
     debug_accumulator: Mutex<HashMap<String, Vec<String>>>,
+    /// Structured traces for sessions that opted in via `begin_turn_trace`.
+    turn_traces: DashMap<String, TurnTrace>,
     /// Optional trajectory store. When set, every tool call and turn end is persisted.
     trajectory_store: Option<Arc<TrajectoryStore>>,
     /// Per-session turn counter used to order trajectory events.
@@ -96,6 +121,10 @@ pub struct AgentRuntime {
     /// Timestamp of the last trajectory auto-suggest check. Used to rate-limit
     /// the cross-session pattern query (at most once per 10 minutes).
     trajectory_last_suggest_at: Mutex<Option<std::time::Instant>>,
+    /// Optional debug transcript writer. When set, every request sent to the
+    /// provider during a conversation turn and its response are appended to
+    /// a per-session JSONL file.
+    transcripts: Option<TranscriptWriter>,
     /// Path to the document store DB for auto-RAG injection.
     doc_db_path: Option<PathBuf>,
     /// Cached document store opened once at startup for auto-RAG.
@@ -103,6 +132,42 @@ pub struct AgentRuntime {
     /// True when at least one document has been ingested into the store.
     /// Guards the embedding call in auto_rag_context — skip when false.
     has_documents: AtomicBool,
+    /// When true, destructive tools return a dry-run preview until the user confirms.
+    confirm_destructive: bool,
+    /// Destructive tool calls awaiting (or holding) user confirmation, keyed by session_id.
+    pending_confirmations: DashMap<String, Vec<PendingToolCall>>,
+    /// Questions asked through `ask_user`, keyed by session_id. The session's
+    /// next message is passed to the model as the answer.
+    pending_questions: DashMap<String, PendingQuestion>,
+    /// Structured tool results of the current turn, keyed by session_id, as
+    /// `(tool name, data)`. See [`ToolOutput::data`].
+    tool_results: DashMap<String, Vec<(String, serde_json::Value)>>,
+    /// Media produced by tools in the current turn, keyed by session_id.
+    /// See [`ToolOutput::attachments`].
+    tool_attachments: DashMap<String, Vec<MediaAttachment>>,
+    /// Sessions whose caller delivers tool media, registered with
+    /// [`Self::deliver_tool_attachments`]. Media for other sessions is dropped.
+    attachment_sessions: DashMap<String, ()>,
+    /// Extended thinking budget sent with each tool-loop request. See
+    /// [`LlmRequest::thinking`].
+    thinking_budget: Option<u32>,
+    /// When true, the model's reasoning is kept per session for
+    /// [`Self::take_reasoning`].
+    show_reasoning: bool,
+    /// Reasoning produced in the current turn, keyed by session_id.
+    session_reasoning: DashMap<String, String>,
+    /// Root directory for per-session tool workspaces. Each session gets
+    /// `{workspace_root}/{session_id}` as its `ToolContext::workspace_dir`.
+    workspace_root: Option<PathBuf>,
+    /// Per-language prompts keyed by ISO 639-3 code. `Some` enables language
+    /// detection on inbound text; `None` leaves the system prompt untouched.
+    language_prompts: Option<HashMap<String, String>>,
+    /// Per-tool status overrides. `Some` streams a status line to the delta
+    /// channel whenever a tool starts; `None` keeps tool runs silent.
+    tool_status_messages: Option<HashMap<String, String>>,
+    /// Directory for raw files shared by users, stored by content hash and
+    /// referenced from memory entries. `None` disables attachment memory.
+    attachments_dir: Option<PathBuf>,
 }
 
 /// Per-session tool configuration set before processing a message.
@@ -113,6 +178,47 @@ struct SessionToolConfig {
     budget: Option<u32>,
 }
 
+/// Provider and model a session runs on. `None` fields fall back to the
+/// channel's configuration, then to the runtime default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionModel {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Tool calls and tool-loop iterations recorded for a single turn.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct TurnTrace {
+    /// Number of LLM round-trips made by the tool loop.
+    pub iterations: u32,
+    pub tool_calls: Vec<ToolCallTrace>,
+}
+
+/// One tool invocation within a `TurnTrace`. Input and output are truncated.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolCallTrace {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+    pub is_error: bool,
+}
+
+/// A question from `ask_user` awaiting the user's answer.
+#[derive(Debug, Clone)]
+struct PendingQuestion {
+    question: String,
+    options: Vec<String>,
+}
+
+/// A destructive tool call that was previewed instead of executed.
+#[derive(Debug, Clone)]
+struct PendingToolCall {
+    name: String,
+    input: serde_json::Value,
+    /// Set once the user replies with a confirmation; the next identical call executes.
+    approved: bool,
+}
+
 /// Bundles the LLM call parameters needed by `skill_nudge_followup`.
 struct NudgeContext<'a> {
     provider: &'a dyn LlmProvider,
@@ -138,23 +244,45 @@ impl AgentRuntime {
             skills_content: RwLock::new(None),
             skills_index: RwLock::new(Vec::new()),
             skill_recall_limit: DEFAULT_SKILL_RECALL_LIMIT,
+            skills_source_dir: None,
+            skills_fingerprint: Mutex::new(None),
+            skill_template_engine: Arc::new(opencrust_skills::SimpleTemplateEngine),
             max_tokens: None,
+            provider_max_tokens: HashMap::new(),
+            seed: None,
+            model_aliases: HashMap::new(),
             max_context_tokens: None,
             recall_limit: 10,
             doc_db_path: None,
             doc_store: None,
             has_documents: AtomicBool::new(false),
+            workspace_root: None,
+            language_prompts: None,
+            tool_status_messages: None,
+            attachments_dir: None,
+            confirm_destructive: false,
+            pending_confirmations: DashMap::new(),
+            pending_questions: DashMap::new(),
+            tool_results: DashMap::new(),
+            tool_attachments: DashMap::new(),
+            attachment_sessions: DashMap::new(),
+            thinking_budget: None,
+            show_reasoning: false,
+            session_reasoning: DashMap::new(),
             summarization_enabled: true,
             usage_accumulator: Mutex::new(HashMap::new()),
             session_tool_config: DashMap::new(),
             session_user_name: DashMap::new(),
             session_dna_override: DashMap::new(),
             session_skills_override: DashMap::new(),
+            session_models: DashMap::new(),
             debug: false,
             debug_accumulator: Mutex::new(HashMap::new()),
+            turn_traces: DashMap::new(),
             trajectory_store: None,
             session_turn_index: DashMap::new(),
             trajectory_last_suggest_at: Mutex::new(None),
+            transcripts: None,
         }
     }
 
@@ -206,6 +334,33 @@ impl AgentRuntime {
         self.skill_recall_limit = limit;
     }
 
+    /// Replace the engine that renders `template: true` skills.
+    pub fn set_skill_template_engine(&mut self, engine: Arc<dyn opencrust_skills::TemplateEngine>) {
+        self.skill_template_engine = engine;
+    }
+
+    /// Set the directory that `reload_skills` scans for skill definitions.
+    pub fn set_skills_dir(&mut self, dir: PathBuf) {
+        self.skills_source_dir = Some(dir);
+    }
+
+    /// Re-scan the skills directory and re-index when skills were added, changed
+    /// or removed. Parsed skills are cached, so an unchanged directory costs one
+    /// scan and no embedding calls. Returns the number of skills found.
+    pub async fn reload_skills(&self) -> Result<usize> {
+        let Some(dir) = self.configured_skills_dir() else {
+            return Ok(0);
+        };
+        let skills = opencrust_skills::SkillScanner::new(&dir).discover()?;
+        let count = skills.len();
+        let unchanged = self.skills_fingerprint.lock().unwrap().as_deref()
+            == Some(skill_prompt_block(&skills).as_str());
+        if !unchanged {
+            self.index_skills(skills).await;
+        }
+        Ok(count)
+    }
+
     /// Index skills for semantic retrieval. When an embedding provider is configured and
     /// the skill count exceeds `skill_recall_limit`, each skill is embedded and stored in
     /// `skills_index` so only the most relevant ones are injected per turn.
@@ -214,6 +369,7 @@ impl AgentRuntime {
     /// - no embedding provider is configured, or
     /// - skill count ≤ `skill_recall_limit` (embedding call would be wasted).
     pub async fn index_skills(&self, skills: Vec<opencrust_skills::SkillDefinition>) {
+        *self.skills_fingerprint.lock().unwrap() = Some(skill_prompt_block(&skills));
         // Clear both stores first.
         *self.skills_index.write().unwrap() = Vec::new();
 
@@ -317,6 +473,40 @@ impl AgentRuntime {
         self.max_tokens = Some(max_tokens);
     }
 
+    /// Cap output tokens for requests sent to `provider_id`, taking precedence
+    /// over the global `set_max_tokens` value.
+    pub fn set_provider_max_tokens(&mut self, provider_id: impl Into<String>, max_tokens: u32) {
+        self.provider_max_tokens
+            .insert(provider_id.into(), max_tokens);
+    }
+
+    /// Resolve the output token cap for a request to `provider_id`: an explicit
+    /// override (e.g. a named agent's `max_tokens`), then the provider's
+    /// configured value, then the global value, then [`DEFAULT_MAX_TOKENS`].
+    pub fn max_tokens_for(&self, provider_id: &str, override_tokens: Option<u32>) -> u32 {
+        override_tokens
+            .or_else(|| self.provider_max_tokens.get(provider_id).copied())
+            .or(self.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+
+    /// Send `seed` with every request so providers that support it (OpenAI
+    /// `seed`) return reproducible outputs.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Register a model alias that sessions can switch to with
+    /// [`switch_session_model`](Self::switch_session_model).
+    pub fn set_model_alias(&mut self, alias: impl Into<String>, target: SessionModel) {
+        self.model_aliases.insert(alias.into(), target);
+    }
+
+    /// Configured model aliases, keyed by alias name.
+    pub fn model_aliases(&self) -> &HashMap<String, SessionModel> {
+        &self.model_aliases
+    }
+
     pub fn set_max_context_tokens(&mut self, max_context_tokens: usize) {
         self.max_context_tokens = Some(max_context_tokens);
     }
@@ -329,6 +519,43 @@ impl AgentRuntime {
         self.trajectory_store = Some(store);
     }
 
+    /// Write LLM request/response transcripts to `{dir}/{session_id}.jsonl`.
+    pub fn set_transcripts_dir(&mut self, dir: PathBuf) {
+        self.transcripts = Some(TranscriptWriter::new(dir));
+    }
+
+    /// Call `provider.complete`, recording the round-trip in the session's
+    /// transcript when transcripts are enabled.
+    async fn complete_for_session(
+        &self,
+        session_id: &str,
+        provider: &dyn LlmProvider,
+        request: &LlmRequest,
+    ) -> Result<LlmResponse> {
+        let result = provider.complete(request).await;
+        self.record_transcript(session_id, provider, request, result.as_ref());
+        if let Ok(response) = &result {
+            for block in &response.content {
+                if let ContentBlock::Thinking { thinking, .. } = block {
+                    self.record_reasoning(session_id, thinking);
+                }
+            }
+        }
+        result
+    }
+
+    fn record_transcript(
+        &self,
+        session_id: &str,
+        provider: &dyn LlmProvider,
+        request: &LlmRequest,
+        response: std::result::Result<&LlmResponse, &Error>,
+    ) {
+        if let Some(transcripts) = &self.transcripts {
+            transcripts.record(session_id, provider.provider_id(), request, response);
+        }
+    }
+
     /// Analyse stored trajectories and return skill suggestions for tool sequences
     /// that have been repeated at least `min_occurrences` times.
     ///
@@ -527,7 +754,10 @@ impl AgentRuntime {
                 system: None,
                 max_tokens: Some(256),
                 temperature: None,
+                seed: self.seed,
                 tools: vec![],
+                response_format: None,
+                thinking: None,
             };
             let response = match provider.complete(&request).await {
                 Ok(r) => r,
@@ -580,6 +810,90 @@ impl AgentRuntime {
         self.usage_accumulator.lock().unwrap().remove(session_id)
     }
 
+    /// Drain the structured results of tools run for a session since the last
+    /// call, as `(tool name, data)` in the order the tools ran.
+    pub fn take_tool_results(&self, session_id: &str) -> Vec<(String, serde_json::Value)> {
+        self.tool_results
+            .remove(session_id)
+            .map(|(_, results)| results)
+            .unwrap_or_default()
+    }
+
+    /// Retain only tool results whose session IDs satisfy the predicate.
+    /// Callers that never take the results rely on this to free them.
+    pub fn retain_tool_results<F>(&self, f: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.tool_results.retain(|id, _| f(id));
+    }
+
+    /// Keep media produced by tools for `session_id` until
+    /// [`Self::take_tool_attachments`] is called. Only callers that send the
+    /// media to the user should register; for every other session tools are
+    /// told their media cannot be delivered.
+    pub fn deliver_tool_attachments(&self, session_id: &str) {
+        self.attachment_sessions.insert(session_id.to_string(), ());
+    }
+
+    /// Drain the media produced by tools run for a session since the last
+    /// call, in the order the tools ran.
+    pub fn take_tool_attachments(&self, session_id: &str) -> Vec<MediaAttachment> {
+        self.tool_attachments
+            .remove(session_id)
+            .map(|(_, attachments)| attachments)
+            .unwrap_or_default()
+    }
+
+    /// Retain only tool media and delivery registrations whose session IDs
+    /// satisfy the predicate.
+    pub fn retain_tool_attachments<F>(&self, f: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.tool_attachments.retain(|id, _| f(id));
+        self.attachment_sessions.retain(|id, _| f(id));
+    }
+
+    /// Let the model think for up to `budget_tokens` before answering, on
+    /// providers that support it. With `show_reasoning`, the reasoning is
+    /// kept for [`Self::take_reasoning`].
+    pub fn set_thinking(&mut self, budget_tokens: u32, show_reasoning: bool) {
+        self.thinking_budget = Some(budget_tokens);
+        self.show_reasoning = show_reasoning;
+    }
+
+    /// Drain the reasoning the model produced for a session since the last
+    /// call. `None` unless reasoning display is enabled and the model thought.
+    pub fn take_reasoning(&self, session_id: &str) -> Option<String> {
+        self.session_reasoning
+            .remove(session_id)
+            .map(|(_, reasoning)| reasoning)
+    }
+
+    /// Retain only reasoning whose session IDs satisfy the predicate.
+    pub fn retain_session_reasoning<F>(&self, f: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.session_reasoning.retain(|id, _| f(id));
+    }
+
+    fn record_reasoning(&self, session_id: &str, thinking: &str) {
+        let thinking = thinking.trim();
+        if !self.show_reasoning || thinking.is_empty() {
+            return;
+        }
+        let mut reasoning = self
+            .session_reasoning
+            .entry(session_id.to_string())
+            .or_default();
+        if !reasoning.is_empty() {
+            reasoning.push_str("\n\n");
+        }
+        reasoning.push_str(thinking);
+    }
+
     /// Set the tool configuration for a session before processing a message.
     /// `allowed_tools = None` means all tools are permitted.
     /// `budget = None` means no per-session call-count cap.
@@ -640,6 +954,69 @@ impl AgentRuntime {
         self.session_user_name.retain(|id, _| f(id));
     }
 
+    /// Retain only `ask_user` questions whose session IDs satisfy the predicate.
+    pub fn retain_pending_questions<F>(&self, f: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.pending_questions.retain(|id, _| f(id));
+    }
+
+    /// The question the agent is waiting on for this session, if any.
+    pub fn pending_question(&self, session_id: &str) -> Option<String> {
+        self.pending_questions
+            .get(session_id)
+            .map(|q| q.question.clone())
+    }
+
+    /// Suggested answers for the pending question of this session (empty when
+    /// there is no question or it came without options).
+    pub fn pending_question_options(&self, session_id: &str) -> Vec<String> {
+        self.pending_questions
+            .get(session_id)
+            .map(|q| q.options.clone())
+            .unwrap_or_default()
+    }
+
+    /// Park `question` as awaiting an answer and close the turn with it as the reply.
+    fn end_turn_with_question(
+        &self,
+        session_id: &str,
+        turn_index: u32,
+        question: String,
+        options: Vec<String>,
+    ) -> String {
+        self.pending_questions.insert(
+            session_id.to_string(),
+            PendingQuestion {
+                question: question.clone(),
+                options,
+            },
+        );
+        self.traj_log_turn_end(session_id, turn_index, &question, 0);
+        question
+    }
+
+    /// When the agent is waiting on an `ask_user` answer, label `content` as
+    /// that answer so the model resumes the task it paused.
+    fn resume_after_question(&self, session_id: &str, content: MessagePart) -> MessagePart {
+        let Some((_, PendingQuestion { question, .. })) = self.pending_questions.remove(session_id)
+        else {
+            return content;
+        };
+        let preamble = format!(
+            "[You paused your task to ask the user: \"{question}\". Their reply below is the \
+             answer. Continue the task from where you stopped.]"
+        );
+        match content {
+            MessagePart::Text(text) => MessagePart::Text(format!("{preamble}\n\n{text}")),
+            MessagePart::Parts(mut blocks) => {
+                blocks.insert(0, ContentBlock::Text { text: preamble });
+                MessagePart::Parts(blocks)
+            }
+        }
+    }
+
     /// Retain only DNA overrides whose session IDs satisfy the predicate.
     pub fn retain_session_dna_overrides<F>(&self, f: F)
     where
@@ -656,6 +1033,14 @@ impl AgentRuntime {
         self.session_skills_override.retain(|id, _| f(id));
     }
 
+    /// Retain only model selections whose session IDs satisfy the predicate.
+    pub fn retain_session_models<F>(&self, f: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.session_models.retain(|id, _| f(id));
+    }
+
     /// Returns `true` if a DNA override is stored for the session.
     /// Intended for use in tests and diagnostics.
     pub fn has_session_dna_override(&self, session_id: &str) -> bool {
@@ -686,6 +1071,59 @@ impl AgentRuntime {
         }
     }
 
+    /// Switch a session to the model registered under `alias`. Returns the
+    /// selected target, or an error naming the known aliases.
+    pub fn switch_session_model(&self, session_id: &str, alias: &str) -> Result<SessionModel> {
+        let target = self.model_aliases.get(alias).cloned().ok_or_else(|| {
+            let mut known: Vec<&str> = self.model_aliases.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            Error::Agent(format!(
+                "unknown model alias '{alias}' (available: {})",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+        })?;
+        self.session_models
+            .insert(session_id.to_string(), target.clone());
+        Ok(target)
+    }
+
+    /// The model selection for a session, if one was made with `/model`.
+    pub fn session_model(&self, session_id: &str) -> Option<SessionModel> {
+        self.session_models.get(session_id).map(|v| v.clone())
+    }
+
+    /// Drop a session's model selection so it falls back to the channel default.
+    pub fn clear_session_model(&self, session_id: &str) {
+        self.session_models.remove(session_id);
+    }
+
+    /// Apply the session's model selection on top of the caller's provider and
+    /// model. A selection naming a provider replaces both; a bare model keeps
+    /// the caller's provider.
+    fn session_provider_and_model(
+        &self,
+        session_id: &str,
+        provider_id: Option<&str>,
+        model_override: Option<&str>,
+    ) -> (Option<String>, Option<String>) {
+        let owned = |v: Option<&str>| v.map(str::to_string);
+        match self.session_model(session_id) {
+            Some(SessionModel {
+                provider: Some(provider),
+                model,
+            }) => (Some(provider), model),
+            Some(SessionModel {
+                provider: None,
+                model: Some(model),
+            }) => (owned(provider_id), Some(model)),
+            _ => (owned(provider_id), owned(model_override)),
+        }
+    }
+
     /// Override the skills content for a specific session (per-agent skill set).
     /// Pass `None` to clear the override and fall back to global skill retrieval.
     pub fn set_session_skills_override(&self, session_id: &str, content: Option<String>) {
@@ -717,6 +1155,175 @@ impl AgentRuntime {
         self.relevant_skills_content(user_text).await
     }
 
+    /// Render template skills in `block` with this turn's context: `user_name`,
+    /// `date` and recalled `memories`. On a template error the block is
+    /// injected unrendered.
+    fn render_skills_block(
+        &self,
+        block: Option<String>,
+        session_id: &str,
+        memory_context: Option<&str>,
+    ) -> Option<String> {
+        let block = block?;
+        let mut ctx = opencrust_skills::TemplateContext::new();
+        ctx.set("date", chrono::Local::now().format("%Y-%m-%d").to_string());
+        if let Some(name) = self.session_user_name(session_id) {
+            ctx.set("user_name", name);
+        }
+        if let Some(memories) = memory_context {
+            ctx.set("memories", memories);
+        }
+        match self.skill_template_engine.render(&block, &ctx) {
+            Ok(rendered) => Some(rendered),
+            Err(e) => {
+                warn!("skills: template rendering failed, injecting unrendered: {e}");
+                Some(block)
+            }
+        }
+    }
+
+    /// Enable language detection on inbound text. Each turn's system prompt gets
+    /// the configured prompt for the detected language, or a generic
+    /// "respond in {lang}" instruction when none is configured.
+    pub fn set_language_detection(&mut self, prompts: HashMap<String, String>) {
+        self.language_prompts = Some(prompts);
+    }
+
+    /// Base prompt for a turn, with the language instruction for `user_text`
+    /// appended when language detection is enabled.
+    fn base_prompt_for_input(&self, user_text: &str) -> Option<String> {
+        let base = self.base_prompt_with_tools();
+        let Some(prompts) = &self.language_prompts else {
+            return base;
+        };
+        match crate::language::language_instruction(user_text, prompts) {
+            Some(instruction) => Some(match base {
+                Some(base) => format!("{base}\n\n{instruction}"),
+                None => instruction,
+            }),
+            None => base,
+        }
+    }
+
+    /// Require user confirmation before destructive tools (bash, file writes) run.
+    pub fn set_confirm_destructive(&mut self, enabled: bool) {
+        self.confirm_destructive = enabled;
+    }
+
+    /// Stream a status line ("Searching the web...") when a tool starts.
+    /// `overrides` replaces the built-in line for the named tools.
+    pub fn set_tool_status_messages(&mut self, overrides: HashMap<String, String>) {
+        self.tool_status_messages = Some(overrides);
+    }
+
+    /// Status line for `tool_name`, or `None` when status messages are off.
+    fn tool_status_message(&self, tool_name: &str) -> Option<String> {
+        let overrides = self.tool_status_messages.as_ref()?;
+        Some(
+            overrides
+                .get(tool_name)
+                .cloned()
+                .unwrap_or_else(|| default_tool_status(tool_name)),
+        )
+    }
+
+    /// Announce a tool on the delta channel before it runs (streaming only).
+    async fn send_tool_status(&self, delta_tx: &mpsc::Sender<String>, tool_name: &str) {
+        if let Some(status) = self.tool_status_message(tool_name) {
+            let _ = delta_tx.send(format!("{status}\n\n")).await;
+        }
+    }
+
+    /// Called at the start of every turn. A confirmation reply approves the
+    /// session's pending destructive calls; any other message discards them.
+    fn update_tool_confirmations(&self, session_id: &str, user_text: &str) {
+        if !self.confirm_destructive {
+            return;
+        }
+        if is_confirmation_reply(user_text) {
+            if let Some(mut pending) = self.pending_confirmations.get_mut(session_id) {
+                for call in pending.iter_mut() {
+                    call.approved = true;
+                }
+            }
+        } else {
+            self.pending_confirmations.remove(session_id);
+        }
+    }
+
+    /// Consume an approved confirmation for this exact call. When none exists,
+    /// record the call as pending and return `false` so the caller previews it.
+    fn take_tool_confirmation(
+        &self,
+        session_id: &str,
+        name: &str,
+        input: &serde_json::Value,
+    ) -> bool {
+        let mut pending = self
+            .pending_confirmations
+            .entry(session_id.to_string())
+            .or_default();
+        if let Some(pos) = pending
+            .iter()
+            .position(|c| c.approved && c.name == name && &c.input == input)
+        {
+            pending.remove(pos);
+            return true;
+        }
+        if !pending.iter().any(|c| c.name == name && &c.input == input) {
+            pending.push(PendingToolCall {
+                name: name.to_string(),
+                input: input.clone(),
+                approved: false,
+            });
+        }
+        false
+    }
+
+    /// Set the root directory under which per-session tool workspaces are created.
+    pub fn set_workspace_root(&mut self, root: PathBuf) {
+        self.workspace_root = Some(root);
+    }
+
+    /// Return the workspace directory for a session, creating it on first use.
+    /// Returns `None` when no workspace root is configured.
+    fn session_workspace_dir(&self, session_id: &str) -> Option<PathBuf> {
+        let root = self.workspace_root.as_ref()?;
+        let safe_id: String = session_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let dir = root.join(safe_id);
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            warn!("failed to create workspace {}: {e}", dir.display());
+        }
+        Some(dir)
+    }
+
+    /// Build the `ToolContext` handed to every tool executed for this turn.
+    fn tool_context(
+        &self,
+        session_id: &str,
+        user_id: Option<&str>,
+        continuity_key: Option<&str>,
+        heartbeat_depth: u8,
+    ) -> ToolContext {
+        ToolContext {
+            session_id: session_id.to_string(),
+            user_id: user_id.map(|s| s.to_string()),
+            heartbeat_depth,
+            allowed_tools: self.session_allowed_tools(session_id),
+            continuity_key: continuity_key.map(|s| s.to_string()),
+            workspace_dir: self.session_workspace_dir(session_id),
+        }
+    }
+
     /// Return the `allowed_tools` list for a session (used to populate `ToolContext`).
     fn session_allowed_tools(&self, session_id: &str) -> Option<Vec<String>> {
         self.session_tool_config
@@ -752,7 +1359,11 @@ impl AgentRuntime {
         Ok(())
     }
 
+    /// Register an LLM provider. Calls to it are retried on HTTP 429 after
+    /// the wait the provider asks for (see [`crate::rate_limit`]).
     pub fn register_provider(&self, provider: Arc<dyn LlmProvider>) {
+        let provider: Arc<dyn LlmProvider> =
+            Arc::new(crate::rate_limit::RateLimitRetry::new(provider));
         let id = provider.provider_id().to_string();
         info!("registered LLM provider: {}", id);
         {
@@ -778,6 +1389,18 @@ impl AgentRuntime {
         default_id.and_then(|id| self.get_provider(&id))
     }
 
+    /// The provider registered as `provider_id`, or the default provider.
+    fn resolve_provider(&self, provider_id: Option<&str>) -> Result<Arc<dyn LlmProvider>> {
+        match provider_id {
+            Some(pid) => self
+                .get_provider(pid)
+                .ok_or_else(|| Error::Agent(format!("provider '{pid}' not found"))),
+            None => self
+                .default_provider()
+                .ok_or_else(|| Error::Agent("no LLM provider configured".into())),
+        }
+    }
+
     /// Return the IDs of all registered providers.
     pub fn provider_ids(&self) -> Vec<String> {
         self.providers
@@ -802,6 +1425,13 @@ impl AgentRuntime {
         exists
     }
 
+    /// Whether the default provider accepts documents of `mime` natively, so
+    /// channels can attach them as `ContentBlock::Document` instead of text.
+    pub fn supports_documents(&self, mime: &str) -> bool {
+        self.default_provider()
+            .is_some_and(|provider| provider.supports_documents(mime))
+    }
+
     /// Return the current default provider ID.
     pub fn default_provider_id(&self) -> Option<String> {
         self.default_provider.read().unwrap().clone()
@@ -816,6 +1446,18 @@ impl AgentRuntime {
         self.memory.is_some()
     }
 
+    /// Delete conversation memory created before `before`, keeping notes saved
+    /// with the memory tool. Returns 0 without a memory provider.
+    pub async fn delete_expired_memory(
+        &self,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<usize> {
+        match &self.memory {
+            Some(memory) => memory.delete_expired(before).await,
+            None => Ok(0),
+        }
+    }
+
     pub fn set_embedding_provider(&mut self, embeddings: Arc<dyn EmbeddingProvider>) {
         self.embeddings = Some(embeddings);
         info!("embedding provider attached to agent runtime");
@@ -895,6 +1537,60 @@ impl AgentRuntime {
             .await
     }
 
+    /// Store files shared by users under `dir` so they can be recalled later.
+    pub fn set_attachments_dir(&mut self, dir: PathBuf) {
+        self.attachments_dir = Some(dir);
+    }
+
+    /// Record a file the user shared: the raw bytes go to the attachments
+    /// directory and a memory entry referencing them is persisted, so later
+    /// turns can recall "the spreadsheet you sent yesterday".
+    /// Returns `None` when memory or the attachments directory is not configured.
+    pub async fn remember_attachment(
+        &self,
+        session_id: &str,
+        continuity_key: Option<&str>,
+        user_id: Option<&str>,
+        filename: &str,
+        mime_type: Option<&str>,
+        data: &[u8],
+    ) -> Result<Option<MemoryAttachment>> {
+        let (Some(memory), Some(dir)) = (&self.memory, &self.attachments_dir) else {
+            return Ok(None);
+        };
+
+        let attachment =
+            MemoryAttachment::store(dir, filename, mime_type.map(str::to_string), data)?;
+        let content = match mime_type {
+            Some(mime) => format!(
+                "User shared file: {filename} ({mime}, {} bytes)",
+                attachment.size_bytes
+            ),
+            None => format!(
+                "User shared file: {filename} ({} bytes)",
+                attachment.size_bytes
+            ),
+        };
+        let embedding = self.embed_document(&content).await;
+
+        memory
+            .remember(NewMemoryEntry {
+                session_id: session_id.to_string(),
+                channel_id: None,
+                user_id: user_id.map(|s| s.to_string()),
+                continuity_key: continuity_key.map(|s| s.to_string()),
+                role: MemoryRole::User,
+                content,
+                embedding,
+                embedding_model: self.embedding_model(),
+                metadata: serde_json::json!({ "kind": "attachment" }),
+                attachments: vec![attachment.clone()],
+            })
+            .await?;
+
+        Ok(Some(attachment))
+    }
+
     pub async fn remember_turn(
         &self,
         session_id: &str,
@@ -921,6 +1617,7 @@ impl AgentRuntime {
                 embedding: user_embedding,
                 embedding_model: self.embedding_model(),
                 metadata: serde_json::json!({ "kind": "turn_user" }),
+                attachments: Vec::new(),
             })
             .await?;
 
@@ -935,12 +1632,22 @@ impl AgentRuntime {
                 embedding: assistant_embedding,
                 embedding_model: self.embedding_model(),
                 metadata: serde_json::json!({ "kind": "turn_assistant" }),
+                attachments: Vec::new(),
             })
             .await?;
 
         Ok(())
     }
 
+    /// Score the assistant memory entry for `reply` in `session_id`, e.g. +1
+    /// for a thumbs-up reaction. Returns whether a matching entry was found.
+    pub async fn record_feedback(&self, session_id: &str, reply: &str, delta: i64) -> Result<bool> {
+        let Some(memory) = &self.memory else {
+            return Ok(false);
+        };
+        memory.record_feedback(session_id, reply, delta).await
+    }
+
     pub async fn recall_context(
         &self,
         query_text: &str,
@@ -958,7 +1665,12 @@ impl AgentRuntime {
             .recall(RecallQuery {
                 query_text: Some(query_text.to_string()),
                 query_embedding,
-                session_id: session_id.map(|s| s.to_string()),
+                // A continuity key spans sessions (and channels), so it
+                // replaces the session filter rather than narrowing it.
+                session_id: session_id
+                    .filter(|_| continuity_key.is_none())
+                    .map(|s| s.to_string()),
+                session_ids: None,
                 continuity_key: continuity_key.map(|s| s.to_string()),
                 limit,
             })
@@ -970,7 +1682,12 @@ impl AgentRuntime {
         self.tools.push(tool);
     }
 
-    fn tool_definitions(&self) -> Vec<ToolDefinition> {
+    /// Tool definitions to offer `provider`; empty when the provider's
+    /// endpoint rejects tools, so the turn ends with a plain text answer.
+    fn tool_definitions(&self, provider: &dyn LlmProvider) -> Vec<ToolDefinition> {
+        if !provider.supports_tools() {
+            return Vec::new();
+        }
         self.tools
             .iter()
             .map(|t| ToolDefinition {
@@ -999,9 +1716,21 @@ impl AgentRuntime {
     ) -> ToolOutput {
         self.traj_log_tool_call(session_id, traj_turn_index, name, input);
         let t0 = std::time::Instant::now();
-        let output = match self.check_tool_allowed(session_id, name) {
+        let mut output = match self.check_tool_allowed(session_id, name) {
             Err(e) => ToolOutput::error(e.to_string()),
             Ok(()) => match self.find_tool(name) {
+                Some(tool)
+                    if self.confirm_destructive
+                        && tool.is_destructive()
+                        && !self.take_tool_confirmation(session_id, name, input) =>
+                {
+                    ToolOutput::success(format!(
+                        "[dry run] would run: {name} {input}\n\
+                         This tool is destructive and was NOT executed. Show the user \
+                         what will happen and ask them to confirm. After they reply \
+                         \"yes\", call {name} again with exactly the same input."
+                    ))
+                }
                 Some(tool) => tool
                     .execute(context, input.clone())
                     .await
@@ -1018,15 +1747,52 @@ impl AgentRuntime {
             latency_ms,
         );
         self.record_debug_tool_call(session_id, name, &input.to_string());
+        if let Some(data) = output.data.as_ref().filter(|_| !output.is_error) {
+            self.tool_results
+                .entry(session_id.to_string())
+                .or_default()
+                .push((name.to_string(), data.clone()));
+        }
+        if !output.is_error && !output.attachments.is_empty() {
+            if self.attachment_sessions.contains_key(session_id) {
+                self.tool_attachments
+                    .entry(session_id.to_string())
+                    .or_default()
+                    .extend(output.attachments.iter().cloned());
+                output
+                    .content
+                    .push_str("\n\nIt will be sent to the user with your reply.");
+            } else {
+                output.content.push_str(
+                    "\n\nThis channel cannot send it to the user. Do not say it was sent.",
+                );
+            }
+        }
+        if let Some(mut trace) = self.turn_traces.get_mut(session_id) {
+            trace.tool_calls.push(ToolCallTrace {
+                name: name.to_string(),
+                input: trace_snippet(&input.to_string()),
+                output: trace_snippet(&output.content),
+                is_error: output.is_error,
+            });
+        }
         output
     }
 
-    /// Return the skills directory from the registered `create_skill` tool, if any.
+    /// Return the skills directory set via `set_skills_dir`, or the one from the
+    /// registered `create_skill` tool.
+    fn configured_skills_dir(&self) -> Option<PathBuf> {
+        self.skills_source_dir.clone().or_else(|| {
+            self.tools
+                .iter()
+                .find(|t| t.name() == "create_skill")
+                .and_then(|t| t.skills_dir_hint())
+        })
+    }
+
+    /// Return the skills directory, defaulting to the current directory.
     fn skills_dir(&self) -> std::path::PathBuf {
-        self.tools
-            .iter()
-            .find(|t| t.name() == "create_skill")
-            .and_then(|t| t.skills_dir_hint())
+        self.configured_skills_dir()
             .unwrap_or_else(|| std::path::PathBuf::from("."))
     }
 
@@ -1076,7 +1842,10 @@ impl AgentRuntime {
             system: system.clone(),
             max_tokens: Some(max_tokens.min(256)),
             temperature: None,
+            seed: self.seed,
             tools: vec![], // no tools — prevents re-entering the tool loop
+            response_format: None,
+            thinking: None,
         };
         match provider.complete(&request).await {
             Ok(response) => {
@@ -1162,7 +1931,10 @@ impl AgentRuntime {
             system: system.clone(),
             max_tokens: Some(128),
             temperature: None,
+            seed: self.seed,
             tools: vec![],
+            response_format: None,
+            thinking: None,
         };
         let assess_response = match provider.complete(&assess_request).await {
             Ok(r) => r,
@@ -1207,7 +1979,10 @@ impl AgentRuntime {
             system: system.clone(),
             max_tokens: Some(max_tokens.min(512)),
             temperature: None,
+            seed: self.seed,
             tools: vec![create_skill_def],
+            response_format: None,
+            thinking: None,
         };
         let response = match provider.complete(&request).await {
             Ok(r) => r,
@@ -1241,6 +2016,8 @@ impl AgentRuntime {
                     user_id: None,
                     heartbeat_depth: 0,
                     allowed_tools: None,
+                    continuity_key: None,
+                    workspace_dir: None,
                 };
                 if let Some(tool) = self.find_tool("create_skill") {
                     match tool.execute(&ctx, input.clone()).await {
@@ -1343,7 +2120,10 @@ impl AgentRuntime {
             system: system.clone(),
             max_tokens: Some(max_tokens.min(1024)),
             temperature: None,
+            seed: self.seed,
             tools: vec![create_skill_def],
+            response_format: None,
+            thinking: None,
         };
         let response = match provider.complete(&request).await {
             Ok(r) => r,
@@ -1382,6 +2162,8 @@ impl AgentRuntime {
                     user_id: None,
                     heartbeat_depth: 0,
                     allowed_tools: None,
+                    continuity_key: None,
+                    workspace_dir: None,
                 };
                 if let Some(tool) = self.find_tool("create_skill") {
                     match tool.execute(&tool_ctx, input.clone()).await {
@@ -1476,6 +2258,24 @@ impl AgentRuntime {
         acc.entry(session_id.to_string()).or_default().push(entry);
     }
 
+    /// Start recording a structured `TurnTrace` for the next turn of a session,
+    /// independent of the global `debug` flag. Collect it with `take_turn_trace`.
+    pub fn begin_turn_trace(&self, session_id: &str) {
+        self.turn_traces
+            .insert(session_id.to_string(), TurnTrace::default());
+    }
+
+    /// Stop recording and return the trace started by `begin_turn_trace`.
+    pub fn take_turn_trace(&self, session_id: &str) -> Option<TurnTrace> {
+        self.turn_traces.remove(session_id).map(|(_, trace)| trace)
+    }
+
+    fn trace_iteration(&self, session_id: &str) {
+        if let Some(mut trace) = self.turn_traces.get_mut(session_id) {
+            trace.iterations += 1;
+        }
+    }
+
     /// Take accumulated debug info for a session. Returns None if debug is off or no data.
     pub fn take_debug_info(&self, session_id: &str) -> Option<Vec<String>> {
         if !self.debug {
@@ -1592,6 +2392,8 @@ impl AgentRuntime {
             continuity_key,
             user_id,
             0,
+            None,
+            None,
         )
         .await
     }
@@ -1619,6 +2421,8 @@ impl AgentRuntime {
             session_summary,
             continuity_key,
             user_id,
+            None,
+            None,
         )
         .await
     }
@@ -1644,6 +2448,8 @@ impl AgentRuntime {
             continuity_key,
             user_id,
             0,
+            None,
+            None,
         )
         .await
     }
@@ -1670,10 +2476,63 @@ impl AgentRuntime {
             session_summary,
             continuity_key,
             user_id,
-        )
+            None,
+            None,
+        )
         .await
     }
 
+    /// Same as the `*_and_summary` variants but answered by `provider_id`
+    /// (the default provider when `None`) with an optional model override.
+    /// Streams deltas to `delta_tx` when given.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn process_message_with_provider_and_summary(
+        &self,
+        session_id: &str,
+        user_content: MessagePart,
+        user_text_for_memory: &str,
+        conversation_history: &[ChatMessage],
+        delta_tx: Option<mpsc::Sender<String>>,
+        session_summary: Option<&str>,
+        continuity_key: Option<&str>,
+        user_id: Option<&str>,
+        provider_id: Option<&str>,
+        model_override: Option<&str>,
+    ) -> Result<(String, Option<String>)> {
+        match delta_tx {
+            Some(delta_tx) => {
+                self.process_message_streaming_summarized_impl(
+                    session_id,
+                    user_content,
+                    user_text_for_memory,
+                    conversation_history,
+                    delta_tx,
+                    session_summary,
+                    continuity_key,
+                    user_id,
+                    provider_id,
+                    model_override,
+                )
+                .await
+            }
+            None => {
+                self.process_message_summarized_impl(
+                    session_id,
+                    user_content,
+                    user_text_for_memory,
+                    conversation_history,
+                    session_summary,
+                    continuity_key,
+                    user_id,
+                    0,
+                    provider_id,
+                    model_override,
+                )
+                .await
+            }
+        }
+    }
+
     /// Process a message with explicit agent config overrides (for multi-agent routing).
     /// `depth` is the handoff nesting level — 0 for direct user requests.
     #[allow(clippy::too_many_arguments)]
@@ -1723,13 +2582,11 @@ impl AgentRuntime {
         max_context_tokens_override: Option<usize>,
         depth: u8,
     ) -> Result<String> {
-        let provider: Arc<dyn LlmProvider> = if let Some(pid) = provider_id {
-            self.get_provider(pid)
-                .ok_or_else(|| Error::Agent(format!("provider '{pid}' not found")))?
-        } else {
-            self.default_provider()
-                .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?
-        };
+        let (provider_id, model_override) =
+            self.session_provider_and_model(session_id, provider_id, model_override);
+        let provider_id = provider_id.as_deref();
+        let model_override = model_override.as_deref();
+        let provider = self.resolve_provider(provider_id)?;
 
         let _system_prompt_override = system_prompt_override;
         let effective_model = model_override
@@ -1737,7 +2594,7 @@ impl AgentRuntime {
             .filter(|m| !m.is_empty())
             .map(|m| m.to_string())
             .unwrap_or_default();
-        let effective_max_tokens = max_tokens_override.or(self.max_tokens).unwrap_or(4096);
+        let effective_max_tokens = self.max_tokens_for(provider.provider_id(), max_tokens_override);
 
         let memory_context = match self
             .recall_context(
@@ -1748,13 +2605,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -1764,10 +2615,12 @@ impl AgentRuntime {
 
         let dna = self.session_dna_content(session_id);
         let skills = self.session_skills_content(session_id, user_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(user_text);
         let rag_context = self.auto_rag_context(user_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -1780,12 +2633,13 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
-            content: MessagePart::Text(user_text.to_string()),
+            content: self
+                .resume_after_question(session_id, MessagePart::Text(user_text.to_string())),
         });
 
         let max_ctx = max_context_tokens_override
@@ -1794,18 +2648,25 @@ impl AgentRuntime {
         trim_messages_to_budget(&mut messages, &system, &tool_defs, max_ctx);
 
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, user_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
                 model: effective_model.clone(),
                 messages: messages.clone(),
                 system: system.clone(),
                 max_tokens: Some(effective_max_tokens),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
-            let response = provider.complete(&request).await?;
+            let response = self
+                .complete_for_session(session_id, provider.as_ref(), &request)
+                .await?;
 
             if let Some(usage) = &response.usage {
                 self.accumulate_usage(
@@ -1858,21 +2719,21 @@ impl AgentRuntime {
                 content: MessagePart::Parts(response.content.clone()),
             });
 
+            let mut asked = None;
             let mut tool_results = Vec::new();
             for block in &response.content {
                 if let ContentBlock::ToolUse { id, name, input } = block {
-                    let context = crate::tools::ToolContext {
-                        session_id: session_id.to_string(),
-                        user_id: user_id.map(|s| s.to_string()),
-                        heartbeat_depth: depth,
-                        allowed_tools: self.session_allowed_tools(session_id),
-                    };
+                    let context = self.tool_context(session_id, user_id, continuity_key, depth);
                     let output = self
                         .run_tool(session_id, traj_turn_index, &context, name, input)
                         .await;
+                    if name == ASK_USER_TOOL && !output.is_error {
+                        asked = Some((output.content.clone(), ask_user_options(input)));
+                    }
                     tool_results.push(ContentBlock::ToolResult {
                         tool_use_id: id.clone(),
                         content: output.content,
+                        is_error: output.is_error,
                     });
                 }
             }
@@ -1882,6 +2743,15 @@ impl AgentRuntime {
                 .filter(|b| matches!(b, ContentBlock::ToolUse { .. }))
                 .count();
 
+            if let Some((question, options)) = asked {
+                return Ok(self.end_turn_with_question(
+                    session_id,
+                    traj_turn_index,
+                    question,
+                    options,
+                ));
+            }
+
             messages.push(ChatMessage {
                 role: ChatRole::User,
                 content: MessagePart::Parts(tool_results),
@@ -1910,13 +2780,11 @@ impl AgentRuntime {
         max_context_tokens_override: Option<usize>,
         session_summary: Option<&str>,
     ) -> Result<(String, Option<String>)> {
-        let provider: Arc<dyn LlmProvider> = if let Some(pid) = provider_id {
-            self.get_provider(pid)
-                .ok_or_else(|| Error::Agent(format!("provider '{pid}' not found")))?
-        } else {
-            self.default_provider()
-                .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?
-        };
+        let (provider_id, model_override) =
+            self.session_provider_and_model(session_id, provider_id, model_override);
+        let provider_id = provider_id.as_deref();
+        let model_override = model_override.as_deref();
+        let provider = self.resolve_provider(provider_id)?;
 
         let _system_prompt_override = system_prompt_override;
         let effective_model = model_override
@@ -1924,7 +2792,7 @@ impl AgentRuntime {
             .filter(|m| !m.is_empty())
             .map(|m| m.to_string())
             .unwrap_or_default();
-        let effective_max_tokens = max_tokens_override.or(self.max_tokens).unwrap_or(4096);
+        let effective_max_tokens = self.max_tokens_for(provider.provider_id(), max_tokens_override);
 
         let memory_context = match self
             .recall_context(
@@ -1935,13 +2803,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -1951,10 +2813,12 @@ impl AgentRuntime {
 
         let dna = self.session_dna_content(session_id);
         let skills = self.session_skills_content(session_id, user_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(user_text);
         let rag_context = self.auto_rag_context(user_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -1967,13 +2831,13 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
             content: inject_rag_into_content(
-                MessagePart::Text(user_text.to_string()),
+                self.resume_after_question(session_id, MessagePart::Text(user_text.to_string())),
                 rag_context.as_deref(),
             ),
         });
@@ -2007,18 +2871,25 @@ impl AgentRuntime {
         };
 
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, user_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
                 model: effective_model.clone(),
                 messages: messages.clone(),
                 system: system.clone(),
                 max_tokens: Some(effective_max_tokens),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
-            let response = provider.complete(&request).await?;
+            let response = self
+                .complete_for_session(session_id, provider.as_ref(), &request)
+                .await?;
 
             if let Some(usage) = &response.usage {
                 self.accumulate_usage(
@@ -2070,21 +2941,21 @@ impl AgentRuntime {
                 content: MessagePart::Parts(response.content.clone()),
             });
 
+            let mut asked = None;
             let mut tool_results = Vec::new();
             for block in &response.content {
                 if let ContentBlock::ToolUse { id, name, input } = block {
-                    let context = crate::tools::ToolContext {
-                        session_id: session_id.to_string(),
-                        user_id: user_id.map(|s| s.to_string()),
-                        heartbeat_depth: 0,
-                        allowed_tools: self.session_allowed_tools(session_id),
-                    };
+                    let context = self.tool_context(session_id, user_id, continuity_key, 0);
                     let output = self
                         .run_tool(session_id, traj_turn_index, &context, name, input)
                         .await;
+                    if name == ASK_USER_TOOL && !output.is_error {
+                        asked = Some((output.content.clone(), ask_user_options(input)));
+                    }
                     tool_results.push(ContentBlock::ToolResult {
                         tool_use_id: id.clone(),
                         content: output.content,
+                        is_error: output.is_error,
                     });
                 }
             }
@@ -2094,6 +2965,12 @@ impl AgentRuntime {
                 .filter(|b| matches!(b, ContentBlock::ToolUse { .. }))
                 .count();
 
+            if let Some((question, options)) = asked {
+                let question =
+                    self.end_turn_with_question(session_id, traj_turn_index, question, options);
+                return Ok((question, new_summary));
+            }
+
             messages.push(ChatMessage {
                 role: ChatRole::User,
                 content: MessagePart::Parts(tool_results),
@@ -2132,13 +3009,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -2148,10 +3019,12 @@ impl AgentRuntime {
 
         let dna = self.dna_content();
         let skills = self.relevant_skills_content(memory_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(memory_text);
         let rag_context = self.auto_rag_context(memory_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -2164,12 +3037,15 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
-            content: inject_rag_into_content(user_content, rag_context.as_deref()),
+            content: inject_rag_into_content(
+                self.resume_after_question(session_id, user_content),
+                rag_context.as_deref(),
+            ),
         });
 
         // Trim conversation history to fit context window
@@ -2177,18 +3053,25 @@ impl AgentRuntime {
         trim_messages_to_budget(&mut messages, &system, &tool_defs, max_ctx);
 
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, memory_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
                 model: String::new(),
                 messages: messages.clone(),
                 system: system.clone(),
-                max_tokens: Some(self.max_tokens.unwrap_or(4096)),
+                max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
-            let response = provider.complete(&request).await?;
+            let response = self
+                .complete_for_session(session_id, provider.as_ref(), &request)
+                .await?;
 
             let has_tool_use = response
                 .content
@@ -2223,7 +3106,7 @@ impl AgentRuntime {
                             messages: &messages,
                             system: &system,
                             model: "",
-                            max_tokens: self.max_tokens.unwrap_or(4096),
+                            max_tokens: self.max_tokens_for(provider.provider_id(), None),
                             skills_content: skills.as_deref(),
                         },
                         session_id,
@@ -2252,26 +3135,36 @@ impl AgentRuntime {
             });
 
             // Execute each tool and collect results
+            let mut asked = None;
             let mut tool_results = Vec::new();
             for block in &response.content {
                 if let ContentBlock::ToolUse { id, name, input } = block {
-                    let context = ToolContext {
-                        session_id: session_id.to_string(),
-                        user_id: user_id.map(|s| s.to_string()),
-                        heartbeat_depth,
-                        allowed_tools: self.session_allowed_tools(session_id),
-                    };
+                    let context =
+                        self.tool_context(session_id, user_id, continuity_key, heartbeat_depth);
                     let output = self
                         .run_tool(session_id, traj_turn_index, &context, name, input)
                         .await;
+                    if name == ASK_USER_TOOL && !output.is_error {
+                        asked = Some((output.content.clone(), ask_user_options(input)));
+                    }
                     tool_results.push(ContentBlock::ToolResult {
                         tool_use_id: id.clone(),
                         content: output.content,
+                        is_error: output.is_error,
                     });
                 }
             }
 
             // Append tool results as a user message
+            if let Some((question, options)) = asked {
+                return Ok(self.end_turn_with_question(
+                    session_id,
+                    traj_turn_index,
+                    question,
+                    options,
+                ));
+            }
+
             messages.push(ChatMessage {
                 role: ChatRole::User,
                 content: MessagePart::Parts(tool_results),
@@ -2376,13 +3269,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -2392,10 +3279,12 @@ impl AgentRuntime {
 
         let dna = self.dna_content();
         let skills = self.relevant_skills_content(memory_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(memory_text);
         let rag_context = self.auto_rag_context(memory_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -2408,12 +3297,15 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
-            content: inject_rag_into_content(user_content, rag_context.as_deref()),
+            content: inject_rag_into_content(
+                self.resume_after_question(session_id, user_content),
+                rag_context.as_deref(),
+            ),
         });
 
         let max_ctx = self.max_context_tokens.unwrap_or(100_000);
@@ -2421,15 +3313,20 @@ impl AgentRuntime {
 
         let mut full_response = String::new();
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, memory_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
                 model: String::new(),
                 messages: messages.clone(),
                 system: system.clone(),
-                max_tokens: Some(self.max_tokens.unwrap_or(4096)),
+                max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
             // Try streaming; fall back to non-streaming if not supported
@@ -2441,13 +3338,53 @@ impl AgentRuntime {
                     let mut response_text = String::new();
                     let mut tool_uses: Vec<(String, String, String)> = Vec::new(); // (id, name, input_json)
                     let mut current_tool: Option<(String, String, String)> = None;
-                    let mut _stop_reason: Option<String> = None;
-
-                    while let Some(event) = stream.next().await {
-                        match event? {
+                    let mut thinking_blocks: Vec<ContentBlock> = Vec::new(); // in stream order
+                    let mut current_thinking: Option<(String, String)> = None;
+                    let mut stop_reason: Option<String> = None;
+                    let mut stream_usage: Option<Usage> = None;
+                    let mut interrupted = false;
+
+                    loop {
+                        // Stop as soon as the consumer goes away. Returning drops
+                        // `stream`, which drops the provider's HTTP response and
+                        // closes the connection so generation stops.
+                        let event = tokio::select! {
+                            biased;
+                            _ = delta_tx.closed() => return Err(stream_cancelled()),
+                            event = stream.next() => event,
+                        };
+                        let Some(event) = event else { break };
+                        let event = match event {
+                            Ok(event) => event,
+                            // Text already reached the user: keep it and end the
+                            // turn instead of failing it. Half-streamed tool calls
+                            // are dropped.
+                            Err(e) if !full_response.is_empty() || !response_text.is_empty() => {
+                                warn!("provider stream failed mid-response: {e}");
+                                let note = format!("\n\n{STREAM_INTERRUPTED_NOTE}");
+                                let _ = delta_tx.send(note.clone()).await;
+                                response_text.push_str(&note);
+                                tool_uses.clear();
+                                current_tool = None;
+                                interrupted = true;
+                                break;
+                            }
+                            Err(e) => {
+                                self.record_transcript(
+                                    session_id,
+                                    provider.as_ref(),
+                                    &request,
+                                    Err(&e),
+                                );
+                                return Err(e);
+                            }
+                        };
+                        match event {
                             StreamEvent::TextDelta(text) => {
                                 response_text.push_str(&text);
-                                let _ = delta_tx.send(text).await;
+                                if delta_tx.send(text).await.is_err() {
+                                    return Err(stream_cancelled());
+                                }
                             }
                             StreamEvent::ToolUseStart { id, name, .. } => {
                                 current_tool = Some((id, name, String::new()));
@@ -2457,7 +3394,25 @@ impl AgentRuntime {
                                     input.push_str(&json);
                                 }
                             }
+                            StreamEvent::ThinkingDelta(text) => {
+                                current_thinking.get_or_insert_default().0.push_str(&text);
+                            }
+                            StreamEvent::SignatureDelta(signature) => {
+                                current_thinking
+                                    .get_or_insert_default()
+                                    .1
+                                    .push_str(&signature);
+                            }
+                            StreamEvent::RedactedThinking(data) => {
+                                thinking_blocks.push(ContentBlock::RedactedThinking { data });
+                            }
                             StreamEvent::ContentBlockStop { .. } => {
+                                if let Some((thinking, signature)) = current_thinking.take() {
+                                    thinking_blocks.push(ContentBlock::Thinking {
+                                        thinking,
+                                        signature,
+                                    });
+                                }
                                 if let Some(tool) = current_tool.take() {
                                     tool_uses.push(tool);
                                 }
@@ -2471,7 +3426,7 @@ impl AgentRuntime {
                                 if let Some(tool) = current_tool.take() {
                                     tool_uses.push(tool);
                                 }
-                                _stop_reason = sr;
+                                stop_reason = sr;
                                 if let Some(u) = usage {
                                     self.accumulate_usage(
                                         session_id,
@@ -2480,6 +3435,7 @@ impl AgentRuntime {
                                         u.input_tokens,
                                         u.output_tokens,
                                     );
+                                    stream_usage = Some(u);
                                 }
                             }
                             StreamEvent::MessageStop => break,
@@ -2492,6 +3448,35 @@ impl AgentRuntime {
                         tool_uses.push(tool);
                     }
 
+                    if let Some((thinking, signature)) = current_thinking.take() {
+                        thinking_blocks.push(ContentBlock::Thinking {
+                            thinking,
+                            signature,
+                        });
+                    }
+                    for block in &thinking_blocks {
+                        if let ContentBlock::Thinking { thinking, .. } = block {
+                            self.record_reasoning(session_id, thinking);
+                        }
+                    }
+
+                    if self.transcripts.is_some() {
+                        let response = streamed_response(
+                            provider.configured_model().unwrap_or(""),
+                            &thinking_blocks,
+                            &response_text,
+                            &tool_uses,
+                            stop_reason.take(),
+                            stream_usage.take(),
+                        );
+                        self.record_transcript(
+                            session_id,
+                            provider.as_ref(),
+                            &request,
+                            Ok(&response),
+                        );
+                    }
+
                     if tool_uses.is_empty() {
                         full_response.push_str(&response_text);
 
@@ -2508,21 +3493,23 @@ impl AgentRuntime {
                             warn!("failed to store turn in memory: {}", e);
                         }
 
-                        if let Some(followup) = self
-                            .skill_completion_followup(
-                                tool_call_count,
-                                skills.is_some(),
-                                NudgeContext {
-                                    provider: provider.as_ref(),
-                                    messages: &messages,
-                                    system: &system,
-                                    model: "",
-                                    max_tokens: self.max_tokens.unwrap_or(4096),
-                                    skills_content: skills.as_deref(),
-                                },
-                                session_id,
-                            )
-                            .await
+                        if !interrupted
+                            && let Some(followup) = self
+                                .skill_completion_followup(
+                                    tool_call_count,
+                                    skills.is_some(),
+                                    NudgeContext {
+                                        provider: provider.as_ref(),
+                                        messages: &messages,
+                                        system: &system,
+                                        model: "",
+                                        max_tokens: self
+                                            .max_tokens_for(provider.provider_id(), None),
+                                        skills_content: skills.as_deref(),
+                                    },
+                                    session_id,
+                                )
+                                .await
                         {
                             let chunk = format!("\n\n{followup}");
                             let _ = delta_tx.send(chunk.clone()).await;
@@ -2536,7 +3523,8 @@ impl AgentRuntime {
                     tool_call_count += tool_uses.len();
 
                     // Build assistant response with text + tool_use blocks
-                    let mut content_blocks = Vec::new();
+                    // Anthropic requires the thinking blocks back with the tool calls.
+                    let mut content_blocks: Vec<ContentBlock> = thinking_blocks;
                     if !response_text.is_empty() {
                         content_blocks.push(ContentBlock::Text {
                             text: response_text.clone(),
@@ -2560,25 +3548,43 @@ impl AgentRuntime {
                     });
 
                     // Execute tools
+                    let mut asked = None;
                     let mut tool_results = Vec::new();
                     for (id, name, input_json) in &tool_uses {
                         let input: serde_json::Value =
                             serde_json::from_str(input_json).unwrap_or_default();
-                        let context = ToolContext {
-                            session_id: session_id.to_string(),
-                            user_id: user_id.map(|s| s.to_string()),
-                            heartbeat_depth: 0,
-                            allowed_tools: self.session_allowed_tools(session_id),
-                        };
+                        let context = self.tool_context(session_id, user_id, continuity_key, 0);
+                        self.send_tool_status(&delta_tx, name).await;
                         let output = self
                             .run_tool(session_id, traj_turn_index, &context, name, &input)
                             .await;
+                        if name == ASK_USER_TOOL && !output.is_error {
+                            asked = Some((output.content.clone(), ask_user_options(&input)));
+                        }
                         tool_results.push(ContentBlock::ToolResult {
                             tool_use_id: id.clone(),
                             content: output.content,
+                            is_error: output.is_error,
                         });
                     }
 
+                    if let Some((question, options)) = asked {
+                        let question = self.end_turn_with_question(
+                            session_id,
+                            traj_turn_index,
+                            question,
+                            options,
+                        );
+                        let chunk = if full_response.is_empty() {
+                            question
+                        } else {
+                            format!("\n\n{question}")
+                        };
+                        let _ = delta_tx.send(chunk.clone()).await;
+                        full_response.push_str(&chunk);
+                        return Ok(full_response);
+                    }
+
                     messages.push(ChatMessage {
                         role: ChatRole::User,
                         content: MessagePart::Parts(tool_results),
@@ -2592,7 +3598,9 @@ impl AgentRuntime {
                 }
                 Err(_) => {
                     // Streaming not supported — fall back to non-streaming
-                    let response = provider.complete(&request).await?;
+                    let response = self
+                        .complete_for_session(session_id, provider.as_ref(), &request)
+                        .await?;
 
                     if let Some(usage) = &response.usage {
                         self.accumulate_usage(
@@ -2636,7 +3644,7 @@ impl AgentRuntime {
                                     messages: &messages,
                                     system: &system,
                                     model: "",
-                                    max_tokens: self.max_tokens.unwrap_or(4096),
+                                    max_tokens: self.max_tokens_for(provider.provider_id(), None),
                                     skills_content: skills.as_deref(),
                                 },
                                 session_id,
@@ -2663,25 +3671,43 @@ impl AgentRuntime {
                         content: MessagePart::Parts(response.content.clone()),
                     });
 
+                    let mut asked = None;
                     let mut tool_results = Vec::new();
                     for block in &response.content {
                         if let ContentBlock::ToolUse { id, name, input } = block {
-                            let context = ToolContext {
-                                session_id: session_id.to_string(),
-                                user_id: user_id.map(|s| s.to_string()),
-                                heartbeat_depth: 0,
-                                allowed_tools: self.session_allowed_tools(session_id),
-                            };
+                            let context = self.tool_context(session_id, user_id, continuity_key, 0);
+                            self.send_tool_status(&delta_tx, name).await;
                             let output = self
                                 .run_tool(session_id, traj_turn_index, &context, name, input)
                                 .await;
+                            if name == ASK_USER_TOOL && !output.is_error {
+                                asked = Some((output.content.clone(), ask_user_options(input)));
+                            }
                             tool_results.push(ContentBlock::ToolResult {
                                 tool_use_id: id.clone(),
                                 content: output.content,
+                                is_error: output.is_error,
                             });
                         }
                     }
 
+                    if let Some((question, options)) = asked {
+                        let question = self.end_turn_with_question(
+                            session_id,
+                            traj_turn_index,
+                            question,
+                            options,
+                        );
+                        let chunk = if full_response.is_empty() {
+                            question
+                        } else {
+                            format!("\n\n{question}")
+                        };
+                        let _ = delta_tx.send(chunk.clone()).await;
+                        full_response.push_str(&chunk);
+                        return Ok(full_response);
+                    }
+
                     messages.push(ChatMessage {
                         role: ChatRole::User,
                         content: MessagePart::Parts(tool_results),
@@ -2709,10 +3735,15 @@ impl AgentRuntime {
         continuity_key: Option<&str>,
         user_id: Option<&str>,
         heartbeat_depth: u8,
+        provider_id: Option<&str>,
+        model_override: Option<&str>,
     ) -> Result<(String, Option<String>)> {
-        let provider: Arc<dyn LlmProvider> = self
-            .default_provider()
-            .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?;
+        let (provider_id, model_override) =
+            self.session_provider_and_model(session_id, provider_id, model_override);
+        let provider_id = provider_id.as_deref();
+        let model_override = model_override.as_deref();
+        let provider = self.resolve_provider(provider_id)?;
+        let effective_model = model_override.map(str::trim).unwrap_or_default();
 
         let memory_context = match self
             .recall_context(
@@ -2723,13 +3754,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -2739,10 +3764,12 @@ impl AgentRuntime {
 
         let dna = self.dna_content();
         let skills = self.relevant_skills_content(memory_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(memory_text);
         let rag_context = self.auto_rag_context(memory_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -2755,12 +3782,15 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
-            content: inject_rag_into_content(user_content, rag_context.as_deref()),
+            content: inject_rag_into_content(
+                self.resume_after_question(session_id, user_content),
+                rag_context.as_deref(),
+            ),
         });
 
         let max_ctx = self.max_context_tokens.unwrap_or(100_000);
@@ -2791,18 +3821,25 @@ impl AgentRuntime {
         };
 
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, memory_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
-                model: String::new(),
+                model: effective_model.to_string(),
                 messages: messages.clone(),
                 system: system.clone(),
-                max_tokens: Some(self.max_tokens.unwrap_or(4096)),
+                max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
-            let response = provider.complete(&request).await?;
+            let response = self
+                .complete_for_session(session_id, provider.as_ref(), &request)
+                .await?;
 
             if let Some(usage) = &response.usage {
                 self.accumulate_usage(
@@ -2843,8 +3880,8 @@ impl AgentRuntime {
                             provider: provider.as_ref(),
                             messages: &messages,
                             system: &system,
-                            model: "",
-                            max_tokens: self.max_tokens.unwrap_or(4096),
+                            model: effective_model,
+                            max_tokens: self.max_tokens_for(provider.provider_id(), None),
                             skills_content: skills.as_deref(),
                         },
                         session_id,
@@ -2870,25 +3907,32 @@ impl AgentRuntime {
                 content: MessagePart::Parts(response.content.clone()),
             });
 
+            let mut asked = None;
             let mut tool_results = Vec::new();
             for block in &response.content {
                 if let ContentBlock::ToolUse { id, name, input } = block {
-                    let context = ToolContext {
-                        session_id: session_id.to_string(),
-                        user_id: user_id.map(|s| s.to_string()),
-                        heartbeat_depth,
-                        allowed_tools: self.session_allowed_tools(session_id),
-                    };
+                    let context =
+                        self.tool_context(session_id, user_id, continuity_key, heartbeat_depth);
                     let output = self
                         .run_tool(session_id, traj_turn_index, &context, name, input)
                         .await;
+                    if name == ASK_USER_TOOL && !output.is_error {
+                        asked = Some((output.content.clone(), ask_user_options(input)));
+                    }
                     tool_results.push(ContentBlock::ToolResult {
                         tool_use_id: id.clone(),
                         content: output.content,
+                        is_error: output.is_error,
                     });
                 }
             }
 
+            if let Some((question, options)) = asked {
+                let question =
+                    self.end_turn_with_question(session_id, traj_turn_index, question, options);
+                return Ok((question, new_summary));
+            }
+
             messages.push(ChatMessage {
                 role: ChatRole::User,
                 content: MessagePart::Parts(tool_results),
@@ -2914,10 +3958,15 @@ impl AgentRuntime {
         session_summary: Option<&str>,
         continuity_key: Option<&str>,
         user_id: Option<&str>,
+        provider_id: Option<&str>,
+        model_override: Option<&str>,
     ) -> Result<(String, Option<String>)> {
-        let provider: Arc<dyn LlmProvider> = self
-            .default_provider()
-            .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?;
+        let (provider_id, model_override) =
+            self.session_provider_and_model(session_id, provider_id, model_override);
+        let provider_id = provider_id.as_deref();
+        let model_override = model_override.as_deref();
+        let provider = self.resolve_provider(provider_id)?;
+        let effective_model = model_override.map(str::trim).unwrap_or_default();
 
         let memory_context = match self
             .recall_context(
@@ -2928,13 +3977,7 @@ impl AgentRuntime {
             )
             .await
         {
-            Ok(entries) if !entries.is_empty() => {
-                let context: Vec<String> = entries.iter().map(|e| e.content.clone()).collect();
-                Some(format!(
-                    "Relevant context from memory:\n- {}",
-                    context.join("\n- ")
-                ))
-            }
+            Ok(entries) if !entries.is_empty() => Some(format_memory_context(&entries)),
             Err(e) => {
                 warn!("memory recall failed, continuing without context: {}", e);
                 None
@@ -2944,10 +3987,12 @@ impl AgentRuntime {
 
         let dna = self.dna_content();
         let skills = self.relevant_skills_content(memory_text).await;
+
+        let skills = self.render_skills_block(skills, session_id, memory_context.as_deref());
         if let Some(block) = &skills {
             self.log_injected_skills(session_id, block);
         }
-        let base_prompt = self.base_prompt_with_tools();
+        let base_prompt = self.base_prompt_for_input(memory_text);
         let rag_context = self.auto_rag_context(memory_text).await;
         let user_display = self.session_user_name(session_id);
         let system = build_system_prompt(
@@ -2960,12 +4005,15 @@ impl AgentRuntime {
             user_display.as_deref(),
         );
 
-        let tool_defs = self.tool_definitions();
+        let tool_defs = self.tool_definitions(provider.as_ref());
 
         let mut messages: Vec<ChatMessage> = conversation_history.to_vec();
         messages.push(ChatMessage {
             role: ChatRole::User,
-            content: inject_rag_into_content(user_content, rag_context.as_deref()),
+            content: inject_rag_into_content(
+                self.resume_after_question(session_id, user_content),
+                rag_context.as_deref(),
+            ),
         });
 
         let max_ctx = self.max_context_tokens.unwrap_or(100_000);
@@ -2996,15 +4044,20 @@ impl AgentRuntime {
 
         let mut full_response = String::new();
         let mut tool_call_count: usize = 0;
+        self.update_tool_confirmations(session_id, memory_text);
         let traj_turn_index = self.traj_advance_turn(session_id);
         for _iteration in 0..MAX_TOOL_ITERATIONS {
+            self.trace_iteration(session_id);
             let request = LlmRequest {
-                model: String::new(),
+                model: effective_model.to_string(),
                 messages: messages.clone(),
                 system: system.clone(),
-                max_tokens: Some(self.max_tokens.unwrap_or(4096)),
+                max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
                 temperature: None,
+                seed: self.seed,
                 tools: tool_defs.clone(),
+                response_format: None,
+                thinking: self.thinking_budget,
             };
 
             let stream_result = provider.stream_complete(&request).await;
@@ -3014,13 +4067,53 @@ impl AgentRuntime {
                     let mut response_text = String::new();
                     let mut tool_uses: Vec<(String, String, String)> = Vec::new();
                     let mut current_tool: Option<(String, String, String)> = None;
-                    let mut _stop_reason: Option<String> = None;
-
-                    while let Some(event) = stream.next().await {
-                        match event? {
+                    let mut thinking_blocks: Vec<ContentBlock> = Vec::new(); // in stream order
+                    let mut current_thinking: Option<(String, String)> = None;
+                    let mut stop_reason: Option<String> = None;
+                    let mut stream_usage: Option<Usage> = None;
+                    let mut interrupted = false;
+
+                    loop {
+                        // Stop as soon as the consumer goes away. Returning drops
+                        // `stream`, which drops the provider's HTTP response and
+                        // closes the connection so generation stops.
+                        let event = tokio::select! {
+                            biased;
+                            _ = delta_tx.closed() => return Err(stream_cancelled()),
+                            event = stream.next() => event,
+                        };
+                        let Some(event) = event else { break };
+                        let event = match event {
+                            Ok(event) => event,
+                            // Text already reached the user: keep it and end the
+                            // turn instead of failing it. Half-streamed tool calls
+                            // are dropped.
+                            Err(e) if !full_response.is_empty() || !response_text.is_empty() => {
+                                warn!("provider stream failed mid-response: {e}");
+                                let note = format!("\n\n{STREAM_INTERRUPTED_NOTE}");
+                                let _ = delta_tx.send(note.clone()).await;
+                                response_text.push_str(&note);
+                                tool_uses.clear();
+                                current_tool = None;
+                                interrupted = true;
+                                break;
+                            }
+                            Err(e) => {
+                                self.record_transcript(
+                                    session_id,
+                                    provider.as_ref(),
+                                    &request,
+                                    Err(&e),
+                                );
+                                return Err(e);
+                            }
+                        };
+                        match event {
                             StreamEvent::TextDelta(text) => {
                                 response_text.push_str(&text);
-                                let _ = delta_tx.send(text).await;
+                                if delta_tx.send(text).await.is_err() {
+                                    return Err(stream_cancelled());
+                                }
                             }
                             StreamEvent::ToolUseStart { id, name, .. } => {
                                 current_tool = Some((id, name, String::new()));
@@ -3030,7 +4123,25 @@ impl AgentRuntime {
                                     input.push_str(&json);
                                 }
                             }
+                            StreamEvent::ThinkingDelta(text) => {
+                                current_thinking.get_or_insert_default().0.push_str(&text);
+                            }
+                            StreamEvent::SignatureDelta(signature) => {
+                                current_thinking
+                                    .get_or_insert_default()
+                                    .1
+                                    .push_str(&signature);
+                            }
+                            StreamEvent::RedactedThinking(data) => {
+                                thinking_blocks.push(ContentBlock::RedactedThinking { data });
+                            }
                             StreamEvent::ContentBlockStop { .. } => {
+                                if let Some((thinking, signature)) = current_thinking.take() {
+                                    thinking_blocks.push(ContentBlock::Thinking {
+                                        thinking,
+                                        signature,
+                                    });
+                                }
                                 if let Some(tool) = current_tool.take() {
                                     tool_uses.push(tool);
                                 }
@@ -3044,7 +4155,7 @@ impl AgentRuntime {
                                 if let Some(tool) = current_tool.take() {
                                     tool_uses.push(tool);
                                 }
-                                _stop_reason = sr;
+                                stop_reason = sr;
                                 if let Some(u) = usage {
                                     self.accumulate_usage(
                                         session_id,
@@ -3053,6 +4164,7 @@ impl AgentRuntime {
                                         u.input_tokens,
                                         u.output_tokens,
                                     );
+                                    stream_usage = Some(u);
                                 }
                             }
                             StreamEvent::MessageStop => break,
@@ -3065,25 +4177,56 @@ impl AgentRuntime {
                         tool_uses.push(tool);
                     }
 
+                    if let Some((thinking, signature)) = current_thinking.take() {
+                        thinking_blocks.push(ContentBlock::Thinking {
+                            thinking,
+                            signature,
+                        });
+                    }
+                    for block in &thinking_blocks {
+                        if let ContentBlock::Thinking { thinking, .. } = block {
+                            self.record_reasoning(session_id, thinking);
+                        }
+                    }
+
+                    if self.transcripts.is_some() {
+                        let response = streamed_response(
+                            provider.configured_model().unwrap_or(""),
+                            &thinking_blocks,
+                            &response_text,
+                            &tool_uses,
+                            stop_reason.take(),
+                            stream_usage.take(),
+                        );
+                        self.record_transcript(
+                            session_id,
+                            provider.as_ref(),
+                            &request,
+                            Ok(&response),
+                        );
+                    }
+
                     if tool_uses.is_empty() {
                         full_response.push_str(&response_text);
 
                         // Post-completion reflection nudge.
-                        if let Some(followup) = self
-                            .skill_completion_followup(
-                                tool_call_count,
-                                skills.is_some(),
-                                NudgeContext {
-                                    provider: provider.as_ref(),
-                                    messages: &messages,
-                                    system: &system,
-                                    model: "",
-                                    max_tokens: self.max_tokens.unwrap_or(4096),
-                                    skills_content: skills.as_deref(),
-                                },
-                                session_id,
-                            )
-                            .await
+                        if !interrupted
+                            && let Some(followup) = self
+                                .skill_completion_followup(
+                                    tool_call_count,
+                                    skills.is_some(),
+                                    NudgeContext {
+                                        provider: provider.as_ref(),
+                                        messages: &messages,
+                                        system: &system,
+                                        model: effective_model,
+                                        max_tokens: self
+                                            .max_tokens_for(provider.provider_id(), None),
+                                        skills_content: skills.as_deref(),
+                                    },
+                                    session_id,
+                                )
+                                .await
                         {
                             let chunk = format!("\n\n{followup}");
                             let _ = delta_tx.send(chunk.clone()).await;
@@ -3109,7 +4252,8 @@ impl AgentRuntime {
                     // Count tool calls from this streaming-summarized iteration.
                     tool_call_count += tool_uses.len();
 
-                    let mut content_blocks = Vec::new();
+                    // Anthropic requires the thinking blocks back with the tool calls.
+                    let mut content_blocks: Vec<ContentBlock> = thinking_blocks;
                     if !response_text.is_empty() {
                         content_blocks.push(ContentBlock::Text {
                             text: response_text.clone(),
@@ -3132,25 +4276,43 @@ impl AgentRuntime {
                         content: MessagePart::Parts(content_blocks),
                     });
 
+                    let mut asked = None;
                     let mut tool_results = Vec::new();
                     for (id, name, input_json) in &tool_uses {
                         let input: serde_json::Value =
                             serde_json::from_str(input_json).unwrap_or_default();
-                        let context = ToolContext {
-                            session_id: session_id.to_string(),
-                            user_id: user_id.map(|s| s.to_string()),
-                            heartbeat_depth: 0,
-                            allowed_tools: self.session_allowed_tools(session_id),
-                        };
+                        let context = self.tool_context(session_id, user_id, continuity_key, 0);
+                        self.send_tool_status(&delta_tx, name).await;
                         let output = self
                             .run_tool(session_id, traj_turn_index, &context, name, &input)
                             .await;
+                        if name == ASK_USER_TOOL && !output.is_error {
+                            asked = Some((output.content.clone(), ask_user_options(&input)));
+                        }
                         tool_results.push(ContentBlock::ToolResult {
                             tool_use_id: id.clone(),
                             content: output.content,
+                            is_error: output.is_error,
                         });
                     }
 
+                    if let Some((question, options)) = asked {
+                        let question = self.end_turn_with_question(
+                            session_id,
+                            traj_turn_index,
+                            question,
+                            options,
+                        );
+                        let chunk = if full_response.is_empty() {
+                            question
+                        } else {
+                            format!("\n\n{question}")
+                        };
+                        let _ = delta_tx.send(chunk.clone()).await;
+                        full_response.push_str(&chunk);
+                        return Ok((full_response, new_summary));
+                    }
+
                     messages.push(ChatMessage {
                         role: ChatRole::User,
                         content: MessagePart::Parts(tool_results),
@@ -3162,7 +4324,9 @@ impl AgentRuntime {
                     }
                 }
                 Err(_) => {
-                    let response = provider.complete(&request).await?;
+                    let response = self
+                        .complete_for_session(session_id, provider.as_ref(), &request)
+                        .await?;
 
                     let has_tool_use = response
                         .content
@@ -3183,8 +4347,8 @@ impl AgentRuntime {
                                     provider: provider.as_ref(),
                                     messages: &messages,
                                     system: &system,
-                                    model: "",
-                                    max_tokens: self.max_tokens.unwrap_or(4096),
+                                    model: effective_model,
+                                    max_tokens: self.max_tokens_for(provider.provider_id(), None),
                                     skills_content: skills.as_deref(),
                                 },
                                 session_id,
@@ -3224,25 +4388,43 @@ impl AgentRuntime {
                         content: MessagePart::Parts(response.content.clone()),
                     });
 
+                    let mut asked = None;
                     let mut tool_results = Vec::new();
                     for block in &response.content {
                         if let ContentBlock::ToolUse { id, name, input } = block {
-                            let context = ToolContext {
-                                session_id: session_id.to_string(),
-                                user_id: user_id.map(|s| s.to_string()),
-                                heartbeat_depth: 0,
-                                allowed_tools: self.session_allowed_tools(session_id),
-                            };
+                            let context = self.tool_context(session_id, user_id, continuity_key, 0);
+                            self.send_tool_status(&delta_tx, name).await;
                             let output = self
                                 .run_tool(session_id, traj_turn_index, &context, name, input)
                                 .await;
+                            if name == ASK_USER_TOOL && !output.is_error {
+                                asked = Some((output.content.clone(), ask_user_options(input)));
+                            }
                             tool_results.push(ContentBlock::ToolResult {
                                 tool_use_id: id.clone(),
                                 content: output.content,
+                                is_error: output.is_error,
                             });
                         }
                     }
 
+                    if let Some((question, options)) = asked {
+                        let question = self.end_turn_with_question(
+                            session_id,
+                            traj_turn_index,
+                            question,
+                            options,
+                        );
+                        let chunk = if full_response.is_empty() {
+                            question
+                        } else {
+                            format!("\n\n{question}")
+                        };
+                        let _ = delta_tx.send(chunk.clone()).await;
+                        full_response.push_str(&chunk);
+                        return Ok((full_response, new_summary));
+                    }
+
                     messages.push(ChatMessage {
                         role: ChatRole::User,
                         content: MessagePart::Parts(tool_results),
@@ -3257,12 +4439,111 @@ impl AgentRuntime {
         )))
     }
 
-    pub async fn health_check_all(&self) -> Result<Vec<(String, bool)>> {
+    /// Ask the default provider for a short, user-facing summary of
+    /// `history`. `existing_summary` covers turns already compacted out of the
+    /// history, so the result still spans the whole conversation.
+    pub async fn summarize_conversation(
+        &self,
+        history: &[ChatMessage],
+        existing_summary: Option<&str>,
+    ) -> Result<String> {
+        let provider = self
+            .default_provider()
+            .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?;
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text(summary_transcript(existing_summary, history)),
+            }],
+            system: Some(
+                "Summarize this conversation for someone catching up on it. Cover the \
+                 main topics, decisions, and open questions in a few short bullet points."
+                    .to_string(),
+            ),
+            max_tokens: Some(500),
+            temperature: Some(0.0),
+            seed: self.seed,
+            tools: Vec::new(),
+            response_format: None,
+            thinking: None,
+        };
+        let response = provider.complete(&request).await?;
+        let summary = extract_text(&response.content);
+        if summary.trim().is_empty() {
+            return Err(Error::Agent("summary came back empty".into()));
+        }
+        Ok(summary)
+    }
+
+    /// Ask the default provider for an answer shaped by `format` and parse it
+    /// into `T`. The JSON is checked against the schema first, so a reply
+    /// that deserializes but breaks the schema is still rejected. This is a
+    /// single tool-free call outside the session history.
+    pub async fn process_structured<T: serde::de::DeserializeOwned>(
+        &self,
+        session_id: &str,
+        prompt: &str,
+        format: ResponseFormat,
+    ) -> Result<T> {
+        let provider = self
+            .default_provider()
+            .ok_or_else(|| Error::Agent("no LLM provider configured".into()))?;
+
+        // Providers without a native JSON mode only have the prompt to go on.
+        let ResponseFormat::JsonSchema { schema, .. } = &format;
+        let instruction = format!(
+            "Respond with only a JSON value matching this JSON Schema, without \
+             code fences or commentary:\n{schema}"
+        );
+        let system = match &self.system_prompt {
+            Some(base) => format!("{base}\n\n{instruction}"),
+            None => instruction,
+        };
+
+        let request = LlmRequest {
+            model: String::new(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text(prompt.to_string()),
+            }],
+            system: Some(system),
+            max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
+            temperature: None,
+            seed: self.seed,
+            tools: Vec::new(),
+            response_format: Some(format.clone()),
+            thinking: None,
+        };
+
+        let response = provider.complete(&request).await?;
+        if let Some(usage) = &response.usage {
+            self.accumulate_usage(
+                session_id,
+                provider.provider_id(),
+                &response.model,
+                usage.input_tokens,
+                usage.output_tokens,
+            );
+        }
+
+        let text = extract_text(&response.content);
+        let value: serde_json::Value = serde_json::from_str(strip_code_fence(&text))
+            .map_err(|e| Error::Agent(format!("structured response is not valid JSON: {e}")))?;
+        format
+            .validate(&value)
+            .map_err(|e| Error::Agent(format!("structured response does not match schema: {e}")))?;
+        serde_json::from_value(value)
+            .map_err(|e| Error::Agent(format!("failed to parse structured response: {e}")))
+    }
+
+    /// Run every provider's health check. An error carries the provider's
+    /// explanation, e.g. that a local server is still loading its model.
+    pub async fn health_check_all(&self) -> Result<Vec<(String, Result<bool>)>> {
         let providers: Vec<Arc<dyn LlmProvider>> = self.providers.read().unwrap().clone();
         let checks = providers.iter().map(|provider| async {
             let provider_id = provider.provider_id().to_string();
-            let ok = provider.health_check().await.unwrap_or(false);
-            (provider_id, ok)
+            (provider_id, provider.health_check().await)
         });
 
         Ok(join_all(checks).await)
@@ -3290,6 +4571,7 @@ impl AgentRuntime {
                 embedding: None,
                 embedding_model: None,
                 metadata: serde_json::json!({ "kind": event }),
+                attachments: Vec::new(),
             })
             .await?;
 
@@ -3455,9 +4737,12 @@ impl AgentRuntime {
             model: String::new(),
             messages,
             system,
-            max_tokens: Some(self.max_tokens.unwrap_or(4096)),
+            max_tokens: Some(self.max_tokens_for(provider.provider_id(), None)),
             temperature: None,
+            seed: self.seed,
             tools: vec![], // structurally no tools — prevents FileRead/Bash from firing
+            response_format: None,
+            thinking: None,
         };
 
         let response = provider.complete(&request).await?;
@@ -3509,6 +4794,11 @@ fn estimate_tokens(
                         ContentBlock::ToolUse { input, .. } => chars += input.to_string().len(),
                         ContentBlock::ToolResult { content, .. } => chars += content.len(),
                         ContentBlock::Image { .. } => chars += 1000,
+                        ContentBlock::Document { text, .. } => {
+                            chars += text.as_ref().map_or(1000, String::len)
+                        }
+                        ContentBlock::Thinking { thinking, .. } => chars += thinking.len(),
+                        ContentBlock::RedactedThinking { data } => chars += data.len(),
                     }
                 }
             }
@@ -3577,36 +4867,7 @@ async fn compact_messages(
         return None;
     }
 
-    // Extract messages to summarize
-    let to_summarize: Vec<&ChatMessage> = messages[..drop_count].iter().collect();
-    let mut summary_input = String::new();
-    if let Some(existing) = existing_summary {
-        summary_input.push_str("Previous summary:\n");
-        summary_input.push_str(existing);
-        summary_input.push_str("\n\n");
-    }
-    summary_input.push_str("Recent conversation to incorporate:\n");
-    for msg in &to_summarize {
-        let role = match msg.role {
-            ChatRole::User => "User",
-            ChatRole::Assistant => "Assistant",
-            ChatRole::System => "System",
-            ChatRole::Tool => "Tool",
-        };
-        let text = match &msg.content {
-            MessagePart::Text(t) => t.clone(),
-            MessagePart::Parts(parts) => parts
-                .iter()
-                .filter_map(|p| match p {
-                    ContentBlock::Text { text } => Some(text.as_str()),
-                    ContentBlock::ToolResult { content, .. } => Some(content.as_str()),
-                    _ => None,
-                })
-                .collect::<Vec<_>>()
-                .join(" "),
-        };
-        summary_input.push_str(&format!("{role}: {text}\n"));
-    }
+    let summary_input = summary_transcript(existing_summary, &messages[..drop_count]);
 
     let summarize_request = LlmRequest {
         model: String::new(),
@@ -3621,7 +4882,10 @@ async fn compact_messages(
         ),
         max_tokens: Some(500),
         temperature: Some(0.0),
+        seed: None,
         tools: Vec::new(),
+        response_format: None,
+        thinking: None,
     };
 
     match provider.complete(&summarize_request).await {
@@ -3650,12 +4914,46 @@ async fn compact_messages(
     }
 }
 
-/// The bootstrap instruction injected when no dna.md exists yet.
-/// The agent will ask the user a few questions and write dna.md itself.
-/// Build the bootstrap instruction with the resolved config directory path.
-/// We can't use a const because `~` doesn't expand in file paths and the
-/// home directory must be resolved at runtime.
-fn self_learning_guidance() -> String {
+/// Render messages as a `Role: text` transcript for summarization, preceded by
+/// the previous summary when there is one. Images and tool calls are skipped.
+fn summary_transcript(existing_summary: Option<&str>, messages: &[ChatMessage]) -> String {
+    let mut summary_input = String::new();
+    if let Some(existing) = existing_summary {
+        summary_input.push_str("Previous summary:\n");
+        summary_input.push_str(existing);
+        summary_input.push_str("\n\n");
+    }
+    summary_input.push_str("Recent conversation to incorporate:\n");
+    for msg in messages {
+        let role = match msg.role {
+            ChatRole::User => "User",
+            ChatRole::Assistant => "Assistant",
+            ChatRole::System => "System",
+            ChatRole::Tool => "Tool",
+        };
+        let text = match &msg.content {
+            MessagePart::Text(t) => t.clone(),
+            MessagePart::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    ContentBlock::ToolResult { content, .. } => Some(content.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        summary_input.push_str(&format!("{role}: {text}\n"));
+    }
+    summary_input
+}
+
+/// The bootstrap instruction injected when no dna.md exists yet.
+/// The agent will ask the user a few questions and write dna.md itself.
+/// Build the bootstrap instruction with the resolved config directory path.
+/// We can't use a const because `~` doesn't expand in file paths and the
+/// home directory must be resolved at runtime.
+fn self_learning_guidance() -> String {
     "## Self-Learning\n\
      You can persist reusable knowledge using `create_skill`. \
      Consider it **proactively** after completing a multi-step workflow — \
@@ -3719,6 +5017,59 @@ fn bootstrap_instruction() -> String {
     )
 }
 
+/// Render recalled memories as a bulleted list for the system prompt.
+fn format_memory_context(entries: &[MemoryEntry]) -> String {
+    let context: Vec<String> = entries.iter().map(format_memory_entry).collect();
+    format!("Relevant context from memory:\n- {}", context.join("\n- "))
+}
+
+/// Render a recalled memory entry for the system prompt, including references
+/// to any attached files so the model can point the user back to them, and
+/// where and when the memory was recorded so it can judge how current it is.
+fn format_memory_entry(entry: &MemoryEntry) -> String {
+    let mut rendered = entry.content.clone();
+    if !entry.attachments.is_empty() {
+        let refs: Vec<String> = entry
+            .attachments
+            .iter()
+            .map(|a| {
+                format!(
+                    "[attachment: {} shared {}, stored at {}]",
+                    a.filename,
+                    entry.created_at.format("%Y-%m-%d %H:%M UTC"),
+                    a.path.display()
+                )
+            })
+            .collect();
+        rendered.push(' ');
+        rendered.push_str(&refs.join(" "));
+    }
+    rendered.push(' ');
+    rendered.push_str(&memory_provenance(entry));
+    rendered
+}
+
+/// `[remembered on <date>, said by <role> via <channel>, session <id>]`
+fn memory_provenance(entry: &MemoryEntry) -> String {
+    let mut parts = vec![format!(
+        "remembered on {}",
+        entry.created_at.format("%Y-%m-%d %H:%M UTC")
+    )];
+    let role = match entry.role {
+        MemoryRole::User => Some("user"),
+        MemoryRole::Assistant => Some("assistant"),
+        MemoryRole::System | MemoryRole::Tool => None,
+    };
+    match (role, entry.channel_id.as_deref()) {
+        (Some(role), Some(channel)) => parts.push(format!("said by {role} via {channel}")),
+        (Some(role), None) => parts.push(format!("said by {role}")),
+        (None, Some(channel)) => parts.push(format!("via {channel}")),
+        (None, None) => {}
+    }
+    parts.push(format!("session {}", entry.session_id));
+    format!("[{}]", parts.join(", "))
+}
+
 /// Build the system prompt by combining all layers:
 /// 1. Base system prompt + tool guidance (from effective_system_prompt)
 /// 2. DNA content (personality)
@@ -3765,7 +5116,9 @@ fn build_system_prompt(
             "## Relevant memories from past conversations\n\
              The following was recalled from previous sessions with this user. \
              Use it for context and personalisation, but prefer document sources \
-             when they contradict each other.\n\n{ctx}"
+             when they contradict each other. Each memory ends with when and where \
+             it was recorded; for time-sensitive questions prefer recent memories, \
+             and when you rely on one you may cite it as \"(remembered on <date>)\".\n\n{ctx}"
         ));
     }
     if let Some(summary) = session_summary {
@@ -3802,6 +5155,31 @@ fn inject_rag_into_content(user_content: MessagePart, rag_context: Option<&str>)
     }
 }
 
+/// Returns true when the user's message is a short confirmation of a pending action.
+fn is_confirmation_reply(text: &str) -> bool {
+    let normalized = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+    matches!(
+        normalized.as_str(),
+        "yes" | "y" | "confirm" | "confirmed" | "/confirm" | "go ahead" | "do it" | "proceed"
+    )
+}
+
+/// Drop a Markdown code fence wrapped around a JSON answer, if any.
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(inner) = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return trimmed;
+    };
+    // Skip the language tag on the opening fence line, e.g. "json".
+    match inner.split_once('\n') {
+        Some((tag, body)) if !tag.trim_start().starts_with(['{', '[']) => body.trim(),
+        _ => inner.trim(),
+    }
+}
+
 fn extract_text(content: &[ContentBlock]) -> String {
     content
         .iter()
@@ -3932,6 +5310,74 @@ fn skill_embed_text(skill: &opencrust_skills::SkillDefinition) -> String {
     parts.join(" | ")
 }
 
+/// Truncate a tool input or output to [`TRACE_SNIPPET_CHARS`] for a turn trace.
+fn trace_snippet(text: &str) -> String {
+    match text.char_indices().nth(TRACE_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Built-in status line shown while a tool runs.
+fn default_tool_status(name: &str) -> String {
+    let status = match name {
+        "web_search" => "Searching the web...",
+        "web_fetch" => "Fetching the page...",
+        "bash" => "Running command...",
+        "file_read" => "Reading file...",
+        "file_write" => "Writing file...",
+        "file_patch" => "Editing file...",
+        "search_files" => "Searching files...",
+        "doc_search" | "list_documents" => "Checking documents...",
+        "memory" => "Checking memory...",
+        "send_message" => "Sending message...",
+        "generate_image" => "Drawing image...",
+        "handoff" => "Handing off...",
+        "create_skill" => "Saving skill...",
+        "ask_user" => "Preparing a question...",
+        "schedule_heartbeat" | "schedule_message" | "cancel_heartbeat" | "list_heartbeats" => {
+            "Updating reminders..."
+        }
+        _ => return format!("Running {name}..."),
+    };
+    status.to_string()
+}
+
+/// Rebuild the provider response from a consumed stream, for transcripts.
+fn streamed_response(
+    model: &str,
+    thinking: &[ContentBlock],
+    text: &str,
+    tool_uses: &[(String, String, String)],
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+) -> LlmResponse {
+    let mut content = thinking.to_vec();
+    if !text.is_empty() {
+        content.push(ContentBlock::Text {
+            text: text.to_string(),
+        });
+    }
+    for (id, name, input_json) in tool_uses {
+        content.push(ContentBlock::ToolUse {
+            id: id.clone(),
+            name: name.clone(),
+            input: serde_json::from_str(input_json).unwrap_or_default(),
+        });
+    }
+    LlmResponse {
+        content,
+        model: model.to_string(),
+        usage,
+        stop_reason,
+    }
+}
+
+/// Error returned when the delta receiver is dropped mid-stream (session cancelled).
+fn stream_cancelled() -> Error {
+    Error::Agent("stream cancelled: response consumer dropped".into())
+}
+
 /// Format an owned slice of `SkillDefinition`s into the prompt block injected into the
 /// system prompt. Mirrors `build_skill_block` in bootstrap.rs.
 fn skill_prompt_block(skills: &[opencrust_skills::SkillDefinition]) -> String {
@@ -3948,7 +5394,8 @@ fn skill_prompt_block_refs(skills: &[&opencrust_skills::SkillDefinition]) -> Str
     for skill in skills {
         block.push_str(&format!(
             "\n## {}\n{}\n",
-            skill.frontmatter.name, skill.frontmatter.description
+            opencrust_skills::template::escape(&skill.frontmatter.name),
+            opencrust_skills::template::escape(&skill.frontmatter.description)
         ));
         if !skill.frontmatter.triggers.is_empty() {
             block.push_str(&format!(
@@ -3957,7 +5404,7 @@ fn skill_prompt_block_refs(skills: &[&opencrust_skills::SkillDefinition]) -> Str
             ));
         }
         block.push('\n');
-        block.push_str(&skill.body);
+        block.push_str(&skill.prompt_body());
         block.push('\n');
     }
     block
@@ -4189,6 +5636,32 @@ mod tests {
         assert!(runtime.session_user_name("drop").is_none());
     }
 
+    #[test]
+    fn retain_tool_results_removes_evicted() {
+        let runtime = AgentRuntime::new();
+        for id in ["keep", "drop"] {
+            runtime
+                .tool_results
+                .entry(id.to_string())
+                .or_default()
+                .push(("chart".into(), serde_json::json!({"id": id})));
+        }
+        runtime.retain_tool_results(|id| id == "keep");
+        assert_eq!(runtime.take_tool_results("keep").len(), 1);
+        assert!(runtime.take_tool_results("drop").is_empty());
+    }
+
+    #[test]
+    fn retain_session_reasoning_removes_evicted() {
+        let mut runtime = AgentRuntime::new();
+        runtime.set_thinking(1024, true);
+        runtime.record_reasoning("keep", "think");
+        runtime.record_reasoning("drop", "think");
+        runtime.retain_session_reasoning(|id| id == "keep");
+        assert_eq!(runtime.take_reasoning("keep").as_deref(), Some("think"));
+        assert!(runtime.take_reasoning("drop").is_none());
+    }
+
     #[test]
     fn dna_content_set_and_get() {
         let runtime = AgentRuntime::new();
@@ -4663,6 +6136,7 @@ mod tests {
                 rationale: None,
                 triggers: triggers.into_iter().map(|t| t.to_string()).collect(),
                 dependencies: Vec::new(),
+                template: false,
                 version: None,
                 license: None,
                 compatibility: None,
@@ -5776,4 +7250,1172 @@ mod tests {
             .block_on(rt.compress_old_trajectories(90));
         assert_eq!(result, 0);
     }
+
+    /// Tool that records the `ToolContext` it was executed with.
+    struct ContextProbeTool {
+        seen: Arc<Mutex<Vec<ToolContext>>>,
+    }
+    #[async_trait::async_trait]
+    impl Tool for ContextProbeTool {
+        fn name(&self) -> &str {
+            "probe"
+        }
+        fn description(&self) -> &str {
+            "records its context"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        async fn execute(
+            &self,
+            context: &ToolContext,
+            _input: serde_json::Value,
+        ) -> Result<ToolOutput> {
+            self.seen.lock().unwrap().push(context.clone());
+            Ok(ToolOutput::success("ok"))
+        }
+    }
+
+    /// Provider that calls the `probe` tool once and then answers with text.
+    struct ProbeCallingProvider {
+        call_count: std::sync::atomic::AtomicUsize,
+    }
+    #[async_trait::async_trait]
+    impl LlmProvider for ProbeCallingProvider {
+        fn provider_id(&self) -> &str {
+            "probe-caller"
+        }
+        async fn complete(&self, _request: &LlmRequest) -> Result<crate::providers::LlmResponse> {
+            let round = self
+                .call_count
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let content = if round == 0 {
+                vec![ContentBlock::ToolUse {
+                    id: "tu_probe".to_string(),
+                    name: "probe".to_string(),
+                    input: serde_json::json!({}),
+                }]
+            } else {
+                vec![ContentBlock::Text {
+                    text: "done".to_string(),
+                }]
+            };
+            Ok(crate::providers::LlmResponse {
+                content,
+                model: String::new(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn tools_receive_session_and_user_context() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(ProbeCallingProvider {
+            call_count: std::sync::atomic::AtomicUsize::new(0),
+        }));
+        runtime.register_tool(Box::new(ContextProbeTool { seen: seen.clone() }));
+        runtime.set_workspace_root(dir.path().to_path_buf());
+
+        let reply = runtime
+            .process_message_with_context("sess-1", "hi", &[], Some("ck-7"), Some("user-42"))
+            .await
+            .unwrap();
+        assert_eq!(reply, "done");
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        let ctx = &seen[0];
+        assert_eq!(ctx.session_id, "sess-1");
+        assert_eq!(ctx.user_id.as_deref(), Some("user-42"));
+        assert_eq!(ctx.continuity_key.as_deref(), Some("ck-7"));
+        let workspace = ctx.workspace_dir.as_ref().expect("workspace set");
+        assert_eq!(workspace, &dir.path().join("sess-1"));
+        assert!(workspace.is_dir());
+    }
+
+    /// Destructive tool that counts how many times it actually ran.
+    struct DestructiveProbeTool {
+        runs: Arc<std::sync::atomic::AtomicUsize>,
+    }
+    #[async_trait::async_trait]
+    impl Tool for DestructiveProbeTool {
+        fn name(&self) -> &str {
+            "wipe"
+        }
+        fn description(&self) -> &str {
+            "pretends to delete things"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        fn is_destructive(&self) -> bool {
+            true
+        }
+        async fn execute(
+            &self,
+            _context: &ToolContext,
+            _input: serde_json::Value,
+        ) -> Result<ToolOutput> {
+            self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(ToolOutput::success("wiped"))
+        }
+    }
+
+    fn runtime_with_destructive_tool(
+        confirm: bool,
+    ) -> (AgentRuntime, Arc<std::sync::atomic::AtomicUsize>) {
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut runtime = AgentRuntime::new();
+        runtime.register_tool(Box::new(DestructiveProbeTool { runs: runs.clone() }));
+        runtime.set_confirm_destructive(confirm);
+        (runtime, runs)
+    }
+
+    #[tokio::test]
+    async fn destructive_tool_requires_confirmation() {
+        let (runtime, runs) = runtime_with_destructive_tool(true);
+        let ctx = runtime.tool_context("s", None, None, 0);
+        let input = serde_json::json!({"path": "/tmp/x"});
+
+        runtime.update_tool_confirmations("s", "delete /tmp/x please");
+        let first = runtime.run_tool("s", 0, &ctx, "wipe", &input).await;
+        assert!(first.content.starts_with("[dry run] would run: wipe"));
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        runtime.update_tool_confirmations("s", "Yes!");
+        let second = runtime.run_tool("s", 1, &ctx, "wipe", &input).await;
+        assert_eq!(second.content, "wiped");
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // The approval is single-use.
+        let third = runtime.run_tool("s", 1, &ctx, "wipe", &input).await;
+        assert!(third.content.starts_with("[dry run]"));
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    /// Tool that returns a small image attachment.
+    struct DrawTool;
+    #[async_trait::async_trait]
+    impl Tool for DrawTool {
+        fn name(&self) -> &str {
+            "draw"
+        }
+        fn description(&self) -> &str {
+            "draws a picture"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        async fn execute(
+            &self,
+            _context: &ToolContext,
+            _input: serde_json::Value,
+        ) -> Result<ToolOutput> {
+            Ok(
+                ToolOutput::success("drawn").with_attachment(MediaAttachment::new(
+                    opencrust_common::MediaKind::Photo,
+                    b"PNG".to_vec(),
+                )),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_attachments_are_kept_only_for_delivering_sessions() {
+        let mut runtime = AgentRuntime::new();
+        runtime.register_tool(Box::new(DrawTool));
+        let input = serde_json::json!({});
+
+        let ctx = runtime.tool_context("ws", None, None, 0);
+        let output = runtime.run_tool("ws", 0, &ctx, "draw", &input).await;
+        assert!(output.content.contains("cannot send it to the user"));
+        assert!(runtime.take_tool_attachments("ws").is_empty());
+
+        runtime.deliver_tool_attachments("chat");
+        let ctx = runtime.tool_context("chat", None, None, 0);
+        let output = runtime.run_tool("chat", 0, &ctx, "draw", &input).await;
+        assert!(output.content.contains("sent to the user with your reply"));
+        runtime.run_tool("chat", 0, &ctx, "draw", &input).await;
+        runtime.retain_tool_attachments(|id| id == "chat");
+        assert_eq!(runtime.take_tool_attachments("chat").len(), 2);
+
+        runtime.run_tool("chat", 1, &ctx, "draw", &input).await;
+        runtime.retain_tool_attachments(|_| false);
+        assert!(runtime.take_tool_attachments("chat").is_empty());
+        runtime.run_tool("chat", 2, &ctx, "draw", &input).await;
+        assert!(runtime.take_tool_attachments("chat").is_empty());
+    }
+
+    #[tokio::test]
+    async fn unrelated_message_discards_pending_confirmation() {
+        let (runtime, runs) = runtime_with_destructive_tool(true);
+        let ctx = runtime.tool_context("s", None, None, 0);
+        let input = serde_json::json!({});
+
+        runtime.run_tool("s", 0, &ctx, "wipe", &input).await;
+        runtime.update_tool_confirmations("s", "actually, never mind");
+        runtime.update_tool_confirmations("s", "yes");
+        let output = runtime.run_tool("s", 1, &ctx, "wipe", &input).await;
+        assert!(output.content.starts_with("[dry run]"));
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn destructive_tool_runs_immediately_when_confirmation_disabled() {
+        let (runtime, runs) = runtime_with_destructive_tool(false);
+        let ctx = runtime.tool_context("s", None, None, 0);
+        let output = runtime
+            .run_tool("s", 0, &ctx, "wipe", &serde_json::json!({}))
+            .await;
+        assert_eq!(output.content, "wiped");
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn confirmation_reply_detection() {
+        assert!(is_confirmation_reply("yes"));
+        assert!(is_confirmation_reply("  Go ahead. "));
+        assert!(is_confirmation_reply("/confirm"));
+        assert!(!is_confirmation_reply("yes, but change the path first"));
+        assert!(!is_confirmation_reply("no"));
+    }
+
+    #[test]
+    fn language_instruction_injected_only_when_enabled() {
+        let spanish = "Hola, ¿podrías ayudarme a escribir una nota corta para mi casero sobre la calefacción rota?";
+        let mut runtime = AgentRuntime::new();
+        let plain = runtime.base_prompt_for_input(spanish).unwrap();
+        assert!(!plain.contains("Respond in"));
+
+        runtime.set_language_detection(HashMap::new());
+        let localized = runtime.base_prompt_for_input(spanish).unwrap();
+        assert!(localized.starts_with(&plain));
+        assert!(localized.ends_with(
+            "The user is writing in Spanish. Respond in Spanish unless they ask for another language."
+        ));
+
+        // Too short to detect reliably: prompt stays as-is.
+        assert_eq!(runtime.base_prompt_for_input("ok").unwrap(), plain);
+    }
+
+    #[tokio::test]
+    async fn shared_attachment_is_persisted_and_surfaced_in_recall() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(opencrust_db::MemoryStore::in_memory().unwrap());
+        let mut runtime = AgentRuntime::new();
+        runtime.set_memory_provider(store.clone());
+        runtime.set_attachments_dir(dir.path().to_path_buf());
+
+        let attachment = runtime
+            .remember_attachment(
+                "sess",
+                Some("ck"),
+                Some("user-1"),
+                "budget-spreadsheet.csv",
+                Some("text/csv"),
+                b"month,total\njan,42\n",
+            )
+            .await
+            .unwrap()
+            .expect("attachment should be stored");
+        assert!(attachment.path.starts_with(dir.path()));
+        assert!(attachment.path.exists());
+
+        let recalled = store
+            .recall(RecallQuery {
+                query_text: Some("spreadsheet".to_string()),
+                query_embedding: None,
+                session_id: None,
+                session_ids: None,
+                continuity_key: Some("ck".to_string()),
+                limit: 5,
+            })
+            .await
+            .unwrap();
+        assert_eq!(recalled.len(), 1);
+        let rendered = format_memory_entry(&recalled[0]);
+        assert!(rendered.starts_with("User shared file: budget-spreadsheet.csv (text/csv"));
+        assert!(rendered.contains(&format!("stored at {}", attachment.path.display())));
+    }
+
+    #[tokio::test]
+    async fn continuity_key_recalls_across_sessions() {
+        let mut runtime = AgentRuntime::new();
+        runtime.set_memory_provider(Arc::new(opencrust_db::MemoryStore::in_memory().unwrap()));
+        runtime
+            .remember_turn(
+                "telegram-1",
+                Some("bus:user:1"),
+                Some("1"),
+                "my sister lives in Porto",
+                "noted",
+            )
+            .await
+            .unwrap();
+
+        let scoped = runtime
+            .recall_context("porto", Some("discord-1"), None, 5)
+            .await
+            .unwrap();
+        assert!(scoped.is_empty());
+        let shared = runtime
+            .recall_context("porto", Some("discord-1"), Some("bus:user:1"), 5)
+            .await
+            .unwrap();
+        assert!(shared.iter().any(|e| e.content.contains("Porto")));
+    }
+
+    #[tokio::test]
+    async fn tool_results_are_collected_per_session() {
+        struct ProbeTool;
+        #[async_trait::async_trait]
+        impl crate::tools::Tool for ProbeTool {
+            fn name(&self) -> &str {
+                "probe"
+            }
+            fn description(&self) -> &str {
+                "probe"
+            }
+            fn input_schema(&self) -> serde_json::Value {
+                serde_json::json!({"type": "object"})
+            }
+            async fn execute(
+                &self,
+                _context: &crate::tools::ToolContext,
+                _input: serde_json::Value,
+            ) -> Result<crate::tools::ToolOutput> {
+                Ok(crate::tools::ToolOutput::success("found 1")
+                    .with_data(serde_json::json!({"hits": 1})))
+            }
+        }
+
+        struct ProbeProvider {
+            calls: std::sync::atomic::AtomicUsize,
+        }
+        #[async_trait::async_trait]
+        impl LlmProvider for ProbeProvider {
+            fn provider_id(&self) -> &str {
+                "probe"
+            }
+            async fn complete(
+                &self,
+                _request: &LlmRequest,
+            ) -> Result<crate::providers::LlmResponse> {
+                let round = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let content = if round == 0 {
+                    vec![ContentBlock::ToolUse {
+                        id: "tu_1".to_string(),
+                        name: "probe".to_string(),
+                        input: serde_json::json!({}),
+                    }]
+                } else {
+                    vec![ContentBlock::Text {
+                        text: "done".to_string(),
+                    }]
+                };
+                Ok(crate::providers::LlmResponse {
+                    content,
+                    model: String::new(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+            async fn health_check(&self) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(ProbeProvider {
+            calls: std::sync::atomic::AtomicUsize::new(0),
+        }));
+        runtime.register_tool(Box::new(ProbeTool));
+
+        let reply = runtime.process_message("sess", "look", &[]).await.unwrap();
+        assert_eq!(reply, "done");
+        assert!(runtime.take_tool_results("other").is_empty());
+        assert_eq!(
+            runtime.take_tool_results("sess"),
+            vec![("probe".to_string(), serde_json::json!({"hits": 1}))]
+        );
+        assert!(runtime.take_tool_results("sess").is_empty());
+    }
+
+    #[tokio::test]
+    async fn attachment_memory_disabled_without_directory() {
+        let mut runtime = AgentRuntime::new();
+        runtime.set_memory_provider(Arc::new(opencrust_db::MemoryStore::in_memory().unwrap()));
+        let stored = runtime
+            .remember_attachment("sess", None, None, "a.txt", None, b"hi")
+            .await
+            .unwrap();
+        assert!(stored.is_none());
+    }
+
+    #[tokio::test]
+    async fn reload_skills_picks_up_new_skill_without_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("greet.md"),
+            "---\nname: greet\ndescription: Greeting skill\n---\nSay hello to the user.",
+        )
+        .unwrap();
+        let mut runtime = AgentRuntime::new();
+        runtime.set_skills_dir(dir.path().to_path_buf());
+
+        assert_eq!(runtime.reload_skills().await.unwrap(), 1);
+        let content = runtime.skills_content().unwrap();
+        assert!(content.contains("greet"));
+        assert!(!content.contains("farewell"));
+
+        let folder = dir.path().join("farewell");
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(
+            folder.join("SKILL.md"),
+            "---\nname: farewell\ndescription: Goodbye skill\n---\nSay goodbye.",
+        )
+        .unwrap();
+
+        assert_eq!(runtime.reload_skills().await.unwrap(), 2);
+        let content = runtime.skills_content().unwrap();
+        assert!(content.contains("greet"));
+        assert!(content.contains("farewell"));
+    }
+
+    #[tokio::test]
+    async fn reload_skills_skips_reindex_when_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("greet.md"),
+            "---\nname: greet\ndescription: Greeting skill\n---\nSay hello to the user.",
+        )
+        .unwrap();
+        let mut runtime = AgentRuntime::new();
+        runtime.set_skills_dir(dir.path().to_path_buf());
+        runtime.reload_skills().await.unwrap();
+
+        // A manual override survives a no-op reload because nothing is re-indexed.
+        runtime.set_skills_content(Some("manual block".to_string()));
+        assert_eq!(runtime.reload_skills().await.unwrap(), 1);
+        assert_eq!(runtime.skills_content().as_deref(), Some("manual block"));
+    }
+
+    #[tokio::test]
+    async fn reload_skills_without_dir_is_noop() {
+        let runtime = AgentRuntime::new();
+        assert_eq!(runtime.reload_skills().await.unwrap(), 0);
+        assert!(runtime.skills_content().is_none());
+    }
+
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Streams text deltas forever; records how many were produced and when the
+    /// stream (standing in for the HTTP response) is dropped.
+    struct EndlessStreamProvider {
+        produced: Arc<std::sync::atomic::AtomicUsize>,
+        dropped: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for EndlessStreamProvider {
+        fn provider_id(&self) -> &str {
+            "endless"
+        }
+        async fn complete(&self, _request: &LlmRequest) -> Result<crate::providers::LlmResponse> {
+            Err(Error::Agent("streaming only".into()))
+        }
+        async fn stream_complete(
+            &self,
+            _request: &LlmRequest,
+        ) -> Result<std::pin::Pin<Box<dyn futures::Stream<Item = Result<StreamEvent>> + Send>>>
+        {
+            let state = (
+                Arc::clone(&self.produced),
+                DropFlag(Arc::clone(&self.dropped)),
+            );
+            Ok(Box::pin(futures::stream::unfold(
+                state,
+                |(produced, guard)| async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    let n = produced.fetch_add(1, Ordering::SeqCst);
+                    Some((
+                        Ok(StreamEvent::TextDelta(format!("chunk {n} "))),
+                        (produced, guard),
+                    ))
+                },
+            )))
+        }
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    /// Streams `deltas`, then fails as if the connection dropped.
+    struct FailingStreamProvider {
+        deltas: Vec<&'static str>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for FailingStreamProvider {
+        fn provider_id(&self) -> &str {
+            "failing-stream"
+        }
+        async fn complete(&self, _request: &LlmRequest) -> Result<crate::providers::LlmResponse> {
+            Err(Error::Agent("streaming only".into()))
+        }
+        async fn stream_complete(
+            &self,
+            _request: &LlmRequest,
+        ) -> Result<std::pin::Pin<Box<dyn futures::Stream<Item = Result<StreamEvent>> + Send>>>
+        {
+            let events = self
+                .deltas
+                .iter()
+                .map(|d| Ok(StreamEvent::TextDelta(d.to_string())))
+                .chain(std::iter::once(Err(Error::Agent(
+                    "connection reset by peer".into(),
+                ))))
+                .collect::<Vec<_>>();
+            Ok(Box::pin(futures::stream::iter(events)))
+        }
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn mid_stream_error_keeps_partial_text() {
+        let runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(FailingStreamProvider {
+            deltas: vec!["Hello", " world"],
+        }));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let reply = runtime
+            .process_message_streaming("s", "hi", &[], tx)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Hello world\n\n[connection interrupted]");
+
+        let mut streamed = String::new();
+        while let Ok(delta) = rx.try_recv() {
+            streamed.push_str(&delta);
+        }
+        assert_eq!(streamed, reply);
+
+        let (tx, _rx) = mpsc::channel(16);
+        let (reply, _summary) = runtime
+            .process_message_streaming_with_context_and_summary(
+                "s",
+                "hi",
+                &[],
+                tx,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply, "Hello world\n\n[connection interrupted]");
+    }
+
+    #[tokio::test]
+    async fn stream_error_before_any_text_is_returned() {
+        let runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(FailingStreamProvider { deltas: vec![] }));
+
+        let (tx, _rx) = mpsc::channel(16);
+        let err = runtime
+            .process_message_streaming("s", "hi", &[], tx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("connection reset by peer"));
+    }
+
+    #[tokio::test]
+    async fn dropping_stream_consumer_stops_provider_stream() {
+        let produced = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let runtime = Arc::new(AgentRuntime::new());
+        runtime.register_provider(Arc::new(EndlessStreamProvider {
+            produced: Arc::clone(&produced),
+            dropped: Arc::clone(&dropped),
+        }));
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let task = tokio::spawn({
+            let runtime = Arc::clone(&runtime);
+            async move { runtime.process_message_streaming("s", "hi", &[], tx).await }
+        });
+        assert!(rx.recv().await.is_some());
+        assert!(rx.recv().await.is_some());
+        drop(rx);
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), task)
+            .await
+            .expect("runtime should stop once the consumer is gone")
+            .unwrap();
+        assert!(result.unwrap_err().to_string().contains("stream cancelled"));
+        assert!(
+            dropped.load(Ordering::SeqCst),
+            "provider stream should be dropped"
+        );
+
+        let after = produced.load(Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(produced.load(Ordering::SeqCst), after);
+    }
+
+    #[tokio::test]
+    async fn turn_trace_records_tool_calls_only_when_requested() {
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(ProbeCallingProvider {
+            call_count: std::sync::atomic::AtomicUsize::new(0),
+        }));
+        runtime.register_tool(Box::new(ContextProbeTool {
+            seen: Arc::new(Mutex::new(Vec::new())),
+        }));
+
+        runtime.begin_turn_trace("traced");
+        runtime
+            .process_message_with_context("traced", "hi", &[], None, None)
+            .await
+            .unwrap();
+        let trace = runtime.take_turn_trace("traced").expect("trace recorded");
+        assert_eq!(trace.iterations, 2);
+        assert_eq!(trace.tool_calls.len(), 1);
+        assert_eq!(trace.tool_calls[0].name, "probe");
+        assert_eq!(trace.tool_calls[0].input, "{}");
+        assert_eq!(trace.tool_calls[0].output, "ok");
+        assert!(!trace.tool_calls[0].is_error);
+        assert!(runtime.take_turn_trace("traced").is_none());
+
+        runtime
+            .process_message_with_context("plain", "hi", &[], None, None)
+            .await
+            .unwrap();
+        assert!(runtime.take_turn_trace("plain").is_none());
+    }
+
+    #[test]
+    fn trace_snippet_truncates_on_char_boundary() {
+        let long = "é".repeat(TRACE_SNIPPET_CHARS + 10);
+        let snippet = trace_snippet(&long);
+        assert_eq!(snippet.chars().count(), TRACE_SNIPPET_CHARS + 3);
+        assert!(snippet.ends_with("..."));
+        assert_eq!(trace_snippet("short"), "short");
+    }
+
+    /// Tool named `probe` that records the deltas already delivered when it runs.
+    struct StatusProbeTool {
+        rx: Arc<Mutex<mpsc::Receiver<String>>>,
+        seen: Arc<Mutex<Vec<String>>>,
+    }
+    #[async_trait::async_trait]
+    impl Tool for StatusProbeTool {
+        fn name(&self) -> &str {
+            "probe"
+        }
+        fn description(&self) -> &str {
+            "records streamed deltas"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        async fn execute(
+            &self,
+            _context: &ToolContext,
+            _input: serde_json::Value,
+        ) -> Result<ToolOutput> {
+            let mut rx = self.rx.lock().unwrap();
+            while let Ok(delta) = rx.try_recv() {
+                self.seen.lock().unwrap().push(delta);
+            }
+            Ok(ToolOutput::success("ok"))
+        }
+    }
+
+    async fn deltas_seen_by_tool(runtime: &mut AgentRuntime) -> Vec<String> {
+        let (tx, rx) = mpsc::channel(16);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        runtime.register_provider(Arc::new(ProbeCallingProvider {
+            call_count: std::sync::atomic::AtomicUsize::new(0),
+        }));
+        runtime.register_tool(Box::new(StatusProbeTool {
+            rx: Arc::new(Mutex::new(rx)),
+            seen: seen.clone(),
+        }));
+        runtime
+            .process_message_streaming("s", "hi", &[], tx)
+            .await
+            .unwrap();
+        seen.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn tool_status_is_streamed_before_the_tool_runs() {
+        let mut runtime = AgentRuntime::new();
+        runtime.set_tool_status_messages(HashMap::from([(
+            "probe".to_string(),
+            "Probing...".to_string(),
+        )]));
+        assert_eq!(
+            deltas_seen_by_tool(&mut runtime).await,
+            vec!["Probing...\n\n".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_status_is_silent_by_default() {
+        let mut runtime = AgentRuntime::new();
+        assert!(deltas_seen_by_tool(&mut runtime).await.is_empty());
+    }
+
+    #[test]
+    fn tool_status_falls_back_to_built_in_lines() {
+        let mut runtime = AgentRuntime::new();
+        assert_eq!(runtime.tool_status_message("web_search"), None);
+        runtime.set_tool_status_messages(HashMap::from([(
+            "bash".to_string(),
+            "Working on it...".to_string(),
+        )]));
+        assert_eq!(
+            runtime.tool_status_message("web_search").as_deref(),
+            Some("Searching the web...")
+        );
+        assert_eq!(
+            runtime.tool_status_message("bash").as_deref(),
+            Some("Working on it...")
+        );
+        assert_eq!(
+            runtime.tool_status_message("mcp_lookup").as_deref(),
+            Some("Running mcp_lookup...")
+        );
+    }
+
+    fn memory_entry(role: MemoryRole, channel_id: Option<&str>, content: &str) -> MemoryEntry {
+        use chrono::TimeZone;
+        MemoryEntry {
+            id: "m1".to_string(),
+            session_id: "telegram-42".to_string(),
+            channel_id: channel_id.map(str::to_string),
+            user_id: Some("user-1".to_string()),
+            continuity_key: None,
+            role,
+            content: content.to_string(),
+            embedding: None,
+            embedding_model: None,
+            embedding_dimensions: None,
+            metadata: serde_json::json!({}),
+            attachments: Vec::new(),
+            created_at: chrono::Utc.with_ymd_and_hms(2026, 3, 14, 9, 30, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn recalled_memory_includes_date_channel_and_session() {
+        let entry = memory_entry(MemoryRole::User, Some("telegram"), "My flight is on Friday");
+        assert_eq!(
+            format_memory_entry(&entry),
+            "My flight is on Friday [remembered on 2026-03-14 09:30 UTC, \
+             said by user via telegram, session telegram-42]"
+        );
+    }
+
+    #[test]
+    fn recalled_memory_without_channel_keeps_date_and_session() {
+        let entry = memory_entry(MemoryRole::Assistant, None, "Suggested the 9am train");
+        assert_eq!(
+            format_memory_entry(&entry),
+            "Suggested the 9am train [remembered on 2026-03-14 09:30 UTC, \
+             said by assistant, session telegram-42]"
+        );
+    }
+
+    #[test]
+    fn memory_context_lists_entries_and_prompt_explains_citation() {
+        let entries = vec![
+            memory_entry(MemoryRole::User, Some("slack"), "Prefers metric units"),
+            memory_entry(MemoryRole::User, Some("discord"), "Lives in Oslo"),
+        ];
+        let context = format_memory_context(&entries);
+        assert!(context.starts_with("Relevant context from memory:\n- Prefers metric units ["));
+        assert!(context.contains("\n- Lives in Oslo [remembered on 2026-03-14"));
+
+        let prompt =
+            build_system_prompt(None, None, None, None, Some(&context), None, None).unwrap();
+        assert!(prompt.contains("(remembered on <date>)"));
+        assert!(prompt.contains("via discord, session telegram-42]"));
+    }
+
+    #[test]
+    fn max_tokens_for_prefers_override_then_provider_then_global() {
+        let mut runtime = AgentRuntime::new();
+        assert_eq!(runtime.max_tokens_for("any", None), DEFAULT_MAX_TOKENS);
+
+        runtime.set_max_tokens(2048);
+        runtime.set_provider_max_tokens("big", 16000);
+        assert_eq!(runtime.max_tokens_for("big", None), 16000);
+        assert_eq!(runtime.max_tokens_for("other", None), 2048);
+        assert_eq!(runtime.max_tokens_for("big", Some(512)), 512);
+    }
+
+    #[tokio::test]
+    async fn process_message_uses_provider_max_tokens() {
+        struct CaptureProvider {
+            max_tokens: std::sync::Arc<std::sync::Mutex<Option<u32>>>,
+        }
+        #[async_trait::async_trait]
+        impl LlmProvider for CaptureProvider {
+            fn provider_id(&self) -> &str {
+                "big"
+            }
+            async fn complete(
+                &self,
+                request: &LlmRequest,
+            ) -> Result<crate::providers::LlmResponse> {
+                *self.max_tokens.lock().unwrap() = request.max_tokens;
+                Ok(crate::providers::LlmResponse {
+                    content: vec![ContentBlock::Text {
+                        text: "ok".to_string(),
+                    }],
+                    model: String::new(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+            async fn health_check(&self) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let captured = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(CaptureProvider {
+            max_tokens: captured.clone(),
+        }));
+        runtime.set_max_tokens(1024);
+        runtime.set_provider_max_tokens("big", 16000);
+
+        runtime.process_message("sess", "hello", &[]).await.unwrap();
+        assert_eq!(*captured.lock().unwrap(), Some(16000));
+    }
+
+    #[tokio::test]
+    async fn process_message_sends_no_tools_to_tool_less_provider() {
+        struct NoToolsProvider {
+            tool_count: std::sync::Arc<std::sync::Mutex<Option<usize>>>,
+        }
+        #[async_trait::async_trait]
+        impl LlmProvider for NoToolsProvider {
+            fn provider_id(&self) -> &str {
+                "plain"
+            }
+            fn supports_tools(&self) -> bool {
+                false
+            }
+            async fn complete(
+                &self,
+                request: &LlmRequest,
+            ) -> Result<crate::providers::LlmResponse> {
+                *self.tool_count.lock().unwrap() = Some(request.tools.len());
+                Ok(crate::providers::LlmResponse {
+                    content: vec![ContentBlock::Text {
+                        text: "plain answer".to_string(),
+                    }],
+                    model: String::new(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+            async fn health_check(&self) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let tool_count = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let dir = tempfile::TempDir::new().unwrap();
+        let runtime = runtime_with_create_skill_tool(dir.path());
+        runtime.register_provider(Arc::new(NoToolsProvider {
+            tool_count: tool_count.clone(),
+        }));
+
+        let reply = runtime.process_message("sess", "hello", &[]).await.unwrap();
+        assert_eq!(reply, "plain answer");
+        assert_eq!(*tool_count.lock().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn render_skills_block_fills_template_skills_only() {
+        let mut greet = make_skill("greet", "Greet the user", vec![]);
+        greet.frontmatter.template = true;
+        greet.body = "Say hi to {{user_name}}.{{#if memories}} Mention: {{memories}}{{/if}}".into();
+        let mut literal = make_skill("ci", "CI help", vec![]);
+        literal.body = "Use ${{ secrets.TOKEN }}".into();
+
+        let runtime = AgentRuntime::new();
+        runtime.set_session_user_name("sess", "Alice");
+        let block = skill_prompt_block(&[greet, literal]);
+        let rendered = runtime
+            .render_skills_block(Some(block), "sess", Some("likes tea"))
+            .unwrap();
+        assert!(rendered.contains("Say hi to Alice. Mention: likes tea"));
+        assert!(rendered.contains("Use ${{ secrets.TOKEN }}"));
+    }
+
+    /// Provider that asks the user a question, then finishes once answered.
+    struct AskingProvider {
+        requests: Mutex<Vec<LlmRequest>>,
+    }
+    #[async_trait::async_trait]
+    impl LlmProvider for AskingProvider {
+        fn provider_id(&self) -> &str {
+            "asker"
+        }
+        async fn complete(&self, request: &LlmRequest) -> Result<crate::providers::LlmResponse> {
+            let round = {
+                let mut requests = self.requests.lock().unwrap();
+                requests.push(request.clone());
+                requests.len()
+            };
+            let content = if round == 1 {
+                vec![ContentBlock::ToolUse {
+                    id: "tu_ask".to_string(),
+                    name: ASK_USER_TOOL.to_string(),
+                    input: serde_json::json!({
+                        "question": "Deploy to staging or production?",
+                        "options": ["Staging", "Production"]
+                    }),
+                }]
+            } else {
+                vec![ContentBlock::Text {
+                    text: "Deploying to staging.".to_string(),
+                }]
+            };
+            Ok(crate::providers::LlmResponse {
+                content,
+                model: String::new(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn ask_user_ends_turn_and_next_message_resumes() {
+        let provider = Arc::new(AskingProvider {
+            requests: Mutex::new(Vec::new()),
+        });
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(provider.clone());
+        runtime.register_tool(Box::new(crate::tools::AskUserTool));
+
+        let reply = runtime
+            .process_message("sess", "deploy the app", &[])
+            .await
+            .unwrap();
+        assert_eq!(reply, "Deploy to staging or production?");
+        assert_eq!(provider.requests.lock().unwrap().len(), 1, "loop must stop");
+        assert_eq!(
+            runtime.pending_question("sess").as_deref(),
+            Some("Deploy to staging or production?")
+        );
+        assert_eq!(
+            runtime.pending_question_options("sess"),
+            ["Staging", "Production"]
+        );
+
+        let history = vec![
+            make_msg(ChatRole::User, "deploy the app"),
+            make_msg(ChatRole::Assistant, &reply),
+        ];
+        let reply = runtime
+            .process_message("sess", "staging", &history)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Deploying to staging.");
+        assert!(runtime.pending_question("sess").is_none());
+        assert!(runtime.pending_question_options("sess").is_empty());
+
+        let requests = provider.requests.lock().unwrap();
+        let MessagePart::Text(answer) = &requests[1].messages.last().unwrap().content else {
+            panic!("expected text user message");
+        };
+        assert!(answer.contains("Deploy to staging or production?"));
+        assert!(answer.ends_with("staging"));
+    }
+
+    #[tokio::test]
+    async fn process_structured_parses_and_validates_json() {
+        struct JsonProvider {
+            reply: &'static str,
+        }
+        #[async_trait::async_trait]
+        impl LlmProvider for JsonProvider {
+            fn provider_id(&self) -> &str {
+                "json"
+            }
+            async fn complete(
+                &self,
+                request: &LlmRequest,
+            ) -> Result<crate::providers::LlmResponse> {
+                assert!(request.response_format.is_some());
+                assert!(request.tools.is_empty());
+                Ok(crate::providers::LlmResponse {
+                    content: vec![ContentBlock::Text {
+                        text: self.reply.to_string(),
+                    }],
+                    model: String::new(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+            async fn health_check(&self) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Place {
+            city: String,
+            population: u64,
+        }
+
+        let format = ResponseFormat::json_schema(
+            "place",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "city": { "type": "string" },
+                    "population": { "type": "integer" },
+                },
+                "required": ["city", "population"],
+            }),
+        );
+
+        let runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(JsonProvider {
+            reply: "```json\n{\"city\": \"Oslo\", \"population\": 709000}\n```",
+        }));
+        let place: Place = runtime
+            .process_structured("sess", "Largest city in Norway?", format.clone())
+            .await
+            .unwrap();
+        assert_eq!(place.city, "Oslo");
+        assert_eq!(place.population, 709000);
+
+        let runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(JsonProvider {
+            reply: r#"{"city": "Oslo"}"#,
+        }));
+        let err = runtime
+            .process_structured::<Place>("sess", "Largest city in Norway?", format)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .contains("missing required property 'population'")
+        );
+    }
+
+    #[tokio::test]
+    async fn switch_session_model_routes_session_to_alias_target() {
+        struct EchoModelProvider {
+            id: &'static str,
+        }
+        #[async_trait::async_trait]
+        impl LlmProvider for EchoModelProvider {
+            fn provider_id(&self) -> &str {
+                self.id
+            }
+            async fn complete(
+                &self,
+                request: &LlmRequest,
+            ) -> Result<crate::providers::LlmResponse> {
+                Ok(crate::providers::LlmResponse {
+                    content: vec![ContentBlock::Text {
+                        text: format!("{}:{}", self.id, request.model),
+                    }],
+                    model: request.model.clone(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+            async fn health_check(&self) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let mut runtime = AgentRuntime::new();
+        runtime.register_provider(Arc::new(EchoModelProvider { id: "a" }));
+        runtime.register_provider(Arc::new(EchoModelProvider { id: "b" }));
+        runtime.set_model_alias(
+            "smart",
+            SessionModel {
+                provider: Some("b".to_string()),
+                model: Some("big".to_string()),
+            },
+        );
+        runtime.set_model_alias(
+            "fast",
+            SessionModel {
+                provider: None,
+                model: Some("small".to_string()),
+            },
+        );
+
+        let reply = |session: &'static str| {
+            let runtime = &runtime;
+            async move {
+                runtime
+                    .process_message_with_provider_and_summary(
+                        session,
+                        MessagePart::Text("hi".to_string()),
+                        "hi",
+                        &[],
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some("a"),
+                        Some("channel-model"),
+                    )
+                    .await
+                    .unwrap()
+                    .0
+            }
+        };
+
+        assert_eq!(reply("s1").await, "a:channel-model");
+
+        runtime.switch_session_model("s1", "smart").unwrap();
+        assert_eq!(reply("s1").await, "b:big");
+        assert_eq!(reply("s2").await, "a:channel-model");
+
+        runtime.switch_session_model("s1", "fast").unwrap();
+        assert_eq!(reply("s1").await, "a:small");
+
+        let err = runtime.switch_session_model("s1", "huge").unwrap_err();
+        assert!(err.to_string().contains("available: fast, smart"));
+
+        runtime.clear_session_model("s1");
+        assert_eq!(reply("s1").await, "a:channel-model");
+    }
 }
@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use opencrust_common::Result;
+
+use super::{Tool, ToolContext, ToolOutput};
+
+/// Tool name the runtime watches for to end a turn with a question.
+pub const ASK_USER_TOOL: &str = "ask_user";
+
+/// Most suggested answers kept from one `ask_user` call.
+const MAX_OPTIONS: usize = 8;
+
+/// Suggested answers from an `ask_user` input. Channels with inline buttons
+/// (e.g. Telegram) offer them as one-tap replies.
+pub fn ask_user_options(input: &serde_json::Value) -> Vec<String> {
+    input
+        .get("options")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .take(MAX_OPTIONS)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Pause the current task and ask the user a clarifying question.
+///
+/// The runtime ends the turn as soon as this tool succeeds and sends the
+/// question as the reply. The user's next message is then passed to the model
+/// as the answer, so it can pick the task up where it stopped.
+pub struct AskUserTool;
+
+#[async_trait]
+impl Tool for AskUserTool {
+    fn name(&self) -> &str {
+        ASK_USER_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Stop and ask the user a clarifying question. The current turn ends and the \
+         question is sent to the user; their next message is the answer and you can \
+         continue the task from there."
+    }
+
+    fn system_hint(&self) -> Option<&str> {
+        Some(
+            "Use ask_user when you cannot continue a multi-step task without information \
+             only the user has (a missing choice, credential, or ambiguous goal). Ask one \
+             concise question. Do not use it for normal replies or to confirm work that \
+             is already done.",
+        )
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question to send to the user"
+                },
+                "options": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Optional short answers to offer as buttons, for multiple-choice or yes/no questions"
+                }
+            },
+            "required": ["question"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        _context: &ToolContext,
+        input: serde_json::Value,
+    ) -> Result<ToolOutput> {
+        let question = input
+            .get("question")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .unwrap_or_default();
+        if question.is_empty() {
+            return Ok(ToolOutput::error("missing required parameter: question"));
+        }
+        Ok(ToolOutput::success(question))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> ToolContext {
+        ToolContext {
+            session_id: "s1".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_the_question() {
+        let output = AskUserTool
+            .execute(
+                &ctx(),
+                serde_json::json!({ "question": "  Which branch?  " }),
+            )
+            .await
+            .unwrap();
+        assert!(!output.is_error);
+        assert_eq!(output.content, "Which branch?");
+    }
+
+    #[tokio::test]
+    async fn empty_question_is_an_error() {
+        let output = AskUserTool
+            .execute(&ctx(), serde_json::json!({ "question": " " }))
+            .await
+            .unwrap();
+        assert!(output.is_error);
+    }
+
+    #[test]
+    fn options_are_trimmed_and_capped() {
+        let input = serde_json::json!({
+            "question": "Deploy?",
+            "options": [" Yes ", "", "No", 3, "a", "b", "c", "d", "e", "f", "g"]
+        });
+        let options = ask_user_options(&input);
+        assert_eq!(options.len(), MAX_OPTIONS);
+        assert_eq!(options[..2], ["Yes", "No"]);
+        assert!(ask_user_options(&serde_json::json!({ "question": "Why?" })).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use base64::Engine;
 use opencrust_common::{Error, Result};
 use std::time::Duration;
 use tokio::process::Command;
@@ -28,6 +29,10 @@ impl Tool for BashTool {
         "bash"
     }
 
+    fn is_destructive(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         if cfg!(target_os = "windows") {
             "Execute a shell command using PowerShell. Returns the output."
@@ -49,11 +54,7 @@ impl Tool for BashTool {
         })
     }
 
-    async fn execute(
-        &self,
-        _context: &ToolContext,
-        input: serde_json::Value,
-    ) -> Result<ToolOutput> {
+    async fn execute(&self, context: &ToolContext, input: serde_json::Value) -> Result<ToolOutput> {
         let command_str = input
             .get("command")
             .and_then(|v| v.as_str())
@@ -65,16 +66,19 @@ impl Tool for BashTool {
             ("bash", "-c")
         };
 
-        let result = tokio::time::timeout(
-            self.timeout,
-            Command::new(shell).arg(arg).arg(command_str).output(),
-        )
-        .await;
+        let mut command = Command::new(shell);
+        command.arg(arg).arg(command_str);
+        // Run inside the session workspace, like the file tools.
+        if let Some(dir) = &context.workspace_dir {
+            command.current_dir(dir);
+        }
+
+        let result = tokio::time::timeout(self.timeout, command.output()).await;
 
         match result {
             Ok(Ok(output)) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
+                let stdout = decode_output(&output.stdout);
+                let stderr = decode_output(&output.stderr);
 
                 let mut combined = String::new();
                 if !stdout.is_empty() {
@@ -88,9 +92,13 @@ impl Tool for BashTool {
                     combined.push_str(&stderr);
                 }
 
-                // Truncate if too large
+                // Truncate if too large, backing off to a char boundary
                 if combined.len() > MAX_OUTPUT_BYTES {
-                    combined.truncate(MAX_OUTPUT_BYTES);
+                    let mut end = MAX_OUTPUT_BYTES;
+                    while !combined.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    combined.truncate(end);
                     combined.push_str("\n... (output truncated)");
                 }
 
@@ -117,6 +125,32 @@ impl Tool for BashTool {
     }
 }
 
+/// Turn captured output bytes into text for the model. Valid UTF-8 passes
+/// through unchanged. Output that looks binary (any NUL byte, or more than a
+/// quarter of the bytes invalid) is base64-encoded; other invalid bytes are
+/// replaced with U+FFFD. Both cases are prefixed with a note saying so.
+fn decode_output(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+
+    let invalid: usize = bytes.utf8_chunks().map(|c| c.invalid().len()).sum();
+    if bytes.contains(&0) || invalid * 4 > bytes.len() {
+        // Only encode what can survive the output cap.
+        let shown = &bytes[..bytes.len().min(MAX_OUTPUT_BYTES / 4 * 3)];
+        return format!(
+            "(binary output, {} bytes, base64-encoded)\n{}",
+            bytes.len(),
+            base64::engine::general_purpose::STANDARD.encode(shown)
+        );
+    }
+
+    format!(
+        "(output was not valid UTF-8; {invalid} invalid byte(s) replaced)\n{}",
+        String::from_utf8_lossy(bytes)
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,6 +164,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let output = tool
             .execute(&ctx, serde_json::json!({"command": "echo hello"}))
@@ -139,6 +175,29 @@ mod tests {
         assert!(output.content.contains("hello"));
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn runs_in_session_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let tool = BashTool::new(None);
+        let ctx = ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: Some(dir.path().to_path_buf()),
+        };
+        let output = tool
+            .execute(&ctx, serde_json::json!({"command": "pwd -P"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            output.content.trim(),
+            dir.path().canonicalize().unwrap().to_str().unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn reports_error_on_failing_command() {
         let tool = BashTool::new(None);
@@ -152,6 +211,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let output = tool
             .execute(&ctx, serde_json::json!({"command": cmd}))
@@ -168,6 +229,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let result = tool.execute(&ctx, serde_json::json!({})).await;
         assert!(result.is_err());
@@ -186,6 +249,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let output = tool
             .execute(&ctx, serde_json::json!({"command": cmd}))
@@ -193,4 +258,65 @@ mod tests {
             .unwrap();
         assert!(output.content.contains("err"));
     }
+
+    #[test]
+    fn decode_output_handles_invalid_and_binary_bytes() {
+        assert_eq!(decode_output("héllo".as_bytes()), "héllo");
+
+        let lossy = decode_output(b"caf\xe9 au lait");
+        assert!(lossy.starts_with("(output was not valid UTF-8; 1 invalid byte(s) replaced)"));
+        assert!(lossy.ends_with("caf\u{FFFD} au lait"));
+
+        let binary = decode_output(&[0x89, b'P', b'N', b'G', 0, 0xff]);
+        assert!(binary.starts_with("(binary output, 6 bytes, base64-encoded)\n"));
+        assert!(binary.ends_with("iVBORwD/"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn invalid_utf8_output_is_returned_lossily() {
+        let tool = BashTool::new(None);
+        let ctx = ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        };
+        let output = tool
+            .execute(
+                &ctx,
+                serde_json::json!({"command": "printf 'ok \\377\\376 done'"}),
+            )
+            .await
+            .unwrap();
+        assert!(!output.is_error, "{}", output.content);
+        assert!(output.content.contains("invalid byte(s) replaced"));
+        assert!(output.content.contains("ok \u{FFFD}\u{FFFD} done"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn oversized_multibyte_output_is_truncated_on_a_char_boundary() {
+        let tool = BashTool::new(None);
+        let ctx = ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        };
+        // 3-byte chars after a 1-byte prefix never line up with the cap.
+        let output = tool
+            .execute(
+                &ctx,
+                serde_json::json!({"command": "printf x; head -c 40000 /dev/zero | tr '\\0' 'a' | sed 's/a/€/g'"}),
+            )
+            .await
+            .unwrap();
+        assert!(!output.is_error, "{}", output.content);
+        assert!(output.content.ends_with("... (output truncated)"));
+    }
 }
@@ -505,6 +505,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
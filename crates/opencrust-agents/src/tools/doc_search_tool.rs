@@ -202,6 +202,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let rt = tokio::runtime::Runtime::new().unwrap();
         let result = rt.block_on(tool.execute(&ctx, serde_json::json!({})));
@@ -218,6 +220,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let rt = tokio::runtime::Runtime::new().unwrap();
         let result = rt
@@ -269,6 +273,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let rt = tokio::runtime::Runtime::new().unwrap();
         let output = rt
@@ -48,6 +48,10 @@ impl Tool for FilePatchTool {
         "file_patch"
     }
 
+    fn is_destructive(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Apply a targeted find-and-replace edit to a file. \
          Safer than file_write for editing existing files — only the changed region is touched. \
@@ -89,11 +93,7 @@ impl Tool for FilePatchTool {
         })
     }
 
-    async fn execute(
-        &self,
-        _context: &ToolContext,
-        input: serde_json::Value,
-    ) -> Result<ToolOutput> {
+    async fn execute(&self, context: &ToolContext, input: serde_json::Value) -> Result<ToolOutput> {
         let path_str = input
             .get("path")
             .and_then(|v| v.as_str())
@@ -114,7 +114,7 @@ impl Tool for FilePatchTool {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
-        let path = PathBuf::from(path_str);
+        let path = context.resolve_path(path_str)?;
         self.validate_path(&path)?;
 
         let metadata = tokio::fs::metadata(&path)
@@ -176,6 +176,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -5,6 +5,8 @@ use std::path::PathBuf;
 use super::{Tool, ToolContext, ToolOutput};
 
 const MAX_READ_BYTES: u64 = 1024 * 1024; // 1MB
+/// Lines returned when the caller does not pass `limit`.
+const DEFAULT_MAX_LINES: usize = 2000;
 
 /// Read the contents of a file with path validation and size limits.
 pub struct FileReadTool {
@@ -40,6 +42,49 @@ impl FileReadTool {
     }
 }
 
+/// Language name for a source file, used as a highlighting hint for the model.
+fn language_for_path(path: &std::path::Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" => "bash",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "md" | "markdown" => "markdown",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+fn usize_param(input: &serde_json::Value, name: &str) -> Result<Option<usize>> {
+    match input.get(name) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(v) => v
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .ok_or_else(|| Error::Agent(format!("'{name}' must be a non-negative integer"))),
+    }
+}
+
 #[async_trait]
 impl Tool for FileReadTool {
     fn name(&self) -> &str {
@@ -47,7 +92,8 @@ impl Tool for FileReadTool {
     }
 
     fn description(&self) -> &str {
-        "Read the contents of a file at the given path."
+        "Read the contents of a file at the given path. Long files are returned in \
+         line ranges; use offset and limit to read further."
     }
 
     fn system_hint(&self) -> Option<&str> {
@@ -63,23 +109,27 @@ impl Tool for FileReadTool {
                 "path": {
                     "type": "string",
                     "description": "The file path to read"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "1-based line number to start reading from (default: 1)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of lines to return, at least 1 (default: 2000)"
                 }
             },
             "required": ["path"]
         })
     }
 
-    async fn execute(
-        &self,
-        _context: &ToolContext,
-        input: serde_json::Value,
-    ) -> Result<ToolOutput> {
+    async fn execute(&self, context: &ToolContext, input: serde_json::Value) -> Result<ToolOutput> {
         let path_str = input
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or_else(|| Error::Agent("missing 'path' parameter".into()))?;
 
-        let path = PathBuf::from(path_str);
+        let path = context.resolve_path(path_str)?;
         self.validate_path(&path)?;
 
         let metadata = tokio::fs::metadata(&path)
@@ -94,11 +144,43 @@ impl Tool for FileReadTool {
             )));
         }
 
+        let offset = usize_param(&input, "offset")?.unwrap_or(1).max(1);
+        let limit = usize_param(&input, "limit")?.unwrap_or(DEFAULT_MAX_LINES);
+        if limit == 0 {
+            return Ok(ToolOutput::error("'limit' must be at least 1"));
+        }
+
         let content = tokio::fs::read_to_string(&path)
             .await
             .map_err(|e| Error::Agent(format!("failed to read file: {e}")))?;
 
-        Ok(ToolOutput::success(content))
+        let total = content.lines().count();
+        if offset > 1 && offset > total {
+            return Ok(ToolOutput::error(format!(
+                "offset {offset} is past the end of the file ({total} lines)"
+            )));
+        }
+
+        let end = (offset - 1).saturating_add(limit).min(total);
+        let mut out = String::new();
+        if let Some(lang) = language_for_path(&path) {
+            out.push_str(&format!("[language: {lang}]\n"));
+        }
+        if offset == 1 && end == total {
+            out.push_str(&content);
+        } else {
+            let slice: Vec<&str> = content.lines().skip(offset - 1).take(limit).collect();
+            out.push_str(&slice.join("\n"));
+            if end < total {
+                out.push_str(&format!(
+                    "\n\n[truncated: showing lines {offset}-{end} of {total}. \
+                     Use offset={} to continue reading.]",
+                    end + 1
+                ));
+            }
+        }
+
+        Ok(ToolOutput::success(out))
     }
 }
 
@@ -119,6 +201,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let output = tool
             .execute(
@@ -139,6 +223,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let result = tool
             .execute(&ctx, serde_json::json!({"path": "/nonexistent/file.txt"}))
@@ -154,8 +240,173 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let result = tool.execute(&ctx, serde_json::json!({})).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn resolves_relative_path_in_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "from workspace").unwrap();
+
+        let tool = FileReadTool::new(None);
+        let ctx = ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: Some(dir.path().to_path_buf()),
+        };
+        let output = tool
+            .execute(&ctx, serde_json::json!({"path": "notes.txt"}))
+            .await
+            .unwrap();
+        assert_eq!(output.content, "from workspace");
+    }
+
+    fn test_ctx() -> ToolContext {
+        ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        }
+    }
+
+    fn numbered_lines(n: usize) -> String {
+        (1..=n)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tokio::test]
+    async fn offset_and_limit_select_line_range() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, numbered_lines(10)).unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap(), "offset": 3, "limit": 4}),
+            )
+            .await
+            .unwrap();
+        assert!(!output.is_error);
+        assert!(output.content.starts_with("line 3\nline 4\nline 5\nline 6"));
+        assert!(!output.content.contains("line 7"));
+        assert!(
+            output.content.contains(
+                "[truncated: showing lines 3-6 of 10. Use offset=7 to continue reading.]"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn long_files_are_capped_at_default_line_limit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("big.txt");
+        std::fs::write(&path, numbered_lines(DEFAULT_MAX_LINES + 5)).unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap()}),
+            )
+            .await
+            .unwrap();
+        assert!(
+            output
+                .content
+                .contains(&format!("line {DEFAULT_MAX_LINES}\n"))
+        );
+        assert!(
+            !output
+                .content
+                .contains(&format!("line {}", DEFAULT_MAX_LINES + 1))
+        );
+        assert!(output.content.contains(&format!(
+            "showing lines 1-{DEFAULT_MAX_LINES} of {}",
+            DEFAULT_MAX_LINES + 5
+        )));
+    }
+
+    #[tokio::test]
+    async fn reading_to_end_has_no_truncation_note() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, numbered_lines(10)).unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap(), "offset": 8}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.content, "line 8\nline 9\nline 10");
+    }
+
+    #[tokio::test]
+    async fn offset_past_end_is_an_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, numbered_lines(3)).unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap(), "offset": 10}),
+            )
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert!(output.content.contains("past the end"));
+    }
+
+    #[tokio::test]
+    async fn zero_limit_is_an_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, numbered_lines(3)).unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap(), "limit": 0}),
+            )
+            .await
+            .unwrap();
+        assert!(output.is_error);
+        assert!(output.content.contains("at least 1"));
+    }
+
+    #[tokio::test]
+    async fn source_files_get_language_hint() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("main.rs");
+        std::fs::write(&path, "fn main() {}\n").unwrap();
+
+        let tool = FileReadTool::new(None);
+        let output = tool
+            .execute(
+                &test_ctx(),
+                serde_json::json!({"path": path.to_str().unwrap()}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output.content, "[language: rust]\nfn main() {}\n");
+    }
 }
@@ -98,6 +98,10 @@ impl Tool for FileWriteTool {
         "file_write"
     }
 
+    fn is_destructive(&self) -> bool {
+        true
+    }
+
     fn description(&self) -> &str {
         "Write content to a file at the given path. Creates the file if it doesn't exist, overwrites if it does."
     }
@@ -119,11 +123,7 @@ impl Tool for FileWriteTool {
         })
     }
 
-    async fn execute(
-        &self,
-        _context: &ToolContext,
-        input: serde_json::Value,
-    ) -> Result<ToolOutput> {
+    async fn execute(&self, context: &ToolContext, input: serde_json::Value) -> Result<ToolOutput> {
         let path_str = input
             .get("path")
             .and_then(|v| v.as_str())
@@ -142,7 +142,7 @@ impl Tool for FileWriteTool {
             )));
         }
 
-        let path = PathBuf::from(path_str);
+        let path = context.resolve_path(path_str)?;
         self.validate_path(&path)?;
 
         // Create parent directories if needed
@@ -184,6 +184,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let output = tool
             .execute(
@@ -209,6 +211,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         assert!(tool.execute(&ctx, serde_json::json!({})).await.is_err());
         assert!(
@@ -231,6 +235,8 @@ mod tests {
                 user_id: None,
                 heartbeat_depth: 0,
                 allowed_tools: None,
+                continuity_key: None,
+                workspace_dir: None,
             };
             let output = tool
                 .execute(
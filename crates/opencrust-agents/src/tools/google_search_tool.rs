@@ -135,7 +135,19 @@ impl Tool for GoogleSearchTool {
             ));
         }
 
-        Ok(ToolOutput::success(output.trim_end()))
+        let data = serde_json::json!({
+            "query": query,
+            "results": results
+                .iter()
+                .map(|r| serde_json::json!({
+                    "title": r.title,
+                    "url": r.link,
+                    "description": r.snippet,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        Ok(ToolOutput::success(output.trim_end()).with_data(data))
     }
 }
 
@@ -151,6 +163,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -225,6 +239,9 @@ mod tests {
         assert!(result.content.contains("https://www.rust-lang.org"));
         assert!(result.content.contains("2. **Learn Rust**"));
         assert!(result.content.contains("https://doc.rust-lang.org/book/"));
+        let data = result.data.expect("structured results");
+        assert_eq!(data["query"], "rust programming");
+        assert_eq!(data["results"][0]["url"], "https://www.rust-lang.org");
     }
 
     #[tokio::test]
@@ -242,6 +242,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: depth,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use opencrust_common::{Error, MediaAttachment, MediaKind, Result};
+use std::sync::Arc;
+
+use super::{Tool, ToolContext, ToolOutput};
+use crate::image_gen::ImageProvider;
+
+const MAX_PROMPT_CHARS: usize = 4000;
+
+/// Generate an image from a text prompt. The image is returned as a tool
+/// attachment, which channels that can send media deliver as a photo.
+pub struct ImageGenTool {
+    provider: Arc<dyn ImageProvider>,
+}
+
+impl ImageGenTool {
+    pub fn new(provider: Arc<dyn ImageProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl Tool for ImageGenTool {
+    fn name(&self) -> &str {
+        "generate_image"
+    }
+
+    fn description(&self) -> &str {
+        "Generate an image (picture, illustration, diagram, logo) from a text description. \
+         Where the channel supports it, the image is sent to the user as a photo."
+    }
+
+    fn system_hint(&self) -> Option<&str> {
+        Some(
+            "Use generate_image when the user asks you to draw, create, or show a picture. \
+             Write a detailed visual prompt (subject, style, composition, colours); text inside \
+             images often renders poorly, so keep labels short. The tool result says whether \
+             the image reaches the user; do not paste links or base64, just describe what you made.",
+        )
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "prompt": {
+                    "type": "string",
+                    "description": "Detailed description of the image to generate"
+                },
+                "size": {
+                    "type": "string",
+                    "description": "Optional size as WIDTHxHEIGHT (e.g. \"1024x1024\", \"1792x1024\")"
+                }
+            },
+            "required": ["prompt"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        _context: &ToolContext,
+        input: serde_json::Value,
+    ) -> Result<ToolOutput> {
+        let prompt = input
+            .get("prompt")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Error::Agent("missing 'prompt' parameter".into()))?
+            .trim();
+        if prompt.is_empty() {
+            return Ok(ToolOutput::error("prompt cannot be empty"));
+        }
+        if prompt.chars().count() > MAX_PROMPT_CHARS {
+            return Ok(ToolOutput::error(format!(
+                "prompt is too long (max {MAX_PROMPT_CHARS} characters)"
+            )));
+        }
+        let size = input.get("size").and_then(|v| v.as_str());
+
+        let image = match self.provider.generate(prompt, size).await {
+            Ok(image) => image,
+            Err(e) => return Ok(ToolOutput::error(format!("image generation failed: {e}"))),
+        };
+
+        let extension = image.mime_type.rsplit('/').next().unwrap_or("png");
+        let attachment = MediaAttachment::new(MediaKind::Photo, image.data)
+            .with_filename(format!("image.{extension}"))
+            .with_mime_type(Some(image.mime_type));
+        let content = match image.revised_prompt {
+            Some(revised) => {
+                format!("Image generated. The provider rewrote the prompt as: {revised}")
+            }
+            None => "Image generated.".to_string(),
+        };
+        Ok(ToolOutput::success(content).with_attachment(attachment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_gen::GeneratedImage;
+
+    struct FakeImages;
+
+    #[async_trait]
+    impl ImageProvider for FakeImages {
+        fn provider_id(&self) -> &str {
+            "fake"
+        }
+
+        async fn generate(&self, prompt: &str, size: Option<&str>) -> Result<GeneratedImage> {
+            if prompt == "fail" {
+                return Err(Error::Agent("content policy".into()));
+            }
+            Ok(GeneratedImage {
+                data: format!("{prompt}@{}", size.unwrap_or("default")).into_bytes(),
+                mime_type: "image/png".to_string(),
+                revised_prompt: None,
+            })
+        }
+    }
+
+    fn ctx() -> ToolContext {
+        ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn attaches_generated_image_as_photo() {
+        let tool = ImageGenTool::new(Arc::new(FakeImages));
+        let output = tool
+            .execute(
+                &ctx(),
+                serde_json::json!({"prompt": "a red fox", "size": "512x512"}),
+            )
+            .await
+            .unwrap();
+
+        assert!(!output.is_error);
+        assert_eq!(output.attachments.len(), 1);
+        let image = &output.attachments[0];
+        assert_eq!(image.kind, MediaKind::Photo);
+        assert_eq!(image.data, b"a red fox@512x512");
+        assert_eq!(image.filename.as_deref(), Some("image.png"));
+    }
+
+    #[tokio::test]
+    async fn provider_failure_is_a_tool_error() {
+        let tool = ImageGenTool::new(Arc::new(FakeImages));
+        let output = tool
+            .execute(&ctx(), serde_json::json!({"prompt": "fail"}))
+            .await
+            .unwrap();
+
+        assert!(output.is_error);
+        assert!(output.content.contains("content policy"));
+        assert!(output.attachments.is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use opencrust_common::{Error, Result};
-use opencrust_db::{MemoryRole, MemoryStore, NewMemoryEntry, RecallQuery};
+use opencrust_db::{MemoryRole, MemoryStore, NewMemoryEntry, RecallQuery, SessionStore};
 use std::path::PathBuf;
 
 use super::{Tool, ToolContext, ToolOutput};
@@ -17,11 +17,36 @@ const MAX_CONTENT_BYTES: usize = 4096;
 /// be available in future conversations.
 pub struct MemoryTool {
     db_path: PathBuf,
+    sessions_db_path: Option<PathBuf>,
 }
 
 impl MemoryTool {
     pub fn new(db_path: PathBuf) -> Self {
-        Self { db_path }
+        Self {
+            db_path,
+            sessions_db_path: None,
+        }
+    }
+
+    /// Enable `tag` on recall by resolving session tags from this session
+    /// store database.
+    pub fn with_sessions_db(mut self, path: PathBuf) -> Self {
+        self.sessions_db_path = Some(path);
+        self
+    }
+
+    /// IDs of the sessions tagged `tag`.
+    fn tagged_sessions(&self, tag: &str) -> Result<Vec<String>> {
+        let path = self
+            .sessions_db_path
+            .as_ref()
+            .ok_or_else(|| Error::Agent("recall by tag needs the session store".into()))?;
+        let store = SessionStore::open(path)
+            .map_err(|e| Error::Agent(format!("failed to open session store: {e}")))?;
+        let tag = opencrust_db::normalize_tag(tag).unwrap_or_default();
+        store
+            .sessions_with_tag(&tag)
+            .map_err(|e| Error::Agent(format!("failed to look up tag: {e}")))
     }
 }
 
@@ -67,6 +92,10 @@ impl Tool for MemoryTool {
                 "limit": {
                     "type": "number",
                     "description": "Maximum number of notes to return for recall (1–50, default 10)"
+                },
+                "tag": {
+                    "type": "string",
+                    "description": "Only recall notes from conversations tagged with this name (set with /tag)"
                 }
             },
             "required": ["action"]
@@ -113,6 +142,7 @@ impl Tool for MemoryTool {
                     embedding: None,
                     embedding_model: None,
                     metadata: serde_json::json!({ "source": "explicit" }),
+                    attachments: Vec::new(),
                 };
 
                 let id = store
@@ -134,6 +164,11 @@ impl Tool for MemoryTool {
                     .map(|v| (v as usize).clamp(1, MAX_RECALL_LIMIT))
                     .unwrap_or(DEFAULT_RECALL_LIMIT);
 
+                let session_ids = match input.get("tag").and_then(|v| v.as_str()) {
+                    Some(tag) => Some(self.tagged_sessions(tag)?),
+                    None => None,
+                };
+
                 let store = MemoryStore::open(&self.db_path)
                     .map_err(|e| Error::Agent(format!("failed to open memory store: {e}")))?;
 
@@ -142,6 +177,7 @@ impl Tool for MemoryTool {
                         query_text: Some(query.to_string()),
                         query_embedding: None,
                         session_id: None,
+                        session_ids,
                         continuity_key: context.user_id.clone(),
                         limit,
                     })
@@ -196,6 +232,8 @@ mod tests {
             user_id: Some("user-1".into()),
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -281,4 +319,38 @@ mod tests {
         let result = tool.execute(&ctx(), serde_json::json!({})).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn recall_by_tag_only_returns_notes_from_tagged_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let sessions_db = dir.path().join("sessions.db");
+        let sessions = SessionStore::open(&sessions_db).unwrap();
+        sessions.tag_session("work-chat", "work").unwrap();
+        let tool =
+            MemoryTool::new(dir.path().join("memory.db")).with_sessions_db(sessions_db.clone());
+
+        for (session, note) in [
+            ("work-chat", "deploy freeze starts friday"),
+            ("home-chat", "freeze the leftover soup"),
+        ] {
+            let mut ctx = ctx();
+            ctx.session_id = session.into();
+            tool.execute(
+                &ctx,
+                serde_json::json!({ "action": "save", "content": note }),
+            )
+            .await
+            .unwrap();
+        }
+
+        let out = tool
+            .execute(
+                &ctx(),
+                serde_json::json!({ "action": "recall", "query": "freeze", "tag": "Work" }),
+            )
+            .await
+            .unwrap();
+        assert!(out.content.contains("deploy freeze"), "{}", out.content);
+        assert!(!out.content.contains("soup"), "{}", out.content);
+    }
 }
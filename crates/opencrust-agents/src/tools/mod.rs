@@ -1,3 +1,4 @@
+pub mod ask_user_tool;
 pub mod bash_tool;
 pub mod create_skill_tool;
 pub mod doc_search_tool;
@@ -6,6 +7,7 @@ pub mod file_read_tool;
 pub mod file_write_tool;
 pub mod google_search_tool;
 pub mod handoff_tool;
+pub mod image_gen_tool;
 pub mod list_documents_tool;
 pub mod memory_tool;
 pub mod schedule;
@@ -14,6 +16,7 @@ pub mod send_message_tool;
 pub mod web_fetch_tool;
 pub mod web_search_tool;
 
+pub use ask_user_tool::AskUserTool;
 pub use bash_tool::BashTool;
 pub use create_skill_tool::CreateSkillTool;
 pub use doc_search_tool::DocSearchTool;
@@ -22,17 +25,22 @@ pub use file_read_tool::FileReadTool;
 pub use file_write_tool::FileWriteTool;
 pub use google_search_tool::GoogleSearchTool;
 pub use handoff_tool::{HandoffHandle, HandoffTool};
+pub use image_gen_tool::ImageGenTool;
 pub use list_documents_tool::ListDocumentsTool;
 pub use memory_tool::MemoryTool;
-pub use schedule::{CancelHeartbeat, ListHeartbeats, ScheduleHeartbeat};
+pub use schedule::{
+    CancelHeartbeat, ListHeartbeats, ScheduleHeartbeat, ScheduleMessage, ScheduleMessageHandle,
+    ScheduleTargetCheck,
+};
 pub use search_files_tool::SearchFilesTool;
 pub use send_message_tool::{OutboundMessage, SendMessageHandle, SendMessageTool};
 pub use web_fetch_tool::WebFetchTool;
 pub use web_search_tool::WebSearchTool;
 
 use async_trait::async_trait;
-use opencrust_common::Result;
+use opencrust_common::{Error, MediaAttachment, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
 
 /// Context passed to tools during execution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +55,56 @@ pub struct ToolContext {
     /// Empty list means no tools are allowed; `None` means all tools are allowed.
     #[serde(default)]
     pub allowed_tools: Option<Vec<String>>,
+    /// Cross-channel continuity key for the caller, when shared memory is enabled.
+    #[serde(default)]
+    pub continuity_key: Option<String>,
+    /// Per-session working directory. When set, file tools resolve relative
+    /// paths against it and refuse paths outside it, and `bash` runs in it.
+    #[serde(default)]
+    pub workspace_dir: Option<PathBuf>,
+}
+
+impl ToolContext {
+    /// Resolve a user-supplied path against the session workspace. With a
+    /// workspace, the result must stay inside it once `..` and symlinks are
+    /// resolved, so absolute paths are only accepted when they point into it.
+    /// Without a workspace, paths pass through unchanged.
+    pub fn resolve_path(&self, path: &str) -> Result<PathBuf> {
+        let Some(dir) = &self.workspace_dir else {
+            return Ok(PathBuf::from(path));
+        };
+        let root = canonicalize_lenient(dir);
+        let resolved = canonicalize_lenient(&dir.join(path));
+        if !resolved.starts_with(&root) {
+            return Err(Error::Agent(format!(
+                "path '{path}' is outside the session workspace"
+            )));
+        }
+        Ok(resolved)
+    }
+}
+
+/// `path` with symlinks resolved for the part that exists. The missing rest
+/// (e.g. a file about to be written) cannot contain symlinks, so its `.` and
+/// `..` components are resolved lexically.
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    let components: Vec<Component> = path.components().collect();
+    for split in (1..=components.len()).rev() {
+        let head: PathBuf = components[..split].iter().collect();
+        if let Ok(mut resolved) = head.canonicalize() {
+            for component in &components[split..] {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::CurDir => {}
+                    other => resolved.push(other),
+                }
+            }
+            return resolved;
+        }
+    }
+    path.to_path_buf()
 }
 
 /// Trait for tools that agents can invoke (bash, browser, file operations, etc.).
@@ -60,6 +118,12 @@ pub trait Tool: Send + Sync {
     fn system_hint(&self) -> Option<&str> {
         None
     }
+    /// Whether the tool can modify the system (run commands, write files).
+    /// Destructive tools are previewed instead of executed when
+    /// `tools.confirm_destructive` is enabled.
+    fn is_destructive(&self) -> bool {
+        false
+    }
     /// Optional path hint for the directory where this tool stores skills.
     /// Only implemented by skill-management tools (e.g. CreateSkillTool).
     fn skills_dir_hint(&self) -> Option<std::path::PathBuf> {
@@ -71,6 +135,15 @@ pub trait Tool: Send + Sync {
 pub struct ToolOutput {
     pub content: String,
     pub is_error: bool,
+    /// Structured form of a successful result (search hits, file matches,
+    /// ...) for channels that can render it richly. The model only sees
+    /// `content`.
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+    /// Media produced by the tool (generated images, ...), delivered to the
+    /// user's chat after the turn. The model only sees `content`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<MediaAttachment>,
 }
 
 impl ToolOutput {
@@ -78,6 +151,8 @@ impl ToolOutput {
         Self {
             content: content.into(),
             is_error: false,
+            data: None,
+            attachments: Vec::new(),
         }
     }
 
@@ -85,13 +160,83 @@ impl ToolOutput {
         Self {
             content: content.into(),
             is_error: true,
+            data: None,
+            attachments: Vec::new(),
         }
     }
+
+    /// Attach the structured form of the result.
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Attach media to deliver to the user.
+    pub fn with_attachment(mut self, attachment: MediaAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ToolOutput;
+    use super::{ToolContext, ToolOutput};
+    use std::path::PathBuf;
+
+    fn ctx(workspace_dir: Option<PathBuf>) -> ToolContext {
+        ToolContext {
+            session_id: "s1".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir,
+        }
+    }
+
+    #[test]
+    fn resolve_path_joins_relative_paths_onto_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workspace = dir.path().canonicalize().unwrap();
+        let ctx = ctx(Some(dir.path().to_path_buf()));
+        assert_eq!(
+            ctx.resolve_path("notes.txt").unwrap(),
+            workspace.join("notes.txt")
+        );
+        assert_eq!(
+            ctx.resolve_path("drafts/../new/a.md").unwrap(),
+            workspace.join("new/a.md")
+        );
+        let inside = workspace.join("notes.txt");
+        assert_eq!(ctx.resolve_path(inside.to_str().unwrap()).unwrap(), inside);
+    }
+
+    #[test]
+    fn resolve_path_rejects_escapes_from_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ctx = ctx(Some(dir.path().join("s1")));
+        assert!(ctx.resolve_path("/etc/hosts").is_err());
+        assert!(ctx.resolve_path("../s2/notes.txt").is_err());
+        assert!(ctx.resolve_path("a/../../escape").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_path_rejects_symlinks_out_of_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workspace = dir.path().join("s1");
+        std::fs::create_dir(&workspace).unwrap();
+        std::os::unix::fs::symlink("/etc", workspace.join("etc")).unwrap();
+        assert!(ctx(Some(workspace)).resolve_path("etc/hosts").is_err());
+    }
+
+    #[test]
+    fn resolve_path_without_workspace_is_unchanged() {
+        assert_eq!(
+            ctx(None).resolve_path("notes.txt").unwrap(),
+            PathBuf::from("notes.txt")
+        );
+    }
 
     #[test]
     fn success_helper_sets_non_error_state() {
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use opencrust_common::{Error, Result};
 use opencrust_db::SessionStore;
 use serde_json::json;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use crate::tools::{Tool, ToolContext, ToolOutput};
 
@@ -18,6 +18,34 @@ const MAX_PENDING_PER_SESSION: i64 = 20;
 /// Maximum heartbeat chaining depth (0 = user request, 1-3 = heartbeat chains).
 const MAX_HEARTBEAT_DEPTH: u8 = 3;
 
+/// Parse an ISO 8601 local datetime (`2026-02-25T09:00:00`) in the IANA
+/// timezone `tz_name` (default UTC) and convert it to UTC.
+fn parse_local_datetime(
+    iso_str: &str,
+    tz_name: Option<&str>,
+) -> Result<chrono::DateTime<chrono::Utc>> {
+    let tz_name = tz_name.unwrap_or("UTC");
+    let tz: chrono_tz::Tz = tz_name
+        .parse()
+        .map_err(|_| Error::Agent(format!("unknown timezone: '{tz_name}'")))?;
+
+    let naive = chrono::NaiveDateTime::parse_from_str(iso_str, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(iso_str, "%Y-%m-%dT%H:%M:%S%.f"))
+        .map_err(|e| {
+            Error::Agent(format!(
+                "invalid datetime format '{iso_str}'. Use ISO 8601 like '2026-02-25T09:00:00': {e}"
+            ))
+        })?;
+
+    let local_dt = naive.and_local_timezone(tz).single().ok_or_else(|| {
+        Error::Agent(format!(
+            "ambiguous or invalid datetime '{iso_str}' in timezone '{tz_name}'"
+        ))
+    })?;
+
+    Ok(local_dt.with_timezone(&chrono::Utc))
+}
+
 // ---------------------------------------------------------------------------
 // ScheduleHeartbeat
 // ---------------------------------------------------------------------------
@@ -108,28 +136,7 @@ impl Tool for ScheduleHeartbeat {
 
         // Resolve execution time: execute_at_iso + timezone takes precedence over delay_seconds
         let execute_at = if let Some(iso_str) = args["execute_at_iso"].as_str() {
-            let tz_name = args["timezone"].as_str().unwrap_or("UTC");
-            let tz: chrono_tz::Tz = tz_name
-                .parse()
-                .map_err(|_| Error::Agent(format!("unknown timezone: '{tz_name}'")))?;
-
-            let naive = chrono::NaiveDateTime::parse_from_str(iso_str, "%Y-%m-%dT%H:%M:%S")
-                .or_else(|_| {
-                    chrono::NaiveDateTime::parse_from_str(iso_str, "%Y-%m-%dT%H:%M:%S%.f")
-                })
-                .map_err(|e| {
-                    Error::Agent(format!(
-                        "invalid datetime format '{iso_str}'. Use ISO 8601 like '2026-02-25T09:00:00': {e}"
-                    ))
-                })?;
-
-            let local_dt = naive.and_local_timezone(tz).single().ok_or_else(|| {
-                Error::Agent(format!(
-                    "ambiguous or invalid datetime '{iso_str}' in timezone '{tz_name}'"
-                ))
-            })?;
-
-            let utc_dt = local_dt.with_timezone(&chrono::Utc);
+            let utc_dt = parse_local_datetime(iso_str, args["timezone"].as_str())?;
             if utc_dt <= chrono::Utc::now() {
                 return Err(Error::Agent(
                     "execute_at_iso must be in the future".to_string(),
@@ -209,7 +216,10 @@ impl Tool for ScheduleHeartbeat {
         // Enforce per-session pending task limit
         let pending = self
             .store
-            .count_pending_tasks_for_session(&context.session_id)?;
+            .count_pending_tasks_for_session(&context.session_id)?
+            + self
+                .store
+                .count_pending_messages_for_session(&context.session_id)?;
         if pending >= MAX_PENDING_PER_SESSION {
             return Err(Error::Agent(format!(
                 "session already has {} pending heartbeats (max {})",
@@ -252,6 +262,167 @@ impl Tool for ScheduleHeartbeat {
     }
 }
 
+// ---------------------------------------------------------------------------
+// ScheduleMessage
+// ---------------------------------------------------------------------------
+
+/// Decides whether the caller may schedule a message to another chat on the
+/// named channel, returning the reason when not.
+pub type ScheduleTargetCheck =
+    Arc<dyn Fn(&ToolContext, &str) -> std::result::Result<(), String> + Send + Sync>;
+
+/// Tool for scheduling a message that is sent as-is at a given time: a
+/// reminder in the current session, or a message to another recipient when
+/// `channel` and `to` are given.
+///
+/// Messages to other recipients are refused until the gateway wires a
+/// [`ScheduleTargetCheck`] through [`ScheduleMessageHandle::wire`], since
+/// only it knows the configured channels and who owns the bot.
+pub struct ScheduleMessage {
+    store: Arc<SessionStore>,
+    targets: Arc<OnceLock<ScheduleTargetCheck>>,
+}
+
+impl ScheduleMessage {
+    /// Create the tool and the handle used to wire its target check.
+    pub fn new(store: Arc<SessionStore>) -> (Self, ScheduleMessageHandle) {
+        let targets = Arc::new(OnceLock::new());
+        let tool = Self {
+            store,
+            targets: Arc::clone(&targets),
+        };
+        (tool, ScheduleMessageHandle { targets })
+    }
+}
+
+/// Returned by `ScheduleMessage::new()`.
+pub struct ScheduleMessageHandle {
+    targets: Arc<OnceLock<ScheduleTargetCheck>>,
+}
+
+impl ScheduleMessageHandle {
+    /// Allow messages to other recipients that pass `check`. Safe to call
+    /// only once; later calls are ignored.
+    pub fn wire(&self, check: ScheduleTargetCheck) {
+        let _ = self.targets.set(check);
+    }
+}
+
+#[async_trait]
+impl Tool for ScheduleMessage {
+    fn name(&self) -> &'static str {
+        "schedule_message"
+    }
+
+    fn description(&self) -> &'static str {
+        "Send the user a message at a future time, in this conversation. Use this for \
+         reminders like 'remind me tomorrow at 9 to call mom'. The text is delivered \
+         exactly as written, so phrase it for the user (e.g. 'Time to call mom!'). \
+         To message someone else or another chat, also give 'channel' and 'to'; \
+         only the owner may do this."
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "when": {
+                    "type": "string",
+                    "description": "ISO 8601 datetime to send the message (e.g. '2026-02-25T09:00:00'). Must be in the future and within 30 days."
+                },
+                "timezone": {
+                    "type": "string",
+                    "description": "IANA timezone name used to interpret 'when' (e.g. 'Europe/London'). Always provide this when the user mentions a timezone. Defaults to 'UTC'."
+                },
+                "text": {
+                    "type": "string",
+                    "description": "The message to send."
+                },
+                "channel": {
+                    "type": "string",
+                    "description": "Name of a connected channel (e.g. 'telegram', 'slack') to send on instead of this conversation. Requires 'to'. Owner only."
+                },
+                "to": {
+                    "type": "string",
+                    "description": "Platform-native chat or user ID to send to on 'channel'."
+                }
+            },
+            "required": ["when", "text"]
+        })
+    }
+
+    async fn execute(&self, context: &ToolContext, args: serde_json::Value) -> Result<ToolOutput> {
+        let text = args["text"]
+            .as_str()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| Error::Agent("missing or invalid 'text' argument".to_string()))?;
+        let when = args["when"]
+            .as_str()
+            .ok_or_else(|| Error::Agent("missing or invalid 'when' argument".to_string()))?;
+
+        let execute_at = parse_local_datetime(when, args["timezone"].as_str())?;
+        let now = chrono::Utc::now();
+        if execute_at <= now {
+            return Err(Error::Agent("'when' must be in the future".to_string()));
+        }
+        if execute_at > now + chrono::Duration::seconds(MAX_DELAY_SECONDS) {
+            return Err(Error::Agent(
+                "'when' cannot be more than 30 days away".to_string(),
+            ));
+        }
+
+        // Reminders and messages to others share the per-session limit.
+        let pending = self
+            .store
+            .count_pending_tasks_for_session(&context.session_id)?
+            + self
+                .store
+                .count_pending_messages_for_session(&context.session_id)?;
+        if pending >= MAX_PENDING_PER_SESSION {
+            return Err(Error::Agent(format!(
+                "session already has {} pending scheduled tasks (max {})",
+                pending, MAX_PENDING_PER_SESSION
+            )));
+        }
+
+        let channel = args["channel"].as_str().filter(|c| !c.trim().is_empty());
+        let to = args["to"].as_str().filter(|t| !t.trim().is_empty());
+        let task_id = match (channel, to) {
+            (Some(channel), Some(to)) => {
+                let channel = channel.trim();
+                let check = self.targets.get().ok_or_else(|| {
+                    Error::Agent("scheduling messages to other chats is not available".to_string())
+                })?;
+                check(context, channel).map_err(Error::Agent)?;
+                self.store.schedule_message(
+                    channel,
+                    to.trim(),
+                    execute_at,
+                    text,
+                    Some(&context.session_id),
+                )?
+            }
+            (None, None) => {
+                let user_id = context.user_id.as_deref().unwrap_or("unknown");
+                self.store
+                    .schedule_reminder(&context.session_id, user_id, execute_at, text)?
+            }
+            _ => {
+                return Err(Error::Agent(
+                    "'channel' and 'to' must be given together".to_string(),
+                ));
+            }
+        };
+
+        Ok(ToolOutput::success(format!(
+            "Message scheduled for {} (task ID: {})",
+            execute_at.to_rfc3339(),
+            task_id
+        )))
+    }
+}
+
 // ---------------------------------------------------------------------------
 // CancelHeartbeat
 // ---------------------------------------------------------------------------
@@ -378,6 +549,9 @@ impl Tool for ListHeartbeats {
             if let Some(ref channel) = task.deliver_to_channel {
                 line.push_str(&format!(" -> {}", channel));
             }
+            if task.is_reminder() {
+                line.push_str(" (message)");
+            }
             if task.retry_count > 0 {
                 line.push_str(&format!(
                     " [retry {}/{}]",
@@ -401,6 +575,8 @@ mod tests {
             user_id: Some("u-1".to_string()),
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -518,6 +694,8 @@ mod tests {
             user_id: Some("u-1".to_string()),
             heartbeat_depth: MAX_HEARTBEAT_DEPTH,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
 
         let err = tool
@@ -541,6 +719,8 @@ mod tests {
             user_id: Some("u-1".to_string()),
             heartbeat_depth: MAX_HEARTBEAT_DEPTH - 1,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
 
         let out = tool
@@ -612,6 +792,8 @@ mod tests {
                     user_id: Some("u2".to_string()),
                     heartbeat_depth: 0,
                     allowed_tools: None,
+                    continuity_key: None,
+                    workspace_dir: None,
                 },
                 serde_json::json!({ "delay_seconds": 60, "reason": "s2 ok" }),
             )
@@ -698,6 +880,8 @@ mod tests {
                     user_id: Some("u2".to_string()),
                     heartbeat_depth: 0,
                     allowed_tools: None,
+                    continuity_key: None,
+                    workspace_dir: None,
                 },
                 serde_json::json!({ "task_id": task_id }),
             )
@@ -872,4 +1056,143 @@ mod tests {
         assert!(err.is_err());
         assert!(err.unwrap_err().to_string().contains("delay_seconds"));
     }
+
+    #[tokio::test]
+    async fn schedule_message_persists_reminder_for_session_channel() {
+        let store = SessionStore::in_memory().unwrap();
+        store
+            .upsert_session(
+                "telegram-42",
+                "telegram",
+                "u-1",
+                &serde_json::json!({ "chat_id": 42 }),
+            )
+            .unwrap();
+        let store = Arc::new(store);
+        let (tool, _) = ScheduleMessage::new(Arc::clone(&store));
+
+        let tomorrow_nine = (chrono::Utc::now() + chrono::Duration::days(1))
+            .format("%Y-%m-%dT09:00:00")
+            .to_string();
+        let out = tool
+            .execute(
+                &test_context("telegram-42"),
+                serde_json::json!({
+                    "when": tomorrow_nine,
+                    "timezone": "UTC",
+                    "text": "Time to call mom!"
+                }),
+            )
+            .await
+            .unwrap();
+        assert!(!out.is_error);
+
+        let tasks = store.list_pending_tasks("telegram-42").unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task = &tasks[0];
+        assert!(task.is_reminder());
+        assert_eq!(task.payload, "Time to call mom!");
+        assert_eq!(task.user_id, "u-1");
+        assert_eq!(task.channel_id, "telegram");
+        assert_eq!(task.session_metadata["chat_id"], 42);
+        assert_eq!(
+            task.execute_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            tomorrow_nine
+        );
+    }
+
+    #[tokio::test]
+    async fn schedule_message_rejects_past_and_far_future_times() {
+        let store = setup_store("sess-1").await;
+        let (tool, _) = ScheduleMessage::new(store);
+
+        for when in ["2020-01-01T09:00:00", "2099-01-01T09:00:00"] {
+            let result = tool
+                .execute(
+                    &test_context("sess-1"),
+                    serde_json::json!({ "when": when, "text": "hi" }),
+                )
+                .await;
+            assert!(result.is_err(), "{when} should be rejected");
+        }
+    }
+
+    #[tokio::test]
+    async fn schedule_message_to_another_recipient() {
+        let store = setup_store("sess-1").await;
+        let (tool, handle) = ScheduleMessage::new(Arc::clone(&store));
+        let when = (chrono::Utc::now() + chrono::Duration::hours(3))
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string();
+        let to_slack = serde_json::json!({
+            "when": when,
+            "text": "Standup in 5",
+            "channel": "slack",
+            "to": "C123"
+        });
+
+        // Refused until the gateway wires a check.
+        let err = tool
+            .execute(&test_context("sess-1"), to_slack.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not available"));
+
+        handle.wire(Arc::new(|context: &ToolContext, channel: &str| {
+            match (context.user_id.as_deref(), channel) {
+                (Some("u-1"), "slack") => Ok(()),
+                (Some("u-1"), _) => Err(format!("unknown channel '{channel}'")),
+                _ => Err("only the owner can message other chats".to_string()),
+            }
+        }));
+        let mut stranger = test_context("sess-1");
+        stranger.user_id = Some("u-2".to_string());
+        let err = tool.execute(&stranger, to_slack.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("only the owner"));
+        let mut to_irc = to_slack.clone();
+        to_irc["channel"] = "irc".into();
+        let err = tool
+            .execute(&test_context("sess-1"), to_irc)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown channel"));
+
+        let out = tool
+            .execute(&test_context("sess-1"), to_slack)
+            .await
+            .unwrap();
+        assert!(!out.is_error);
+        assert!(store.list_pending_tasks("sess-1").unwrap().is_empty());
+        assert_eq!(
+            store.count_pending_messages_for_session("sess-1").unwrap(),
+            1
+        );
+        let messages = store.list_scheduled_messages().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].channel, "slack");
+        assert_eq!(messages[0].recipient, "C123");
+        assert_eq!(messages[0].text, "Standup in 5");
+
+        let result = tool
+            .execute(
+                &test_context("sess-1"),
+                serde_json::json!({ "when": when, "text": "hi", "channel": "slack" }),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn schedule_message_requires_text() {
+        let store = setup_store("sess-1").await;
+        let (tool, _) = ScheduleMessage::new(store);
+
+        let result = tool
+            .execute(
+                &test_context("sess-1"),
+                serde_json::json!({ "when": "2099-01-01T09:00:00", "text": "  " }),
+            )
+            .await;
+        assert!(result.is_err());
+    }
 }
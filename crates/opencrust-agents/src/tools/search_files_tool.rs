@@ -191,7 +191,7 @@ impl Tool for SearchFilesTool {
             ));
         }
 
-        let mut matches: Vec<String> = Vec::new();
+        let mut matches: Vec<(String, usize, String)> = Vec::new();
 
         'files: for file_path in &files {
             // Skip files that are too large
@@ -211,7 +211,7 @@ impl Tool for SearchFilesTool {
 
             for (line_no, line) in content.lines().enumerate() {
                 if regex.is_match(line) {
-                    matches.push(format!("{}:{}: {}", display, line_no + 1, line));
+                    matches.push((display.clone(), line_no + 1, line.to_string()));
                     if matches.len() >= max_results {
                         break 'files;
                     }
@@ -233,14 +233,31 @@ impl Tool for SearchFilesTool {
             matches.len(),
             files.len()
         );
-        output.push_str(&matches.join("\n"));
+        let lines: Vec<String> = matches
+            .iter()
+            .map(|(path, line, text)| format!("{path}:{line}: {text}"))
+            .collect();
+        output.push_str(&lines.join("\n"));
         if truncated {
             output.push_str(&format!(
                 "\n\n... results truncated at {max_results} — use max_results or a more specific pattern."
             ));
         }
 
-        Ok(ToolOutput::success(output))
+        let data = serde_json::json!({
+            "pattern": pattern,
+            "truncated": truncated,
+            "matches": matches
+                .iter()
+                .map(|(path, line, text)| serde_json::json!({
+                    "path": path,
+                    "line": line,
+                    "text": text,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        Ok(ToolOutput::success(output).with_data(data))
     }
 }
 
@@ -256,6 +273,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
@@ -282,6 +301,10 @@ mod tests {
         assert!(output.content.contains("hello world"));
         assert!(output.content.contains("hello rust"));
         assert!(!output.content.contains("goodbye"));
+        let data = output.data.expect("structured matches");
+        assert_eq!(data["matches"].as_array().unwrap().len(), 2);
+        assert_eq!(data["matches"][1]["line"], 3);
+        assert_eq!(data["matches"][1]["text"], "hello rust");
     }
 
     #[tokio::test]
@@ -1,36 +1,312 @@
 use async_trait::async_trait;
 use opencrust_common::{Error, Result};
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::{Tool, ToolContext, ToolOutput};
 
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 const MAX_RESPONSE_BYTES: usize = 1024 * 1024; // 1MB
+const ROBOTS_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_CACHE_TTL_SECS: u64 = 300;
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 100;
+
+/// User-Agent sent when none is configured.
+pub const DEFAULT_USER_AGENT: &str = concat!("OpenCrust/", env!("CARGO_PKG_VERSION"));
 
 /// Fetch content from a URL with timeout, size limits, and domain blocking.
 pub struct WebFetchTool {
     client: reqwest::Client,
     blocked_domains: Vec<String>,
+    user_agent: String,
+    respect_robots: bool,
+    cache: FetchCache,
 }
 
 impl WebFetchTool {
     pub fn new(blocked_domains: Option<Vec<String>>) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-            .build()
-            .unwrap_or_default();
-
         Self {
-            client,
+            client: build_client(DEFAULT_USER_AGENT),
             blocked_domains: blocked_domains.unwrap_or_default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            respect_robots: false,
+            cache: FetchCache::new(
+                Duration::from_secs(DEFAULT_CACHE_TTL_SECS),
+                DEFAULT_CACHE_MAX_ENTRIES,
+            ),
         }
     }
 
+    /// Keep successful responses for up to `ttl` (shorter when the server's
+    /// `Cache-Control: max-age` says so), holding at most `max_entries` URLs.
+    /// A zero `ttl` or `max_entries` disables caching.
+    pub fn with_cache(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.cache.ttl = ttl;
+        self.cache.max_entries = max_entries;
+        self
+    }
+
+    /// Also persist cached responses under `dir`, so they survive restarts.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache.dir = Some(dir.into());
+        self
+    }
+
+    /// Send `user_agent` as the User-Agent header on every request.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self.client = build_client(&self.user_agent);
+        self
+    }
+
+    /// Check the site's robots.txt before fetching and refuse disallowed paths.
+    pub fn with_respect_robots(mut self, respect_robots: bool) -> Self {
+        self.respect_robots = respect_robots;
+        self
+    }
+
     fn is_blocked(&self, url: &str) -> bool {
         self.blocked_domains
             .iter()
             .any(|domain| url.contains(domain))
     }
+
+    /// Fetch robots.txt for `url`'s origin and check whether the path may be
+    /// crawled. A missing or unreachable robots.txt allows everything.
+    async fn robots_allowed(&self, url: &str) -> bool {
+        let Ok(parsed) = reqwest::Url::parse(url) else {
+            return true;
+        };
+        let Ok(robots_url) = parsed.join("/robots.txt") else {
+            return true;
+        };
+
+        let response = match self
+            .client
+            .get(robots_url)
+            .timeout(Duration::from_secs(ROBOTS_TIMEOUT_SECS))
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => response,
+            _ => return true,
+        };
+        let Ok(body) = response.text().await else {
+            return true;
+        };
+
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{query}", parsed.path()),
+            None => parsed.path().to_string(),
+        };
+        robots_allows(&body, &self.user_agent, &path)
+    }
+}
+
+/// Read-through cache of fetched pages keyed by URL.
+struct FetchCache {
+    ttl: Duration,
+    max_entries: usize,
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<String, CachedPage>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedPage {
+    content: String,
+    /// Expiry as milliseconds since the Unix epoch.
+    expires_at_ms: u128,
+}
+
+impl FetchCache {
+    fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            dir: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    fn get(&self, url: &str) -> Option<String> {
+        if !self.enabled() {
+            return None;
+        }
+        let now = now_ms();
+        let mut entries = self.entries.lock().ok()?;
+        if let Some(page) = entries.get(url) {
+            if page.expires_at_ms > now {
+                return Some(page.content.clone());
+            }
+            entries.remove(url);
+            self.remove_file(url);
+            return None;
+        }
+
+        let path = self.file_path(url)?;
+        let page: CachedPage = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+        if page.expires_at_ms <= now {
+            let _ = std::fs::remove_file(path);
+            return None;
+        }
+        let content = page.content.clone();
+        self.insert_entry(&mut entries, url, page);
+        Some(content)
+    }
+
+    /// Store `content` for `url` unless `cache_control` forbids it.
+    fn put(&self, url: &str, content: &str, cache_control: Option<&str>) {
+        if !self.enabled() {
+            return;
+        }
+        let Some(ttl) = cache_ttl(self.ttl, cache_control) else {
+            return;
+        };
+        let page = CachedPage {
+            content: content.to_string(),
+            expires_at_ms: now_ms() + ttl.as_millis(),
+        };
+        if let Some(path) = self.file_path(url)
+            && let Ok(json) = serde_json::to_vec(&page)
+        {
+            let _ = std::fs::create_dir_all(path.parent().unwrap_or(&path));
+            let _ = std::fs::write(path, json);
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            self.insert_entry(&mut entries, url, page);
+        }
+    }
+
+    /// Insert, evicting the entry closest to expiry when full.
+    fn insert_entry(&self, entries: &mut HashMap<String, CachedPage>, url: &str, page: CachedPage) {
+        if !entries.contains_key(url) && entries.len() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, p)| p.expires_at_ms)
+                .map(|(u, _)| u.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+                self.remove_file(&oldest);
+            }
+        }
+        entries.insert(url.to_string(), page);
+    }
+
+    fn file_path(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let digest = ring::digest::digest(&ring::digest::SHA256, url.as_bytes());
+        let name: String = digest.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+        Some(dir.join(format!("{name}.json")))
+    }
+
+    fn remove_file(&self, url: &str) {
+        if let Some(path) = self.file_path(url) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+/// How long a response may be cached: `ttl`, shortened by `max-age`. `None`
+/// when `Cache-Control` has `no-store`, `no-cache`, or `max-age=0`.
+fn cache_ttl(ttl: Duration, cache_control: Option<&str>) -> Option<Duration> {
+    let mut ttl = ttl;
+    for directive in cache_control.unwrap_or_default().split(',') {
+        let directive = directive.trim().to_ascii_lowercase();
+        if directive == "no-store" || directive == "no-cache" {
+            return None;
+        }
+        if let Some(secs) = directive.strip_prefix("max-age=")
+            && let Ok(secs) = secs.trim_matches('"').parse::<u64>()
+        {
+            ttl = ttl.min(Duration::from_secs(secs));
+        }
+    }
+    (!ttl.is_zero()).then_some(ttl)
+}
+
+fn build_client(user_agent: &str) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+        .user_agent(user_agent)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Decide whether `path` may be fetched under `robots_txt` for `user_agent`.
+///
+/// Uses the group naming our product token (the part of the User-Agent before
+/// `/`, compared case-insensitively as a whole token per RFC 9309), falling
+/// back to the `*` group. The longest matching `Allow`/`Disallow`
+/// rule wins and `Allow` wins ties. `*` and a trailing `$` are not expanded.
+fn robots_allows(robots_txt: &str, user_agent: &str, path: &str) -> bool {
+    let token = user_agent
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let mut specific: Vec<(bool, String)> = Vec::new();
+    let mut wildcard: Vec<(bool, String)> = Vec::new();
+    let mut matched_specific = false;
+    let mut group_agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+
+    for line in robots_txt.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let field = field.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        match field.as_str() {
+            "user-agent" => {
+                // A user-agent line after rules starts a new group.
+                if in_rules {
+                    group_agents.clear();
+                    in_rules = false;
+                }
+                let agent = value.split('/').next().unwrap_or_default().trim();
+                group_agents.push(agent.to_ascii_lowercase());
+            }
+            "allow" | "disallow" => {
+                in_rules = true;
+                if value.is_empty() {
+                    continue;
+                }
+                let rule = (field == "allow", value.to_string());
+                if !token.is_empty() && group_agents.contains(&token) {
+                    matched_specific = true;
+                    specific.push(rule);
+                } else if group_agents.iter().any(|a| a == "*") {
+                    wildcard.push(rule);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let rules = if matched_specific { specific } else { wildcard };
+    rules
+        .iter()
+        .filter(|(_, prefix)| path.starts_with(prefix.as_str()))
+        .max_by_key(|(allow, prefix)| (prefix.len(), *allow))
+        .is_none_or(|(allow, _)| *allow)
 }
 
 #[async_trait]
@@ -70,6 +346,16 @@ impl Tool for WebFetchTool {
             return Ok(ToolOutput::error("domain is blocked".to_string()));
         }
 
+        if let Some(content) = self.cache.get(url) {
+            return Ok(ToolOutput::success(content));
+        }
+
+        if self.respect_robots && !self.robots_allowed(url).await {
+            return Ok(ToolOutput::error(format!(
+                "robots.txt disallows fetching {url}"
+            )));
+        }
+
         let response = self
             .client
             .get(url)
@@ -81,22 +367,29 @@ impl Tool for WebFetchTool {
         if !status.is_success() {
             return Ok(ToolOutput::error(format!("HTTP {status}")));
         }
+        let cache_control = response
+            .headers()
+            .get(reqwest::header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
 
         let bytes = response
             .bytes()
             .await
             .map_err(|e| Error::Agent(format!("failed to read response body: {e}")))?;
 
-        if bytes.len() > MAX_RESPONSE_BYTES {
+        let content = if bytes.len() > MAX_RESPONSE_BYTES {
             let truncated = String::from_utf8_lossy(&bytes[..MAX_RESPONSE_BYTES]);
-            return Ok(ToolOutput::success(format!(
+            format!(
                 "{}\n... (response truncated at {} bytes)",
                 truncated, MAX_RESPONSE_BYTES
-            )));
-        }
+            )
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
 
-        let text = String::from_utf8_lossy(&bytes);
-        Ok(ToolOutput::success(text.into_owned()))
+        self.cache.put(url, &content, cache_control.as_deref());
+        Ok(ToolOutput::success(content))
     }
 }
 
@@ -120,8 +413,224 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         };
         let result = rt.block_on(tool.execute(&ctx, serde_json::json!({})));
         assert!(result.is_err());
     }
+
+    fn test_context() -> ToolContext {
+        ToolContext {
+            session_id: "test".into(),
+            user_id: None,
+            heartbeat_depth: 0,
+            allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
+        }
+    }
+
+    #[test]
+    fn robots_rules_pick_specific_group_and_longest_match() {
+        let robots = "User-agent: *\nDisallow: /\n\nUser-agent: OpenCrust\nDisallow: /private\nAllow: /private/open\n";
+        assert!(robots_allows(robots, "OpenCrust/1.0", "/docs"));
+        assert!(!robots_allows(robots, "OpenCrust/1.0", "/private/x"));
+        assert!(robots_allows(robots, "OpenCrust/1.0", "/private/open/x"));
+        assert!(!robots_allows(robots, "OtherBot/2.0", "/docs"));
+    }
+
+    #[test]
+    fn robots_groups_match_whole_product_token() {
+        let robots = "User-agent: Open\nDisallow: /\n\nUser-agent: *\nDisallow: /private\n";
+        assert!(robots_allows(robots, "OpenCrust/1.0", "/docs"));
+        assert!(!robots_allows(robots, "OpenCrust/1.0", "/private"));
+
+        let robots = "User-agent: opencrust\nDisallow: /\n";
+        assert!(!robots_allows(robots, "OpenCrust/1.0", "/docs"));
+    }
+
+    #[test]
+    fn robots_empty_disallow_allows_everything() {
+        assert!(robots_allows(
+            "User-agent: *\nDisallow:\n",
+            "OpenCrust/1.0",
+            "/a"
+        ));
+        assert!(robots_allows("", "OpenCrust/1.0", "/a"));
+    }
+
+    #[tokio::test]
+    async fn sends_configured_user_agent() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .and(header(
+                "user-agent",
+                "TestBot/1.0 (+https://example.com/bot)",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tool =
+            WebFetchTool::new(None).with_user_agent("TestBot/1.0 (+https://example.com/bot)");
+        let output = tool
+            .execute(
+                &test_context(),
+                serde_json::json!({ "url": format!("{}/page", server.uri()) }),
+            )
+            .await
+            .unwrap();
+        assert!(!output.is_error);
+        assert_eq!(output.content, "hello");
+    }
+
+    #[tokio::test]
+    async fn refuses_robots_disallowed_url() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/robots.txt"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("User-agent: *\nDisallow: /secret\n"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/secret/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hidden"))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/public"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("visible"))
+            .mount(&server)
+            .await;
+
+        let tool = WebFetchTool::new(None).with_respect_robots(true);
+        let refused = tool
+            .execute(
+                &test_context(),
+                serde_json::json!({ "url": format!("{}/secret/page", server.uri()) }),
+            )
+            .await
+            .unwrap();
+        assert!(refused.is_error);
+        assert!(refused.content.contains("robots.txt disallows"));
+
+        let allowed = tool
+            .execute(
+                &test_context(),
+                serde_json::json!({ "url": format!("{}/public", server.uri()) }),
+            )
+            .await
+            .unwrap();
+        assert!(!allowed.is_error);
+        assert_eq!(allowed.content, "visible");
+    }
+
+    async fn fetch(tool: &WebFetchTool, url: String) -> ToolOutput {
+        tool.execute(&test_context(), serde_json::json!({ "url": url }))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn second_fetch_within_ttl_is_served_from_cache() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tool = WebFetchTool::new(None);
+        let url = format!("{}/page", server.uri());
+        assert_eq!(fetch(&tool, url.clone()).await.content, "hello");
+        assert_eq!(fetch(&tool, url).await.content, "hello");
+    }
+
+    #[tokio::test]
+    async fn expired_entry_is_refetched() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let tool = WebFetchTool::new(None).with_cache(Duration::from_millis(50), 10);
+        let url = format!("{}/page", server.uri());
+        fetch(&tool, url.clone()).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        fetch(&tool, url).await;
+    }
+
+    #[tokio::test]
+    async fn no_store_responses_are_not_cached() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/live"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("cache-control", "no-store")
+                    .set_body_string("now"),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let tool = WebFetchTool::new(None);
+        let url = format!("{}/live", server.uri());
+        fetch(&tool, url.clone()).await;
+        fetch(&tool, url).await;
+    }
+
+    #[test]
+    fn cache_ttl_honours_cache_control() {
+        let ttl = Duration::from_secs(300);
+        assert_eq!(cache_ttl(ttl, None), Some(ttl));
+        assert_eq!(
+            cache_ttl(ttl, Some("public, max-age=60")),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(cache_ttl(ttl, Some("max-age=86400")), Some(ttl));
+        assert_eq!(cache_ttl(ttl, Some("max-age=0")), None);
+        assert_eq!(cache_ttl(ttl, Some("private, no-cache")), None);
+    }
+
+    #[test]
+    fn cache_evicts_when_full_and_reads_from_disk() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut cache = FetchCache::new(Duration::from_secs(60), 1);
+        cache.dir = Some(dir.path().to_path_buf());
+
+        cache.put("https://a.test/", "a", None);
+        cache.put("https://b.test/", "b", None);
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+        assert!(cache.get("https://a.test/").is_none());
+
+        let mut restarted = FetchCache::new(Duration::from_secs(60), 10);
+        restarted.dir = Some(dir.path().to_path_buf());
+        assert_eq!(restarted.get("https://b.test/").as_deref(), Some("b"));
+    }
 }
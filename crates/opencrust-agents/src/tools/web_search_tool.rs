@@ -124,7 +124,19 @@ impl Tool for WebSearchTool {
             ));
         }
 
-        Ok(ToolOutput::success(output.trim_end()))
+        let data = serde_json::json!({
+            "query": query,
+            "results": results
+                .iter()
+                .map(|r| serde_json::json!({
+                    "title": r.title,
+                    "url": r.url,
+                    "description": r.description,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        Ok(ToolOutput::success(output.trim_end()).with_data(data))
     }
 }
 
@@ -138,6 +150,8 @@ mod tests {
             user_id: None,
             heartbeat_depth: 0,
             allowed_tools: None,
+            continuity_key: None,
+            workspace_dir: None,
         }
     }
 
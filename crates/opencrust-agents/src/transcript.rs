@@ -0,0 +1,251 @@
+//! Debug transcripts of every LLM round-trip.
+//!
+//! When `agent.transcripts: true`, each request sent to a provider and the
+//! response (or error) that came back are appended as one JSON line to
+//! `{dir}/{session_id}-{hash}.jsonl`, where the session id is made file-name
+//! safe and `hash` tells apart ids that sanitize to the same name. Strings are
+//! run through [`opencrust_security::redact_secrets`] and inline base64
+//! payloads (`data:` URIs) are replaced by their size, so the files are safe
+//! to share when debugging prompts.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use opencrust_common::Error;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::providers::{LlmRequest, LlmResponse};
+
+/// Appends request/response pairs to per-session JSONL files.
+pub struct TranscriptWriter {
+    dir: PathBuf,
+    /// Serializes appends so concurrent turns never interleave lines.
+    lock: Mutex<()>,
+}
+
+impl TranscriptWriter {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Transcript file for `session_id`.
+    pub fn path_for(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", file_stem(session_id)))
+    }
+
+    /// Append one round-trip. Failures are logged, never returned: a broken
+    /// transcript must not break the conversation.
+    pub fn record(
+        &self,
+        session_id: &str,
+        provider_id: &str,
+        request: &LlmRequest,
+        response: std::result::Result<&LlmResponse, &Error>,
+    ) {
+        let mut entry = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "session_id": session_id,
+            "provider": provider_id,
+            "request": request,
+        });
+        match response {
+            Ok(response) => entry["response"] = json!(response),
+            Err(e) => entry["error"] = json!(e.to_string()),
+        }
+        redact_value(&mut entry);
+
+        if let Err(e) = self.append(session_id, &entry) {
+            warn!("failed to write transcript for session {session_id}: {e}");
+        }
+    }
+
+    fn append(&self, session_id: &str, entry: &Value) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        std::fs::create_dir_all(&self.dir)?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path_for(session_id))?
+            .write_all(line.as_bytes())
+    }
+}
+
+/// Session ids can contain `:` or `/` (e.g. `telegram:123`); keep file names
+/// portable. A short hash of the raw id keeps `telegram:42` and `telegram_42`
+/// in separate files.
+fn file_stem(session_id: &str) -> String {
+    let stem: String = session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = if stem.trim_matches('.').is_empty() {
+        "_".to_string()
+    } else {
+        stem
+    };
+    format!("{stem}-{:08x}", fnv1a(session_id) as u32)
+}
+
+/// FNV-1a, chosen over `DefaultHasher` because file names must stay the same
+/// across Rust releases.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Redact every string in `value` in place. Each string is handled on its own
+/// so a greedy key pattern can never run past the end of a JSON string.
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if let Some(elided) = elide_data_uri(s) {
+                *s = elided;
+            } else {
+                *s = opencrust_security::redact_secrets(s);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        Value::Object(map) => map.values_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// `data:image/png;base64,AAAA...` -> `data:image/png;base64,[4 chars omitted]`.
+fn elide_data_uri(s: &str) -> Option<String> {
+    let rest = s.strip_prefix("data:")?;
+    let (header, payload) = rest.split_once(',')?;
+    header
+        .ends_with(";base64")
+        .then(|| format!("data:{header},[{} chars omitted]", payload.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ChatMessage, ChatRole, ContentBlock, MessagePart};
+
+    fn request(text: &str) -> LlmRequest {
+        LlmRequest {
+            model: "test-model".into(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Parts(vec![
+                    ContentBlock::Text { text: text.into() },
+                    ContentBlock::Image {
+                        url: "data:image/png;base64,iVBORw0KGgo=".into(),
+                    },
+                ]),
+            }],
+            system: Some("be brief".into()),
+            max_tokens: Some(100),
+            temperature: None,
+            seed: None,
+            tools: vec![],
+            response_format: None,
+            thinking: None,
+        }
+    }
+
+    fn read_lines(path: &Path) -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn appends_request_and_response_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = TranscriptWriter::new(dir.path().join("transcripts"));
+        let response = LlmResponse {
+            content: vec![ContentBlock::ToolUse {
+                id: "call_1".into(),
+                name: "bash".into(),
+                input: json!({"command": "ls"}),
+            }],
+            model: "test-model".into(),
+            usage: None,
+            stop_reason: Some("tool_use".into()),
+        };
+
+        writer.record("telegram:42", "openai", &request("hi"), Ok(&response));
+        writer.record(
+            "telegram:42",
+            "openai",
+            &request("again"),
+            Err(&Error::Agent("rate limited".into())),
+        );
+
+        let path = writer.path_for("telegram:42");
+        assert!(
+            path.file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with("telegram_42-")
+        );
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["session_id"], "telegram:42");
+        assert_eq!(lines[0]["provider"], "openai");
+        assert_eq!(lines[0]["request"]["system"], "be brief");
+        assert_eq!(lines[0]["response"]["content"][0]["name"], "bash");
+        assert!(lines[1]["error"].as_str().unwrap().contains("rate limited"));
+        assert!(lines[1].get("response").is_none());
+    }
+
+    #[test]
+    fn redacts_secrets_and_elides_inline_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = TranscriptWriter::new(dir.path());
+        let secret = "my key is sk-1234567890123456789012345";
+
+        writer.record(
+            "s",
+            "openai",
+            &request(secret),
+            Err(&Error::Agent("failed".into())),
+        );
+
+        let raw = std::fs::read_to_string(writer.path_for("s")).unwrap();
+        assert!(!raw.contains("sk-1234567890"));
+        assert!(!raw.contains("iVBORw0KGgo"));
+        let line = &read_lines(&writer.path_for("s"))[0];
+        let parts = &line["request"]["messages"][0]["content"];
+        assert_eq!(parts[0]["text"], "my key is [REDACTED]");
+        assert_eq!(parts[1]["url"], "data:image/png;base64,[12 chars omitted]");
+    }
+
+    #[test]
+    fn file_stem_is_portable() {
+        assert!(file_stem("discord:guild/1").starts_with("discord_guild_1-"));
+        assert!(file_stem("web-abc.1").starts_with("web-abc.1-"));
+        assert!(file_stem("..").starts_with("_-"));
+    }
+
+    #[test]
+    fn file_stem_keeps_colliding_ids_apart() {
+        assert_ne!(file_stem("telegram:42"), file_stem("telegram_42"));
+        assert_eq!(file_stem("telegram:42"), file_stem("telegram:42"));
+    }
+}
@@ -0,0 +1,500 @@
+pub mod socket;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+use crate::protocol::{ConnectorCapability, ConnectorFrame, ConnectorHandshake};
+use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
+use opencrust_common::{Error, Message, MessageContent, Result};
+
+/// Callback invoked when a connector reports an incoming message.
+///
+/// The returned reply is sent back to the connector as a `send_message`
+/// frame carrying the incoming message's metadata, so the connector can
+/// route it. Return `Err("__blocked__")` to silently drop the message.
+pub type RemoteOnMessageFn = Arc<
+    dyn Fn(
+            Message,
+        )
+            -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// How long [`RemoteChannel::health_check`] waits for the connector.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The connector currently attached to a channel.
+struct Session {
+    /// Frames queued for the connector's socket.
+    frames: mpsc::Sender<ConnectorFrame>,
+    handshake: ConnectorHandshake,
+    /// Last status the connector reported.
+    status: ChannelStatus,
+    /// Dropped with the session, which tells the socket to close.
+    _close: oneshot::Sender<()>,
+}
+
+impl Session {
+    fn supports(&self, capability: &ConnectorCapability) -> bool {
+        self.handshake.capabilities.contains(capability)
+    }
+}
+
+type SessionSlot = Arc<Mutex<Option<Session>>>;
+
+type PendingHealthChecks = Arc<Mutex<HashMap<String, oneshot::Sender<(bool, Option<String>)>>>>;
+
+/// A channel implemented by an out-of-process connector.
+///
+/// Connectors (written in any language) open a WebSocket to the gateway's
+/// `/connector` endpoint, handshake with the channel's token and exchange
+/// [`ConnectorFrame`]s. Only one connector is attached at a time; a new
+/// connection replaces the old one.
+pub struct RemoteChannel {
+    name: String,
+    channel_type: String,
+    display: String,
+    token: String,
+    listening: bool,
+    on_message: RemoteOnMessageFn,
+    session: SessionSlot,
+    health_checks: PendingHealthChecks,
+    next_request: Arc<AtomicU64>,
+}
+
+impl RemoteChannel {
+    /// `channel_type` is the type connectors must declare in their handshake;
+    /// `token` authenticates them.
+    pub fn new(channel_type: String, token: String, on_message: RemoteOnMessageFn) -> Self {
+        Self {
+            name: channel_type.clone(),
+            display: format!("Connector ({channel_type})"),
+            channel_type,
+            token,
+            listening: false,
+            on_message,
+            session: Arc::new(Mutex::new(None)),
+            health_checks: Arc::new(Mutex::new(HashMap::new())),
+            next_request: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Override the config key name for this channel instance.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub(crate) fn on_message(&self) -> &RemoteOnMessageFn {
+        &self.on_message
+    }
+
+    /// Check a handshake against this channel.
+    pub(crate) fn accept(&self, handshake: &ConnectorHandshake) -> Result<()> {
+        handshake.validate()?;
+        if handshake.channel_type != self.channel_type {
+            return Err(Error::Channel(format!(
+                "channel '{}' expects channel_type '{}', got '{}'",
+                self.name, self.channel_type, handshake.channel_type
+            )));
+        }
+        Ok(())
+    }
+
+    /// Attach a connector, replacing any previous one. The returned receiver
+    /// resolves once the connector is replaced or the channel disconnects.
+    pub(crate) fn attach(
+        &self,
+        handshake: ConnectorHandshake,
+        frames: mpsc::Sender<ConnectorFrame>,
+    ) -> oneshot::Receiver<()> {
+        info!(
+            "connector '{}': attached {} {}",
+            self.name, handshake.connector_name, handshake.connector_version
+        );
+        let (close, closed) = oneshot::channel();
+        *self.session.lock().unwrap() = Some(Session {
+            frames,
+            handshake,
+            status: ChannelStatus::Connected,
+            _close: close,
+        });
+        closed
+    }
+
+    /// Detach `frames` unless a newer connection replaced it.
+    pub(crate) fn detach(&self, frames: &mpsc::Sender<ConnectorFrame>) {
+        let mut session = self.session.lock().unwrap();
+        if session
+            .as_ref()
+            .is_some_and(|current| current.frames.same_channel(frames))
+        {
+            *session = None;
+            info!("connector '{}': detached", self.name);
+        }
+    }
+
+    pub(crate) fn set_remote_status(&self, status: ChannelStatus) {
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.status = status;
+        }
+    }
+
+    pub(crate) fn resolve_health_check(
+        &self,
+        request_id: &str,
+        healthy: bool,
+        details: Option<String>,
+    ) {
+        if let Some(tx) = self.health_checks.lock().unwrap().remove(request_id) {
+            let _ = tx.send((healthy, details));
+        }
+    }
+
+    /// Capabilities the attached connector declared, if one is attached.
+    pub fn capabilities(&self) -> Option<Vec<ConnectorCapability>> {
+        self.session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.handshake.capabilities.clone())
+    }
+
+    /// Ask the attached connector whether it is healthy. Fails when no
+    /// connector is attached, it does not support health checks, or it does
+    /// not answer in time.
+    pub async fn health_check(&self) -> Result<(bool, Option<String>)> {
+        let frames = {
+            let session = self.session.lock().unwrap();
+            let session = session.as_ref().ok_or_else(|| {
+                Error::Channel(format!("connector '{}' is not attached", self.name))
+            })?;
+            if !session.supports(&ConnectorCapability::HealthCheck) {
+                return Err(Error::Channel(format!(
+                    "connector '{}' does not support health checks",
+                    self.name
+                )));
+            }
+            session.frames.clone()
+        };
+
+        let request_id = format!(
+            "health-{}",
+            self.next_request.fetch_add(1, Ordering::Relaxed)
+        );
+        let (tx, rx) = oneshot::channel();
+        self.health_checks
+            .lock()
+            .unwrap()
+            .insert(request_id.clone(), tx);
+
+        let result = async {
+            frames
+                .send(ConnectorFrame::HealthCheck {
+                    request_id: request_id.clone(),
+                })
+                .await
+                .map_err(|_| Error::Channel(format!("connector '{}' disconnected", self.name)))?;
+            tokio::time::timeout(HEALTH_CHECK_TIMEOUT, rx)
+                .await
+                .map_err(|_| {
+                    Error::Channel(format!("connector '{}' health check timed out", self.name))
+                })?
+                .map_err(|_| Error::Channel(format!("connector '{}' disconnected", self.name)))
+        }
+        .await;
+        self.health_checks.lock().unwrap().remove(&request_id);
+        result
+    }
+
+    fn sender(&self) -> RemoteSender {
+        RemoteSender {
+            name: self.name.clone(),
+            channel_type: self.channel_type.clone(),
+            session: Arc::clone(&self.session),
+        }
+    }
+}
+
+/// Text of an incoming connector message, for callbacks that only handle
+/// text. Attachments are described by their caption and a file marker.
+pub fn message_text(message: &Message) -> Option<String> {
+    match &message.content {
+        MessageContent::Text(text) => Some(text.clone()),
+        MessageContent::Attachment(attachment) => Some(attachment.describe()),
+        _ => None,
+    }
+}
+
+// ── RemoteSender ─────────────────────────────────────────────────────────────
+
+/// Send-only handle that forwards messages to the attached connector.
+pub struct RemoteSender {
+    name: String,
+    channel_type: String,
+    session: SessionSlot,
+}
+
+#[async_trait]
+impl ChannelSender for RemoteSender {
+    fn channel_type(&self) -> &str {
+        &self.channel_type
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sends the message as a `send_message` frame with the message id as
+    /// its request id. Attachments go to connectors without the
+    /// `attachments` capability as their caption and a file marker.
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        let (frames, attachments) = {
+            let session = self.session.lock().unwrap();
+            let session = session.as_ref().ok_or_else(|| {
+                Error::Channel(format!("connector '{}' is not attached", self.name))
+            })?;
+            if !session.supports(&ConnectorCapability::SendMessage) {
+                return Err(Error::Channel(format!(
+                    "connector '{}' does not accept outgoing messages",
+                    self.name
+                )));
+            }
+            (
+                session.frames.clone(),
+                session.supports(&ConnectorCapability::Attachments),
+            )
+        };
+
+        let mut message = message.clone();
+        if let MessageContent::Attachment(attachment) = &message.content
+            && !attachments
+        {
+            message.content = MessageContent::Text(attachment.describe());
+        }
+        frames
+            .send(ConnectorFrame::SendMessage {
+                request_id: message.id.clone(),
+                message,
+            })
+            .await
+            .map_err(|_| Error::Channel(format!("connector '{}' disconnected", self.name)))
+    }
+}
+
+// ── ChannelLifecycle ─────────────────────────────────────────────────────────
+
+#[async_trait]
+impl ChannelLifecycle for RemoteChannel {
+    fn display_name(&self) -> &str {
+        &self.display
+    }
+
+    fn create_sender(&self) -> Box<dyn ChannelSender> {
+        Box::new(self.sender())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        // Connectors dial in to the gateway; nothing to connect to here.
+        self.listening = true;
+        info!("connector channel '{}' waiting at /connector", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.listening = false;
+        // Dropping the session closes the connector's socket.
+        self.session.lock().unwrap().take();
+        info!("connector channel '{}' disconnected", self.name);
+        Ok(())
+    }
+
+    /// `Connecting` until a connector attaches, then whatever it last
+    /// reported (`Connected` right after the handshake).
+    fn status(&self) -> ChannelStatus {
+        if !self.listening {
+            return ChannelStatus::Disconnected;
+        }
+        match self.session.lock().unwrap().as_ref() {
+            Some(session) => session.status.clone(),
+            None => ChannelStatus::Connecting,
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelSender for RemoteChannel {
+    fn channel_type(&self) -> &str {
+        &self.channel_type
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        self.sender().send_message(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CONNECTOR_PROTOCOL_VERSION;
+    use opencrust_common::{
+        ChannelId, MediaAttachment, MediaKind, MessageDirection, SessionId, UserId,
+    };
+
+    fn channel() -> RemoteChannel {
+        RemoteChannel::new(
+            "matrix".to_string(),
+            "secret".to_string(),
+            Arc::new(|_| Box::pin(async { Ok(ChannelResponse::Text("hi".to_string())) })),
+        )
+    }
+
+    fn handshake(capabilities: Vec<ConnectorCapability>) -> ConnectorHandshake {
+        ConnectorHandshake {
+            protocol_version: CONNECTOR_PROTOCOL_VERSION,
+            connector_name: "matrix-bridge".to_string(),
+            connector_version: "0.1.0".to_string(),
+            channel_type: "matrix".to_string(),
+            capabilities,
+        }
+    }
+
+    fn outgoing() -> Message {
+        Message::text(
+            SessionId::from_string("s"),
+            ChannelId::from_string("matrix"),
+            UserId::from_string("u"),
+            MessageDirection::Outgoing,
+            "hello",
+        )
+    }
+
+    #[test]
+    fn accepts_only_matching_channel_type() {
+        let ch = channel();
+        assert!(ch.accept(&handshake(vec![])).is_ok());
+
+        let mut other = handshake(vec![]);
+        other.channel_type = "irc".to_string();
+        assert!(ch.accept(&other).is_err());
+    }
+
+    #[tokio::test]
+    async fn status_follows_attached_connector() {
+        let mut ch = channel();
+        assert_eq!(ch.status(), ChannelStatus::Disconnected);
+        ch.connect().await.unwrap();
+        assert_eq!(ch.status(), ChannelStatus::Connecting);
+
+        let (tx, _rx) = mpsc::channel(4);
+        ch.attach(handshake(vec![]), tx.clone());
+        assert_eq!(ch.status(), ChannelStatus::Connected);
+        ch.set_remote_status(ChannelStatus::Error("homeserver down".into()));
+        assert_eq!(ch.status(), ChannelStatus::Error("homeserver down".into()));
+
+        ch.detach(&tx);
+        assert_eq!(ch.status(), ChannelStatus::Connecting);
+    }
+
+    #[tokio::test]
+    async fn sender_forwards_to_newest_connector() {
+        let ch = channel();
+        let sender = ch.create_sender();
+        assert!(sender.send_message(&outgoing()).await.is_err());
+
+        let (old_tx, mut old_rx) = mpsc::channel(4);
+        let (new_tx, mut new_rx) = mpsc::channel(4);
+        let old_closed = ch.attach(
+            handshake(vec![ConnectorCapability::SendMessage]),
+            old_tx.clone(),
+        );
+        ch.attach(
+            handshake(vec![ConnectorCapability::SendMessage]),
+            new_tx.clone(),
+        );
+        // The replaced connection is told to close, and closing it must not
+        // detach the live one.
+        assert!(old_closed.await.is_err());
+        ch.detach(&old_tx);
+
+        let message = outgoing();
+        sender.send_message(&message).await.unwrap();
+        match new_rx.recv().await.unwrap() {
+            ConnectorFrame::SendMessage { request_id, .. } => assert_eq!(request_id, message.id),
+            other => panic!("unexpected frame {other:?}"),
+        }
+        assert!(old_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn sender_respects_capabilities() {
+        let ch = channel();
+        let sender = ch.create_sender();
+        let (tx, mut rx) = mpsc::channel(4);
+
+        ch.attach(
+            handshake(vec![ConnectorCapability::ReceiveMessages]),
+            tx.clone(),
+        );
+        assert!(sender.send_message(&outgoing()).await.is_err());
+
+        ch.attach(handshake(vec![ConnectorCapability::SendMessage]), tx);
+        let mut message = outgoing();
+        message.content = MessageContent::Attachment(
+            MediaAttachment::new(MediaKind::Photo, vec![1, 2, 3]).with_filename("chart.png"),
+        );
+        sender.send_message(&message).await.unwrap();
+        match rx.recv().await.unwrap() {
+            ConnectorFrame::SendMessage { message, .. } => {
+                assert!(
+                    matches!(message.content, MessageContent::Text(t) if t == "[photo: chart.png]")
+                )
+            }
+            other => panic!("unexpected frame {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn health_check_round_trip() {
+        let ch = Arc::new(channel());
+        let (tx, mut rx) = mpsc::channel(4);
+        ch.attach(handshake(vec![]), tx.clone());
+        assert!(ch.health_check().await.is_err());
+
+        ch.attach(handshake(vec![ConnectorCapability::HealthCheck]), tx);
+        let responder = {
+            let ch = Arc::clone(&ch);
+            tokio::spawn(async move {
+                if let Some(ConnectorFrame::HealthCheck { request_id }) = rx.recv().await {
+                    ch.resolve_health_check(&request_id, true, Some("ok".to_string()));
+                }
+            })
+        };
+        assert_eq!(
+            ch.health_check().await.unwrap(),
+            (true, Some("ok".to_string()))
+        );
+        responder.await.unwrap();
+    }
+}
@@ -0,0 +1,462 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures::{SinkExt, StreamExt};
+use opencrust_common::{Message, MessageDirection};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::RemoteChannel;
+use crate::protocol::{
+    CONNECTOR_PROTOCOL_VERSION, ConnectorCapability, ConnectorFrame, MAX_CONNECTOR_FRAME_BYTES,
+};
+
+/// Shared state passed to the connector handler.
+pub type ConnectorState = Arc<Vec<Arc<RemoteChannel>>>;
+
+/// How long a connector has to send its handshake after connecting.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ping interval that keeps idle connections open through proxies.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+pub struct ConnectorParams {
+    /// Config key of the channel; defaults to the first connector channel.
+    pub channel: Option<String>,
+}
+
+fn find_channel(channels: &ConnectorState, name: Option<&str>) -> Option<Arc<RemoteChannel>> {
+    match name {
+        Some(name) => channels.iter().find(|ch| ch.name() == name),
+        None => channels.first(),
+    }
+    .cloned()
+}
+
+/// GET /connector — WebSocket used by external channel connectors.
+///
+/// The connector authenticates with `Authorization: Bearer <token>`, then
+/// sends a `handshake` frame and waits for `handshake_ack`. After that it
+/// reports messages with `message_received` and status with `status_update`,
+/// and receives `send_message` frames for replies and proactive messages.
+/// Health checks may go either way.
+pub async fn connector_socket(
+    State(channels): State<ConnectorState>,
+    Query(params): Query<ConnectorParams>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(channel) = find_channel(&channels, params.channel.as_deref()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if !bool::from(token.as_bytes().ct_eq(channel.token().as_bytes())) {
+        warn!(
+            "connector '{}': rejected connection with bad token",
+            channel.name()
+        );
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    ws.max_message_size(MAX_CONNECTOR_FRAME_BYTES)
+        .on_upgrade(move |socket| run_socket(socket, channel))
+}
+
+async fn send_frame<S>(ws_tx: &mut S, frame: &ConnectorFrame) -> bool
+where
+    S: SinkExt<WsMessage> + Unpin,
+{
+    match frame.to_json() {
+        Ok(json) => ws_tx.send(WsMessage::Text(json.into())).await.is_ok(),
+        Err(e) => {
+            warn!("connector: failed to serialize frame: {e}");
+            true
+        }
+    }
+}
+
+/// Read frames until the connector's handshake arrives.
+async fn read_handshake(
+    ws_rx: &mut (impl StreamExt<Item = Result<WsMessage, axum::Error>> + Unpin),
+) -> Option<ConnectorFrame> {
+    loop {
+        match ws_rx.next().await? {
+            Ok(WsMessage::Text(raw)) => return ConnectorFrame::parse_json(&raw).ok(),
+            Ok(WsMessage::Close(_)) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+async fn run_socket(socket: WebSocket, channel: Arc<RemoteChannel>) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+
+    let handshake = match tokio::time::timeout(HANDSHAKE_TIMEOUT, read_handshake(&mut ws_rx)).await
+    {
+        Ok(Some(ConnectorFrame::Handshake { payload })) => payload,
+        _ => {
+            warn!("connector '{}': no valid handshake", channel.name());
+            let _ = send_frame(&mut ws_tx, &reject("expected a handshake frame")).await;
+            return;
+        }
+    };
+    if let Err(e) = channel.accept(&handshake) {
+        warn!("connector '{}': handshake rejected: {e}", channel.name());
+        let _ = send_frame(&mut ws_tx, &reject(&e.to_string())).await;
+        return;
+    }
+    let ack = ConnectorFrame::HandshakeAck {
+        protocol_version: CONNECTOR_PROTOCOL_VERSION,
+        accepted: true,
+        message: None,
+    };
+    if !send_frame(&mut ws_tx, &ack).await {
+        return;
+    }
+
+    let receives = handshake
+        .capabilities
+        .contains(&ConnectorCapability::ReceiveMessages);
+    let (frames_tx, mut frames_rx) = mpsc::channel::<ConnectorFrame>(64);
+    let mut closed = channel.attach(handshake, frames_tx.clone());
+
+    // Turns run one at a time, in order, while the socket keeps reading.
+    let (turn_tx, turn_rx) = mpsc::channel::<Message>(32);
+    let worker = tokio::spawn(run_turns(Arc::clone(&channel), turn_rx, frames_tx.clone()));
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        tokio::select! {
+            incoming = ws_rx.next() => match incoming {
+                Some(Ok(WsMessage::Text(raw))) => {
+                    let frame = match ConnectorFrame::parse_json(&raw) {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            let _ = frames_tx.try_send(error_frame(None, "invalid_frame", &e.to_string()));
+                            continue;
+                        }
+                    };
+                    match frame {
+                        ConnectorFrame::MessageReceived { message } if receives => {
+                            if turn_tx.try_send(message).is_err() {
+                                let _ = frames_tx.try_send(error_frame(
+                                    None,
+                                    "busy",
+                                    "too many messages waiting for a reply",
+                                ));
+                            }
+                        }
+                        ConnectorFrame::StatusUpdate { status } => channel.set_remote_status(status),
+                        ConnectorFrame::HealthCheck { request_id } => {
+                            let _ = frames_tx.try_send(ConnectorFrame::HealthCheckResult {
+                                request_id,
+                                healthy: true,
+                                details: None,
+                            });
+                        }
+                        ConnectorFrame::HealthCheckResult { request_id, healthy, details } => {
+                            channel.resolve_health_check(&request_id, healthy, details);
+                        }
+                        ConnectorFrame::Error { request_id, code, message } => {
+                            warn!(
+                                "connector '{}': error {code} for {}: {message}",
+                                channel.name(),
+                                request_id.as_deref().unwrap_or("-")
+                            );
+                        }
+                        _ => {
+                            let _ = frames_tx.try_send(error_frame(
+                                None,
+                                "unexpected_frame",
+                                "frame not accepted from connectors at this point",
+                            ));
+                        }
+                    }
+                }
+                Some(Ok(WsMessage::Close(_))) | None => break,
+                Some(Err(e)) => {
+                    warn!("connector '{}': socket error: {e}", channel.name());
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+            Some(frame) = frames_rx.recv() => {
+                if !send_frame(&mut ws_tx, &frame).await {
+                    break;
+                }
+            }
+            // Another connection replaced this one, or the channel was
+            // disconnected.
+            _ = &mut closed => break,
+            _ = ping.tick() => {
+                if ws_tx.send(WsMessage::Ping(Vec::new().into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    worker.abort();
+    channel.detach(&frames_tx);
+    let _ = ws_tx.close().await;
+    info!("connector '{}': connection closed", channel.name());
+}
+
+async fn run_turns(
+    channel: Arc<RemoteChannel>,
+    mut turns: mpsc::Receiver<Message>,
+    frames: mpsc::Sender<ConnectorFrame>,
+) {
+    while let Some(incoming) = turns.recv().await {
+        let request_id = incoming.id.clone();
+        let session_id = incoming.session_id.clone();
+        let channel_id = incoming.channel_id.clone();
+        let user_id = incoming.user_id.clone();
+        let metadata = incoming.metadata.clone();
+
+        let out = match (channel.on_message())(incoming).await {
+            Ok(response) => {
+                let mut reply = Message::text(
+                    session_id,
+                    channel_id,
+                    user_id,
+                    MessageDirection::Outgoing,
+                    response.text(),
+                );
+                reply.metadata = metadata;
+                ConnectorFrame::SendMessage {
+                    request_id,
+                    message: reply,
+                }
+            }
+            Err(e) if e == "__blocked__" => continue,
+            Err(e) => {
+                warn!(
+                    "connector '{}': error processing message: {e}",
+                    channel.name()
+                );
+                error_frame(
+                    Some(request_id),
+                    "processing_failed",
+                    "an error occurred processing the message",
+                )
+            }
+        };
+        if frames.send(out).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn reject(message: &str) -> ConnectorFrame {
+    ConnectorFrame::HandshakeAck {
+        protocol_version: CONNECTOR_PROTOCOL_VERSION,
+        accepted: false,
+        message: Some(message.to_string()),
+    }
+}
+
+fn error_frame(request_id: Option<String>, code: &str, message: &str) -> ConnectorFrame {
+    ConnectorFrame::Error {
+        request_id,
+        code: code.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::get;
+    use opencrust_common::{ChannelId, MessageContent, SessionId, UserId};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+    use crate::connector::{RemoteOnMessageFn, message_text};
+    use crate::protocol::ConnectorHandshake;
+    use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelStatus};
+
+    /// Serve `channel` on a random port and return it with the WebSocket URL.
+    async fn serve(channel: RemoteChannel) -> (Arc<RemoteChannel>, String) {
+        let channel = Arc::new(channel);
+        let state: ConnectorState = Arc::new(vec![Arc::clone(&channel)]);
+        let app = Router::new()
+            .route("/connector", get(connector_socket))
+            .with_state(state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (channel, format!("ws://{addr}/connector"))
+    }
+
+    fn echo() -> RemoteOnMessageFn {
+        Arc::new(|message| {
+            Box::pin(async move {
+                let text = message_text(&message).unwrap_or_default();
+                Ok(ChannelResponse::Text(format!("echo: {text}")))
+            })
+        })
+    }
+
+    async fn connect(
+        url: &str,
+        token: &str,
+    ) -> Result<
+        tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+        tungstenite::Error,
+    > {
+        let mut req = url.into_client_request().unwrap();
+        req.headers_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        tokio_tungstenite::connect_async(req)
+            .await
+            .map(|(ws, _)| ws)
+    }
+
+    async fn next_frame<S>(ws: &mut S) -> ConnectorFrame
+    where
+        S: StreamExt<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timed out waiting for a frame")
+                .unwrap()
+                .unwrap();
+            if let tungstenite::Message::Text(text) = msg {
+                return ConnectorFrame::parse_json(&text).unwrap();
+            }
+        }
+    }
+
+    fn handshake_frame(channel_type: &str) -> tungstenite::Message {
+        let frame = ConnectorFrame::Handshake {
+            payload: ConnectorHandshake {
+                protocol_version: CONNECTOR_PROTOCOL_VERSION,
+                connector_name: "matrix-bridge".to_string(),
+                connector_version: "0.1.0".to_string(),
+                channel_type: channel_type.to_string(),
+                capabilities: vec![
+                    ConnectorCapability::SendMessage,
+                    ConnectorCapability::ReceiveMessages,
+                ],
+            },
+        };
+        tungstenite::Message::Text(frame.to_json().unwrap().into())
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_token() {
+        let (_, url) = serve(RemoteChannel::new("matrix".into(), "secret".into(), echo())).await;
+        match connect(&url, "wrong").await {
+            Err(tungstenite::Error::Http(resp)) => {
+                assert_eq!(resp.status(), StatusCode::UNAUTHORIZED)
+            }
+            other => panic!("expected 401, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_handshake() {
+        let (_, url) = serve(RemoteChannel::new("matrix".into(), "secret".into(), echo())).await;
+        let mut ws = connect(&url, "secret").await.unwrap();
+        ws.send(handshake_frame("irc")).await.unwrap();
+        match next_frame(&mut ws).await {
+            ConnectorFrame::HandshakeAck {
+                accepted, message, ..
+            } => {
+                assert!(!accepted);
+                assert!(message.unwrap().contains("expects channel_type 'matrix'"));
+            }
+            other => panic!("unexpected frame {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn exchanges_messages_after_handshake() {
+        let mut channel = RemoteChannel::new("matrix".into(), "secret".into(), echo());
+        channel.connect().await.unwrap();
+        let (channel, url) = serve(channel).await;
+        let mut ws = connect(&url, "secret").await.unwrap();
+
+        ws.send(handshake_frame("matrix")).await.unwrap();
+        assert!(matches!(
+            next_frame(&mut ws).await,
+            ConnectorFrame::HandshakeAck { accepted: true, .. }
+        ));
+        assert_eq!(channel.status(), ChannelStatus::Connected);
+
+        let mut incoming = Message::text(
+            SessionId::from_string("matrix-room1"),
+            ChannelId::from_string("matrix"),
+            UserId::from_string("@alice:example.org"),
+            MessageDirection::Incoming,
+            "hello",
+        );
+        incoming.metadata = serde_json::json!({ "room": "!room1" });
+        let frame = ConnectorFrame::MessageReceived {
+            message: incoming.clone(),
+        };
+        ws.send(tungstenite::Message::Text(frame.to_json().unwrap().into()))
+            .await
+            .unwrap();
+        match next_frame(&mut ws).await {
+            ConnectorFrame::SendMessage {
+                request_id,
+                message,
+            } => {
+                assert_eq!(request_id, incoming.id);
+                assert!(matches!(message.content, MessageContent::Text(t) if t == "echo: hello"));
+                assert_eq!(message.metadata["room"], "!room1");
+            }
+            other => panic!("unexpected frame {other:?}"),
+        }
+
+        // Proactive messages go out through the channel's sender.
+        let sender = channel.create_sender();
+        let outgoing = Message::text(
+            SessionId::from_string("matrix-room1"),
+            ChannelId::from_string("matrix"),
+            UserId::from_string("bot"),
+            MessageDirection::Outgoing,
+            "reminder",
+        );
+        sender.send_message(&outgoing).await.unwrap();
+        assert!(matches!(
+            next_frame(&mut ws).await,
+            ConnectorFrame::SendMessage { request_id, .. } if request_id == outgoing.id
+        ));
+
+        let status = ConnectorFrame::StatusUpdate {
+            status: ChannelStatus::Reconnecting,
+        };
+        ws.send(tungstenite::Message::Text(status.to_json().unwrap().into()))
+            .await
+            .unwrap();
+        ws.close(None).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while channel.status() != ChannelStatus::Connecting {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("connector was not detached");
+    }
+}
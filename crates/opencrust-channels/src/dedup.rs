@@ -0,0 +1,65 @@
+//! De-duplication of inbound messages that a platform delivers more than
+//! once: Telegram retries updates, Slack redelivers envelopes and webhook
+//! platforms post again when the gateway answers slowly.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Records an inbound message key and returns `true` if it was not seen
+/// within the TTL. The gateway backs this with the session store so
+/// redeliveries are caught across restarts.
+pub type SeenFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// How long message keys are remembered by default. Webhook platforms give
+/// up retrying well within a day.
+pub const DEFAULT_DEDUP_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Inbound de-duplication for one channel. Channels ask it before running
+/// the agent callback and drop messages it has already seen. Without a
+/// hook, keys are kept in memory for [`DEFAULT_DEDUP_TTL`]. Clones share the
+/// same record.
+#[derive(Clone)]
+pub struct InboundDedup {
+    seen: SeenFn,
+}
+
+impl Default for InboundDedup {
+    fn default() -> Self {
+        let keys: Mutex<HashMap<String, Instant>> = Mutex::default();
+        Self::new(Arc::new(move |key| {
+            let now = Instant::now();
+            let mut keys = keys.lock().unwrap();
+            keys.retain(|_, seen_at| now.duration_since(*seen_at) < DEFAULT_DEDUP_TTL);
+            keys.insert(key.to_string(), now).is_none()
+        }))
+    }
+}
+
+impl InboundDedup {
+    pub fn new(seen: SeenFn) -> Self {
+        Self { seen }
+    }
+
+    /// Record `message_id` and return `true` the first time it arrives;
+    /// redeliveries return `false`. Messages without an id always pass.
+    pub fn first_time(&self, message_id: &str) -> bool {
+        message_id.is_empty() || (self.seen)(message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redeliveries_are_caught_and_clones_share_keys() {
+        let dedup = InboundDedup::default();
+        assert!(dedup.first_time("chat:1"));
+        assert!(!dedup.first_time("chat:1"));
+        assert!(!dedup.clone().first_time("chat:1"));
+        assert!(dedup.first_time("chat:2"));
+        assert!(dedup.first_time(""));
+        assert!(dedup.first_time(""));
+    }
+}
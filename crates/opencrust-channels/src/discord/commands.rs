@@ -6,6 +6,7 @@ pub enum DiscordSlashCommand {
     Start,
     Help,
     Clear,
+    Summarize,
     Pair,
     Users,
 }
@@ -16,6 +17,7 @@ impl DiscordSlashCommand {
             Self::Start => "start",
             Self::Help => "help",
             Self::Clear => "clear",
+            Self::Summarize => "summarize",
             Self::Pair => "pair",
             Self::Users => "users",
         }
@@ -26,6 +28,7 @@ impl DiscordSlashCommand {
             "start" => Some(Self::Start),
             "help" => Some(Self::Help),
             "clear" => Some(Self::Clear),
+            "summarize" => Some(Self::Summarize),
             "pair" => Some(Self::Pair),
             "users" => Some(Self::Users),
             _ => None,
@@ -38,6 +41,7 @@ pub fn all_commands() -> Vec<CreateCommand> {
         CreateCommand::new("start").description("Initialize the bot and access flow"),
         CreateCommand::new("help").description("Show available OpenCrust commands"),
         CreateCommand::new("clear").description("Clear conversation history for this thread/DM"),
+        CreateCommand::new("summarize").description("Summarize the conversation so far"),
         CreateCommand::new("pair").description("Generate a pairing code (owner only)"),
         CreateCommand::new("users").description("List allowed users (owner only)"),
     ]
@@ -87,7 +91,7 @@ mod tests {
     #[test]
     fn all_commands_includes_expected_names() {
         let commands = all_commands();
-        assert_eq!(commands.len(), 5);
+        assert_eq!(commands.len(), 6);
     }
 
     #[test]
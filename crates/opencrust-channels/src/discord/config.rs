@@ -21,6 +21,19 @@ pub struct DiscordConfig {
 
     /// Optional command prefix for text-based commands.
     pub prefix: Option<String>,
+
+    /// Voice channel to listen in (needs the `discord-voice` feature).
+    pub voice: Option<DiscordVoiceConfig>,
+}
+
+/// A voice channel the bot joins, and the text channel it answers in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscordVoiceConfig {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    /// Where transcripts are answered; the conversation is shared with
+    /// messages typed there.
+    pub text_channel_id: u64,
 }
 
 /// Intermediate struct for deserializing from the settings map.
@@ -31,6 +44,9 @@ struct RawDiscordConfig {
     #[serde(default)]
     guild_ids: Vec<u64>,
     prefix: Option<String>,
+    voice_guild_id: Option<u64>,
+    voice_channel_id: Option<u64>,
+    voice_text_channel_id: Option<u64>,
 }
 
 impl DiscordConfig {
@@ -66,12 +82,38 @@ impl DiscordConfig {
             | GatewayIntents::MESSAGE_CONTENT
             | GatewayIntents::GUILD_MESSAGE_REACTIONS;
 
+        let voice = match raw.voice_channel_id {
+            None => None,
+            Some(channel_id) => {
+                let (Some(guild_id), Some(text_channel_id)) =
+                    (raw.voice_guild_id, raw.voice_text_channel_id)
+                else {
+                    return Err(Error::Config(
+                        "discord voice_channel_id needs voice_guild_id and voice_text_channel_id"
+                            .into(),
+                    ));
+                };
+                Some(DiscordVoiceConfig {
+                    guild_id,
+                    channel_id,
+                    text_channel_id,
+                })
+            }
+        };
+        // Joining a voice channel needs voice state updates.
+        let intents = if voice.is_some() {
+            intents | GatewayIntents::GUILD_VOICE_STATES
+        } else {
+            intents
+        };
+
         Ok(Self {
             bot_token,
             application_id,
             guild_ids: raw.guild_ids,
             intents,
             prefix: raw.prefix,
+            voice,
         })
     }
 }
@@ -144,5 +186,31 @@ mod tests {
         let config = DiscordConfig::from_settings(&settings).expect("should parse");
         assert!(config.guild_ids.is_empty());
         assert!(config.prefix.is_none());
+        assert!(config.voice.is_none());
+        assert!(!config.intents.contains(GatewayIntents::GUILD_VOICE_STATES));
+    }
+
+    #[test]
+    fn voice_channel_needs_guild_and_text_channel() {
+        let mut settings = make_settings(vec![
+            ("bot_token", serde_json::json!("my-secret-token")),
+            ("application_id", serde_json::json!(123456789012345678_u64)),
+            ("voice_channel_id", serde_json::json!(2_u64)),
+        ]);
+        let err = DiscordConfig::from_settings(&settings).expect_err("incomplete voice config");
+        assert!(err.to_string().contains("voice_guild_id"));
+
+        settings.insert("voice_guild_id".into(), serde_json::json!(1_u64));
+        settings.insert("voice_text_channel_id".into(), serde_json::json!(3_u64));
+        let config = DiscordConfig::from_settings(&settings).expect("should parse");
+        assert_eq!(
+            config.voice,
+            Some(DiscordVoiceConfig {
+                guild_id: 1,
+                channel_id: 2,
+                text_channel_id: 3,
+            })
+        );
+        assert!(config.intents.contains(GatewayIntents::GUILD_VOICE_STATES));
     }
 }
@@ -2,6 +2,8 @@ use chrono::Utc;
 use opencrust_common::{ChannelId, Message, MessageContent, MessageDirection, SessionId, UserId};
 use serenity::all as serenity_model;
 
+use crate::format::FormatProfile;
+
 pub const DISCORD_MESSAGE_CHAR_LIMIT: usize = 2000;
 
 /// Convert a serenity Discord message into an OpenCrust `Message`.
@@ -140,20 +142,55 @@ pub fn opencrust_content_to_text(content: &MessageContent) -> String {
             format!("{emoji} (on message {target_message_id})")
         }
         MessageContent::System(text) => format!("ℹ️ {text}"),
+        MessageContent::Attachment(attachment) => attachment.describe(),
     }
 }
 
-/// Convert generic markdown to Discord-friendly markdown.
+/// Discord markdown formatting profile.
 ///
 /// Discord supports most common markdown constructs, so this primarily normalizes
 /// line endings and neutralizes mass mentions.
-pub fn to_discord_markdown(input: &str) -> String {
-    input
-        .replace("\r\n", "\n")
+pub struct DiscordFormat;
+
+impl FormatProfile for DiscordFormat {
+    fn text(&self, text: &str, out: &mut String) {
+        out.push_str(&neutralize(text));
+    }
+
+    fn bold(&self, text: &str, out: &mut String) {
+        out.push_str("**");
+        out.push_str(&neutralize(text));
+        out.push_str("**");
+    }
+
+    fn link(&self, text: &str, url: &str, out: &mut String) {
+        out.push_str(&format!("[{}]({url})", neutralize(text)));
+    }
+
+    fn inline_code(&self, code: &str, out: &mut String) {
+        out.push('`');
+        out.push_str(&neutralize(code));
+        out.push('`');
+    }
+
+    fn code_block(&self, body: &str, out: &mut String) {
+        out.push_str("```");
+        out.push_str(&neutralize(body));
+        out.push_str("```");
+    }
+}
+
+fn neutralize(text: &str) -> String {
+    text.replace("\r\n", "\n")
         .replace("@everyone", "@\u{200B}everyone")
         .replace("@here", "@\u{200B}here")
 }
 
+/// Convert generic markdown to Discord-friendly markdown.
+pub fn to_discord_markdown(input: &str) -> String {
+    DiscordFormat.render(input)
+}
+
 /// Split text into Discord-safe chunks (<= 2000 chars each).
 pub fn split_discord_chunks(input: &str) -> Vec<String> {
     if input.is_empty() {
@@ -181,6 +218,29 @@ pub fn split_discord_chunks(input: &str) -> Vec<String> {
     chunks
 }
 
+/// Render model reasoning as one collapsed (spoiler) message. Long reasoning
+/// is cut so the message stays within Discord's limit.
+pub fn reasoning_spoiler(reasoning: &str) -> String {
+    const HEADER: &str = "-# Reasoning\n||";
+    const ELLIPSIS: &str = "…";
+    // `||` inside the text would close the spoiler early.
+    let body = neutralize(reasoning.trim()).replace("||", "|\u{200B}|");
+    let budget = DISCORD_MESSAGE_CHAR_LIMIT - HEADER.len() - "||".len() - ELLIPSIS.len();
+    let mut out = String::from(HEADER);
+    if body.len() <= budget {
+        out.push_str(&body);
+    } else {
+        let mut end = budget;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.push_str(&body[..end]);
+        out.push_str(ELLIPSIS);
+    }
+    out.push_str("||");
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +305,17 @@ mod tests {
         assert_eq!(chunks[0].len(), DISCORD_MESSAGE_CHAR_LIMIT);
         assert_eq!(chunks[1].len(), 10);
     }
+
+    #[test]
+    fn reasoning_is_wrapped_in_a_spoiler() {
+        let spoiler = reasoning_spoiler("a || b");
+        assert_eq!(spoiler, "-# Reasoning\n||a |\u{200B}| b||");
+    }
+
+    #[test]
+    fn long_reasoning_fits_one_message() {
+        let spoiler = reasoning_spoiler(&"é".repeat(3000));
+        assert!(spoiler.len() <= DISCORD_MESSAGE_CHAR_LIMIT);
+        assert!(spoiler.ends_with("…||"));
+    }
 }
@@ -1,16 +1,24 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use opencrust_common::{MediaAttachment, MediaKind};
 use serenity::all::{
     self as serenity_model, CommandInteraction, Context, CreateMessage, EditMessage, EventHandler,
-    Interaction as SerenityInteraction, Message as SerenityMessage, MessageId, Ready,
+    Interaction as SerenityInteraction, Message as SerenityMessage, MessageId, MessageUpdateEvent,
+    Ready, User,
 };
 use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn};
 
+use crate::dedup::InboundDedup;
+use crate::edit::EditRegeneration;
+use crate::feedback::{ReactionFeedback, SentReply};
+use crate::queue::RateLimiter;
 use crate::traits::{ChannelEvent, ChannelResponse, ChannelStatus};
+use crate::typing::while_typing;
 
-use super::{DiscordFile, DiscordGroupFilter, DiscordOnMessageFn, commands, convert};
+use super::render::ResponseRenderers;
+use super::{DiscordGroupFilter, DiscordOnMessageFn, commands, convert, send_embed};
 
 /// Serenity event handler that bridges Discord events into OpenCrust `ChannelEvent`s.
 pub struct DiscordHandler {
@@ -28,6 +36,25 @@ pub struct DiscordHandler {
 
     /// Group filter closure (decides whether to process group messages).
     group_filter: DiscordGroupFilter,
+
+    /// Regenerates the reply when the last answered message is edited.
+    edits: EditRegeneration,
+
+    /// Recent replies, for 👍/👎 reaction feedback.
+    feedback: ReactionFeedback,
+
+    /// Embed renderers for structured tool output.
+    renderers: ResponseRenderers,
+
+    /// Message ids already handled, so redelivered events are dropped.
+    dedup: InboundDedup,
+
+    /// Outbound rate limits every reply waits on.
+    limiter: Arc<RateLimiter>,
+
+    /// Voice channel to listen in once connected.
+    #[cfg(feature = "discord-voice")]
+    voice: Option<super::config::DiscordVoiceConfig>,
 }
 
 impl DiscordHandler {
@@ -44,7 +71,74 @@ impl DiscordHandler {
             guild_ids,
             on_message,
             group_filter,
+            edits: EditRegeneration::default(),
+            feedback: ReactionFeedback::default(),
+            renderers: ResponseRenderers::default(),
+            dedup: InboundDedup::default(),
+            limiter: Arc::new(RateLimiter::for_channel("discord")),
+            #[cfg(feature = "discord-voice")]
+            voice: None,
+        }
+    }
+
+    /// Regenerate replies for edited messages.
+    pub fn with_edits(mut self, edits: EditRegeneration) -> Self {
+        self.edits = edits;
+        self
+    }
+
+    /// Record replies and pass reactions to them on as feedback.
+    pub fn with_feedback(mut self, feedback: ReactionFeedback) -> Self {
+        self.feedback = feedback;
+        self
+    }
+
+    /// Render structured tool output with `renderers`.
+    pub fn with_renderers(mut self, renderers: ResponseRenderers) -> Self {
+        self.renderers = renderers;
+        self
+    }
+
+    /// Drop messages `dedup` has already seen.
+    pub fn with_dedup(mut self, dedup: InboundDedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Send replies under `limiter`, the channel's outbound rate limits.
+    pub fn with_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Join and listen in the voice channel in `voice` once connected.
+    #[cfg(feature = "discord-voice")]
+    pub fn with_voice(mut self, voice: Option<super::config::DiscordVoiceConfig>) -> Self {
+        self.voice = voice;
+        self
+    }
+
+    /// Apply the group filter to a guild message. Replying to the bot counts
+    /// as a mention, and the mention itself is not passed on to the agent.
+    /// Returns `None` when the message should be ignored.
+    fn addressed_text(
+        &self,
+        ctx: &Context,
+        content: &str,
+        is_group: bool,
+        mentions: &[User],
+        replied_to: Option<&SerenityMessage>,
+    ) -> Option<String> {
+        if !is_group {
+            return Some(content.to_string());
+        }
+        let bot_id = ctx.cache.current_user().id;
+        let is_mentioned = mentions.iter().any(|u| u.id == bot_id)
+            || replied_to.is_some_and(|m| m.author.id == bot_id);
+        if !(self.group_filter)(is_mentioned) {
+            return None;
         }
+        Some(strip_bot_mention(content, bot_id.get()))
     }
 
     fn emit(&self, event: ChannelEvent) {
@@ -53,6 +147,8 @@ impl DiscordHandler {
         }
     }
 
+    /// Run a turn and deliver the reply. Returns whether the agent answered
+    /// (the message was not blocked and did not fail).
     #[allow(clippy::too_many_arguments)]
     async fn process_message(
         &self,
@@ -62,23 +158,13 @@ impl DiscordHandler {
         user_name: String,
         text: String,
         is_group: bool,
-        file: Option<DiscordFile>,
-    ) {
+        file: Option<MediaAttachment>,
+    ) -> bool {
         // Skip only if there is neither text nor an attached file.
         if text.trim().is_empty() && file.is_none() {
-            return;
+            return false;
         }
 
-        // Keep typing indicator alive while callback/streaming is in progress.
-        let typing_http = ctx.http.clone();
-        let typing_channel = channel_id;
-        let typing_handle = tokio::spawn(async move {
-            loop {
-                let _ = typing_channel.broadcast_typing(&typing_http).await;
-                tokio::time::sleep(Duration::from_secs(4)).await;
-            }
-        });
-
         let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
         let on_message = Arc::clone(&self.on_message);
         let cb_channel_id = channel_id.to_string();
@@ -104,65 +190,149 @@ impl DiscordHandler {
         let mut first_delta_at: Option<Instant> = None;
         let mut last_update = Instant::now();
 
-        while let Some(delta) = delta_rx.recv().await {
-            accumulated.push_str(&delta);
-            if first_delta_at.is_none() {
-                first_delta_at = Some(Instant::now());
-            }
+        // Keep typing indicator alive while callback/streaming is in progress.
+        let typing = super::DiscordSender {
+            http: ctx.http.clone(),
+            name: self.channel_id.clone(),
+            limiter: Arc::clone(&self.limiter),
+        };
+        let result = while_typing(&typing, &channel_id.to_string(), async {
+            while let Some(delta) = delta_rx.recv().await {
+                accumulated.push_str(&delta);
+                if first_delta_at.is_none() {
+                    first_delta_at = Some(Instant::now());
+                }
 
-            if first_delta_at
-                .map(|t| t.elapsed() >= Duration::from_secs(1))
-                .unwrap_or(false)
-                && last_update.elapsed() >= Duration::from_millis(1000)
-            {
-                if let Err(e) =
-                    sync_discord_chunks(ctx, channel_id, &accumulated, &mut sent, false).await
+                if first_delta_at
+                    .map(|t| t.elapsed() >= Duration::from_secs(1))
+                    .unwrap_or(false)
+                    && last_update.elapsed() >= Duration::from_millis(1000)
                 {
-                    warn!("failed to stream Discord update: {e}");
-                    break;
+                    if let Err(e) = sync_discord_chunks(
+                        ctx,
+                        &self.limiter,
+                        channel_id,
+                        &accumulated,
+                        &mut sent,
+                        false,
+                    )
+                    .await
+                    {
+                        warn!("failed to stream Discord update: {e}");
+                        break;
+                    }
+                    last_update = Instant::now();
                 }
-                last_update = Instant::now();
             }
-        }
 
-        typing_handle.abort();
-
-        let result = callback_handle
-            .await
-            .unwrap_or_else(|e| Err(format!("task panic: {e}")));
+            callback_handle
+                .await
+                .unwrap_or_else(|e| Err(format!("task panic: {e}")))
+        })
+        .await;
+        let answered = result.is_ok();
+        // Text of the delivered reply, recorded against every message in `sent`.
+        let mut reply_text = None;
 
         match result {
-            Ok(ChannelResponse::Text(final_text)) => {
+            Ok(response @ (ChannelResponse::Text(_) | ChannelResponse::Buttons { .. })) => {
+                // Inline buttons are Telegram-only; send just the text.
+                if let Err(e) = sync_discord_chunks(
+                    ctx,
+                    &self.limiter,
+                    channel_id,
+                    response.text(),
+                    &mut sent,
+                    true,
+                )
+                .await
+                {
+                    warn!("failed to send Discord final response: {e}");
+                }
+                reply_text = Some(response.text().to_string());
+            }
+            Ok(ChannelResponse::ToolResults { text, results }) => {
+                if let Err(e) =
+                    sync_discord_chunks(ctx, &self.limiter, channel_id, &text, &mut sent, true)
+                        .await
+                {
+                    warn!("failed to send Discord final response: {e}");
+                }
+                for embed in self.renderers.render(&results) {
+                    self.limiter.acquire(&channel_id.to_string()).await;
+                    if let Err(e) = send_embed(&ctx.http, channel_id, embed).await {
+                        warn!("{e}");
+                    }
+                }
+                reply_text = Some(text);
+            }
+            Ok(ChannelResponse::Reasoning { text, reasoning }) => {
                 if let Err(e) =
-                    sync_discord_chunks(ctx, channel_id, &final_text, &mut sent, true).await
+                    sync_discord_chunks(ctx, &self.limiter, channel_id, &text, &mut sent, true)
+                        .await
                 {
                     warn!("failed to send Discord final response: {e}");
                 }
+                let spoiler = CreateMessage::new().content(convert::reasoning_spoiler(&reasoning));
+                self.limiter.acquire(&channel_id.to_string()).await;
+                if let Err(e) = channel_id.send_message(&ctx.http, spoiler).await {
+                    warn!("failed to send Discord reasoning: {e}");
+                }
+                reply_text = Some(text);
             }
             Ok(ChannelResponse::Voice { text, audio }) => {
                 // Send OGG/Opus audio as a file attachment.
                 let attachment = serenity_model::CreateAttachment::bytes(audio, "voice.ogg");
                 let msg = CreateMessage::new().add_file(attachment);
-                if let Err(e) = channel_id.send_message(&ctx.http, msg).await {
-                    warn!("failed to send Discord voice attachment: {e}");
-                    // Fallback: send text
-                    if let Err(e2) =
-                        sync_discord_chunks(ctx, channel_id, &text, &mut sent, true).await
-                    {
-                        warn!("failed to send Discord voice fallback text: {e2}");
+                self.limiter.acquire(&channel_id.to_string()).await;
+                match channel_id.send_message(&ctx.http, msg).await {
+                    Ok(voice) => sent.push((voice.id, text.clone())),
+                    Err(e) => {
+                        warn!("failed to send Discord voice attachment: {e}");
+                        // Fallback: send text
+                        if let Err(e2) = sync_discord_chunks(
+                            ctx,
+                            &self.limiter,
+                            channel_id,
+                            &text,
+                            &mut sent,
+                            true,
+                        )
+                        .await
+                        {
+                            warn!("failed to send Discord voice fallback text: {e2}");
+                        }
                     }
                 }
+                reply_text = Some(text);
             }
             Err(e) if e == "__blocked__" => {}
             Err(e) => {
                 let err_text = format!("Sorry, an error occurred: {e}");
                 if let Err(send_err) =
-                    sync_discord_chunks(ctx, channel_id, &err_text, &mut sent, true).await
+                    sync_discord_chunks(ctx, &self.limiter, channel_id, &err_text, &mut sent, true)
+                        .await
                 {
                     warn!("failed to send Discord error response: {send_err}");
                 }
             }
         }
+
+        if let Some(text) = reply_text {
+            for (id, _) in &sent {
+                self.feedback.record(
+                    &id.to_string(),
+                    SentReply {
+                        chat_id: channel_id.to_string(),
+                        thread_id: None,
+                        user_id: user_id.clone(),
+                        is_group,
+                        text: text.clone(),
+                    },
+                );
+            }
+        }
+        answered
     }
 
     async fn process_slash_command(
@@ -260,6 +430,17 @@ impl EventHandler for DiscordHandler {
             info!("registered {} discord slash command(s)", command_defs.len());
         }
 
+        #[cfg(feature = "discord-voice")]
+        if let Some(voice) = self.voice {
+            tokio::spawn(super::voice::listen(
+                ctx.clone(),
+                voice,
+                Arc::clone(&self.on_message),
+                Arc::clone(&self.group_filter),
+                Arc::clone(&self.limiter),
+            ));
+        }
+
         self.emit(ChannelEvent::StatusChanged(ChannelStatus::Connected));
     }
 
@@ -271,20 +452,22 @@ impl EventHandler for DiscordHandler {
 
     /// Fired when a message is received in any channel the bot can see.
     async fn message(&self, ctx: Context, msg: SerenityMessage) {
-        if msg.author.bot {
+        if msg.author.bot || !self.dedup.first_time(&msg.id.to_string()) {
             return;
         }
 
         let is_group = msg.guild_id.is_some();
 
         // Apply group filter before processing
-        if is_group {
-            let bot_id = ctx.cache.current_user().id;
-            let is_mentioned = msg.mentions.iter().any(|u| u.id == bot_id);
-            if !(self.group_filter)(is_mentioned) {
-                return;
-            }
-        }
+        let Some(content) = self.addressed_text(
+            &ctx,
+            &msg.content,
+            is_group,
+            &msg.mentions,
+            msg.referenced_message.as_deref(),
+        ) else {
+            return;
+        };
 
         let opencrust_msg = convert::discord_message_to_opencrust(&msg, &self.channel_id);
         self.emit(ChannelEvent::MessageReceived(opencrust_msg));
@@ -313,11 +496,19 @@ impl EventHandler for DiscordHandler {
                 None
             } else {
                 match attachment.download().await {
-                    Ok(data) => Some(DiscordFile {
-                        filename: attachment.filename.clone(),
-                        data,
-                        content_type: attachment.content_type.clone(),
-                    }),
+                    Ok(data) => {
+                        let content_type = attachment.content_type.clone();
+                        let mut file = MediaAttachment::new(
+                            MediaKind::from_mime(content_type.as_deref()),
+                            data,
+                        )
+                        .with_filename(attachment.filename.clone())
+                        .with_mime_type(content_type);
+                        if let Some(secs) = attachment.duration_secs {
+                            file = file.with_duration(secs.round() as u32);
+                        }
+                        Some(file)
+                    }
                     Err(e) => {
                         warn!("discord: failed to download attachment: {e}");
                         None
@@ -328,19 +519,97 @@ impl EventHandler for DiscordHandler {
             None
         };
 
-        self.process_message(
+        let answered = self
+            .process_message(
+                &ctx,
+                msg.channel_id,
+                msg.author.id.to_string(),
+                msg.author
+                    .global_name
+                    .clone()
+                    .unwrap_or_else(|| msg.author.name.clone()),
+                content,
+                is_group,
+                file,
+            )
+            .await;
+        if answered {
+            self.edits
+                .record(&msg.channel_id.to_string(), &msg.id.to_string());
+        }
+    }
+
+    /// Fired when a message is edited. An edit of the last message the bot
+    /// answered in the channel regenerates the reply, if enabled.
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<SerenityMessage>,
+        _new: Option<SerenityMessage>,
+        event: MessageUpdateEvent,
+    ) {
+        // Embed unfurls also arrive as updates, without content or author.
+        let (Some(author), Some(content)) = (&event.author, &event.content) else {
+            return;
+        };
+        if author.bot {
+            return;
+        }
+        let chat_id = event.channel_id.to_string();
+        let message_id = event.id.to_string();
+        let edited_at = event
+            .edited_timestamp
+            .map(|t| t.unix_timestamp())
+            .unwrap_or(0);
+        if !self.dedup.first_time(&format!("{message_id}:{edited_at}")) {
+            return;
+        }
+        let Some(on_edit) = self.edits.hook_for(&chat_id, &message_id) else {
+            return;
+        };
+
+        let is_group = event.guild_id.is_some();
+        let replied_to = event.referenced_message.as_ref().and_then(|m| m.as_deref());
+        let Some(content) = self.addressed_text(
             &ctx,
-            msg.channel_id,
-            msg.author.id.to_string(),
-            msg.author
-                .global_name
-                .clone()
-                .unwrap_or_else(|| msg.author.name.clone()),
-            msg.content.clone(),
+            content,
             is_group,
-            file,
-        )
-        .await;
+            event.mentions.as_deref().unwrap_or_default(),
+            replied_to,
+        ) else {
+            return;
+        };
+
+        let user_id = author.id.to_string();
+        info!(
+            "discord edit from {} (channel {chat_id}): regenerating",
+            author.name
+        );
+        self.emit(ChannelEvent::MessageEdited {
+            chat_id: chat_id.clone(),
+            user_id: user_id.clone(),
+            message_id: message_id.clone(),
+            text: content.clone(),
+        });
+        on_edit(chat_id.clone(), None, user_id.clone(), is_group).await;
+
+        let answered = self
+            .process_message(
+                &ctx,
+                event.channel_id,
+                user_id,
+                author
+                    .global_name
+                    .clone()
+                    .unwrap_or_else(|| author.name.clone()),
+                content,
+                is_group,
+                None,
+            )
+            .await;
+        if answered {
+            self.edits.record(&chat_id, &message_id);
+        }
     }
 
     /// Fired when a slash command interaction is created.
@@ -356,8 +625,10 @@ impl EventHandler for DiscordHandler {
         self.process_slash_command(&ctx, &command, slash).await;
     }
 
-    /// Fired when a reaction is added to a message.
-    async fn reaction_add(&self, _ctx: Context, reaction: serenity_model::Reaction) {
+    /// Fired when a reaction is added to a message. Reactions to the bot's
+    /// replies are also reported as [`ChannelEvent::Reaction`], and 👍/👎
+    /// passed on as feedback.
+    async fn reaction_add(&self, ctx: Context, reaction: serenity_model::Reaction) {
         let opencrust_msg = convert::reaction_to_opencrust(&reaction, &self.channel_id);
 
         tracing::debug!(
@@ -367,6 +638,28 @@ impl EventHandler for DiscordHandler {
         );
 
         self.emit(ChannelEvent::MessageReceived(opencrust_msg));
+
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        let serenity_model::ReactionType::Unicode(emoji) = &reaction.emoji else {
+            return;
+        };
+        if user_id == ctx.cache.current_user().id {
+            return;
+        }
+        let chat_id = reaction.channel_id.to_string();
+        let message_id = reaction.message_id.to_string();
+        let Some(reply) = self.feedback.reply(&chat_id, &message_id) else {
+            return;
+        };
+        self.emit(ChannelEvent::Reaction {
+            chat_id,
+            user_id: user_id.to_string(),
+            message_id,
+            emoji: emoji.clone(),
+        });
+        self.feedback.react(reply, emoji).await;
     }
 
     /// Fired when a thread is created.
@@ -379,8 +672,12 @@ impl EventHandler for DiscordHandler {
     }
 }
 
-async fn sync_discord_chunks(
+/// Make the messages in `sent` show `text`, editing changed chunks and
+/// sending new ones, each under `limiter`. A final sync deletes chunks the
+/// text no longer needs.
+pub(super) async fn sync_discord_chunks(
     ctx: &Context,
+    limiter: &RateLimiter,
     channel_id: serenity_model::ChannelId,
     text: &str,
     sent: &mut Vec<(MessageId, String)>,
@@ -392,6 +689,7 @@ async fn sync_discord_chunks(
     for (idx, chunk) in chunks.iter().enumerate() {
         if idx < sent.len() {
             if sent[idx].1 != *chunk {
+                limiter.acquire(&channel_id.to_string()).await;
                 channel_id
                     .edit_message(&ctx.http, sent[idx].0, EditMessage::new().content(chunk))
                     .await
@@ -399,6 +697,7 @@ async fn sync_discord_chunks(
                 sent[idx].1 = chunk.clone();
             }
         } else {
+            limiter.acquire(&channel_id.to_string()).await;
             let msg = channel_id
                 .send_message(&ctx.http, CreateMessage::new().content(chunk))
                 .await
@@ -416,6 +715,19 @@ async fn sync_discord_chunks(
     Ok(())
 }
 
+/// Remove `<@id>` / `<@!id>` mentions of the bot from a message.
+fn strip_bot_mention(content: &str, bot_id: u64) -> String {
+    content
+        .replace(&format!("<@{bot_id}>"), "")
+        .replace(&format!("<@!{bot_id}>"), "")
+        .split(' ')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim()
+        .to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,4 +767,14 @@ mod tests {
         );
         handler.emit(ChannelEvent::StatusChanged(ChannelStatus::Connected));
     }
+
+    #[test]
+    fn strip_bot_mention_removes_both_forms() {
+        assert_eq!(strip_bot_mention("<@42> hello there", 42), "hello there");
+        assert_eq!(strip_bot_mention("hey <@!42> help", 42), "hey help");
+        assert_eq!(
+            strip_bot_mention("ping <@7> please", 42),
+            "ping <@7> please"
+        );
+    }
 }
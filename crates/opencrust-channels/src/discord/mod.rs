@@ -8,47 +8,52 @@ pub mod commands;
 pub mod config;
 pub mod convert;
 pub mod handler;
+pub mod render;
+#[cfg(feature = "discord-voice")]
+mod voice;
 
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use opencrust_common::{Error, Message, Result};
+use opencrust_common::{Error, MediaAttachment, Message, MessageContent, Result};
 use serenity::all::{self as serenity_model, CreateAttachment, CreateMessage};
-use tokio::sync::{broadcast, mpsc};
-use tracing::{error, info};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{error, info, warn};
 
+use crate::dedup::{InboundDedup, SeenFn};
+use crate::edit::{EditRegeneration, OnEditFn};
+use crate::feedback::{OnReactionFn, ReactionFeedback};
+use crate::queue::RateLimiter;
 use crate::traits::{
     ChannelEvent, ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus,
 };
 use config::DiscordConfig;
 use handler::DiscordHandler;
+use render::{ResponseRenderer, ResponseRenderers};
+
+/// Consecutive client failures tolerated before the channel gives up.
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+/// Delay before the first reconnect; doubles on each consecutive failure.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound on the reconnect delay.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(120);
+/// A client that ran at least this long before failing resets the attempt counter.
+const STABLE_RUN: Duration = Duration::from_secs(60);
 
 /// Closure that decides whether to process a group message.
 /// Argument: `is_mentioned` (whether the bot was mentioned).
 /// Returns `true` if the message should be processed.
 pub type DiscordGroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
 
-/// A file attached to a Discord message, with bytes already downloaded.
-///
-/// The channel handler downloads the attachment before invoking
-/// `DiscordOnMessageFn` so the callback does not need to perform any HTTP
-/// calls.
-#[derive(Debug, Clone)]
-pub struct DiscordFile {
-    /// Original filename as reported by Discord.
-    pub filename: String,
-    /// Raw file bytes.
-    pub data: Vec<u8>,
-    /// MIME type string from Discord (e.g. `"application/pdf"`).
-    pub content_type: Option<String>,
-}
-
 /// Callback invoked when the bot receives a message from Discord.
 ///
 /// Arguments: `(channel_id, user_id, user_name, text, is_group, file, delta_sender)`.
-/// `file` is `Some` when the user attached a file to the message.
+/// `file` is `Some` when the user attached a file to the message; it is
+/// downloaded before the callback runs.
 /// Return `Err("__blocked__")` to silently drop unauthorized messages.
 pub type DiscordOnMessageFn = Arc<
     dyn Fn(
@@ -57,7 +62,7 @@ pub type DiscordOnMessageFn = Arc<
             String,
             String,
             bool,
-            Option<DiscordFile>,
+            Option<MediaAttachment>,
             Option<mpsc::Sender<String>>,
         )
             -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
@@ -88,14 +93,35 @@ pub struct DiscordChannel {
     /// Broadcast sender for channel events.
     event_tx: broadcast::Sender<ChannelEvent>,
 
+    /// Regenerates the reply when the last answered message is edited.
+    edits: EditRegeneration,
+
+    /// Recent replies, for 👍/👎 reaction feedback.
+    feedback: ReactionFeedback,
+
+    /// Embed renderers for structured tool output, keyed by tool name.
+    renderers: ResponseRenderers,
+
+    /// Message ids already handled, so redelivered events are dropped.
+    dedup: InboundDedup,
+
+    /// Outbound rate limits, shared by replies and the sender's queue.
+    limiter: Arc<RateLimiter>,
+
     /// HTTP client for sending messages (available after connect).
     http: Option<std::sync::Arc<serenity_model::Http>>,
 
-    /// Handle to the spawned client task.
+    /// Handle to the spawned client supervision task.
     client_handle: Option<tokio::task::JoinHandle<()>>,
 
-    /// Shard manager for graceful shutdown.
-    shard_manager: Option<std::sync::Arc<serenity_model::ShardManager>>,
+    /// Shard manager of the running client, replaced on every restart.
+    shard_manager: Arc<std::sync::Mutex<Option<Arc<serenity_model::ShardManager>>>>,
+
+    /// Stops the supervision loop from restarting the client.
+    shutdown_tx: Option<watch::Sender<bool>>,
+
+    /// Number of times the client has been restarted after a failure.
+    reconnect_count: Arc<AtomicU32>,
 }
 
 impl std::fmt::Debug for DiscordChannel {
@@ -126,9 +152,16 @@ impl DiscordChannel {
             on_message,
             group_filter,
             event_tx,
+            edits: EditRegeneration::default(),
+            feedback: ReactionFeedback::default(),
+            renderers: ResponseRenderers::default(),
+            dedup: InboundDedup::default(),
+            limiter: Arc::new(RateLimiter::for_channel("discord")),
             http: None,
             client_handle: None,
-            shard_manager: None,
+            shard_manager: Arc::new(std::sync::Mutex::new(None)),
+            shutdown_tx: None,
+            reconnect_count: Arc::new(AtomicU32::new(0)),
         }
     }
 
@@ -138,6 +171,32 @@ impl DiscordChannel {
         self
     }
 
+    /// Regenerate the reply when a user edits the last message the bot
+    /// answered in a channel. `on_edit` runs first to drop the replaced turn.
+    pub fn with_edit_regeneration(mut self, on_edit: OnEditFn) -> Self {
+        self.edits = EditRegeneration::new(on_edit);
+        self
+    }
+
+    /// Pass 👍/👎 reactions on the bot's replies to `on_reaction`.
+    pub fn with_reaction_feedback(mut self, on_reaction: OnReactionFn) -> Self {
+        self.feedback = ReactionFeedback::new(on_reaction);
+        self
+    }
+
+    /// Render the structured output of `renderer.tool()` as an embed under
+    /// replies, replacing the built-in renderer for that tool if any.
+    pub fn with_renderer(mut self, renderer: Arc<dyn ResponseRenderer>) -> Self {
+        self.renderers.register(renderer);
+        self
+    }
+
+    /// Drop redelivered messages using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
     /// Create a `DiscordChannel` from the generic `ChannelConfig` settings.
     pub fn from_settings(
         settings: &std::collections::HashMap<String, serde_json::Value>,
@@ -159,6 +218,11 @@ impl DiscordChannel {
         Ok(Self::new(config, on_message))
     }
 
+    /// Number of times the client has been restarted after a failure.
+    pub fn reconnect_count(&self) -> u32 {
+        self.reconnect_count.load(Ordering::Relaxed)
+    }
+
     /// Subscribe to channel events.
     ///
     /// Returns a broadcast receiver that will receive all `ChannelEvent`s
@@ -178,14 +242,12 @@ impl DiscordChannel {
             .as_ref()
             .ok_or_else(|| Error::Channel("not connected to Discord".into()))?;
 
-        let channel = serenity_model::ChannelId::new(discord_channel_id);
-        let builder = CreateMessage::new().embed(embed);
-        channel
-            .send_message(http.as_ref(), builder)
-            .await
-            .map_err(|e| Error::Channel(format!("failed to send embed: {e}")))?;
-
-        Ok(())
+        send_embed(
+            http,
+            serenity_model::ChannelId::new(discord_channel_id),
+            embed,
+        )
+        .await
     }
 
     /// Send a file attachment to a specific Discord channel.
@@ -216,6 +278,7 @@ impl DiscordChannel {
 pub struct DiscordSender {
     http: std::sync::Arc<serenity_model::Http>,
     name: String,
+    limiter: Arc<RateLimiter>,
 }
 
 #[async_trait]
@@ -228,9 +291,17 @@ impl ChannelSender for DiscordSender {
         &self.name
     }
 
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        Some(Arc::clone(&self.limiter))
+    }
+
     async fn send_message(&self, message: &Message) -> Result<()> {
         discord_send_message(&self.http, message).await
     }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        discord_send_typing(&self.http, channel_ref).await
+    }
 }
 
 #[async_trait]
@@ -243,6 +314,7 @@ impl ChannelLifecycle for DiscordChannel {
         Box::new(DiscordSender {
             http: std::sync::Arc::new(serenity_model::Http::new(&self.config.bot_token)),
             name: self.name.clone(),
+            limiter: Arc::clone(&self.limiter),
         })
     }
 
@@ -254,34 +326,86 @@ impl ChannelLifecycle for DiscordChannel {
         self.status = ChannelStatus::Connecting;
         info!("connecting to Discord...");
 
-        let handler = DiscordHandler::new(
-            self.event_tx.clone(),
-            "discord".to_string(),
-            self.config.guild_ids.clone(),
-            Arc::clone(&self.on_message),
-            Arc::clone(&self.group_filter),
-        );
+        #[cfg(not(feature = "discord-voice"))]
+        if self.config.voice.is_some() {
+            warn!(
+                "discord voice channel is configured but this build lacks the discord-voice feature"
+            );
+        }
 
-        let mut client =
-            serenity_model::Client::builder(&self.config.bot_token, self.config.intents)
-                .event_handler(handler)
-                .await
-                .map_err(|e| Error::Channel(format!("failed to build Discord client: {e}")))?;
+        let client = build_client(
+            &self.config,
+            &self.event_tx,
+            &self.on_message,
+            &self.group_filter,
+            &self.edits,
+            &self.feedback,
+            &self.renderers,
+            &self.dedup,
+            &self.limiter,
+        )
+        .await?;
 
-        // Store the HTTP client for sending messages
+        // Store the HTTP client for sending messages. It only carries the bot
+        // token, so it stays valid across client restarts.
         self.http = Some(client.http.clone());
 
-        // Store the shard manager for graceful shutdown
-        self.shard_manager = Some(client.shard_manager.clone());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        self.shutdown_tx = Some(shutdown_tx);
 
-        // Spawn the client in a background task
+        // Spawn the client under a supervisor that rebuilds and restarts it
+        // with backoff whenever it stops with an error.
+        let config = self.config.clone();
         let event_tx = self.event_tx.clone();
-        let handle = tokio::spawn(async move {
-            if let Err(e) = client.start().await {
-                error!("Discord client error: {e}");
-                let _ = event_tx.send(ChannelEvent::Error(format!("Discord client error: {e}")));
+        let on_message = Arc::clone(&self.on_message);
+        let group_filter = Arc::clone(&self.group_filter);
+        let edits = self.edits.clone();
+        let feedback = self.feedback.clone();
+        let renderers = self.renderers.clone();
+        let dedup = self.dedup.clone();
+        let limiter = Arc::clone(&self.limiter);
+        let shard_slot = Arc::clone(&self.shard_manager);
+        let mut first_client = Some(client);
+        let start = move || {
+            let first = first_client.take();
+            let config = config.clone();
+            let event_tx = event_tx.clone();
+            let on_message = Arc::clone(&on_message);
+            let group_filter = Arc::clone(&group_filter);
+            let edits = edits.clone();
+            let feedback = feedback.clone();
+            let renderers = renderers.clone();
+            let dedup = dedup.clone();
+            let limiter = Arc::clone(&limiter);
+            let shard_slot = Arc::clone(&shard_slot);
+            async move {
+                let mut client = match first {
+                    Some(client) => client,
+                    None => build_client(
+                        &config,
+                        &event_tx,
+                        &on_message,
+                        &group_filter,
+                        &edits,
+                        &feedback,
+                        &renderers,
+                        &dedup,
+                        &limiter,
+                    )
+                    .await
+                    .map_err(|e| e.to_string())?,
+                };
+                *shard_slot.lock().unwrap() = Some(client.shard_manager.clone());
+                client.start().await.map_err(|e| e.to_string())
             }
-        });
+        };
+        let handle = tokio::spawn(supervise_client(
+            start,
+            self.event_tx.clone(),
+            Arc::clone(&self.reconnect_count),
+            ReconnectPolicy::default(),
+            shutdown_rx,
+        ));
 
         self.client_handle = Some(handle);
         self.status = ChannelStatus::Connected;
@@ -297,8 +421,12 @@ impl ChannelLifecycle for DiscordChannel {
 
         info!("disconnecting from Discord...");
 
-        // Signal the shard manager to shut down
-        if let Some(shard_manager) = self.shard_manager.take() {
+        // Stop the supervisor from restarting, then shut down the running shards
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(true);
+        }
+        let shard_manager = self.shard_manager.lock().unwrap().take();
+        if let Some(shard_manager) = shard_manager {
             shard_manager.shutdown_all().await;
         }
 
@@ -319,6 +447,16 @@ impl ChannelLifecycle for DiscordChannel {
     }
 
     fn status(&self) -> ChannelStatus {
+        // The client task ends when the supervisor gives up or the client
+        // stops on its own; either way the bot no longer receives messages.
+        if matches!(self.status, ChannelStatus::Connected)
+            && self
+                .client_handle
+                .as_ref()
+                .is_some_and(|handle| handle.is_finished())
+        {
+            return ChannelStatus::Error("Discord client stopped".into());
+        }
         self.status.clone()
     }
 }
@@ -340,6 +478,147 @@ impl ChannelSender for DiscordChannel {
             .ok_or_else(|| Error::Channel("not connected to Discord".into()))?;
         discord_send_message(http, message).await
     }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        let http = self
+            .http
+            .as_ref()
+            .ok_or_else(|| Error::Channel("not connected to Discord".into()))?;
+        discord_send_typing(http, channel_ref).await
+    }
+}
+
+/// Build a serenity client wired to the OpenCrust event handler.
+#[allow(clippy::too_many_arguments)]
+async fn build_client(
+    config: &DiscordConfig,
+    event_tx: &broadcast::Sender<ChannelEvent>,
+    on_message: &DiscordOnMessageFn,
+    group_filter: &DiscordGroupFilter,
+    edits: &EditRegeneration,
+    feedback: &ReactionFeedback,
+    renderers: &ResponseRenderers,
+    dedup: &InboundDedup,
+    limiter: &Arc<RateLimiter>,
+) -> Result<serenity_model::Client> {
+    let handler = DiscordHandler::new(
+        event_tx.clone(),
+        "discord".to_string(),
+        config.guild_ids.clone(),
+        Arc::clone(on_message),
+        Arc::clone(group_filter),
+    )
+    .with_edits(edits.clone())
+    .with_feedback(feedback.clone())
+    .with_renderers(renderers.clone())
+    .with_dedup(dedup.clone())
+    .with_limiter(Arc::clone(limiter));
+    #[cfg(feature = "discord-voice")]
+    let handler = handler.with_voice(config.voice);
+    let builder =
+        serenity_model::Client::builder(&config.bot_token, config.intents).event_handler(handler);
+    #[cfg(feature = "discord-voice")]
+    let builder = songbird::serenity::SerenityInit::register_songbird_from_config(
+        builder,
+        voice::songbird_config(),
+    );
+    builder
+        .await
+        .map_err(|e| Error::Channel(format!("failed to build Discord client: {e}")))
+}
+
+/// Send `embed` as its own message in `channel`.
+pub(crate) async fn send_embed(
+    http: &serenity_model::Http,
+    channel: serenity_model::ChannelId,
+    embed: serenity_model::CreateEmbed,
+) -> Result<()> {
+    channel
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await
+        .map_err(|e| Error::Channel(format!("failed to send embed: {e}")))?;
+    Ok(())
+}
+
+/// Retry limits for `supervise_client`.
+#[derive(Debug, Clone, Copy)]
+struct ReconnectPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: MAX_RECONNECT_ATTEMPTS,
+            initial_delay: INITIAL_RECONNECT_DELAY,
+            max_delay: MAX_RECONNECT_DELAY,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before reconnect attempt `attempt` (1-based).
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay)
+    }
+}
+
+/// Run the client produced by `start` until it stops cleanly or shutdown is
+/// requested. Each error restarts it after an exponential backoff, up to
+/// `policy.max_attempts` consecutive failures.
+async fn supervise_client<F, Fut>(
+    mut start: F,
+    event_tx: broadcast::Sender<ChannelEvent>,
+    reconnect_count: Arc<AtomicU32>,
+    policy: ReconnectPolicy,
+    mut shutdown_rx: watch::Receiver<bool>,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<(), String>>,
+{
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let err = match start().await {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        if *shutdown_rx.borrow() {
+            return;
+        }
+
+        error!("Discord client error: {err}");
+        let _ = event_tx.send(ChannelEvent::Error(format!("Discord client error: {err}")));
+
+        if started.elapsed() >= STABLE_RUN {
+            attempt = 0;
+        }
+        attempt += 1;
+        if attempt > policy.max_attempts {
+            error!(
+                "Discord client failed {} times in a row, giving up",
+                policy.max_attempts
+            );
+            let _ = event_tx.send(ChannelEvent::StatusChanged(ChannelStatus::Error(err)));
+            return;
+        }
+
+        let delay = policy.delay(attempt);
+        warn!(
+            "Discord client restarting in {delay:?} (attempt {attempt}/{})",
+            policy.max_attempts
+        );
+        let _ = event_tx.send(ChannelEvent::StatusChanged(ChannelStatus::Reconnecting));
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown_rx.changed() => return,
+        }
+        reconnect_count.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 /// Shared send logic used by both `DiscordChannel` and `DiscordSender`.
@@ -354,6 +633,20 @@ async fn discord_send_message(http: &serenity_model::Http, message: &Message) ->
         })?;
 
     let channel = serenity_model::ChannelId::new(discord_channel_id);
+    if let MessageContent::Attachment(attachment) = &message.content {
+        let file =
+            CreateAttachment::bytes(attachment.data.clone(), attachment.filename_or_default());
+        let mut builder = CreateMessage::new().add_file(file);
+        if let Some(caption) = &attachment.caption {
+            builder = builder.content(convert::to_discord_markdown(caption));
+        }
+        channel
+            .send_message(http, builder)
+            .await
+            .map_err(|e| Error::Channel(format!("failed to send file: {e}")))?;
+        return Ok(());
+    }
+
     let text = convert::to_discord_markdown(&convert::opencrust_content_to_text(&message.content));
     let chunks = convert::split_discord_chunks(&text);
     for chunk in chunks {
@@ -367,9 +660,23 @@ async fn discord_send_message(http: &serenity_model::Http, message: &Message) ->
     Ok(())
 }
 
+/// Show the typing indicator in the Discord channel `channel_ref`.
+async fn discord_send_typing(http: &serenity_model::Http, channel_ref: &str) -> Result<()> {
+    let channel_id = channel_ref
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .ok_or_else(|| Error::Channel(format!("invalid Discord channel id: {channel_ref}")))?;
+    serenity_model::ChannelId::new(channel_id)
+        .broadcast_typing(http)
+        .await
+        .map_err(|e| Error::Channel(format!("failed to send typing indicator: {e}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use opencrust_common::MediaKind;
     use std::collections::HashMap;
 
     fn test_config() -> DiscordConfig {
@@ -379,6 +686,7 @@ mod tests {
             guild_ids: vec![],
             intents: serenity_model::GatewayIntents::default(),
             prefix: None,
+            voice: None,
         }
     }
 
@@ -392,6 +700,27 @@ mod tests {
         assert_eq!(channel.status(), ChannelStatus::Disconnected);
     }
 
+    #[tokio::test]
+    async fn stopped_client_task_reports_error() {
+        let on_msg: DiscordOnMessageFn =
+            Arc::new(|_ch, _uid, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("test".to_string())) })
+            });
+        let mut channel = DiscordChannel::new(test_config(), on_msg);
+        channel.status = ChannelStatus::Connected;
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        channel.client_handle = Some(tokio::spawn(async {
+            let _ = stop_rx.await;
+        }));
+        assert_eq!(channel.status(), ChannelStatus::Connected);
+
+        let _ = stop_tx.send(());
+        while !channel.client_handle.as_ref().unwrap().is_finished() {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(channel.status(), ChannelStatus::Error(_)));
+    }
+
     #[test]
     fn channel_type_returns_discord() {
         let on_msg: DiscordOnMessageFn =
@@ -506,28 +835,21 @@ mod tests {
         assert!(err.to_string().contains("not connected"));
     }
 
-    // --- DiscordFile / file-ingest tests ---
+    // --- Attachment / file-ingest tests ---
 
     #[test]
-    fn discord_file_fields_accessible() {
-        let file = DiscordFile {
-            filename: "report.pdf".to_string(),
-            data: vec![1, 2, 3],
-            content_type: Some("application/pdf".to_string()),
-        };
-        assert_eq!(file.filename, "report.pdf");
+    fn discord_attachment_kind_follows_content_type() {
+        let file =
+            MediaAttachment::new(MediaKind::from_mime(Some("application/pdf")), vec![1, 2, 3])
+                .with_filename("report.pdf")
+                .with_mime_type(Some("application/pdf".to_string()));
+        assert_eq!(file.kind, MediaKind::Document);
+        assert_eq!(file.filename_or_default(), "report.pdf");
         assert_eq!(file.data.len(), 3);
-        assert_eq!(file.content_type.as_deref(), Some("application/pdf"));
-    }
 
-    #[test]
-    fn discord_file_content_type_optional() {
-        let file = DiscordFile {
-            filename: "data.bin".to_string(),
-            data: vec![],
-            content_type: None,
-        };
-        assert!(file.content_type.is_none());
+        let photo = MediaAttachment::new(MediaKind::from_mime(Some("image/png")), vec![]);
+        assert_eq!(photo.kind, MediaKind::Photo);
+        assert!(photo.mime_type.is_none());
     }
 
     #[tokio::test]
@@ -536,17 +858,14 @@ mod tests {
             Arc::new(|_ch, _uid, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
             });
 
-        let discord_file = DiscordFile {
-            filename: "slides.pdf".to_string(),
-            data: vec![0u8; 16],
-            content_type: Some("application/pdf".to_string()),
-        };
+        let discord_file =
+            MediaAttachment::new(MediaKind::Document, vec![0u8; 16]).with_filename("slides.pdf");
 
         let result = on_msg(
             "C123".to_string(),
@@ -568,7 +887,7 @@ mod tests {
             Arc::new(|_ch, _uid, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
@@ -624,4 +943,124 @@ mod tests {
 
         assert_eq!(result.text(), "synthesized reply");
     }
+
+    fn fast_policy(max_attempts: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_cap() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(4), Duration::from_secs(8));
+        assert_eq!(policy.delay(30), MAX_RECONNECT_DELAY);
+    }
+
+    #[tokio::test]
+    async fn failing_client_is_restarted() {
+        let (event_tx, mut events) = broadcast::channel(16);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let reconnects = Arc::new(AtomicU32::new(0));
+        let starts = Arc::new(AtomicU32::new(0));
+
+        let counter = Arc::clone(&starts);
+        let start = move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if n == 0 {
+                    Err("gateway closed".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        supervise_client(
+            start,
+            event_tx,
+            Arc::clone(&reconnects),
+            fast_policy(3),
+            shutdown_rx,
+        )
+        .await;
+
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+        assert!(
+            matches!(events.try_recv(), Ok(ChannelEvent::Error(e)) if e.contains("gateway closed"))
+        );
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChannelEvent::StatusChanged(ChannelStatus::Reconnecting))
+        ));
+    }
+
+    #[tokio::test]
+    async fn supervisor_gives_up_after_max_attempts() {
+        let (event_tx, mut events) = broadcast::channel(64);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let reconnects = Arc::new(AtomicU32::new(0));
+        let starts = Arc::new(AtomicU32::new(0));
+
+        let counter = Arc::clone(&starts);
+        let start = move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err("invalid token".to_string()) }
+        };
+        supervise_client(
+            start,
+            event_tx,
+            Arc::clone(&reconnects),
+            fast_policy(2),
+            shutdown_rx,
+        )
+        .await;
+
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(reconnects.load(Ordering::SeqCst), 2);
+        let mut last = None;
+        while let Ok(event) = events.try_recv() {
+            last = Some(event);
+        }
+        assert!(matches!(
+            last,
+            Some(ChannelEvent::StatusChanged(ChannelStatus::Error(e))) if e == "invalid token"
+        ));
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_reconnecting() {
+        let (event_tx, _events) = broadcast::channel(16);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let starts = Arc::new(AtomicU32::new(0));
+
+        let counter = Arc::clone(&starts);
+        let start = move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err("boom".to_string()) }
+        };
+        let policy = ReconnectPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(60),
+        };
+        let task = tokio::spawn(supervise_client(
+            start,
+            event_tx,
+            Arc::new(AtomicU32::new(0)),
+            policy,
+            shutdown_rx,
+        ));
+        tokio::task::yield_now().await;
+        shutdown_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("supervisor should stop during backoff")
+            .unwrap();
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+    }
 }
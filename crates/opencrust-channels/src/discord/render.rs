@@ -0,0 +1,282 @@
+//! Rich rendering of structured tool output as Discord embeds.
+//!
+//! Tools attach structured data to their output (see `ToolOutput::data`);
+//! the gateway forwards it as [`ChannelResponse::ToolResults`] and the Discord
+//! handler asks a [`ResponseRenderer`] registered for the tool to turn it into
+//! an embed shown under the reply.
+//!
+//! [`ChannelResponse::ToolResults`]: crate::traits::ChannelResponse::ToolResults
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+
+use crate::traits::ToolResult;
+
+/// Discord limits on embed content.
+const MAX_TITLE: usize = 256;
+const MAX_FIELD_NAME: usize = 256;
+const MAX_FIELD_VALUE: usize = 1024;
+const MAX_FIELDS: usize = 25;
+const MAX_EMBEDS: usize = 10;
+
+/// Matches shown in a `search_files` embed before the rest are summarized.
+const MAX_FILE_MATCHES: usize = 10;
+
+/// Turns the structured output of one tool into a Discord embed.
+pub trait ResponseRenderer: Send + Sync {
+    /// Name of the tool whose output this renderer handles (e.g. `web_search`).
+    fn tool(&self) -> &str;
+
+    /// Build an embed from the tool's data, or `None` to show nothing.
+    fn render(&self, data: &Value) -> Option<CreateEmbed>;
+}
+
+/// The renderers available to a Discord channel, keyed by tool name.
+///
+/// Starts with the built-in renderers; a renderer registered later for the
+/// same tool replaces the earlier one.
+#[derive(Clone)]
+pub struct ResponseRenderers {
+    renderers: Vec<Arc<dyn ResponseRenderer>>,
+}
+
+impl Default for ResponseRenderers {
+    fn default() -> Self {
+        Self {
+            renderers: vec![Arc::new(WebSearchRenderer), Arc::new(SearchFilesRenderer)],
+        }
+    }
+}
+
+impl ResponseRenderers {
+    /// Add `renderer`, replacing any renderer for the same tool.
+    pub fn register(&mut self, renderer: Arc<dyn ResponseRenderer>) {
+        self.renderers.retain(|r| r.tool() != renderer.tool());
+        self.renderers.push(renderer);
+    }
+
+    /// Embeds for the `results` that have a renderer, capped at Discord's
+    /// per-message limit.
+    pub fn render(&self, results: &[ToolResult]) -> Vec<CreateEmbed> {
+        results
+            .iter()
+            .filter_map(|result| {
+                self.renderers
+                    .iter()
+                    .find(|r| r.tool() == result.tool)
+                    .and_then(|r| r.render(&result.data))
+            })
+            .take(MAX_EMBEDS)
+            .collect()
+    }
+}
+
+/// `web_search` results: one field per hit, titled and linked.
+pub struct WebSearchRenderer;
+
+impl ResponseRenderer for WebSearchRenderer {
+    fn tool(&self) -> &str {
+        "web_search"
+    }
+
+    fn render(&self, data: &Value) -> Option<CreateEmbed> {
+        let results = data.get("results")?.as_array()?;
+        if results.is_empty() {
+            return None;
+        }
+        let query = str_field(data, "query");
+        let mut embed = CreateEmbed::new().title(truncate(
+            &format!("Search results for \"{query}\""),
+            MAX_TITLE,
+        ));
+        for result in results.iter().take(MAX_FIELDS) {
+            let title = str_field(result, "title");
+            let url = str_field(result, "url");
+            let description = str_field(result, "description");
+            let value = if description.is_empty() {
+                url.to_string()
+            } else {
+                format!("{url}\n{description}")
+            };
+            embed = embed.field(
+                truncate(or_untitled(title), MAX_FIELD_NAME),
+                truncate(&value, MAX_FIELD_VALUE),
+                false,
+            );
+        }
+        Some(embed)
+    }
+}
+
+/// `search_files` matches: grouped by file, one line per match.
+pub struct SearchFilesRenderer;
+
+impl ResponseRenderer for SearchFilesRenderer {
+    fn tool(&self) -> &str {
+        "search_files"
+    }
+
+    fn render(&self, data: &Value) -> Option<CreateEmbed> {
+        let matches = data.get("matches")?.as_array()?;
+        if matches.is_empty() {
+            return None;
+        }
+        let pattern = str_field(data, "pattern");
+
+        // Group matches by file, keeping the order files were found in.
+        let mut files: Vec<(&str, Vec<String>)> = Vec::new();
+        for m in matches.iter().take(MAX_FILE_MATCHES) {
+            let path = str_field(m, "path");
+            let line = m.get("line").and_then(Value::as_u64).unwrap_or(0);
+            let entry = format!("`{line}` {}", str_field(m, "text").trim());
+            match files.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, lines)) => lines.push(entry),
+                None => files.push((path, vec![entry])),
+            }
+        }
+
+        let mut embed =
+            CreateEmbed::new().title(truncate(&format!("Matches for /{pattern}/"), MAX_TITLE));
+        for (path, lines) in files.into_iter().take(MAX_FIELDS) {
+            embed = embed.field(
+                truncate(or_untitled(path), MAX_FIELD_NAME),
+                truncate(&lines.join("\n"), MAX_FIELD_VALUE),
+                false,
+            );
+        }
+
+        let truncated = data.get("truncated").and_then(Value::as_bool) == Some(true);
+        let hidden = matches.len().saturating_sub(MAX_FILE_MATCHES);
+        if hidden > 0 || truncated {
+            let more = if truncated { "+" } else { "" };
+            embed = embed.footer(CreateEmbedFooter::new(format!(
+                "{} match(es){more}, showing {}",
+                matches.len(),
+                matches.len().min(MAX_FILE_MATCHES)
+            )));
+        }
+        Some(embed)
+    }
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+/// Discord rejects embeds with empty field names.
+fn or_untitled(name: &str) -> &str {
+    if name.trim().is_empty() {
+        "(untitled)"
+    } else {
+        name
+    }
+}
+
+/// Cut `text` to at most `max` characters, marking the cut with `…`.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn embed_json(embed: &CreateEmbed) -> Value {
+        serde_json::to_value(embed).unwrap()
+    }
+
+    #[test]
+    fn web_search_results_become_fields() {
+        let data = json!({
+            "query": "rust",
+            "results": [
+                {"title": "Rust", "url": "https://www.rust-lang.org", "description": "A language."},
+                {"title": "", "url": "https://crates.io", "description": ""},
+            ],
+        });
+        let embed = embed_json(&WebSearchRenderer.render(&data).unwrap());
+        assert_eq!(embed["title"], "Search results for \"rust\"");
+        assert_eq!(embed["fields"][0]["name"], "Rust");
+        assert_eq!(
+            embed["fields"][0]["value"],
+            "https://www.rust-lang.org\nA language."
+        );
+        assert_eq!(embed["fields"][1]["name"], "(untitled)");
+        assert_eq!(embed["fields"][1]["value"], "https://crates.io");
+
+        assert!(
+            WebSearchRenderer
+                .render(&json!({"query": "x", "results": []}))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn search_files_matches_group_by_file() {
+        let matches: Vec<Value> = (1..=12)
+            .map(|i| json!({"path": if i % 2 == 0 { "b.rs" } else { "a.rs" }, "line": i, "text": format!("  hit {i}")}))
+            .collect();
+        let data = json!({"pattern": "hit", "truncated": false, "matches": matches});
+        let embed = embed_json(&SearchFilesRenderer.render(&data).unwrap());
+        assert_eq!(embed["title"], "Matches for /hit/");
+        assert_eq!(embed["fields"][0]["name"], "a.rs");
+        assert!(
+            embed["fields"][0]["value"]
+                .as_str()
+                .unwrap()
+                .starts_with("`1` hit 1\n`3` hit 3")
+        );
+        assert_eq!(embed["fields"][1]["name"], "b.rs");
+        assert_eq!(embed["footer"]["text"], "12 match(es), showing 10");
+    }
+
+    #[test]
+    fn renderers_pick_by_tool_and_can_be_replaced() {
+        struct Shout;
+        impl ResponseRenderer for Shout {
+            fn tool(&self) -> &str {
+                "web_search"
+            }
+            fn render(&self, _data: &Value) -> Option<CreateEmbed> {
+                Some(CreateEmbed::new().title("SHOUT"))
+            }
+        }
+
+        let results = vec![
+            ToolResult {
+                tool: "bash".into(),
+                data: json!({}),
+            },
+            ToolResult {
+                tool: "web_search".into(),
+                data: json!({"query": "q", "results": [{"title": "t", "url": "u"}]}),
+            },
+        ];
+        let mut renderers = ResponseRenderers::default();
+        let embeds = renderers.render(&results);
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embed_json(&embeds[0])["title"], "Search results for \"q\"");
+
+        renderers.register(Arc::new(Shout));
+        let embeds = renderers.render(&results);
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embed_json(&embeds[0])["title"], "SHOUT");
+    }
+
+    #[test]
+    fn truncate_respects_char_limits() {
+        assert_eq!(truncate("short", 10), "short");
+        let long = "é".repeat(300);
+        let cut = truncate(&long, MAX_TITLE);
+        assert_eq!(cut.chars().count(), MAX_TITLE);
+        assert!(cut.ends_with('…'));
+    }
+}
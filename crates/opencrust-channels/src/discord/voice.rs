@@ -0,0 +1,335 @@
+//! Listening in a Discord voice channel (`discord-voice` feature).
+//!
+//! The bot joins the configured voice channel and cuts what each member says
+//! into utterances at pauses. Every utterance reaches the message callback as
+//! a WAV voice attachment for the gateway to transcribe; the answer is posted
+//! in the paired text channel, and spoken in the call when it comes back as
+//! voice.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use opencrust_common::{MediaAttachment, MediaKind};
+use serenity::all::{self as serenity_model, Context};
+use songbird::driver::{Channels, DecodeMode, SampleRate};
+use songbird::{Call, CoreEvent, Event, EventContext, EventHandler};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::config::DiscordVoiceConfig;
+use super::handler::sync_discord_chunks;
+use super::{DiscordGroupFilter, DiscordOnMessageFn};
+use crate::queue::RateLimiter;
+use crate::traits::ChannelResponse;
+
+/// Rate received audio is decoded at; plenty for speech recognition.
+const SAMPLE_RATE: u32 = 16_000;
+/// Voice ticks (20ms each) of silence that end an utterance.
+const END_SILENCE_TICKS: u32 = 50;
+/// Utterances shorter than half a second are noise and dropped.
+const MIN_UTTERANCE_SAMPLES: usize = SAMPLE_RATE as usize / 2;
+/// Long monologues are cut into 30 second pieces.
+const MAX_UTTERANCE_SAMPLES: usize = SAMPLE_RATE as usize * 30;
+
+/// Songbird configuration decoding received audio to 16 kHz mono.
+pub(super) fn songbird_config() -> songbird::Config {
+    songbird::Config::default()
+        .decode_mode(DecodeMode::Decode)
+        .decode_channels(Channels::Mono)
+        .decode_sample_rate(SampleRate::Hz16000)
+}
+
+/// Join the voice channel in `voice` and answer what is said there, until
+/// the bot leaves the call or rejoins it after a reconnect.
+pub(super) async fn listen(
+    ctx: Context,
+    voice: DiscordVoiceConfig,
+    on_message: DiscordOnMessageFn,
+    group_filter: DiscordGroupFilter,
+    limiter: Arc<RateLimiter>,
+) {
+    let Some(manager) = songbird::get(&ctx).await else {
+        warn!("discord voice: songbird is not registered with the client");
+        return;
+    };
+    let call = match manager
+        .join(
+            serenity_model::GuildId::new(voice.guild_id),
+            serenity_model::ChannelId::new(voice.channel_id),
+        )
+        .await
+    {
+        Ok(call) => call,
+        Err(e) => {
+            warn!(
+                "discord voice: failed to join channel {}: {e}",
+                voice.channel_id
+            );
+            return;
+        }
+    };
+    info!("discord voice: listening in channel {}", voice.channel_id);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let receiver = Receiver {
+        users: Arc::default(),
+        utterances: Arc::default(),
+        tx,
+    };
+    {
+        let mut handle = call.lock().await;
+        // Drop the receiver from an earlier join, which ends its loop below.
+        handle.remove_all_global_events();
+        for event in [
+            CoreEvent::SpeakingStateUpdate,
+            CoreEvent::VoiceTick,
+            CoreEvent::ClientDisconnect,
+        ] {
+            handle.add_global_event(event.into(), receiver.clone());
+        }
+    }
+    drop(receiver);
+
+    let text_channel = serenity_model::ChannelId::new(voice.text_channel_id);
+    while let Some((user_id, samples)) = rx.recv().await {
+        // Speaking in the bot's voice channel counts as mentioning it.
+        if !group_filter(true) {
+            continue;
+        }
+        answer(
+            &ctx,
+            &call,
+            text_channel,
+            &on_message,
+            &limiter,
+            user_id,
+            &samples,
+        )
+        .await;
+    }
+}
+
+/// Run a turn for one utterance and deliver the reply.
+async fn answer(
+    ctx: &Context,
+    call: &tokio::sync::Mutex<Call>,
+    text_channel: serenity_model::ChannelId,
+    on_message: &DiscordOnMessageFn,
+    limiter: &RateLimiter,
+    user_id: u64,
+    samples: &[i16],
+) {
+    let user_name = match serenity_model::UserId::new(user_id).to_user(ctx).await {
+        Ok(user) => user.global_name.unwrap_or(user.name),
+        Err(_) => user_id.to_string(),
+    };
+    info!(
+        "discord voice: {:.1}s utterance from {user_name}",
+        samples.len() as f32 / SAMPLE_RATE as f32
+    );
+
+    let audio = MediaAttachment::new(MediaKind::Voice, wav(samples))
+        .with_filename("voice.wav")
+        .with_mime_type(Some("audio/wav".to_string()));
+    let result = on_message(
+        text_channel.to_string(),
+        user_id.to_string(),
+        user_name,
+        String::new(),
+        true,
+        Some(audio),
+        None,
+    )
+    .await;
+
+    let text = match result {
+        Ok(ChannelResponse::Voice { text, audio }) => {
+            call.lock().await.play_input(audio.into());
+            text
+        }
+        Ok(response) => response.text().to_string(),
+        Err(e) if e == "__blocked__" => return,
+        Err(e) => {
+            warn!("discord voice: turn failed: {e}");
+            return;
+        }
+    };
+    if text.trim().is_empty() {
+        return;
+    }
+    if let Err(e) =
+        sync_discord_chunks(ctx, limiter, text_channel, &text, &mut Vec::new(), true).await
+    {
+        warn!("discord voice: {e}");
+    }
+}
+
+/// Songbird event handler collecting utterances from the call.
+#[derive(Clone)]
+struct Receiver {
+    /// Discord user behind each audio stream (SSRC).
+    users: Arc<Mutex<HashMap<u32, u64>>>,
+    utterances: Arc<Mutex<Utterances>>,
+    tx: mpsc::UnboundedSender<(u64, Vec<i16>)>,
+}
+
+#[async_trait]
+impl EventHandler for Receiver {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        match ctx {
+            EventContext::SpeakingStateUpdate(speaking) => {
+                if let Some(user) = speaking.user_id {
+                    self.users.lock().unwrap().insert(speaking.ssrc, user.0);
+                }
+            }
+            EventContext::VoiceTick(tick) => {
+                let done = self.utterances.lock().unwrap().tick(
+                    tick.speaking
+                        .iter()
+                        .filter_map(|(ssrc, data)| Some((*ssrc, data.decoded_voice.as_deref()?))),
+                );
+                let users = self.users.lock().unwrap();
+                for (ssrc, samples) in done {
+                    // Audio from a stream nobody has claimed yet is dropped.
+                    if let Some(&user) = users.get(&ssrc) {
+                        let _ = self.tx.send((user, samples));
+                    }
+                }
+            }
+            EventContext::ClientDisconnect(disconnect) => {
+                self.users
+                    .lock()
+                    .unwrap()
+                    .retain(|_, user| *user != disconnect.user_id.0);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Splits the audio of each speaker into utterances at pauses.
+#[derive(Default)]
+struct Utterances {
+    speakers: HashMap<u32, Speech>,
+}
+
+#[derive(Default)]
+struct Speech {
+    samples: Vec<i16>,
+    silent_ticks: u32,
+}
+
+impl Utterances {
+    /// Add one voice tick, `voices` holding the audio of each stream that
+    /// spoke in it. Returns the utterances that ended with this tick.
+    fn tick<'a>(
+        &mut self,
+        voices: impl IntoIterator<Item = (u32, &'a [i16])>,
+    ) -> Vec<(u32, Vec<i16>)> {
+        for speech in self.speakers.values_mut() {
+            speech.silent_ticks += 1;
+        }
+        for (ssrc, samples) in voices {
+            let speech = self.speakers.entry(ssrc).or_default();
+            speech.samples.extend_from_slice(samples);
+            speech.silent_ticks = 0;
+        }
+
+        let mut done = Vec::new();
+        self.speakers.retain(|ssrc, speech| {
+            let finished = speech.silent_ticks >= END_SILENCE_TICKS
+                || speech.samples.len() >= MAX_UTTERANCE_SAMPLES;
+            if finished && speech.samples.len() >= MIN_UTTERANCE_SAMPLES {
+                done.push((*ssrc, std::mem::take(&mut speech.samples)));
+            }
+            !finished
+        });
+        done
+    }
+}
+
+/// Mono 16-bit `samples` at [`SAMPLE_RATE`] as a WAV file.
+fn wav(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    out.extend_from_slice(&2u16.to_le_bytes()); // block align
+    out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: [i16; 320] = [7; 320];
+
+    #[test]
+    fn utterance_ends_after_a_pause() {
+        let mut utterances = Utterances::default();
+        for _ in 0..40 {
+            assert!(utterances.tick([(1, &TICK[..])]).is_empty());
+        }
+        for _ in 0..END_SILENCE_TICKS - 1 {
+            assert!(utterances.tick([]).is_empty());
+        }
+        let done = utterances.tick([]);
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].0, 1);
+        assert_eq!(done[0].1.len(), 40 * TICK.len());
+        assert!(utterances.speakers.is_empty());
+    }
+
+    #[test]
+    fn short_noise_is_dropped_and_speakers_are_separate() {
+        let mut utterances = Utterances::default();
+        utterances.tick([(1, &TICK[..]), (2, &TICK[..])]);
+        for _ in 0..30 {
+            utterances.tick([(2, &TICK[..])]);
+        }
+        let mut done = Vec::new();
+        for _ in 0..END_SILENCE_TICKS {
+            done.extend(utterances.tick([]));
+        }
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].0, 2);
+    }
+
+    #[test]
+    fn long_speech_is_cut() {
+        let mut utterances = Utterances::default();
+        let ticks = MAX_UTTERANCE_SAMPLES / TICK.len();
+        let done: Vec<_> = (0..ticks)
+            .flat_map(|_| utterances.tick([(1, &TICK[..])]))
+            .collect();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].1.len(), MAX_UTTERANCE_SAMPLES);
+    }
+
+    #[test]
+    fn wav_header_describes_the_samples() {
+        let file = wav(&[1, -1]);
+        assert_eq!(file.len(), 48);
+        assert_eq!(&file[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(file[4..8].try_into().unwrap()), 40);
+        assert_eq!(&file[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(file[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(&file[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(file[40..44].try_into().unwrap()), 4);
+        assert_eq!(&file[44..], &[1, 0, 0xff, 0xff]);
+    }
+}
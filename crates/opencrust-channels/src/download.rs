@@ -0,0 +1,107 @@
+//! Size-limited reading of attachment downloads.
+
+use tracing::debug;
+
+/// Read `response`'s body, failing as soon as more than `limit` bytes arrive.
+///
+/// The body is read chunk by chunk, so an oversized or endless file is
+/// dropped after at most `limit` bytes plus one chunk instead of being
+/// buffered whole. A `Content-Length` above the limit is rejected before
+/// anything is read.
+///
+/// Errors read `"file too large: ..."` or `"file read failed: ..."`; callers
+/// prefix them with the channel name.
+pub async fn read_limited(
+    mut response: reqwest::Response,
+    limit: usize,
+) -> Result<Vec<u8>, String> {
+    if let Some(len) = response.content_length()
+        && len > limit as u64
+    {
+        return Err(format!(
+            "file too large: {len} bytes exceeds {limit} byte limit"
+        ));
+    }
+
+    let mut body = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("file read failed: {e}"))?
+    {
+        if body.len() + chunk.len() > limit {
+            return Err(format!(
+                "file too large: more than {limit} bytes received, download aborted"
+            ));
+        }
+        body.extend_from_slice(&chunk);
+        debug!(received = body.len(), limit, "attachment download progress");
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn reads_body_within_limit() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![7u8; 512]))
+            .mount(&server)
+            .await;
+
+        let resp = reqwest::get(server.uri()).await.unwrap();
+        assert_eq!(read_limited(resp, 1024).await.unwrap(), vec![7u8; 512]);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_content_length() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 2048]))
+            .mount(&server)
+            .await;
+
+        let resp = reqwest::get(server.uri()).await.unwrap();
+        let err = read_limited(resp, 1024).await.unwrap_err();
+        assert!(err.contains("2048 bytes exceeds 1024"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn aborts_endless_chunked_body_once_over_limit() {
+        // A server that never finishes its body and sends no Content-Length.
+        // Buffering the whole response would hang; reading it must stop early.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+            if socket.write_all(head.as_bytes()).await.is_err() {
+                return;
+            }
+            let chunk = format!("400\r\n{}\r\n", "x".repeat(0x400));
+            while socket.write_all(chunk.as_bytes()).await.is_ok() {}
+        });
+
+        let resp = reqwest::get(format!("http://{addr}")).await.unwrap();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            read_limited(resp, 8 * 1024),
+        )
+        .await
+        .expect("download should be aborted, not buffered to the end");
+        let err = result.unwrap_err();
+        assert!(err.contains("download aborted"), "{err}");
+    }
+}
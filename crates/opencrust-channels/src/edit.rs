@@ -0,0 +1,127 @@
+//! Regenerating a reply when the user edits their last message.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// Called before an edited message is run again, so the turn it replaces can
+/// be dropped from the session history.
+///
+/// Arguments: `(chat_id, thread_id, user_id, is_group)`. `thread_id` is only
+/// set by channels with threaded sessions (Slack).
+pub type OnEditFn = Arc<
+    dyn Fn(String, Option<String>, String, bool) -> Pin<Box<dyn Future<Output = ()> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Number of chats remembered before the oldest entries are forgotten.
+const MAX_CHATS: usize = 1024;
+
+/// Edit regeneration for one channel: the `on_edit` hook plus the last
+/// message answered in each chat. Clones share the same record.
+///
+/// Only an edit of the last answered message regenerates the reply; edits of
+/// older messages would rewrite turns the conversation has already moved past.
+#[derive(Clone, Default)]
+pub struct EditRegeneration {
+    on_edit: Option<OnEditFn>,
+    last: Arc<LastMessages>,
+}
+
+impl EditRegeneration {
+    pub fn new(on_edit: OnEditFn) -> Self {
+        Self {
+            on_edit: Some(on_edit),
+            last: Arc::default(),
+        }
+    }
+
+    /// Remember `message_id` as the latest message answered in `chat`.
+    pub fn record(&self, chat: &str, message_id: &str) {
+        if self.on_edit.is_some() {
+            self.last.record(chat, message_id);
+        }
+    }
+
+    /// The hook to run for an edit of `message_id`, or `None` when edits are
+    /// off or the message is not the latest one answered in `chat`.
+    pub fn hook_for(&self, chat: &str, message_id: &str) -> Option<&OnEditFn> {
+        self.on_edit
+            .as_ref()
+            .filter(|_| self.last.is_last(chat, message_id))
+    }
+}
+
+/// The last message answered in each chat.
+#[derive(Default)]
+struct LastMessages {
+    inner: Mutex<HashMap<String, (u64, String)>>,
+    counter: std::sync::atomic::AtomicU64,
+}
+
+impl LastMessages {
+    fn record(&self, chat: &str, message_id: &str) {
+        let seq = self
+            .counter
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let mut map = self.inner.lock().unwrap();
+        map.insert(chat.to_string(), (seq, message_id.to_string()));
+        if map.len() > MAX_CHATS
+            && let Some(oldest) = map
+                .iter()
+                .min_by_key(|(_, (seq, _))| *seq)
+                .map(|(chat, _)| chat.clone())
+        {
+            map.remove(&oldest);
+        }
+    }
+
+    fn is_last(&self, chat: &str, message_id: &str) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .get(chat)
+            .is_some_and(|(_, id)| id == message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_latest_message_counts() {
+        let last = LastMessages::default();
+        assert!(!last.is_last("c1", "1"));
+        last.record("c1", "1");
+        last.record("c1", "2");
+        last.record("c2", "1");
+        assert!(!last.is_last("c1", "1"));
+        assert!(last.is_last("c1", "2"));
+        assert!(last.is_last("c2", "1"));
+    }
+
+    #[test]
+    fn forgets_oldest_chat_past_capacity() {
+        let last = LastMessages::default();
+        for i in 0..=MAX_CHATS {
+            last.record(&format!("c{i}"), "1");
+        }
+        assert!(!last.is_last("c0", "1"));
+        assert!(last.is_last(&format!("c{MAX_CHATS}"), "1"));
+    }
+
+    #[test]
+    fn disabled_regeneration_has_no_hook() {
+        let edits = EditRegeneration::default();
+        edits.record("c1", "1");
+        assert!(edits.hook_for("c1", "1").is_none());
+
+        let edits = EditRegeneration::new(Arc::new(|_, _, _, _| Box::pin(async {})));
+        edits.record("c1", "1");
+        assert!(edits.hook_for("c1", "1").is_some());
+        assert!(edits.hook_for("c1", "2").is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Emoji reactions on the bot's replies, used as feedback on its answers.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// A thumbs-up or thumbs-down on one of the bot's replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    Positive,
+    Negative,
+}
+
+impl Feedback {
+    /// Map a reaction to feedback. Accepts the emoji itself (with or without
+    /// a skin tone) and Slack's reaction names (`+1`, `thumbsup`, ...).
+    pub fn from_reaction(reaction: &str) -> Option<Self> {
+        // Slack appends skin tones as `+1::skin-tone-2`.
+        let name = reaction.split("::").next().unwrap_or(reaction);
+        match name.trim_end_matches(|c| ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)) {
+            "👍" | "+1" | "thumbsup" => Some(Self::Positive),
+            "👎" | "-1" | "thumbsdown" => Some(Self::Negative),
+            _ => None,
+        }
+    }
+
+    /// Score added to the rated memory entry.
+    pub fn score(self) -> i64 {
+        match self {
+            Self::Positive => 1,
+            Self::Negative => -1,
+        }
+    }
+}
+
+/// A reply the bot sent, kept so a later reaction can be traced back to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentReply {
+    pub chat_id: String,
+    /// Thread the reply was posted in, for channels with threaded sessions (Slack).
+    pub thread_id: Option<String>,
+    /// User whose message the reply answered. With per-user group sessions
+    /// this decides the session, not the user who reacted.
+    pub user_id: String,
+    pub is_group: bool,
+    pub text: String,
+}
+
+/// Called when a user reacts to one of the bot's replies with 👍 or 👎.
+pub type OnReactionFn =
+    Arc<dyn Fn(SentReply, Feedback) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Number of replies remembered per channel before the oldest are forgotten.
+const MAX_REPLIES: usize = 256;
+
+/// Reaction feedback for one channel: the recent replies the bot sent and the
+/// optional `on_reaction` hook. Clones share the same record.
+#[derive(Clone, Default)]
+pub struct ReactionFeedback {
+    on_reaction: Option<OnReactionFn>,
+    replies: Arc<Mutex<VecDeque<(String, SentReply)>>>,
+}
+
+impl ReactionFeedback {
+    pub fn new(on_reaction: OnReactionFn) -> Self {
+        Self {
+            on_reaction: Some(on_reaction),
+            replies: Arc::default(),
+        }
+    }
+
+    /// Remember that `message_id` carries `reply`. Long replies sent as
+    /// several messages are recorded once per message.
+    pub fn record(&self, message_id: &str, reply: SentReply) {
+        let mut replies = self.replies.lock().unwrap();
+        if replies.len() >= MAX_REPLIES {
+            replies.pop_front();
+        }
+        replies.push_back((message_id.to_string(), reply));
+    }
+
+    /// The bot reply sent as `message_id` in `chat_id`, if it is recent enough
+    /// to be remembered.
+    pub fn reply(&self, chat_id: &str, message_id: &str) -> Option<SentReply> {
+        self.replies
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(id, reply)| id == message_id && reply.chat_id == chat_id)
+            .map(|(_, reply)| reply.clone())
+    }
+
+    /// Run the `on_reaction` hook when `reaction` is a 👍 or 👎.
+    pub async fn react(&self, reply: SentReply, reaction: &str) {
+        if let (Some(on_reaction), Some(feedback)) =
+            (&self.on_reaction, Feedback::from_reaction(reaction))
+        {
+            on_reaction(reply, feedback).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(chat_id: &str, text: &str) -> SentReply {
+        SentReply {
+            chat_id: chat_id.to_string(),
+            thread_id: None,
+            user_id: "u1".to_string(),
+            is_group: false,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn maps_thumbs_reactions() {
+        assert_eq!(Feedback::from_reaction("👍"), Some(Feedback::Positive));
+        assert_eq!(Feedback::from_reaction("👍🏽"), Some(Feedback::Positive));
+        assert_eq!(
+            Feedback::from_reaction("+1::skin-tone-3"),
+            Some(Feedback::Positive)
+        );
+        assert_eq!(
+            Feedback::from_reaction("thumbsdown"),
+            Some(Feedback::Negative)
+        );
+        assert_eq!(Feedback::from_reaction("👎"), Some(Feedback::Negative));
+        assert_eq!(Feedback::from_reaction("🔥"), None);
+    }
+
+    #[test]
+    fn replies_are_keyed_by_chat_and_message() {
+        let feedback = ReactionFeedback::default();
+        feedback.record("1", reply("c1", "first"));
+        feedback.record("1", reply("c2", "other chat"));
+        assert_eq!(feedback.reply("c1", "1").unwrap().text, "first");
+        assert_eq!(feedback.reply("c2", "1").unwrap().text, "other chat");
+        assert!(feedback.reply("c1", "2").is_none());
+    }
+
+    #[test]
+    fn forgets_oldest_replies() {
+        let feedback = ReactionFeedback::default();
+        for i in 0..=MAX_REPLIES {
+            feedback.record(&i.to_string(), reply("c1", "text"));
+        }
+        assert!(feedback.reply("c1", "0").is_none());
+        assert!(feedback.reply("c1", &MAX_REPLIES.to_string()).is_some());
+    }
+
+    #[tokio::test]
+    async fn react_runs_hook_for_thumbs_only() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let feedback = ReactionFeedback::new({
+            let seen = Arc::clone(&seen);
+            Arc::new(move |reply: SentReply, feedback: Feedback| {
+                seen.lock().unwrap().push((reply.text, feedback));
+                Box::pin(async {})
+            })
+        });
+        feedback.react(reply("c1", "answer"), "👎").await;
+        feedback.react(reply("c1", "answer"), "🎉").await;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("answer".to_string(), Feedback::Negative)]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Shared markdown formatting for outbound channel messages.
+//!
+//! Agents reply in generic markdown. [`parse_markdown`] splits a reply into the
+//! constructs channels care about, and a [`FormatProfile`] renders each one in
+//! the channel's own syntax. A new channel formatter only needs to implement
+//! the trait; parsing stays consistent across channels.
+
+/// A piece of agent markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Span<'a> {
+    /// Plain text outside any markup.
+    Text(&'a str),
+    /// `**bold**`
+    Bold(&'a str),
+    /// `` `code` ``
+    InlineCode(&'a str),
+    /// Everything between a pair of ```` ``` ```` fences, language tag included.
+    CodeBlock(&'a str),
+    /// `[text](url)`
+    Link { text: &'a str, url: &'a str },
+}
+
+/// Channel-specific rendering of markdown spans.
+pub trait FormatProfile {
+    /// Render plain text (escaping as the channel requires).
+    fn text(&self, text: &str, out: &mut String);
+
+    /// Render bold text.
+    fn bold(&self, text: &str, out: &mut String);
+
+    /// Render a hyperlink.
+    fn link(&self, text: &str, url: &str, out: &mut String);
+
+    /// Render inline code. Defaults to backticks, passed through verbatim.
+    fn inline_code(&self, code: &str, out: &mut String) {
+        out.push('`');
+        out.push_str(code);
+        out.push('`');
+    }
+
+    /// Render a fenced code block. Defaults to ```` ``` ```` fences, passed through verbatim.
+    fn code_block(&self, body: &str, out: &mut String) {
+        out.push_str("```");
+        out.push_str(body);
+        out.push_str("```");
+    }
+
+    /// Convert agent markdown into this channel's format.
+    fn render(&self, markdown: &str) -> String {
+        let mut out = String::with_capacity(markdown.len());
+        for span in parse_markdown(markdown) {
+            match span {
+                Span::Text(text) => self.text(text, &mut out),
+                Span::Bold(text) => self.bold(text, &mut out),
+                Span::InlineCode(code) => self.inline_code(code, &mut out),
+                Span::CodeBlock(body) => self.code_block(body, &mut out),
+                Span::Link { text, url } => self.link(text, url, &mut out),
+            }
+        }
+        out
+    }
+}
+
+/// Split markdown into spans. Unclosed code fences, inline code and bold run
+/// to the end of the input.
+pub fn parse_markdown(input: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < input.len() {
+        let rest = &input[i..];
+        let parsed = if let Some(body) = rest.strip_prefix("```") {
+            let (inner, used) = delimited(body, "```");
+            Some((Span::CodeBlock(inner), 3 + used))
+        } else if let Some(body) = rest.strip_prefix('`') {
+            let (inner, used) = delimited(body, "`");
+            Some((Span::InlineCode(inner), 1 + used))
+        } else if let Some(body) = rest.strip_prefix("**") {
+            let (inner, used) = delimited(body, "**");
+            Some((Span::Bold(inner), 2 + used))
+        } else if rest.starts_with('[') {
+            parse_link(rest)
+        } else {
+            None
+        };
+
+        match parsed {
+            Some((span, used)) => {
+                if text_start < i {
+                    spans.push(Span::Text(&input[text_start..i]));
+                }
+                spans.push(span);
+                i += used;
+                text_start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    if text_start < input.len() {
+        spans.push(Span::Text(&input[text_start..]));
+    }
+    spans
+}
+
+/// Content up to the closing `delim` (or the end of input when unclosed) and
+/// the number of bytes consumed, including the delimiter.
+fn delimited<'a>(body: &'a str, delim: &str) -> (&'a str, usize) {
+    match body.find(delim) {
+        Some(end) => (&body[..end], end + delim.len()),
+        None => (body, body.len()),
+    }
+}
+
+/// `[text](url)`. Parentheses in the URL must be balanced, so
+/// `[x](https://en.wikipedia.org/wiki/Rust_(language))` keeps the whole URL.
+fn parse_link(rest: &str) -> Option<(Span<'_>, usize)> {
+    let close = rest.find("](")?;
+    let text = &rest[1..close];
+    if text.is_empty() || text.contains(['\n', '[']) {
+        return None;
+    }
+    let after = &rest[close + 2..];
+    let mut depth = 0usize;
+    let end = after.char_indices().find_map(|(i, c)| match c {
+        '(' => {
+            depth += 1;
+            None
+        }
+        ')' if depth == 0 => Some(i),
+        ')' => {
+            depth -= 1;
+            None
+        }
+        _ => None,
+    })?;
+    let url = &after[..end];
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((Span::Link { text, url }, close + 2 + end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_span_kinds() {
+        let spans =
+            parse_markdown("Hi **there**, run `ls` or see [docs](https://x.io).\n```sh\nls\n```");
+        assert_eq!(
+            spans,
+            vec![
+                Span::Text("Hi "),
+                Span::Bold("there"),
+                Span::Text(", run "),
+                Span::InlineCode("ls"),
+                Span::Text(" or see "),
+                Span::Link {
+                    text: "docs",
+                    url: "https://x.io"
+                },
+                Span::Text(".\n"),
+                Span::CodeBlock("sh\nls\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn markup_inside_code_is_not_parsed() {
+        assert_eq!(
+            parse_markdown("`**not bold**`"),
+            vec![Span::InlineCode("**not bold**")]
+        );
+        assert_eq!(
+            parse_markdown("```\n[a](b)\n```"),
+            vec![Span::CodeBlock("\n[a](b)\n")]
+        );
+    }
+
+    #[test]
+    fn brackets_without_url_stay_text() {
+        assert_eq!(
+            parse_markdown("see [1] and [x](not a url)"),
+            vec![Span::Text("see [1] and [x](not a url)")]
+        );
+    }
+
+    #[test]
+    fn link_urls_keep_balanced_parentheses() {
+        assert_eq!(
+            parse_markdown("[Rust](https://en.wikipedia.org/wiki/Rust_(language)) rocks"),
+            vec![
+                Span::Link {
+                    text: "Rust",
+                    url: "https://en.wikipedia.org/wiki/Rust_(language)"
+                },
+                Span::Text(" rocks"),
+            ]
+        );
+        assert_eq!(
+            parse_markdown("([a](b))"),
+            vec![
+                Span::Text("("),
+                Span::Link {
+                    text: "a",
+                    url: "b"
+                },
+                Span::Text(")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_markup_runs_to_end() {
+        assert_eq!(
+            parse_markdown("a **b"),
+            vec![Span::Text("a "), Span::Bold("b")]
+        );
+    }
+
+    #[cfg(all(feature = "slack", feature = "discord", feature = "telegram"))]
+    #[test]
+    fn same_markdown_renders_per_profile() {
+        use crate::discord::convert::DiscordFormat;
+        use crate::slack::fmt::SlackFormat;
+        use crate::telegram_fmt::TelegramFormat;
+
+        let source = "**Done!** See [the docs](https://example.com/a_b) and run `make test`.\n```sh\nmake test\n```";
+
+        assert_eq!(
+            SlackFormat.render(source),
+            "*Done!* See <https://example.com/a_b|the docs> and run `make test`.\n```sh\nmake test\n```"
+        );
+        assert_eq!(
+            DiscordFormat.render(source),
+            "**Done!** See [the docs](https://example.com/a_b) and run `make test`.\n```sh\nmake test\n```"
+        );
+        assert_eq!(
+            TelegramFormat.render(source),
+            "*Done\\!* See [the docs](https://example.com/a_b) and run `make test`\\.\n```sh\nmake test\n```"
+        );
+    }
+}
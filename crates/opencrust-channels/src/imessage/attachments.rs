@@ -0,0 +1,295 @@
+//! Reading attachments of incoming iMessages and staging outgoing files for
+//! Messages.app.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opencrust_common::{MediaAttachment, MediaKind};
+use tracing::{debug, warn};
+
+use super::chatdb::ChatDbAttachment;
+
+/// Largest attachment read into memory for the agent.
+pub const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// A format conversion done with a macOS command line tool before an
+/// attachment is handed on, because vision and speech-to-text APIs do not
+/// accept the formats Messages stores.
+#[derive(Debug, PartialEq, Eq)]
+struct Conversion {
+    program: &'static str,
+    extension: &'static str,
+    mime_type: &'static str,
+}
+
+impl Conversion {
+    /// HEIC photos become JPEG and CAF voice memos become M4A.
+    fn for_mime(mime_type: Option<&str>) -> Option<Self> {
+        match mime_type? {
+            "image/heic" | "image/heif" => Some(Self {
+                program: "sips",
+                extension: "jpg",
+                mime_type: "image/jpeg",
+            }),
+            "audio/x-caf" => Some(Self {
+                program: "afconvert",
+                extension: "m4a",
+                mime_type: "audio/mp4",
+            }),
+            _ => None,
+        }
+    }
+
+    fn args(&self, input: &Path, output: &Path) -> Vec<String> {
+        let (input, output) = (
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+        );
+        match self.program {
+            "sips" => vec![
+                "-s".into(),
+                "format".into(),
+                "jpeg".into(),
+                input,
+                "--out".into(),
+                output,
+            ],
+            _ => vec![
+                "-f".into(),
+                "m4af".into(),
+                "-d".into(),
+                "aac".into(),
+                input,
+                output,
+            ],
+        }
+    }
+
+    async fn run(&self, input: &Path) -> Result<Vec<u8>, String> {
+        let output = scratch_path(&format!("converted.{}", self.extension))?;
+        let status = tokio::process::Command::new(self.program)
+            .args(self.args(input, &output))
+            .output()
+            .await
+            .map_err(|e| format!("failed to spawn {}: {e}", self.program))?;
+        if !status.status.success() {
+            return Err(format!("{} exited with {}", self.program, status.status));
+        }
+        let data = tokio::fs::read(&output)
+            .await
+            .map_err(|e| format!("failed to read {}: {e}", output.display()));
+        let _ = tokio::fs::remove_file(&output).await;
+        data
+    }
+}
+
+/// Read an incoming attachment for the message callback, with `caption` as
+/// its caption. Returns `None` when the file is missing, unreadable or larger
+/// than [`MAX_ATTACHMENT_BYTES`].
+pub async fn load(attachment: &ChatDbAttachment, caption: &str) -> Option<MediaAttachment> {
+    let size = match tokio::fs::metadata(&attachment.path).await {
+        Ok(meta) => meta.len(),
+        Err(e) => {
+            warn!(
+                "imessage: cannot read attachment {}: {e}",
+                attachment.path.display()
+            );
+            return None;
+        }
+    };
+    if size > MAX_ATTACHMENT_BYTES {
+        warn!(
+            "imessage: attachment {} is too large ({size} bytes)",
+            attachment.path.display()
+        );
+        return None;
+    }
+
+    let mut name = attachment.name();
+    let mut mime_type = attachment.mime_type.clone();
+    let converted = match Conversion::for_mime(mime_type.as_deref()) {
+        Some(conversion) => match conversion.run(&attachment.path).await {
+            Ok(data) => {
+                name = format!("{}.{}", file_stem(&name), conversion.extension);
+                mime_type = Some(conversion.mime_type.to_string());
+                Some(data)
+            }
+            Err(e) => {
+                debug!("imessage: keeping {name} as is: {e}");
+                None
+            }
+        },
+        None => None,
+    };
+    let data = match converted {
+        Some(data) => data,
+        None => match tokio::fs::read(&attachment.path).await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(
+                    "imessage: cannot read attachment {}: {e}",
+                    attachment.path.display()
+                );
+                return None;
+            }
+        },
+    };
+
+    let caption = Some(caption.to_string()).filter(|c| !c.is_empty());
+    Some(
+        MediaAttachment::new(MediaKind::from_mime(mime_type.as_deref()), data)
+            .with_filename(name)
+            .with_mime_type(mime_type)
+            .with_caption(caption),
+    )
+}
+
+/// How long a staged file is kept after it was handed to Messages.app, which
+/// uploads it after the send script has returned.
+const STAGED_FILE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Write an outgoing attachment to a file Messages.app can send. Pass the
+/// path to [`remove_later`] once the send was requested. Files left behind
+/// by an earlier run are deleted here once they are older than
+/// [`STAGED_FILE_TTL`].
+pub async fn stage(attachment: &MediaAttachment) -> Result<PathBuf, String> {
+    let name = attachment.filename_or_default();
+    let path = scratch_path(&name)?;
+    if let Some(dir) = path.parent() {
+        remove_stale(dir).await;
+    }
+    tokio::fs::write(&path, &attachment.data)
+        .await
+        .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+/// Delete a staged file once Messages.app has had time to upload it.
+pub fn remove_later(path: PathBuf) {
+    tokio::spawn(async move {
+        tokio::time::sleep(STAGED_FILE_TTL).await;
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            debug!("imessage: cannot remove {}: {e}", path.display());
+        }
+    });
+}
+
+/// Delete files in `dir` that are older than [`STAGED_FILE_TTL`].
+async fn remove_stale(dir: &Path) {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let stale = entry
+            .metadata()
+            .await
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STAGED_FILE_TTL);
+        if stale {
+            let _ = tokio::fs::remove_file(entry.path()).await;
+        }
+    }
+}
+
+/// A fresh path named after `name` in the channel's cache directory.
+fn scratch_path(name: &str) -> Result<PathBuf, String> {
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("opencrust")
+        .join("imessage");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    Ok(dir.join(format!("{nanos}-{}", safe_file_name(name))))
+}
+
+/// The last path component of `name`, so a sender-chosen name cannot point
+/// outside the staging directory.
+fn safe_file_name(name: &str) -> String {
+    Path::new(name)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+fn file_stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heic_and_caf_are_converted() {
+        let photo = Conversion::for_mime(Some("image/heic")).unwrap();
+        assert_eq!(photo.mime_type, "image/jpeg");
+        assert_eq!(
+            photo.args(Path::new("/a/IMG.heic"), Path::new("/b/IMG.jpg")),
+            ["-s", "format", "jpeg", "/a/IMG.heic", "--out", "/b/IMG.jpg"]
+        );
+        let voice = Conversion::for_mime(Some("audio/x-caf")).unwrap();
+        assert_eq!(voice.program, "afconvert");
+        assert_eq!(voice.mime_type, "audio/mp4");
+        assert!(Conversion::for_mime(Some("image/jpeg")).is_none());
+        assert!(Conversion::for_mime(None).is_none());
+    }
+
+    #[tokio::test]
+    async fn load_reads_file_with_caption_and_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("F00D.png");
+        std::fs::write(&path, b"png").unwrap();
+        let attachment = ChatDbAttachment {
+            path,
+            mime_type: Some("image/png".to_string()),
+            transfer_name: Some("chart.png".to_string()),
+        };
+
+        let media = load(&attachment, "what is this?").await.unwrap();
+        assert_eq!(media.kind, MediaKind::Photo);
+        assert_eq!(media.data, b"png");
+        assert_eq!(media.filename.as_deref(), Some("chart.png"));
+        assert_eq!(media.caption.as_deref(), Some("what is this?"));
+
+        let missing = ChatDbAttachment {
+            path: dir.path().join("gone.png"),
+            ..attachment
+        };
+        assert!(load(&missing, "").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stale_staged_files_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let fresh = dir.path().join("fresh.png");
+        let stale = dir.path().join("stale.png");
+        std::fs::write(&fresh, b"png").unwrap();
+        std::fs::write(&stale, b"png").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(SystemTime::now() - STAGED_FILE_TTL * 2)
+            .unwrap();
+
+        remove_stale(dir.path()).await;
+
+        assert!(fresh.exists());
+        assert!(!stale.exists());
+    }
+
+    #[test]
+    fn staged_names_stay_in_the_staging_directory() {
+        assert_eq!(safe_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(safe_file_name(""), "file");
+        assert_eq!(file_stem("IMG_1.heic"), "IMG_1");
+        assert_eq!(file_stem("voice"), "voice");
+    }
+}
@@ -11,6 +11,30 @@ pub struct IncomingMessage {
     pub sender: String,
     pub timestamp: i64,
     pub group_name: Option<String>,
+    /// Files attached to the message, in the order Messages shows them.
+    pub attachments: Vec<ChatDbAttachment>,
+}
+
+/// A file attached to an incoming iMessage, stored under
+/// `~/Library/Messages/Attachments`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatDbAttachment {
+    pub path: PathBuf,
+    pub mime_type: Option<String>,
+    /// Name the sender gave the file; the stored file may be renamed.
+    pub transfer_name: Option<String>,
+}
+
+impl ChatDbAttachment {
+    /// File name to show for the attachment.
+    pub fn name(&self) -> String {
+        self.transfer_name.clone().unwrap_or_else(|| {
+            self.path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "file".to_string())
+        })
+    }
 }
 
 /// Read-only handle to `~/Library/Messages/chat.db`.
@@ -87,17 +111,13 @@ impl ChatDb {
     ///
     /// Returns messages ordered by date ascending, including both DMs and group chats.
     /// Group chat messages will have `group_name` set.
-    /// Messages with attachments but no text get synthesized text like `[Attachment: file.heic]`.
+    /// Messages with attachments but no text have empty `text`.
     pub fn poll(&mut self) -> Result<Vec<IncomingMessage>, String> {
         let mut stmt = self
             .conn
             .prepare(
                 "SELECT m.ROWID, m.text, m.date, m.is_from_me, m.cache_roomnames, \
-                        h.id AS sender_id, \
-                        (SELECT GROUP_CONCAT(a.filename, ', ') \
-                         FROM message_attachment_join maj \
-                         JOIN attachment a ON maj.attachment_id = a.ROWID \
-                         WHERE maj.message_id = m.ROWID) AS attachments \
+                        h.id AS sender_id \
                  FROM message m \
                  JOIN handle h ON m.handle_id = h.ROWID \
                  WHERE m.ROWID > ?1 AND m.is_from_me = 0 \
@@ -112,33 +132,33 @@ impl ChatDb {
                 let date: i64 = row.get(2)?;
                 let cache_roomnames: Option<String> = row.get(4)?;
                 let sender: String = row.get(5)?;
-                let attachments: Option<String> = row.get(6)?;
-                Ok((rowid, text, date, cache_roomnames, sender, attachments))
+                Ok((rowid, text, date, cache_roomnames, sender))
             })
             .map_err(|e| format!("failed to execute poll query: {e}"))?;
 
         let mut messages = Vec::new();
         for row in rows {
             match row {
-                Ok((rowid, text, date, cache_roomnames, sender, attachments)) => {
+                Ok((rowid, text, date, cache_roomnames, sender)) => {
                     if rowid > self.last_seen_rowid {
                         self.last_seen_rowid = rowid;
                     }
 
-                    let resolved_text = match (text.as_deref(), attachments.as_deref()) {
-                        (Some(t), _) if !t.is_empty() => t.to_string(),
-                        (_, Some(att)) if !att.is_empty() => synthesize_attachment_text(att),
-                        _ => continue, // no text and no attachments — skip
-                    };
+                    let text = text.unwrap_or_default();
+                    let attachments = self.attachments(rowid)?;
+                    if text.is_empty() && attachments.is_empty() {
+                        continue; // no text and no attachments — skip
+                    }
 
                     let group_name = cache_roomnames.filter(|r| !r.is_empty());
 
                     messages.push(IncomingMessage {
                         rowid,
-                        text: resolved_text,
+                        text,
                         sender,
                         timestamp: core_data_ns_to_unix(date),
                         group_name,
+                        attachments,
                     });
                 }
                 Err(e) => {
@@ -149,20 +169,49 @@ impl ChatDb {
 
         Ok(messages)
     }
+
+    /// Attachments of the message with `rowid`. Files not downloaded to this
+    /// Mac yet have no filename and are left out.
+    fn attachments(&self, rowid: i64) -> Result<Vec<ChatDbAttachment>, String> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT a.filename, a.mime_type, a.transfer_name \
+                 FROM message_attachment_join maj \
+                 JOIN attachment a ON maj.attachment_id = a.ROWID \
+                 WHERE maj.message_id = ?1 AND a.filename IS NOT NULL \
+                 ORDER BY a.ROWID",
+            )
+            .map_err(|e| format!("failed to prepare attachment query: {e}"))?;
+        let rows = stmt
+            .query_map([rowid], |row| {
+                let filename: String = row.get(0)?;
+                Ok(ChatDbAttachment {
+                    path: expand_home(&filename),
+                    mime_type: row.get(1)?,
+                    transfer_name: row.get(2)?,
+                })
+            })
+            .map_err(|e| format!("failed to query attachments: {e}"))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| format!("failed to read attachment row: {e}"))
+    }
 }
 
-/// Convert an attachment filename list into a human-readable placeholder.
-///
-/// Input: comma-separated full paths like `~/Library/Messages/Attachments/.../IMG_1234.heic`
-/// Output: `[Attachment: IMG_1234.heic]` or `[Attachments: a.heic, b.png]`
-fn synthesize_attachment_text(filenames: &str) -> String {
-    let names: Vec<&str> = filenames
-        .split(", ")
-        .map(|f| {
-            // Extract just the filename from the full path
-            f.rsplit('/').next().unwrap_or(f)
-        })
-        .collect();
+/// chat.db stores attachment paths relative to the home directory (`~/...`).
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("/"))
+            .join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// A placeholder naming attachments the agent cannot see, e.g.
+/// `[Attachment: IMG_1234.heic]` or `[Attachments: a.heic, b.png]`.
+pub fn synthesize_attachment_text(attachments: &[ChatDbAttachment]) -> String {
+    let names: Vec<String> = attachments.iter().map(ChatDbAttachment::name).collect();
 
     if names.len() == 1 {
         format!("[Attachment: {}]", names[0])
@@ -192,24 +241,48 @@ mod tests {
         assert_eq!(core_data_ns_to_unix(0), CORE_DATA_EPOCH_OFFSET);
     }
 
+    fn attachment(path: &str, transfer_name: Option<&str>) -> ChatDbAttachment {
+        ChatDbAttachment {
+            path: expand_home(path),
+            mime_type: None,
+            transfer_name: transfer_name.map(str::to_string),
+        }
+    }
+
     #[test]
     fn synthesize_single_attachment() {
-        let result = synthesize_attachment_text("~/Library/Messages/Attachments/ab/IMG_1234.heic");
+        let result = synthesize_attachment_text(&[attachment(
+            "~/Library/Messages/Attachments/ab/IMG_1234.heic",
+            None,
+        )]);
         assert_eq!(result, "[Attachment: IMG_1234.heic]");
     }
 
     #[test]
     fn synthesize_multiple_attachments() {
-        let result = synthesize_attachment_text("~/path/a.heic, ~/path/b.png");
+        let result = synthesize_attachment_text(&[
+            attachment("~/path/a.heic", None),
+            attachment("~/path/b.png", None),
+        ]);
         assert_eq!(result, "[Attachments: a.heic, b.png]");
     }
 
     #[test]
-    fn synthesize_bare_filename() {
+    fn synthesize_prefers_transfer_name() {
+        assert_eq!(
+            synthesize_attachment_text(&[attachment("/tmp/ab/1F2E.pdf", Some("report.pdf"))]),
+            "[Attachment: report.pdf]"
+        );
+    }
+
+    #[test]
+    fn expand_home_resolves_tilde_paths() {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
         assert_eq!(
-            synthesize_attachment_text("photo.jpg"),
-            "[Attachment: photo.jpg]"
+            expand_home("~/Library/Messages/Attachments/a.jpg"),
+            home.join("Library/Messages/Attachments/a.jpg")
         );
+        assert_eq!(expand_home("/var/a.jpg"), PathBuf::from("/var/a.jpg"));
     }
 
     // --- Mock chat.db integration tests ---
@@ -232,7 +305,9 @@ mod tests {
             );
             CREATE TABLE attachment (
                 ROWID INTEGER PRIMARY KEY,
-                filename TEXT
+                filename TEXT,
+                mime_type TEXT,
+                transfer_name TEXT
             );
             CREATE TABLE message_attachment_join (
                 message_id INTEGER REFERENCES message(ROWID),
@@ -317,7 +392,7 @@ mod tests {
     }
 
     #[test]
-    fn poll_synthesizes_attachment_text() {
+    fn poll_returns_attachments() {
         let conn = mock_chat_db();
         conn.execute(
             "INSERT INTO handle (ROWID, id) VALUES (1, '+15551234567')",
@@ -331,12 +406,19 @@ mod tests {
         )
         .unwrap();
         conn.execute(
-            "INSERT INTO attachment (ROWID, filename) VALUES (1, '~/Library/Messages/Attachments/ab/IMG_1234.heic')",
+            "INSERT INTO attachment (ROWID, filename, mime_type, transfer_name) \
+             VALUES (1, '/Users/a/Library/Messages/Attachments/ab/IMG_1234.heic', 'image/heic', 'IMG_1234.heic')",
             [],
         )
         .unwrap();
+        // Not downloaded from iCloud yet: no filename.
         conn.execute(
-            "INSERT INTO message_attachment_join (message_id, attachment_id) VALUES (1, 1)",
+            "INSERT INTO attachment (ROWID, filename, mime_type) VALUES (2, NULL, 'image/jpeg')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO message_attachment_join (message_id, attachment_id) VALUES (1, 1), (1, 2)",
             [],
         )
         .unwrap();
@@ -344,7 +426,15 @@ mod tests {
         let mut db = chat_db_from_conn(conn);
         let msgs = db.poll().unwrap();
         assert_eq!(msgs.len(), 1);
-        assert_eq!(msgs[0].text, "[Attachment: IMG_1234.heic]");
+        assert_eq!(msgs[0].text, "");
+        assert_eq!(
+            msgs[0].attachments,
+            vec![ChatDbAttachment {
+                path: PathBuf::from("/Users/a/Library/Messages/Attachments/ab/IMG_1234.heic"),
+                mime_type: Some("image/heic".to_string()),
+                transfer_name: Some("IMG_1234.heic".to_string()),
+            }]
+        );
     }
 
     #[test]
@@ -398,14 +488,14 @@ mod tests {
     }
 
     #[test]
-    fn poll_text_with_attachment_uses_text() {
+    fn poll_keeps_text_alongside_attachment() {
         let conn = mock_chat_db();
         conn.execute(
             "INSERT INTO handle (ROWID, id) VALUES (1, '+15551234567')",
             [],
         )
         .unwrap();
-        // Message with both text AND attachment — text should win
+        // Message with both text AND attachment — the text is the caption
         conn.execute(
             "INSERT INTO message (ROWID, text, date, is_from_me, handle_id) VALUES (1, 'look at this', 725760000000000000, 0, 1)",
             [],
@@ -426,5 +516,6 @@ mod tests {
         let msgs = db.poll().unwrap();
         assert_eq!(msgs.len(), 1);
         assert_eq!(msgs[0].text, "look at this");
+        assert_eq!(msgs[0].attachments.len(), 1);
     }
 }
@@ -1,3 +1,4 @@
+pub mod attachments;
 pub mod chatdb;
 pub mod sender;
 
@@ -11,16 +12,17 @@ use tokio::sync::{mpsc, watch};
 use tracing::{error, info, warn};
 
 use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
+use opencrust_common::{MediaAttachment, Message, MessageContent, Result};
 
 /// Group filter closure for iMessage channels.
 /// Argument: `is_mentioned` (always `false` - iMessage has no mention concept).
 /// Returns `true` if the message should be processed.
 pub type IMessageGroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
 
-/// Callback invoked when the bot receives a text message from iMessage.
+/// Callback invoked when the bot receives a message from iMessage.
 ///
-/// Arguments: `(session_key, sender_id, text, is_group, delta_tx)`.
+/// Arguments: `(session_key, sender_id, text, is_group, attachment, delta_tx)`.
+/// `attachment` is the first file of the message, with `text` as its caption.
 /// `delta_tx` is always `None` for iMessage (no streaming support).
 /// Return `Err("__blocked__")` to silently drop the message (unauthorized user).
 pub type IMessageOnMessageFn = Arc<
@@ -29,6 +31,7 @@ pub type IMessageOnMessageFn = Arc<
             String,
             String,
             bool,
+            Option<MediaAttachment>,
             Option<mpsc::Sender<String>>,
         )
             -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
@@ -169,9 +172,10 @@ impl ChannelLifecycle for IMessageChannel {
                             }
 
                             info!(
-                                "imessage from {} ({} chars, rowid={}{}) session={}",
+                                "imessage from {} ({} chars, {} attachment(s), rowid={}{}) session={}",
                                 msg.sender,
                                 msg.text.len(),
+                                msg.attachments.len(),
                                 msg.rowid,
                                 if is_group {
                                     format!(", group={}", msg.group_name.as_deref().unwrap_or(""))
@@ -184,6 +188,7 @@ impl ChannelLifecycle for IMessageChannel {
                             let on_message = Arc::clone(&on_message);
                             let sender = msg.sender.clone();
                             let text = msg.text;
+                            let message_attachments = msg.attachments;
                             let group_name = msg.group_name.clone();
 
                             tokio::spawn(async move {
@@ -195,9 +200,24 @@ impl ChannelLifecycle for IMessageChannel {
                                     sender.clone()
                                 };
 
-                                let result =
-                                    on_message(session_key, sender.clone(), text, is_group, None)
-                                        .await;
+                                let file = match message_attachments.first() {
+                                    Some(first) => attachments::load(first, &text).await,
+                                    None => None,
+                                };
+                                let text = with_skipped_attachments(
+                                    text,
+                                    &message_attachments[usize::from(file.is_some())..],
+                                );
+
+                                let result = on_message(
+                                    session_key,
+                                    sender.clone(),
+                                    text,
+                                    is_group,
+                                    file,
+                                    None,
+                                )
+                                .await;
 
                                 match result {
                                     Ok(response) => {
@@ -286,40 +306,95 @@ impl ChannelSender for IMessageChannel {
 }
 
 /// Shared send logic used by both `IMessageChannel` and `IMessageSender`.
+/// Messages go to the group chat named by `imessage_group` when set, and
+/// otherwise to the `imessage_sender` handle.
 async fn imessage_send_message(message: &Message) -> Result<()> {
+    let group = message
+        .metadata
+        .get("imessage_group")
+        .and_then(|v| v.as_str());
     let to = message
         .metadata
         .get("imessage_sender")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            opencrust_common::Error::Channel("missing imessage_sender in metadata".into())
-        })?;
+        .and_then(|v| v.as_str());
+    let recipient = match (group, to) {
+        (Some(group), _) => Recipient::Group(group),
+        (None, Some(to)) => Recipient::Handle(to),
+        (None, None) => {
+            return Err(opencrust_common::Error::Channel(
+                "missing imessage_sender in metadata".into(),
+            ));
+        }
+    };
 
     let text = match &message.content {
         MessageContent::Text(t) => t.clone(),
+        MessageContent::Attachment(attachment) => {
+            let path = attachments::stage(attachment)
+                .await
+                .map_err(opencrust_common::Error::Channel)?;
+            let sent = match recipient {
+                Recipient::Group(group) => sender::send_imessage_group_file(group, &path).await,
+                Recipient::Handle(to) => sender::send_imessage_file(to, &path).await,
+            };
+            attachments::remove_later(path);
+            sent.map_err(|e| {
+                opencrust_common::Error::Channel(format!("imessage send failed: {e}"))
+            })?;
+            match &attachment.caption {
+                Some(caption) if !caption.is_empty() => caption.clone(),
+                _ => return Ok(()),
+            }
+        }
         _ => {
             return Err(opencrust_common::Error::Channel(
-                "only text messages are supported for imessage send".into(),
+                "only text messages and attachments are supported for imessage send".into(),
             ));
         }
     };
 
-    sender::send_imessage(to, &text)
-        .await
-        .map_err(|e| opencrust_common::Error::Channel(format!("imessage send failed: {e}")))?;
+    match recipient {
+        Recipient::Group(group) => sender::send_imessage_group(group, &text).await,
+        Recipient::Handle(to) => sender::send_imessage(to, &text).await,
+    }
+    .map_err(|e| opencrust_common::Error::Channel(format!("imessage send failed: {e}")))?;
 
     Ok(())
 }
 
+/// Where an outgoing message is delivered.
+#[derive(Clone, Copy)]
+enum Recipient<'a> {
+    /// A group chat, by its `cache_roomnames` value.
+    Group(&'a str),
+    /// A phone number or email address.
+    Handle(&'a str),
+}
+
+/// Append a placeholder naming the attachments that are not passed to the
+/// callback, so the agent knows they were sent.
+fn with_skipped_attachments(text: String, skipped: &[chatdb::ChatDbAttachment]) -> String {
+    if skipped.is_empty() {
+        return text;
+    }
+    let placeholder = chatdb::synthesize_attachment_text(skipped);
+    if text.is_empty() {
+        placeholder
+    } else {
+        format!("{text}\n{placeholder}")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn channel_type_is_imessage() {
-        let on_msg: IMessageOnMessageFn = Arc::new(|_from, _user, _text, _is_group, _delta_tx| {
-            Box::pin(async { Ok(ChannelResponse::Text("test".to_string())) })
-        });
+        let on_msg: IMessageOnMessageFn =
+            Arc::new(|_from, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("test".to_string())) })
+            });
         let channel = IMessageChannel::new(2, on_msg);
         assert_eq!(channel.channel_type(), "imessage");
         assert_eq!(channel.display_name(), "iMessage");
@@ -332,6 +407,24 @@ mod tests {
         assert!(!filter(false));
     }
 
+    #[test]
+    fn skipped_attachments_are_named_in_the_text() {
+        let attachment = |name: &str| chatdb::ChatDbAttachment {
+            path: format!("/tmp/{name}").into(),
+            mime_type: None,
+            transfer_name: None,
+        };
+        assert_eq!(with_skipped_attachments("hi".into(), &[]), "hi");
+        assert_eq!(
+            with_skipped_attachments(String::new(), &[attachment("a.mov")]),
+            "[Attachment: a.mov]"
+        );
+        assert_eq!(
+            with_skipped_attachments("look".into(), &[attachment("a.mov"), attachment("b.pdf")]),
+            "look\n[Attachments: a.mov, b.pdf]"
+        );
+    }
+
     #[test]
     fn max_backoff_is_30s() {
         assert_eq!(MAX_BACKOFF, Duration::from_secs(30));
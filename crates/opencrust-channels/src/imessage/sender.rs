@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use tracing::debug;
 
 /// Escape a string for use inside an AppleScript double-quoted literal.
@@ -57,6 +59,42 @@ end tell"#
     run_osascript(&script).await
 }
 
+/// Send the file at `path` as an iMessage attachment to `to`.
+pub async fn send_imessage_file(to: &str, path: &Path) -> Result<(), String> {
+    let escaped_to = applescript_escape(to);
+    let escaped_path = applescript_escape(&path.to_string_lossy());
+
+    let script = format!(
+        r#"tell application "Messages"
+    set targetService to 1st account whose service type = iMessage
+    set targetBuddy to participant targetService handle "{escaped_to}"
+    send POSIX file "{escaped_path}" to targetBuddy
+end tell"#
+    );
+
+    debug!("imessage: sending file {} to {to}", path.display());
+    run_osascript(&script).await
+}
+
+/// Send the file at `path` to a group chat.
+pub async fn send_imessage_group_file(group_name: &str, path: &Path) -> Result<(), String> {
+    let escaped_group = applescript_escape(group_name);
+    let escaped_path = applescript_escape(&path.to_string_lossy());
+
+    let script = format!(
+        r#"tell application "Messages"
+    set targetChat to chat "{escaped_group}"
+    send POSIX file "{escaped_path}" to targetChat
+end tell"#
+    );
+
+    debug!(
+        "imessage: sending file {} to group {group_name}",
+        path.display()
+    );
+    run_osascript(&script).await
+}
+
 /// Execute an AppleScript via `osascript` and return the result.
 async fn run_osascript(script: &str) -> Result<(), String> {
     let output = tokio::process::Command::new("osascript")
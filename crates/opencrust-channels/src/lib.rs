@@ -1,17 +1,26 @@
+#[cfg(feature = "connector")]
+pub mod connector;
+pub mod dedup;
+pub mod download;
+pub mod edit;
+pub mod feedback;
+pub mod format;
 pub mod protocol;
+pub mod queue;
 pub mod registry;
 
 /// Maximum file size accepted when downloading attachments from any channel (10 MiB).
 ///
-/// Applied as both a `Content-Length` pre-check (where the header is available)
-/// and a post-download `bytes.len()` check as a safety net.  This matches the
-/// limit already in use by the Slack channel (`SLACK_MAX_FILE_BYTES`).
+/// Enforced by [`download::read_limited`], which checks `Content-Length` up
+/// front and aborts a streamed body as soon as it passes the limit. This
+/// matches the limit already in use by the Slack channel (`SLACK_MAX_FILE_BYTES`).
 pub const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
 #[cfg(feature = "telegram")]
 pub mod telegram;
 #[cfg(feature = "telegram")]
 pub mod telegram_fmt;
 pub mod traits;
+pub mod typing;
 
 #[cfg(feature = "discord")]
 pub mod discord;
@@ -21,13 +30,27 @@ pub mod imessage;
 pub mod line;
 #[cfg(feature = "mqtt")]
 pub mod mqtt;
+#[cfg(feature = "signal")]
+pub mod signal;
 #[cfg(feature = "slack")]
 pub mod slack;
+#[cfg(feature = "teams")]
+pub mod teams;
+#[cfg(feature = "webchat")]
+pub mod webchat;
 #[cfg(feature = "wechat")]
 pub mod wechat;
 #[cfg(feature = "whatsapp")]
 pub mod whatsapp;
 
+#[cfg(feature = "connector")]
+pub use connector::socket::{ConnectorState, connector_socket};
+#[cfg(feature = "connector")]
+pub use connector::{RemoteChannel, RemoteOnMessageFn, RemoteSender};
+pub use dedup::{DEFAULT_DEDUP_TTL, InboundDedup, SeenFn};
+pub use edit::{EditRegeneration, OnEditFn};
+pub use feedback::{Feedback, OnReactionFn, ReactionFeedback, SentReply};
+pub use format::{FormatProfile, Span, parse_markdown};
 #[cfg(all(target_os = "macos", feature = "imessage"))]
 pub use imessage::{IMessageChannel, IMessageGroupFilter, IMessageOnMessageFn};
 #[cfg(feature = "line")]
@@ -36,18 +59,31 @@ pub use line::webhook::{LineWebhookState, line_webhook};
 pub use line::{LineChannel, LineFile, LineGroupFilter, LineOnMessageFn};
 #[cfg(feature = "mqtt")]
 pub use mqtt::{MqttChannel, MqttOnMessageFn};
+pub use opencrust_common::{MediaAttachment, MediaKind};
 pub use protocol::{
     CONNECTOR_PROTOCOL_VERSION, ConnectorCapability, ConnectorFrame, ConnectorHandshake,
     MAX_CONNECTOR_FRAME_BYTES,
 };
-pub use registry::ChannelRegistry;
+pub use queue::{QueuedSender, Rate, RateLimiter, RateLimits};
+pub use registry::{ChannelFactory, ChannelRegistry, RestartPolicy};
+#[cfg(feature = "signal")]
+pub use signal::{SignalChannel, SignalGroupFilter, SignalOnMessageFn};
 #[cfg(feature = "slack")]
-pub use slack::{SlackChannel, SlackFile, SlackGroupFilter, SlackOnMessageFn};
+pub use slack::{SlackChannel, SlackGroupFilter, SlackOnMessageFn};
+#[cfg(feature = "teams")]
+pub use teams::webhook::{TeamsWebhookState, teams_webhook};
+#[cfg(feature = "teams")]
+pub use teams::{TeamsChannel, TeamsGroupFilter, TeamsOnMessageFn};
 #[cfg(feature = "telegram")]
-pub use telegram::{GroupFilter, MediaAttachment, OnMessageFn, TelegramChannel};
+pub use telegram::{GroupFilter, OnMessageFn, TelegramChannel};
 pub use traits::{
     Channel, ChannelEvent, ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus,
+    InlineButton, ToolResult,
 };
+#[cfg(feature = "webchat")]
+pub use webchat::socket::{WebChatState, webchat_page, webchat_socket, webchat_widget};
+#[cfg(feature = "webchat")]
+pub use webchat::{WebChatChannel, WebChatOnMessageFn};
 #[cfg(feature = "wechat")]
 pub use wechat::webhook::{WeChatWebhookState, wechat_webhook, wechat_webhook_verify};
 #[cfg(feature = "wechat")]
@@ -55,4 +91,4 @@ pub use wechat::{WeChatChannel, WeChatFile, WeChatGroupFilter, WeChatOnMessageFn
 #[cfg(feature = "whatsapp-web")]
 pub use whatsapp::web::{WhatsAppWebChannel, WhatsAppWebGroupFilter};
 #[cfg(feature = "whatsapp")]
-pub use whatsapp::{WhatsAppChannel, WhatsAppFile, WhatsAppOnMessageFn};
+pub use whatsapp::{WhatsAppChannel, WhatsAppOnMessageFn, WhatsAppTemplate};
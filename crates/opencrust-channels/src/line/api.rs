@@ -27,32 +27,14 @@ pub async fn download_content(
         return Err(format!("line download_content error {status}: {body}"));
     }
 
-    if let Some(len) = resp.content_length()
-        && len > crate::MAX_DOWNLOAD_BYTES as u64
-    {
-        return Err(format!(
-            "line file too large: {len} bytes exceeds {} byte limit",
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    let bytes = resp
-        .bytes()
+    crate::download::read_limited(resp, crate::MAX_DOWNLOAD_BYTES)
         .await
-        .map_err(|e| format!("line download_content read failed: {e}"))?;
-
-    if bytes.len() > crate::MAX_DOWNLOAD_BYTES {
-        return Err(format!(
-            "line file too large: {} bytes exceeds {} byte limit",
-            bytes.len(),
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    Ok(bytes.to_vec())
+        .map_err(|e| format!("line {e}"))
 }
 
 /// Send a reply using a reply token (free, expires in 30 seconds, one use).
+///
+/// Text too long for one text message is sent as a flex carousel.
 pub async fn reply(
     client: &Client,
     channel_access_token: &str,
@@ -62,7 +44,7 @@ pub async fn reply(
 ) -> Result<(), String> {
     let body = serde_json::json!({
         "replyToken": reply_token,
-        "messages": [{"type": "text", "text": text}]
+        "messages": super::fmt::to_line_messages(text)
     });
 
     let resp = client
@@ -170,16 +152,36 @@ pub async fn get_group_member_display_name(
 }
 
 /// Send a push message to a user ID (paid tier, works at any time).
+///
+/// Text too long for one text message is sent as a flex carousel.
 pub async fn push(
     client: &Client,
     channel_access_token: &str,
     user_id: &str,
     text: &str,
     base_url: &str,
+) -> Result<(), String> {
+    push_messages(
+        client,
+        channel_access_token,
+        user_id,
+        super::fmt::to_line_messages(text),
+        base_url,
+    )
+    .await
+}
+
+/// Send already-built LINE message objects to a user ID with the Push API.
+pub async fn push_messages(
+    client: &Client,
+    channel_access_token: &str,
+    user_id: &str,
+    messages: Vec<serde_json::Value>,
+    base_url: &str,
 ) -> Result<(), String> {
     let body = serde_json::json!({
         "to": user_id,
-        "messages": [{"type": "text", "text": text}]
+        "messages": messages
     });
 
     let resp = client
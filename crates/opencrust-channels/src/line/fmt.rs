@@ -1,13 +1,158 @@
 /// Maximum characters allowed in a single LINE text message.
 const LINE_TEXT_MAX: usize = 5000;
 
-/// Prepare text for a LINE text message. Truncates at the 5000-char limit.
-pub fn to_line_text(text: &str) -> String {
+/// Characters per carousel page. Keeps each bubble well under LINE's 30 KB
+/// bubble limit even for 3-byte scripts such as Thai.
+const FLEX_PAGE_CHARS: usize = 2000;
+
+/// LINE allows at most 12 bubbles in a carousel.
+const FLEX_MAX_PAGES: usize = 12;
+
+/// LINE rejects a flex message whose `contents` serialize to more than 50 KB,
+/// which 12 full pages of a 3-byte script such as CJK would exceed.
+const FLEX_MAX_BYTES: usize = 50_000;
+
+/// Maximum characters in a flex message's `altText` (shown in notifications
+/// and chat lists).
+const ALT_TEXT_MAX: usize = 400;
+
+/// Build the message objects for an outgoing reply.
+///
+/// Text that fits in one LINE text message is sent as-is. Longer text becomes
+/// a single flex carousel with one page per bubble, so nothing is cut off at
+/// the 5000-character text limit. Text beyond 12 pages or 50 KB is truncated.
+pub fn to_line_messages(text: &str) -> Vec<serde_json::Value> {
     if text.chars().count() <= LINE_TEXT_MAX {
-        text.to_string()
-    } else {
-        text.chars().take(LINE_TEXT_MAX).collect()
+        return vec![serde_json::json!({"type": "text", "text": text})];
+    }
+
+    let pages = fit_to_budget(paginate(text));
+    let total = pages.len();
+    let bubbles: Vec<serde_json::Value> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| bubble(page, i + 1, total))
+        .collect();
+
+    vec![serde_json::json!({
+        "type": "flex",
+        "altText": alt_text(text),
+        "contents": carousel(bubbles)
+    })]
+}
+
+fn carousel(bubbles: Vec<serde_json::Value>) -> serde_json::Value {
+    serde_json::json!({"type": "carousel", "contents": bubbles})
+}
+
+/// Page `number` of `total`, with the page number in the footer.
+fn bubble(page: &str, number: usize, total: usize) -> serde_json::Value {
+    serde_json::json!({
+        "type": "bubble",
+        "size": "giga",
+        "body": {
+            "type": "box",
+            "layout": "vertical",
+            "contents": [{"type": "text", "text": page, "wrap": true, "size": "sm"}]
+        },
+        "footer": {
+            "type": "box",
+            "layout": "vertical",
+            "contents": [{
+                "type": "text",
+                "text": format!("{number}/{total}"),
+                "size": "xs",
+                "color": "#999999",
+                "align": "end"
+            }]
+        }
+    })
+}
+
+/// Keep as many pages as fit in [`FLEX_MAX_BYTES`] of serialized carousel,
+/// cutting the first page that does not fit short with `…`.
+fn fit_to_budget(pages: Vec<String>) -> Vec<String> {
+    let empty_carousel = serde_json::to_string(&carousel(Vec::new())).map_or(0, |j| j.len());
+    // An empty bubble with the widest footer, plus the separating comma.
+    let per_page = serde_json::to_string(&bubble("", FLEX_MAX_PAGES, FLEX_MAX_PAGES))
+        .map_or(0, |j| j.len())
+        + 1;
+    let mut budget = FLEX_MAX_BYTES.saturating_sub(empty_carousel);
+    let mut fitted = Vec::with_capacity(pages.len());
+
+    for page in pages {
+        let size = per_page + json_len(&page);
+        if size <= budget {
+            budget -= size;
+            fitted.push(page);
+            continue;
+        }
+        let ellipsis = '…'.len_utf8();
+        if let Some(mut room) = budget.checked_sub(per_page + ellipsis) {
+            let mut cut: String = page
+                .chars()
+                .take_while(|c| {
+                    let len = json_len(c.encode_utf8(&mut [0; 4]));
+                    room.checked_sub(len).map(|left| room = left).is_some()
+                })
+                .collect();
+            if !cut.is_empty() {
+                cut.push('…');
+                fitted.push(cut);
+            }
+        }
+        break;
+    }
+
+    fitted
+}
+
+/// Length of `text` once escaped in a JSON string, without the quotes.
+fn json_len(text: &str) -> usize {
+    serde_json::to_string(text).map_or(text.len(), |j| j.len() - 2)
+}
+
+/// Split `text` into pages of at most `FLEX_PAGE_CHARS`, breaking at the last
+/// newline (or space) in each window when there is one.
+fn paginate(text: &str) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        if pages.len() == FLEX_MAX_PAGES - 1 && rest.chars().count() > FLEX_PAGE_CHARS {
+            let mut last: String = rest.chars().take(FLEX_PAGE_CHARS - 1).collect();
+            last.push('…');
+            pages.push(last);
+            break;
+        }
+
+        let window_end = rest
+            .char_indices()
+            .nth(FLEX_PAGE_CHARS)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let cut = if window_end == rest.len() {
+            window_end
+        } else {
+            let window = &rest[..window_end];
+            window
+                .rfind('\n')
+                .or_else(|| window.rfind(' '))
+                .filter(|&i| i > 0)
+                .unwrap_or(window_end)
+        };
+
+        pages.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
     }
+
+    pages
+}
+
+fn alt_text(text: &str) -> String {
+    let mut alt: String = text.chars().take(ALT_TEXT_MAX - 1).collect();
+    alt.push('…');
+    alt
 }
 
 #[cfg(test)]
@@ -15,14 +160,89 @@ mod tests {
     use super::*;
 
     #[test]
-    fn short_text_unchanged() {
-        assert_eq!(to_line_text("hello"), "hello");
+    fn short_text_is_plain_text_message() {
+        let messages = to_line_messages("hello");
+        assert_eq!(
+            messages,
+            vec![serde_json::json!({"type": "text", "text": "hello"})]
+        );
+    }
+
+    #[test]
+    fn long_text_becomes_flex_carousel() {
+        let paragraph = format!("{}\n", "word ".repeat(100));
+        let long = paragraph.repeat(12);
+        assert!(long.chars().count() > LINE_TEXT_MAX);
+
+        let messages = to_line_messages(&long);
+        assert_eq!(messages.len(), 1);
+        let flex = &messages[0];
+        assert_eq!(flex["type"], "flex");
+        assert_eq!(
+            flex["altText"].as_str().unwrap().chars().count(),
+            ALT_TEXT_MAX
+        );
+
+        let bubbles = flex["contents"]["contents"].as_array().unwrap();
+        assert_eq!(bubbles.len(), 4);
+        for (i, bubble) in bubbles.iter().enumerate() {
+            let page = bubble["body"]["contents"][0]["text"].as_str().unwrap();
+            assert!(page.chars().count() <= FLEX_PAGE_CHARS);
+            // Pages break on line boundaries, not mid-word.
+            assert!(page.ends_with("word"), "page {i}: {page:?}");
+            assert_eq!(
+                bubble["footer"]["contents"][0]["text"],
+                format!("{}/4", i + 1)
+            );
+        }
+
+        let joined: Vec<&str> = bubbles
+            .iter()
+            .map(|b| b["body"]["contents"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            joined.join("\n").split_whitespace().count(),
+            long.split_whitespace().count()
+        );
+    }
+
+    #[test]
+    fn unbroken_text_is_hard_split() {
+        let long = "ก".repeat(LINE_TEXT_MAX + 1);
+        let pages = paginate(&long);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].chars().count(), FLEX_PAGE_CHARS);
+        assert_eq!(pages.concat(), long);
+    }
+
+    #[test]
+    fn cjk_carousel_stays_under_byte_limit() {
+        let long = "漢".repeat(FLEX_PAGE_CHARS * FLEX_MAX_PAGES);
+        assert_eq!(paginate(&long).len(), FLEX_MAX_PAGES);
+
+        let messages = to_line_messages(&long);
+        let contents = &messages[0]["contents"];
+        let size = serde_json::to_string(contents).unwrap().len();
+        assert!(size <= FLEX_MAX_BYTES, "carousel is {size} bytes");
+        assert!(size > FLEX_MAX_BYTES - 1000, "budget left unused: {size}");
+
+        let bubbles = contents["contents"].as_array().unwrap();
+        assert!(bubbles.len() < FLEX_MAX_PAGES);
+        let last = bubbles.last().unwrap();
+        let page = last["body"]["contents"][0]["text"].as_str().unwrap();
+        assert!(page.ends_with('…'));
+        assert_eq!(
+            last["footer"]["contents"][0]["text"],
+            format!("{0}/{0}", bubbles.len())
+        );
     }
 
     #[test]
-    fn long_text_truncated() {
-        let long: String = "a".repeat(6000);
-        let result = to_line_text(&long);
-        assert_eq!(result.chars().count(), LINE_TEXT_MAX);
+    fn overlong_text_is_truncated_at_page_limit() {
+        let long = "a".repeat(FLEX_PAGE_CHARS * (FLEX_MAX_PAGES + 2));
+        let pages = paginate(&long);
+        assert_eq!(pages.len(), FLEX_MAX_PAGES);
+        assert!(pages.last().unwrap().ends_with('…'));
+        assert_eq!(pages.last().unwrap().chars().count(), FLEX_PAGE_CHARS);
     }
 }
@@ -0,0 +1,169 @@
+//! Outbound media for LINE.
+//!
+//! LINE image, audio and video messages reference their content by HTTPS URL
+//! rather than taking an upload, so attachments are kept in memory and served
+//! from `{public_url}/webhooks/line/media/{id}` until LINE has fetched them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use opencrust_common::{MediaAttachment, MediaKind};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// How long a pushed attachment stays downloadable.
+const MEDIA_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Attachments kept at once; the oldest is dropped beyond this.
+const MAX_MEDIA: usize = 100;
+
+/// Id of the placeholder preview image for videos.
+pub const VIDEO_PREVIEW_ID: &str = "video-preview.png";
+
+/// 1x1 grey PNG. LINE requires a preview image for videos and we do not
+/// render frames.
+const VIDEO_PREVIEW_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x3a, 0x7e, 0x9b,
+    0x55, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x70, 0x00, 0x00, 0x00,
+    0x42, 0x00, 0x41, 0x29, 0x37, 0xf4, 0xef, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+    0x42, 0x60, 0x82,
+];
+
+/// Attachments waiting to be fetched by LINE, keyed by an unguessable id.
+/// Clones share the same store.
+#[derive(Clone, Default)]
+pub struct LineMedia {
+    inner: Arc<Mutex<HashMap<String, (MediaAttachment, Instant)>>>,
+}
+
+impl LineMedia {
+    /// Keep `attachment` available and return its id.
+    pub fn stage(&self, attachment: &MediaAttachment) -> String {
+        let mut bytes = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .expect("system random source available");
+        let id: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+        let now = Instant::now();
+        let mut media = self.inner.lock().unwrap();
+        media.retain(|_, (_, staged_at)| now.duration_since(*staged_at) < MEDIA_TTL);
+        if media.len() >= MAX_MEDIA
+            && let Some(oldest) = media
+                .iter()
+                .min_by_key(|(_, (_, staged_at))| *staged_at)
+                .map(|(id, _)| id.clone())
+        {
+            media.remove(&oldest);
+        }
+        media.insert(id.clone(), (attachment.clone(), now));
+        id
+    }
+
+    /// Bytes and MIME type of the media with `id`, if it has not expired.
+    pub fn get(&self, id: &str) -> Option<(Vec<u8>, String)> {
+        if id == VIDEO_PREVIEW_ID {
+            return Some((VIDEO_PREVIEW_PNG.to_vec(), "image/png".to_string()));
+        }
+        let media = self.inner.lock().unwrap();
+        let (attachment, staged_at) = media.get(id)?;
+        (staged_at.elapsed() < MEDIA_TTL).then(|| {
+            let mime = attachment
+                .mime_type
+                .clone()
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            (attachment.data.clone(), mime)
+        })
+    }
+}
+
+/// LINE messages for `attachment`, whose content is served at `url`.
+/// `base_url` is the channel's public media prefix, for the video preview.
+///
+/// Documents have no message type for bots, so they are sent as a text
+/// message with a download link.
+pub fn attachment_messages(
+    attachment: &MediaAttachment,
+    url: &str,
+    base_url: &str,
+) -> Vec<serde_json::Value> {
+    let caption = attachment.caption.as_deref().filter(|c| !c.is_empty());
+    let media = match attachment.kind {
+        MediaKind::Photo => serde_json::json!({
+            "type": "image",
+            "originalContentUrl": url,
+            "previewImageUrl": url,
+        }),
+        MediaKind::Voice => serde_json::json!({
+            "type": "audio",
+            "originalContentUrl": url,
+            "duration": attachment.duration_secs.unwrap_or(1).max(1) * 1000,
+        }),
+        MediaKind::Video => serde_json::json!({
+            "type": "video",
+            "originalContentUrl": url,
+            "previewImageUrl": format!("{base_url}/{VIDEO_PREVIEW_ID}"),
+        }),
+        MediaKind::Document => {
+            return vec![serde_json::json!({
+                "type": "text",
+                "text": format!("{}\n{url}", attachment.describe()),
+            })];
+        }
+    };
+    let mut messages = vec![media];
+    if let Some(caption) = caption {
+        messages.push(serde_json::json!({ "type": "text", "text": caption }));
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_media_is_served_by_id() {
+        let media = LineMedia::default();
+        let photo = MediaAttachment::new(MediaKind::Photo, b"jpeg".to_vec())
+            .with_mime_type(Some("image/jpeg".into()));
+        let id = media.stage(&photo);
+        assert_eq!(id.len(), 32);
+        assert_eq!(
+            media.get(&id),
+            Some((b"jpeg".to_vec(), "image/jpeg".to_string()))
+        );
+        assert_eq!(media.get("unknown"), None);
+        assert_eq!(media.get(VIDEO_PREVIEW_ID).unwrap().1, "image/png");
+    }
+
+    #[test]
+    fn messages_match_attachment_kind() {
+        let base = "https://bot.example.com/webhooks/line/media";
+        let url = format!("{base}/abc");
+
+        let photo = MediaAttachment::new(MediaKind::Photo, vec![])
+            .with_caption(Some("Weekly numbers".into()));
+        let messages = attachment_messages(&photo, &url, base);
+        assert_eq!(messages[0]["type"], "image");
+        assert_eq!(messages[0]["previewImageUrl"], url);
+        assert_eq!(messages[1]["text"], "Weekly numbers");
+
+        let voice = MediaAttachment::new(MediaKind::Voice, vec![]).with_duration(3);
+        let messages = attachment_messages(&voice, &url, base);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["duration"], 3000);
+
+        let video = MediaAttachment::new(MediaKind::Video, vec![]);
+        let messages = attachment_messages(&video, &url, base);
+        assert_eq!(
+            messages[0]["previewImageUrl"],
+            format!("{base}/{VIDEO_PREVIEW_ID}")
+        );
+
+        let doc = MediaAttachment::new(MediaKind::Document, vec![]).with_filename("q3.pdf");
+        let messages = attachment_messages(&doc, &url, base);
+        assert_eq!(messages[0]["text"], format!("[file: q3.pdf]\n{url}"));
+    }
+}
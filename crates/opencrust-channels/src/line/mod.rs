@@ -1,5 +1,6 @@
 pub mod api;
 pub mod fmt;
+pub mod media;
 pub mod webhook;
 
 use std::future::Future;
@@ -14,7 +15,9 @@ use ring::hmac;
 use tokio::sync::mpsc;
 use tracing::info;
 
+use crate::dedup::{InboundDedup, SeenFn};
 use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
+use media::LineMedia;
 use opencrust_common::{Message, MessageContent, Result};
 
 /// Group filter closure for LINE channels.
@@ -84,6 +87,10 @@ pub struct LineChannel {
     group_filter: LineGroupFilter,
     /// Optional RAG observer: called for every group text message before reply filtering.
     group_observe_fn: Option<GroupObserveFn>,
+    dedup: InboundDedup,
+    /// Public HTTPS origin of the gateway, for serving outbound media to LINE.
+    public_url: Option<String>,
+    media: LineMedia,
 }
 
 impl LineChannel {
@@ -119,6 +126,9 @@ impl LineChannel {
             on_message,
             group_filter,
             group_observe_fn: None,
+            dedup: InboundDedup::default(),
+            public_url: None,
+            media: LineMedia::default(),
         }
     }
 
@@ -132,12 +142,36 @@ impl LineChannel {
         self.group_observe_fn.as_ref()
     }
 
+    /// Drop redelivered webhook events using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
+    /// Whether the event with `event_id` has not been handled yet. LINE
+    /// redelivers webhook events that were not acknowledged.
+    pub fn first_delivery(&self, event_id: &str) -> bool {
+        self.dedup.first_time(event_id)
+    }
+
     /// Override the config key name for this channel instance.
     pub fn with_name(mut self, name: String) -> Self {
         self.name = name;
         self
     }
 
+    /// Public HTTPS origin of the gateway (e.g. `https://bot.example.com`).
+    /// Without it, outbound attachments are sent as text.
+    pub fn with_public_url(mut self, public_url: String) -> Self {
+        self.public_url = Some(public_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Outbound media waiting to be fetched by LINE.
+    pub fn media(&self) -> &LineMedia {
+        &self.media
+    }
+
     /// Override the LINE messaging API base URL (e.g. to point at a mock server in tests).
     /// Also sets the data API base URL to the same value for test convenience.
     pub fn with_api_base_url(mut self, base_url: String) -> Self {
@@ -210,6 +244,8 @@ pub struct LineSender {
     channel_access_token: String,
     api_base_url: String,
     name: String,
+    public_url: Option<String>,
+    media: LineMedia,
 }
 
 #[async_trait]
@@ -227,6 +263,8 @@ impl ChannelSender for LineSender {
             &self.client,
             &self.channel_access_token,
             &self.api_base_url,
+            self.public_url.as_deref(),
+            &self.media,
             message,
         )
         .await
@@ -245,6 +283,8 @@ impl ChannelLifecycle for LineChannel {
             channel_access_token: self.channel_access_token.clone(),
             api_base_url: self.api_base_url.clone(),
             name: self.name.clone(),
+            public_url: self.public_url.clone(),
+            media: self.media.clone(),
         })
     }
 
@@ -318,6 +358,8 @@ impl ChannelSender for LineChannel {
             &self.client,
             &self.channel_access_token,
             &self.api_base_url,
+            self.public_url.as_deref(),
+            &self.media,
             message,
         )
         .await
@@ -327,11 +369,14 @@ impl ChannelSender for LineChannel {
 /// Push a message via LINE Push API.
 ///
 /// Requires `line_user_id` in `message.metadata`. Used by the scheduler
-/// and any code path that cannot use a reply token.
+/// and any code path that cannot use a reply token. Attachments are served
+/// from `public_url`; without one they are sent as text.
 async fn line_push_message(
     client: &Client,
     channel_access_token: &str,
     api_base_url: &str,
+    public_url: Option<&str>,
+    media: &LineMedia,
     message: &Message,
 ) -> Result<()> {
     let user_id = message
@@ -342,18 +387,32 @@ async fn line_push_message(
             opencrust_common::Error::Channel("missing line_user_id in metadata".into())
         })?;
 
-    let text = match &message.content {
-        MessageContent::Text(t) => fmt::to_line_text(t),
+    let messages = match (&message.content, public_url) {
+        (MessageContent::Text(t), _) => fmt::to_line_messages(t),
+        (MessageContent::Attachment(attachment), Some(public_url)) => {
+            let base = format!("{public_url}/webhooks/line/media");
+            let url = format!("{base}/{}", media.stage(attachment));
+            media::attachment_messages(attachment, &url, &base)
+        }
+        (MessageContent::Attachment(attachment), None) => {
+            fmt::to_line_messages(&attachment.describe())
+        }
         _ => {
             return Err(opencrust_common::Error::Channel(
-                "only text messages are supported for line send".into(),
+                "only text and attachment messages are supported for line send".into(),
             ));
         }
     };
 
-    api::push(client, channel_access_token, user_id, &text, api_base_url)
-        .await
-        .map_err(|e| opencrust_common::Error::Channel(format!("line push failed: {e}")))?;
+    api::push_messages(
+        client,
+        channel_access_token,
+        user_id,
+        messages,
+        api_base_url,
+    )
+    .await
+    .map_err(|e| opencrust_common::Error::Channel(format!("line push failed: {e}")))?;
 
     Ok(())
 }
@@ -391,6 +450,52 @@ mod tests {
         assert!(!ch.verify_signature(b"other body", &sig));
     }
 
+    #[tokio::test]
+    async fn attachments_are_pushed_as_media_with_public_url() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/message/push"))
+            .and(body_partial_json(serde_json::json!({
+                "to": "Uabc",
+                "messages": [{ "type": "image" }, { "type": "text", "text": "chart" }],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ch = LineChannel::new("tok".to_string(), "sec".to_string(), make_on_msg())
+            .with_api_base_url(server.uri())
+            .with_public_url("https://bot.example.com/".to_string());
+        let mut message = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("line"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "",
+        );
+        message.content = MessageContent::Attachment(
+            opencrust_common::MediaAttachment::new(
+                opencrust_common::MediaKind::Photo,
+                b"png".to_vec(),
+            )
+            .with_caption(Some("chart".into())),
+        );
+        message.metadata = serde_json::json!({ "line_user_id": "Uabc" });
+        ch.create_sender().send_message(&message).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = requests[0].body_json().unwrap();
+        let url = body["messages"][0]["originalContentUrl"].as_str().unwrap();
+        let id = url
+            .strip_prefix("https://bot.example.com/webhooks/line/media/")
+            .unwrap();
+        assert_eq!(ch.media().get(id).unwrap().0, b"png");
+    }
+
     #[test]
     fn group_filter_default_allows_all() {
         let ch = LineChannel::new("tok".to_string(), "sec".to_string(), make_on_msg());
@@ -1,20 +1,31 @@
 use std::sync::Arc;
 
 use axum::body::Bytes;
-use axum::extract::{Request, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::extract::{Path, Request, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
 use tracing::{info, warn};
 
 use crate::traits::ChannelResponse;
 
 use super::api;
-use super::fmt;
 use super::{LineChannel, LineFile};
 
 /// Shared state passed to LINE webhook handlers.
 pub type LineWebhookState = Arc<Vec<Arc<LineChannel>>>;
 
+/// GET /webhooks/line/media/{id} — serves outbound media to LINE, which
+/// fetches image, audio and video content by URL.
+pub async fn line_media(
+    State(channels): State<LineWebhookState>,
+    Path(id): Path<String>,
+) -> Response {
+    match channels.iter().find_map(|ch| ch.media().get(&id)) {
+        Some((data, mime)) => ([(header::CONTENT_TYPE, mime)], data).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
 /// POST /webhooks/line — receives webhook events from the LINE platform.
 ///
 /// Verifies the `X-Line-Signature` header (HMAC-SHA256 with channel secret),
@@ -71,6 +82,16 @@ pub async fn line_webhook(
             None => continue,
         };
 
+        let event_id = event
+            .get("webhookEventId")
+            .or_else(|| msg.get("id"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if !channel.first_delivery(event_id) {
+            info!("line: duplicate delivery of {event_id}, skipping");
+            continue;
+        }
+
         let msg_type = msg.get("type").and_then(|v| v.as_str()).unwrap_or("");
 
         // Extract text and an optional file depending on message type.
@@ -252,14 +273,14 @@ pub async fn line_webhook(
                             "line: audio reply not yet supported (LINE requires CDN URL); sending text"
                         );
                     }
-                    let out = fmt::to_line_text(response.text());
+                    let out = response.text();
                     // Try reply API first (free), fallback to push.
                     if !reply_token.is_empty() {
                         match api::reply(
                             ch.client(),
                             ch.channel_access_token(),
                             &reply_token,
-                            &out,
+                            out,
                             ch.api_base_url(),
                         )
                         .await
@@ -276,7 +297,7 @@ pub async fn line_webhook(
                             ch.client(),
                             ch.channel_access_token(),
                             &user_id,
-                            &out,
+                            out,
                             ch.api_base_url(),
                         )
                         .await
@@ -363,6 +384,7 @@ mod tests {
     fn make_router(state: LineWebhookState) -> Router {
         Router::new()
             .route("/webhooks/line", post(line_webhook))
+            .route("/webhooks/line/media/{id}", axum::routing::get(line_media))
             .with_state(state)
     }
 
@@ -403,6 +425,39 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn staged_media_is_served() {
+        let state = make_state("test-secret");
+        let photo = opencrust_common::MediaAttachment::new(
+            opencrust_common::MediaKind::Photo,
+            b"jpeg".to_vec(),
+        )
+        .with_mime_type(Some("image/jpeg".into()));
+        let id = state[0].media().stage(&photo);
+
+        let get = |uri: String| {
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let resp = make_router(state.clone())
+            .oneshot(get(format!("/webhooks/line/media/{id}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "image/jpeg");
+        let body = axum::body::to_bytes(resp.into_body(), 1024).await.unwrap();
+        assert_eq!(&body[..], b"jpeg");
+
+        let resp = make_router(state)
+            .oneshot(get("/webhooks/line/media/unknown".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn invalid_signature_returns_401() {
         let body = text_event("tok123", "Uabc", "hello");
@@ -92,6 +92,7 @@ impl ChannelSender for MqttSender {
 
         let text = match &message.content {
             MessageContent::Text(t) => t.clone(),
+            MessageContent::Attachment(attachment) => attachment.describe(),
             _ => {
                 return Err(opencrust_common::Error::Channel(
                     "only text messages are supported for mqtt send".into(),
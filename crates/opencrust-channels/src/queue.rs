@@ -0,0 +1,434 @@
+//! Outbound message queue with per-channel rate limiting.
+//!
+//! A [`QueuedSender`] wraps a channel's sender so outbound messages wait their
+//! turn instead of hitting the platform API all at once. Messages to the same
+//! chat go out in the order they were sent, spaced to the channel's per-chat
+//! limit, and every chat shares the channel's global limit. A send rejected
+//! with [`Error::RateLimited`] is retried after the wait the platform asked for.
+//!
+//! The limits live in a [`RateLimiter`] that a channel can share with its
+//! sender (see [`ChannelSender::rate_limiter`]), so replies the channel sends
+//! itself while answering a message count against the same budget.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use opencrust_common::{Error, Message, Result};
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::traits::ChannelSender;
+
+/// At most `messages` sends in any window of length `per`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub messages: usize,
+    pub per: Duration,
+}
+
+impl Rate {
+    pub const fn new(messages: usize, per: Duration) -> Self {
+        Self { messages, per }
+    }
+}
+
+/// Outbound limits for one channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Limit across every chat of the channel (one bot token).
+    pub global: Option<Rate>,
+    /// Limit per chat.
+    pub per_chat: Option<Rate>,
+    /// Message metadata key holding the chat id. Without one, all messages
+    /// share a single chat.
+    pub chat_key: Option<&'static str>,
+}
+
+impl RateLimits {
+    /// The published limits of the built-in channels. Other channels are not
+    /// throttled, but their messages still keep their order and are retried
+    /// when rate limited.
+    pub fn for_channel(channel_type: &str) -> Self {
+        const SECOND: Duration = Duration::from_secs(1);
+        match channel_type {
+            "telegram" => Self {
+                global: Some(Rate::new(30, SECOND)),
+                per_chat: Some(Rate::new(1, SECOND)),
+                chat_key: Some("telegram_chat_id"),
+            },
+            // Discord buckets message creation per channel route (5 per 5s)
+            // under a global 50 requests per second.
+            "discord" => Self {
+                global: Some(Rate::new(50, SECOND)),
+                per_chat: Some(Rate::new(5, Duration::from_secs(5))),
+                chat_key: Some("discord_channel_id"),
+            },
+            "slack" => Self {
+                global: None,
+                per_chat: Some(Rate::new(1, SECOND)),
+                chat_key: Some("slack_channel_id"),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    fn chat_of(&self, message: &Message) -> String {
+        match self.chat_key.and_then(|key| message.metadata.get(key)) {
+            Some(serde_json::Value::String(chat)) => chat.clone(),
+            Some(chat) => chat.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Times a rate-limited send is retried before its error is returned.
+const MAX_RETRIES: u32 = 3;
+
+/// Wait before a retry when the platform did not say how long to wait.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Chats tracked before idle ones are forgotten.
+const MAX_CHATS: usize = 1024;
+
+/// Send times within the last `rate.per`.
+struct Window {
+    rate: Option<Rate>,
+    sent: VecDeque<Instant>,
+}
+
+impl Window {
+    fn new(rate: Option<Rate>) -> Self {
+        Self {
+            rate,
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// Wait for a free slot in the window and take it.
+    async fn acquire(&mut self) {
+        let Some(rate) = self.rate else {
+            return;
+        };
+        loop {
+            let now = Instant::now();
+            while self
+                .sent
+                .front()
+                .is_some_and(|sent| now.duration_since(*sent) >= rate.per)
+            {
+                self.sent.pop_front();
+            }
+            if self.sent.len() < rate.messages.max(1) {
+                self.sent.push_back(now);
+                return;
+            }
+            tokio::time::sleep_until(self.sent[0] + rate.per).await;
+        }
+    }
+
+    /// Whether a send now would not be delayed by earlier ones.
+    fn is_idle(&self) -> bool {
+        self.rate.is_none_or(|rate| {
+            self.sent
+                .back()
+                .is_none_or(|sent| sent.elapsed() >= rate.per)
+        })
+    }
+}
+
+/// The send budget of one channel: its [`RateLimits`] and the sends made
+/// under them so far.
+pub struct RateLimiter {
+    limits: RateLimits,
+    global: tokio::sync::Mutex<Window>,
+    chats: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Window>>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            global: tokio::sync::Mutex::new(Window::new(limits.global)),
+            limits,
+            chats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A limiter with the published limits of `channel_type`.
+    pub fn for_channel(channel_type: &str) -> Self {
+        Self::new(RateLimits::for_channel(channel_type))
+    }
+
+    /// Wait until one more request to `chat` fits the per-chat and global
+    /// limits and count it. For calls that do not go through a
+    /// [`QueuedSender`], such as streaming edits of a reply. `chat` is the
+    /// value of the channel's chat metadata key, e.g. a Telegram chat id.
+    pub async fn acquire(&self, chat: &str) {
+        let chat = self.chat(chat.to_string());
+        chat.lock().await.acquire().await;
+        self.global.lock().await.acquire().await;
+    }
+
+    fn chat(&self, chat: String) -> Arc<tokio::sync::Mutex<Window>> {
+        let mut chats = self.chats.lock().unwrap();
+        if chats.len() >= MAX_CHATS {
+            chats.retain(|_, window| {
+                Arc::strong_count(window) > 1
+                    || match window.try_lock() {
+                        Ok(window) => !window.is_idle(),
+                        Err(_) => true,
+                    }
+            });
+        }
+        let per_chat = self.limits.per_chat;
+        Arc::clone(
+            chats
+                .entry(chat)
+                .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(Window::new(per_chat)))),
+        )
+    }
+}
+
+/// A [`ChannelSender`] that queues messages under the channel's
+/// [`RateLimits`]. Typing indicators are passed straight through.
+pub struct QueuedSender {
+    inner: Box<dyn ChannelSender>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl QueuedSender {
+    pub fn new(inner: Box<dyn ChannelSender>, limits: RateLimits) -> Self {
+        Self::with_limiter(inner, Arc::new(RateLimiter::new(limits)))
+    }
+
+    /// Queue under `limiter`, which other senders of the channel may share.
+    pub fn with_limiter(inner: Box<dyn ChannelSender>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl ChannelSender for QueuedSender {
+    fn channel_type(&self) -> &str {
+        self.inner.channel_type()
+    }
+
+    fn channel_name(&self) -> &str {
+        self.inner.channel_name()
+    }
+
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        Some(Arc::clone(&self.limiter))
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        let chat = self.limiter.chat(self.limiter.limits.chat_of(message));
+        // Held until the message is delivered, so later messages to the chat
+        // queue behind it (tokio's mutex wakes waiters in FIFO order).
+        let mut chat = chat.lock().await;
+        let mut retries = 0;
+        loop {
+            chat.acquire().await;
+            self.limiter.global.lock().await.acquire().await;
+            match self.inner.send_message(message).await {
+                Err(Error::RateLimited {
+                    message: reason,
+                    retry_after,
+                }) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    let wait = retry_after.unwrap_or(DEFAULT_RETRY_AFTER);
+                    warn!(
+                        "{}: {reason}, retrying in {}ms",
+                        self.inner.channel_name(),
+                        wait.as_millis()
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        self.inner.send_typing(channel_ref).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencrust_common::{ChannelId, MessageContent, MessageDirection, SessionId, UserId};
+
+    /// Records what was sent and when. Fails the first `rate_limited` sends.
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Arc<Mutex<Vec<(String, Instant)>>>,
+        rate_limited: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl ChannelSender for RecordingSender {
+        fn channel_type(&self) -> &str {
+            "test"
+        }
+
+        async fn send_message(&self, message: &Message) -> Result<()> {
+            {
+                let mut rate_limited = self.rate_limited.lock().unwrap();
+                if *rate_limited > 0 {
+                    *rate_limited -= 1;
+                    return Err(Error::RateLimited {
+                        message: "429".into(),
+                        retry_after: Some(Duration::from_millis(10)),
+                    });
+                }
+            }
+            let MessageContent::Text(text) = &message.content else {
+                return Err(Error::Channel("text only".into()));
+            };
+            self.sent
+                .lock()
+                .unwrap()
+                .push((text.clone(), Instant::now()));
+            Ok(())
+        }
+    }
+
+    fn message(chat: &str, text: &str) -> Message {
+        let mut message = Message::text(
+            SessionId::from_string("s"),
+            ChannelId::from_string("test"),
+            UserId::from_string("u"),
+            MessageDirection::Outgoing,
+            text,
+        );
+        message.metadata = serde_json::json!({ "chat": chat });
+        message
+    }
+
+    fn per_chat(per: Duration) -> RateLimits {
+        RateLimits {
+            global: None,
+            per_chat: Some(Rate::new(1, per)),
+            chat_key: Some("chat"),
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_order_and_spacing_within_a_chat() {
+        let inner = RecordingSender::default();
+        let sent = Arc::clone(&inner.sent);
+        let queue = QueuedSender::new(Box::new(inner), per_chat(Duration::from_millis(40)));
+
+        let (a, b, c) = (message("1", "a"), message("1", "b"), message("1", "c"));
+        let results = tokio::join!(
+            queue.send_message(&a),
+            queue.send_message(&b),
+            queue.send_message(&c)
+        );
+        assert!(results.0.is_ok() && results.1.is_ok() && results.2.is_ok());
+
+        let sent = sent.lock().unwrap();
+        let texts: Vec<&str> = sent.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["a", "b", "c"]);
+        for pair in sent.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= Duration::from_millis(40));
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_chat_does_not_hold_up_others() {
+        let inner = RecordingSender::default();
+        let sent = Arc::clone(&inner.sent);
+        let queue = QueuedSender::new(Box::new(inner), per_chat(Duration::from_millis(200)));
+
+        let (a1, a2, b) = (message("1", "a1"), message("1", "a2"), message("2", "b"));
+        let _ = tokio::join!(
+            queue.send_message(&a1),
+            queue.send_message(&a2),
+            queue.send_message(&b)
+        );
+
+        let texts: Vec<String> = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(t, _)| t.clone())
+            .collect();
+        assert_eq!(texts, ["a1", "b", "a2"]);
+    }
+
+    #[tokio::test]
+    async fn global_limit_spans_chats() {
+        let inner = RecordingSender::default();
+        let sent = Arc::clone(&inner.sent);
+        let limits = RateLimits {
+            global: Some(Rate::new(2, Duration::from_millis(50))),
+            per_chat: None,
+            chat_key: Some("chat"),
+        };
+        let queue = QueuedSender::new(Box::new(inner), limits);
+
+        let (a, b, c) = (message("1", "a"), message("2", "b"), message("3", "c"));
+        let _ = tokio::join!(
+            queue.send_message(&a),
+            queue.send_message(&b),
+            queue.send_message(&c)
+        );
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[2].1 - sent[0].1 >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn replies_and_queued_sends_share_the_limiter() {
+        let inner = RecordingSender::default();
+        let sent = Arc::clone(&inner.sent);
+        let limiter = Arc::new(RateLimiter::new(per_chat(Duration::from_millis(60))));
+        let queue = QueuedSender::with_limiter(Box::new(inner), Arc::clone(&limiter));
+        assert!(Arc::ptr_eq(&queue.rate_limiter().unwrap(), &limiter));
+
+        let start = Instant::now();
+        limiter.acquire("1").await;
+        queue.send_message(&message("1", "a")).await.unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert!(sent[0].1 - start >= Duration::from_millis(60));
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_sends() {
+        let inner = RecordingSender {
+            rate_limited: Mutex::new(2),
+            ..Default::default()
+        };
+        let sent = Arc::clone(&inner.sent);
+        let queue = QueuedSender::new(Box::new(inner), RateLimits::default());
+
+        queue.send_message(&message("1", "hi")).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let inner = RecordingSender {
+            rate_limited: Mutex::new(MAX_RETRIES + 1),
+            ..Default::default()
+        };
+        let queue = QueuedSender::new(Box::new(inner), RateLimits::default());
+
+        let err = queue.send_message(&message("1", "hi")).await.unwrap_err();
+        assert!(matches!(err, Error::RateLimited { .. }));
+    }
+
+    #[test]
+    fn reads_chat_from_metadata() {
+        let limits = RateLimits::for_channel("telegram");
+        let mut message = message("", "hi");
+        message.metadata = serde_json::json!({ "telegram_chat_id": -100123 });
+        assert_eq!(limits.chat_of(&message), "-100123");
+        assert_eq!(RateLimits::for_channel("mqtt"), RateLimits::default());
+    }
+}
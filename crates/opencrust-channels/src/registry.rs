@@ -1,38 +1,316 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use opencrust_common::Result;
-use tracing::info;
+use opencrust_common::{Error, Message, Result};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
 
-use crate::traits::Channel;
+use crate::queue::{QueuedSender, RateLimiter};
+use crate::traits::{Channel, ChannelEvent, ChannelSender, ChannelStatus};
+
+/// Builds a fresh, disconnected channel from the config it captured.
+///
+/// Registered alongside a channel so the registry can recreate it (e.g. to
+/// restart a channel) without access to the rest of the app state.
+pub type ChannelFactory = Arc<dyn Fn() -> Result<Box<dyn Channel>> + Send + Sync>;
+
+/// Health checks and reconnect backoff for [`ChannelRegistry::supervise`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// How often the channel's status is polled.
+    pub check_interval: Duration,
+    /// Wait before the first reconnect; doubled for each failure in a row.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(30),
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before reconnect attempt `attempt` (1-based).
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay)
+    }
+}
 
 /// Central registry of all available messaging channels.
+///
+/// Channels are kept in registration order, so listings, status output and
+/// connect/disconnect sequencing are stable across runs.
 pub struct ChannelRegistry {
-    channels: HashMap<String, Box<dyn Channel>>,
+    channels: Vec<(String, Box<dyn Channel>)>,
+    factories: HashMap<String, ChannelFactory>,
+    restart_policy: RestartPolicy,
+    events: broadcast::Sender<(String, ChannelEvent)>,
+    /// Senders handed out by [`queued_sender`](Self::queued_sender), the
+    /// targets of [`broadcast`](Self::broadcast).
+    senders: Mutex<Vec<Arc<dyn ChannelSender>>>,
+    /// Last status seen by [`supervise`](Self::supervise), by channel name.
+    statuses: Mutex<HashMap<String, ChannelStatus>>,
 }
 
 impl ChannelRegistry {
     pub fn new() -> Self {
         Self {
-            channels: HashMap::new(),
+            channels: Vec::new(),
+            factories: HashMap::new(),
+            restart_policy: RestartPolicy::default(),
+            events: broadcast::channel(64).0,
+            senders: Mutex::new(Vec::new()),
+            statuses: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Override how supervised channels are checked and reconnected.
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Status changes of supervised channels, tagged with the channel name.
+    pub fn subscribe(&self) -> broadcast::Receiver<(String, ChannelEvent)> {
+        self.events.subscribe()
+    }
+
+    /// Register `channel`. Re-registering a channel type replaces the old
+    /// instance in place, keeping its position.
     pub fn register(&mut self, channel: Box<dyn Channel>) {
         let channel_type = channel.channel_type().to_string();
         info!("registered channel: {}", channel_type);
-        self.channels.insert(channel_type, channel);
+        self.insert(channel_type, channel);
+    }
+
+    fn insert(&mut self, channel_type: String, channel: Box<dyn Channel>) -> &mut Box<dyn Channel> {
+        let index = match self.position(&channel_type) {
+            Some(index) => {
+                self.channels[index].1 = channel;
+                index
+            }
+            None => {
+                self.channels.push((channel_type, channel));
+                self.channels.len() - 1
+            }
+        };
+        &mut self.channels[index].1
+    }
+
+    fn position(&self, channel_type: &str) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|(name, _)| name == channel_type)
+    }
+
+    /// Build a channel with `factory` and register both, so the channel can
+    /// later be recreated with [`rebuild`](Self::rebuild).
+    pub fn register_factory(&mut self, factory: ChannelFactory) -> Result<()> {
+        let channel = factory()?;
+        self.factories
+            .insert(channel.channel_type().to_string(), factory);
+        self.register(channel);
+        Ok(())
+    }
+
+    /// Replace a channel with a fresh, disconnected instance from its factory.
+    ///
+    /// The old instance is dropped without being disconnected; call
+    /// `disconnect` on it first if it may still be running.
+    pub fn rebuild(&mut self, channel_type: &str) -> Result<&mut Box<dyn Channel>> {
+        let factory = self.factories.get(channel_type).ok_or_else(|| {
+            Error::Channel(format!("no factory registered for channel: {channel_type}"))
+        })?;
+        let channel = factory()?;
+        if channel.channel_type() != channel_type {
+            return Err(Error::Channel(format!(
+                "factory for {channel_type} built a {} channel",
+                channel.channel_type()
+            )));
+        }
+        info!("rebuilt channel: {}", channel_type);
+        Ok(self.insert(channel_type.to_string(), channel))
     }
 
     pub fn get(&self, channel_type: &str) -> Option<&dyn Channel> {
-        self.channels.get(channel_type).map(|c| c.as_ref())
+        self.position(channel_type)
+            .map(|index| self.channels[index].1.as_ref())
     }
 
     pub fn get_mut(&mut self, channel_type: &str) -> Option<&mut Box<dyn Channel>> {
-        self.channels.get_mut(channel_type)
+        self.position(channel_type)
+            .map(|index| &mut self.channels[index].1)
     }
 
+    /// Channel types in registration order.
     pub fn list(&self) -> Vec<&str> {
-        self.channels.keys().map(|k| k.as_str()).collect()
+        self.channels
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Channels with their types, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &dyn Channel)> {
+        self.channels
+            .iter()
+            .map(|(name, channel)| (name.as_str(), channel.as_ref()))
+    }
+
+    /// Wrap `sender` in an outbound queue throttled to the published limits
+    /// of its channel type (see [`crate::RateLimits::for_channel`]), on the
+    /// sender's own [`RateLimiter`] when it has one. Senders shared
+    /// with the rest of the app should go through this rather than calling
+    /// the platform API directly.
+    ///
+    /// The sender is also kept as a [`broadcast`](Self::broadcast) target,
+    /// replacing an earlier one with the same channel name.
+    pub fn queued_sender(&self, sender: Box<dyn ChannelSender>) -> Arc<dyn ChannelSender> {
+        let limiter = sender
+            .rate_limiter()
+            .unwrap_or_else(|| Arc::new(RateLimiter::for_channel(sender.channel_type())));
+        let sender: Arc<dyn ChannelSender> = Arc::new(QueuedSender::with_limiter(sender, limiter));
+        let mut senders = self.senders.lock().unwrap();
+        senders.retain(|s| s.channel_name() != sender.channel_name());
+        senders.push(Arc::clone(&sender));
+        sender
+    }
+
+    /// Send a message through every sender from
+    /// [`queued_sender`](Self::queued_sender) whose channel is connected.
+    ///
+    /// `message_for` builds the message for a channel, or returns `None` to
+    /// skip it (e.g. when there is nobody to address). Channels that are not
+    /// supervised have no known status and are treated as connected. Returns
+    /// each attempted channel's name with the result of its send, in the
+    /// order the senders were created.
+    pub async fn broadcast(
+        &self,
+        message_for: impl Fn(&dyn ChannelSender) -> Option<Message>,
+    ) -> Vec<(String, Result<()>)> {
+        let senders: Vec<Arc<dyn ChannelSender>> = {
+            let statuses = self.statuses.lock().unwrap();
+            self.senders
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|sender| {
+                    statuses
+                        .get(sender.channel_name())
+                        .is_none_or(|status| *status == ChannelStatus::Connected)
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut results = Vec::new();
+        for sender in senders {
+            let Some(message) = message_for(sender.as_ref()) else {
+                continue;
+            };
+            let result = sender.send_message(&message).await;
+            if let Err(e) = &result {
+                warn!("{}: broadcast failed: {e}", sender.channel_name());
+            }
+            results.push((sender.channel_name().to_string(), result));
+        }
+        results
+    }
+
+    /// Connect `channel` and keep it connected until `shutdown` resolves,
+    /// then disconnect it.
+    ///
+    /// The channel's status is polled every
+    /// [`check_interval`](RestartPolicy::check_interval). A channel that
+    /// reports `Disconnected` or `Error` (for example because its background
+    /// client exited) is disconnected and connected again after an exponential
+    /// backoff, which resets once it is back up. Every status change is sent to
+    /// [`subscribe`](Self::subscribe) receivers as
+    /// [`ChannelEvent::StatusChanged`].
+    ///
+    /// Fails only if the first connect fails.
+    pub async fn supervise(
+        &self,
+        channel: &mut dyn Channel,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let name = channel.channel_name().to_string();
+        let policy = self.restart_policy;
+        let emit = |status: ChannelStatus| {
+            self.statuses
+                .lock()
+                .unwrap()
+                .insert(name.clone(), status.clone());
+            let _ = self
+                .events
+                .send((name.clone(), ChannelEvent::StatusChanged(status)));
+        };
+
+        channel.connect().await?;
+        let mut last = channel.status();
+        emit(last.clone());
+
+        tokio::pin!(shutdown);
+        let mut ticks = tokio::time::interval(policy.check_interval);
+        ticks.tick().await;
+        let mut attempt = 0;
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = ticks.tick() => {}
+            }
+
+            let status = channel.status();
+            if status != last {
+                emit(status.clone());
+                last = status.clone();
+            }
+            match status {
+                ChannelStatus::Connected => attempt = 0,
+                ChannelStatus::Disconnected | ChannelStatus::Error(_) => {
+                    attempt += 1;
+                    let delay = policy.delay(attempt);
+                    warn!(
+                        "{name}: channel is down ({status:?}), reconnecting in {delay:?} (attempt {attempt})"
+                    );
+                    tokio::select! {
+                        _ = &mut shutdown => break,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+
+                    // Clean up whatever is left of the old connection first.
+                    let _ = channel.disconnect().await;
+                    emit(ChannelStatus::Reconnecting);
+                    match channel.connect().await {
+                        Ok(()) => info!("{name}: channel reconnected"),
+                        Err(e) => warn!("{name}: reconnect failed: {e}"),
+                    }
+                    last = channel.status();
+                    emit(last.clone());
+                    ticks.reset();
+                }
+                // The channel is already (re)connecting on its own.
+                ChannelStatus::Connecting | ChannelStatus::Reconnecting => {}
+            }
+        }
+
+        if let Err(e) = channel.disconnect().await {
+            warn!("{name}: disconnect failed: {e}");
+        }
+        self.statuses
+            .lock()
+            .unwrap()
+            .insert(name, ChannelStatus::Disconnected);
+        Ok(())
     }
 
     pub async fn connect_all(&mut self) -> Result<()> {
@@ -57,3 +335,330 @@ impl Default for ChannelRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::{ChannelLifecycle, ChannelSender, ChannelStatus};
+    use async_trait::async_trait;
+    use opencrust_common::Message;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockChannel {
+        instance: usize,
+        status: ChannelStatus,
+    }
+
+    #[async_trait]
+    impl ChannelLifecycle for MockChannel {
+        fn display_name(&self) -> &str {
+            "Mock"
+        }
+        async fn connect(&mut self) -> Result<()> {
+            self.status = ChannelStatus::Connected;
+            Ok(())
+        }
+        async fn disconnect(&mut self) -> Result<()> {
+            self.status = ChannelStatus::Disconnected;
+            Ok(())
+        }
+        fn status(&self) -> ChannelStatus {
+            self.status.clone()
+        }
+        fn create_sender(&self) -> Box<dyn ChannelSender> {
+            Box::new(MockChannel {
+                instance: self.instance,
+                status: ChannelStatus::Disconnected,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl ChannelSender for MockChannel {
+        fn channel_type(&self) -> &str {
+            "mock"
+        }
+        async fn send_message(&self, _message: &Message) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn counting_factory(built: Arc<AtomicUsize>) -> ChannelFactory {
+        Arc::new(move || {
+            Ok(Box::new(MockChannel {
+                instance: built.fetch_add(1, Ordering::SeqCst),
+                status: ChannelStatus::Disconnected,
+            }) as Box<dyn Channel>)
+        })
+    }
+
+    #[tokio::test]
+    async fn rebuild_replaces_channel_with_fresh_disconnected_instance() {
+        let built = Arc::new(AtomicUsize::new(0));
+        let mut registry = ChannelRegistry::new();
+        registry
+            .register_factory(counting_factory(built.clone()))
+            .unwrap();
+        registry.connect_all().await.unwrap();
+        assert_eq!(
+            registry.get("mock").unwrap().status(),
+            ChannelStatus::Connected
+        );
+
+        let channel = registry.rebuild("mock").unwrap();
+        assert_eq!(channel.status(), ChannelStatus::Disconnected);
+        assert_eq!(built.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.list(), vec!["mock"]);
+    }
+
+    #[test]
+    fn rebuild_without_factory_fails() {
+        let mut registry = ChannelRegistry::new();
+        registry.register(Box::new(MockChannel {
+            instance: 0,
+            status: ChannelStatus::Disconnected,
+        }));
+        let err = registry.rebuild("mock").err().unwrap();
+        assert!(err.to_string().contains("no factory registered"));
+    }
+
+    #[test]
+    fn factory_errors_leave_registry_unchanged() {
+        let mut registry = ChannelRegistry::new();
+        let factory: ChannelFactory =
+            Arc::new(|| Err(Error::Channel("missing bot token".to_string())));
+        assert!(registry.register_factory(factory).is_err());
+        assert!(registry.list().is_empty());
+    }
+
+    struct NamedChannel(&'static str);
+
+    #[async_trait]
+    impl ChannelLifecycle for NamedChannel {
+        fn display_name(&self) -> &str {
+            self.0
+        }
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+        async fn disconnect(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn status(&self) -> ChannelStatus {
+            ChannelStatus::Disconnected
+        }
+        fn create_sender(&self) -> Box<dyn ChannelSender> {
+            Box::new(NamedChannel(self.0))
+        }
+    }
+
+    #[async_trait]
+    impl ChannelSender for NamedChannel {
+        fn channel_type(&self) -> &str {
+            self.0
+        }
+        async fn send_message(&self, _message: &Message) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn channels_are_listed_in_registration_order() {
+        let names = ["telegram", "slack", "discord", "line", "whatsapp", "mqtt"];
+        let mut registry = ChannelRegistry::new();
+        for name in names {
+            registry.register(Box::new(NamedChannel(name)));
+        }
+        assert_eq!(registry.list(), names);
+
+        // Re-registering keeps the channel's original position.
+        registry.register(Box::new(NamedChannel("slack")));
+        let iterated: Vec<&str> = registry.iter().map(|(name, _)| name).collect();
+        assert_eq!(iterated, names);
+        assert_eq!(registry.get("line").unwrap().display_name(), "line");
+    }
+
+    #[tokio::test]
+    async fn queued_sender_keeps_channel_identity() {
+        let registry = ChannelRegistry::new();
+        let sender = registry.queued_sender(Box::new(NamedChannel("telegram")));
+        assert_eq!(sender.channel_type(), "telegram");
+        assert!(
+            sender
+                .send_message(&Message::text(
+                    opencrust_common::SessionId::from_string("s"),
+                    opencrust_common::ChannelId::from_string("telegram"),
+                    opencrust_common::UserId::from_string("u"),
+                    opencrust_common::MessageDirection::Outgoing,
+                    "hi",
+                ))
+                .await
+                .is_ok()
+        );
+    }
+
+    /// A channel whose status can be knocked over from outside, like a client
+    /// task dying in the background.
+    #[derive(Clone, Default)]
+    struct FlakyChannel {
+        status: Arc<std::sync::Mutex<Option<ChannelStatus>>>,
+        connects: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ChannelLifecycle for FlakyChannel {
+        fn display_name(&self) -> &str {
+            "Flaky"
+        }
+        async fn connect(&mut self) -> Result<()> {
+            self.connects.fetch_add(1, Ordering::SeqCst);
+            *self.status.lock().unwrap() = Some(ChannelStatus::Connected);
+            Ok(())
+        }
+        async fn disconnect(&mut self) -> Result<()> {
+            *self.status.lock().unwrap() = Some(ChannelStatus::Disconnected);
+            Ok(())
+        }
+        fn status(&self) -> ChannelStatus {
+            self.status
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap_or(ChannelStatus::Disconnected)
+        }
+        fn create_sender(&self) -> Box<dyn ChannelSender> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[async_trait]
+    impl ChannelSender for FlakyChannel {
+        fn channel_type(&self) -> &str {
+            "flaky"
+        }
+        async fn send_message(&self, _message: &Message) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Records the text of every message it sends.
+    struct RecordingSender {
+        name: &'static str,
+        sent: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl ChannelSender for RecordingSender {
+        fn channel_type(&self) -> &str {
+            "recording"
+        }
+        fn channel_name(&self) -> &str {
+            self.name
+        }
+        async fn send_message(&self, message: &Message) -> Result<()> {
+            let opencrust_common::MessageContent::Text(text) = &message.content else {
+                return Err(Error::Channel("text only".into()));
+            };
+            self.sent
+                .lock()
+                .unwrap()
+                .push(format!("{}: {text}", self.name));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast_skips_channels_that_are_down() {
+        use opencrust_common::{ChannelId, MessageDirection, SessionId, UserId};
+
+        let registry = ChannelRegistry::new();
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        for name in ["up", "down", "unaddressed"] {
+            registry.queued_sender(Box::new(RecordingSender {
+                name,
+                sent: Arc::clone(&sent),
+            }));
+        }
+        registry
+            .statuses
+            .lock()
+            .unwrap()
+            .insert("down".into(), ChannelStatus::Error("gone".into()));
+
+        let results = registry
+            .broadcast(|sender| {
+                (sender.channel_name() != "unaddressed").then(|| {
+                    Message::text(
+                        SessionId::new(),
+                        ChannelId::from_string(sender.channel_name()),
+                        UserId::from_string("owner"),
+                        MessageDirection::Outgoing,
+                        "maintenance at 22:00",
+                    )
+                })
+            })
+            .await;
+
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["up"]);
+        assert!(results[0].1.is_ok());
+        assert_eq!(*sent.lock().unwrap(), ["up: maintenance at 22:00"]);
+    }
+
+    #[tokio::test]
+    async fn supervise_reconnects_dead_channel() {
+        let registry = ChannelRegistry::new().with_restart_policy(RestartPolicy {
+            check_interval: Duration::from_millis(10),
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        });
+        let mut events = registry.subscribe();
+        let mut channel = FlakyChannel::default();
+        let probe = channel.clone();
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let supervisor = async {
+            registry
+                .supervise(&mut channel, async {
+                    let _ = stop_rx.await;
+                })
+                .await
+        };
+        let driver = async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            *probe.status.lock().unwrap() = Some(ChannelStatus::Error("client exited".into()));
+            while probe.connects.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            let _ = stop_tx.send(());
+        };
+        let (result, ()) = tokio::join!(supervisor, driver);
+        result.unwrap();
+
+        assert_eq!(probe.connects.load(Ordering::SeqCst), 2);
+        assert_eq!(probe.status(), ChannelStatus::Disconnected);
+
+        let mut statuses = Vec::new();
+        while let Ok((name, ChannelEvent::StatusChanged(status))) = events.try_recv() {
+            assert_eq!(name, "flaky");
+            statuses.push(status);
+        }
+        assert_eq!(
+            statuses,
+            [
+                ChannelStatus::Connected,
+                ChannelStatus::Error("client exited".into()),
+                ChannelStatus::Reconnecting,
+                ChannelStatus::Connected,
+            ]
+        );
+    }
+
+    #[test]
+    fn restart_delay_backs_off_up_to_max() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(4), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), policy.max_delay);
+    }
+}
@@ -0,0 +1,570 @@
+pub mod rpc;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, mpsc, watch};
+use tracing::{info, warn};
+
+use crate::dedup::{InboundDedup, SeenFn};
+use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
+use opencrust_common::{Error, MediaAttachment, Message, MessageContent, Result};
+use rpc::{Frame, SignalIncoming, SignalTarget};
+
+/// Default address of `signal-cli daemon --tcp`.
+pub const DEFAULT_RPC_ADDR: &str = "127.0.0.1:7583";
+
+/// Longest wait between reconnect attempts to the daemon.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(120);
+
+/// Callback invoked when a Signal message is received.
+///
+/// Arguments: `(source, source_name, text, group_id)`.
+/// * `source` is the sender's phone number, or their ACI when it is hidden.
+/// * `group_id` is `Some` for group messages.
+///
+/// Return `Err("__blocked__")` to silently drop the message.
+pub type SignalOnMessageFn = Arc<
+    dyn Fn(
+            String,
+            String,
+            String,
+            Option<String>,
+        )
+            -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Group filter closure for Signal channels.
+/// Argument: `is_mentioned` — whether the bot's account was @-mentioned.
+/// Returns `true` if the message should be processed.
+pub type SignalGroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
+
+/// Line writer to the daemon. `None` while disconnected.
+type SharedWriter = Arc<Mutex<Option<mpsc::Sender<String>>>>;
+
+/// Signal channel backed by a signal-cli JSON-RPC daemon
+/// (`signal-cli -a <account> daemon --tcp`).
+pub struct SignalChannel {
+    name: String,
+    display: String,
+    account: String,
+    rpc_addr: String,
+    status: ChannelStatus,
+    on_message: SignalOnMessageFn,
+    group_filter: SignalGroupFilter,
+    writer: SharedWriter,
+    next_id: Arc<AtomicU64>,
+    shutdown_tx: Option<watch::Sender<bool>>,
+    dedup: InboundDedup,
+}
+
+impl SignalChannel {
+    /// `account` is the bot's registered phone number; `rpc_addr` is the
+    /// daemon's `host:port`.
+    pub fn new(account: String, rpc_addr: String, on_message: SignalOnMessageFn) -> Self {
+        Self::with_group_filter(account, rpc_addr, on_message, Arc::new(|_| true))
+    }
+
+    pub fn with_group_filter(
+        account: String,
+        rpc_addr: String,
+        on_message: SignalOnMessageFn,
+        group_filter: SignalGroupFilter,
+    ) -> Self {
+        Self {
+            name: "signal".to_string(),
+            display: format!("Signal({account})"),
+            account,
+            rpc_addr,
+            status: ChannelStatus::Disconnected,
+            on_message,
+            group_filter,
+            writer: Arc::new(Mutex::new(None)),
+            next_id: Arc::new(AtomicU64::new(1)),
+            shutdown_tx: None,
+            dedup: InboundDedup::default(),
+        }
+    }
+
+    /// Override the config key name for this channel instance.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Drop messages the daemon delivers again (e.g. after a reconnect)
+    /// using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
+    fn sender(&self) -> SignalSender {
+        SignalSender {
+            name: self.name.clone(),
+            account: self.account.clone(),
+            writer: Arc::clone(&self.writer),
+            next_id: Arc::clone(&self.next_id),
+        }
+    }
+}
+
+// ── SignalSender ──────────────────────────────────────────────────────────────
+
+/// Lightweight send-only handle. Shares the daemon connection with the
+/// channel's read loop.
+pub struct SignalSender {
+    name: String,
+    account: String,
+    writer: SharedWriter,
+    next_id: Arc<AtomicU64>,
+}
+
+impl SignalSender {
+    async fn send(
+        &self,
+        target: &SignalTarget,
+        text: &str,
+        attachment: Option<&MediaAttachment>,
+    ) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = rpc::send_request(id, &self.account, target, text, attachment);
+        let tx = self.writer.lock().await.clone().ok_or_else(|| {
+            Error::Channel(format!("signal channel '{}' is not connected", self.name))
+        })?;
+        tx.send(request)
+            .await
+            .map_err(|_| Error::Channel(format!("signal channel '{}' is not connected", self.name)))
+    }
+}
+
+#[async_trait]
+impl ChannelSender for SignalSender {
+    fn channel_type(&self) -> &str {
+        "signal"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        let (text, attachment) = match &message.content {
+            MessageContent::Text(t) => (t.as_str(), None),
+            MessageContent::Attachment(attachment) => (
+                attachment.caption.as_deref().unwrap_or_default(),
+                Some(attachment),
+            ),
+            _ => {
+                return Err(Error::Channel(
+                    "only text and attachment messages are supported for signal send".into(),
+                ));
+            }
+        };
+
+        let metadata = |key: &str| {
+            message
+                .metadata
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        let target = if let Some(group_id) = metadata("signal_group_id") {
+            SignalTarget::Group(group_id)
+        } else if let Some(recipient) = metadata("signal_recipient") {
+            SignalTarget::User(recipient)
+        } else {
+            return Err(Error::Channel(
+                "missing signal_recipient or signal_group_id in message metadata".into(),
+            ));
+        };
+
+        self.send(&target, text, attachment).await
+    }
+}
+
+// ── ChannelLifecycle ──────────────────────────────────────────────────────────
+
+#[async_trait]
+impl ChannelLifecycle for SignalChannel {
+    fn display_name(&self) -> &str {
+        &self.display
+    }
+
+    fn create_sender(&self) -> Box<dyn ChannelSender> {
+        Box::new(self.sender())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        let stream = TcpStream::connect(&self.rpc_addr).await.map_err(|e| {
+            Error::Channel(format!(
+                "signal-cli daemon at {} is unreachable: {e}",
+                self.rpc_addr
+            ))
+        })?;
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        self.shutdown_tx = Some(shutdown_tx);
+
+        let conn = Connection {
+            rpc_addr: self.rpc_addr.clone(),
+            sender: Arc::new(self.sender()),
+            on_message: Arc::clone(&self.on_message),
+            group_filter: Arc::clone(&self.group_filter),
+            dedup: self.dedup.clone(),
+        };
+        tokio::spawn(run_signal_loop(conn, stream, shutdown_rx));
+
+        self.status = ChannelStatus::Connected;
+        info!(
+            "signal channel '{}' connected to signal-cli at {}",
+            self.name, self.rpc_addr
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(true);
+        }
+        self.status = ChannelStatus::Disconnected;
+        info!("signal channel '{}' disconnected", self.name);
+        Ok(())
+    }
+
+    fn status(&self) -> ChannelStatus {
+        self.status.clone()
+    }
+}
+
+#[async_trait]
+impl ChannelSender for SignalChannel {
+    fn channel_type(&self) -> &str {
+        "signal"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        self.sender().send_message(message).await
+    }
+}
+
+// ── Read loop ─────────────────────────────────────────────────────────────────
+
+struct Connection {
+    rpc_addr: String,
+    sender: Arc<SignalSender>,
+    on_message: SignalOnMessageFn,
+    group_filter: SignalGroupFilter,
+    dedup: InboundDedup,
+}
+
+/// Serve `stream` until it drops, then reconnect with exponential backoff
+/// until shutdown is requested.
+async fn run_signal_loop(
+    conn: Connection,
+    stream: TcpStream,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let name = conn.sender.name.clone();
+    let mut stream = Some(stream);
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        if *shutdown_rx.borrow() {
+            return;
+        }
+
+        let current = match stream.take() {
+            Some(s) => s,
+            None => match TcpStream::connect(&conn.rpc_addr).await {
+                Ok(s) => {
+                    info!("signal '{name}': reconnected to signal-cli");
+                    s
+                }
+                Err(e) => {
+                    warn!("signal '{name}': reconnect failed: {e}, retrying in {delay:?}");
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = shutdown_rx.changed() => {}
+                    }
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    continue;
+                }
+            },
+        };
+        delay = Duration::from_secs(1);
+
+        if !serve_connection(&conn, current, &mut shutdown_rx).await {
+            return;
+        }
+        warn!("signal '{name}': connection to signal-cli lost, reconnecting...");
+    }
+}
+
+/// Returns `true` if the connection dropped (should reconnect), `false` if a
+/// shutdown was requested.
+async fn serve_connection(
+    conn: &Connection,
+    stream: TcpStream,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) -> bool {
+    let (read_half, mut write_half) = stream.into_split();
+    let (tx, mut rx) = mpsc::channel::<String>(64);
+    *conn.sender.writer.lock().await = Some(tx);
+
+    let mut lines = BufReader::new(read_half).lines();
+    let reconnect = loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => handle_line(conn, &line),
+                Ok(None) => break true,
+                Err(e) => {
+                    warn!("signal '{}': read error: {e}", conn.sender.name);
+                    break true;
+                }
+            },
+            Some(request) = rx.recv() => {
+                let frame = format!("{request}\n");
+                if let Err(e) = write_half.write_all(frame.as_bytes()).await {
+                    warn!("signal '{}': write error: {e}", conn.sender.name);
+                    break true;
+                }
+            }
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    break false;
+                }
+            }
+        }
+    };
+
+    *conn.sender.writer.lock().await = None;
+    reconnect
+}
+
+/// Dispatch one line from the daemon.
+fn handle_line(conn: &Connection, line: &str) {
+    match rpc::parse_frame(line, &conn.sender.account) {
+        Frame::Message(msg) => dispatch_message(conn, msg),
+        Frame::Error { id, message } => {
+            warn!(
+                "signal '{}': request {} failed: {message}",
+                conn.sender.name,
+                id.map(|id| id.to_string()).unwrap_or_else(|| "?".into())
+            );
+        }
+        Frame::Other => {}
+    }
+}
+
+fn dispatch_message(conn: &Connection, msg: SignalIncoming) {
+    if msg.group_id.is_some() && !(conn.group_filter)(msg.mentioned) {
+        return;
+    }
+    if msg.text.trim().is_empty() {
+        return;
+    }
+    if msg.timestamp != 0
+        && !conn
+            .dedup
+            .first_time(&format!("{}:{}", msg.source, msg.timestamp))
+    {
+        return;
+    }
+
+    let target = match &msg.group_id {
+        Some(group_id) => SignalTarget::Group(group_id.clone()),
+        None => SignalTarget::User(msg.source.clone()),
+    };
+    let on_message = Arc::clone(&conn.on_message);
+    let sender = Arc::clone(&conn.sender);
+
+    // Spawn so the read loop is not blocked during processing.
+    tokio::spawn(async move {
+        match on_message(msg.source, msg.source_name, msg.text, msg.group_id).await {
+            Ok(response) => {
+                let reply = response.text();
+                if !reply.is_empty()
+                    && let Err(e) = sender.send(&target, reply, None).await
+                {
+                    warn!("signal '{}': reply failed: {e}", sender.name);
+                }
+            }
+            Err(e) if e == "__blocked__" => {}
+            Err(e) => warn!("signal '{}': message handler error: {e}", sender.name),
+        }
+    });
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const BOT: &str = "+15550000000";
+
+    fn echo_callback() -> SignalOnMessageFn {
+        Arc::new(|source, _name, text, group_id| {
+            Box::pin(async move {
+                Ok(ChannelResponse::Text(format!(
+                    "{source}/{}: {text}",
+                    group_id.unwrap_or_default()
+                )))
+            })
+        })
+    }
+
+    /// Accept one connection and return its line reader and writer.
+    async fn accept(
+        listener: &TcpListener,
+    ) -> (
+        tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+        tokio::net::tcp::OwnedWriteHalf,
+    ) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, write) = stream.into_split();
+        (BufReader::new(read).lines(), write)
+    }
+
+    async fn next_request(
+        lines: &mut tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    ) -> serde_json::Value {
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .expect("timed out waiting for a request")
+            .unwrap()
+            .expect("connection closed");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    fn receive_line(envelope: serde_json::Value) -> String {
+        format!(
+            "{}\n",
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "receive",
+                "params": { "account": BOT, "envelope": envelope },
+            })
+        )
+    }
+
+    #[test]
+    fn initial_status_and_names() {
+        let ch = SignalChannel::new(BOT.into(), DEFAULT_RPC_ADDR.into(), echo_callback())
+            .with_name("signal-home".into());
+        assert!(matches!(ch.status(), ChannelStatus::Disconnected));
+        assert!(ch.display_name().contains(BOT));
+        let sender = ch.create_sender();
+        assert_eq!(sender.channel_type(), "signal");
+        assert_eq!(sender.channel_name(), "signal-home");
+    }
+
+    #[tokio::test]
+    async fn connect_fails_when_daemon_is_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut ch = SignalChannel::new(BOT.into(), addr, echo_callback());
+        let err = ch.connect().await.unwrap_err();
+        assert!(err.to_string().contains("unreachable"));
+    }
+
+    #[tokio::test]
+    async fn send_without_connection_fails() {
+        let ch = SignalChannel::new(BOT.into(), DEFAULT_RPC_ADDR.into(), echo_callback());
+        let mut msg = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("signal"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "hi",
+        );
+        msg.metadata = serde_json::json!({ "signal_recipient": "+15551234567" });
+        assert!(ch.send_message(&msg).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn replies_to_direct_and_group_messages_and_sends_outbound() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let mentions_only: SignalGroupFilter = Arc::new(|mentioned| mentioned);
+        let mut ch =
+            SignalChannel::with_group_filter(BOT.into(), addr, echo_callback(), mentions_only);
+        ch.connect().await.unwrap();
+        let (mut lines, mut daemon) = accept(&listener).await;
+
+        // Direct message → reply to the sender.
+        daemon
+            .write_all(
+                receive_line(serde_json::json!({
+                    "sourceNumber": "+15551234567",
+                    "dataMessage": { "message": "ping" },
+                }))
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let reply = next_request(&mut lines).await;
+        assert_eq!(reply["method"], "send");
+        assert_eq!(reply["params"]["recipient"][0], "+15551234567");
+        assert_eq!(reply["params"]["message"], "+15551234567/: ping");
+
+        // Unmentioned group message is filtered out; mentioned one is answered
+        // in the group.
+        for mentions in [
+            serde_json::json!([]),
+            serde_json::json!([{ "number": BOT }]),
+        ] {
+            daemon
+                .write_all(
+                    receive_line(serde_json::json!({
+                        "sourceNumber": "+15557654321",
+                        "dataMessage": {
+                            "message": "hey bot",
+                            "groupInfo": { "groupId": "R3JvdXA=" },
+                            "mentions": mentions,
+                        },
+                    }))
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+        let reply = next_request(&mut lines).await;
+        assert_eq!(reply["params"]["groupId"], "R3JvdXA=");
+        assert_eq!(reply["params"]["message"], "+15557654321/R3JvdXA=: hey bot");
+
+        // Proactive send through the sender handle.
+        let sender = ch.create_sender();
+        let mut msg = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("signal"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "reminder",
+        );
+        msg.metadata = serde_json::json!({ "signal_recipient": "+15551234567" });
+        sender.send_message(&msg).await.unwrap();
+        let request = next_request(&mut lines).await;
+        assert_eq!(request["params"]["message"], "reminder");
+        assert_eq!(request["params"]["account"], BOT);
+
+        ch.disconnect().await.unwrap();
+    }
+}
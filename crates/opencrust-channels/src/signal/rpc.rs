@@ -0,0 +1,331 @@
+//! signal-cli JSON-RPC framing.
+//!
+//! `signal-cli daemon --tcp` exchanges one JSON-RPC object per line. Incoming
+//! messages arrive as `receive` notifications; replies are `send` requests.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use opencrust_common::MediaAttachment;
+use serde::Deserialize;
+
+/// A text message received over Signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalIncoming {
+    /// Sender phone number, or their ACI (UUID) when the number is hidden.
+    pub source: String,
+    /// Sender profile name; may be empty.
+    pub source_name: String,
+    pub text: String,
+    /// Base64 group ID for group messages.
+    pub group_id: Option<String>,
+    /// Whether the message @-mentions the bot's account.
+    pub mentioned: bool,
+    /// Sender-assigned timestamp (ms); with `source` it identifies the message.
+    pub timestamp: u64,
+}
+
+/// Where a `send` request is delivered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignalTarget {
+    /// A phone number or ACI.
+    User(String),
+    /// A base64 group ID.
+    Group(String),
+}
+
+/// One decoded line from the daemon.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame {
+    Message(SignalIncoming),
+    /// Error response to one of our requests.
+    Error {
+        id: Option<u64>,
+        message: String,
+    },
+    /// Receipts, typing indicators, sync messages, successful responses.
+    Other,
+}
+
+#[derive(Deserialize)]
+struct RawFrame {
+    method: Option<String>,
+    params: Option<serde_json::Value>,
+    id: Option<u64>,
+    error: Option<RawError>,
+}
+
+#[derive(Deserialize)]
+struct RawError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ReceiveParams {
+    account: Option<String>,
+    envelope: Envelope,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    source: Option<String>,
+    source_number: Option<String>,
+    source_uuid: Option<String>,
+    source_name: Option<String>,
+    data_message: Option<DataMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataMessage {
+    #[serde(default)]
+    timestamp: u64,
+    message: Option<String>,
+    group_info: Option<GroupInfo>,
+    #[serde(default)]
+    mentions: Vec<Mention>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupInfo {
+    group_id: String,
+}
+
+#[derive(Deserialize)]
+struct Mention {
+    number: Option<String>,
+}
+
+/// Decode one line. Messages addressed to an account other than `account`
+/// (multi-account daemons) are ignored.
+pub fn parse_frame(line: &str, account: &str) -> Frame {
+    let Ok(raw) = serde_json::from_str::<RawFrame>(line) else {
+        return Frame::Other;
+    };
+
+    if let Some(error) = raw.error {
+        return Frame::Error {
+            id: raw.id,
+            message: error.message,
+        };
+    }
+
+    if raw.method.as_deref() != Some("receive") {
+        return Frame::Other;
+    }
+    let Some(params) = raw
+        .params
+        .and_then(|p| serde_json::from_value::<ReceiveParams>(p).ok())
+    else {
+        return Frame::Other;
+    };
+    if params.account.as_deref().is_some_and(|a| a != account) {
+        return Frame::Other;
+    }
+
+    let envelope = params.envelope;
+    let Some(data) = envelope.data_message else {
+        return Frame::Other;
+    };
+    let Some(text) = data.message else {
+        return Frame::Other;
+    };
+    let Some(source) = envelope
+        .source_number
+        .or(envelope.source_uuid)
+        .or(envelope.source)
+    else {
+        return Frame::Other;
+    };
+
+    Frame::Message(SignalIncoming {
+        source,
+        source_name: envelope.source_name.unwrap_or_default(),
+        text,
+        group_id: data.group_info.map(|g| g.group_id),
+        mentioned: data
+            .mentions
+            .iter()
+            .any(|m| m.number.as_deref() == Some(account)),
+        timestamp: data.timestamp,
+    })
+}
+
+/// Encode a `send` request for `text` to `target`. An `attachment` is passed
+/// as a `data:` URI, which signal-cli accepts in place of a file path.
+pub fn send_request(
+    id: u64,
+    account: &str,
+    target: &SignalTarget,
+    text: &str,
+    attachment: Option<&MediaAttachment>,
+) -> String {
+    let mut params = serde_json::json!({
+        "account": account,
+        "message": text,
+    });
+    if let Some(attachment) = attachment {
+        let mime = attachment
+            .mime_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        params["attachments"] = serde_json::json!([format!(
+            "data:{mime};filename={};base64,{}",
+            attachment.filename_or_default(),
+            STANDARD.encode(&attachment.data)
+        )]);
+    }
+    match target {
+        SignalTarget::User(recipient) => params["recipient"] = serde_json::json!([recipient]),
+        SignalTarget::Group(group_id) => params["groupId"] = serde_json::json!(group_id),
+    }
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "send",
+        "params": params,
+        "id": id,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: &str = "+15550000000";
+
+    fn receive(envelope: serde_json::Value) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "receive",
+            "params": { "account": BOT, "envelope": envelope },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_direct_message() {
+        let line = receive(serde_json::json!({
+            "source": "+15551234567",
+            "sourceNumber": "+15551234567",
+            "sourceUuid": "a1b2",
+            "sourceName": "Alice",
+            "timestamp": 1,
+            "dataMessage": { "timestamp": 1, "message": "hello" },
+        }));
+        assert_eq!(
+            parse_frame(&line, BOT),
+            Frame::Message(SignalIncoming {
+                source: "+15551234567".into(),
+                source_name: "Alice".into(),
+                text: "hello".into(),
+                group_id: None,
+                mentioned: false,
+                timestamp: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_group_message_with_mention() {
+        let line = receive(serde_json::json!({
+            "sourceUuid": "a1b2",
+            "dataMessage": {
+                "message": "\u{fffc} what's up",
+                "groupInfo": { "groupId": "R3JvdXA=", "type": "DELIVER" },
+                "mentions": [{ "number": BOT, "start": 0, "length": 1 }],
+            },
+        }));
+        let Frame::Message(msg) = parse_frame(&line, BOT) else {
+            panic!("expected a message");
+        };
+        assert_eq!(msg.source, "a1b2");
+        assert_eq!(msg.group_id.as_deref(), Some("R3JvdXA="));
+        assert!(msg.mentioned);
+    }
+
+    #[test]
+    fn ignores_receipts_other_accounts_and_garbage() {
+        let receipt = receive(serde_json::json!({
+            "sourceNumber": "+15551234567",
+            "receiptMessage": { "isDelivery": true },
+        }));
+        assert_eq!(parse_frame(&receipt, BOT), Frame::Other);
+
+        let line = receive(serde_json::json!({
+            "sourceNumber": "+15551234567",
+            "dataMessage": { "message": "hi" },
+        }));
+        assert_eq!(parse_frame(&line, "+15559999999"), Frame::Other);
+
+        assert_eq!(parse_frame("not json", BOT), Frame::Other);
+        assert_eq!(
+            parse_frame(r#"{"jsonrpc":"2.0","result":{"timestamp":1},"id":3}"#, BOT),
+            Frame::Other
+        );
+    }
+
+    #[test]
+    fn parses_error_response() {
+        let line = r#"{"jsonrpc":"2.0","error":{"code":-1,"message":"Unregistered user"},"id":7}"#;
+        assert_eq!(
+            parse_frame(line, BOT),
+            Frame::Error {
+                id: Some(7),
+                message: "Unregistered user".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn send_request_targets_user_or_group() {
+        let user: serde_json::Value = serde_json::from_str(&send_request(
+            1,
+            BOT,
+            &SignalTarget::User("+15551234567".into()),
+            "hi",
+            None,
+        ))
+        .unwrap();
+        assert_eq!(user["method"], "send");
+        assert_eq!(user["id"], 1);
+        assert_eq!(user["params"]["account"], BOT);
+        assert_eq!(user["params"]["recipient"][0], "+15551234567");
+        assert_eq!(user["params"]["message"], "hi");
+        assert!(user["params"].get("groupId").is_none());
+
+        let group: serde_json::Value = serde_json::from_str(&send_request(
+            2,
+            BOT,
+            &SignalTarget::Group("R3JvdXA=".into()),
+            "hey all",
+            None,
+        ))
+        .unwrap();
+        assert_eq!(group["params"]["groupId"], "R3JvdXA=");
+        assert!(group["params"].get("recipient").is_none());
+        assert!(group["params"].get("attachments").is_none());
+    }
+
+    #[test]
+    fn send_request_inlines_attachment() {
+        let attachment =
+            MediaAttachment::new(opencrust_common::MediaKind::Document, b"pdf".to_vec())
+                .with_filename("report.pdf")
+                .with_mime_type(Some("application/pdf".into()));
+        let request: serde_json::Value = serde_json::from_str(&send_request(
+            3,
+            BOT,
+            &SignalTarget::User("+15551234567".into()),
+            "Q3",
+            Some(&attachment),
+        ))
+        .unwrap();
+        assert_eq!(request["params"]["message"], "Q3");
+        assert_eq!(
+            request["params"]["attachments"],
+            serde_json::json!(["data:application/pdf;filename=report.pdf;base64,cGRm"])
+        );
+    }
+}
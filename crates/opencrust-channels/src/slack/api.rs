@@ -2,6 +2,8 @@ use reqwest::Client;
 use serde::Deserialize;
 use tracing::warn;
 
+use super::fmt::BlockMessage;
+
 const SLACK_API_BASE: &str = "https://slack.com/api";
 
 /// Maximum file size accepted for document ingestion (10 MiB).
@@ -89,10 +91,35 @@ pub async fn post_message(
     text: &str,
     thread_ts: Option<&str>,
 ) -> Result<String, String> {
-    let mut body = serde_json::json!({
+    let body = serde_json::json!({
         "channel": channel,
         "text": text,
     });
+    chat_post_message(client, bot_token, body, thread_ts).await
+}
+
+/// Post a Block Kit message. `message.text` is the notification fallback.
+pub async fn post_blocks(
+    client: &Client,
+    bot_token: &str,
+    channel: &str,
+    message: &BlockMessage,
+    thread_ts: Option<&str>,
+) -> Result<String, String> {
+    let body = serde_json::json!({
+        "channel": channel,
+        "text": message.text,
+        "blocks": message.blocks,
+    });
+    chat_post_message(client, bot_token, body, thread_ts).await
+}
+
+async fn chat_post_message(
+    client: &Client,
+    bot_token: &str,
+    mut body: serde_json::Value,
+    thread_ts: Option<&str>,
+) -> Result<String, String> {
     if let Some(ts) = thread_ts {
         body["thread_ts"] = serde_json::Value::String(ts.to_string());
     }
@@ -118,11 +145,179 @@ pub async fn post_message(
         .ok_or_else(|| "chat.postMessage: no ts in response".to_string())
 }
 
+/// Reply to a slash command through its `response_url`. Ephemeral replies are
+/// shown only to the user who ran the command; the rest are posted in the
+/// channel, even one the bot has not joined.
+pub async fn respond(
+    client: &Client,
+    response_url: &str,
+    text: &str,
+    ephemeral: bool,
+) -> Result<(), String> {
+    let body = serde_json::json!({
+        "response_type": if ephemeral { "ephemeral" } else { "in_channel" },
+        "text": text,
+    });
+    let resp = client
+        .post(response_url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("slash command response failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!(
+            "slash command response error: HTTP {}",
+            resp.status()
+        ));
+    }
+    Ok(())
+}
+
+/// Show a status line such as "is typing..." under a thread via
+/// `assistant.threads.setStatus`. Slack clears it when the app replies in the
+/// thread. Requires the `assistant:write` scope.
+pub async fn set_thread_status(
+    client: &Client,
+    bot_token: &str,
+    channel: &str,
+    thread_ts: &str,
+    status: &str,
+) -> Result<(), String> {
+    let body = serde_json::json!({
+        "channel_id": channel,
+        "thread_ts": thread_ts,
+        "status": status,
+    });
+    let resp = client
+        .post(format!("{SLACK_API_BASE}/assistant.threads.setStatus"))
+        .bearer_auth(bot_token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("assistant.threads.setStatus request failed: {e}"))?;
+
+    let body: SlackApiResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("assistant.threads.setStatus parse failed: {e}"))?;
+
+    if !body.ok {
+        let err = body.error.unwrap_or_else(|| "unknown".to_string());
+        return Err(format!("assistant.threads.setStatus error: {err}"));
+    }
+    Ok(())
+}
+
+/// Add the emoji `name` (without colons) as a reaction to the message `ts`
+/// via `reactions.add`. Requires the `reactions:write` scope.
+pub async fn add_reaction(
+    client: &Client,
+    bot_token: &str,
+    channel: &str,
+    ts: &str,
+    name: &str,
+) -> Result<(), String> {
+    let body = serde_json::json!({
+        "channel": channel,
+        "timestamp": ts,
+        "name": name,
+    });
+    let resp = client
+        .post(format!("{SLACK_API_BASE}/reactions.add"))
+        .bearer_auth(bot_token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("reactions.add request failed: {e}"))?;
+
+    let body: SlackApiResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("reactions.add parse failed: {e}"))?;
+
+    // Reacting twice (e.g. to a redelivered event) is not an error.
+    match body.error.as_deref() {
+        _ if body.ok => Ok(()),
+        Some("already_reacted") => Ok(()),
+        error => Err(format!(
+            "reactions.add error: {}",
+            error.unwrap_or("unknown")
+        )),
+    }
+}
+
+/// Upload a file and share it in `channel` (and `thread_ts`, if set) with an
+/// optional comment. Uses the external upload flow: reserve an upload URL,
+/// POST the bytes to it, then complete the upload. Requires `files:write`.
+pub async fn upload_file(
+    client: &Client,
+    bot_token: &str,
+    channel: &str,
+    filename: &str,
+    data: &[u8],
+    comment: Option<&str>,
+    thread_ts: Option<&str>,
+) -> Result<(), String> {
+    let length = data.len().to_string();
+    let reserved: serde_json::Value = client
+        .post(format!("{SLACK_API_BASE}/files.getUploadURLExternal"))
+        .bearer_auth(bot_token)
+        .form(&[("filename", filename), ("length", length.as_str())])
+        .send()
+        .await
+        .map_err(|e| format!("files.getUploadURLExternal request failed: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("files.getUploadURLExternal parse failed: {e}"))?;
+    let field = |key: &str| reserved.get(key).and_then(|v| v.as_str());
+    let (Some(upload_url), Some(file_id)) = (field("upload_url"), field("file_id")) else {
+        let err = field("error").unwrap_or("unknown");
+        return Err(format!("files.getUploadURLExternal error: {err}"));
+    };
+
+    let resp = client
+        .post(upload_url)
+        .body(data.to_vec())
+        .send()
+        .await
+        .map_err(|e| format!("file upload failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("file upload failed: HTTP {}", resp.status()));
+    }
+
+    let mut body = serde_json::json!({
+        "files": [{"id": file_id, "title": filename}],
+        "channel_id": channel,
+    });
+    if let Some(comment) = comment {
+        body["initial_comment"] = serde_json::Value::String(comment.to_string());
+    }
+    if let Some(ts) = thread_ts {
+        body["thread_ts"] = serde_json::Value::String(ts.to_string());
+    }
+    let completed: SlackApiResponse = client
+        .post(format!("{SLACK_API_BASE}/files.completeUploadExternal"))
+        .bearer_auth(bot_token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("files.completeUploadExternal request failed: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("files.completeUploadExternal parse failed: {e}"))?;
+    if !completed.ok {
+        let err = completed.error.unwrap_or_else(|| "unknown".to_string());
+        return Err(format!("files.completeUploadExternal error: {err}"));
+    }
+    Ok(())
+}
+
 /// Download a private Slack file using the bot token for authorization.
 ///
 /// Slack files require `Authorization: Bearer <bot_token>` — they cannot be
 /// fetched without credentials. Returns the raw file bytes.
-/// Rejects files larger than [`SLACK_MAX_FILE_BYTES`] based on `Content-Length`.
+/// Rejects files larger than [`SLACK_MAX_FILE_BYTES`], aborting the download as
+/// soon as that many bytes have arrived.
 pub async fn download_file(client: &Client, bot_token: &str, url: &str) -> Result<Vec<u8>, String> {
     let resp = client
         .get(url)
@@ -138,27 +333,9 @@ pub async fn download_file(client: &Client, bot_token: &str, url: &str) -> Resul
         ));
     }
 
-    if let Some(len) = resp.content_length()
-        && len > SLACK_MAX_FILE_BYTES as u64
-    {
-        return Err(format!(
-            "file too large: {len} bytes exceeds {SLACK_MAX_FILE_BYTES} byte limit"
-        ));
-    }
-
-    let bytes = resp
-        .bytes()
+    crate::download::read_limited(resp, SLACK_MAX_FILE_BYTES)
         .await
-        .map_err(|e| format!("slack file read failed: {e}"))?;
-
-    if bytes.len() > SLACK_MAX_FILE_BYTES {
-        return Err(format!(
-            "file too large: {} bytes exceeds {SLACK_MAX_FILE_BYTES} byte limit",
-            bytes.len()
-        ));
-    }
-
-    Ok(bytes.to_vec())
+        .map_err(|e| format!("slack {e}"))
 }
 
 /// Look up a Slack user's display name via `users.info`.
@@ -231,15 +408,41 @@ pub async fn update_message(
     channel: &str,
     ts: &str,
     text: &str,
+) -> Result<(), String> {
+    let body = serde_json::json!({
+        "channel": channel,
+        "ts": ts,
+        "text": text,
+    });
+    chat_update(client, bot_token, body).await
+}
+
+/// Replace an existing message with Block Kit content.
+pub async fn update_blocks(
+    client: &Client,
+    bot_token: &str,
+    channel: &str,
+    ts: &str,
+    message: &BlockMessage,
+) -> Result<(), String> {
+    let body = serde_json::json!({
+        "channel": channel,
+        "ts": ts,
+        "text": message.text,
+        "blocks": message.blocks,
+    });
+    chat_update(client, bot_token, body).await
+}
+
+async fn chat_update(
+    client: &Client,
+    bot_token: &str,
+    body: serde_json::Value,
 ) -> Result<(), String> {
     let resp = client
         .post(format!("{SLACK_API_BASE}/chat.update"))
         .bearer_auth(bot_token)
-        .json(&serde_json::json!({
-            "channel": channel,
-            "ts": ts,
-            "text": text,
-        }))
+        .json(&body)
         .send()
         .await
         .map_err(|e| format!("chat.update request failed: {e}"))?;
@@ -1,79 +1,261 @@
-/// Convert standard markdown to Slack mrkdwn format.
+use crate::format::FormatProfile;
+
+/// Slack mrkdwn formatting profile.
 ///
 /// - `**bold**` → `*bold*`  (Slack uses single asterisks)
+/// - `[text](url)` → `<url|text>`
 /// - `` `code` `` and ` ```blocks``` ` pass through unchanged
-/// - Escapes `&`, `<`, `>` outside code blocks
+pub struct SlackFormat;
+
+impl FormatProfile for SlackFormat {
+    fn text(&self, text: &str, out: &mut String) {
+        // Slack's chat.postMessage with a JSON body handles &, <, > encoding,
+        // so plain text passes through as-is.
+        out.push_str(text);
+    }
+
+    fn bold(&self, text: &str, out: &mut String) {
+        out.push('*');
+        out.push_str(text);
+        out.push('*');
+    }
+
+    fn link(&self, text: &str, url: &str, out: &mut String) {
+        out.push_str(&format!("<{url}|{text}>"));
+    }
+}
+
+/// Convert standard markdown to Slack mrkdwn format.
 pub fn to_slack_mrkdwn(input: &str) -> String {
-    let mut result = String::with_capacity(input.len());
-    let chars: Vec<char> = input.chars().collect();
-    let len = chars.len();
-    let mut i = 0;
-
-    while i < len {
-        // Fenced code block: ```...```
-        if i + 2 < len && chars[i] == '`' && chars[i + 1] == '`' && chars[i + 2] == '`' {
-            result.push_str("```");
-            i += 3;
-            // Copy everything until closing ```
-            loop {
-                if i + 2 < len && chars[i] == '`' && chars[i + 1] == '`' && chars[i + 2] == '`' {
-                    result.push_str("```");
-                    i += 3;
+    SlackFormat.render(input)
+}
+
+// ── Block Kit ────────────────────────────────────────────────────────────────
+
+/// Most blocks Slack accepts in one message.
+pub const MAX_BLOCKS: usize = 50;
+
+/// Most characters Slack accepts in a section or context text.
+const MAX_SECTION_CHARS: usize = 3000;
+
+/// Most characters Slack accepts in a header block.
+const MAX_HEADER_CHARS: usize = 150;
+
+/// Context blocks of reasoning shown under a reply before it is cut.
+const MAX_REASONING_BLOCKS: usize = 3;
+
+/// Notification/fallback text is cut to this many characters.
+const MAX_FALLBACK_CHARS: usize = 3000;
+
+/// One Slack message rendered with Block Kit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMessage {
+    /// Plain mrkdwn shown in notifications and by clients without Block Kit.
+    pub text: String,
+    /// At most [`MAX_BLOCKS`] blocks.
+    pub blocks: Vec<serde_json::Value>,
+}
+
+/// Whether `input` reads better as Block Kit than as one mrkdwn message: it
+/// contains code blocks, headings or rules, or is too long for one section.
+pub fn needs_blocks(input: &str) -> bool {
+    input.chars().count() > MAX_SECTION_CHARS
+        || input.lines().any(|line| {
+            let line = line.trim();
+            line.starts_with("```") || is_rule(line) || heading(line).is_some()
+        })
+}
+
+/// Render markdown as Block Kit:
+///
+/// - fenced code blocks → their own section
+/// - `#` headings → header blocks
+/// - `---` rules → dividers
+/// - `>` quotes → context blocks
+/// - paragraphs → sections, split at Slack's size limit
+///
+/// More than [`MAX_BLOCKS`] blocks are split across several messages.
+pub fn to_slack_blocks(input: &str) -> Vec<BlockMessage> {
+    let mut blocks = Vec::new();
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut lines = input.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if !trimmed.starts_with('>') && !quote.is_empty() {
+            push_context(&mut blocks, &quote.join("\n"));
+            quote.clear();
+        }
+
+        if trimmed.starts_with("```") {
+            flush_paragraphs(&mut blocks, &mut paragraphs);
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
                     break;
                 }
-                if i >= len {
-                    break;
+                code.push(line);
+            }
+            push_code(&mut blocks, &code.join("\n"));
+        } else if is_rule(trimmed) {
+            flush_paragraphs(&mut blocks, &mut paragraphs);
+            blocks.push(serde_json::json!({ "type": "divider" }));
+        } else if let Some(title) = heading(trimmed) {
+            flush_paragraphs(&mut blocks, &mut paragraphs);
+            blocks.push(serde_json::json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": truncate(&title.replace("**", ""), MAX_HEADER_CHARS) },
+            }));
+        } else if let Some(rest) = trimmed.strip_prefix('>') {
+            flush_paragraphs(&mut blocks, &mut paragraphs);
+            quote.push(rest.trim_start());
+        } else if trimmed.is_empty() {
+            paragraphs.push(String::new());
+        } else {
+            match paragraphs.last_mut() {
+                Some(last) if !last.is_empty() => {
+                    last.push('\n');
+                    last.push_str(line);
                 }
-                result.push(chars[i]);
-                i += 1;
+                _ => paragraphs.push(line.to_string()),
             }
-            continue;
         }
+    }
+    if !quote.is_empty() {
+        push_context(&mut blocks, &quote.join("\n"));
+    }
+    flush_paragraphs(&mut blocks, &mut paragraphs);
 
-        // Inline code: `...`
-        if chars[i] == '`' {
-            result.push('`');
-            i += 1;
-            while i < len && chars[i] != '`' {
-                result.push(chars[i]);
-                i += 1;
-            }
-            if i < len {
-                result.push('`');
-                i += 1;
-            }
-            continue;
-        }
+    blocks
+        .chunks(MAX_BLOCKS)
+        .map(|chunk| BlockMessage {
+            text: fallback_text(chunk),
+            blocks: chunk.to_vec(),
+        })
+        .collect()
+}
 
-        // Bold: **text** → *text*
-        if i + 1 < len && chars[i] == '*' && chars[i + 1] == '*' {
-            result.push('*');
-            i += 2;
-            while i < len && !(i + 1 < len && chars[i] == '*' && chars[i + 1] == '*') {
-                result.push(escape_char(chars[i]));
-                i += 1;
-            }
-            result.push('*');
-            if i + 1 < len {
-                i += 2; // skip closing **
-            }
-            continue;
+/// Render model reasoning as small grey context blocks, headed "Reasoning".
+/// Long reasoning is cut to a few blocks.
+pub fn reasoning_blocks(reasoning: &str) -> BlockMessage {
+    let mut blocks = vec![serde_json::json!({
+        "type": "context",
+        "elements": [{ "type": "mrkdwn", "text": ":thought_balloon: *Reasoning*" }],
+    })];
+    push_context(
+        &mut blocks,
+        &truncate(reasoning.trim(), MAX_REASONING_BLOCKS * MAX_SECTION_CHARS),
+    );
+    BlockMessage {
+        text: "Reasoning".to_string(),
+        blocks,
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    line.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| line.chars().all(|ch| ch == c))
+}
+
+fn heading(line: &str) -> Option<&str> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    line[hashes..]
+        .strip_prefix(' ')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// Merge paragraphs into as few sections as the size limit allows.
+fn flush_paragraphs(blocks: &mut Vec<serde_json::Value>, paragraphs: &mut Vec<String>) {
+    let mut section = String::new();
+    for paragraph in paragraphs.drain(..).filter(|p| !p.is_empty()) {
+        let paragraph = to_slack_mrkdwn(&paragraph);
+        let joined = section.chars().count() + paragraph.chars().count() + 2;
+        if !section.is_empty() && joined > MAX_SECTION_CHARS {
+            push_section(blocks, &section);
+            section.clear();
         }
+        if !section.is_empty() {
+            section.push_str("\n\n");
+        }
+        section.push_str(&paragraph);
+    }
+    if !section.is_empty() {
+        push_section(blocks, &section);
+    }
+}
 
-        // Escape & < > in plain text
-        result.push(escape_char(chars[i]));
-        i += 1;
+fn push_section(blocks: &mut Vec<serde_json::Value>, mrkdwn: &str) {
+    for chunk in split_text(mrkdwn, MAX_SECTION_CHARS) {
+        blocks.push(serde_json::json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": chunk },
+        }));
     }
+}
 
-    result
+fn push_code(blocks: &mut Vec<serde_json::Value>, code: &str) {
+    // Leave room for the fences around each chunk.
+    for chunk in split_text(code, MAX_SECTION_CHARS - 8) {
+        push_section(blocks, &format!("```\n{chunk}\n```"));
+    }
 }
 
-fn escape_char(c: char) -> char {
-    // Slack mrkdwn doesn't use HTML entities in the same way, but the Web API
-    // requires &, <, > to be escaped when sending via JSON payload.
-    // However, Slack's chat.postMessage with JSON body handles this automatically.
-    // We just pass through as-is — the API handles encoding.
-    c
+fn push_context(blocks: &mut Vec<serde_json::Value>, text: &str) {
+    for chunk in split_text(&to_slack_mrkdwn(text), MAX_SECTION_CHARS) {
+        blocks.push(serde_json::json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": chunk }],
+        }));
+    }
+}
+
+/// Split `text` into pieces of at most `max` characters, preferring line breaks.
+fn split_text(text: &str, max: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max {
+        let limit = rest.char_indices().nth(max).map_or(rest.len(), |(i, _)| i);
+        let cut = match rest[..limit].rfind('\n') {
+            Some(i) if i > 0 => i,
+            _ => limit,
+        };
+        chunks.push(&rest[..cut]);
+        rest = rest[cut..].strip_prefix('\n').unwrap_or(&rest[cut..]);
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Notification text for a message: the blocks' text joined, cut to size.
+fn fallback_text(blocks: &[serde_json::Value]) -> String {
+    let text = blocks
+        .iter()
+        .filter_map(|block| {
+            block["text"]["text"]
+                .as_str()
+                .or_else(|| block["elements"][0]["text"].as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    truncate(&text, MAX_FALLBACK_CHARS)
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max - 1).collect();
+    out.push('…');
+    out
 }
 
 #[cfg(test)]
@@ -111,4 +293,97 @@ mod tests {
         let output = to_slack_mrkdwn(input);
         assert_eq!(output, "Try `code` and *bold* together.");
     }
+
+    #[test]
+    fn link_converted() {
+        assert_eq!(
+            to_slack_mrkdwn("see [docs](https://example.com)"),
+            "see <https://example.com|docs>"
+        );
+    }
+
+    #[test]
+    fn short_plain_text_does_not_need_blocks() {
+        assert!(!needs_blocks("hello **world**"));
+        assert!(needs_blocks("run:\n```\nls\n```"));
+        assert!(needs_blocks("# Title\nbody"));
+        assert!(needs_blocks(&"a".repeat(MAX_SECTION_CHARS + 1)));
+    }
+
+    #[test]
+    fn blocks_from_markdown_structure() {
+        let input = "# Result\n\nAll **good**.\n\n```rust\nfn main() {}\n```\n---\n> exit code 0";
+        let messages = to_slack_blocks(input);
+        assert_eq!(messages.len(), 1);
+        let blocks = &messages[0].blocks;
+        let types: Vec<_> = blocks.iter().map(|b| b["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            types,
+            ["header", "section", "section", "divider", "context"]
+        );
+        assert_eq!(blocks[0]["text"]["text"], "Result");
+        assert_eq!(blocks[1]["text"]["text"], "All *good*.");
+        assert_eq!(blocks[2]["text"]["text"], "```\nfn main() {}\n```");
+        assert_eq!(blocks[4]["elements"][0]["text"], "exit code 0");
+        assert!(messages[0].text.contains("All *good*."));
+    }
+
+    #[test]
+    fn paragraphs_share_a_section_until_the_limit() {
+        let para = "x".repeat(2000);
+        let input = format!("# T\n\n{para}\n\n{para}\n\nshort");
+        let blocks = &to_slack_blocks(&input)[0].blocks;
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            blocks[2]["text"]["text"].as_str().unwrap(),
+            format!("{para}\n\nshort")
+        );
+    }
+
+    #[test]
+    fn long_code_is_split_into_fenced_sections() {
+        let code = "line of code\n".repeat(400);
+        let input = format!("```\n{code}```");
+        let blocks = &to_slack_blocks(&input)[0].blocks;
+        assert!(blocks.len() > 1);
+        for block in blocks {
+            let text = block["text"]["text"].as_str().unwrap();
+            assert!(text.starts_with("```\n") && text.ends_with("\n```"));
+            assert!(text.chars().count() <= MAX_SECTION_CHARS);
+        }
+    }
+
+    #[test]
+    fn more_than_fifty_blocks_split_into_messages() {
+        let input = "# h\n---\n".repeat(30);
+        let messages = to_slack_blocks(&input);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].blocks.len(), MAX_BLOCKS);
+        assert_eq!(messages[1].blocks.len(), 10);
+        assert_eq!(messages[1].text, "h\n\nh\n\nh\n\nh\n\nh");
+    }
+
+    #[test]
+    fn split_text_respects_char_boundaries() {
+        let text = "é".repeat(10);
+        let chunks = split_text(&text, 4);
+        assert_eq!(chunks, ["éééé", "éééé", "éé"]);
+    }
+
+    #[test]
+    fn reasoning_renders_as_context_blocks() {
+        let message = reasoning_blocks("First check the date.");
+        assert_eq!(message.blocks.len(), 2);
+        assert!(message.blocks.iter().all(|b| b["type"] == "context"));
+        assert_eq!(
+            message.blocks[1]["elements"][0]["text"],
+            "First check the date."
+        );
+    }
+
+    #[test]
+    fn long_reasoning_is_capped() {
+        let message = reasoning_blocks(&"word ".repeat(5000));
+        assert_eq!(message.blocks.len(), 1 + MAX_REASONING_BLOCKS);
+    }
 }
@@ -1,5 +1,8 @@
 pub mod api;
 pub mod fmt;
+pub mod signature;
+
+pub use signature::verify_signature;
 
 use std::future::Future;
 use std::pin::Pin;
@@ -9,46 +12,43 @@ use std::time::Duration;
 use async_trait::async_trait;
 use futures::stream::StreamExt;
 use reqwest::Client;
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{broadcast, mpsc, watch};
 use tracing::{error, info, warn};
 
-use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
+use crate::dedup::{InboundDedup, SeenFn};
+use crate::edit::{EditRegeneration, OnEditFn};
+use crate::feedback::{OnReactionFn, ReactionFeedback, SentReply};
+use crate::queue::RateLimiter;
+use crate::traits::{
+    ChannelEvent, ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus,
+};
+use crate::typing::while_typing;
+use opencrust_common::{MediaAttachment, MediaKind, Message, MessageContent, Result};
 
 /// Group filter closure for Slack channels.
 /// Argument: `is_mentioned` (whether the bot was mentioned).
 /// Returns `true` if the message should be processed.
 pub type SlackGroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
 
-/// A file shared in a Slack message, with bytes already downloaded.
-///
-/// The channel handler downloads the file before invoking `SlackOnMessageFn`
-/// so the callback does not need to perform any HTTP calls.
-#[derive(Debug, Clone)]
-pub struct SlackFile {
-    /// Original filename as reported by Slack.
-    pub filename: String,
-    /// Raw file bytes (capped at [`api::SLACK_MAX_FILE_BYTES`]).
-    pub data: Vec<u8>,
-    /// MIME type string from Slack (e.g. `"application/pdf"`).
-    pub mime_type: Option<String>,
-}
-
 /// Callback invoked when the bot receives a message from Slack.
 ///
-/// Arguments: `(channel_id, user_id, user_name, text, is_group, file, delta_sender)`.
-/// `file` is `Some` when the user shared a file along with the message.
+/// Arguments: `(channel_id, thread_ts, user_id, user_name, text, is_group, file, delta_sender)`.
+/// `thread_ts` is the thread the reply is posted in: the message's own thread,
+/// or a new thread under it for channel messages. `None` for top-level DMs.
+/// `file` is `Some` when the user shared a file along with the message; it is
+/// downloaded (up to [`api::SLACK_MAX_FILE_BYTES`]) before the callback runs.
 /// When `delta_sender` is `Some`, the callback should send text deltas through it
 /// for streaming display. The callback still returns the final complete text.
 /// Return `Err("__blocked__")` to silently drop the message (unauthorized user).
 pub type SlackOnMessageFn = Arc<
     dyn Fn(
             String,
+            Option<String>,
             String,
             String,
             String,
             bool,
-            Option<SlackFile>,
+            Option<MediaAttachment>,
             Option<mpsc::Sender<String>>,
         )
             -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
@@ -56,6 +56,9 @@ pub type SlackOnMessageFn = Arc<
         + Sync,
 >;
 
+/// Reaction added to a message when the bot starts working on it.
+const READ_RECEIPT_EMOJI: &str = "eyes";
+
 pub struct SlackChannel {
     bot_token: String,
     app_token: String,
@@ -66,6 +69,13 @@ pub struct SlackChannel {
     group_filter: SlackGroupFilter,
     bot_user_id: Option<String>,
     shutdown_tx: Option<watch::Sender<bool>>,
+    event_tx: broadcast::Sender<ChannelEvent>,
+    edits: EditRegeneration,
+    feedback: ReactionFeedback,
+    dedup: InboundDedup,
+    read_receipts: bool,
+    /// Shared by interactive replies and the sender's outbound queue.
+    limiter: Arc<RateLimiter>,
 }
 
 impl SlackChannel {
@@ -80,6 +90,7 @@ impl SlackChannel {
         group_filter: SlackGroupFilter,
         bot_user_id: Option<String>,
     ) -> Self {
+        let (event_tx, _) = broadcast::channel(256);
         Self {
             bot_token,
             app_token,
@@ -90,6 +101,12 @@ impl SlackChannel {
             group_filter,
             bot_user_id,
             shutdown_tx: None,
+            event_tx,
+            edits: EditRegeneration::default(),
+            feedback: ReactionFeedback::default(),
+            dedup: InboundDedup::default(),
+            read_receipts: false,
+            limiter: Arc::new(RateLimiter::for_channel("slack")),
         }
     }
 
@@ -98,12 +115,47 @@ impl SlackChannel {
         self.name = name;
         self
     }
+
+    /// Regenerate the reply when a user edits the last message the bot
+    /// answered in a channel or thread. `on_edit` runs first to drop the
+    /// replaced turn.
+    pub fn with_edit_regeneration(mut self, on_edit: OnEditFn) -> Self {
+        self.edits = EditRegeneration::new(on_edit);
+        self
+    }
+
+    /// Pass 👍/👎 reactions on the bot's replies to `on_reaction`.
+    pub fn with_reaction_feedback(mut self, on_reaction: OnReactionFn) -> Self {
+        self.feedback = ReactionFeedback::new(on_reaction);
+        self
+    }
+
+    /// Drop redelivered events using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
+    /// React with 👀 to each message the bot starts working on, so users can
+    /// see it was received during long replies. Needs `reactions:write`.
+    pub fn with_read_receipts(mut self, enabled: bool) -> Self {
+        self.read_receipts = enabled;
+        self
+    }
+
+    /// Subscribe to channel events: regenerated edits arrive as
+    /// [`ChannelEvent::MessageEdited`] and reactions to the bot's replies as
+    /// [`ChannelEvent::Reaction`].
+    pub fn subscribe(&self) -> broadcast::Receiver<ChannelEvent> {
+        self.event_tx.subscribe()
+    }
 }
 
 /// Lightweight send-only handle for Slack. Holds a bot token for API calls.
 pub struct SlackSender {
     bot_token: String,
     name: String,
+    limiter: Arc<RateLimiter>,
 }
 
 #[async_trait]
@@ -116,9 +168,17 @@ impl ChannelSender for SlackSender {
         &self.name
     }
 
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        Some(Arc::clone(&self.limiter))
+    }
+
     async fn send_message(&self, message: &Message) -> Result<()> {
         slack_send_message(&self.bot_token, message).await
     }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        slack_send_typing(&Client::new(), &self.bot_token, channel_ref).await
+    }
 }
 
 #[async_trait]
@@ -131,6 +191,7 @@ impl ChannelLifecycle for SlackChannel {
         Box::new(SlackSender {
             bot_token: self.bot_token.clone(),
             name: self.name.clone(),
+            limiter: Arc::clone(&self.limiter),
         })
     }
 
@@ -157,6 +218,12 @@ impl ChannelLifecycle for SlackChannel {
         let on_message = Arc::clone(&self.on_message);
         let group_filter = Arc::clone(&self.group_filter);
         let bot_user_id = self.bot_user_id.clone();
+        let edits = self.edits.clone();
+        let feedback = self.feedback.clone();
+        let dedup = self.dedup.clone();
+        let read_receipts = self.read_receipts;
+        let limiter = Arc::clone(&self.limiter);
+        let event_tx = self.event_tx.clone();
 
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
         self.shutdown_tx = Some(shutdown_tx);
@@ -169,6 +236,12 @@ impl ChannelLifecycle for SlackChannel {
                 on_message,
                 group_filter,
                 bot_user_id,
+                edits,
+                feedback,
+                dedup,
+                read_receipts,
+                limiter,
+                event_tx,
                 shutdown_rx,
             )
             .await;
@@ -206,6 +279,25 @@ impl ChannelSender for SlackChannel {
     async fn send_message(&self, message: &Message) -> Result<()> {
         slack_send_message(&self.bot_token, message).await
     }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        slack_send_typing(&Client::new(), &self.bot_token, channel_ref).await
+    }
+}
+
+/// Show "is typing..." in a Slack thread. `channel_ref` is
+/// `channel_id:thread_ts`, the same key edits are tracked by. Slack only offers
+/// a typing status inside threads, so a bare channel id is a no-op.
+async fn slack_send_typing(client: &Client, bot_token: &str, channel_ref: &str) -> Result<()> {
+    let Some((channel_id, thread_ts)) = channel_ref
+        .split_once(':')
+        .filter(|(_, thread_ts)| !thread_ts.is_empty())
+    else {
+        return Ok(());
+    };
+    api::set_thread_status(client, bot_token, channel_id, thread_ts, "is typing...")
+        .await
+        .map_err(|e| opencrust_common::Error::Channel(format!("slack typing failed: {e}")))
 }
 
 /// Shared send logic used by both `SlackChannel` and `SlackSender`.
@@ -218,25 +310,103 @@ async fn slack_send_message(bot_token: &str, message: &Message) -> Result<()> {
             opencrust_common::Error::Channel("missing slack_channel_id in metadata".into())
         })?;
 
-    let text = match &message.content {
-        MessageContent::Text(t) => t.clone(),
+    let thread_ts = message
+        .metadata
+        .get("slack_thread_ts")
+        .and_then(|v| v.as_str());
+
+    let client = Client::new();
+    let sent = match &message.content {
+        MessageContent::Text(text) => {
+            // The outbound queue has already waited for this message.
+            deliver_reply(&client, bot_token, channel_id, text, None, thread_ts, None)
+                .await
+                .map(|_| ())
+        }
+        MessageContent::Attachment(attachment) => {
+            let comment = attachment.caption.as_deref().map(fmt::to_slack_mrkdwn);
+            api::upload_file(
+                &client,
+                bot_token,
+                channel_id,
+                &attachment.filename_or_default(),
+                &attachment.data,
+                comment.as_deref(),
+                thread_ts,
+            )
+            .await
+        }
         _ => {
             return Err(opencrust_common::Error::Channel(
-                "only text messages are supported for slack send".into(),
+                "only text and attachment messages are supported for slack send".into(),
             ));
         }
     };
+    sent.map_err(|e| {
+        // Slack reports HTTP 429 as the `ratelimited` API error.
+        if e.ends_with("error: ratelimited") {
+            opencrust_common::Error::RateLimited {
+                message: format!("slack send failed: {e}"),
+                retry_after: None,
+            }
+        } else {
+            opencrust_common::Error::Channel(format!("slack send failed: {e}"))
+        }
+    })
+}
 
-    let client = Client::new();
-    let formatted = fmt::to_slack_mrkdwn(&text);
-    api::post_message(&client, bot_token, channel_id, &formatted, None)
-        .await
-        .map_err(|e| opencrust_common::Error::Channel(format!("slack send failed: {e}")))?;
+/// Send agent markdown to Slack. Code blocks, headings and long text go out
+/// as Block Kit (split into several messages past 50 blocks); anything else
+/// as a single mrkdwn message. When `replace_ts` is set, that message (the
+/// streaming preview) is replaced by the first part. With a `limiter`, each
+/// message waits for the channel's rate limits. Returns the ts of every
+/// message carrying the reply.
+async fn deliver_reply(
+    client: &Client,
+    bot_token: &str,
+    channel_id: &str,
+    text: &str,
+    replace_ts: Option<&str>,
+    thread_ts: Option<&str>,
+    limiter: Option<&RateLimiter>,
+) -> std::result::Result<Vec<String>, String> {
+    if !fmt::needs_blocks(text) {
+        if let Some(limiter) = limiter {
+            limiter.acquire(channel_id).await;
+        }
+        let formatted = fmt::to_slack_mrkdwn(text);
+        return match replace_ts {
+            Some(ts) => api::update_message(client, bot_token, channel_id, ts, &formatted)
+                .await
+                .map(|_| vec![ts.to_string()]),
+            None => api::post_message(client, bot_token, channel_id, &formatted, thread_ts)
+                .await
+                .map(|ts| vec![ts]),
+        };
+    }
 
-    Ok(())
+    let mut sent = Vec::new();
+    for (i, message) in fmt::to_slack_blocks(text).iter().enumerate() {
+        if let Some(limiter) = limiter {
+            limiter.acquire(channel_id).await;
+        }
+        match replace_ts.filter(|_| i == 0) {
+            Some(ts) => {
+                api::update_blocks(client, bot_token, channel_id, ts, message).await?;
+                sent.push(ts.to_string());
+            }
+            None => {
+                sent.push(
+                    api::post_blocks(client, bot_token, channel_id, message, thread_ts).await?,
+                );
+            }
+        }
+    }
+    Ok(sent)
 }
 
 /// Main Socket Mode event loop with automatic reconnection.
+#[allow(clippy::too_many_arguments)]
 async fn run_socket_mode(
     client: Client,
     bot_token: String,
@@ -244,6 +414,12 @@ async fn run_socket_mode(
     on_message: SlackOnMessageFn,
     group_filter: SlackGroupFilter,
     bot_user_id: Option<String>,
+    edits: EditRegeneration,
+    feedback: ReactionFeedback,
+    dedup: InboundDedup,
+    read_receipts: bool,
+    limiter: Arc<RateLimiter>,
+    event_tx: broadcast::Sender<ChannelEvent>,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
     loop {
@@ -295,6 +471,12 @@ async fn run_socket_mode(
                                     &on_message,
                                     &group_filter,
                                     bot_user_id.as_deref(),
+                                    &dedup,
+                                    read_receipts,
+                                    &limiter,
+                                    &edits,
+                                    &feedback,
+                                    &event_tx,
                                     &ws_write,
                                 ).await;
                                 if let HandleResult::Reconnect = handled {
@@ -336,6 +518,20 @@ async fn run_socket_mode(
     }
 }
 
+/// De-duplication key of a message: `ts` is only unique within a channel.
+fn message_key(channel_id: &str, ts: &str) -> String {
+    format!("{channel_id}:{ts}")
+}
+
+/// Thread to reply in: the message's own thread, or a new thread under the
+/// message when it was posted at the top level of a channel. Top-level DMs
+/// are answered inline.
+fn reply_thread(thread_ts: Option<&str>, ts: &str, is_group: bool) -> Option<String> {
+    thread_ts
+        .or((is_group && !ts.is_empty()).then_some(ts))
+        .map(str::to_string)
+}
+
 enum HandleResult {
     Ok,
     Reconnect,
@@ -352,6 +548,7 @@ type WsWriter = Arc<
     >,
 >;
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_socket_event(
     raw: &str,
     client: &Client,
@@ -359,6 +556,12 @@ async fn handle_socket_event(
     on_message: &SlackOnMessageFn,
     group_filter: &SlackGroupFilter,
     bot_user_id: Option<&str>,
+    dedup: &InboundDedup,
+    read_receipts: bool,
+    limiter: &Arc<RateLimiter>,
+    edits: &EditRegeneration,
+    feedback: &ReactionFeedback,
+    event_tx: &broadcast::Sender<ChannelEvent>,
     ws_write: &WsWriter,
 ) -> HandleResult {
     let envelope: serde_json::Value = match serde_json::from_str(raw) {
@@ -380,21 +583,18 @@ async fn handle_socket_event(
             info!("slack: received disconnect — will reconnect");
             HandleResult::Reconnect
         }
+        "slash_commands" => {
+            // Slack shows the command as failed unless it is acked within 3s;
+            // the answer follows through the command's `response_url`.
+            ack(ws_write, &envelope).await;
+            if let Some(command) = envelope.get("payload").and_then(parse_slash_command) {
+                handle_slash_command(command, client, on_message);
+            }
+            HandleResult::Ok
+        }
         "events_api" => {
             // Acknowledge the envelope immediately
-            if let Some(envelope_id) = envelope.get("envelope_id").and_then(|v| v.as_str()) {
-                let ack = serde_json::json!({ "envelope_id": envelope_id });
-                use futures::SinkExt;
-                let mut writer = ws_write.lock().await;
-                if let Err(e) = writer
-                    .send(tokio_tungstenite::tungstenite::Message::Text(
-                        ack.to_string().into(),
-                    ))
-                    .await
-                {
-                    warn!("slack: failed to send ack: {e}");
-                }
-            }
+            ack(ws_write, &envelope).await;
 
             // Extract the event payload
             let payload = match envelope.get("payload") {
@@ -408,13 +608,29 @@ async fn handle_socket_event(
             };
 
             let event_type = event.get("type").and_then(|v| v.as_str()).unwrap_or("");
-            if event_type != "message" {
+            if event_type == "reaction_added" {
+                if let Some(reaction) = parse_reaction(event)
+                    && bot_user_id != Some(reaction.user_id.as_str())
+                    && let Some(reply) = feedback.reply(&reaction.channel_id, &reaction.ts)
+                {
+                    info!(
+                        "slack: reaction :{}: from {} in {}",
+                        reaction.name, reaction.user_id, reaction.channel_id
+                    );
+                    let _ = event_tx.send(ChannelEvent::Reaction {
+                        chat_id: reaction.channel_id,
+                        user_id: reaction.user_id,
+                        message_id: reaction.ts,
+                        emoji: reaction.name.clone(),
+                    });
+                    let feedback = feedback.clone();
+                    tokio::spawn(async move { feedback.react(reply, &reaction.name).await });
+                }
                 return HandleResult::Ok;
             }
-
-            // Skip bot messages. Allow file_share subtype — all other subtypes are skipped.
-            let subtype = event.get("subtype").and_then(|v| v.as_str());
-            if event.get("bot_id").is_some() || subtype.is_some_and(|s| s != "file_share") {
+            // `app_mention` arrives for @mentions in channels the app is not
+            // subscribed to with `message.channels`.
+            if event_type != "message" && event_type != "app_mention" {
                 return HandleResult::Ok;
             }
 
@@ -423,6 +639,28 @@ async fn handle_socket_event(
                 .and_then(|v| v.as_str())
                 .unwrap_or("")
                 .to_string();
+
+            // An edit wraps the new message; the wrapper's own ts identifies
+            // the edit for deduplication.
+            let edit_ts = match event.get("subtype").and_then(|v| v.as_str()) {
+                Some("message_changed") => event.get("ts").and_then(|v| v.as_str()).unwrap_or(""),
+                _ => "",
+            };
+            let is_edit = !edit_ts.is_empty();
+            let event = if is_edit {
+                match edited_message(event) {
+                    Some(message) => message,
+                    None => return HandleResult::Ok,
+                }
+            } else {
+                event
+            };
+
+            // Skip bot messages. Allow file_share subtype — all other subtypes are skipped.
+            let subtype = event.get("subtype").and_then(|v| v.as_str());
+            if event.get("bot_id").is_some() || subtype.is_some_and(|s| s != "file_share") {
+                return HandleResult::Ok;
+            }
             let user_id = event
                 .get("user")
                 .and_then(|v| v.as_str())
@@ -433,17 +671,13 @@ async fn handle_socket_event(
                 .and_then(|v| v.as_str())
                 .unwrap_or("")
                 .to_string();
-            // thread_ts is present when the message was sent inside a thread.
-            // Capture it so replies stay in the same thread.
-            let thread_ts: Option<String> = event
-                .get("thread_ts")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
+            let ts = event.get("ts").and_then(|v| v.as_str()).unwrap_or("");
 
             // Extract the first file from the event (if present).
-            // Slack puts shared files in event.files[0].
+            // Slack puts shared files in event.files[0]. Edits only change the text.
             let file_info = event
                 .get("files")
+                .filter(|_| !is_edit)
                 .and_then(|v| v.as_array())
                 .and_then(|arr| arr.first())
                 .map(|f| {
@@ -472,17 +706,49 @@ async fn handle_socket_event(
             // Slack channel IDs starting with 'D' are DMs, everything else is a group/channel
             let is_group = !channel_id.starts_with('D');
 
+            // thread_ts is present when the message was sent inside a thread.
+            // Replies (and the session) stay in that thread.
+            let thread_ts = reply_thread(
+                event.get("thread_ts").and_then(|v| v.as_str()),
+                ts,
+                is_group,
+            );
+
+            // Only an edit of the last message answered in this channel or
+            // thread is run again.
+            let chat_key = format!("{channel_id}:{}", thread_ts.as_deref().unwrap_or(""));
+            let on_edit = if is_edit {
+                match edits.hook_for(&chat_key, ts) {
+                    Some(on_edit) => Some(Arc::clone(on_edit)),
+                    None => return HandleResult::Ok,
+                }
+            } else {
+                None
+            };
+
             if is_group {
-                let is_mentioned = bot_user_id
-                    .map(|id| text.contains(&format!("<@{id}>")))
-                    .unwrap_or(false);
+                let is_mentioned = event_type == "app_mention"
+                    || bot_user_id
+                        .map(|id| text.contains(&format!("<@{id}>")))
+                        .unwrap_or(false);
                 if !group_filter(is_mentioned) {
                     return HandleResult::Ok;
                 }
             }
 
+            // Slack delivers a channel message that mentions the bot both as
+            // a `message` and as an `app_mention` event, and redelivers events
+            // that were not acknowledged in time; only the first is handled.
+            // Checked after filtering, so a mention dropped as a plain `message`
+            // (bot user ID unknown) is still handled as an `app_mention`.
+            let dedup_ts = if is_edit { edit_ts } else { ts };
+            if !dedup_ts.is_empty() && !dedup.first_time(&message_key(&channel_id, dedup_ts)) {
+                return HandleResult::Ok;
+            }
+
             info!(
-                "slack: message from {} in {}: {} chars{}{}{}",
+                "slack: {} from {} in {}: {} chars{}{}{}",
+                if is_edit { "edit" } else { "message" },
                 user_id,
                 channel_id,
                 text.len(),
@@ -495,8 +761,39 @@ async fn handle_socket_event(
             let client = client.clone();
             let bot_token = bot_token.to_string();
             let on_message = Arc::clone(on_message);
+            let edits = edits.clone();
+            let feedback = feedback.clone();
+            let limiter = Arc::clone(limiter);
+            let ts = ts.to_string();
+            if on_edit.is_some() {
+                let _ = event_tx.send(ChannelEvent::MessageEdited {
+                    chat_id: channel_id.clone(),
+                    user_id: user_id.clone(),
+                    message_id: ts.clone(),
+                    text: text.clone(),
+                });
+            }
 
             tokio::spawn(async move {
+                if let Some(on_edit) = on_edit {
+                    on_edit(
+                        channel_id.clone(),
+                        thread_ts.clone(),
+                        user_id.clone(),
+                        is_group,
+                    )
+                    .await;
+                }
+
+                if read_receipts
+                    && !is_edit
+                    && let Err(e) =
+                        api::add_reaction(&client, &bot_token, &channel_id, &ts, READ_RECEIPT_EMOJI)
+                            .await
+                {
+                    warn!("slack: failed to acknowledge message: {e}");
+                }
+
                 // Download file bytes before invoking the callback.
                 let slack_file = if let Some((filename, url, mime_type)) = file_info {
                     if url.is_empty() {
@@ -504,14 +801,18 @@ async fn handle_socket_event(
                         None
                     } else {
                         match api::download_file(&client, &bot_token, &url).await {
-                            Ok(data) => Some(SlackFile {
-                                filename,
-                                data,
-                                mime_type,
-                            }),
+                            Ok(data) => Some(
+                                MediaAttachment::new(
+                                    MediaKind::from_mime(mime_type.as_deref()),
+                                    data,
+                                )
+                                .with_filename(filename)
+                                .with_mime_type(mime_type),
+                            ),
                             Err(e) => {
                                 warn!("slack: failed to download file: {e}");
                                 // Post error and return early — reply in thread if applicable
+                                limiter.acquire(&channel_id).await;
                                 let _ = api::post_message(
                                     &client,
                                     &bot_token,
@@ -531,6 +832,7 @@ async fn handle_socket_event(
                 let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
 
                 let cb_channel = channel_id.clone();
+                let cb_thread = thread_ts.clone();
                 let cb_user = user_id.clone();
                 let cb_text = text.clone();
 
@@ -540,6 +842,7 @@ async fn handle_socket_event(
                     let user_name = api::get_user_name(&name_client, &name_token, &cb_user).await;
                     on_message(
                         cb_channel,
+                        cb_thread,
                         cb_user,
                         user_name,
                         cb_text,
@@ -556,72 +859,112 @@ async fn handle_socket_event(
                 let mut last_update = tokio::time::Instant::now();
                 let mut first_delta_at: Option<tokio::time::Instant> = None;
 
-                while let Some(delta) = delta_rx.recv().await {
-                    accumulated.push_str(&delta);
-                    if first_delta_at.is_none() {
-                        first_delta_at = Some(tokio::time::Instant::now());
-                    }
+                // Keep "is typing..." up in the thread while the agent works.
+                let typing = SlackSender {
+                    bot_token: bot_token.clone(),
+                    name: "slack".to_string(),
+                    limiter: Arc::clone(&limiter),
+                };
+                let result = while_typing(&typing, &chat_key, async {
+                    while let Some(delta) = delta_rx.recv().await {
+                        accumulated.push_str(&delta);
+                        if first_delta_at.is_none() {
+                            first_delta_at = Some(tokio::time::Instant::now());
+                        }
 
-                    if msg_ts.is_none() {
-                        // Buffer 1s before sending first message
-                        if first_delta_at.unwrap().elapsed() >= Duration::from_secs(1) {
-                            match api::post_message(
+                        if msg_ts.is_none() {
+                            // Buffer 1s before sending first message
+                            if first_delta_at.unwrap().elapsed() >= Duration::from_secs(1) {
+                                limiter.acquire(&channel_id).await;
+                                match api::post_message(
+                                    &client,
+                                    &bot_token,
+                                    &channel_id,
+                                    &accumulated,
+                                    thread_ts.as_deref(),
+                                )
+                                .await
+                                {
+                                    Ok(ts) => {
+                                        msg_ts = Some(ts);
+                                        last_update = tokio::time::Instant::now();
+                                    }
+                                    Err(e) => {
+                                        error!("slack: failed to post streaming message: {e}");
+                                        break;
+                                    }
+                                }
+                            }
+                        } else if last_update.elapsed() >= Duration::from_millis(1000)
+                            && let Some(ts) = &msg_ts
+                        {
+                            limiter.acquire(&channel_id).await;
+                            let _ = api::update_message(
                                 &client,
                                 &bot_token,
                                 &channel_id,
+                                ts,
                                 &accumulated,
-                                thread_ts.as_deref(),
                             )
-                            .await
-                            {
-                                Ok(ts) => {
-                                    msg_ts = Some(ts);
-                                    last_update = tokio::time::Instant::now();
-                                }
-                                Err(e) => {
-                                    error!("slack: failed to post streaming message: {e}");
-                                    break;
-                                }
-                            }
+                            .await;
+                            last_update = tokio::time::Instant::now();
                         }
-                    } else if last_update.elapsed() >= Duration::from_millis(1000)
-                        && let Some(ts) = &msg_ts
-                    {
-                        let _ =
-                            api::update_message(&client, &bot_token, &channel_id, ts, &accumulated)
-                                .await;
-                        last_update = tokio::time::Instant::now();
                     }
-                }
 
-                // Get final result
-                let result = callback_handle
-                    .await
-                    .unwrap_or_else(|e| Err(format!("task panic: {e}")));
+                    callback_handle
+                        .await
+                        .unwrap_or_else(|e| Err(format!("task panic: {e}")))
+                })
+                .await;
+
+                if result.is_ok() {
+                    edits.record(&chat_key, &ts);
+                }
 
                 match result {
                     Ok(response) => {
                         // Slack has no native audio API — Voice falls back to text.
-                        let formatted = fmt::to_slack_mrkdwn(response.text());
-                        if let Some(ts) = &msg_ts {
-                            let _ = api::update_message(
-                                &client,
-                                &bot_token,
-                                &channel_id,
-                                ts,
-                                &formatted,
-                            )
-                            .await;
-                        } else {
-                            // No streaming happened — send final message directly
-                            let _ = api::post_message(
+                        // Without a streaming message, the reply is posted directly.
+                        match deliver_reply(
+                            &client,
+                            &bot_token,
+                            &channel_id,
+                            response.text(),
+                            msg_ts.as_deref(),
+                            thread_ts.as_deref(),
+                            Some(&limiter),
+                        )
+                        .await
+                        {
+                            Ok(sent) => {
+                                for reply_ts in sent {
+                                    feedback.record(
+                                        &reply_ts,
+                                        SentReply {
+                                            chat_id: channel_id.clone(),
+                                            thread_id: thread_ts.clone(),
+                                            user_id: user_id.clone(),
+                                            is_group,
+                                            text: response.text().to_string(),
+                                        },
+                                    );
+                                }
+                            }
+                            Err(e) => warn!("slack: failed to send reply: {e}"),
+                        }
+                        if let ChannelResponse::Reasoning { reasoning, .. } = &response {
+                            limiter.acquire(&channel_id).await;
+                            if let Err(e) = api::post_blocks(
                                 &client,
                                 &bot_token,
                                 &channel_id,
-                                &formatted,
+                                &fmt::reasoning_blocks(reasoning),
                                 thread_ts.as_deref(),
                             )
-                            .await;
+                            .await
+                            {
+                                warn!("slack: failed to send reasoning: {e}");
+                            }
                         }
                     }
                     Err(e) if e == "__blocked__" => {
@@ -629,6 +972,7 @@ async fn handle_socket_event(
                     }
                     Err(e) => {
                         let error_text = format!("Sorry, an error occurred: {e}");
+                        limiter.acquire(&channel_id).await;
                         if let Some(ts) = &msg_ts {
                             let _ = api::update_message(
                                 &client,
@@ -661,6 +1005,153 @@ async fn handle_socket_event(
     }
 }
 
+/// Acknowledge a Socket Mode envelope so Slack does not redeliver it.
+async fn ack(ws_write: &WsWriter, envelope: &serde_json::Value) {
+    let Some(envelope_id) = envelope.get("envelope_id").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let ack = serde_json::json!({ "envelope_id": envelope_id });
+    use futures::SinkExt;
+    let mut writer = ws_write.lock().await;
+    if let Err(e) = writer
+        .send(tokio_tungstenite::tungstenite::Message::Text(
+            ack.to_string().into(),
+        ))
+        .await
+    {
+        warn!("slack: failed to send ack: {e}");
+    }
+}
+
+/// Shown for `/opencrust` without a question.
+const SLASH_USAGE: &str =
+    "Usage: `/opencrust ask <question>`, or `/opencrust <command>` (e.g. `/opencrust help`).";
+
+/// Shown, only to them, to users the bot does not serve.
+const SLASH_BLOCKED: &str = "You are not authorized to use this assistant.";
+
+/// A slash command invocation (e.g. `/opencrust ask ...`).
+#[derive(Debug, PartialEq)]
+struct SlackSlashCommand {
+    channel_id: String,
+    user_id: String,
+    /// The user's handle, as sent with the command.
+    user_name: String,
+    /// Everything after the command name.
+    text: String,
+    /// Where the answer is posted; valid for 30 minutes.
+    response_url: String,
+}
+
+fn parse_slash_command(payload: &serde_json::Value) -> Option<SlackSlashCommand> {
+    let field = |key: &str| payload.get(key)?.as_str().map(str::to_string);
+    Some(SlackSlashCommand {
+        channel_id: field("channel_id")?,
+        user_id: field("user_id")?,
+        user_name: field("user_name").unwrap_or_default(),
+        text: field("text").unwrap_or_default(),
+        response_url: field("response_url")?,
+    })
+}
+
+/// The message a slash command stands for: `ask <question>` is the
+/// question, anything else a chat command (`/opencrust clear` runs `/clear`).
+/// `None` when there is nothing to ask.
+fn slash_message(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    if word.eq_ignore_ascii_case("ask") {
+        let question = rest.trim();
+        return (!question.is_empty()).then(|| question.to_string());
+    }
+    (!word.is_empty()).then(|| format!("/{text}"))
+}
+
+/// Run a slash command through the agent and answer via its `response_url`.
+///
+/// Like a DM, the command is exempt from the group filter but goes through
+/// DM authorization. Only the answer to `ask` is posted in the channel; chat
+/// command replies (which can carry pairing or link codes), refusals, errors
+/// and usage hints are shown only to the user who ran it.
+fn handle_slash_command(
+    command: SlackSlashCommand,
+    client: &Client,
+    on_message: &SlackOnMessageFn,
+) {
+    info!(
+        "slack: slash command from {} in {}: {} chars",
+        command.user_id,
+        command.channel_id,
+        command.text.len()
+    );
+    let client = client.clone();
+    let on_message = Arc::clone(on_message);
+    tokio::spawn(async move {
+        let (reply, ephemeral) = match slash_message(&command.text) {
+            None => (SLASH_USAGE.to_string(), true),
+            Some(text) => {
+                let ephemeral = text.starts_with('/');
+                let result = on_message(
+                    command.channel_id,
+                    None,
+                    command.user_id,
+                    command.user_name,
+                    text,
+                    false,
+                    None,
+                    None,
+                )
+                .await;
+                match result {
+                    Ok(response) => (fmt::to_slack_mrkdwn(response.text()), ephemeral),
+                    Err(e) if e == "__blocked__" => (SLASH_BLOCKED.to_string(), true),
+                    Err(e) => (format!("Sorry, an error occurred: {e}"), true),
+                }
+            }
+        };
+        if let Err(e) = api::respond(&client, &command.response_url, &reply, ephemeral).await {
+            warn!("slack: {e}");
+        }
+    });
+}
+
+/// A `reaction_added` event on a message.
+#[derive(Debug, PartialEq)]
+struct SlackReaction {
+    channel_id: String,
+    ts: String,
+    user_id: String,
+    /// Reaction name without colons, e.g. `+1` or `thumbsdown`.
+    name: String,
+}
+
+/// Returns None for reactions on files or other non-message items.
+fn parse_reaction(event: &serde_json::Value) -> Option<SlackReaction> {
+    let item = event.get("item")?;
+    if item.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return None;
+    }
+    let field = |v: &serde_json::Value, key: &str| v.get(key)?.as_str().map(str::to_string);
+    Some(SlackReaction {
+        channel_id: field(item, "channel")?,
+        ts: field(item, "ts")?,
+        user_id: field(event, "user")?,
+        name: field(event, "reaction")?,
+    })
+}
+
+/// The new message inside a `message_changed` event, when a user changed its
+/// text. Link unfurls and the bot's own streaming updates also arrive as
+/// `message_changed` and are ignored.
+fn edited_message(event: &serde_json::Value) -> Option<&serde_json::Value> {
+    let message = event.get("message")?;
+    if message.get("bot_id").is_some() {
+        return None;
+    }
+    let previous = event.get("previous_message").and_then(|m| m.get("text"));
+    (message.get("text") != previous).then_some(message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -668,20 +1159,24 @@ mod tests {
     #[test]
     fn bot_user_id_none_by_default_in_new() {
         // SlackChannel::new does not require bot_user_id; connect() fills it in.
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, _file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, _file, _delta_tx| {
                 Box::pin(async { Ok(ChannelResponse::Text("ok".to_string())) })
-            });
+            },
+        );
         let channel = SlackChannel::new("xoxb-tok".to_string(), "xapp-tok".to_string(), on_msg);
         assert!(channel.bot_user_id.is_none());
+        assert!(!channel.read_receipts);
+        assert!(channel.with_read_receipts(true).read_receipts);
     }
 
     #[test]
     fn with_group_filter_accepts_explicit_bot_user_id() {
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, _file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, _file, _delta_tx| {
                 Box::pin(async { Ok(ChannelResponse::Text("ok".to_string())) })
-            });
+            },
+        );
         let channel = SlackChannel::with_group_filter(
             "xoxb-tok".to_string(),
             "xapp-tok".to_string(),
@@ -694,10 +1189,11 @@ mod tests {
 
     #[test]
     fn channel_type_is_slack() {
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, _file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, _file, _delta_tx| {
                 Box::pin(async { Ok(ChannelResponse::Text("test".to_string())) })
-            });
+            },
+        );
         let channel = SlackChannel::new("xoxb-fake".to_string(), "xapp-fake".to_string(), on_msg);
         assert_eq!(channel.channel_type(), "slack");
         assert_eq!(channel.display_name(), "Slack");
@@ -745,6 +1241,178 @@ mod tests {
         assert!(thread_ts.is_none());
     }
 
+    #[test]
+    fn reply_thread_starts_threads_in_channels_only() {
+        let ts = "1234567890.000200";
+        assert_eq!(
+            reply_thread(Some("1234567890.000100"), ts, true).as_deref(),
+            Some("1234567890.000100")
+        );
+        assert_eq!(reply_thread(None, ts, true).as_deref(), Some(ts));
+        assert!(reply_thread(None, ts, false).is_none());
+        assert_eq!(
+            reply_thread(Some("1234567890.000100"), ts, false).as_deref(),
+            Some("1234567890.000100")
+        );
+    }
+
+    #[test]
+    fn edited_message_requires_changed_user_text() {
+        let edit = serde_json::json!({
+            "type": "message",
+            "subtype": "message_changed",
+            "channel": "C1",
+            "ts": "2.0",
+            "message": { "type": "message", "user": "U1", "text": "new", "ts": "1.0" },
+            "previous_message": { "type": "message", "user": "U1", "text": "old", "ts": "1.0" }
+        });
+        assert_eq!(edited_message(&edit).unwrap()["text"], "new");
+
+        let mut unfurl = edit.clone();
+        unfurl["previous_message"]["text"] = "new".into();
+        assert!(edited_message(&unfurl).is_none());
+
+        let mut bot_update = edit.clone();
+        bot_update["message"]["bot_id"] = "B1".into();
+        assert!(edited_message(&bot_update).is_none());
+    }
+
+    #[test]
+    fn parse_reaction_reads_message_reactions() {
+        let event = serde_json::json!({
+            "type": "reaction_added",
+            "user": "U1",
+            "reaction": "+1",
+            "item_user": "UBOT",
+            "item": { "type": "message", "channel": "C1", "ts": "1.5" },
+            "event_ts": "2.0"
+        });
+        assert_eq!(
+            parse_reaction(&event),
+            Some(SlackReaction {
+                channel_id: "C1".to_string(),
+                ts: "1.5".to_string(),
+                user_id: "U1".to_string(),
+                name: "+1".to_string(),
+            })
+        );
+
+        let mut on_file = event.clone();
+        on_file["item"] = serde_json::json!({ "type": "file", "file": "F1" });
+        assert!(parse_reaction(&on_file).is_none());
+    }
+
+    #[tokio::test]
+    async fn typing_outside_threads_is_a_no_op() {
+        let client = Client::new();
+        assert!(
+            slack_send_typing(&client, "xoxb-test", "C123")
+                .await
+                .is_ok()
+        );
+        assert!(
+            slack_send_typing(&client, "xoxb-test", "C123:")
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn parse_slash_command_reads_payload() {
+        let payload = serde_json::json!({
+            "command": "/opencrust",
+            "text": "ask what is rust?",
+            "user_id": "U1",
+            "user_name": "alice",
+            "channel_id": "C1",
+            "response_url": "https://hooks.slack.com/commands/T1/1/abc",
+        });
+        assert_eq!(
+            parse_slash_command(&payload),
+            Some(SlackSlashCommand {
+                channel_id: "C1".to_string(),
+                user_id: "U1".to_string(),
+                user_name: "alice".to_string(),
+                text: "ask what is rust?".to_string(),
+                response_url: "https://hooks.slack.com/commands/T1/1/abc".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_slash_command(&serde_json::json!({"user_id": "U1", "channel_id": "C1"})),
+            None
+        );
+    }
+
+    #[test]
+    fn slash_message_maps_ask_and_commands() {
+        assert_eq!(
+            slash_message("ask  what is rust? "),
+            Some("what is rust?".to_string())
+        );
+        assert_eq!(slash_message("ASK hi"), Some("hi".to_string()));
+        assert_eq!(slash_message("help"), Some("/help".to_string()));
+        assert_eq!(
+            slash_message("link 123456"),
+            Some("/link 123456".to_string())
+        );
+        assert_eq!(slash_message("ask"), None);
+        assert_eq!(slash_message("  "), None);
+    }
+
+    #[tokio::test]
+    async fn slash_command_replies_through_response_url() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/commands/1"))
+            .and(body_partial_json(serde_json::json!({
+                "response_type": "ephemeral",
+                "text": SLASH_BLOCKED,
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, is_group, _file, _delta_tx| {
+                assert!(!is_group, "slash commands are handled like DMs");
+                Box::pin(async { Err("__blocked__".to_string()) })
+            },
+        );
+        let command = SlackSlashCommand {
+            channel_id: "C1".to_string(),
+            user_id: "U1".to_string(),
+            user_name: "alice".to_string(),
+            text: "ask hi".to_string(),
+            response_url: format!("{}/commands/1", server.uri()),
+        };
+        handle_slash_command(command, &Client::new(), &on_msg);
+
+        for _ in 0..100 {
+            if !server
+                .received_requests()
+                .await
+                .unwrap_or_default()
+                .is_empty()
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        server.verify().await;
+    }
+
+    #[test]
+    fn duplicate_deliveries_are_skipped_per_channel() {
+        let dedup = InboundDedup::default();
+        assert!(dedup.first_time(&message_key("C1", "1.0")));
+        assert!(!dedup.first_time(&message_key("C1", "1.0")));
+        assert!(dedup.first_time(&message_key("C2", "1.0")));
+    }
+
     #[test]
     fn slack_dm_channel_detection() {
         // Slack DM channel IDs start with 'D'
@@ -753,52 +1421,40 @@ mod tests {
         assert!(!"G12345".starts_with('D'));
     }
 
-    // --- SlackFile / file-ingest tests ---
+    // --- Attachment / file-ingest tests ---
 
     #[test]
-    fn slack_file_fields_accessible() {
-        let file = SlackFile {
-            filename: "report.pdf".to_string(),
-            data: vec![1, 2, 3],
-            mime_type: Some("application/pdf".to_string()),
-        };
-        assert_eq!(file.filename, "report.pdf");
-        assert_eq!(file.data.len(), 3);
-        assert_eq!(file.mime_type.as_deref(), Some("application/pdf"));
-    }
-
-    #[test]
-    fn slack_file_mime_type_optional() {
-        let file = SlackFile {
-            filename: "data.bin".to_string(),
-            data: vec![],
-            mime_type: None,
-        };
-        assert!(file.mime_type.is_none());
+    fn slack_attachment_kind_follows_mime_type() {
+        let file = MediaAttachment::new(MediaKind::from_mime(Some("image/jpeg")), vec![1, 2, 3])
+            .with_filename("photo.jpg")
+            .with_mime_type(Some("image/jpeg".to_string()));
+        assert_eq!(file.kind, MediaKind::Photo);
+        assert_eq!(file.filename.as_deref(), Some("photo.jpg"));
+        assert_eq!(MediaKind::from_mime(None), MediaKind::Document);
     }
 
     #[tokio::test]
     async fn on_message_callback_receives_slack_file() {
-        // Verify that the SlackOnMessageFn signature accepts Option<SlackFile>
+        // Verify that the SlackOnMessageFn signature accepts Option<MediaAttachment>
         // and the file reaches the callback.
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
-            });
+            },
+        );
 
-        let slack_file = SlackFile {
-            filename: "doc.pdf".to_string(),
-            data: vec![0u8; 8],
-            mime_type: Some("application/pdf".to_string()),
-        };
+        let slack_file = MediaAttachment::new(MediaKind::Document, vec![0u8; 8])
+            .with_filename("doc.pdf")
+            .with_mime_type(Some("application/pdf".to_string()));
 
         let result = on_msg(
             "C123".to_string(),
+            None,
             "U456".to_string(),
             "user".to_string(),
             "/ingest".to_string(),
@@ -813,18 +1469,20 @@ mod tests {
 
     #[tokio::test]
     async fn on_message_callback_with_no_file() {
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
-            });
+            },
+        );
 
         let result = on_msg(
             "C123".to_string(),
+            None,
             "U456".to_string(),
             "user".to_string(),
             "hello".to_string(),
@@ -860,18 +1518,20 @@ mod tests {
     async fn on_message_returning_voice_exposes_text() {
         // Even if a callback returns Voice (e.g. TTS-enabled), Slack should be
         // able to extract the text via .text() without panicking.
-        let on_msg: SlackOnMessageFn =
-            Arc::new(|_ch, _uid, _user, _text, _is_group, _file, _delta_tx| {
+        let on_msg: SlackOnMessageFn = Arc::new(
+            |_ch, _thread, _uid, _user, _text, _is_group, _file, _delta_tx| {
                 Box::pin(async {
                     Ok(ChannelResponse::Voice {
                         text: "synthesized reply".to_string(),
                         audio: vec![0xDE, 0xAD],
                     })
                 })
-            });
+            },
+        );
 
         let result = on_msg(
             "C123".to_string(),
+            None,
             "U456".to_string(),
             "user".to_string(),
             "speak".to_string(),
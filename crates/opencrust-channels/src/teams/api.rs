@@ -0,0 +1,280 @@
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use opencrust_common::MediaAttachment;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+/// Tenant used for multi-tenant bots.
+pub const DEFAULT_TENANT: &str = "botframework.com";
+
+/// Scope requested for Bot Connector access tokens.
+const TOKEN_SCOPE: &str = "https://api.botframework.com/.default";
+
+/// Tokens are refreshed this long before they expire.
+const TOKEN_MARGIN: Duration = Duration::from_secs(300);
+
+/// Azure AD v2 token endpoint for `tenant`.
+pub fn token_url(tenant: &str) -> String {
+    format!("https://login.microsoftonline.com/{tenant}/oauth2/v2.0/token")
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Client-credentials access token for the Bot Connector API, cached until
+/// shortly before it expires.
+pub struct TokenCache {
+    app_id: String,
+    app_password: String,
+    token_url: String,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenCache {
+    pub fn new(app_id: String, app_password: String, token_url: String) -> Self {
+        Self {
+            app_id,
+            app_password,
+            token_url,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Return a valid access token, fetching a new one if needed.
+    pub async fn get(&self, client: &Client) -> Result<String, String> {
+        let mut guard = self.token.lock().await;
+        if let Some((token, expires)) = guard.as_ref()
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let resp = client
+            .post(&self.token_url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.app_id.as_str()),
+                ("client_secret", self.app_password.as_str()),
+                ("scope", TOKEN_SCOPE),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("teams token request failed: {e}"))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("teams token request failed ({status}): {body}"));
+        }
+
+        let token: TokenResponse = resp
+            .json()
+            .await
+            .map_err(|e| format!("invalid teams token response: {e}"))?;
+        let lifetime = Duration::from_secs(token.expires_in).saturating_sub(TOKEN_MARGIN);
+        *guard = Some((token.access_token.clone(), Instant::now() + lifetime));
+        Ok(token.access_token)
+    }
+}
+
+/// Post a message activity into `conversation_id`, threaded under
+/// `reply_to_id` when given. An `attachment` is sent inline as a `data:` URI,
+/// the form the Bot Connector accepts without a separate upload.
+pub async fn send_activity(
+    client: &Client,
+    token: &str,
+    service_url: &str,
+    conversation_id: &str,
+    reply_to_id: Option<&str>,
+    text: &str,
+    attachment: Option<&MediaAttachment>,
+) -> Result<(), String> {
+    let mut url =
+        Url::parse(service_url).map_err(|e| format!("invalid serviceUrl {service_url}: {e}"))?;
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| format!("invalid serviceUrl {service_url}"))?;
+        segments
+            .pop_if_empty()
+            .extend(["v3", "conversations", conversation_id, "activities"]);
+        if let Some(id) = reply_to_id {
+            segments.push(id);
+        }
+    }
+
+    let mut body = serde_json::json!({
+        "type": "message",
+        "text": text,
+        "textFormat": "markdown",
+    });
+    if let Some(attachment) = attachment {
+        let mime = attachment
+            .mime_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        body["attachments"] = serde_json::json!([{
+            "contentType": mime,
+            "contentUrl": format!("data:{mime};base64,{}", STANDARD.encode(&attachment.data)),
+            "name": attachment.filename_or_default(),
+        }]);
+    }
+
+    let resp = client
+        .post(url)
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("teams send request failed: {e}"))?;
+
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        let status = resp.status();
+        let body_text = resp.text().await.unwrap_or_default();
+        Err(format!("teams send failed ({status}): {body_text}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn token_is_fetched_once_and_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .and(body_string_contains("client_id=app-123"))
+            .and(body_string_contains("client_secret=p%40ss"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "token_type": "Bearer",
+                "expires_in": 3600,
+                "access_token": "tok-1",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let cache = TokenCache::new(
+            "app-123".into(),
+            "p@ss".into(),
+            format!("{}/token", server.uri()),
+        );
+        let client = Client::new();
+        assert_eq!(cache.get(&client).await.unwrap(), "tok-1");
+        assert_eq!(cache.get(&client).await.unwrap(), "tok-1");
+    }
+
+    #[tokio::test]
+    async fn token_error_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid_client"))
+            .mount(&server)
+            .await;
+
+        let cache = TokenCache::new("a".into(), "b".into(), server.uri());
+        let err = cache.get(&Client::new()).await.unwrap_err();
+        assert!(err.contains("401"), "{err}");
+        assert!(err.contains("invalid_client"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn send_activity_posts_to_conversation_path() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/amer/v3/conversations/19:abc;messageid=1/activities/act-9",
+            ))
+            .and(header("authorization", "Bearer tok"))
+            .and(body_json(serde_json::json!({
+                "type": "message",
+                "text": "hi",
+                "textFormat": "markdown",
+            })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        send_activity(
+            &Client::new(),
+            "tok",
+            &format!("{}/amer/", server.uri()),
+            "19:abc;messageid=1",
+            Some("act-9"),
+            "hi",
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_activity_inlines_attachment() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v3/conversations/c1/activities"))
+            .and(body_json(serde_json::json!({
+                "type": "message",
+                "text": "chart",
+                "textFormat": "markdown",
+                "attachments": [{
+                    "contentType": "image/png",
+                    "contentUrl": "data:image/png;base64,iVBO",
+                    "name": "chart.png",
+                }],
+            })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let attachment =
+            MediaAttachment::new(opencrust_common::MediaKind::Photo, vec![0x89, 0x50, 0x4e])
+                .with_filename("chart.png")
+                .with_mime_type(Some("image/png".into()));
+        send_activity(
+            &Client::new(),
+            "tok",
+            &server.uri(),
+            "c1",
+            None,
+            "chart",
+            Some(&attachment),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_activity_reports_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("forbidden"))
+            .mount(&server)
+            .await;
+
+        let err = send_activity(&Client::new(), "tok", &server.uri(), "c1", None, "hi", None)
+            .await
+            .unwrap_err();
+        assert!(err.contains("403"), "{err}");
+
+        assert!(
+            send_activity(&Client::new(), "tok", "not a url", "c1", None, "hi", None)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Bot Framework request authentication.
+//!
+//! Every activity the Bot Connector posts to the webhook carries a JWT signed
+//! with one of the keys published in the Bot Framework OpenID metadata. The
+//! token must be issued by `https://api.botframework.com`, addressed to this
+//! bot's app ID, unexpired, and (when present) its `serviceurl` claim must
+//! match the activity's `serviceUrl` so a forged activity cannot make the bot
+//! send its access token elsewhere.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::Client;
+use ring::signature::{RSA_PKCS1_2048_8192_SHA256, RsaPublicKeyComponents};
+use serde::Deserialize;
+use tracing::info;
+
+/// OpenID metadata document that points at the Bot Framework signing keys.
+pub const OPENID_CONFIG_URL: &str =
+    "https://login.botframework.com/v1/.well-known/openidconfiguration";
+
+/// Issuer of tokens sent by the Bot Connector service.
+const ISSUER: &str = "https://api.botframework.com";
+
+/// Signing keys are refetched after this long, or when a token names an
+/// unknown key.
+const KEY_REFRESH: Duration = Duration::from_secs(24 * 3600);
+
+/// Allowed clock skew when checking `exp` and `nbf`.
+const CLOCK_SKEW_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Jwk {
+    kid: String,
+    n: String,
+    e: String,
+    /// Channel IDs this key may sign for. Empty means any.
+    #[serde(default)]
+    endorsements: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct OpenIdConfig {
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    aud: String,
+    exp: u64,
+    nbf: Option<u64>,
+    serviceurl: Option<String>,
+}
+
+/// Validates the `Authorization` header on inbound activities.
+pub struct BotFrameworkAuth {
+    client: Client,
+    app_id: String,
+    openid_url: String,
+    keys: Mutex<Option<(Instant, Vec<Jwk>)>>,
+}
+
+impl BotFrameworkAuth {
+    pub fn new(client: Client, app_id: String) -> Self {
+        Self {
+            client,
+            app_id,
+            openid_url: OPENID_CONFIG_URL.to_string(),
+            keys: Mutex::new(None),
+        }
+    }
+
+    /// Override the OpenID metadata URL (for tests).
+    pub fn with_openid_url(mut self, url: String) -> Self {
+        self.openid_url = url;
+        self
+    }
+
+    /// Check `authorization` for an activity from `channel_id` (e.g.
+    /// `"msteams"`) whose `serviceUrl` is `service_url`.
+    pub async fn verify(
+        &self,
+        authorization: &str,
+        service_url: &str,
+        channel_id: &str,
+    ) -> Result<(), String> {
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .ok_or("missing bearer token")?;
+        let header: Header = decode_part(token.split('.').next().unwrap_or(""))?;
+        if header.alg != "RS256" {
+            return Err(format!("unsupported token algorithm '{}'", header.alg));
+        }
+        let kid = header.kid.ok_or("token has no key id")?;
+        let key = self.key(&kid).await?;
+        if !key.endorsements.is_empty() && !key.endorsements.iter().any(|e| e == channel_id) {
+            return Err(format!("signing key is not endorsed for '{channel_id}'"));
+        }
+        verify_token(token, &key, &self.app_id, service_url, unix_now())
+    }
+
+    async fn key(&self, kid: &str) -> Result<Jwk, String> {
+        let cached = self
+            .keys
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(fetched, _)| fetched.elapsed() < KEY_REFRESH)
+            .and_then(|(_, keys)| keys.iter().find(|k| k.kid == kid).cloned());
+        if let Some(key) = cached {
+            return Ok(key);
+        }
+
+        let keys = self.fetch_keys().await?;
+        let key = keys.iter().find(|k| k.kid == kid).cloned();
+        *self.keys.lock().unwrap() = Some((Instant::now(), keys));
+        key.ok_or_else(|| format!("unknown signing key '{kid}'"))
+    }
+
+    async fn fetch_keys(&self) -> Result<Vec<Jwk>, String> {
+        let config: OpenIdConfig = self
+            .client
+            .get(&self.openid_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("failed to fetch Bot Framework OpenID metadata: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("invalid Bot Framework OpenID metadata: {e}"))?;
+        let set: JwkSet = self
+            .client
+            .get(&config.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("failed to fetch Bot Framework signing keys: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("invalid Bot Framework signing keys: {e}"))?;
+        info!(
+            "teams: loaded {} Bot Framework signing key(s)",
+            set.keys.len()
+        );
+        Ok(set.keys)
+    }
+}
+
+/// Check the signature and claims of `token` against `key` at time `now`.
+pub(crate) fn verify_token(
+    token: &str,
+    key: &Jwk,
+    app_id: &str,
+    service_url: &str,
+    now: u64,
+) -> Result<(), String> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(claims), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("malformed token".to_string());
+    };
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| "malformed token signature")?;
+    let n = URL_SAFE_NO_PAD
+        .decode(&key.n)
+        .map_err(|_| "malformed key")?;
+    let e = URL_SAFE_NO_PAD
+        .decode(&key.e)
+        .map_err(|_| "malformed key")?;
+    let signed = &token[..header.len() + 1 + claims.len()];
+    RsaPublicKeyComponents { n, e }
+        .verify(&RSA_PKCS1_2048_8192_SHA256, signed.as_bytes(), &signature)
+        .map_err(|_| "invalid token signature")?;
+
+    let claims: Claims = decode_part(claims)?;
+    if claims.iss != ISSUER {
+        return Err(format!("unexpected token issuer '{}'", claims.iss));
+    }
+    if claims.aud != app_id {
+        return Err("token audience does not match app_id".to_string());
+    }
+    if claims.exp + CLOCK_SKEW_SECS < now {
+        return Err("token expired".to_string());
+    }
+    if claims.nbf.is_some_and(|nbf| nbf > now + CLOCK_SKEW_SECS) {
+        return Err("token not yet valid".to_string());
+    }
+    if let Some(claimed) = &claims.serviceurl
+        && claimed.trim_end_matches('/') != service_url.trim_end_matches('/')
+    {
+        return Err("token serviceurl does not match activity".to_string());
+    }
+    Ok(())
+}
+
+fn decode_part<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| "malformed token".to_string())?;
+    serde_json::from_slice(&bytes).map_err(|_| "malformed token".to_string())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const SERVICE_URL: &str = "https://smba.trafficmanager.net/amer/";
+    const NOW: u64 = 1_800_000_000;
+
+    fn fixtures() -> serde_json::Value {
+        serde_json::from_str(include_str!("testdata/auth.json")).unwrap()
+    }
+
+    fn token(name: &str) -> String {
+        fixtures()[name].as_str().unwrap().to_string()
+    }
+
+    fn key() -> Jwk {
+        serde_json::from_value(fixtures()["jwks"]["keys"][0].clone()).unwrap()
+    }
+
+    #[test]
+    fn accepts_valid_token() {
+        assert_eq!(
+            verify_token(&token("valid"), &key(), "app-123", SERVICE_URL, NOW),
+            Ok(())
+        );
+        // Trailing slash differences are ignored.
+        assert!(
+            verify_token(
+                &token("valid"),
+                &key(),
+                "app-123",
+                SERVICE_URL.trim_end_matches('/'),
+                NOW
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_tokens() {
+        let cases = [
+            ("wrong_audience", "audience"),
+            ("expired", "expired"),
+            ("wrong_issuer", "issuer"),
+            ("tampered", "signature"),
+        ];
+        for (name, expected) in cases {
+            let err = verify_token(&token(name), &key(), "app-123", SERVICE_URL, NOW).unwrap_err();
+            assert!(err.contains(expected), "{name}: {err}");
+        }
+
+        let err = verify_token(
+            &token("valid"),
+            &key(),
+            "app-123",
+            "https://evil.example.com/",
+            NOW,
+        )
+        .unwrap_err();
+        assert!(err.contains("serviceurl"));
+
+        assert!(verify_token("a.b", &key(), "app-123", SERVICE_URL, NOW).is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_fetches_keys_and_checks_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/openid"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jwks_uri": format!("{}/keys", server.uri()),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/keys"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fixtures()["jwks"].clone()))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let auth = BotFrameworkAuth::new(Client::new(), "app-123".into())
+            .with_openid_url(format!("{}/openid", server.uri()));
+
+        let bearer = format!("Bearer {}", token("valid"));
+        assert_eq!(auth.verify(&bearer, SERVICE_URL, "msteams").await, Ok(()));
+        // Cached keys are reused.
+        assert_eq!(auth.verify(&bearer, SERVICE_URL, "msteams").await, Ok(()));
+
+        let err = auth
+            .verify(&bearer, SERVICE_URL, "webchat")
+            .await
+            .unwrap_err();
+        assert!(err.contains("endorsed"));
+
+        // An unknown key id triggers one refetch, then fails.
+        let err = auth
+            .verify(
+                &format!("Bearer {}", token("unknown_key")),
+                SERVICE_URL,
+                "msteams",
+            )
+            .await
+            .unwrap_err();
+        assert!(err.contains("unknown signing key"));
+
+        assert!(
+            auth.verify(&token("valid"), SERVICE_URL, "msteams")
+                .await
+                .is_err()
+        );
+    }
+}
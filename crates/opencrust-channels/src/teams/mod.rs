@@ -0,0 +1,629 @@
+pub mod api;
+pub mod auth;
+pub mod webhook;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::{info, warn};
+
+use crate::dedup::{InboundDedup, SeenFn};
+use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
+use api::TokenCache;
+use auth::BotFrameworkAuth;
+use opencrust_common::{Error, MediaAttachment, Message, MessageContent, Result};
+
+/// Callback invoked when the bot receives a message from Teams.
+///
+/// Arguments: `(user_id, user_name, conversation_id, service_url, text, is_group)`.
+/// * `user_id` is the sender's Teams ID (`29:...`).
+/// * `service_url` is the Bot Connector endpoint for the conversation; keep
+///   it with `conversation_id` to post into the conversation later.
+/// * `is_group` is `true` for group chats and team channels.
+///
+/// Return `Err("__blocked__")` to silently drop the message.
+pub type TeamsOnMessageFn = Arc<
+    dyn Fn(
+            String,
+            String,
+            String,
+            String,
+            String,
+            bool,
+        )
+            -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Group filter closure for Teams channels.
+/// Argument: `is_mentioned` — whether the bot was @-mentioned.
+/// Returns `true` if the message should be processed.
+pub type TeamsGroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
+
+/// Where to post activities for a conversation, as learned from inbound
+/// activities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConversationRef {
+    service_url: String,
+    conversation_id: String,
+}
+
+/// Personal (1:1) conversation references keyed by user ID, so proactive
+/// messages can reach users who have talked to the bot before.
+type ConversationRefs = Arc<Mutex<HashMap<String, ConversationRef>>>;
+
+/// Microsoft Teams channel speaking the Bot Framework REST protocol.
+///
+/// Inbound activities arrive on `POST /webhooks/teams`; replies and
+/// proactive messages are posted to the Bot Connector `serviceUrl`.
+pub struct TeamsChannel {
+    client: Client,
+    app_id: String,
+    app_password: String,
+    name: String,
+    display: String,
+    status: ChannelStatus,
+    on_message: TeamsOnMessageFn,
+    group_filter: TeamsGroupFilter,
+    auth: BotFrameworkAuth,
+    tokens: Arc<TokenCache>,
+    conversations: ConversationRefs,
+    dedup: InboundDedup,
+}
+
+impl TeamsChannel {
+    /// `tenant_id` is only needed for single-tenant bots.
+    pub fn new(
+        app_id: String,
+        app_password: String,
+        tenant_id: Option<String>,
+        on_message: TeamsOnMessageFn,
+    ) -> Self {
+        Self::with_group_filter(
+            app_id,
+            app_password,
+            tenant_id,
+            on_message,
+            Arc::new(|_| true),
+        )
+    }
+
+    pub fn with_group_filter(
+        app_id: String,
+        app_password: String,
+        tenant_id: Option<String>,
+        on_message: TeamsOnMessageFn,
+        group_filter: TeamsGroupFilter,
+    ) -> Self {
+        let client = Client::new();
+        let tenant = tenant_id.as_deref().unwrap_or(api::DEFAULT_TENANT);
+        Self {
+            auth: BotFrameworkAuth::new(client.clone(), app_id.clone()),
+            tokens: Arc::new(TokenCache::new(
+                app_id.clone(),
+                app_password.clone(),
+                api::token_url(tenant),
+            )),
+            client,
+            app_id,
+            app_password,
+            name: "teams".to_string(),
+            display: "Microsoft Teams".to_string(),
+            status: ChannelStatus::Disconnected,
+            on_message,
+            group_filter,
+            conversations: Arc::new(Mutex::new(HashMap::new())),
+            dedup: InboundDedup::default(),
+        }
+    }
+
+    /// Override the config key name for this channel instance.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Drop redelivered activities using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
+    /// Override the Azure AD token endpoint (e.g. to point at a mock server in tests).
+    pub fn with_token_url(mut self, url: String) -> Self {
+        self.tokens = Arc::new(TokenCache::new(
+            self.app_id.clone(),
+            self.app_password.clone(),
+            url,
+        ));
+        self
+    }
+
+    /// Override the Bot Framework OpenID metadata URL (for tests).
+    pub fn with_openid_url(mut self, url: String) -> Self {
+        self.auth = self.auth.with_openid_url(url);
+        self
+    }
+
+    /// Microsoft App ID of the bot registration.
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Check the `Authorization` header of an inbound activity.
+    pub async fn verify(
+        &self,
+        authorization: &str,
+        service_url: &str,
+        channel_id: &str,
+    ) -> std::result::Result<(), String> {
+        self.auth
+            .verify(authorization, service_url, channel_id)
+            .await
+    }
+
+    fn sender(&self) -> TeamsSender {
+        TeamsSender {
+            client: self.client.clone(),
+            tokens: Arc::clone(&self.tokens),
+            conversations: Arc::clone(&self.conversations),
+            name: self.name.clone(),
+        }
+    }
+
+    /// Handle one authenticated activity. Only `message` activities are
+    /// processed; the reply is threaded under the incoming activity.
+    pub async fn dispatch(&self, activity: &serde_json::Value) {
+        let Some(msg) = parse_message(activity) else {
+            return;
+        };
+        // The Bot Connector retries activities it did not see acknowledged.
+        let key = format!(
+            "{}:{}",
+            msg.conversation_id,
+            msg.activity_id.as_deref().unwrap_or_default()
+        );
+        if msg.activity_id.is_some() && !self.dedup.first_time(&key) {
+            info!("teams: duplicate activity {key}, skipping");
+            return;
+        }
+
+        if !msg.is_group {
+            self.conversations.lock().unwrap().insert(
+                msg.user_id.clone(),
+                ConversationRef {
+                    service_url: msg.service_url.clone(),
+                    conversation_id: msg.conversation_id.clone(),
+                },
+            );
+        }
+
+        if msg.is_group && !(self.group_filter)(msg.mentioned) {
+            return;
+        }
+        if msg.text.is_empty() {
+            return;
+        }
+
+        info!(
+            "teams: {} from {} ({}): {} chars",
+            if msg.is_group { "group" } else { "dm" },
+            msg.user_name,
+            msg.user_id,
+            msg.text.len()
+        );
+
+        let reply = match (self.on_message)(
+            msg.user_id.clone(),
+            msg.user_name.clone(),
+            msg.conversation_id.clone(),
+            msg.service_url.clone(),
+            msg.text.clone(),
+            msg.is_group,
+        )
+        .await
+        {
+            Ok(response) => response.text().to_string(),
+            Err(e) if e == "__blocked__" => return,
+            Err(e) => {
+                warn!("teams: error processing message: {e}");
+                "Sorry, an error occurred processing your message.".to_string()
+            }
+        };
+        if reply.is_empty() {
+            return;
+        }
+
+        let target = ConversationRef {
+            service_url: msg.service_url,
+            conversation_id: msg.conversation_id,
+        };
+        if let Err(e) = self
+            .sender()
+            .post(&target, msg.activity_id.as_deref(), &reply, None)
+            .await
+        {
+            warn!("teams: failed to send reply: {e}");
+        }
+    }
+}
+
+/// A `message` activity reduced to what the channel needs.
+#[derive(Debug, PartialEq, Eq)]
+struct IncomingMessage {
+    activity_id: Option<String>,
+    service_url: String,
+    conversation_id: String,
+    user_id: String,
+    user_name: String,
+    text: String,
+    is_group: bool,
+    mentioned: bool,
+}
+
+fn parse_message(activity: &serde_json::Value) -> Option<IncomingMessage> {
+    if activity.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return None;
+    }
+    let str_at = |ptr: &str| activity.pointer(ptr).and_then(|v| v.as_str());
+
+    let service_url = str_at("/serviceUrl")?.to_string();
+    let conversation_id = str_at("/conversation/id")?.to_string();
+    let user_id = str_at("/from/id")?.to_string();
+    let bot_id = str_at("/recipient/id").unwrap_or("");
+    let is_group = str_at("/conversation/conversationType").is_some_and(|t| t != "personal");
+
+    // Mentions of the bot show up as `<at>Name</at>` in the text; drop them
+    // so commands like "@bot /help" still parse.
+    let mut text = str_at("/text").unwrap_or("").to_string();
+    let mut mentioned = false;
+    for entity in activity
+        .get("entities")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if entity.get("type").and_then(|v| v.as_str()) != Some("mention")
+            || entity.pointer("/mentioned/id").and_then(|v| v.as_str()) != Some(bot_id)
+        {
+            continue;
+        }
+        mentioned = true;
+        if let Some(tag) = entity.get("text").and_then(|v| v.as_str()) {
+            text = text.replace(tag, "");
+        }
+    }
+
+    Some(IncomingMessage {
+        activity_id: str_at("/id").map(str::to_string),
+        service_url,
+        conversation_id,
+        user_name: str_at("/from/name").unwrap_or(&user_id).to_string(),
+        user_id,
+        text: text.trim().to_string(),
+        is_group,
+        mentioned,
+    })
+}
+
+// ── TeamsSender ──────────────────────────────────────────────────────────────
+
+/// Lightweight send-only handle for proactive messages.
+pub struct TeamsSender {
+    client: Client,
+    tokens: Arc<TokenCache>,
+    conversations: ConversationRefs,
+    name: String,
+}
+
+impl TeamsSender {
+    async fn post(
+        &self,
+        target: &ConversationRef,
+        reply_to_id: Option<&str>,
+        text: &str,
+        attachment: Option<&MediaAttachment>,
+    ) -> std::result::Result<(), String> {
+        let token = self.tokens.get(&self.client).await?;
+        api::send_activity(
+            &self.client,
+            &token,
+            &target.service_url,
+            &target.conversation_id,
+            reply_to_id,
+            text,
+            attachment,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl ChannelSender for TeamsSender {
+    fn channel_type(&self) -> &str {
+        "teams"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Needs `teams_service_url` and `teams_conversation_id` in the message
+    /// metadata, or a `teams_user_id` the bot has already heard from.
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        let (text, attachment) = match &message.content {
+            MessageContent::Text(t) => (t.clone(), None),
+            MessageContent::Attachment(attachment) => (
+                attachment.caption.clone().unwrap_or_default(),
+                Some(attachment),
+            ),
+            _ => {
+                return Err(Error::Channel(
+                    "only text and attachment messages are supported for teams send".into(),
+                ));
+            }
+        };
+
+        let metadata = |key: &str| message.metadata.get(key).and_then(|v| v.as_str());
+        let target = match (
+            metadata("teams_service_url"),
+            metadata("teams_conversation_id"),
+        ) {
+            (Some(service_url), Some(conversation_id)) => ConversationRef {
+                service_url: service_url.to_string(),
+                conversation_id: conversation_id.to_string(),
+            },
+            _ => {
+                let user_id = metadata("teams_user_id").ok_or_else(|| {
+                    Error::Channel(
+                        "missing teams_conversation_id or teams_user_id in message metadata".into(),
+                    )
+                })?;
+                self.conversations
+                    .lock()
+                    .unwrap()
+                    .get(user_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::Channel(format!(
+                            "no teams conversation known for user {user_id}; \
+                             they must message the bot first"
+                        ))
+                    })?
+            }
+        };
+
+        self.post(&target, None, &text, attachment)
+            .await
+            .map_err(|e| Error::Channel(format!("teams send failed: {e}")))
+    }
+}
+
+// ── ChannelLifecycle ─────────────────────────────────────────────────────────
+
+#[async_trait]
+impl ChannelLifecycle for TeamsChannel {
+    fn display_name(&self) -> &str {
+        &self.display
+    }
+
+    fn create_sender(&self) -> Box<dyn ChannelSender> {
+        Box::new(self.sender())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        // Teams is webhook-driven; fetching a token up front surfaces bad
+        // credentials at startup instead of on the first reply.
+        self.tokens
+            .get(&self.client)
+            .await
+            .map_err(|e| Error::Channel(format!("teams authentication failed: {e}")))?;
+        self.status = ChannelStatus::Connected;
+        info!("teams channel '{}' connected (webhook mode)", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.status = ChannelStatus::Disconnected;
+        info!("teams channel '{}' disconnected", self.name);
+        Ok(())
+    }
+
+    fn status(&self) -> ChannelStatus {
+        self.status.clone()
+    }
+}
+
+#[async_trait]
+impl ChannelSender for TeamsChannel {
+    fn channel_type(&self) -> &str {
+        "teams"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        self.sender().send_message(message).await
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn echo_callback() -> TeamsOnMessageFn {
+        Arc::new(|user_id, _name, _conv, _url, text, is_group| {
+            Box::pin(async move {
+                Ok(ChannelResponse::Text(format!(
+                    "{user_id}/{is_group}: {text}"
+                )))
+            })
+        })
+    }
+
+    fn activity(service_url: &str, conversation_type: &str, text: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "message",
+            "id": "act-1",
+            "channelId": "msteams",
+            "serviceUrl": service_url,
+            "from": { "id": "29:user", "name": "Alice" },
+            "recipient": { "id": "28:app-123", "name": "Crusty" },
+            "conversation": { "id": "conv-1", "conversationType": conversation_type },
+            "text": text,
+        })
+    }
+
+    async fn mock_token(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "expires_in": 3600,
+                "access_token": "tok",
+            })))
+            .mount(server)
+            .await;
+    }
+
+    fn channel(server: &MockServer, on_message: TeamsOnMessageFn) -> TeamsChannel {
+        TeamsChannel::with_group_filter(
+            "app-123".into(),
+            "secret".into(),
+            None,
+            on_message,
+            Arc::new(|mentioned| mentioned),
+        )
+        .with_token_url(format!("{}/token", server.uri()))
+    }
+
+    #[test]
+    fn parses_group_mention_and_strips_tag() {
+        let mut a = activity("https://svc/", "channel", "<at>Crusty</at> /help");
+        a["entities"] = serde_json::json!([{
+            "type": "mention",
+            "text": "<at>Crusty</at>",
+            "mentioned": { "id": "28:app-123", "name": "Crusty" },
+        }]);
+        let msg = parse_message(&a).unwrap();
+        assert_eq!(msg.text, "/help");
+        assert!(msg.is_group);
+        assert!(msg.mentioned);
+        assert_eq!(msg.user_name, "Alice");
+        assert_eq!(msg.activity_id.as_deref(), Some("act-1"));
+
+        let dm = parse_message(&activity("https://svc/", "personal", "hi")).unwrap();
+        assert!(!dm.is_group);
+        assert!(!dm.mentioned);
+
+        let mut update = activity("https://svc/", "personal", "hi");
+        update["type"] = "conversationUpdate".into();
+        assert_eq!(parse_message(&update), None);
+    }
+
+    #[tokio::test]
+    async fn dispatch_replies_in_thread() {
+        let server = MockServer::start().await;
+        mock_token(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/v3/conversations/conv-1/activities/act-1"))
+            .and(body_partial_json(
+                serde_json::json!({ "text": "29:user/false: hello" }),
+            ))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ch = channel(&server, echo_callback());
+        ch.dispatch(&activity(&server.uri(), "personal", "hello"))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn dispatch_applies_group_filter_and_blocks() {
+        let server = MockServer::start().await;
+        mock_token(&server).await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        // Not mentioned in a group chat.
+        let ch = channel(&server, echo_callback());
+        ch.dispatch(&activity(&server.uri(), "groupChat", "hello"))
+            .await;
+
+        // Blocked user.
+        let blocked: TeamsOnMessageFn =
+            Arc::new(|_, _, _, _, _, _| Box::pin(async { Err("__blocked__".to_string()) }));
+        let ch = channel(&server, blocked);
+        ch.dispatch(&activity(&server.uri(), "personal", "hello"))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn sender_uses_metadata_or_known_conversation() {
+        let server = MockServer::start().await;
+        mock_token(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/v3/conversations/conv-1/activities"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let ch = channel(&server, echo_callback());
+        let sender = ch.create_sender();
+
+        let mut msg = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("teams"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "reminder",
+        );
+        msg.metadata = serde_json::json!({ "teams_user_id": "29:user" });
+        assert!(sender.send_message(&msg).await.is_err());
+
+        // Once the user has written, the conversation is known.
+        ch.conversations.lock().unwrap().insert(
+            "29:user".into(),
+            ConversationRef {
+                service_url: server.uri(),
+                conversation_id: "conv-1".into(),
+            },
+        );
+        sender.send_message(&msg).await.unwrap();
+
+        msg.metadata = serde_json::json!({
+            "teams_service_url": server.uri(),
+            "teams_conversation_id": "conv-1",
+        });
+        sender.send_message(&msg).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn connect_fails_on_bad_credentials() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let mut ch = channel(&server, echo_callback());
+        assert!(ch.connect().await.is_err());
+        assert_eq!(ch.status(), ChannelStatus::Disconnected);
+    }
+}
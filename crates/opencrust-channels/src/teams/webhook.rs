@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use tracing::warn;
+
+use super::TeamsChannel;
+
+/// Shared state passed to the Teams webhook handler.
+pub type TeamsWebhookState = Arc<Vec<Arc<TeamsChannel>>>;
+
+/// POST /webhooks/teams — receives activities from the Bot Connector.
+///
+/// Validates the Bot Framework JWT in the `Authorization` header, then
+/// processes the activity in the background so the connector gets its
+/// response within its 15 second timeout.
+pub async fn teams_webhook(
+    State(channels): State<TeamsWebhookState>,
+    headers: HeaderMap,
+    Json(activity): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    // The bot's ID in Teams is its app ID prefixed with "28:".
+    let recipient = activity
+        .pointer("/recipient/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let recipient = recipient.strip_prefix("28:").unwrap_or(recipient);
+    let Some(channel) = channels
+        .iter()
+        .find(|ch| ch.app_id() == recipient)
+        .or_else(|| channels.first())
+        .cloned()
+    else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let service_url = activity
+        .get("serviceUrl")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let channel_id = activity
+        .get("channelId")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    if let Err(e) = channel.verify(authorization, service_url, channel_id).await {
+        warn!("teams: rejected activity: {e}");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    tokio::spawn(async move { channel.dispatch(&activity).await });
+    StatusCode::OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use axum::{Router, body::Body, routing::post};
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::teams::TeamsOnMessageFn;
+    use crate::traits::ChannelResponse;
+
+    fn fixtures() -> serde_json::Value {
+        serde_json::from_str(include_str!("testdata/auth.json")).unwrap()
+    }
+
+    async fn make_router() -> (Router, MockServer) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/openid"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jwks_uri": format!("{}/keys", server.uri()),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/keys"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fixtures()["jwks"].clone()))
+            .mount(&server)
+            .await;
+
+        let on_msg: TeamsOnMessageFn = Arc::new(|_, _, _, _, _, _| {
+            Box::pin(async { Ok(ChannelResponse::Text("reply".to_string())) })
+        });
+        let ch = TeamsChannel::new("app-123".into(), "secret".into(), None, on_msg)
+            .with_openid_url(format!("{}/openid", server.uri()))
+            .with_token_url(format!("{}/token", server.uri()));
+        let router = Router::new()
+            .route("/webhooks/teams", post(teams_webhook))
+            .with_state(Arc::new(vec![Arc::new(ch)]));
+        (router, server)
+    }
+
+    /// A `conversationUpdate` activity: authenticated but never answered.
+    fn activity() -> String {
+        serde_json::json!({
+            "type": "conversationUpdate",
+            "channelId": "msteams",
+            "serviceUrl": "https://smba.trafficmanager.net/amer/",
+            "recipient": { "id": "28:app-123" },
+            "conversation": { "id": "conv-1" },
+        })
+        .to_string()
+    }
+
+    async fn post_activity(router: Router, authorization: Option<String>) -> StatusCode {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/webhooks/teams")
+            .header("content-type", "application/json");
+        if let Some(auth) = authorization {
+            req = req.header("authorization", auth);
+        }
+        router
+            .oneshot(req.body(Body::from(activity())).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn valid_token_returns_200() {
+        let (router, _server) = make_router().await;
+        let token = fixtures()["valid"].as_str().unwrap().to_string();
+        assert_eq!(
+            post_activity(router, Some(format!("Bearer {token}"))).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_or_bad_token_returns_401() {
+        let (router, _server) = make_router().await;
+        assert_eq!(
+            post_activity(router.clone(), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let token = fixtures()["wrong_audience"].as_str().unwrap().to_string();
+        assert_eq!(
+            post_activity(router, Some(format!("Bearer {token}"))).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -1,6 +1,7 @@
+use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -8,39 +9,30 @@ use futures::future::BoxFuture;
 use teloxide::dispatching::UpdateFilterExt;
 use teloxide::error_handlers::ErrorHandler;
 use teloxide::prelude::*;
-use teloxide::types::{ChatAction, InputFile, ParseMode};
+use teloxide::types::{
+    ChatAction, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, InputFile,
+    MessageReactionUpdated, ParseMode, ReactionType,
+};
 use teloxide::{ApiError, RequestError, update_listeners};
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{broadcast, mpsc, watch};
 use tracing::{error, info, warn};
 
+use crate::dedup::{InboundDedup, SeenFn};
+use crate::edit::{EditRegeneration, OnEditFn};
+use crate::feedback::{OnReactionFn, ReactionFeedback, SentReply};
+use crate::queue::RateLimiter;
 use crate::telegram_fmt::to_telegram_markdown;
-use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
+use crate::traits::{
+    ChannelEvent, ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus, InlineButton,
+};
+use crate::typing::while_typing;
+use opencrust_common::{MediaAttachment, MediaKind, Message, MessageContent, Result};
 
 /// Closure that decides whether to process a group message.
 /// Argument: `is_mentioned` (whether the bot was mentioned).
 /// Returns `true` if the message should be processed.
 pub type GroupFilter = Arc<dyn Fn(bool) -> bool + Send + Sync>;
 
-/// Media attachment extracted from an incoming Telegram message.
-#[derive(Debug, Clone)]
-pub enum MediaAttachment {
-    Photo {
-        data: Vec<u8>,
-        caption: Option<String>,
-    },
-    Document {
-        data: Vec<u8>,
-        filename: Option<String>,
-        mime_type: Option<String>,
-        caption: Option<String>,
-    },
-    Voice {
-        data: Vec<u8>,
-        duration: u32,
-    },
-}
-
 /// Callback invoked when the bot receives a message.
 ///
 /// Arguments: `(chat_id, user_id_string, user_display_name, text, is_group, attachment, delta_sender)`.
@@ -92,6 +84,13 @@ pub struct TelegramChannel {
     bot_username: String,
     bot: Option<Bot>,
     shutdown_tx: Option<watch::Sender<bool>>,
+    event_tx: broadcast::Sender<ChannelEvent>,
+    edits: EditRegeneration,
+    feedback: ReactionFeedback,
+    dedup: InboundDedup,
+    /// Shared by interactive replies and the sender's outbound queue.
+    limiter: Arc<RateLimiter>,
+    buttons: ButtonData,
 }
 
 impl TelegramChannel {
@@ -104,6 +103,7 @@ impl TelegramChannel {
         on_message: OnMessageFn,
         group_filter: GroupFilter,
     ) -> Self {
+        let (event_tx, _) = broadcast::channel(256);
         Self {
             bot_token,
             name: "telegram".to_string(),
@@ -114,14 +114,49 @@ impl TelegramChannel {
             bot_username: String::new(),
             bot: None,
             shutdown_tx: None,
+            event_tx,
+            edits: EditRegeneration::default(),
+            feedback: ReactionFeedback::default(),
+            dedup: InboundDedup::default(),
+            limiter: Arc::new(RateLimiter::for_channel("telegram")),
+            buttons: ButtonData::default(),
         }
     }
 
+    /// Regenerate the reply when a user edits the last message the bot
+    /// answered in a chat. `on_edit` runs first to drop the replaced turn.
+    pub fn with_edit_regeneration(mut self, on_edit: OnEditFn) -> Self {
+        self.edits = EditRegeneration::new(on_edit);
+        self
+    }
+
+    /// Pass 👍/👎 reactions on the bot's replies to `on_reaction`.
+    pub fn with_reaction_feedback(mut self, on_reaction: OnReactionFn) -> Self {
+        self.feedback = ReactionFeedback::new(on_reaction);
+        self
+    }
+
+    /// Drop redelivered updates using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
     /// Override the config key name for this channel instance.
     pub fn with_name(mut self, name: String) -> Self {
         self.name = name;
         self
     }
+
+    /// Subscribe to channel events.
+    ///
+    /// Inline button presses arrive as [`ChannelEvent::ButtonPressed`]; the
+    /// button's data is also passed to `on_message` as the user's reply.
+    /// Regenerated edits arrive as [`ChannelEvent::MessageEdited`], and
+    /// reactions to the bot's replies as [`ChannelEvent::Reaction`].
+    pub fn subscribe(&self) -> broadcast::Receiver<ChannelEvent> {
+        self.event_tx.subscribe()
+    }
 }
 
 /// Download a file from Telegram by its file_id.
@@ -144,29 +179,9 @@ async fn download_telegram_file(
         .await
         .map_err(|e| format!("telegram file download failed: {e}"))?;
 
-    if let Some(len) = response.content_length()
-        && len > crate::MAX_DOWNLOAD_BYTES as u64
-    {
-        return Err(format!(
-            "telegram file too large: {len} bytes exceeds {} byte limit",
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    let bytes = response
-        .bytes()
+    crate::download::read_limited(response, crate::MAX_DOWNLOAD_BYTES)
         .await
-        .map_err(|e| format!("telegram file read failed: {e}"))?;
-
-    if bytes.len() > crate::MAX_DOWNLOAD_BYTES {
-        return Err(format!(
-            "telegram file too large: {} bytes exceeds {} byte limit",
-            bytes.len(),
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    Ok(bytes.to_vec())
+        .map_err(|e| format!("telegram {e}"))
 }
 
 /// Extracts chat ID and user info from a message.
@@ -226,84 +241,620 @@ fn is_bot_mentioned(msg: &teloxide::types::Message, bot_username: &str) -> bool
     false
 }
 
+/// Check if a message replies to one of the bot's own messages.
+fn is_reply_to_bot(msg: &teloxide::types::Message, bot_username: &str) -> bool {
+    msg.reply_to_message()
+        .and_then(|reply| reply.from.as_ref())
+        .is_some_and(|user| {
+            user.is_bot
+                && user
+                    .username
+                    .as_deref()
+                    .is_some_and(|u| u.eq_ignore_ascii_case(bot_username))
+        })
+}
+
+/// Remove `@bot_username` mentions so the agent sees only the request.
+fn strip_bot_mention(text: &str, bot_username: &str) -> String {
+    if bot_username.is_empty() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let after = &rest[at + 1..];
+        let is_mention = after
+            .get(..bot_username.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(bot_username))
+            && !after[bot_username.len()..]
+                .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_');
+        if is_mention {
+            out.push_str(&rest[..at]);
+            rest = &after[bot_username.len()..];
+            // Drop the space the mention leaves behind.
+            if out.is_empty() || out.ends_with(' ') {
+                rest = rest.strip_prefix(' ').unwrap_or(rest);
+            }
+        } else {
+            out.push_str(&rest[..=at]);
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
 /// Extract text and optional media attachment from a Telegram message.
 /// Returns None if the message type is unsupported.
 async fn extract_content(
     bot: &Bot,
     msg: &teloxide::types::Message,
 ) -> Option<(String, Option<MediaAttachment>)> {
-    // Photos (take the largest resolution)
-    if let Some(photo) = msg.photo().and_then(|p| p.last()) {
-        {
-            let caption = msg.caption().map(|c| c.to_string());
-            match download_telegram_file(bot, &photo.file.id).await {
-                Ok(data) => {
-                    let text = caption.clone().unwrap_or_default();
-                    return Some((text, Some(MediaAttachment::Photo { data, caption })));
+    let caption = msg.caption().map(|c| c.to_string());
+    let media = if let Some(photo) = msg.photo().and_then(|p| p.last()) {
+        // Photos (take the largest resolution)
+        Some((&photo.file.id, MediaKind::Photo, None, None, None))
+    } else if let Some(doc) = msg.document() {
+        Some((
+            &doc.file.id,
+            MediaKind::Document,
+            doc.file_name.clone(),
+            doc.mime_type.as_ref().map(|m| m.to_string()),
+            None,
+        ))
+    } else if let Some(voice) = msg.voice() {
+        Some((
+            &voice.file.id,
+            MediaKind::Voice,
+            None,
+            voice.mime_type.as_ref().map(|m| m.to_string()),
+            Some(voice.duration.seconds()),
+        ))
+    } else {
+        msg.video().map(|video| {
+            (
+                &video.file.id,
+                MediaKind::Video,
+                video.file_name.clone(),
+                video.mime_type.as_ref().map(|m| m.to_string()),
+                Some(video.duration.seconds()),
+            )
+        })
+    };
+
+    if let Some((file_id, kind, filename, mime_type, duration)) = media {
+        return match download_telegram_file(bot, file_id).await {
+            Ok(data) => {
+                let attachment = MediaAttachment {
+                    filename,
+                    duration_secs: duration,
+                    ..MediaAttachment::new(kind, data)
+                        .with_mime_type(mime_type)
+                        .with_caption(caption.clone())
+                };
+                Some((caption.unwrap_or_default(), Some(attachment)))
+            }
+            Err(e) => {
+                warn!("telegram: failed to download {kind:?}: {e}");
+                None
+            }
+        };
+    }
+
+    // Plain text
+    if let Some(text) = msg.text() {
+        return Some((text.to_string(), None));
+    }
+
+    // Unsupported message type
+    None
+}
+
+/// One incoming message (or button press) to hand to the agent.
+struct Turn {
+    chat_id: ChatId,
+    user_id: String,
+    user_name: String,
+    text: String,
+    is_group: bool,
+    attachment: Option<MediaAttachment>,
+}
+
+/// Run `on_message` for a turn, streaming partial replies into an edited
+/// message, then deliver the final response and record it for reaction
+/// feedback. Every send and edit waits on `limiter`, the channel's outbound
+/// rate limits. Returns whether the agent answered (the message was not
+/// blocked and did not fail).
+async fn run_turn(
+    bot: &Bot,
+    typing: &dyn ChannelSender,
+    on_message: &OnMessageFn,
+    feedback: &ReactionFeedback,
+    limiter: &RateLimiter,
+    buttons: &ButtonData,
+    turn: Turn,
+) -> bool {
+    let chat_id = turn.chat_id;
+    let chat_key = chat_id.0.to_string();
+    let (asker_id, is_group) = (turn.user_id.clone(), turn.is_group);
+
+    // Create streaming channel
+    let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
+
+    // Spawn callback
+    let callback_handle = tokio::spawn({
+        let on_message = Arc::clone(on_message);
+        async move {
+            on_message(
+                chat_id.0,
+                turn.user_id,
+                turn.user_name,
+                turn.text,
+                turn.is_group,
+                turn.attachment,
+                Some(delta_tx),
+            )
+            .await
+        }
+    });
+
+    // Consume streaming deltas and edit message.
+    // Buffer for 1s before sending the first message so short
+    // responses appear as a single formatted message instead of
+    // flashing the first word then replacing it.
+    let mut accumulated = String::new();
+    let mut msg_id: Option<teloxide::types::MessageId> = None;
+    let mut last_edit = tokio::time::Instant::now();
+    let mut first_delta_at: Option<tokio::time::Instant> = None;
+
+    // Keep "typing..." up while the agent works, including tool loops.
+    let result = while_typing(typing, &chat_id.0.to_string(), async {
+        while let Some(text) = delta_rx.recv().await {
+            accumulated.push_str(&text);
+            if first_delta_at.is_none() {
+                first_delta_at = Some(tokio::time::Instant::now());
+            }
+
+            if msg_id.is_none() {
+                // Only send after 1s buffer period
+                if first_delta_at.unwrap().elapsed() >= Duration::from_secs(1) {
+                    limiter.acquire(&chat_key).await;
+                    match bot.send_message(chat_id, &accumulated).await {
+                        Ok(sent) => {
+                            msg_id = Some(sent.id);
+                            last_edit = tokio::time::Instant::now();
+                        }
+                        Err(e) => {
+                            error!("failed to send streaming message: {e}");
+                            break;
+                        }
+                    }
                 }
+            } else if last_edit.elapsed() >= Duration::from_millis(1000)
+                && let Some(id) = msg_id
+            {
+                limiter.acquire(&chat_key).await;
+                let _ = bot.edit_message_text(chat_id, id, &accumulated).await;
+                last_edit = tokio::time::Instant::now();
+            }
+        }
+
+        callback_handle
+            .await
+            .unwrap_or_else(|e| Err(format!("task panic: {e}")))
+    })
+    .await;
+    let answered = result.is_ok();
+    // The delivered reply and its message id, for reaction feedback.
+    let mut delivered: Option<(teloxide::types::MessageId, String)> = None;
+
+    match result {
+        Ok(ChannelResponse::Voice {
+            text: final_text,
+            audio,
+        }) => {
+            // Delete the streaming placeholder (if any) and send voice
+            if let Some(id) = msg_id {
+                let _ = bot.delete_message(chat_id, id).await;
+            }
+            limiter.acquire(&chat_key).await;
+            let sent = match bot
+                .send_voice(chat_id, InputFile::memory(audio))
+                .caption(&final_text)
+                .await
+            {
+                Ok(sent) => Some(sent),
                 Err(e) => {
-                    warn!("telegram: failed to download photo: {e}");
-                    return None;
+                    warn!("telegram send_voice failed, falling back to text: {e}");
+                    limiter.acquire(&chat_key).await;
+                    bot.send_message(chat_id, &final_text).await.ok()
+                }
+            };
+            delivered = sent.map(|sent| (sent.id, final_text));
+        }
+        Ok(
+            response @ (ChannelResponse::Text(_)
+            | ChannelResponse::Buttons { .. }
+            | ChannelResponse::ToolResults { .. }
+            | ChannelResponse::Reasoning { .. }),
+        ) => {
+            let keyboard = match &response {
+                ChannelResponse::Buttons { buttons: rows, .. } => {
+                    Some(inline_keyboard(rows, buttons))
+                }
+                _ => None,
+            };
+            let final_text = response.text();
+            let formatted = to_telegram_markdown(final_text);
+            limiter.acquire(&chat_key).await;
+            if let Some(id) = msg_id {
+                // Final edit with MarkdownV2 formatting
+                let mut edit = bot
+                    .edit_message_text(chat_id, id, &formatted)
+                    .parse_mode(ParseMode::MarkdownV2);
+                if let Some(keyboard) = &keyboard {
+                    edit = edit.reply_markup(keyboard.clone());
+                }
+                if edit.await.is_err() {
+                    // Fallback: plain text
+                    limiter.acquire(&chat_key).await;
+                    let mut plain = bot.edit_message_text(chat_id, id, final_text);
+                    if let Some(keyboard) = keyboard {
+                        plain = plain.reply_markup(keyboard);
+                    }
+                    let _ = plain.await;
+                }
+                delivered = Some((id, final_text.to_string()));
+            } else {
+                // No streaming happened (command response) - send directly
+                let mut send = bot
+                    .send_message(chat_id, &formatted)
+                    .parse_mode(ParseMode::MarkdownV2);
+                if let Some(keyboard) = &keyboard {
+                    send = send.reply_markup(keyboard.clone());
                 }
+                let sent = match send.await {
+                    Ok(sent) => Some(sent),
+                    Err(_) => {
+                        // Fallback: plain text
+                        limiter.acquire(&chat_key).await;
+                        let mut plain = bot.send_message(chat_id, final_text);
+                        if let Some(keyboard) = keyboard {
+                            plain = plain.reply_markup(keyboard);
+                        }
+                        plain.await.ok()
+                    }
+                };
+                delivered = sent.map(|sent| (sent.id, final_text.to_string()));
+            }
+        }
+        Err(e) if e == "__blocked__" => {
+            // Silently drop - unauthorized user
+        }
+        Err(e) => {
+            limiter.acquire(&chat_key).await;
+            if let Some(id) = msg_id {
+                let _ = bot
+                    .edit_message_text(chat_id, id, format!("Sorry, an error occurred: {e}"))
+                    .await;
+            } else {
+                warn!("agent error for telegram chat {}: {e}", chat_id);
+                let _ = bot
+                    .send_message(chat_id, format!("Sorry, an error occurred: {e}"))
+                    .await;
             }
         }
     }
 
-    // Documents
-    if let Some(doc) = msg.document() {
-        let caption = msg.caption().map(|c| c.to_string());
-        match download_telegram_file(bot, &doc.file.id).await {
-            Ok(data) => {
-                let text = caption.clone().unwrap_or_default();
-                let filename = doc.file_name.clone();
-                let mime_type = doc.mime_type.as_ref().map(|m| m.to_string());
-                return Some((
-                    text,
-                    Some(MediaAttachment::Document {
-                        data,
-                        filename,
-                        mime_type,
-                        caption,
-                    }),
-                ));
-            }
-            Err(e) => {
-                warn!("telegram: failed to download document: {e}");
-                return None;
-            }
+    if let Some((id, text)) = delivered {
+        feedback.record(
+            &id.0.to_string(),
+            SentReply {
+                chat_id: chat_id.0.to_string(),
+                thread_id: None,
+                user_id: asker_id,
+                is_group,
+                text,
+            },
+        );
+    }
+    answered
+}
+
+/// State shared by the message and edited-message handlers.
+#[derive(Clone)]
+struct MessageContext {
+    on_message: OnMessageFn,
+    group_filter: GroupFilter,
+    bot_username: String,
+    event_tx: broadcast::Sender<ChannelEvent>,
+    edits: EditRegeneration,
+    feedback: ReactionFeedback,
+    dedup: InboundDedup,
+    typing: Arc<dyn ChannelSender>,
+    limiter: Arc<RateLimiter>,
+    buttons: ButtonData,
+}
+
+/// Filter an incoming message and run it as a turn. An edited message is
+/// only run again when it is the last message answered in the chat, after the
+/// `on_edit` hook has dropped the turn it replaces.
+async fn handle_message(
+    bot: &Bot,
+    context: &MessageContext,
+    msg: teloxide::types::Message,
+    is_edit: bool,
+) {
+    let Some((chat_id_raw, user_id, user_name)) = extract_message_info(&msg) else {
+        return;
+    };
+    let chat_key = chat_id_raw.to_string();
+    let message_id = msg.id.0.to_string();
+    // Each edit is a new delivery of the same message id.
+    let edit_date = msg.edit_date().map(|d| d.timestamp()).unwrap_or(0);
+    if !context
+        .dedup
+        .first_time(&format!("{chat_key}:{message_id}:{edit_date}"))
+    {
+        return;
+    }
+    let on_edit = match is_edit {
+        true => match context.edits.hook_for(&chat_key, &message_id) {
+            Some(on_edit) => Some(on_edit),
+            None => return,
+        },
+        false => None,
+    };
+
+    // Extract content (text + optional media)
+    let Some((text, attachment)) = extract_content(bot, &msg).await else {
+        return;
+    };
+
+    // Group filtering: check policy before processing
+    let is_group = chat_id_raw < 0;
+    let text = if is_group {
+        let is_mentioned = is_bot_mentioned(&msg, &context.bot_username)
+            || is_reply_to_bot(&msg, &context.bot_username);
+        if !(context.group_filter)(is_mentioned) {
+            return;
         }
+        strip_bot_mention(&text, &context.bot_username)
+    } else {
+        text
+    };
+
+    // ChatId wrapper for teloxide calls
+    let chat_id = ChatId(chat_id_raw);
+
+    let kind = match attachment.as_ref().map(|a| a.kind) {
+        Some(MediaKind::Photo) => "photo",
+        Some(MediaKind::Document) => "document",
+        Some(MediaKind::Voice) => "voice",
+        Some(MediaKind::Video) => "video",
+        None => "text",
+    };
+    info!(
+        "telegram {kind}{} from {} [uid={}] (chat {}): {} chars",
+        if is_edit { " edit" } else { "" },
+        user_name,
+        user_id,
+        chat_id,
+        text.len()
+    );
+
+    if let Some(on_edit) = on_edit {
+        let _ = context.event_tx.send(ChannelEvent::MessageEdited {
+            chat_id: chat_key.clone(),
+            user_id: user_id.clone(),
+            message_id: message_id.clone(),
+            text: text.clone(),
+        });
+        on_edit(chat_key.clone(), None, user_id.clone(), is_group).await;
     }
 
-    // Voice messages
-    if let Some(voice) = msg.voice() {
-        match download_telegram_file(bot, &voice.file.id).await {
-            Ok(data) => {
-                let duration = voice.duration.seconds();
-                return Some((
-                    String::new(),
-                    Some(MediaAttachment::Voice { data, duration }),
-                ));
-            }
-            Err(e) => {
-                warn!("telegram: failed to download voice: {e}");
-                return None;
-            }
+    let answered = run_turn(
+        bot,
+        context.typing.as_ref(),
+        &context.on_message,
+        &context.feedback,
+        &context.limiter,
+        &context.buttons,
+        Turn {
+            chat_id,
+            user_id,
+            user_name,
+            text,
+            is_group,
+            attachment,
+        },
+    )
+    .await;
+    if answered {
+        context.edits.record(&chat_key, &message_id);
+    }
+}
+
+/// A pressed inline button, extracted from a callback query.
+#[derive(Debug, PartialEq)]
+struct ButtonPress {
+    chat_id: ChatId,
+    message_id: teloxide::types::MessageId,
+    user_id: String,
+    user_name: String,
+    data: String,
+}
+
+/// Returns None for presses that carry no data or whose message is gone.
+fn parse_button_press(query: &CallbackQuery, buttons: &ButtonData) -> Option<ButtonPress> {
+    let message = query.message.as_ref()?;
+    let key = query.data.as_deref()?;
+    Some(ButtonPress {
+        chat_id: message.chat().id,
+        message_id: message.id(),
+        user_id: query.from.id.0.to_string(),
+        user_name: query.from.first_name.clone(),
+        data: buttons
+            .data_for(key)
+            .or_else(|| pressed_label(query, key))
+            .unwrap_or_else(|| key.to_string()),
+    })
+}
+
+/// Label of the pressed button, for keys sent before a restart that the
+/// channel no longer remembers.
+fn pressed_label(query: &CallbackQuery, key: &str) -> Option<String> {
+    query
+        .regular_message()?
+        .reply_markup()?
+        .inline_keyboard
+        .iter()
+        .flatten()
+        .find(|button| matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(data) if data == key))
+        .map(|button| button.text.clone())
+}
+
+/// Acknowledge a button press, remove the keyboard so the choice cannot be
+/// made twice, and hand the button's data to the agent as the user's reply.
+async fn handle_button_press(bot: &Bot, context: &MessageContext, query: CallbackQuery) {
+    // Stops the loading spinner on the user's client.
+    let _ = bot.answer_callback_query(query.id.clone()).await;
+
+    let Some(press) = parse_button_press(&query, &context.buttons) else {
+        return;
+    };
+    let _ = bot
+        .edit_message_reply_markup(press.chat_id, press.message_id)
+        .await;
+
+    info!(
+        "telegram button press from {} [uid={}] (chat {}): {}",
+        press.user_name, press.user_id, press.chat_id, press.data
+    );
+    let _ = context.event_tx.send(ChannelEvent::ButtonPressed {
+        chat_id: press.chat_id.0.to_string(),
+        user_id: press.user_id.clone(),
+        user_name: press.user_name.clone(),
+        data: press.data.clone(),
+    });
+
+    run_turn(
+        bot,
+        context.typing.as_ref(),
+        &context.on_message,
+        &context.feedback,
+        &context.limiter,
+        &context.buttons,
+        Turn {
+            chat_id: press.chat_id,
+            user_id: press.user_id,
+            user_name: press.user_name,
+            text: press.data,
+            is_group: press.chat_id.0 < 0,
+            attachment: None,
+        },
+    )
+    .await;
+}
+
+/// Emoji the user added in a reaction update (custom and paid reactions
+/// are skipped).
+fn added_emojis(update: &MessageReactionUpdated) -> Vec<String> {
+    update
+        .new_reaction
+        .iter()
+        .filter(|r| !update.old_reaction.contains(r))
+        .filter_map(|r| match r {
+            ReactionType::Emoji { emoji } => Some(emoji.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Report reactions to the bot's replies and pass 👍/👎 on as feedback.
+async fn handle_reaction(context: &MessageContext, update: &MessageReactionUpdated) {
+    let Some(user) = update.user().filter(|u| !u.is_bot) else {
+        return;
+    };
+    let chat_id = update.chat.id.0.to_string();
+    let message_id = update.message_id.0.to_string();
+    let Some(reply) = context.feedback.reply(&chat_id, &message_id) else {
+        return;
+    };
+    for emoji in added_emojis(update) {
+        info!(
+            "telegram reaction {emoji} from [uid={}] (chat {chat_id})",
+            user.id
+        );
+        let _ = context.event_tx.send(ChannelEvent::Reaction {
+            chat_id: chat_id.clone(),
+            user_id: user.id.0.to_string(),
+            message_id: message_id.clone(),
+            emoji: emoji.clone(),
+        });
+        context.feedback.react(reply.clone(), &emoji).await;
+    }
+}
+
+/// Number of sent buttons remembered before the oldest are forgotten.
+const MAX_BUTTONS: usize = 4096;
+
+/// Full data of the inline buttons this channel sent. Telegram rejects
+/// callback data longer than 64 bytes, so each button carries a short key
+/// and a press is mapped back to its data here. Clones share the same record.
+#[derive(Clone, Default)]
+struct ButtonData {
+    inner: Arc<Mutex<ButtonRecord>>,
+}
+
+#[derive(Default)]
+struct ButtonRecord {
+    next: u64,
+    sent: VecDeque<(String, String)>,
+}
+
+impl ButtonData {
+    /// Remember `data` and return the short key to send as callback data.
+    fn key_for(&self, data: &str) -> String {
+        let mut record = self.inner.lock().unwrap();
+        let key = format!("b{}", record.next);
+        record.next += 1;
+        record.sent.push_back((key.clone(), data.to_string()));
+        if record.sent.len() > MAX_BUTTONS {
+            record.sent.pop_front();
         }
+        key
     }
 
-    // Plain text
-    if let Some(text) = msg.text() {
-        return Some((text.to_string(), None));
+    /// The data of the button sent with `key`, if it is still remembered.
+    fn data_for(&self, key: &str) -> Option<String> {
+        let record = self.inner.lock().unwrap();
+        record
+            .sent
+            .iter()
+            .find(|(sent, _)| sent == key)
+            .map(|(_, data)| data.clone())
     }
+}
 
-    // Unsupported message type
-    None
+/// Build a Telegram inline keyboard whose buttons carry short keys from
+/// `buttons` instead of their full data.
+fn inline_keyboard(rows: &[Vec<InlineButton>], buttons: &ButtonData) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(rows.iter().map(|row| {
+        row.iter()
+            .map(|button| {
+                InlineKeyboardButton::callback(button.text.clone(), buttons.key_for(&button.data))
+            })
+            .collect::<Vec<_>>()
+    }))
 }
 
 /// Lightweight send-only handle for Telegram. Holds a pre-built `Bot` instance.
 pub struct TelegramSender {
     bot: Bot,
     name: String,
+    limiter: Arc<RateLimiter>,
+    buttons: ButtonData,
 }
 
 #[async_trait]
@@ -316,8 +867,16 @@ impl ChannelSender for TelegramSender {
         &self.name
     }
 
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        Some(Arc::clone(&self.limiter))
+    }
+
     async fn send_message(&self, message: &Message) -> Result<()> {
-        telegram_send_message(&self.bot, message).await
+        telegram_send_message(&self.bot, &self.buttons, message).await
+    }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        telegram_send_typing(&self.bot, channel_ref).await
     }
 }
 
@@ -331,6 +890,8 @@ impl ChannelLifecycle for TelegramChannel {
         Box::new(TelegramSender {
             bot: Bot::new(&self.bot_token),
             name: self.name.clone(),
+            limiter: Arc::clone(&self.limiter),
+            buttons: self.buttons.clone(),
         })
     }
 
@@ -355,213 +916,71 @@ impl ChannelLifecycle for TelegramChannel {
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
         self.shutdown_tx = Some(shutdown_tx);
 
-        let on_message = Arc::clone(&self.on_message);
-        let group_filter = Arc::clone(&self.group_filter);
-        let bot_username = self.bot_username.clone();
+        let context = MessageContext {
+            on_message: Arc::clone(&self.on_message),
+            group_filter: Arc::clone(&self.group_filter),
+            bot_username: self.bot_username.clone(),
+            event_tx: self.event_tx.clone(),
+            edits: self.edits.clone(),
+            feedback: self.feedback.clone(),
+            dedup: self.dedup.clone(),
+            typing: Arc::from(self.create_sender()),
+            limiter: Arc::clone(&self.limiter),
+            buttons: self.buttons.clone(),
+        };
 
         tokio::spawn(async move {
-            let handler = Update::filter_message().endpoint(
+            let messages = Update::filter_message().endpoint({
+                let context = context.clone();
                 move |bot: Bot, msg: teloxide::types::Message| {
-                    let on_message = Arc::clone(&on_message);
-                    let group_filter = Arc::clone(&group_filter);
-                    let bot_username = bot_username.clone();
+                    let context = context.clone();
                     async move {
-                        let (chat_id_raw, user_id, user_name) =
-                            match extract_message_info(&msg) {
-                                Some(info) => info,
-                                None => return respond(()),
-                            };
-
-                        // Extract content (text + optional media)
-                        let (text, attachment) = match extract_content(&bot, &msg).await {
-                            Some(content) => content,
-                            None => return respond(()),
-                        };
-
-                        // Group filtering: check policy before processing
-                        let is_group = chat_id_raw < 0;
-                        if is_group {
-                            let is_mentioned = is_bot_mentioned(&msg, &bot_username);
-                            if !group_filter(is_mentioned) {
-                                return respond(());
-                            }
-                        }
+                        handle_message(&bot, &context, msg, false).await;
+                        respond(())
+                    }
+                }
+            });
 
-                        // ChatId wrapper for teloxide calls
-                        let chat_id = ChatId(chat_id_raw);
-
-                        let kind = match &attachment {
-                            Some(MediaAttachment::Photo { .. }) => "photo",
-                            Some(MediaAttachment::Document { .. }) => "document",
-                            Some(MediaAttachment::Voice { .. }) => "voice",
-                            None => "text",
-                        };
-                        info!(
-                            "telegram {kind} from {} [uid={}] (chat {}): {} chars",
-                            user_name,
-                            user_id,
-                            chat_id,
-                            text.len()
-                        );
-
-                        // Send typing indicator
-                        let _ = bot.send_chat_action(chat_id, ChatAction::Typing).await;
-
-                        // Create streaming channel
-                        let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
-
-                        // Spawn callback
-                        let callback_handle = tokio::spawn({
-                            let on_message = Arc::clone(&on_message);
-                            let user_id = user_id.clone();
-                            let user_name = user_name.clone();
-                            let text = text.clone();
-                            async move {
-                                on_message(
-                                    chat_id.0,
-                                    user_id,
-                                    user_name,
-                                    text,
-                                    is_group,
-                                    attachment,
-                                    Some(delta_tx),
-                                )
-                                .await
-                            }
-                        });
-
-                        // Consume streaming deltas and edit message.
-                        // Buffer for 1s before sending the first message so short
-                        // responses appear as a single formatted message instead of
-                        // flashing the first word then replacing it.
-                        let mut accumulated = String::new();
-                        let mut msg_id: Option<teloxide::types::MessageId> = None;
-                        let mut last_edit = tokio::time::Instant::now();
-                        let mut first_delta_at: Option<tokio::time::Instant> = None;
-
-                        loop {
-                            tokio::select! {
-                                delta = delta_rx.recv() => {
-                                    match delta {
-                                        Some(text) => {
-                                            accumulated.push_str(&text);
-                                            if first_delta_at.is_none() {
-                                                first_delta_at = Some(tokio::time::Instant::now());
-                                            }
-
-                                            if msg_id.is_none() {
-                                                // Only send after 1s buffer period
-                                                if first_delta_at.unwrap().elapsed() >= Duration::from_secs(1) {
-                                                    match bot.send_message(chat_id, &accumulated).await {
-                                                        Ok(sent) => {
-                                                            msg_id = Some(sent.id);
-                                                            last_edit = tokio::time::Instant::now();
-                                                        }
-                                                        Err(e) => {
-                                                            error!("failed to send streaming message: {e}");
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-                                            } else if last_edit.elapsed() >= Duration::from_millis(1000)
-                                                && let Some(id) = msg_id
-                                            {
-                                                let _ = bot
-                                                    .edit_message_text(chat_id, id, &accumulated)
-                                                    .await;
-                                                last_edit = tokio::time::Instant::now();
-                                            }
-                                        }
-                                        None => break, // Sender dropped - callback finished
-                                    }
-                                }
-                                _ = tokio::time::sleep(Duration::from_secs(4)) => {
-                                    // Keep typing indicator alive during pauses (e.g. tool execution)
-                                    let _ = bot.send_chat_action(chat_id, ChatAction::Typing).await;
-                                }
-                            }
-                        }
+            let edits = Update::filter_edited_message().endpoint({
+                let context = context.clone();
+                move |bot: Bot, msg: teloxide::types::Message| {
+                    let context = context.clone();
+                    async move {
+                        handle_message(&bot, &context, msg, true).await;
+                        respond(())
+                    }
+                }
+            });
 
-                        // Get callback result
-                        let result = callback_handle
-                            .await
-                            .unwrap_or_else(|e| Err(format!("task panic: {e}")));
-
-                        match result {
-                            Ok(ChannelResponse::Voice { text: final_text, audio }) => {
-                                // Delete the streaming placeholder (if any) and send voice
-                                if let Some(id) = msg_id {
-                                    let _ = bot.delete_message(chat_id, id).await;
-                                }
-                                if let Err(e) = bot
-                                    .send_voice(chat_id, InputFile::memory(audio))
-                                    .caption(&final_text)
-                                    .await
-                                {
-                                    warn!("telegram send_voice failed, falling back to text: {e}");
-                                    let _ = bot.send_message(chat_id, &final_text).await;
-                                }
-                            }
-                            Ok(ChannelResponse::Text(final_text)) => {
-                                if let Some(id) = msg_id {
-                                    // Final edit with MarkdownV2 formatting
-                                    let formatted = to_telegram_markdown(&final_text);
-                                    let edit_result = bot
-                                        .edit_message_text(chat_id, id, &formatted)
-                                        .parse_mode(ParseMode::MarkdownV2)
-                                        .await;
-                                    if edit_result.is_err() {
-                                        // Fallback: plain text
-                                        let _ = bot
-                                            .edit_message_text(chat_id, id, &final_text)
-                                            .await;
-                                    }
-                                } else {
-                                    // No streaming happened (command response) - send directly
-                                    let formatted = to_telegram_markdown(&final_text);
-                                    let send_result = bot
-                                        .send_message(chat_id, &formatted)
-                                        .parse_mode(ParseMode::MarkdownV2)
-                                        .await;
-                                    if send_result.is_err() {
-                                        // Fallback: plain text
-                                        let _ =
-                                            bot.send_message(chat_id, &final_text).await;
-                                    }
-                                }
-                            }
-                            Err(e) if e == "__blocked__" => {
-                                // Silently drop - unauthorized user
-                            }
-                            Err(e) => {
-                                if let Some(id) = msg_id {
-                                    let _ = bot
-                                        .edit_message_text(
-                                            chat_id,
-                                            id,
-                                            format!("Sorry, an error occurred: {e}"),
-                                        )
-                                        .await;
-                                } else {
-                                    warn!(
-                                        "agent error for telegram chat {}: {e}",
-                                        chat_id
-                                    );
-                                    let _ = bot
-                                        .send_message(
-                                            chat_id,
-                                            format!("Sorry, an error occurred: {e}"),
-                                        )
-                                        .await;
-                                }
-                            }
-                        }
+            let button_presses = Update::filter_callback_query().endpoint({
+                let context = context.clone();
+                move |bot: Bot, query: CallbackQuery| {
+                    let context = context.clone();
+                    async move {
+                        handle_button_press(&bot, &context, query).await;
+                        respond(())
+                    }
+                }
+            });
 
+            // Registering this branch also subscribes the poller to
+            // `message_reaction` updates, which Telegram leaves out by default.
+            let reactions = Update::filter_message_reaction_updated().endpoint(
+                move |reaction: MessageReactionUpdated| {
+                    let context = context.clone();
+                    async move {
+                        handle_reaction(&context, &reaction).await;
                         respond(())
                     }
                 },
             );
 
+            let handler = dptree::entry()
+                .branch(messages)
+                .branch(edits)
+                .branch(button_presses)
+                .branch(reactions);
+
             let listener = update_listeners::polling_default(bot.clone()).await;
 
             let mut dispatcher = Dispatcher::builder(bot, handler)
@@ -624,12 +1043,52 @@ impl ChannelSender for TelegramChannel {
             .bot
             .as_ref()
             .ok_or_else(|| opencrust_common::Error::Channel("telegram bot not connected".into()))?;
-        telegram_send_message(bot, message).await
+        telegram_send_message(bot, &self.buttons, message).await
+    }
+
+    async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+        let bot = self
+            .bot
+            .as_ref()
+            .ok_or_else(|| opencrust_common::Error::Channel("telegram bot not connected".into()))?;
+        telegram_send_typing(bot, channel_ref).await
+    }
+}
+
+/// Show "typing..." in the chat `channel_ref` (a numeric chat id).
+async fn telegram_send_typing(bot: &Bot, channel_ref: &str) -> Result<()> {
+    let chat_id: i64 = channel_ref.parse().map_err(|_| {
+        opencrust_common::Error::Channel(format!("invalid telegram chat id: {channel_ref}"))
+    })?;
+    bot.send_chat_action(ChatId(chat_id), ChatAction::Typing)
+        .await
+        .map(|_| ())
+        .map_err(|e| opencrust_common::Error::Channel(format!("telegram typing failed: {e}")))
+}
+
+/// Inline buttons requested by the sender through `"buttons"` metadata, given
+/// as rows of `{"text", "data"}` objects.
+fn metadata_buttons(metadata: &serde_json::Value) -> Option<Vec<Vec<InlineButton>>> {
+    let rows: Vec<Vec<InlineButton>> =
+        serde_json::from_value(metadata.get("buttons")?.clone()).ok()?;
+    (!rows.iter().all(Vec::is_empty)).then_some(rows)
+}
+
+/// Map a failed Telegram request to an error. Flood control (`RetryAfter`)
+/// becomes [`Error::RateLimited`](opencrust_common::Error::RateLimited) so
+/// queued sends wait and retry.
+fn request_error(action: &str, e: RequestError) -> opencrust_common::Error {
+    match e {
+        RequestError::RetryAfter(wait) => opencrust_common::Error::RateLimited {
+            message: format!("telegram {action} hit flood control"),
+            retry_after: Some(wait.duration()),
+        },
+        e => opencrust_common::Error::Channel(format!("telegram {action} failed: {e}")),
     }
 }
 
 /// Shared send logic used by both `TelegramChannel` and `TelegramSender`.
-async fn telegram_send_message(bot: &Bot, message: &Message) -> Result<()> {
+async fn telegram_send_message(bot: &Bot, buttons: &ButtonData, message: &Message) -> Result<()> {
     let chat_id: i64 = message
         .metadata
         .get("telegram_chat_id")
@@ -639,19 +1098,28 @@ async fn telegram_send_message(bot: &Bot, message: &Message) -> Result<()> {
         })?;
 
     let tg_chat_id = ChatId(chat_id);
+    let keyboard = metadata_buttons(&message.metadata).map(|rows| inline_keyboard(&rows, buttons));
 
     match &message.content {
         MessageContent::Text(text) => {
             let formatted = to_telegram_markdown(text);
-            let send_result = bot
+            let mut send = bot
                 .send_message(tg_chat_id, &formatted)
-                .parse_mode(ParseMode::MarkdownV2)
-                .await;
-            if send_result.is_err() {
-                // Fallback: plain text
-                bot.send_message(tg_chat_id, text).await.map_err(|e| {
-                    opencrust_common::Error::Channel(format!("telegram send failed: {e}"))
-                })?;
+                .parse_mode(ParseMode::MarkdownV2);
+            if let Some(keyboard) = &keyboard {
+                send = send.reply_markup(keyboard.clone());
+            }
+            match send.await {
+                Ok(_) => {}
+                Err(e @ RequestError::RetryAfter(_)) => return Err(request_error("send", e)),
+                Err(_) => {
+                    // Fallback: plain text
+                    let mut plain = bot.send_message(tg_chat_id, text);
+                    if let Some(keyboard) = keyboard {
+                        plain = plain.reply_markup(keyboard);
+                    }
+                    plain.await.map_err(|e| request_error("send", e))?;
+                }
             }
         }
         MessageContent::Image { url, caption } => {
@@ -663,9 +1131,7 @@ async fn telegram_send_message(bot: &Bot, message: &Message) -> Result<()> {
             )
             .caption(caption.as_deref().unwrap_or(""))
             .await
-            .map_err(|e| {
-                opencrust_common::Error::Channel(format!("telegram send_photo failed: {e}"))
-            })?;
+            .map_err(|e| request_error("send_photo", e))?;
         }
         MessageContent::File { url, filename } => {
             bot.send_document(
@@ -676,9 +1142,31 @@ async fn telegram_send_message(bot: &Bot, message: &Message) -> Result<()> {
             )
             .caption(filename)
             .await
-            .map_err(|e| {
-                opencrust_common::Error::Channel(format!("telegram send_document failed: {e}"))
-            })?;
+            .map_err(|e| request_error("send_document", e))?;
+        }
+        MessageContent::Attachment(attachment) => {
+            let file = InputFile::memory(attachment.data.clone())
+                .file_name(attachment.filename_or_default());
+            let caption = attachment.caption.clone().unwrap_or_default();
+            let (action, sent) = match attachment.kind {
+                MediaKind::Photo => (
+                    "send_photo",
+                    bot.send_photo(tg_chat_id, file).caption(caption).await,
+                ),
+                MediaKind::Voice => (
+                    "send_voice",
+                    bot.send_voice(tg_chat_id, file).caption(caption).await,
+                ),
+                MediaKind::Video => (
+                    "send_video",
+                    bot.send_video(tg_chat_id, file).caption(caption).await,
+                ),
+                MediaKind::Document => (
+                    "send_document",
+                    bot.send_document(tg_chat_id, file).caption(caption).await,
+                ),
+            };
+            sent.map_err(|e| request_error(action, e))?;
         }
         _ => {
             return Err(opencrust_common::Error::Channel(
@@ -929,6 +1417,59 @@ mod tests {
         assert!(!is_bot_mentioned(&msg, "mybot"));
     }
 
+    #[test]
+    fn test_is_reply_to_bot() {
+        let json = r#"{
+            "message_id": 13,
+            "date": 1620000000,
+            "chat": { "id": -100, "type": "supergroup", "title": "Group" },
+            "from": { "id": 222, "is_bot": false, "first_name": "Bob" },
+            "text": "and tomorrow?",
+            "reply_to_message": {
+                "message_id": 12,
+                "date": 1620000000,
+                "chat": { "id": -100, "type": "supergroup", "title": "Group" },
+                "from": { "id": 999, "is_bot": true, "first_name": "Bot", "username": "MyBot" },
+                "text": "Sunny today."
+            }
+        }"#;
+        let msg: teloxide::types::Message = serde_json::from_str(json).unwrap();
+        assert!(is_reply_to_bot(&msg, "mybot"));
+        assert!(!is_reply_to_bot(&msg, "otherbot"));
+    }
+
+    #[test]
+    fn test_added_emojis_only_new_ones() {
+        let json = r#"{
+            "chat": { "id": 123, "type": "private", "first_name": "Alice" },
+            "message_id": 7,
+            "user": { "id": 111, "is_bot": false, "first_name": "Alice" },
+            "date": 1620000000,
+            "old_reaction": [{ "type": "emoji", "emoji": "🔥" }],
+            "new_reaction": [
+                { "type": "emoji", "emoji": "🔥" },
+                { "type": "emoji", "emoji": "👍" },
+                { "type": "custom_emoji", "custom_emoji_id": "5" }
+            ]
+        }"#;
+        let update: MessageReactionUpdated = serde_json::from_str(json).unwrap();
+        assert_eq!(added_emojis(&update), vec!["👍".to_string()]);
+    }
+
+    #[test]
+    fn test_strip_bot_mention() {
+        assert_eq!(strip_bot_mention("@MyBot what's up", "mybot"), "what's up");
+        assert_eq!(
+            strip_bot_mention("hey @mybot can you\nhelp?", "mybot"),
+            "hey can you\nhelp?"
+        );
+        assert_eq!(
+            strip_bot_mention("ask @mybot2 instead", "mybot"),
+            "ask @mybot2 instead"
+        );
+        assert_eq!(strip_bot_mention("mail me@x.com", "mybot"), "mail me@x.com");
+    }
+
     #[test]
     fn test_group_filter_disabled_blocks_all() {
         let filter: GroupFilter = Arc::new(|_mentioned| false);
@@ -956,4 +1497,103 @@ mod tests {
     fn download_size_limit_constant_is_10_mib() {
         assert_eq!(crate::MAX_DOWNLOAD_BYTES, 10 * 1024 * 1024);
     }
+
+    // --- inline button tests ---
+
+    #[test]
+    fn inline_keyboard_keeps_rows_and_maps_keys_to_full_data() {
+        let buttons = ButtonData::default();
+        let long = "é".repeat(40); // 80 bytes
+        let keyboard = inline_keyboard(
+            &[
+                vec![
+                    InlineButton::new("Yes", "yes"),
+                    InlineButton::new("No", "no"),
+                ],
+                vec![InlineButton::new("Long", long.clone())],
+            ],
+            &buttons,
+        );
+        let rows = &keyboard.inline_keyboard;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), 2);
+        assert_eq!(rows[0][1].text, "No");
+        let InlineKeyboardButtonKind::CallbackData(key) = &rows[1][0].kind else {
+            panic!("expected callback button");
+        };
+        assert!(key.len() <= 64);
+        assert_eq!(buttons.data_for(key), Some(long));
+        assert_eq!(buttons.data_for("b999"), None);
+    }
+
+    #[test]
+    fn metadata_buttons_parsed_from_message_metadata() {
+        let metadata = serde_json::json!({
+            "telegram_chat_id": 1,
+            "buttons": [[{ "text": "Approve", "data": "approve" }]]
+        });
+        assert_eq!(
+            metadata_buttons(&metadata),
+            Some(vec![vec![InlineButton::new("Approve", "approve")]])
+        );
+        assert!(metadata_buttons(&serde_json::json!({ "buttons": [[]] })).is_none());
+        assert!(metadata_buttons(&serde_json::json!({ "buttons": "nope" })).is_none());
+        assert!(metadata_buttons(&serde_json::json!({})).is_none());
+    }
+
+    #[test]
+    fn parse_button_press_from_callback_query() {
+        let json = r#"{
+            "id": "cb1",
+            "from": { "id": 111, "is_bot": false, "first_name": "Alice" },
+            "message": {
+                "message_id": 7,
+                "date": 1620000000,
+                "chat": { "id": 12345, "type": "private", "first_name": "Alice" },
+                "text": "Deploy?"
+            },
+            "chat_instance": "x",
+            "data": "Staging"
+        }"#;
+        let query: CallbackQuery = serde_json::from_str(json).expect("failed to parse json");
+        let press = parse_button_press(&query, &ButtonData::default()).expect("should parse press");
+        assert_eq!(press.chat_id, ChatId(12345));
+        assert_eq!(press.message_id, teloxide::types::MessageId(7));
+        assert_eq!(press.user_id, "111");
+        assert_eq!(press.user_name, "Alice");
+        assert_eq!(press.data, "Staging");
+
+        let mut no_data = query;
+        no_data.data = None;
+        assert!(parse_button_press(&no_data, &ButtonData::default()).is_none());
+    }
+
+    #[test]
+    fn parse_button_press_maps_key_to_data_or_label() {
+        let json = r#"{
+            "id": "cb1",
+            "from": { "id": 111, "is_bot": false, "first_name": "Alice" },
+            "message": {
+                "message_id": 7,
+                "date": 1620000000,
+                "chat": { "id": 12345, "type": "private", "first_name": "Alice" },
+                "text": "Deploy?",
+                "reply_markup": {
+                    "inline_keyboard": [[{ "text": "Staging", "callback_data": "b0" }]]
+                }
+            },
+            "chat_instance": "x",
+            "data": "b0"
+        }"#;
+        let query: CallbackQuery = serde_json::from_str(json).expect("failed to parse json");
+
+        let buttons = ButtonData::default();
+        assert_eq!(buttons.key_for("deploy to staging"), "b0");
+        let press = parse_button_press(&query, &buttons).expect("should parse press");
+        assert_eq!(press.data, "deploy to staging");
+
+        // A key sent before a restart falls back to the button's label.
+        let press = parse_button_press(&query, &ButtonData::default()).expect("should parse press");
+        assert_eq!(press.data, "Staging");
+    }
 }
@@ -1,102 +1,54 @@
-/// Convert standard markdown to Telegram MarkdownV2 format.
+use crate::format::FormatProfile;
+
+/// Telegram MarkdownV2 formatting profile.
 ///
 /// Telegram MarkdownV2 requires escaping these characters outside of
 /// formatting entities: `_`, `*`, `[`, `]`, `(`, `)`, `~`, `` ` ``, `>`,
 /// `#`, `+`, `-`, `=`, `|`, `{`, `}`, `.`, `!`
 ///
-/// This function handles:
-/// - Code blocks (``` ... ```) — preserved as-is
-/// - Inline code (` ... `) — preserved as-is
+/// - Code blocks (``` ... ```) and inline code (` ... `) — preserved as-is
 /// - Bold (**text**) → *text*
-/// - Italic (*text* or _text_) → _text_
+/// - Links ([text](url)) — text escaped, `)` and `\` escaped in the URL
 /// - Escaping special chars in plain text
-pub fn to_telegram_markdown(input: &str) -> String {
-    let mut result = String::with_capacity(input.len() * 2);
-    let chars: Vec<char> = input.chars().collect();
-    let len = chars.len();
-    let mut i = 0;
-
-    while i < len {
-        // Fenced code block: ```...```
-        if i + 2 < len && chars[i] == '`' && chars[i + 1] == '`' && chars[i + 2] == '`' {
-            // Find the language tag (rest of line after ```)
-            let block_start = i;
-            i += 3;
+pub struct TelegramFormat;
 
-            // Skip optional language tag
-            while i < len && chars[i] != '\n' {
-                i += 1;
-            }
-
-            let header = &input[byte_offset(&chars, block_start)..byte_offset(&chars, i)];
+impl FormatProfile for TelegramFormat {
+    fn text(&self, text: &str, out: &mut String) {
+        push_escaped(text, out, |_| false);
+    }
 
-            // Find closing ```
-            let content_start = i;
-            let mut found_close = false;
-            while i < len {
-                if i + 2 < len && chars[i] == '`' && chars[i + 1] == '`' && chars[i + 2] == '`' {
-                    let content =
-                        &input[byte_offset(&chars, content_start)..byte_offset(&chars, i)];
-                    result.push_str(header);
-                    result.push_str(content);
-                    result.push_str("```");
-                    i += 3;
-                    found_close = true;
-                    break;
-                }
-                i += 1;
-            }
-            if !found_close {
-                // Unclosed code block — just push the rest
-                let content = &input[byte_offset(&chars, content_start)..byte_offset(&chars, len)];
-                result.push_str(header);
-                result.push_str(content);
-            }
-            continue;
-        }
+    fn bold(&self, text: &str, out: &mut String) {
+        out.push('*');
+        push_escaped(text, out, |c| c == '*');
+        out.push('*');
+    }
 
-        // Inline code: `...`
-        if chars[i] == '`' {
-            result.push('`');
-            i += 1;
-            while i < len && chars[i] != '`' {
-                result.push(chars[i]);
-                i += 1;
-            }
-            if i < len {
-                result.push('`');
-                i += 1;
+    fn link(&self, text: &str, url: &str, out: &mut String) {
+        out.push('[');
+        push_escaped(text, out, |_| false);
+        out.push_str("](");
+        for c in url.chars() {
+            if c == ')' || c == '\\' {
+                out.push('\\');
             }
-            continue;
+            out.push(c);
         }
+        out.push(')');
+    }
+}
 
-        // Bold: **text** → *text*
-        if i + 1 < len && chars[i] == '*' && chars[i + 1] == '*' {
-            result.push('*');
-            i += 2;
-            while i < len && !(i + 1 < len && chars[i] == '*' && chars[i + 1] == '*') {
-                if is_special(chars[i]) && chars[i] != '*' {
-                    result.push('\\');
-                }
-                result.push(chars[i]);
-                i += 1;
-            }
-            result.push('*');
-            if i + 1 < len {
-                i += 2; // skip closing **
-            }
-            continue;
-        }
+/// Convert standard markdown to Telegram MarkdownV2 format.
+pub fn to_telegram_markdown(input: &str) -> String {
+    TelegramFormat.render(input)
+}
 
-        // Escape special characters in plain text
-        if is_special(chars[i]) {
-            result.push('\\');
+fn push_escaped(text: &str, out: &mut String, keep: impl Fn(char) -> bool) {
+    for c in text.chars() {
+        if is_special(c) && !keep(c) {
+            out.push('\\');
         }
-        result.push(chars[i]);
-        i += 1;
+        out.push(c);
     }
-
-    result
 }
 
 fn is_special(c: char) -> bool {
@@ -122,10 +74,6 @@ fn is_special(c: char) -> bool {
     )
 }
 
-fn byte_offset(chars: &[char], char_index: usize) -> usize {
-    chars[..char_index].iter().map(|c| c.len_utf8()).sum()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,4 +112,10 @@ mod tests {
         let output = to_telegram_markdown(input);
         assert_eq!(output, "Hello\\! Try `code` and *bold*\\.");
     }
+
+    #[test]
+    fn link_text_escaped_and_url_kept() {
+        let output = to_telegram_markdown("see [v1.2 notes](https://example.com/a_(b))");
+        assert_eq!(output, "see [v1\\.2 notes](https://example.com/a_(b\\))");
+    }
 }
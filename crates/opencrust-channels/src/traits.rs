@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use opencrust_common::{Message, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::queue::RateLimiter;
+
 /// Unified response type returned by every channel's `OnMessageFn`.
 ///
 /// Each channel handler decides how to deliver the variants:
@@ -10,12 +14,32 @@ use serde::{Deserialize, Serialize};
 ///   delivered as a voice/audio message where the channel supports it.
 ///   Channels that cannot deliver audio (e.g. Slack) fall back to sending
 ///   the `text` field as a regular text message.
+/// - `Buttons` — `text` with a keyboard of choices underneath. Channels without
+///   interactive buttons send only the `text`.
+/// - `ToolResults` — `text` plus the structured output of tools that ran this
+///   turn. Channels with rich rendering (Discord embeds) show the results
+///   under the text; the rest send only the `text`.
+/// - `Reasoning` — `text` plus the model's reasoning behind it. Channels that
+///   can collapse content show the reasoning under the text (Discord spoilers,
+///   Slack context blocks); the rest send only the `text`.
 #[derive(Debug, Clone)]
 pub enum ChannelResponse {
     /// Plain text response.
     Text(String),
     /// Voice response: `text` for history/fallback, `audio` for playback.
     Voice { text: String, audio: Vec<u8> },
+    /// Text with inline buttons, given as rows of buttons.
+    Buttons {
+        text: String,
+        buttons: Vec<Vec<InlineButton>>,
+    },
+    /// Text followed by structured tool output a channel may render richly.
+    ToolResults {
+        text: String,
+        results: Vec<ToolResult>,
+    },
+    /// Text with the reasoning the model produced before answering.
+    Reasoning { text: String, reasoning: String },
 }
 
 impl ChannelResponse {
@@ -24,10 +48,38 @@ impl ChannelResponse {
         match self {
             Self::Text(t) => t,
             Self::Voice { text, .. } => text,
+            Self::Buttons { text, .. } => text,
+            Self::ToolResults { text, .. } => text,
+            Self::Reasoning { text, .. } => text,
+        }
+    }
+}
+
+/// A button attached below a message. Pressing it reports `data` back to the
+/// bot as a [`ChannelEvent::ButtonPressed`] and as the user's next message.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InlineButton {
+    pub text: String,
+    pub data: String,
+}
+
+impl InlineButton {
+    pub fn new(text: impl Into<String>, data: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            data: data.into(),
         }
     }
 }
 
+/// Structured output of one tool call, as attached by the tool itself.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolResult {
+    /// Name of the tool that produced `data` (e.g. `web_search`).
+    pub tool: String,
+    pub data: serde_json::Value,
+}
+
 /// Lifecycle management for a messaging channel (connect, disconnect, status).
 #[async_trait]
 pub trait ChannelLifecycle: Send {
@@ -72,6 +124,23 @@ pub trait ChannelSender: Send + Sync {
 
     /// Send a message through this channel.
     async fn send_message(&self, message: &Message) -> Result<()>;
+
+    /// Show a typing indicator in `channel_ref`, the chat the channel passes
+    /// to its `on_message` callback. Indicators expire after a few seconds,
+    /// so callers repeat this while a reply is pending (see
+    /// [`while_typing`](crate::typing::while_typing)).
+    ///
+    /// Defaults to a no-op for channels without typing indicators.
+    async fn send_typing(&self, _channel_ref: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// The rate limiter the channel's own replies use, so that a
+    /// [`QueuedSender`](crate::QueuedSender) wrapping this sender queues under
+    /// the same budget. `None` (the default) gives the queue its own limiter.
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        None
+    }
 }
 
 /// Convenience trait combining lifecycle and send capabilities.
@@ -94,6 +163,28 @@ pub enum ChannelEvent {
     MessageReceived(Message),
     StatusChanged(ChannelStatus),
     Error(String),
+    /// A user pressed an inline button.
+    ButtonPressed {
+        chat_id: String,
+        user_id: String,
+        user_name: String,
+        data: String,
+    },
+    /// A user edited the last message the bot answered; the reply is being
+    /// regenerated from the new text.
+    MessageEdited {
+        chat_id: String,
+        user_id: String,
+        message_id: String,
+        text: String,
+    },
+    /// A user reacted to one of the bot's replies.
+    Reaction {
+        chat_id: String,
+        user_id: String,
+        message_id: String,
+        emoji: String,
+    },
 }
 
 #[cfg(test)]
@@ -124,6 +215,24 @@ mod tests {
         assert_eq!(r.text(), "words");
     }
 
+    #[test]
+    fn buttons_variant_returns_text_field() {
+        let r = ChannelResponse::Buttons {
+            text: "pick one".to_string(),
+            buttons: vec![vec![InlineButton::new("Yes", "yes")]],
+        };
+        assert_eq!(r.text(), "pick one");
+    }
+
+    #[test]
+    fn reasoning_variant_returns_text_field() {
+        let r = ChannelResponse::Reasoning {
+            text: "42".to_string(),
+            reasoning: "six times seven".to_string(),
+        };
+        assert_eq!(r.text(), "42");
+    }
+
     /// Verify the default `channel_name()` falls back to `channel_type()`.
     #[tokio::test]
     async fn channel_name_default_returns_channel_type() {
@@ -0,0 +1,91 @@
+//! Typing indicators shown while the agent works on a reply.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::traits::ChannelSender;
+
+/// How often the indicator is refreshed. Telegram clears it after about five
+/// seconds, Discord after about ten.
+pub const TYPING_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Drive `fut` to completion, calling [`ChannelSender::send_typing`] for
+/// `channel_ref` right away and every [`TYPING_INTERVAL`] until it finishes.
+///
+/// A failed indicator only means the user sees nothing, so errors are logged
+/// and otherwise ignored.
+pub async fn while_typing<F: Future>(
+    sender: &dyn ChannelSender,
+    channel_ref: &str,
+    fut: F,
+) -> F::Output {
+    tokio::pin!(fut);
+    let mut ticks = tokio::time::interval(TYPING_INTERVAL);
+    loop {
+        tokio::select! {
+            biased;
+            output = &mut fut => return output,
+            _ = ticks.tick() => {
+                if let Err(e) = sender.send_typing(channel_ref).await {
+                    debug!(
+                        "{}: typing indicator failed for {channel_ref}: {e}",
+                        sender.channel_name()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use opencrust_common::{Message, Result};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSender {
+        typing: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ChannelSender for RecordingSender {
+        fn channel_type(&self) -> &str {
+            "test"
+        }
+
+        async fn send_message(&self, _message: &Message) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_typing(&self, channel_ref: &str) -> Result<()> {
+            self.typing.lock().unwrap().push(channel_ref.to_string());
+            Err(opencrust_common::Error::Channel("rate limited".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn types_until_future_completes() {
+        let sender = RecordingSender::default();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let _ = tx.send("reply");
+        });
+
+        let output = while_typing(&sender, "42", async { rx.await.unwrap() }).await;
+
+        assert_eq!(output, "reply");
+        assert_eq!(*sender.typing.lock().unwrap(), vec!["42".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn ready_future_skips_typing() {
+        let sender = RecordingSender::default();
+        assert_eq!(while_typing(&sender, "42", async { 7 }).await, 7);
+        assert!(sender.typing.lock().unwrap().is_empty());
+    }
+}
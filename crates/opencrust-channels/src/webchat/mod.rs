@@ -0,0 +1,332 @@
+pub mod socket;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
+use opencrust_common::{Error, MediaAttachment, Message, MessageContent, Result};
+
+/// Callback invoked when a web chat visitor sends a message.
+///
+/// Arguments: `(visitor_id, text, delta_tx)`.
+/// * `visitor_id` is a random ID the widget keeps in the visitor's browser.
+/// * `delta_tx` streams partial replies to the widget as they are generated.
+///
+/// Return `Err("__blocked__")` to silently drop the message.
+pub type WebChatOnMessageFn = Arc<
+    dyn Fn(
+            String,
+            String,
+            Option<mpsc::Sender<String>>,
+        )
+            -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Outgoing frame queues of connected visitors, keyed by visitor ID. A
+/// visitor with several tabs open is reached through the newest one.
+type Visitors = Arc<Mutex<HashMap<String, mpsc::Sender<String>>>>;
+
+/// Chat widget served by the gateway itself.
+///
+/// Visitors load `/webchat/widget.js` on any page; the widget talks to the
+/// gateway over `/webchat/ws`. There is no external service to connect to.
+pub struct WebChatChannel {
+    name: String,
+    display: String,
+    title: String,
+    allowed_origins: Vec<String>,
+    status: ChannelStatus,
+    on_message: WebChatOnMessageFn,
+    visitors: Visitors,
+}
+
+impl WebChatChannel {
+    pub fn new(on_message: WebChatOnMessageFn) -> Self {
+        Self {
+            name: "webchat".to_string(),
+            display: "Web Chat".to_string(),
+            title: "Chat".to_string(),
+            allowed_origins: Vec::new(),
+            status: ChannelStatus::Disconnected,
+            on_message,
+            visitors: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Override the config key name for this channel instance.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Heading shown at the top of the widget.
+    pub fn with_title(mut self, title: String) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Only accept WebSocket connections from pages on these origins
+    /// (e.g. `https://example.com`). Empty allows any origin.
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.allowed_origins = origins
+            .into_iter()
+            .map(|o| o.trim_end_matches('/').to_string())
+            .collect();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Whether a connection with this `Origin` header may open a chat.
+    pub fn origin_allowed(&self, origin: Option<&str>) -> bool {
+        self.allowed_origins.is_empty()
+            || origin.is_some_and(|o| {
+                let o = o.trim_end_matches('/');
+                self.allowed_origins.iter().any(|allowed| allowed == o)
+            })
+    }
+
+    pub(crate) fn on_message(&self) -> &WebChatOnMessageFn {
+        &self.on_message
+    }
+
+    pub(crate) fn register_visitor(&self, visitor_id: &str, frames: mpsc::Sender<String>) {
+        self.visitors
+            .lock()
+            .unwrap()
+            .insert(visitor_id.to_string(), frames);
+    }
+
+    /// Forget `frames` unless a newer connection for the visitor replaced it.
+    pub(crate) fn unregister_visitor(&self, visitor_id: &str, frames: &mpsc::Sender<String>) {
+        let mut visitors = self.visitors.lock().unwrap();
+        if visitors
+            .get(visitor_id)
+            .is_some_and(|current| current.same_channel(frames))
+        {
+            visitors.remove(visitor_id);
+        }
+    }
+
+    fn sender(&self) -> WebChatSender {
+        WebChatSender {
+            name: self.name.clone(),
+            visitors: Arc::clone(&self.visitors),
+        }
+    }
+}
+
+/// Build a server-to-widget frame.
+pub(crate) fn frame(kind: &str, text: &str) -> String {
+    serde_json::json!({ "type": kind, "text": text }).to_string()
+}
+
+/// Build a `file` frame carrying `attachment` as a `data:` URI, which the
+/// widget shows inline (images) or as a download link.
+pub(crate) fn file_frame(attachment: &MediaAttachment) -> String {
+    let mime = attachment
+        .mime_type
+        .as_deref()
+        .unwrap_or("application/octet-stream");
+    serde_json::json!({
+        "type": "file",
+        "text": attachment.caption.as_deref().unwrap_or_default(),
+        "name": attachment.filename_or_default(),
+        "url": format!("data:{mime};base64,{}", STANDARD.encode(&attachment.data)),
+    })
+    .to_string()
+}
+
+// ── WebChatSender ────────────────────────────────────────────────────────────
+
+/// Send-only handle that pushes messages to connected visitors.
+pub struct WebChatSender {
+    name: String,
+    visitors: Visitors,
+}
+
+#[async_trait]
+impl ChannelSender for WebChatSender {
+    fn channel_type(&self) -> &str {
+        "webchat"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Needs `webchat_visitor_id` in the message metadata. Only visitors with
+    /// the widget currently open can be reached.
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        let payload = match &message.content {
+            MessageContent::Text(t) => frame("message", t),
+            MessageContent::Attachment(attachment) => file_frame(attachment),
+            _ => {
+                return Err(Error::Channel(
+                    "only text and attachment messages are supported for webchat send".into(),
+                ));
+            }
+        };
+        let visitor_id = message
+            .metadata
+            .get("webchat_visitor_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Error::Channel("missing webchat_visitor_id in metadata".into()))?;
+
+        let frames = self.visitors.lock().unwrap().get(visitor_id).cloned();
+        let frames = frames.ok_or_else(|| {
+            Error::Channel(format!("webchat visitor {visitor_id} is not connected"))
+        })?;
+        frames
+            .send(payload)
+            .await
+            .map_err(|_| Error::Channel(format!("webchat visitor {visitor_id} disconnected")))
+    }
+}
+
+// ── ChannelLifecycle ─────────────────────────────────────────────────────────
+
+#[async_trait]
+impl ChannelLifecycle for WebChatChannel {
+    fn display_name(&self) -> &str {
+        &self.display
+    }
+
+    fn create_sender(&self) -> Box<dyn ChannelSender> {
+        Box::new(self.sender())
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        // Served by the gateway's own HTTP server; nothing to connect to.
+        self.status = ChannelStatus::Connected;
+        info!("webchat channel '{}' ready at /webchat", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.status = ChannelStatus::Disconnected;
+        self.visitors.lock().unwrap().clear();
+        info!("webchat channel '{}' disconnected", self.name);
+        Ok(())
+    }
+
+    fn status(&self) -> ChannelStatus {
+        self.status.clone()
+    }
+}
+
+#[async_trait]
+impl ChannelSender for WebChatChannel {
+    fn channel_type(&self) -> &str {
+        "webchat"
+    }
+
+    fn channel_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send_message(&self, message: &Message) -> Result<()> {
+        self.sender().send_message(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> WebChatChannel {
+        WebChatChannel::new(Arc::new(|_, _, _| {
+            Box::pin(async { Ok(ChannelResponse::Text("hi".to_string())) })
+        }))
+    }
+
+    #[test]
+    fn origin_check() {
+        assert!(channel().origin_allowed(None));
+
+        let ch = channel().with_allowed_origins(vec!["https://example.com/".into()]);
+        assert!(ch.origin_allowed(Some("https://example.com")));
+        assert!(!ch.origin_allowed(Some("https://evil.example")));
+        assert!(!ch.origin_allowed(None));
+    }
+
+    #[tokio::test]
+    async fn sender_reaches_newest_connection_only() {
+        let ch = channel();
+        let sender = ch.create_sender();
+        let mut msg = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("webchat"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "reminder",
+        );
+        msg.metadata = serde_json::json!({ "webchat_visitor_id": "v1" });
+        assert!(sender.send_message(&msg).await.is_err());
+
+        let (old_tx, mut old_rx) = mpsc::channel(4);
+        let (new_tx, mut new_rx) = mpsc::channel(4);
+        ch.register_visitor("v1", old_tx.clone());
+        ch.register_visitor("v1", new_tx.clone());
+        // The stale tab closing must not unregister the live one.
+        ch.unregister_visitor("v1", &old_tx);
+
+        sender.send_message(&msg).await.unwrap();
+        assert_eq!(new_rx.recv().await.unwrap(), frame("message", "reminder"));
+        assert!(old_rx.try_recv().is_err());
+
+        ch.unregister_visitor("v1", &new_tx);
+        assert!(sender.send_message(&msg).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn sender_pushes_attachments_as_file_frames() {
+        let ch = channel();
+        let sender = ch.create_sender();
+        let (tx, mut rx) = mpsc::channel(4);
+        ch.register_visitor("v1", tx);
+
+        let mut msg = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("webchat"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "",
+        );
+        msg.content = MessageContent::Attachment(
+            MediaAttachment::new(opencrust_common::MediaKind::Photo, b"png".to_vec())
+                .with_filename("chart.png")
+                .with_mime_type(Some("image/png".into()))
+                .with_caption(Some("Weekly numbers".into())),
+        );
+        msg.metadata = serde_json::json!({ "webchat_visitor_id": "v1" });
+        sender.send_message(&msg).await.unwrap();
+
+        let sent: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(
+            sent,
+            serde_json::json!({
+                "type": "file",
+                "text": "Weekly numbers",
+                "name": "chart.png",
+                "url": "data:image/png;base64,cG5n",
+            })
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
+use futures::{SinkExt, StreamExt};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::{WebChatChannel, frame};
+
+/// Shared state passed to the web chat handlers.
+pub type WebChatState = Arc<Vec<Arc<WebChatChannel>>>;
+
+const WIDGET_JS: &str = include_str!("widget.js");
+
+/// Largest message a visitor may send, in bytes.
+const MAX_MESSAGE_BYTES: usize = 16 * 1024;
+
+/// Ping interval that keeps idle connections open through proxies.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+pub struct SocketParams {
+    /// Config key of the channel; defaults to the first web chat channel.
+    pub channel: Option<String>,
+    /// Visitor ID stored by the widget; a new one is issued when missing.
+    pub visitor: Option<String>,
+}
+
+fn find_channel(channels: &WebChatState, name: Option<&str>) -> Option<Arc<WebChatChannel>> {
+    match name {
+        Some(name) => channels.iter().find(|ch| ch.name() == name),
+        None => channels.first(),
+    }
+    .cloned()
+}
+
+/// GET /webchat — a standalone page with the widget open, for linking to
+/// directly or trying the widget out.
+pub async fn webchat_page(
+    State(channels): State<WebChatState>,
+    Query(params): Query<SocketParams>,
+) -> Response {
+    let Some(channel) = find_channel(&channels, params.channel.as_deref()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let title = html_escape(channel.title());
+    let name = html_escape(channel.name());
+    Html(format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n  <meta charset=\"utf-8\">\n  \
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n  \
+         <title>{title}</title>\n</head>\n<body>\n  \
+         <script src=\"/webchat/widget.js\" data-channel=\"{name}\" data-open=\"true\"></script>\n\
+         </body>\n</html>\n"
+    ))
+    .into_response()
+}
+
+/// GET /webchat/widget.js — the embeddable widget script.
+pub async fn webchat_widget() -> impl IntoResponse {
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                "application/javascript; charset=utf-8",
+            ),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        WIDGET_JS,
+    )
+}
+
+/// GET /webchat/ws — WebSocket used by the widget.
+///
+/// Frames from the widget: `{"type":"message","text":"..."}`.
+/// Frames to the widget: `ready` (with `visitor_id` and `title`), `delta`
+/// (partial reply), `message` (complete reply) and `error`.
+pub async fn webchat_socket(
+    State(channels): State<WebChatState>,
+    Query(params): Query<SocketParams>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(channel) = find_channel(&channels, params.channel.as_deref()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let origin = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok());
+    if !channel.origin_allowed(origin) {
+        warn!(
+            "webchat '{}': rejected connection from origin {:?}",
+            channel.name(),
+            origin
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let visitor_id = params
+        .visitor
+        .filter(|v| valid_visitor_id(v))
+        .unwrap_or_else(new_visitor_id);
+
+    ws.max_message_size(MAX_MESSAGE_BYTES)
+        .on_upgrade(move |socket| run_socket(socket, channel, visitor_id))
+}
+
+async fn run_socket(socket: WebSocket, channel: Arc<WebChatChannel>, visitor_id: String) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    let (frames_tx, mut frames_rx) = mpsc::channel::<String>(64);
+    channel.register_visitor(&visitor_id, frames_tx.clone());
+    info!(
+        "webchat '{}': visitor {visitor_id} connected",
+        channel.name()
+    );
+
+    let ready = serde_json::json!({
+        "type": "ready",
+        "visitor_id": visitor_id,
+        "title": channel.title(),
+    });
+    if ws_tx
+        .send(Message::Text(ready.to_string().into()))
+        .await
+        .is_err()
+    {
+        channel.unregister_visitor(&visitor_id, &frames_tx);
+        return;
+    }
+
+    // Turns run one at a time, in order, while the socket keeps reading.
+    let (turn_tx, turn_rx) = mpsc::channel::<String>(8);
+    let worker = tokio::spawn(run_turns(
+        Arc::clone(&channel),
+        visitor_id.clone(),
+        turn_rx,
+        frames_tx.clone(),
+    ));
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        tokio::select! {
+            incoming = ws_rx.next() => match incoming {
+                Some(Ok(Message::Text(raw))) => {
+                    let Some(text) = parse_message(&raw) else {
+                        continue;
+                    };
+                    if turn_tx.try_send(text).is_err() {
+                        let _ = frames_tx
+                            .try_send(frame("error", "Please wait for the current reply."));
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Err(e)) => {
+                    warn!("webchat '{}': socket error: {e}", channel.name());
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+            Some(out) = frames_rx.recv() => {
+                if ws_tx.send(Message::Text(out.into())).await.is_err() {
+                    break;
+                }
+            }
+            _ = ping.tick() => {
+                if ws_tx.send(Message::Ping(Vec::new().into())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    worker.abort();
+    channel.unregister_visitor(&visitor_id, &frames_tx);
+    info!(
+        "webchat '{}': visitor {visitor_id} disconnected",
+        channel.name()
+    );
+}
+
+async fn run_turns(
+    channel: Arc<WebChatChannel>,
+    visitor_id: String,
+    mut turns: mpsc::Receiver<String>,
+    frames: mpsc::Sender<String>,
+) {
+    while let Some(text) = turns.recv().await {
+        let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
+        let forward = {
+            let frames = frames.clone();
+            tokio::spawn(async move {
+                while let Some(delta) = delta_rx.recv().await {
+                    let _ = frames.send(frame("delta", &delta)).await;
+                }
+            })
+        };
+
+        let result = (channel.on_message())(visitor_id.clone(), text, Some(delta_tx)).await;
+        // The callback has returned, so its delta sender is gone; wait for
+        // the last deltas to go out before the final message.
+        let _ = forward.await;
+
+        let out = match result {
+            Ok(response) => frame("message", response.text()),
+            Err(e) if e == "__blocked__" => continue,
+            Err(e) => {
+                warn!(
+                    "webchat '{}': error processing message: {e}",
+                    channel.name()
+                );
+                frame("error", "Sorry, an error occurred processing your message.")
+            }
+        };
+        if frames.send(out).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn parse_message(raw: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(raw).ok()?;
+    if value.get("type")?.as_str()? != "message" {
+        return None;
+    }
+    let text = value.get("text")?.as_str()?.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn valid_visitor_id(id: &str) -> bool {
+    (16..=64).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn new_visitor_id() -> String {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system random number generator failed");
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::get;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+    use crate::traits::ChannelResponse;
+    use crate::webchat::WebChatOnMessageFn;
+
+    /// Serve `channel` on a random port and return the WebSocket URL.
+    async fn serve(channel: WebChatChannel) -> String {
+        let state: WebChatState = Arc::new(vec![Arc::new(channel)]);
+        let app = Router::new()
+            .route("/webchat", get(webchat_page))
+            .route("/webchat/widget.js", get(webchat_widget))
+            .route("/webchat/ws", get(webchat_socket))
+            .with_state(state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{addr}/webchat/ws")
+    }
+
+    async fn next_frame<S>(ws: &mut S) -> serde_json::Value
+    where
+        S: StreamExt<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timed out waiting for a frame")
+                .unwrap()
+                .unwrap();
+            if let tungstenite::Message::Text(text) = msg {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    fn streaming_echo() -> WebChatOnMessageFn {
+        Arc::new(|visitor, text, delta_tx| {
+            Box::pin(async move {
+                if let Some(tx) = delta_tx {
+                    tx.send("echo: ".to_string()).await.unwrap();
+                    tx.send(text.clone()).await.unwrap();
+                }
+                Ok(ChannelResponse::Text(format!("{visitor} said {text}")))
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn streams_deltas_then_final_message() {
+        let url = serve(WebChatChannel::new(streaming_echo()).with_title("Help".into())).await;
+        let visitor = "visitor-0123456789";
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("{url}?visitor={visitor}"))
+            .await
+            .unwrap();
+
+        let ready = next_frame(&mut ws).await;
+        assert_eq!(ready["type"], "ready");
+        assert_eq!(ready["visitor_id"], visitor);
+        assert_eq!(ready["title"], "Help");
+
+        ws.send(tungstenite::Message::Text(
+            r#"{"type":"message","text":"hello"}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "delta", "text": "echo: "})
+        );
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "delta", "text": "hello"})
+        );
+        assert_eq!(
+            next_frame(&mut ws).await,
+            serde_json::json!({"type": "message", "text": format!("{visitor} said hello")})
+        );
+    }
+
+    #[tokio::test]
+    async fn issues_visitor_id_when_missing_or_invalid() {
+        let url = serve(WebChatChannel::new(streaming_echo())).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("{url}?visitor=short"))
+            .await
+            .unwrap();
+        let ready = next_frame(&mut ws).await;
+        let id = ready["visitor_id"].as_str().unwrap();
+        assert_eq!(id.len(), 32);
+        assert!(valid_visitor_id(id));
+    }
+
+    #[tokio::test]
+    async fn rejects_disallowed_origin() {
+        let channel = WebChatChannel::new(streaming_echo())
+            .with_allowed_origins(vec!["https://example.com".into()]);
+        let url = serve(channel).await;
+
+        let mut req = url.clone().into_client_request().unwrap();
+        req.headers_mut()
+            .insert("origin", "https://evil.example".parse().unwrap());
+        match tokio_tungstenite::connect_async(req).await {
+            Err(tungstenite::Error::Http(resp)) => {
+                assert_eq!(resp.status(), StatusCode::FORBIDDEN)
+            }
+            other => panic!("expected 403, got {other:?}"),
+        }
+
+        let mut req = url.into_client_request().unwrap();
+        req.headers_mut()
+            .insert("origin", "https://example.com".parse().unwrap());
+        assert!(tokio_tungstenite::connect_async(req).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn blocked_and_failed_turns() {
+        let on_message: WebChatOnMessageFn = Arc::new(|_, text, _| {
+            Box::pin(async move {
+                if text == "blocked" {
+                    Err("__blocked__".to_string())
+                } else {
+                    Err("boom".to_string())
+                }
+            })
+        });
+        let url = serve(WebChatChannel::new(on_message)).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        next_frame(&mut ws).await;
+
+        for text in ["blocked", "other"] {
+            ws.send(tungstenite::Message::Text(
+                serde_json::json!({"type": "message", "text": text})
+                    .to_string()
+                    .into(),
+            ))
+            .await
+            .unwrap();
+        }
+        // The blocked turn produces nothing; the failed one an error frame.
+        let err = next_frame(&mut ws).await;
+        assert_eq!(err["type"], "error");
+    }
+
+    #[test]
+    fn parses_only_non_empty_message_frames() {
+        assert_eq!(
+            parse_message(r#"{"type":"message","text":" hi "}"#),
+            Some("hi".to_string())
+        );
+        assert_eq!(parse_message(r#"{"type":"message","text":"  "}"#), None);
+        assert_eq!(parse_message(r#"{"type":"typing"}"#), None);
+        assert_eq!(parse_message("hi"), None);
+    }
+
+    #[test]
+    fn page_escapes_title() {
+        assert_eq!(
+            html_escape(r#"<b>"Help" & more</b>"#),
+            "&lt;b&gt;&quot;Help&quot; &amp; more&lt;/b&gt;"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// OpenCrust web chat widget.
+//
+// Embed with:
+//   <script src="https://your-gateway/webchat/widget.js" async></script>
+//
+// Optional attributes on the script tag:
+//   data-channel  config key of the webchat channel (default: first one)
+//   data-title    heading override (default: the channel's `title` setting)
+//   data-open     "true" to start with the chat panel open
+(function () {
+  "use strict";
+
+  var script = document.currentScript;
+  if (!script) return;
+
+  var base = new URL(script.src, location.href);
+  var channel = script.getAttribute("data-channel");
+  var titleOverride = script.getAttribute("data-title");
+  var startOpen = script.getAttribute("data-open") === "true";
+  var storageKey = "opencrust-webchat-visitor" + (channel ? "-" + channel : "");
+
+  function storedVisitor() {
+    try {
+      return localStorage.getItem(storageKey);
+    } catch (e) {
+      return null;
+    }
+  }
+
+  function storeVisitor(id) {
+    try {
+      localStorage.setItem(storageKey, id);
+    } catch (e) {
+      /* storage disabled; the visitor gets a new id next visit */
+    }
+  }
+
+  function socketUrl() {
+    var url = new URL("/webchat/ws", base);
+    url.protocol = base.protocol === "https:" ? "wss:" : "ws:";
+    if (channel) url.searchParams.set("channel", channel);
+    var visitor = storedVisitor();
+    if (visitor) url.searchParams.set("visitor", visitor);
+    return url.toString();
+  }
+
+  var host = document.createElement("div");
+  host.id = "opencrust-webchat";
+  var root = host.attachShadow({ mode: "open" });
+  root.innerHTML =
+    "<style>" +
+    ":host{all:initial}" +
+    "*{box-sizing:border-box;font-family:system-ui,-apple-system,sans-serif}" +
+    ".launcher{position:fixed;right:20px;bottom:20px;width:56px;height:56px;border-radius:50%;" +
+    "border:none;background:#d9480f;color:#fff;font-size:24px;cursor:pointer;" +
+    "box-shadow:0 4px 12px rgba(0,0,0,.25);z-index:2147483000}" +
+    ".panel{position:fixed;right:20px;bottom:88px;width:360px;max-width:calc(100vw - 40px);" +
+    "height:520px;max-height:calc(100vh - 120px);display:none;flex-direction:column;" +
+    "background:#fff;border-radius:12px;overflow:hidden;box-shadow:0 8px 30px rgba(0,0,0,.25);" +
+    "z-index:2147483000}" +
+    ".panel.open{display:flex}" +
+    ".header{padding:14px 16px;background:#d9480f;color:#fff;font-weight:600;font-size:15px;" +
+    "display:flex;justify-content:space-between;align-items:center}" +
+    ".status{font-size:11px;font-weight:400;opacity:.85}" +
+    ".log{flex:1;overflow-y:auto;padding:12px;background:#f8f9fa}" +
+    ".msg{max-width:80%;margin:6px 0;padding:8px 12px;border-radius:12px;font-size:14px;" +
+    "line-height:1.4;white-space:pre-wrap;word-wrap:break-word}" +
+    ".msg img{display:block;max-width:100%;border-radius:8px;margin-bottom:4px}" +
+    ".msg a{color:inherit}" +
+    ".user{margin-left:auto;background:#d9480f;color:#fff}" +
+    ".bot{margin-right:auto;background:#e9ecef;color:#212529}" +
+    ".error{margin-right:auto;background:#fff5f5;color:#c92a2a}" +
+    "form{display:flex;border-top:1px solid #dee2e6}" +
+    "textarea{flex:1;border:none;padding:12px;font-size:14px;resize:none;outline:none;height:48px}" +
+    "button.send{border:none;background:none;color:#d9480f;font-weight:600;padding:0 16px;cursor:pointer}" +
+    "button.send:disabled{color:#adb5bd;cursor:default}" +
+    "</style>" +
+    '<div class="panel" part="panel">' +
+    '<div class="header"><span class="title"></span><span class="status"></span></div>' +
+    '<div class="log" aria-live="polite"></div>' +
+    '<form><textarea placeholder="Type a message…" aria-label="Message"></textarea>' +
+    '<button class="send" type="submit">Send</button></form>' +
+    "</div>" +
+    '<button class="launcher" part="launcher" aria-label="Open chat">\u{1F4AC}</button>';
+
+  var panel = root.querySelector(".panel");
+  var launcher = root.querySelector(".launcher");
+  var titleEl = root.querySelector(".title");
+  var statusEl = root.querySelector(".status");
+  var log = root.querySelector(".log");
+  var form = root.querySelector("form");
+  var input = root.querySelector("textarea");
+  var sendButton = root.querySelector("button.send");
+
+  titleEl.textContent = titleOverride || "Chat";
+
+  var ws = null;
+  var connected = false;
+  var retryDelay = 1000;
+  var pending = null; // bubble receiving streamed deltas
+
+  function setStatus(text) {
+    statusEl.textContent = text;
+    sendButton.disabled = !connected;
+  }
+
+  function append(kind, text) {
+    var el = document.createElement("div");
+    el.className = "msg " + kind;
+    el.textContent = text;
+    log.appendChild(el);
+    log.scrollTop = log.scrollHeight;
+    return el;
+  }
+
+  function appendFile(data) {
+    var el = append("bot", "");
+    if (/^data:image\//.test(data.url)) {
+      var img = document.createElement("img");
+      img.src = data.url;
+      img.alt = data.name;
+      el.appendChild(img);
+    } else {
+      var link = document.createElement("a");
+      link.href = data.url;
+      link.download = data.name;
+      link.textContent = data.name;
+      el.appendChild(link);
+    }
+    if (data.text) el.appendChild(document.createTextNode(data.text));
+    log.scrollTop = log.scrollHeight;
+  }
+
+  function connect() {
+    setStatus("connecting…");
+    ws = new WebSocket(socketUrl());
+
+    ws.onmessage = function (event) {
+      var data;
+      try {
+        data = JSON.parse(event.data);
+      } catch (e) {
+        return;
+      }
+      switch (data.type) {
+        case "ready":
+          connected = true;
+          retryDelay = 1000;
+          storeVisitor(data.visitor_id);
+          if (!titleOverride && data.title) titleEl.textContent = data.title;
+          setStatus("");
+          break;
+        case "delta":
+          if (!pending) pending = append("bot", "");
+          pending.textContent += data.text;
+          log.scrollTop = log.scrollHeight;
+          break;
+        case "message":
+          if (pending) {
+            pending.textContent = data.text;
+            pending = null;
+          } else {
+            append("bot", data.text);
+          }
+          break;
+        case "file":
+          appendFile(data);
+          break;
+        case "error":
+          pending = null;
+          append("error", data.text);
+          break;
+      }
+    };
+
+    ws.onclose = function () {
+      connected = false;
+      pending = null;
+      setStatus("reconnecting…");
+      setTimeout(connect, retryDelay);
+      retryDelay = Math.min(retryDelay * 2, 30000);
+    };
+  }
+
+  function send() {
+    var text = input.value.trim();
+    if (!text || !connected) return;
+    ws.send(JSON.stringify({ type: "message", text: text }));
+    append("user", text);
+    input.value = "";
+  }
+
+  form.addEventListener("submit", function (event) {
+    event.preventDefault();
+    send();
+  });
+
+  input.addEventListener("keydown", function (event) {
+    if (event.key === "Enter" && !event.shiftKey) {
+      event.preventDefault();
+      send();
+    }
+  });
+
+  function toggle(open) {
+    panel.classList.toggle("open", open);
+    launcher.setAttribute("aria-label", open ? "Close chat" : "Open chat");
+    if (open) {
+      if (!ws) connect();
+      input.focus();
+    }
+  }
+
+  launcher.addEventListener("click", function () {
+    toggle(!panel.classList.contains("open"));
+  });
+
+  function mount() {
+    document.body.appendChild(host);
+    if (startOpen) toggle(true);
+  }
+
+  if (document.body) {
+    mount();
+  } else {
+    document.addEventListener("DOMContentLoaded", mount);
+  }
+})();
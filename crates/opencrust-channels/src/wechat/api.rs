@@ -188,18 +188,41 @@ pub async fn upload_voice(
     access_token: &str,
     audio: &[u8],
     base_url: &str,
+) -> Result<String, String> {
+    upload_media(
+        client,
+        access_token,
+        "voice",
+        audio,
+        "voice.ogg",
+        "audio/ogg",
+        base_url,
+    )
+    .await
+}
+
+/// Upload bytes as a temporary media file of `media_type` (`image`, `voice`,
+/// `video` or `thumb`) and return its `media_id` (valid for 3 days).
+pub async fn upload_media(
+    client: &Client,
+    access_token: &str,
+    media_type: &str,
+    data: &[u8],
+    filename: &str,
+    mime_type: &str,
+    base_url: &str,
 ) -> Result<String, String> {
     use reqwest::multipart;
 
-    let part = multipart::Part::bytes(audio.to_vec())
-        .file_name("voice.ogg")
-        .mime_str("audio/ogg")
+    let part = multipart::Part::bytes(data.to_vec())
+        .file_name(filename.to_string())
+        .mime_str(mime_type)
         .map_err(|e| format!("wechat: failed to build multipart part: {e}"))?;
     let form = multipart::Form::new().part("media", part);
 
     let resp = client
         .post(format!(
-            "{base_url}/media/upload?access_token={access_token}&type=voice"
+            "{base_url}/media/upload?access_token={access_token}&type={media_type}"
         ))
         .multipart(form)
         .send()
@@ -249,29 +272,9 @@ pub async fn download_pic(client: &Client, url: &str) -> Result<Vec<u8>, String>
         return Err(format!("wechat download_pic error {status}: {body}"));
     }
 
-    if let Some(len) = resp.content_length()
-        && len > crate::MAX_DOWNLOAD_BYTES as u64
-    {
-        return Err(format!(
-            "wechat file too large: {len} bytes exceeds {} byte limit",
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    let bytes = resp
-        .bytes()
+    crate::download::read_limited(resp, crate::MAX_DOWNLOAD_BYTES)
         .await
-        .map_err(|e| format!("wechat download_pic read failed: {e}"))?;
-
-    if bytes.len() > crate::MAX_DOWNLOAD_BYTES {
-        return Err(format!(
-            "wechat file too large: {} bytes exceeds {} byte limit",
-            bytes.len(),
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    Ok(bytes.to_vec())
+        .map_err(|e| format!("wechat {e}"))
 }
 
 /// Push a voice message to a user via the Customer Service API.
@@ -2,7 +2,6 @@ pub mod api;
 pub mod fmt;
 pub mod webhook;
 
-use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -13,8 +12,9 @@ use reqwest::Client;
 use tokio::sync::{RwLock, mpsc};
 use tracing::info;
 
+use crate::dedup::{InboundDedup, SeenFn};
 use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
+use opencrust_common::{MediaAttachment, MediaKind, Message, MessageContent, Result};
 
 /// Group filter closure for WeChat channels.
 /// Returns `true` if the message should be processed.
@@ -55,10 +55,6 @@ pub type WeChatOnMessageFn = Arc<
 /// Refresh slightly early to avoid expiry during a request.
 const TOKEN_TTL: Duration = Duration::from_secs(7000);
 
-/// How long to remember a MsgId for deduplication.
-/// WeChat retries up to 3 times over ~15 seconds; 120 s gives a safe margin.
-const MSG_ID_TTL: Duration = Duration::from_secs(120);
-
 /// Cached access token with the instant it was fetched.
 type TokenCache = Arc<RwLock<Option<(String, Instant)>>>;
 
@@ -75,8 +71,8 @@ pub struct WeChatChannel {
     on_message: WeChatOnMessageFn,
     group_filter: WeChatGroupFilter,
     token_cache: TokenCache,
-    /// In-memory deduplication cache for WeChat MsgIds (TTL = MSG_ID_TTL).
-    msg_id_cache: Arc<RwLock<HashMap<String, Instant>>>,
+    /// Record of already handled MsgIds.
+    dedup: InboundDedup,
 }
 
 impl WeChatChannel {
@@ -108,7 +104,7 @@ impl WeChatChannel {
             on_message,
             group_filter,
             token_cache: Arc::new(RwLock::new(None)),
-            msg_id_cache: Arc::new(RwLock::new(HashMap::new())),
+            dedup: InboundDedup::default(),
         }
     }
 
@@ -118,6 +114,12 @@ impl WeChatChannel {
         self
     }
 
+    /// Drop retried MsgIds using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
     /// Override the WeChat API base URL (e.g. to point at a mock server in tests).
     pub fn with_api_base_url(mut self, base_url: String) -> Self {
         self.api_base_url = base_url;
@@ -180,22 +182,9 @@ impl WeChatChannel {
         .await
     }
 
-    /// Check whether `msg_id` was already processed within `MSG_ID_TTL`.
-    ///
-    /// Returns `true` (duplicate — skip) or `false` (new — record and process).
-    /// Expired entries are pruned on every call to bound memory usage.
-    pub async fn check_and_mark_msg_id(&self, msg_id: &str) -> bool {
-        if msg_id.is_empty() {
-            return false;
-        }
-        let now = Instant::now();
-        let mut cache = self.msg_id_cache.write().await;
-        cache.retain(|_, seen_at| now.duration_since(*seen_at) < MSG_ID_TTL);
-        if cache.contains_key(msg_id) {
-            return true;
-        }
-        cache.insert(msg_id.to_string(), now);
-        false
+    /// Whether the message with `msg_id` has not been handled yet.
+    pub fn first_delivery(&self, msg_id: &str) -> bool {
+        self.dedup.first_time(msg_id)
     }
 }
 
@@ -318,6 +307,64 @@ async fn get_token_cached(
     Ok(token)
 }
 
+/// Upload a photo or voice note and push it, followed by its caption.
+///
+/// Videos need a separate thumbnail and the Customer Service API has no
+/// file message, so those go out as [`MediaAttachment::describe`] text.
+async fn push_attachment(
+    client: &reqwest::Client,
+    access_token: &str,
+    openid: &str,
+    attachment: &MediaAttachment,
+    api_base_url: &str,
+) -> Result<()> {
+    let media_type = match attachment.kind {
+        MediaKind::Photo => "image",
+        MediaKind::Voice => "voice",
+        MediaKind::Video | MediaKind::Document => {
+            let text = fmt::to_wechat_text(&attachment.describe());
+            return api::push(client, access_token, openid, &text, api_base_url)
+                .await
+                .map_err(|e| opencrust_common::Error::Channel(format!("wechat push failed: {e}")));
+        }
+    };
+    let mime = attachment
+        .mime_type
+        .as_deref()
+        .unwrap_or("application/octet-stream");
+    let filename = attachment.filename.clone().unwrap_or_else(|| {
+        let ext = if media_type == "image" { "jpg" } else { "amr" };
+        format!("{media_type}.{ext}")
+    });
+    let media_id = api::upload_media(
+        client,
+        access_token,
+        media_type,
+        &attachment.data,
+        &filename,
+        mime,
+        api_base_url,
+    )
+    .await
+    .map_err(|e| opencrust_common::Error::Channel(format!("wechat upload failed: {e}")))?;
+    let pushed = if media_type == "image" {
+        api::push_image(client, access_token, openid, &media_id, api_base_url).await
+    } else {
+        api::push_voice(client, access_token, openid, &media_id, api_base_url).await
+    };
+    pushed.map_err(|e| {
+        opencrust_common::Error::Channel(format!("wechat {media_type} push failed: {e}"))
+    })?;
+
+    if let Some(caption) = attachment.caption.as_deref().filter(|c| !c.is_empty()) {
+        let text = fmt::to_wechat_text(caption);
+        api::push(client, access_token, openid, &text, api_base_url)
+            .await
+            .map_err(|e| opencrust_common::Error::Channel(format!("wechat push failed: {e}")))?;
+    }
+    Ok(())
+}
+
 /// Push a message via WeChat Customer Service API.
 ///
 /// Uses a cached access token (refreshed after TOKEN_TTL) to avoid hitting
@@ -353,6 +400,9 @@ async fn wechat_push_message(
                     opencrust_common::Error::Channel(format!("wechat push failed: {e}"))
                 })?;
         }
+        MessageContent::Attachment(attachment) => {
+            push_attachment(client, &access_token, openid, attachment, api_base_url).await?;
+        }
         MessageContent::Image { .. } => {
             let media_id = message
                 .metadata
@@ -557,6 +607,48 @@ mod tests {
         assert!(matches!(result, Ok(ChannelResponse::Text(t)) if t == "none"));
     }
 
+    #[tokio::test]
+    async fn photo_attachments_are_uploaded_then_pushed() {
+        use wiremock::matchers::{body_string_contains, method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/media/upload"))
+            .and(query_param("type", "image"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"media_id": "m1"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/message/custom/send"))
+            .and(body_string_contains("\"media_id\":\"m1\""))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"errcode": 0})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/message/custom/send"))
+            .and(body_string_contains("Weekly numbers"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"errcode": 0})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let photo = MediaAttachment::new(MediaKind::Photo, b"jpeg".to_vec())
+            .with_mime_type(Some("image/jpeg".into()))
+            .with_caption(Some("Weekly numbers".into()));
+        push_attachment(&Client::new(), "tok", "oUser123", &photo, &server.uri())
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn connect_sets_status_connected() {
         let mut ch = WeChatChannel::new(
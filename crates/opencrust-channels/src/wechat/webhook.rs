@@ -215,7 +215,7 @@ pub async fn wechat_webhook(
 
     // Deduplicate: WeChat retries the same MsgId up to 3 times when no
     // response arrives within 5 seconds. Drop already-seen messages.
-    if channel.check_and_mark_msg_id(&msg_id).await {
+    if !channel.first_delivery(&msg_id) {
         info!("wechat: duplicate MsgId={msg_id} from openid={from_user}, dropping");
         return (
             StatusCode::OK,
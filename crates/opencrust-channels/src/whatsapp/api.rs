@@ -19,6 +19,45 @@ struct WhatsAppMessage {
     text: WhatsAppTextBody,
 }
 
+#[derive(Serialize)]
+struct WhatsAppTemplateMessage {
+    messaging_product: String,
+    recipient_type: String,
+    to: String,
+    #[serde(rename = "type")]
+    msg_type: String,
+    template: WhatsAppTemplateBody,
+}
+
+#[derive(Serialize)]
+struct WhatsAppTemplateBody {
+    name: String,
+    language: WhatsAppTemplateLanguage,
+    components: Vec<WhatsAppTemplateComponent>,
+}
+
+#[derive(Serialize)]
+struct WhatsAppTemplateLanguage {
+    code: String,
+}
+
+#[derive(Serialize)]
+struct WhatsAppTemplateComponent {
+    #[serde(rename = "type")]
+    component_type: String,
+    parameters: Vec<WhatsAppTemplateParameter>,
+}
+
+#[derive(Serialize)]
+struct WhatsAppTemplateParameter {
+    #[serde(rename = "type")]
+    param_type: String,
+    text: String,
+}
+
+/// Longest text WhatsApp accepts in a template body parameter.
+const MAX_TEMPLATE_PARAM_CHARS: usize = 1024;
+
 #[derive(Serialize)]
 struct WhatsAppReadReceipt {
     messaging_product: String,
@@ -62,6 +101,181 @@ pub async fn send_text_message(
     Ok(())
 }
 
+/// Upload media bytes to WhatsApp. Returns the media ID to reference in a
+/// media message.
+pub async fn upload_media(
+    client: &Client,
+    token: &str,
+    phone_number_id: &str,
+    data: &[u8],
+    filename: &str,
+    mime_type: &str,
+) -> Result<String, String> {
+    use reqwest::multipart;
+
+    let part = multipart::Part::bytes(data.to_vec())
+        .file_name(filename.to_string())
+        .mime_str(mime_type)
+        .map_err(|e| format!("WhatsApp upload_media invalid mime type: {e}"))?;
+    let form = multipart::Form::new()
+        .text("messaging_product", "whatsapp")
+        .text("type", mime_type.to_string())
+        .part("file", part);
+
+    let resp = client
+        .post(format!("{GRAPH_API_BASE}/{phone_number_id}/media"))
+        .bearer_auth(token)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("WhatsApp upload_media failed: {e}"))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        warn!("WhatsApp upload_media error {status}: {body}");
+        return Err(format!("WhatsApp API error {status}: {body}"));
+    }
+
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("WhatsApp upload_media parse failed: {e}"))?;
+    body.get("id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "WhatsApp upload_media response missing 'id'".to_string())
+}
+
+/// Send previously uploaded media. `media_type` is the WhatsApp message type
+/// (`image`, `audio`, `video` or `document`). Audio messages cannot carry a
+/// caption; `filename` is only shown for documents.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_media_message(
+    client: &Client,
+    token: &str,
+    phone_number_id: &str,
+    to: &str,
+    media_type: &str,
+    media_id: &str,
+    caption: Option<&str>,
+    filename: Option<&str>,
+) -> Result<(), String> {
+    let mut media = serde_json::json!({ "id": media_id });
+    if let Some(caption) = caption.filter(|_| media_type != "audio") {
+        media["caption"] = serde_json::Value::String(caption.to_string());
+    }
+    if let Some(filename) = filename.filter(|_| media_type == "document") {
+        media["filename"] = serde_json::Value::String(filename.to_string());
+    }
+    let msg = serde_json::json!({
+        "messaging_product": "whatsapp",
+        "recipient_type": "individual",
+        "to": to,
+        "type": media_type,
+        media_type: media,
+    });
+
+    let resp = client
+        .post(format!("{GRAPH_API_BASE}/{phone_number_id}/messages"))
+        .bearer_auth(token)
+        .json(&msg)
+        .send()
+        .await
+        .map_err(|e| format!("WhatsApp send_media_message failed: {e}"))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        warn!("WhatsApp send_media_message error {status}: {body}");
+        return Err(format!("WhatsApp API error {status}: {body}"));
+    }
+
+    Ok(())
+}
+
+/// Send a pre-approved template message.
+///
+/// WhatsApp only allows free-form text within 24 hours of the user's last
+/// message; outside that window, business-initiated messages must use a
+/// template registered in Meta Business Manager. Each of `params` fills one
+/// `{{n}}` placeholder in the template body, in order.
+pub async fn send_template_message(
+    client: &Client,
+    token: &str,
+    phone_number_id: &str,
+    to: &str,
+    template_name: &str,
+    language: &str,
+    params: &[&str],
+) -> Result<(), String> {
+    let msg = template_message(to, template_name, language, params);
+
+    let resp = client
+        .post(format!("{GRAPH_API_BASE}/{phone_number_id}/messages"))
+        .bearer_auth(token)
+        .json(&msg)
+        .send()
+        .await
+        .map_err(|e| format!("WhatsApp send_template_message failed: {e}"))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        warn!("WhatsApp send_template_message error {status}: {body}");
+        return Err(format!("WhatsApp API error {status}: {body}"));
+    }
+
+    Ok(())
+}
+
+fn template_message(
+    to: &str,
+    template_name: &str,
+    language: &str,
+    params: &[&str],
+) -> WhatsAppTemplateMessage {
+    let components = if params.is_empty() {
+        Vec::new()
+    } else {
+        vec![WhatsAppTemplateComponent {
+            component_type: "body".to_string(),
+            parameters: params
+                .iter()
+                .map(|p| WhatsAppTemplateParameter {
+                    param_type: "text".to_string(),
+                    text: template_param(p),
+                })
+                .collect(),
+        }]
+    };
+    WhatsAppTemplateMessage {
+        messaging_product: "whatsapp".to_string(),
+        recipient_type: "individual".to_string(),
+        to: to.to_string(),
+        msg_type: "template".to_string(),
+        template: WhatsAppTemplateBody {
+            name: template_name.to_string(),
+            language: WhatsAppTemplateLanguage {
+                code: language.to_string(),
+            },
+            components,
+        },
+    }
+}
+
+/// Template parameters may not contain newlines, tabs or runs of more than
+/// four spaces, and are capped at 1024 characters.
+fn template_param(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= MAX_TEMPLATE_PARAM_CHARS {
+        return flat;
+    }
+    let mut cut: String = flat.chars().take(MAX_TEMPLATE_PARAM_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
 /// Download a media file by its WhatsApp Cloud API media ID.
 ///
 /// Two-step process:
@@ -112,29 +326,9 @@ pub async fn download_media(
         return Err(format!("WhatsApp media download error {status}: {body}"));
     }
 
-    if let Some(len) = file_resp.content_length()
-        && len > crate::MAX_DOWNLOAD_BYTES as u64
-    {
-        return Err(format!(
-            "WhatsApp file too large: {len} bytes exceeds {} byte limit",
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    let bytes = file_resp
-        .bytes()
+    crate::download::read_limited(file_resp, crate::MAX_DOWNLOAD_BYTES)
         .await
-        .map_err(|e| format!("WhatsApp download_media read failed: {e}"))?;
-
-    if bytes.len() > crate::MAX_DOWNLOAD_BYTES {
-        return Err(format!(
-            "WhatsApp file too large: {} bytes exceeds {} byte limit",
-            bytes.len(),
-            crate::MAX_DOWNLOAD_BYTES
-        ));
-    }
-
-    Ok(bytes.to_vec())
+        .map_err(|e| format!("WhatsApp {e}"))
 }
 
 /// Mark a message as read.
@@ -168,6 +362,33 @@ pub async fn mark_as_read(
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn template_message_body_shape() {
+        let msg = template_message("15551234567", "opencrust_update", "en_US", &["Hi\nthere"]);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "template");
+        assert_eq!(json["template"]["name"], "opencrust_update");
+        assert_eq!(json["template"]["language"]["code"], "en_US");
+        let param = &json["template"]["components"][0]["parameters"][0];
+        assert_eq!(param["type"], "text");
+        assert_eq!(param["text"], "Hi there");
+
+        let bare =
+            serde_json::to_value(template_message("1", "hello_world", "en_US", &[])).unwrap();
+        assert_eq!(bare["template"]["components"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn template_param_is_capped() {
+        let long = "a".repeat(2000);
+        assert_eq!(
+            template_param(&long).chars().count(),
+            MAX_TEMPLATE_PARAM_CHARS
+        );
+    }
+
     #[test]
     fn download_size_limit_constant_is_10_mib() {
         assert_eq!(crate::MAX_DOWNLOAD_BYTES, 10 * 1024 * 1024);
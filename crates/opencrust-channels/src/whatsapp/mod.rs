@@ -3,37 +3,28 @@ pub mod api;
 pub mod web;
 pub mod webhook;
 
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use reqwest::Client;
+use ring::hmac;
 use tokio::sync::mpsc;
 use tracing::info;
 
+use crate::dedup::{InboundDedup, SeenFn};
 use crate::traits::{ChannelLifecycle, ChannelResponse, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
-
-/// A file attached to a WhatsApp message, with bytes already downloaded.
-///
-/// For WhatsApp Business the channel handler downloads the media before
-/// invoking `WhatsAppOnMessageFn`. For WhatsApp Web the sidecar does not
-/// yet emit file events, so this will always be `None` on that path.
-#[derive(Debug, Clone)]
-pub struct WhatsAppFile {
-    /// Original filename as reported by WhatsApp (may be empty for some types).
-    pub filename: String,
-    /// Raw file bytes.
-    pub data: Vec<u8>,
-    /// MIME type string (e.g. `"application/pdf"`).
-    pub mime_type: Option<String>,
-}
+use opencrust_common::{MediaAttachment, MediaKind, Message, MessageContent, Result};
 
 /// Callback invoked when the bot receives a message from WhatsApp.
 ///
 /// Arguments: `(from_number, user_name, text, is_group, file, delta_tx)`.
-/// `file` is `Some` when the user sent a document/image along with the message.
+/// `file` is `Some` when the user sent a photo, voice note, video or document;
+/// WhatsApp Business downloads it before the callback runs. The WhatsApp Web
+/// sidecar does not emit media yet, so it is always `None` on that path.
 /// `delta_tx` is always `None` for WhatsApp (no streaming support).
 /// Return `Err("__blocked__")` to silently drop the message (unauthorized user).
 pub type WhatsAppOnMessageFn = Arc<
@@ -42,7 +33,7 @@ pub type WhatsAppOnMessageFn = Arc<
             String,
             String,
             bool,
-            Option<WhatsAppFile>,
+            Option<MediaAttachment>,
             Option<mpsc::Sender<String>>,
         )
             -> Pin<Box<dyn Future<Output = std::result::Result<ChannelResponse, String>> + Send>>
@@ -50,15 +41,36 @@ pub type WhatsAppOnMessageFn = Arc<
         + Sync,
 >;
 
+/// How long after a user's last message free-form replies are allowed.
+const SERVICE_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An approved message template used for messages sent outside the 24-hour
+/// service window (reminders, alerts). The message text fills the
+/// template's single `{{1}}` body placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhatsAppTemplate {
+    pub name: String,
+    /// Template language code, e.g. `en_US`.
+    pub language: String,
+}
+
+/// When each user last wrote to the bot, keyed by phone number.
+type LastInbound = Arc<Mutex<HashMap<String, Instant>>>;
+
 pub struct WhatsAppChannel {
     client: Client,
     access_token: String,
     phone_number_id: String,
     verify_token: String,
+    app_secret: String,
     name: String,
     display: String,
     status: ChannelStatus,
     on_message: WhatsAppOnMessageFn,
+    template: Option<WhatsAppTemplate>,
+    last_inbound: LastInbound,
+    dedup: InboundDedup,
+    read_receipts: bool,
 }
 
 impl WhatsAppChannel {
@@ -66,6 +78,7 @@ impl WhatsAppChannel {
         access_token: String,
         phone_number_id: String,
         verify_token: String,
+        app_secret: String,
         on_message: WhatsAppOnMessageFn,
     ) -> Self {
         Self {
@@ -73,10 +86,15 @@ impl WhatsAppChannel {
             access_token,
             phone_number_id,
             verify_token,
+            app_secret,
             name: "whatsapp".to_string(),
             display: "WhatsApp".to_string(),
             status: ChannelStatus::Disconnected,
             on_message,
+            template: None,
+            last_inbound: Arc::new(Mutex::new(HashMap::new())),
+            dedup: InboundDedup::default(),
+            read_receipts: true,
         }
     }
 
@@ -86,6 +104,37 @@ impl WhatsAppChannel {
         self
     }
 
+    /// Template for proactive messages to users outside the service window.
+    /// Without one, such messages are attempted as plain text and WhatsApp
+    /// rejects them.
+    pub fn with_template(mut self, template: WhatsAppTemplate) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Drop re-posted webhooks using `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
+    /// Whether the message with `message_id` has not been handled yet. Meta
+    /// posts a webhook again when it is not acknowledged quickly.
+    pub fn first_delivery(&self, message_id: &str) -> bool {
+        self.dedup.first_time(message_id)
+    }
+
+    /// Whether incoming messages are marked as read (blue ticks) when the
+    /// bot starts working on them. On by default.
+    pub fn with_read_receipts(mut self, enabled: bool) -> Self {
+        self.read_receipts = enabled;
+        self
+    }
+
+    pub fn read_receipts(&self) -> bool {
+        self.read_receipts
+    }
+
     /// Access token for the WhatsApp Cloud API.
     pub fn access_token(&self) -> &str {
         &self.access_token
@@ -101,6 +150,17 @@ impl WhatsAppChannel {
         &self.verify_token
     }
 
+    /// Verify the `X-Hub-Signature-256` header of a webhook request: Meta
+    /// signs the raw body with HMAC-SHA256 using the app secret and sends
+    /// `sha256=<hex digest>`. The comparison is constant-time.
+    pub fn verify_signature(&self, body: &[u8], signature: &str) -> bool {
+        let Some(expected) = signature.strip_prefix("sha256=").and_then(decode_hex) else {
+            return false;
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.app_secret.as_bytes());
+        hmac::verify(&key, body, &expected).is_ok()
+    }
+
     /// HTTP client shared across requests.
     pub fn client(&self) -> &Client {
         &self.client
@@ -112,8 +172,12 @@ impl WhatsAppChannel {
         from: &str,
         user_name: &str,
         text: &str,
-        file: Option<WhatsAppFile>,
+        file: Option<MediaAttachment>,
     ) -> std::result::Result<ChannelResponse, String> {
+        self.last_inbound
+            .lock()
+            .unwrap()
+            .insert(from.to_string(), Instant::now());
         (self.on_message)(
             from.to_string(),
             user_name.to_string(),
@@ -132,6 +196,8 @@ pub struct WhatsAppSender {
     access_token: String,
     phone_number_id: String,
     name: String,
+    template: Option<WhatsAppTemplate>,
+    last_inbound: LastInbound,
 }
 
 #[async_trait]
@@ -149,6 +215,8 @@ impl ChannelSender for WhatsAppSender {
             &self.client,
             &self.access_token,
             &self.phone_number_id,
+            self.template.as_ref(),
+            &self.last_inbound,
             message,
         )
         .await
@@ -167,6 +235,8 @@ impl ChannelLifecycle for WhatsAppChannel {
             access_token: self.access_token.clone(),
             phone_number_id: self.phone_number_id.clone(),
             name: self.name.clone(),
+            template: self.template.clone(),
+            last_inbound: Arc::clone(&self.last_inbound),
         })
     }
 
@@ -203,6 +273,8 @@ impl ChannelSender for WhatsAppChannel {
             &self.client,
             &self.access_token,
             &self.phone_number_id,
+            self.template.as_ref(),
+            &self.last_inbound,
             message,
         )
         .await
@@ -214,6 +286,8 @@ async fn whatsapp_send_message(
     client: &Client,
     access_token: &str,
     phone_number_id: &str,
+    template: Option<&WhatsAppTemplate>,
+    last_inbound: &LastInbound,
     message: &Message,
 ) -> Result<()> {
     let to = message
@@ -224,20 +298,99 @@ async fn whatsapp_send_message(
             opencrust_common::Error::Channel("missing whatsapp_from in metadata".into())
         })?;
 
+    let template = template.filter(|_| !in_service_window(last_inbound, to));
     let text = match &message.content {
         MessageContent::Text(t) => t.clone(),
+        // Outside the service window only templates can be sent, so the
+        // attachment is described in the template text instead.
+        MessageContent::Attachment(attachment) if template.is_some() => attachment.describe(),
+        MessageContent::Attachment(attachment) => {
+            return send_attachment(client, access_token, phone_number_id, to, attachment)
+                .await
+                .map_err(|e| {
+                    opencrust_common::Error::Channel(format!("whatsapp send failed: {e}"))
+                });
+        }
         _ => {
             return Err(opencrust_common::Error::Channel(
-                "only text messages are supported for whatsapp send".into(),
+                "only text and attachment messages are supported for whatsapp send".into(),
             ));
         }
     };
 
-    api::send_text_message(client, access_token, phone_number_id, to, &text)
-        .await
-        .map_err(|e| opencrust_common::Error::Channel(format!("whatsapp send failed: {e}")))?;
+    let sent = match template {
+        Some(template) => {
+            api::send_template_message(
+                client,
+                access_token,
+                phone_number_id,
+                to,
+                &template.name,
+                &template.language,
+                &[&text],
+            )
+            .await
+        }
+        None => api::send_text_message(client, access_token, phone_number_id, to, &text).await,
+    };
+    sent.map_err(|e| opencrust_common::Error::Channel(format!("whatsapp send failed: {e}")))
+}
 
-    Ok(())
+/// Upload `attachment` and send it as the matching WhatsApp media message.
+async fn send_attachment(
+    client: &Client,
+    access_token: &str,
+    phone_number_id: &str,
+    to: &str,
+    attachment: &MediaAttachment,
+) -> std::result::Result<(), String> {
+    let (media_type, default_mime) = match attachment.kind {
+        MediaKind::Photo => ("image", "image/jpeg"),
+        MediaKind::Voice => ("audio", "audio/ogg"),
+        MediaKind::Video => ("video", "video/mp4"),
+        MediaKind::Document => ("document", "application/octet-stream"),
+    };
+    let filename = attachment.filename_or_default();
+    let media_id = api::upload_media(
+        client,
+        access_token,
+        phone_number_id,
+        &attachment.data,
+        &filename,
+        attachment.mime_type.as_deref().unwrap_or(default_mime),
+    )
+    .await?;
+    api::send_media_message(
+        client,
+        access_token,
+        phone_number_id,
+        to,
+        media_type,
+        &media_id,
+        attachment.caption.as_deref(),
+        Some(&filename),
+    )
+    .await
+}
+
+/// Whether `to` wrote to the bot within the last 24 hours. Unknown after a
+/// restart, so proactive messages then go out as templates.
+fn in_service_window(last_inbound: &LastInbound, to: &str) -> bool {
+    last_inbound
+        .lock()
+        .unwrap()
+        .get(to)
+        .is_some_and(|at| at.elapsed() < SERVICE_WINDOW)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
 }
 
 #[cfg(test)]
@@ -254,6 +407,7 @@ mod tests {
             "fake-token".to_string(),
             "123456".to_string(),
             "verify-me".to_string(),
+            "app-secret".to_string(),
             on_msg,
         );
         assert_eq!(channel.channel_type(), "whatsapp");
@@ -261,47 +415,100 @@ mod tests {
         assert_eq!(channel.status(), ChannelStatus::Disconnected);
     }
 
-    // --- WhatsAppFile / file-ingest tests ---
-
     #[test]
-    fn whatsapp_file_fields_accessible() {
-        let file = WhatsAppFile {
-            filename: "invoice.pdf".to_string(),
-            data: vec![1, 2, 3],
-            mime_type: Some("application/pdf".to_string()),
-        };
-        assert_eq!(file.filename, "invoice.pdf");
-        assert_eq!(file.data.len(), 3);
-        assert_eq!(file.mime_type.as_deref(), Some("application/pdf"));
+    fn read_receipts_are_on_unless_disabled() {
+        let on_msg: WhatsAppOnMessageFn =
+            Arc::new(|_from, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("test".to_string())) })
+            });
+        let channel = WhatsAppChannel::new(
+            "fake-token".to_string(),
+            "123456".to_string(),
+            "verify-me".to_string(),
+            "app-secret".to_string(),
+            on_msg,
+        );
+        assert!(channel.read_receipts());
+        assert!(!channel.with_read_receipts(false).read_receipts());
     }
 
     #[test]
-    fn whatsapp_file_mime_type_optional() {
-        let file = WhatsAppFile {
-            filename: "data.bin".to_string(),
-            data: vec![],
-            mime_type: None,
-        };
-        assert!(file.mime_type.is_none());
+    fn verify_signature_checks_app_secret() {
+        let on_msg: WhatsAppOnMessageFn =
+            Arc::new(|_from, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("ok".to_string())) })
+            });
+        let channel = WhatsAppChannel::new(
+            "fake-token".to_string(),
+            "123456".to_string(),
+            "verify-me".to_string(),
+            "app-secret".to_string(),
+            on_msg,
+        );
+        let body = br#"{"entry":[]}"#;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"app-secret");
+        let digest: String = hmac::sign(&key, body)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let signature = format!("sha256={digest}");
+
+        assert!(channel.verify_signature(body, &signature));
+        assert!(!channel.verify_signature(br#"{"entry":[{}]}"#, &signature));
+        assert!(!channel.verify_signature(body, &digest));
+        assert!(!channel.verify_signature(body, "sha256=zz"));
+        assert!(!channel.verify_signature(body, ""));
+    }
+
+    #[tokio::test]
+    async fn incoming_message_opens_service_window() {
+        let on_msg: WhatsAppOnMessageFn =
+            Arc::new(|_from, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("ok".to_string())) })
+            });
+        let channel = WhatsAppChannel::new(
+            "fake-token".to_string(),
+            "123456".to_string(),
+            "verify-me".to_string(),
+            "app-secret".to_string(),
+            on_msg,
+        );
+        assert!(!in_service_window(&channel.last_inbound, "15551234567"));
+
+        channel
+            .handle_incoming("15551234567", "Alice", "hi", None)
+            .await
+            .unwrap();
+        assert!(in_service_window(&channel.last_inbound, "15551234567"));
+        assert!(!in_service_window(&channel.last_inbound, "15557654321"));
+
+        let expired = Instant::now() - SERVICE_WINDOW - Duration::from_secs(1);
+        channel
+            .last_inbound
+            .lock()
+            .unwrap()
+            .insert("15551234567".to_string(), expired);
+        assert!(!in_service_window(&channel.last_inbound, "15551234567"));
     }
 
+    // --- Attachment / file-ingest tests ---
+
     #[tokio::test]
     async fn on_message_callback_receives_whatsapp_file() {
         let on_msg: WhatsAppOnMessageFn =
             Arc::new(|_from, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
             });
 
-        let wa_file = WhatsAppFile {
-            filename: "contract.pdf".to_string(),
-            data: vec![0u8; 32],
-            mime_type: Some("application/pdf".to_string()),
-        };
+        let wa_file = MediaAttachment::new(MediaKind::Document, vec![0u8; 32])
+            .with_filename("contract.pdf")
+            .with_mime_type(Some("application/pdf".to_string()));
 
         let result = on_msg(
             "+66812345678".to_string(),
@@ -322,7 +529,7 @@ mod tests {
             Arc::new(|_from, _user, _text, _is_group, file, _delta_tx| {
                 Box::pin(async move {
                     let name = file
-                        .map(|f| f.filename)
+                        .map(|f| f.filename_or_default())
                         .unwrap_or_else(|| "none".to_string());
                     Ok(ChannelResponse::Text(name))
                 })
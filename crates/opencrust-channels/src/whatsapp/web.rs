@@ -3,13 +3,16 @@ use std::process::Stdio;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::mpsc;
 use tracing::{info, warn};
 
+use crate::dedup::{InboundDedup, SeenFn};
 use crate::traits::{ChannelLifecycle, ChannelSender, ChannelStatus};
-use opencrust_common::{Message, MessageContent, Result};
+use opencrust_common::{MediaKind, Message, MessageContent, Result};
 
 use super::WhatsAppOnMessageFn;
 
@@ -59,6 +62,7 @@ pub struct WhatsAppWebChannel {
     group_filter: WhatsAppWebGroupFilter,
     auth_dir: PathBuf,
     sidecar_dir: PathBuf,
+    dedup: InboundDedup,
 }
 
 impl WhatsAppWebChannel {
@@ -84,6 +88,7 @@ impl WhatsAppWebChannel {
             group_filter,
             auth_dir: config_dir.join("whatsapp-web-auth"),
             sidecar_dir: config_dir.join("sidecar").join("whatsapp-web"),
+            dedup: InboundDedup::default(),
         }
     }
 
@@ -93,6 +98,13 @@ impl WhatsAppWebChannel {
         self
     }
 
+    /// Drop messages the sidecar replays (e.g. after a reconnect) using
+    /// `seen` instead of an in-memory record.
+    pub fn with_dedup(mut self, seen: SeenFn) -> Self {
+        self.dedup = InboundDedup::new(seen);
+        self
+    }
+
     // Embedded sidecar files - written to disk on first connect if not found elsewhere.
     const EMBEDDED_INDEX_MJS: &'static str =
         include_str!("../../../../sidecar/whatsapp-web/index.mjs");
@@ -263,6 +275,7 @@ impl ChannelLifecycle for WhatsAppWebChannel {
         // Reader task: parse events from sidecar stdout
         let on_message = Arc::clone(&self.on_message);
         let group_filter = Arc::clone(&self.group_filter);
+        let dedup = self.dedup.clone();
         let reply_tx = stdin_tx;
 
         tokio::spawn(async move {
@@ -324,6 +337,11 @@ impl ChannelLifecycle for WhatsAppWebChannel {
                             continue;
                         }
 
+                        let id = event.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                        if !dedup.first_time(id) {
+                            continue;
+                        }
+
                         let reply_tx = reply_tx.clone();
                         let on_message = Arc::clone(&on_message);
 
@@ -427,21 +445,33 @@ async fn whatsapp_web_send_message(tx: &mpsc::Sender<String>, message: &Message)
             opencrust_common::Error::Channel("missing whatsapp_from in metadata".into())
         })?;
 
-    let text = match &message.content {
-        MessageContent::Text(t) => t.clone(),
+    let cmd = match &message.content {
+        MessageContent::Text(text) => serde_json::json!({
+            "type": "send",
+            "to": to,
+            "text": text,
+        }),
+        MessageContent::Attachment(attachment) => serde_json::json!({
+            "type": "send_media",
+            "to": to,
+            "kind": match attachment.kind {
+                MediaKind::Photo => "image",
+                MediaKind::Voice => "audio",
+                MediaKind::Video => "video",
+                MediaKind::Document => "document",
+            },
+            "data": STANDARD.encode(&attachment.data),
+            "mimetype": attachment.mime_type,
+            "fileName": attachment.filename_or_default(),
+            "caption": attachment.caption,
+        }),
         _ => {
             return Err(opencrust_common::Error::Channel(
-                "only text messages are supported for whatsapp-web send".into(),
+                "only text and attachment messages are supported for whatsapp-web send".into(),
             ));
         }
     };
 
-    let cmd = serde_json::json!({
-        "type": "send",
-        "to": to,
-        "text": text,
-    });
-
     tx.send(serde_json::to_string(&cmd).unwrap_or_default())
         .await
         .map_err(|e| opencrust_common::Error::Channel(format!("failed to send to sidecar: {e}")))?;
@@ -465,6 +495,38 @@ mod tests {
         assert_eq!(channel.status(), ChannelStatus::Disconnected);
     }
 
+    #[tokio::test]
+    async fn attachments_are_sent_as_media_commands() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut message = Message::text(
+            opencrust_common::SessionId::new(),
+            opencrust_common::ChannelId::from_string("whatsapp-web"),
+            opencrust_common::UserId::from_string("bot"),
+            opencrust_common::MessageDirection::Outgoing,
+            "",
+        );
+        message.content = MessageContent::Attachment(
+            opencrust_common::MediaAttachment::new(MediaKind::Voice, b"ogg".to_vec())
+                .with_mime_type(Some("audio/ogg".into())),
+        );
+        message.metadata = serde_json::json!({ "whatsapp_from": "1234@s.whatsapp.net" });
+
+        whatsapp_web_send_message(&tx, &message).await.unwrap();
+        let cmd: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(
+            cmd,
+            serde_json::json!({
+                "type": "send_media",
+                "to": "1234@s.whatsapp.net",
+                "kind": "audio",
+                "data": "b2dn",
+                "mimetype": "audio/ogg",
+                "fileName": "voice.ogg",
+                "caption": null,
+            })
+        );
+    }
+
     #[test]
     fn whatsapp_web_group_filter_blocks() {
         let filter: WhatsAppWebGroupFilter = Arc::new(|_mentioned| false);
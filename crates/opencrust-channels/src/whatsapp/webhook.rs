@@ -1,14 +1,45 @@
 use std::sync::Arc;
 
-use axum::Json;
+use axum::body::Bytes;
 use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use serde::Deserialize;
 use tracing::{info, warn};
 
+use super::WhatsAppChannel;
 use super::api;
-use super::{WhatsAppChannel, WhatsAppFile};
+use crate::traits::ChannelResponse;
+use opencrust_common::{MediaAttachment, MediaKind};
+
+/// Media in an incoming message, before its bytes are downloaded.
+struct InboundMedia {
+    media_id: String,
+    kind: MediaKind,
+    filename: Option<String>,
+    mime_type: Option<String>,
+    caption: Option<String>,
+}
+
+/// Read the media object of an `image`, `audio`, `video` or `document` message.
+fn inbound_media(msg: &serde_json::Value, msg_type: &str) -> Option<InboundMedia> {
+    let kind = match msg_type {
+        "image" => MediaKind::Photo,
+        "audio" => MediaKind::Voice,
+        "video" => MediaKind::Video,
+        "document" => MediaKind::Document,
+        _ => return None,
+    };
+    let media = msg.get(msg_type)?;
+    let field = |key: &str| media.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    Some(InboundMedia {
+        media_id: field("id").unwrap_or_default(),
+        kind,
+        filename: field("filename"),
+        mime_type: field("mime_type"),
+        caption: field("caption"),
+    })
+}
 
 /// Shared state passed to WhatsApp webhook handlers.
 pub type WhatsAppState = Arc<Vec<Arc<WhatsAppChannel>>>;
@@ -49,10 +80,37 @@ pub async fn whatsapp_verify(
 }
 
 /// POST handler for incoming WhatsApp messages.
+///
+/// Requests must carry an `X-Hub-Signature-256` header signed with the app
+/// secret of a configured channel; unsigned or invalid ones are rejected.
 pub async fn whatsapp_webhook(
     State(channels): State<WhatsAppState>,
-    Json(body): Json<serde_json::Value>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
+    let signature = headers
+        .get("x-hub-signature-256")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    // Only channels whose app secret validates this request may handle it.
+    let channels: Vec<&Arc<WhatsAppChannel>> = channels
+        .iter()
+        .filter(|ch| ch.verify_signature(&body, signature))
+        .collect();
+    if channels.is_empty() {
+        warn!("whatsapp: no channel matched signature — request rejected");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let body: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("whatsapp: failed to parse webhook body: {e}");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
     // WhatsApp sends: { "entry": [{ "changes": [{ "value": { "messages": [...] } }] }] }
     let entries = match body.get("entry").and_then(|v| v.as_array()) {
         Some(e) => e,
@@ -89,8 +147,9 @@ pub async fn whatsapp_webhook(
             for msg in messages {
                 let msg_type = msg.get("type").and_then(|v| v.as_str()).unwrap_or("");
 
-                // Only handle text and document messages.
-                if msg_type != "text" && msg_type != "document" {
+                // Only handle text and media messages.
+                let media = inbound_media(msg, msg_type);
+                if msg_type != "text" && media.is_none() {
                     continue;
                 }
 
@@ -105,39 +164,17 @@ pub async fn whatsapp_webhook(
                     .unwrap_or("")
                     .to_string();
 
-                // Extract text body (empty string for document-only messages).
+                // Text body, or the caption of a media message.
                 let text = msg
                     .get("text")
                     .and_then(|v| v.get("body"))
                     .and_then(|v| v.as_str())
+                    .or_else(|| media.as_ref().and_then(|m| m.caption.as_deref()))
                     .unwrap_or("")
                     .to_string();
 
-                // Extract document metadata when present.
-                let doc_info = if msg_type == "document" {
-                    msg.get("document").map(|d| {
-                        let media_id = d
-                            .get("id")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        let filename = d
-                            .get("filename")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("document")
-                            .to_string();
-                        let mime_type = d
-                            .get("mime_type")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string());
-                        (media_id, filename, mime_type)
-                    })
-                } else {
-                    None
-                };
-
-                // Skip if there is neither text nor a document.
-                if text.trim().is_empty() && doc_info.is_none() {
+                // Skip if there is neither text nor media.
+                if text.trim().is_empty() && media.is_none() {
                     continue;
                 }
 
@@ -170,7 +207,8 @@ pub async fn whatsapp_webhook(
                 let channel = channels
                     .iter()
                     .find(|ch| ch.phone_number_id() == metadata_phone_id)
-                    .or_else(|| channels.first());
+                    .or_else(|| channels.first())
+                    .copied();
 
                 let Some(channel) = channel else {
                     warn!(
@@ -178,46 +216,57 @@ pub async fn whatsapp_webhook(
                     );
                     continue;
                 };
+                if !channel.first_delivery(&message_id) {
+                    info!("whatsapp: duplicate delivery of {message_id}, skipping");
+                    continue;
+                }
 
-                // Mark as read
+                // Mark as read (blue ticks) as soon as the message is accepted
                 let client = channel.client();
                 let token = channel.access_token();
                 let phone_id = channel.phone_number_id().to_string();
 
-                let read_client = client.clone();
-                let read_token = token.to_string();
-                let read_phone_id = phone_id.clone();
-                let read_msg_id = message_id.clone();
-                tokio::spawn(async move {
-                    let _ =
-                        api::mark_as_read(&read_client, &read_token, &read_phone_id, &read_msg_id)
-                            .await;
-                });
+                if channel.read_receipts() {
+                    let read_client = client.clone();
+                    let read_token = token.to_string();
+                    let read_phone_id = phone_id.clone();
+                    let read_msg_id = message_id.clone();
+                    tokio::spawn(async move {
+                        let _ = api::mark_as_read(
+                            &read_client,
+                            &read_token,
+                            &read_phone_id,
+                            &read_msg_id,
+                        )
+                        .await;
+                    });
+                }
 
                 // Process message (download document first if present)
                 let channel = Arc::clone(channel);
                 let from_clone = from.clone();
                 tokio::spawn(async move {
-                    // Download document bytes before invoking the callback.
-                    let whatsapp_file = if let Some((media_id, filename, mime_type)) = doc_info {
-                        if media_id.is_empty() {
-                            warn!("whatsapp: document has no media id, skipping download");
+                    // Download media bytes before invoking the callback.
+                    let whatsapp_file = if let Some(media) = media {
+                        if media.media_id.is_empty() {
+                            warn!("whatsapp: media has no id, skipping download");
                             None
                         } else {
                             match api::download_media(
                                 channel.client(),
                                 channel.access_token(),
-                                &media_id,
+                                &media.media_id,
                             )
                             .await
                             {
-                                Ok(data) => Some(WhatsAppFile {
-                                    filename,
-                                    data,
-                                    mime_type,
+                                Ok(data) => Some(MediaAttachment {
+                                    filename: media.filename,
+                                    ..MediaAttachment::new(media.kind, data)
+                                        .with_mime_type(media.mime_type)
+                                        .with_caption(media.caption)
                                 }),
                                 Err(e) => {
-                                    warn!("whatsapp: failed to download document: {e}");
+                                    warn!("whatsapp: failed to download media: {e}");
                                     let _ = api::send_text_message(
                                         channel.client(),
                                         channel.access_token(),
@@ -238,6 +287,34 @@ pub async fn whatsapp_webhook(
                         .handle_incoming(&from_clone, &user_name, &text, whatsapp_file)
                         .await
                     {
+                        Ok(ChannelResponse::Voice { text, audio }) => {
+                            // WhatsApp shows OGG/Opus audio as a voice note.
+                            let note = MediaAttachment::new(MediaKind::Voice, audio)
+                                .with_filename("voice.ogg")
+                                .with_mime_type(Some("audio/ogg".to_string()));
+                            if let Err(e) = super::send_attachment(
+                                channel.client(),
+                                channel.access_token(),
+                                channel.phone_number_id(),
+                                &from_clone,
+                                &note,
+                            )
+                            .await
+                            {
+                                warn!("whatsapp: failed to send voice note, sending text: {e}");
+                                if let Err(e) = api::send_text_message(
+                                    channel.client(),
+                                    channel.access_token(),
+                                    channel.phone_number_id(),
+                                    &from_clone,
+                                    &text,
+                                )
+                                .await
+                                {
+                                    warn!("whatsapp: failed to send reply: {e}");
+                                }
+                            }
+                        }
                         Ok(response) => {
                             if let Err(e) = api::send_text_message(
                                 channel.client(),
@@ -273,3 +350,134 @@ pub async fn whatsapp_webhook(
 
     StatusCode::OK
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inbound_media_reads_media_messages() {
+        let msg = serde_json::json!({
+            "type": "image",
+            "image": {"id": "m1", "mime_type": "image/jpeg", "caption": "my cat"}
+        });
+        let media = inbound_media(&msg, "image").unwrap();
+        assert_eq!(media.media_id, "m1");
+        assert_eq!(media.kind, MediaKind::Photo);
+        assert_eq!(media.caption.as_deref(), Some("my cat"));
+        assert!(media.filename.is_none());
+
+        let msg = serde_json::json!({
+            "type": "document",
+            "document": {"id": "m2", "filename": "a.pdf", "mime_type": "application/pdf"}
+        });
+        let media = inbound_media(&msg, "document").unwrap();
+        assert_eq!(media.kind, MediaKind::Document);
+        assert_eq!(media.filename.as_deref(), Some("a.pdf"));
+
+        let msg = serde_json::json!({"type": "text", "text": {"body": "hi"}});
+        assert!(inbound_media(&msg, "text").is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_unsigned_and_invalid_requests() {
+        use crate::traits::ChannelResponse;
+        use crate::whatsapp::WhatsAppOnMessageFn;
+
+        let on_msg: WhatsAppOnMessageFn =
+            Arc::new(|_from, _user, _text, _is_group, _file, _delta_tx| {
+                Box::pin(async { Ok(ChannelResponse::Text("ok".to_string())) })
+            });
+        let channel = WhatsAppChannel::new(
+            "fake-token".to_string(),
+            "123456".to_string(),
+            "verify-me".to_string(),
+            "app-secret".to_string(),
+            on_msg,
+        );
+        let state: WhatsAppState = Arc::new(vec![Arc::new(channel)]);
+        let body = Bytes::from_static(br#"{"entry":[]}"#);
+
+        let response = whatsapp_webhook(State(state.clone()), HeaderMap::new(), body.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-hub-signature-256", "sha256=00ff".parse().unwrap());
+        let response = whatsapp_webhook(State(state.clone()), headers, body.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"app-secret");
+        let digest: String = ring::hmac::sign(&key, &body)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-hub-signature-256",
+            format!("sha256={digest}").parse().unwrap(),
+        );
+        let response = whatsapp_webhook(State(state), headers, body)
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn redelivered_messages_reach_the_callback_once() {
+        use crate::traits::ChannelResponse;
+        use crate::whatsapp::WhatsAppOnMessageFn;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let on_msg: WhatsAppOnMessageFn =
+            Arc::new(move |_from, _user, _text, _is_group, _file, _delta_tx| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok(ChannelResponse::Text(String::new())) })
+            });
+        let channel = WhatsAppChannel::new(
+            "fake-token".to_string(),
+            "123456".to_string(),
+            "verify-me".to_string(),
+            "app-secret".to_string(),
+            on_msg,
+        );
+        let state: WhatsAppState = Arc::new(vec![Arc::new(channel)]);
+        let body = Bytes::from(
+            serde_json::json!({"entry": [{"changes": [{"value": {
+                "metadata": {"phone_number_id": "123456"},
+                "contacts": [{"profile": {"name": "Alice"}}],
+                "messages": [{"from": "15551234567", "id": "wamid.1", "type": "text",
+                              "text": {"body": "hi"}}]
+            }}]}]})
+            .to_string(),
+        );
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"app-secret");
+        let digest: String = ring::hmac::sign(&key, &body)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        for _ in 0..2 {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "x-hub-signature-256",
+                format!("sha256={digest}").parse().unwrap(),
+            );
+            let response = whatsapp_webhook(State(state.clone()), headers, body.clone())
+                .await
+                .into_response();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        for _ in 0..50 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
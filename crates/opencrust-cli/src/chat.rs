@@ -1,94 +1,450 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
 use colored::Colorize;
-use reqwest::Client;
-use std::io::{self, BufRead, Write};
+use dialoguer::theme::Theme;
+use dialoguer::{History, Input};
+use futures::stream::SplitSink;
+use futures::{SinkExt, StreamExt};
+use opencrust_config::AppConfig;
+use opencrust_gateway::GatewayServer;
+use opencrust_gateway::pipeline::{InboundMessage, MessagePipeline};
+use opencrust_security::{ChannelPolicy, DmPolicy};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 use crate::banner;
 
-struct ChatSession {
-    client: Client,
-    base_url: String,
+/// Input lines kept in the history file.
+const HISTORY_LIMIT: usize = 500;
+
+/// Opens and closes a multi-line block when typed on a line of its own.
+const BLOCK_DELIMITER: &str = "\"\"\"";
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, WsMessage>;
+
+// ── Gateway backend ──────────────────────────────────────────────────────────
+
+/// Chat session on a running gateway, over its `/ws` endpoint.
+struct GatewayChat {
+    ws_url: String,
     agent_id: Option<String>,
-    session_id: Option<String>,
+    sink: WsSink,
+    /// JSON frames from the gateway, read by a background task so heartbeat
+    /// pings are answered while the prompt is waiting for input.
+    frames: mpsc::Receiver<serde_json::Value>,
 }
 
-impl ChatSession {
-    fn new(base_url: String, agent_id: Option<String>) -> Self {
-        Self {
-            client: Client::new(),
-            base_url,
+impl GatewayChat {
+    async fn connect(ws_url: String, agent_id: Option<String>) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(ws_url.as_str())
+            .await
+            .context("failed to connect to gateway — is `opencrust start` running?")?;
+        let (mut sink, mut stream) = socket.split();
+
+        let (frames_tx, frames) = mpsc::channel(64);
+        tokio::spawn(async move {
+            while let Some(Ok(msg)) = stream.next().await {
+                if let WsMessage::Text(text) = msg
+                    && let Ok(frame) = serde_json::from_str(&text)
+                    && frames_tx.send(frame).await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        sink.send(WsMessage::Text(
+            serde_json::json!({ "type": "init" }).to_string().into(),
+        ))
+        .await
+        .context("failed to start chat session")?;
+
+        let mut chat = Self {
+            ws_url,
             agent_id,
-            session_id: None,
+            sink,
+            frames,
+        };
+        match chat.next_frame().await?["type"].as_str() {
+            Some("connected") => Ok(chat),
+            other => anyhow::bail!("unexpected gateway reply: {other:?}"),
         }
     }
 
-    async fn create_session(&mut self) -> Result<()> {
-        let body = serde_json::json!({ "agent_id": self.agent_id });
-        let resp = self
-            .client
-            .post(format!("{}/api/sessions", self.base_url))
-            .json(&body)
-            .send()
+    async fn next_frame(&mut self) -> Result<serde_json::Value> {
+        self.frames
+            .recv()
             .await
-            .context("failed to connect to gateway — is `opencrust start` running?")?;
+            .context("gateway closed the connection")
+    }
 
-        if !resp.status().is_success() {
-            anyhow::bail!("gateway returned {}", resp.status());
+    async fn send(&mut self, text: &str, on_delta: &mut dyn FnMut(&str)) -> Result<String> {
+        let mut msg = serde_json::json!({
+            "type": "message",
+            "content": text,
+            "stream": true,
+        });
+        if let Some(agent_id) = &self.agent_id {
+            msg["agent_id"] = agent_id.clone().into();
         }
+        self.sink
+            .send(WsMessage::Text(msg.to_string().into()))
+            .await
+            .context("failed to send message")?;
+
+        loop {
+            let frame = self.next_frame().await?;
+            let content = frame["content"].as_str().unwrap_or("");
+            match frame["type"].as_str() {
+                Some("delta") => on_delta(content),
+                Some("message") => return Ok(content.to_string()),
+                Some("error") => {
+                    let reason = frame["message"]
+                        .as_str()
+                        .or(frame["code"].as_str())
+                        .unwrap_or("unknown error");
+                    anyhow::bail!("{reason}");
+                }
+                _ => {}
+            }
+        }
+    }
 
-        let json: serde_json::Value = resp.json().await.context("invalid session response")?;
-        self.session_id = json["session_id"].as_str().map(str::to_string);
+    /// Start over with a new session, optionally with a different agent.
+    async fn reconnect(&mut self, agent_id: Option<String>) -> Result<()> {
+        let _ = self.sink.close().await;
+        *self = Self::connect(self.ws_url.clone(), agent_id).await?;
         Ok(())
     }
+}
+
+/// The gateway's WebSocket URL for `base_url`, authenticated with `api_key`.
+fn ws_url(base_url: &str, api_key: Option<&str>) -> Result<String> {
+    let mut url = reqwest::Url::parse(base_url).context("invalid gateway URL")?;
+    let scheme = match url.scheme() {
+        "http" | "ws" => "ws",
+        "https" | "wss" => "wss",
+        other => anyhow::bail!("unsupported gateway URL scheme '{other}'"),
+    };
+    url.set_scheme(scheme)
+        .map_err(|()| anyhow::anyhow!("invalid gateway URL"))?;
+    url.set_path("/ws");
+    url.set_query(None);
+    if let Some(key) = api_key {
+        url.query_pairs_mut().append_pair("token", key);
+    }
+    Ok(url.to_string())
+}
 
-    async fn send(&self, text: &str) -> Result<String> {
-        let session_id = self.session_id.as_deref().context("no active session")?;
-        let resp = self
-            .client
-            .post(format!(
-                "{}/api/sessions/{}/messages",
-                self.base_url, session_id
-            ))
-            .json(&serde_json::json!({ "content": text }))
-            .send()
-            .await
-            .context("failed to send message")?;
+// ── Local backend ────────────────────────────────────────────────────────────
+
+/// Chat session running the agent in this process, through the same
+/// pipeline as the gateway's channels.
+struct LocalChat {
+    pipeline: MessagePipeline,
+    user_id: String,
+}
+
+impl LocalChat {
+    async fn start(config: AppConfig) -> Result<Self> {
+        let state = GatewayServer::new(config.clone()).local_state().await;
+        if state.agents.default_provider().is_none() {
+            anyhow::bail!("no LLM provider configured — run `opencrust init` first");
+        }
+        // The terminal belongs to the person running it; no pairing.
+        let policy = Arc::new(ChannelPolicy {
+            dm_policy: Some(DmPolicy::Open),
+            ..Default::default()
+        });
+        let user_id = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "local".to_string());
+        Ok(Self {
+            pipeline: MessagePipeline::new("cli", &state, &config, policy),
+            user_id,
+        })
+    }
+
+    fn message(&self, text: &str) -> InboundMessage {
+        InboundMessage::text(
+            format!("cli-{}", self.user_id),
+            self.user_id.clone(),
+            self.user_id.clone(),
+            text,
+        )
+    }
 
-        if !resp.status().is_success() {
-            anyhow::bail!("gateway returned {}", resp.status());
+    /// Run a pipeline command such as `/clear` or `/summarize`.
+    async fn command(&self, text: &str) -> Result<String> {
+        let msg = self.message(text);
+        match self.pipeline.handle_command(&msg).await {
+            Some(result) => result
+                .map(|r| r.text().to_string())
+                .map_err(anyhow::Error::msg),
+            None => anyhow::bail!("not a command: {text}"),
         }
+    }
+
+    async fn send(&self, text: &str, on_delta: &mut dyn FnMut(&str)) -> Result<String> {
+        let (delta_tx, mut delta_rx) = mpsc::channel::<String>(64);
+        let turn = self
+            .pipeline
+            .handle(self.message(text).with_delta_tx(Some(delta_tx)));
+        tokio::pin!(turn);
 
-        let json: serde_json::Value = resp.json().await.context("invalid message response")?;
-        Ok(json["content"].as_str().unwrap_or("").to_string())
+        let result = loop {
+            tokio::select! {
+                result = &mut turn => break result,
+                Some(delta) = delta_rx.recv() => on_delta(&delta),
+            }
+        };
+        while let Ok(delta) = delta_rx.try_recv() {
+            on_delta(&delta);
+        }
+        result
+            .map(|r| r.text().to_string())
+            .map_err(anyhow::Error::msg)
     }
 }
 
-pub async fn run(base_url: String, agent_id: Option<String>) -> Result<()> {
-    let mut session = ChatSession::new(base_url.clone(), agent_id.clone());
+enum Backend {
+    Gateway(GatewayChat),
+    Local(LocalChat),
+}
 
-    banner::print_chat_banner(&base_url, agent_id.as_deref());
+impl Backend {
+    async fn send(&mut self, text: &str, on_delta: &mut dyn FnMut(&str)) -> Result<String> {
+        match self {
+            Self::Gateway(chat) => chat.send(text, on_delta).await,
+            Self::Local(chat) => chat.send(text, on_delta).await,
+        }
+    }
 
-    session
-        .create_session()
-        .await
-        .context("could not start chat session")?;
+    /// Forget the conversation so far.
+    async fn clear(&mut self) -> Result<()> {
+        match self {
+            Self::Gateway(chat) => chat.reconnect(chat.agent_id.clone()).await,
+            Self::Local(chat) => chat.command("/clear").await.map(|_| ()),
+        }
+    }
+}
 
-    let stdin = io::stdin();
-    loop {
-        print!("{} ", "you ›".cyan().bold());
-        io::stdout().flush()?;
+// ── Input ────────────────────────────────────────────────────────────────────
+
+/// Input history, navigable with the arrow keys and kept between sessions.
+struct ChatHistory {
+    path: Option<PathBuf>,
+    /// Newest first.
+    entries: VecDeque<String>,
+}
+
+impl ChatHistory {
+    fn load(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_deref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|s| {
+                s.lines()
+                    .rev()
+                    .take(HISTORY_LIMIT)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut contents: Vec<&str> = self.entries.iter().rev().map(String::as_str).collect();
+        contents.push("");
+        let _ = std::fs::write(path, contents.join("\n"));
+    }
+}
+
+impl History<String> for ChatHistory {
+    fn read(&self, pos: usize) -> Option<String> {
+        self.entries.get(pos).cloned()
+    }
+
+    fn write(&mut self, val: &String) {
+        if val.trim().is_empty() || self.entries.front() == Some(val) {
+            return;
+        }
+        self.entries.push_front(val.clone());
+        self.entries.truncate(HISTORY_LIMIT);
+        self.save();
+    }
+}
+
+/// Renders the prompt exactly as given, without dialoguer's `: ` suffix.
+struct PromptTheme;
+
+impl Theme for PromptTheme {
+    fn format_input_prompt(
+        &self,
+        f: &mut dyn fmt::Write,
+        prompt: &str,
+        _default: Option<&str>,
+    ) -> fmt::Result {
+        write!(f, "{prompt} ")
+    }
+
+    fn format_input_prompt_selection(
+        &self,
+        f: &mut dyn fmt::Write,
+        prompt: &str,
+        sel: &str,
+    ) -> fmt::Result {
+        write!(f, "{prompt} {sel}")
+    }
+}
+
+/// Reads lines with history and editing on a terminal, or plain lines from
+/// piped input.
+struct LineReader {
+    history: ChatHistory,
+    interactive: bool,
+}
+
+impl LineReader {
+    /// The next line, or `None` at end of input.
+    fn read(&mut self, prompt: &str) -> Option<String> {
+        if self.interactive {
+            let line = tokio::task::block_in_place(|| {
+                Input::<String>::with_theme(&PromptTheme)
+                    .with_prompt(prompt)
+                    .allow_empty(true)
+                    .history_with(&mut self.history)
+                    .interact_text()
+            });
+            return line.ok();
+        }
 
         let mut line = String::new();
-        match stdin.lock().read_line(&mut line) {
-            Ok(0) => break, // EOF (Ctrl-D)
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("read error: {e}");
-                break;
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+        }
+    }
+}
+
+/// Collects input lines into one message. A line ending in `\` continues on
+/// the next line, and a `"""` line opens or closes a block of free text.
+#[derive(Default)]
+struct MultiLine {
+    lines: Vec<String>,
+    in_block: bool,
+}
+
+impl MultiLine {
+    /// Add a line; returns the message once it is complete.
+    fn push(&mut self, line: &str) -> Option<String> {
+        if line.trim() == BLOCK_DELIMITER {
+            self.in_block = !self.in_block;
+            return if self.in_block { None } else { self.take() };
+        }
+        if self.in_block {
+            self.lines.push(line.to_string());
+            return None;
+        }
+        match line.strip_suffix('\\') {
+            Some(rest) => {
+                self.lines.push(rest.to_string());
+                None
+            }
+            None => {
+                self.lines.push(line.to_string());
+                self.take()
             }
         }
+    }
+
+    fn pending(&self) -> bool {
+        self.in_block || !self.lines.is_empty()
+    }
+
+    fn take(&mut self) -> Option<String> {
+        Some(std::mem::take(&mut self.lines).join("\n"))
+    }
+}
+
+// ── REPL ─────────────────────────────────────────────────────────────────────
+
+fn print_help(local: bool) {
+    println!("{}", "Commands:".bold());
+    println!("  /exit, /quit     — end the session");
+    println!("  /clear           — forget the conversation and start fresh");
+    println!("  /new             — same as /clear");
+    if local {
+        println!("  /summarize, /tag — conversation commands, as on other channels");
+    } else {
+        println!("  /agent <id>      — switch to a different agent");
+    }
+    println!("  /help            — show this message");
+    println!();
+    println!("{}", "Input:".bold());
+    println!("  End a line with \\ to continue on the next line.");
+    println!("  Type \"\"\" on its own line to start and end a multi-line message.");
+    println!("  Use ↑/↓ to recall earlier input.");
+}
+
+/// Run the terminal chat, against the gateway at `base_url` or, with
+/// `local`, against an agent runtime built in this process.
+pub async fn run(
+    config: AppConfig,
+    base_url: String,
+    agent_id: Option<String>,
+    local: bool,
+) -> Result<()> {
+    let label = if local {
+        "local (in-process)"
+    } else {
+        &base_url
+    };
+    banner::print_chat_banner(label, agent_id.as_deref());
+
+    let mut backend = if local {
+        Backend::Local(LocalChat::start(config).await?)
+    } else {
+        let url = ws_url(&base_url, config.gateway.api_key.as_deref())?;
+        Backend::Gateway(
+            GatewayChat::connect(url, agent_id)
+                .await
+                .context("could not start chat session")?,
+        )
+    };
+
+    let history_path = opencrust_config::ConfigLoader::default_config_dir().join("chat_history");
+    let mut reader = LineReader {
+        history: ChatHistory::load(Some(history_path)),
+        interactive: io::stdin().is_terminal(),
+    };
+    let mut pending = MultiLine::default();
 
-        let text = line.trim();
+    loop {
+        let prompt = if pending.pending() {
+            "    …".dimmed().to_string()
+        } else {
+            "you ›".cyan().bold().to_string()
+        };
+        let Some(line) = reader.read(&prompt) else {
+            break; // EOF (Ctrl-D)
+        };
+        let Some(message) = pending.push(&line) else {
+            continue;
+        };
+
+        let text = message.trim();
         if text.is_empty() {
             continue;
         }
@@ -98,49 +454,169 @@ pub async fn run(base_url: String, agent_id: Option<String>) -> Result<()> {
                 println!("{}", "Goodbye!".dimmed());
                 break;
             }
-            "/help" => {
-                println!("{}", "Commands:".bold());
-                println!("  /exit, /quit     — end the session");
-                println!("  /new             — start a fresh conversation");
-                println!("  /agent <id>      — switch to a different agent");
-                println!("  /clear           — clear the screen");
-                println!("  /help            — show this message");
-            }
-            "/new" => {
-                session.create_session().await?;
-                println!("{}", "New session started.".dimmed());
-            }
-            "/clear" => {
+            "/help" => print_help(matches!(backend, Backend::Local(_))),
+            "/clear" | "/new" => {
+                backend.clear().await?;
                 print!("\x1b[2J\x1b[1;1H");
                 io::stdout().flush()?;
+                println!("{}", "Started a fresh conversation.".dimmed());
             }
-            _ if text.starts_with("/agent ") => {
-                let id = text[7..].trim().to_string();
-                if id.is_empty() {
-                    println!("{}", "Usage: /agent <agent-id>".yellow());
-                } else {
-                    session.agent_id = Some(id.clone());
-                    session.create_session().await?;
-                    println!("{}", format!("Switched to agent: {id}").dimmed());
+            _ if text.starts_with("/agent") => match &mut backend {
+                Backend::Gateway(chat) => {
+                    let id = text["/agent".len()..].trim().to_string();
+                    if id.is_empty() {
+                        println!("{}", "Usage: /agent <agent-id>".yellow());
+                    } else {
+                        chat.reconnect(Some(id.clone())).await?;
+                        println!("{}", format!("Switched to agent: {id}").dimmed());
+                    }
                 }
-            }
-            _ if text.starts_with('/') => {
-                println!(
-                    "{}",
-                    format!("Unknown command: {text}  (type /help for help)").yellow()
-                );
-            }
-            _ => match session.send(text).await {
-                Ok(reply) => {
-                    println!("{} {}", "bot ›".green().bold(), reply);
-                    println!();
+                Backend::Local(_) => {
+                    println!("{}", "Named agents need a running gateway.".yellow());
                 }
-                Err(e) => {
-                    println!("{} {e}", "error ›".red().bold());
+            },
+            _ if text.starts_with('/') && !text.contains('\n') => match &backend {
+                Backend::Local(chat) => match chat.command(text).await {
+                    Ok(reply) => println!("{} {reply}\n", "bot ›".green().bold()),
+                    Err(e) => println!("{} {e}", "error ›".red().bold()),
+                },
+                Backend::Gateway(_) => {
+                    println!(
+                        "{}",
+                        format!("Unknown command: {text}  (type /help for help)").yellow()
+                    );
                 }
             },
+            _ => {
+                let mut streamed = false;
+                let mut on_delta = |delta: &str| {
+                    if !streamed {
+                        print!("{} ", "bot ›".green().bold());
+                        streamed = true;
+                    }
+                    print!("{delta}");
+                    let _ = io::stdout().flush();
+                };
+                match backend.send(text, &mut on_delta).await {
+                    Ok(_) if streamed => println!("\n"),
+                    Ok(reply) => println!("{} {reply}\n", "bot ›".green().bold()),
+                    Err(e) => {
+                        if streamed {
+                            println!();
+                        }
+                        println!("{} {e}", "error ›".red().bold());
+                    }
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn multi_line_input() {
+        let mut input = MultiLine::default();
+        assert_eq!(input.push("hello"), Some("hello".to_string()));
+        assert!(!input.pending());
+
+        assert_eq!(input.push("first \\"), None);
+        assert!(input.pending());
+        assert_eq!(input.push("second"), Some("first \nsecond".to_string()));
+
+        assert_eq!(input.push("\"\"\""), None);
+        assert_eq!(input.push("fn main() {"), None);
+        assert_eq!(input.push("    println!(\"hi\"); \\"), None);
+        assert_eq!(input.push("}"), None);
+        assert!(input.pending());
+        assert_eq!(
+            input.push("\"\"\""),
+            Some("fn main() {\n    println!(\"hi\"); \\\n}".to_string())
+        );
+        assert!(!input.pending());
+    }
+
+    #[test]
+    fn history_is_newest_first_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chat_history");
+
+        let mut history = ChatHistory::load(Some(path.clone()));
+        history.write(&"one".to_string());
+        history.write(&"two".to_string());
+        history.write(&"two".to_string());
+        history.write(&"  ".to_string());
+        assert_eq!(history.read(0).as_deref(), Some("two"));
+        assert_eq!(history.read(1).as_deref(), Some("one"));
+        assert_eq!(history.read(2), None);
+
+        let reloaded = ChatHistory::load(Some(path));
+        assert_eq!(reloaded.entries, ["two", "one"]);
+    }
+
+    #[test]
+    fn ws_url_from_gateway_url() {
+        assert_eq!(
+            ws_url("http://127.0.0.1:3888", None).unwrap(),
+            "ws://127.0.0.1:3888/ws"
+        );
+        assert_eq!(
+            ws_url("https://gw.example.com/", Some("k&y")).unwrap(),
+            "wss://gw.example.com/ws?token=k%26y"
+        );
+        assert!(ws_url("ftp://example.com", None).is_err());
+    }
+
+    /// Fake gateway: acknowledges `init`, then answers every message with
+    /// two deltas and the full reply.
+    async fn fake_gateway() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(WsMessage::Text(raw))) = ws.next().await {
+                let frame: serde_json::Value = serde_json::from_str(&raw).unwrap();
+                let replies = if frame["type"] == "init" {
+                    vec![serde_json::json!({ "type": "connected", "session_id": "s1" })]
+                } else {
+                    assert_eq!(frame["stream"], true);
+                    let content = frame["content"].as_str().unwrap();
+                    vec![
+                        serde_json::json!({ "type": "delta", "content": "you said " }),
+                        serde_json::json!({ "type": "delta", "content": content }),
+                        serde_json::json!({
+                            "type": "message",
+                            "content": format!("you said {content}"),
+                        }),
+                    ]
+                };
+                for reply in replies {
+                    ws.send(WsMessage::Text(reply.to_string().into()))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        format!("ws://{addr}/ws")
+    }
+
+    #[tokio::test]
+    async fn gateway_chat_streams_deltas() {
+        let mut chat = GatewayChat::connect(fake_gateway().await, None)
+            .await
+            .unwrap();
+        let mut deltas = Vec::new();
+        let reply = chat
+            .send("hi", &mut |d: &str| deltas.push(d.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(reply, "you said hi");
+        assert_eq!(deltas, ["you said ", "hi"]);
+    }
+}
@@ -128,11 +128,13 @@ async fn check_llm_providers(config: &AppConfig) -> Vec<(String, Check)> {
             }
             results
                 .into_iter()
-                .map(|(id, ok)| {
-                    let check = if ok {
-                        Check::Pass("reachable".into())
-                    } else {
-                        Check::Fail("health check failed — check API key and connectivity".into())
+                .map(|(id, result)| {
+                    let check = match result {
+                        Ok(true) => Check::Pass("reachable".into()),
+                        Ok(false) => Check::Fail(
+                            "health check failed — check API key and connectivity".into(),
+                        ),
+                        Err(e) => Check::Fail(e.to_string()),
                     };
                     (format!("LLM provider [{id}]"), check)
                 })
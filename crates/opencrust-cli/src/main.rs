@@ -3,6 +3,7 @@ mod chat;
 mod doctor;
 mod mcp_registry;
 mod migrate;
+mod shutdown;
 mod update;
 mod wizard;
 
@@ -27,6 +28,32 @@ struct Cli {
     /// Log level (trace, debug, info, warn, error)
     #[arg(long, default_value = "info", global = true)]
     log_level: String,
+
+    /// Only log warnings and errors; command output is unaffected
+    #[arg(long, short = 'q', global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log debug output
+    #[arg(long, short = 'v', global = true)]
+    verbose: bool,
+
+    /// Config profile: merges config.<profile>.yml over config.yml
+    /// (default: $OPENCRUST_PROFILE)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+}
+
+/// Build the tracing filter for the CLI flags. `--quiet` and `--verbose`
+/// override both `--log-level` and `RUST_LOG`; otherwise `RUST_LOG` wins
+/// over `--log-level`.
+fn tracing_filter(log_level: &str, quiet: bool, verbose: bool) -> EnvFilter {
+    if quiet {
+        EnvFilter::new("warn")
+    } else if verbose {
+        EnvFilter::new("debug")
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(log_level))
+    }
 }
 
 #[derive(Subcommand)]
@@ -69,7 +96,11 @@ enum Commands {
     },
 
     /// Show current status
-    Status,
+    Status {
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Run the onboarding wizard
     Init,
@@ -81,8 +112,12 @@ enum Commands {
         url: String,
 
         /// Named agent to use (defaults to gateway default)
-        #[arg(long)]
+        #[arg(long, conflicts_with = "local")]
         agent: Option<String>,
+
+        /// Run the agent in this process instead of connecting to a gateway
+        #[arg(long)]
+        local: bool,
     },
 
     /// Manage channels
@@ -122,6 +157,12 @@ enum Commands {
         action: DocCommands,
     },
 
+    /// Inspect stored conversations
+    Session {
+        #[command(subcommand)]
+        action: SessionCommands,
+    },
+
     /// Run diagnostic checks on the current setup
     Doctor,
 
@@ -150,28 +191,64 @@ enum Commands {
 #[derive(Subcommand)]
 enum ChannelCommands {
     /// List configured channels
-    List,
+    List {
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Show channel status
     Status { name: String },
+    /// Put a running channel into maintenance mode: users get an automatic
+    /// offline reply instead of the agent
+    Maintenance {
+        name: String,
+
+        /// Reply sent to users while the channel is down
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Hold incoming messages and answer them on `channel resume`
+        #[arg(long)]
+        queue: bool,
+    },
+    /// Take a channel out of maintenance mode and answer held messages
+    Resume { name: String },
 }
 
 #[cfg(feature = "plugins")]
 #[derive(Subcommand)]
 enum PluginCommands {
     /// List installed plugins
-    List,
+    List {
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Install a plugin
     Install { path: String },
     /// Remove a plugin
     Remove { name: String },
     /// Watch plugin directory and hot-reload on change
     Watch,
+    /// Run a plugin and print its output
+    Run {
+        name: String,
+        /// Arguments passed to the plugin
+        args: Vec<String>,
+        /// User the plugin runs for; scopes its key-value storage
+        #[arg(long)]
+        user: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
 enum SkillCommands {
     /// List installed skills
-    List,
+    List {
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Install a skill from a URL or local file path
     Install { source: String },
     /// Remove a skill by name
@@ -181,7 +258,11 @@ enum SkillCommands {
 #[derive(Subcommand)]
 enum McpCommands {
     /// List configured MCP servers
-    List,
+    List {
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Add a new MCP server (interactive wizard)
     Add {
         /// Server name or registry ID (skip selection prompt)
@@ -245,6 +326,20 @@ enum DocCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum SessionCommands {
+    /// List stored sessions, most recently active first
+    List {
+        /// Only list sessions tagged with this name (set with /tag in chat)
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 /// Build an embedding provider from config for document ingestion.
 fn build_embedding_provider(
     config: &opencrust_config::AppConfig,
@@ -272,6 +367,25 @@ fn build_embedding_provider(
                 embed_config.base_url.clone(),
             )))
         }
+        "openai" => {
+            let api_key = embed_config
+                .api_key
+                .clone()
+                .or_else(|| {
+                    let vault_path = opencrust_config::ConfigLoader::default_config_dir()
+                        .join("credentials")
+                        .join("vault.json");
+                    opencrust_security::try_vault_get(&vault_path, "OPENAI_API_KEY")
+                })
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok())?;
+
+            Some(Box::new(opencrust_agents::OpenAiEmbeddingProvider::new(
+                api_key,
+                embed_config.model.clone(),
+                embed_config.base_url.clone(),
+                embed_config.dimensions,
+            )))
+        }
         "ollama" => Some(Box::new(opencrust_agents::OllamaEmbeddingProvider::new(
             embed_config.model.clone(),
             embed_config.base_url.clone(),
@@ -586,6 +700,145 @@ fn validate_plugin_path(path_str: &str) -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Call the running gateway's maintenance endpoint for channel `name`.
+async fn channel_maintenance_request(
+    config: &opencrust_config::AppConfig,
+    method: reqwest::Method,
+    name: &str,
+    body: Option<serde_json::Value>,
+) -> Result<serde_json::Value> {
+    let url = format!(
+        "http://{}:{}/api/channels/{name}/maintenance",
+        config.gateway.host, config.gateway.port
+    );
+    let mut request = reqwest::Client::new().request(method, url);
+    if let Some(key) = &config.gateway.api_key {
+        request = request.bearer_auth(key);
+    }
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let resp = request
+        .send()
+        .await
+        .context("gateway is not responding; is it running?")?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let error = body["error"]
+            .as_str()
+            .or(body["message"].as_str())
+            .unwrap_or("request failed");
+        anyhow::bail!("{error} ({status})");
+    }
+    Ok(body)
+}
+
+/// `channel list --json`: configured channels sorted by name.
+fn channels_json(
+    channels: &std::collections::HashMap<String, opencrust_config::ChannelConfig>,
+) -> serde_json::Value {
+    let mut names: Vec<&String> = channels.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let ch = &channels[name];
+            serde_json::json!({
+                "name": name,
+                "type": ch.channel_type,
+                "enabled": ch.enabled.unwrap_or(true),
+            })
+        })
+        .collect()
+}
+
+/// `session list --json`: one object per stored session.
+fn sessions_json(sessions: &[opencrust_db::SessionSummary]) -> serde_json::Value {
+    sessions
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "channel": s.channel_id,
+                "user": s.user_id,
+                "updated_at": s.updated_at,
+                "tags": s.tags,
+            })
+        })
+        .collect()
+}
+
+/// `mcp list --json`: configured MCP servers sorted by name. Server `env` is
+/// left out because it usually holds credentials.
+fn mcp_servers_json(
+    servers: &std::collections::HashMap<String, opencrust_config::McpServerConfig>,
+) -> serde_json::Value {
+    let mut names: Vec<&String> = servers.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let server = &servers[name];
+            serde_json::json!({
+                "name": name,
+                "enabled": server.enabled.unwrap_or(true),
+                "transport": server.transport,
+                "command": server.command,
+                "args": server.args,
+                "timeout_secs": server.timeout.unwrap_or(30),
+            })
+        })
+        .collect()
+}
+
+/// `skill list --json`: one object per installed skill.
+fn skills_json(skills: &[opencrust_skills::SkillDefinition]) -> serde_json::Value {
+    skills
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "name": s.frontmatter.name,
+                "description": s.frontmatter.description,
+                "triggers": s.frontmatter.triggers,
+                "version": s.frontmatter.version,
+                "path": s.source_path,
+            })
+        })
+        .collect()
+}
+
+/// `plugin list --json`: one object per installed plugin.
+#[cfg(feature = "plugins")]
+fn plugins_json(plugins: &[std::sync::Arc<dyn opencrust_plugins::Plugin>]) -> serde_json::Value {
+    plugins
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "name": p.name(),
+                "description": p.description(),
+                "capabilities": p.capabilities(),
+            })
+        })
+        .collect()
+}
+
+/// Plugin loader for `plugins_dir` with key-value storage in the data directory.
+#[cfg(feature = "plugins")]
+fn plugin_loader(
+    plugins_dir: &std::path::Path,
+    config: &opencrust_config::AppConfig,
+) -> Result<opencrust_plugins::PluginLoader> {
+    let data_dir = config
+        .data_dir
+        .clone()
+        .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
+    std::fs::create_dir_all(&data_dir).ok();
+    let store = opencrust_db::KvStore::open(&data_dir.join("kv.db"))
+        .context("failed to open plugin key-value store")?;
+    Ok(opencrust_plugins::PluginLoader::new(plugins_dir).with_kv_store(std::sync::Arc::new(store)))
+}
+
 fn opencrust_dir() -> PathBuf {
     opencrust_config::ConfigLoader::default_config_dir()
 }
@@ -742,6 +995,30 @@ fn kill_and_wait(pid: u32) -> bool {
     false
 }
 
+/// Human-readable daemon state for `opencrust status`.
+fn daemon_status_lines(
+    pid: Option<u32>,
+    running: bool,
+    last_shutdown: Option<&shutdown::LastStatus>,
+) -> Vec<String> {
+    let mut lines = vec![match pid {
+        Some(pid) if running => format!("OpenCrust daemon is running (PID {pid})"),
+        Some(pid) => format!("OpenCrust daemon is not running (stale PID file for PID {pid})"),
+        None => "No daemon PID file found.".to_string(),
+    }];
+    if running {
+        return lines;
+    }
+    match last_shutdown {
+        Some(last) => lines.push(last.describe()),
+        None if pid.is_some() => lines.push(
+            "The daemon exited without recording a shutdown reason (crashed or killed).".into(),
+        ),
+        None => {}
+    }
+    lines
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -754,17 +1031,15 @@ fn main() -> Result<()> {
     }
 
     // Init tracing for non-daemon mode (daemon reconfigures after fork)
-    let init_tracing = |level: &str| {
+    let init_tracing = |filter: EnvFilter| {
         tracing_subscriber::fmt()
-            .with_env_filter(
-                EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level)),
-            )
+            .with_env_filter(filter)
             .with_timer(tracing_subscriber::fmt::time::LocalTime::rfc_3339())
             .with_writer(RedactingWriter::stderr())
             .init();
     };
 
-    let config_loader = opencrust_config::ConfigLoader::new()?;
+    let config_loader = opencrust_config::ConfigLoader::new()?.with_profile(cli.profile.clone());
     config_loader.ensure_dirs()?;
     let config = config_loader.load()?;
 
@@ -804,8 +1079,11 @@ async fn async_main(
     cli: Cli,
     config: opencrust_config::AppConfig,
     config_loader: opencrust_config::ConfigLoader,
-    init_tracing: impl Fn(&str),
+    init_tracing: impl Fn(EnvFilter),
 ) -> Result<()> {
+    let (log_level, quiet, verbose) = (cli.log_level, cli.quiet, cli.verbose);
+    let init_tracing = |level: &str| init_tracing(tracing_filter(level, quiet, verbose));
+
     match cli.command {
         Commands::Start {
             host, port, debug, ..
@@ -814,7 +1092,7 @@ async fn async_main(
             config.gateway.host = host.clone();
             config.gateway.port = port;
             config.debug = debug;
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
 
             // If no config file exists and we're in a terminal, offer to run the wizard
             if !config_loader.config_file_exists() && std::io::stdin().is_terminal() {
@@ -852,11 +1130,11 @@ async fn async_main(
             server.run().await?;
         }
         Commands::Stop => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             stop_daemon(config.gateway.port)?;
         }
         Commands::Restart { host, port, .. } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             try_stop_daemon(port);
             let mut config = config;
             config.gateway.host = host;
@@ -871,30 +1149,20 @@ async fn async_main(
             let server = opencrust_gateway::GatewayServer::new(config);
             server.run().await?;
         }
-        Commands::Status => {
-            init_tracing(&cli.log_level);
+        Commands::Status { json } => {
+            init_tracing(&log_level);
 
             // Check PID file first
-            if let Some(pid) = read_pid() {
-                if is_process_running(pid) {
-                    println!("OpenCrust daemon is running (PID {})", pid);
-                } else {
-                    println!(
-                        "OpenCrust daemon is not running (stale PID file for PID {})",
-                        pid
-                    );
-                    // Clean up stale PID file
-                    let _ = std::fs::remove_file(pid_file_path());
-                }
-            } else {
-                println!("No daemon PID file found.");
+            let pid = read_pid();
+            let running = pid.is_some_and(is_process_running);
+            if pid.is_some() && !running {
+                // Clean up stale PID file
+                let _ = std::fs::remove_file(pid_file_path());
             }
 
             // Also try the HTTP status endpoint
-            println!();
-            println!("Gateway status:");
             let client = reqwest::Client::new();
-            match client
+            let gateway = match client
                 .get(format!(
                     "http://{}:{}/api/status",
                     config.gateway.host, config.gateway.port
@@ -902,31 +1170,64 @@ async fn async_main(
                 .send()
                 .await
             {
-                Ok(resp) => {
-                    let body = resp.json::<serde_json::Value>().await?;
-                    println!("{}", serde_json::to_string_pretty(&body)?);
-                }
-                Err(_) => {
-                    println!("Gateway is not responding.");
-                }
+                Ok(resp) => Some(resp.json::<serde_json::Value>().await?),
+                Err(_) => None,
+            };
+
+            // Why the daemon last stopped. A leftover PID file means the
+            // process that owned it never got to record a reason.
+            let last_shutdown = if running {
+                None
+            } else {
+                shutdown::read(&shutdown::last_status_path(&opencrust_dir()))
+                    .filter(|last| pid.is_none_or(|pid| pid == last.pid))
+            };
+
+            if json {
+                let status = serde_json::json!({
+                    "daemon": { "running": running, "pid": pid },
+                    "last_shutdown": last_shutdown,
+                    "gateway": gateway,
+                });
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+
+            for line in daemon_status_lines(pid, running, last_shutdown.as_ref()) {
+                println!("{line}");
+            }
+            println!();
+            println!("Gateway status:");
+            match gateway {
+                Some(body) => println!("{}", serde_json::to_string_pretty(&body)?),
+                None => println!("Gateway is not responding."),
             }
         }
         Commands::Init => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             wizard::run_wizard(config_loader.config_dir()).await?;
         }
-        Commands::Chat { url, agent } => {
-            chat::run(url, agent).await?;
+        Commands::Chat { url, agent, local } => {
+            chat::run(config, url, agent, local).await?;
         }
         Commands::Channel { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             match action {
-                ChannelCommands::List => {
+                ChannelCommands::List { json: true } => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&channels_json(&config.channels))?
+                    );
+                }
+                ChannelCommands::List { json: false } => {
                     println!("Configured channels:");
                     if config.channels.is_empty() {
                         println!("  (none - add channels to config.yml)");
                     }
-                    for (name, ch) in &config.channels {
+                    let mut names: Vec<&String> = config.channels.keys().collect();
+                    names.sort();
+                    for name in names {
+                        let ch = &config.channels[name];
                         let enabled = ch.enabled.unwrap_or(true);
                         let status = if enabled { "enabled" } else { "disabled" };
                         println!("  {} [{}] - {}", name, ch.channel_type, status);
@@ -941,16 +1242,43 @@ async fn async_main(
                     ),
                     None => println!("channel '{}' not found in config", name),
                 },
+                ChannelCommands::Maintenance {
+                    name,
+                    message,
+                    queue,
+                } => {
+                    let body = serde_json::json!({ "message": message, "queue": queue });
+                    channel_maintenance_request(&config, reqwest::Method::PUT, &name, Some(body))
+                        .await?;
+                    let queued = if queue {
+                        "; messages are held until `opencrust channel resume`"
+                    } else {
+                        ""
+                    };
+                    println!("{name} is in maintenance mode{queued}");
+                }
+                ChannelCommands::Resume { name } => {
+                    let body =
+                        channel_maintenance_request(&config, reqwest::Method::DELETE, &name, None)
+                            .await?;
+                    let replayed = body["replayed"].as_u64().unwrap_or(0);
+                    println!("{name} resumed, answering {replayed} held message(s)");
+                }
             }
         }
         #[cfg(feature = "plugins")]
         Commands::Plugin { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             match action {
-                PluginCommands::List => {
+                PluginCommands::List { json } => {
                     let loader = opencrust_plugins::PluginLoader::new(
                         config_loader.config_dir().join("plugins"),
                     );
+                    if json {
+                        let plugins = loader.discover()?;
+                        println!("{}", serde_json::to_string_pretty(&plugins_json(&plugins))?);
+                        return Ok(());
+                    }
                     match loader.discover() {
                         Ok(plugins) => {
                             println!("Installed plugins:");
@@ -1057,7 +1385,10 @@ async fn async_main(
                     let plugins_dir = config_loader.config_dir().join("plugins");
                     std::fs::create_dir_all(&plugins_dir)?;
 
-                    let mut registry = opencrust_plugins::PluginRegistry::from_dir(&plugins_dir);
+                    let mut registry = opencrust_plugins::PluginRegistry::new(plugin_loader(
+                        &plugins_dir,
+                        &config,
+                    )?);
                     let count = registry.reload()?;
                     println!(
                         "Watching plugins directory: {}",
@@ -1069,14 +1400,39 @@ async fn async_main(
                     tokio::signal::ctrl_c().await?;
                     println!("Stopped plugin watcher.");
                 }
+                PluginCommands::Run { name, args, user } => {
+                    use std::io::Write as _;
+                    let plugins_dir = config_loader.config_dir().join("plugins");
+                    let plugins = plugin_loader(&plugins_dir, &config)?.discover()?;
+                    let Some(plugin) = plugins.into_iter().find(|p| p.name() == name) else {
+                        anyhow::bail!("plugin '{name}' is not installed");
+                    };
+                    let output = plugin
+                        .execute(opencrust_plugins::PluginInput {
+                            args,
+                            user_id: user,
+                            ..Default::default()
+                        })
+                        .await?;
+                    std::io::stdout().write_all(&output.stdout)?;
+                    std::io::stderr().write_all(&output.stderr)?;
+                    if output.status != 0 {
+                        std::process::exit(output.status);
+                    }
+                }
             }
         }
         Commands::Skill { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             let skills_dir = config_loader.config_dir().join("skills");
             match action {
-                SkillCommands::List => {
+                SkillCommands::List { json } => {
                     let scanner = opencrust_skills::SkillScanner::new(&skills_dir);
+                    if json {
+                        let skills = scanner.discover()?;
+                        println!("{}", serde_json::to_string_pretty(&skills_json(&skills))?);
+                        return Ok(());
+                    }
                     match scanner.discover() {
                         Ok(skills) => {
                             println!("Installed skills:");
@@ -1123,7 +1479,7 @@ async fn async_main(
             }
         }
         Commands::Mcp { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             let loader = opencrust_config::ConfigLoader::new()?;
             let mcp_configs = loader.merged_mcp_config(&config);
             match action {
@@ -1131,9 +1487,15 @@ async fn async_main(
                     wizard::run_mcp_add_wizard(config_loader.config_dir(), name.as_deref()).await?;
                 }
                 McpCommands::Remove { name } => {
-                    wizard::run_mcp_remove(config_loader.config_dir(), &config, &name)?;
+                    wizard::run_mcp_remove(config_loader.config_dir(), &name)?;
+                }
+                McpCommands::List { json: true } => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&mcp_servers_json(&mcp_configs))?
+                    );
                 }
-                McpCommands::List => {
+                McpCommands::List { json: false } => {
                     println!("Configured MCP servers:");
                     if mcp_configs.is_empty() {
                         println!("  (none — add servers to config.yml or ~/.opencrust/mcp.json)");
@@ -1275,7 +1637,7 @@ async fn async_main(
             }
         }
         Commands::Migrate { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             match action {
                 MigrateCommands::Openclaw { dry_run, source } => {
                     let opencrust_dir = config_loader.config_dir().to_path_buf();
@@ -1287,7 +1649,7 @@ async fn async_main(
             }
         }
         Commands::Doc { action } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             let data_dir = config.data_dir.clone().unwrap_or_else(|| {
                 opencrust_config::ConfigLoader::default_config_dir().join("data")
             });
@@ -1479,6 +1841,52 @@ async fn async_main(
                 }
             }
         }
+        Commands::Session { action } => {
+            init_tracing(&log_level);
+            let data_dir = config.data_dir.clone().unwrap_or_else(|| {
+                opencrust_config::ConfigLoader::default_config_dir().join("data")
+            });
+            std::fs::create_dir_all(&data_dir).ok();
+            let store = opencrust_db::SessionStore::open(&data_dir.join("sessions.db"))
+                .context("failed to open session store")?;
+
+            match action {
+                SessionCommands::List { tag, json } => {
+                    let tag = match tag.as_deref() {
+                        Some(raw) => Some(
+                            opencrust_db::normalize_tag(raw)
+                                .with_context(|| format!("invalid tag {raw:?}"))?,
+                        ),
+                        None => None,
+                    };
+                    let sessions = store.list_sessions(tag.as_deref())?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&sessions_json(&sessions))?
+                        );
+                    } else if sessions.is_empty() {
+                        match &tag {
+                            Some(tag) => println!("No sessions tagged \"{tag}\"."),
+                            None => println!("No stored sessions."),
+                        }
+                    } else {
+                        println!("Sessions:");
+                        for s in &sessions {
+                            let tags = if s.tags.is_empty() {
+                                String::new()
+                            } else {
+                                format!(" #{}", s.tags.join(" #"))
+                            };
+                            println!(
+                                "  {} [{}] {} - updated {}{}",
+                                s.id, s.channel_id, s.user_id, s.updated_at, tags
+                            );
+                        }
+                    }
+                }
+            }
+        }
         Commands::Doctor => {
             init_tracing("error");
             let passed = doctor::run_doctor(&config, config_loader.config_dir()).await?;
@@ -1487,21 +1895,21 @@ async fn async_main(
             }
         }
         Commands::Update { yes } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             match update::run_update(yes).await {
                 Ok(_) => {}
                 Err(e) => println!("update failed: {}", e),
             }
         }
         Commands::Rollback => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             match update::run_rollback() {
                 Ok(()) => {}
                 Err(e) => println!("rollback failed: {}", e),
             }
         }
         Commands::Uninstall { yes, keep_data } => {
-            init_tracing(&cli.log_level);
+            init_tracing(&log_level);
             run_uninstall(yes, keep_data, &config)?;
         }
     }
@@ -1545,17 +1953,40 @@ fn start_daemon(config: opencrust_config::AppConfig) -> Result<()> {
             // Build a fresh tokio runtime in the daemon child process.
             // This is safe because daemonization happened before any runtime
             // was created, so there are no stale kqueue/epoll FDs.
-            let rt = tokio::runtime::Runtime::new()
-                .context("failed to create tokio runtime in daemon")?;
-            rt.block_on(async {
-                let server = opencrust_gateway::GatewayServer::new(config);
-                if let Err(e) = server.run().await {
-                    tracing::error!("gateway error: {e}");
+            let reason = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt.block_on(async {
+                    let server = opencrust_gateway::GatewayServer::new(config);
+                    match server.run().await {
+                        Ok(()) => shutdown::ShutdownReason::Clean,
+                        Err(e) => {
+                            tracing::error!("gateway error: {e}");
+                            shutdown::ShutdownReason::Error {
+                                message: e.to_string(),
+                            }
+                        }
+                    }
+                }),
+                Err(e) => {
+                    tracing::error!("failed to create tokio runtime in daemon: {e}");
+                    shutdown::ShutdownReason::StartupError {
+                        message: format!("failed to create tokio runtime: {e}"),
+                    }
                 }
-            });
+            };
 
-            // Clean up PID file on exit
+            // Record why we stopped for `opencrust status`, then clean up the PID file.
+            let status_path = shutdown::last_status_path(&opencrust_dir());
+            if let Err(e) =
+                shutdown::record(&status_path, &shutdown::LastStatus::new(reason.clone()))
+            {
+                tracing::warn!("failed to write {}: {e}", status_path.display());
+            }
             let _ = std::fs::remove_file(&pid_path);
+
+            let code = reason.exit_code();
+            if code != shutdown::EXIT_CLEAN {
+                std::process::exit(code);
+            }
             Ok(())
         }
         Err(e) => {
@@ -1775,6 +2206,101 @@ mod tests {
     use wiremock::matchers::{body_string_contains, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    #[test]
+    fn tracing_filter_follows_output_mode() {
+        assert_eq!(tracing_filter("info", true, false).to_string(), "warn");
+        assert_eq!(tracing_filter("info", false, true).to_string(), "debug");
+        // Quiet and verbose take precedence over an explicit --log-level.
+        assert_eq!(tracing_filter("trace", true, false).to_string(), "warn");
+        assert_eq!(tracing_filter("error", false, true).to_string(), "debug");
+        if std::env::var_os("RUST_LOG").is_none() {
+            assert_eq!(tracing_filter("error", false, false).to_string(), "error");
+        }
+    }
+
+    #[test]
+    fn quiet_and_verbose_flags_parse_globally() {
+        let cli = Cli::try_parse_from(["opencrust", "status", "--quiet"]).unwrap();
+        assert!(cli.quiet && !cli.verbose);
+        let cli = Cli::try_parse_from(["opencrust", "-v", "status"]).unwrap();
+        assert!(cli.verbose && !cli.quiet);
+        assert!(Cli::try_parse_from(["opencrust", "status", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn list_commands_accept_json_flag() {
+        for args in [
+            &["opencrust", "status", "--json"][..],
+            &["opencrust", "channel", "list", "--json"],
+            &["opencrust", "skill", "list", "--json"],
+            &["opencrust", "mcp", "list", "--json"],
+            &["opencrust", "session", "list", "--json"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_ok(), "{args:?}");
+        }
+        let cli = Cli::try_parse_from(["opencrust", "skill", "list", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Skill {
+                action: SkillCommands::List { json: true }
+            }
+        ));
+    }
+
+    #[test]
+    fn session_list_accepts_tag_filter() {
+        let cli = Cli::try_parse_from(["opencrust", "session", "list", "--tag", "work"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Session {
+                action: SessionCommands::List { tag: Some(ref t), json: false }
+            } if t == "work"
+        ));
+    }
+
+    #[test]
+    fn skill_list_json_is_a_parseable_array() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("greet.md"),
+            "---\nname: greet\ndescription: Greets people\ntriggers: [hello]\n---\nSay hi.",
+        )
+        .unwrap();
+        let skills = opencrust_skills::SkillScanner::new(dir.path())
+            .discover()
+            .unwrap();
+
+        let out = serde_json::to_string_pretty(&skills_json(&skills)).unwrap();
+        let parsed: Vec<serde_json::Value> = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0]["name"], "greet");
+        assert_eq!(parsed[0]["description"], "Greets people");
+        assert_eq!(parsed[0]["triggers"], serde_json::json!(["hello"]));
+        assert!(parsed[0]["path"].as_str().unwrap().ends_with("greet.md"));
+        assert_eq!(skills_json(&[]), serde_json::json!([]));
+    }
+
+    #[test]
+    fn mcp_list_json_omits_env() {
+        let servers = HashMap::from([(
+            "fs".to_string(),
+            opencrust_config::McpServerConfig {
+                command: "npx".to_string(),
+                args: vec!["server-fs".to_string()],
+                env: HashMap::from([("TOKEN".to_string(), "secret".to_string())]),
+                transport: "stdio".to_string(),
+                url: None,
+                enabled: None,
+                timeout: None,
+            },
+        )]);
+        let json = mcp_servers_json(&servers);
+        assert_eq!(json[0]["name"], "fs");
+        assert_eq!(json[0]["enabled"], true);
+        assert_eq!(json[0]["timeout_secs"], 30);
+        assert!(!json.to_string().contains("secret"));
+    }
+
     struct MockEmbeddingProvider {
         model: &'static str,
         fail_on_substring: Option<&'static str>,
@@ -2187,4 +2713,30 @@ mod tests {
             vec![(Some("initial-model".to_string()), Some(3))]
         );
     }
+
+    #[test]
+    fn status_surfaces_last_shutdown_reason() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = shutdown::last_status_path(dir.path());
+        shutdown::record(
+            &path,
+            &shutdown::LastStatus::new(shutdown::ShutdownReason::Error {
+                message: "telegram token rejected".to_string(),
+            }),
+        )
+        .unwrap();
+        let last = shutdown::read(&path).unwrap();
+
+        let lines = daemon_status_lines(None, false, Some(&last));
+        assert_eq!(lines[0], "No daemon PID file found.");
+        assert!(lines[1].contains("gateway error"));
+        assert!(lines[1].contains("telegram token rejected"));
+
+        // A running daemon does not report an earlier shutdown.
+        assert_eq!(daemon_status_lines(Some(42), true, None).len(), 1);
+
+        // A stale PID file with no recorded reason means the daemon died.
+        let lines = daemon_status_lines(Some(42), false, None);
+        assert!(lines[1].contains("crashed or killed"));
+    }
 }
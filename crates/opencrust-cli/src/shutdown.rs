@@ -0,0 +1,132 @@
+//! Last-exit status of the background daemon.
+//!
+//! The daemon records why it stopped in `last_status.json` next to its PID
+//! file, so `opencrust status` can tell a clean stop from a failure after the
+//! process is gone. A daemon that dies without writing the file (killed or
+//! crashed) leaves its PID file behind instead.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Process exit code after a clean shutdown.
+pub const EXIT_CLEAN: i32 = 0;
+/// Process exit code when the gateway stopped because of an error.
+pub const EXIT_GATEWAY_ERROR: i32 = 1;
+/// Process exit code when the daemon could not start the gateway at all.
+pub const EXIT_STARTUP_ERROR: i32 = 3;
+
+/// Why the daemon stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ShutdownReason {
+    /// Stopped on request (SIGTERM or Ctrl+C).
+    Clean,
+    /// The gateway returned an error while running.
+    Error { message: String },
+    /// The daemon failed before the gateway started.
+    StartupError { message: String },
+}
+
+impl ShutdownReason {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Clean => EXIT_CLEAN,
+            Self::Error { .. } => EXIT_GATEWAY_ERROR,
+            Self::StartupError { .. } => EXIT_STARTUP_ERROR,
+        }
+    }
+}
+
+/// Contents of the last-status file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastStatus {
+    pub pid: u32,
+    pub stopped_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub reason: ShutdownReason,
+}
+
+impl LastStatus {
+    pub fn new(reason: ShutdownReason) -> Self {
+        Self {
+            pid: std::process::id(),
+            stopped_at: Utc::now(),
+            reason,
+        }
+    }
+
+    /// One-line summary for `opencrust status`.
+    pub fn describe(&self) -> String {
+        let when = self.stopped_at.format("%Y-%m-%d %H:%M:%S UTC");
+        match &self.reason {
+            ShutdownReason::Clean => {
+                format!("Last shutdown: clean, at {when} (PID {})", self.pid)
+            }
+            ShutdownReason::Error { message } => format!(
+                "Last shutdown: gateway error at {when} (PID {}): {message}",
+                self.pid
+            ),
+            ShutdownReason::StartupError { message } => format!(
+                "Last shutdown: failed to start at {when} (PID {}): {message}",
+                self.pid
+            ),
+        }
+    }
+}
+
+pub fn last_status_path(dir: &Path) -> PathBuf {
+    dir.join("last_status.json")
+}
+
+/// Write `status` to `path`, replacing any previous record.
+pub fn record(path: &Path, status: &LastStatus) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(status).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+/// Read the last-status file. Returns `None` when it is missing or unreadable.
+pub fn read(path: &Path) -> Option<LastStatus> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_shutdown_is_recorded_and_described() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = last_status_path(dir.path());
+        let status = LastStatus::new(ShutdownReason::Error {
+            message: "address already in use".to_string(),
+        });
+
+        record(&path, &status).unwrap();
+        let loaded = read(&path).unwrap();
+
+        assert_eq!(loaded, status);
+        assert_eq!(loaded.reason.exit_code(), EXIT_GATEWAY_ERROR);
+        let line = loaded.describe();
+        assert!(line.contains("gateway error"));
+        assert!(line.contains("address already in use"));
+    }
+
+    #[test]
+    fn clean_shutdown_exits_zero() {
+        let status = LastStatus::new(ShutdownReason::Clean);
+        assert_eq!(status.reason.exit_code(), EXIT_CLEAN);
+        assert!(status.describe().starts_with("Last shutdown: clean"));
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_reads_as_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = last_status_path(dir.path());
+        assert!(read(&path).is_none());
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read(&path).is_none());
+    }
+}
@@ -28,6 +28,7 @@ struct DetectedKeys {
     slack_bot_token: Option<String>,
     slack_app_token: Option<String>,
     whatsapp_access_token: Option<String>,
+    whatsapp_app_secret: Option<String>,
     line_channel_secret: Option<String>,
     line_channel_access_token: Option<String>,
     wechat_appid: Option<String>,
@@ -103,6 +104,7 @@ fn detect_env_keys() -> DetectedKeys {
         slack_bot_token: get("SLACK_BOT_TOKEN"),
         slack_app_token: get("SLACK_APP_TOKEN"),
         whatsapp_access_token: get("WHATSAPP_ACCESS_TOKEN"),
+        whatsapp_app_secret: get("WHATSAPP_APP_SECRET"),
         line_channel_secret: get("LINE_CHANNEL_SECRET"),
         line_channel_access_token: get("LINE_CHANNEL_ACCESS_TOKEN"),
         wechat_appid: get("WECHAT_APPID"),
@@ -166,6 +168,14 @@ async fn validate_llm_key(provider: &str, api_key: &str, base_url: Option<&str>)
             // 200 = ok, 401 = server reachable but key required
             status == 200 || status == 401
         }
+        "llamacpp" => {
+            // llama-server: /health needs no key and answers 503 while the model loads
+            let url = base_url.unwrap_or("http://localhost:8080");
+            let url = url.trim_end_matches('/');
+            let resp = client.get(format!("{url}/health")).send().await?;
+            let status = resp.status().as_u16();
+            status == 200 || status == 503
+        }
         _ => {
             // Unknown provider - skip validation
             return Ok(true);
@@ -317,6 +327,8 @@ fn validate_base_url(url: &str) -> Result<()> {
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// Load the base `config.yml` on its own. No profile overlay is applied, so
+/// whatever the wizard writes back never picks up `config.<profile>.yml` values.
 fn load_existing_config(config_dir: &Path) -> Option<AppConfig> {
     let loader = opencrust_config::ConfigLoader::with_dir(config_dir);
     if loader.config_file_exists() {
@@ -326,6 +338,24 @@ fn load_existing_config(config_dir: &Path) -> Option<AppConfig> {
     }
 }
 
+/// Re-read the base `config.yml`, apply `patch` and write it back. Returns
+/// `false` (and leaves the file alone) when `patch` reports no change.
+fn patch_base_config(
+    config_dir: &Path,
+    patch: impl FnOnce(&mut AppConfig) -> bool,
+) -> Result<bool> {
+    let config_path = config_dir.join("config.yml");
+    let mut config = load_existing_config(config_dir).unwrap_or_default();
+    if !patch(&mut config) {
+        return Ok(false);
+    }
+    opencrust_config::try_backup_file(&config_path);
+    let yaml = serde_yaml::to_string(&config).context("failed to serialize config")?;
+    std::fs::write(&config_path, &yaml)
+        .context(format!("failed to write {}", config_path.display()))?;
+    Ok(true)
+}
+
 fn env_var_for_provider(provider: &str) -> &str {
     opencrust_config::providers::env_var_for_provider(provider)
 }
@@ -860,11 +890,18 @@ async fn section_channels(
                     .and_then(|c| c.settings.get("verify_token"))
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string());
+                let existing_secret = channels
+                    .get("whatsapp")
+                    .and_then(|c| c.settings.get("app_secret"))
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
                 if let Some(cfg) = setup_whatsapp(
                     existing_token.as_deref(),
                     existing_phone.as_deref(),
                     existing_verify.as_deref(),
+                    existing_secret.as_deref(),
                     detected.whatsapp_access_token.as_deref(),
+                    detected.whatsapp_app_secret.as_deref(),
                 )
                 .await?
                 {
@@ -999,6 +1036,8 @@ async fn setup_telegram(
     Ok(Some(ChannelConfig {
         channel_type: "telegram".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1080,6 +1119,8 @@ async fn setup_discord(
     Ok(Some(ChannelConfig {
         channel_type: "discord".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1159,6 +1200,8 @@ async fn setup_slack(
     Ok(Some(ChannelConfig {
         channel_type: "slack".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1167,7 +1210,9 @@ async fn setup_whatsapp(
     existing_token: Option<&str>,
     existing_phone: Option<&str>,
     existing_verify: Option<&str>,
+    existing_secret: Option<&str>,
     env_token: Option<&str>,
+    env_secret: Option<&str>,
 ) -> Result<Option<ChannelConfig>> {
     println!();
     println!("  WhatsApp Setup");
@@ -1195,6 +1240,8 @@ async fn setup_whatsapp(
         return Ok(Some(ChannelConfig {
             channel_type: "whatsapp".to_string(),
             enabled: Some(true),
+            provider: None,
+            model: None,
             settings,
         }));
     }
@@ -1204,6 +1251,7 @@ async fn setup_whatsapp(
     println!("  1. Go to https://developers.facebook.com");
     println!("  2. Create a WhatsApp Business app");
     println!("  3. Get your access token and phone number ID");
+    println!("  4. Under 'App settings > Basic', copy the App secret");
     println!();
 
     let access_token = prompt_token_with_source(
@@ -1238,6 +1286,18 @@ async fn setup_whatsapp(
         .context("input cancelled")?;
     let verify_token = verify_token.trim().to_string();
 
+    // App secret, used to verify webhook signatures
+    let app_secret = prompt_token_with_source(
+        "App secret",
+        existing_secret,
+        env_secret,
+        "WHATSAPP_APP_SECRET",
+    )?;
+    if app_secret.is_empty() {
+        println!("  Skipping WhatsApp (webhooks need the app secret).");
+        return Ok(None);
+    }
+
     // No simple validation endpoint for WhatsApp - just save
     println!("  WhatsApp configured (no connection test available).");
 
@@ -1250,10 +1310,13 @@ async fn setup_whatsapp(
     if !verify_token.is_empty() {
         settings.insert("verify_token".to_string(), serde_json::json!(verify_token));
     }
+    settings.insert("app_secret".to_string(), serde_json::json!(app_secret));
 
     Ok(Some(ChannelConfig {
         channel_type: "whatsapp".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1341,6 +1404,8 @@ async fn setup_line(
     Ok(Some(ChannelConfig {
         channel_type: "line".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1407,6 +1472,8 @@ async fn setup_wechat(
     Ok(Some(ChannelConfig {
         channel_type: "wechat".to_string(),
         enabled: Some(true),
+        provider: None,
+        model: None,
         settings,
     }))
 }
@@ -1671,6 +1738,9 @@ pub async fn run_wizard(config_dir: &Path) -> Result<()> {
         if detected.whatsapp_access_token.is_some() {
             println!("    Found WHATSAPP_ACCESS_TOKEN");
         }
+        if detected.whatsapp_app_secret.is_some() {
+            println!("    Found WHATSAPP_APP_SECRET");
+        }
         if detected.line_channel_access_token.is_some() {
             println!("    Found LINE_CHANNEL_ACCESS_TOKEN");
         }
@@ -1712,6 +1782,8 @@ pub async fn run_wizard(config_dir: &Path) -> Result<()> {
             model: pr.model.clone(),
             api_key: None,
             base_url: pr.base_url.clone(),
+            max_tokens: None,
+            supports_tools: true,
             extra: Default::default(),
         };
 
@@ -2024,8 +2096,7 @@ async fn setup_cohere_embeddings(
 pub async fn run_mcp_add_wizard(config_dir: &Path, pre_selected: Option<&str>) -> Result<()> {
     use crate::mcp_registry::{self, KNOWN_MCP_SERVERS};
 
-    let config_path = config_dir.join("config.yml");
-    let mut config = load_existing_config(config_dir).unwrap_or_default();
+    let config = load_existing_config(config_dir).unwrap_or_default();
 
     // --- Server selection ---
     let known = if let Some(id) = pre_selected {
@@ -2119,11 +2190,12 @@ pub async fn run_mcp_add_wizard(config_dir: &Path, pre_selected: Option<&str>) -
     }
 
     // --- Save config ---
-    config.mcp.insert(server_name.clone(), mcp_config);
-    opencrust_config::try_backup_file(&config_path);
-    let yaml = serde_yaml::to_string(&config).context("failed to serialize config")?;
-    std::fs::write(&config_path, &yaml)
-        .context(format!("failed to write {}", config_path.display()))?;
+    // Patch a fresh read of the base file rather than `config`, which was
+    // loaded before the (possibly long) interactive prompts above.
+    patch_base_config(config_dir, |config| {
+        config.mcp.insert(server_name.clone(), mcp_config);
+        true
+    })?;
 
     println!();
     println!("  Server '{server_name}' added to config.yml.");
@@ -2365,11 +2437,11 @@ fn resolve_wizard_mcp_env(
 }
 
 /// Remove an MCP server from config and clean up vault entries.
-pub fn run_mcp_remove(config_dir: &Path, config: &AppConfig, name: &str) -> Result<()> {
-    let config_path = config_dir.join("config.yml");
-    let mut config = config.clone();
-
-    if config.mcp.remove(name).is_none() {
+///
+/// Only the base `config.yml` is rewritten; servers defined in a profile
+/// overlay have to be removed from that file by hand.
+pub fn run_mcp_remove(config_dir: &Path, name: &str) -> Result<()> {
+    if !patch_base_config(config_dir, |config| config.mcp.remove(name).is_some())? {
         println!("MCP server '{name}' not found in config.yml.");
         println!("If it's in ~/.opencrust/mcp.json, remove it manually.");
         return Ok(());
@@ -2401,11 +2473,6 @@ pub fn run_mcp_remove(config_dir: &Path, config: &AppConfig, name: &str) -> Resu
         }
     }
 
-    opencrust_config::try_backup_file(&config_path);
-    let yaml = serde_yaml::to_string(&config).context("failed to serialize config")?;
-    std::fs::write(&config_path, &yaml)
-        .context(format!("failed to write {}", config_path.display()))?;
-
     println!("Removed MCP server '{name}' from config.yml.");
     Ok(())
 }
@@ -2448,4 +2515,47 @@ mod tests {
         let result = validate_base_url("https://invalid url with spaces");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn mcp_remove_with_active_profile_leaves_overlay_out_of_base_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("config.yml"),
+            "gateway:\n  port: 3888\nmcp:\n  fs:\n    command: npx\n  git:\n    command: uvx\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("config.prod.yml"),
+            "gateway:\n  port: 9999\nmcp:\n  prod-only:\n    command: prod-server\n",
+        )
+        .unwrap();
+        let merged = opencrust_config::ConfigLoader::with_dir(dir.path())
+            .with_profile(Some("prod".into()))
+            .load()
+            .unwrap();
+        assert_eq!(merged.gateway.port, 9999);
+
+        run_mcp_remove(dir.path(), "fs").unwrap();
+
+        let base = load_existing_config(dir.path()).unwrap();
+        assert_eq!(base.gateway.port, 3888);
+        assert!(!base.mcp.contains_key("fs"));
+        assert!(base.mcp.contains_key("git"));
+        assert!(!base.mcp.contains_key("prod-only"));
+        let overlay = std::fs::read_to_string(dir.path().join("config.prod.yml")).unwrap();
+        assert!(overlay.contains("prod-only"));
+    }
+
+    #[test]
+    fn patch_base_config_skips_write_without_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yml");
+        std::fs::write(&path, "gateway:\n  port: 3888\n").unwrap();
+
+        assert!(!patch_base_config(dir.path(), |_| false).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "gateway:\n  port: 3888\n"
+        );
+    }
 }
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Source of the current time.
+///
+/// Components that expire or schedule things take an `Arc<dyn Clock>` so
+/// tests can swap in a [`FakeClock`] and move time forward without sleeping.
+pub trait Clock: Send + Sync {
+    /// Monotonic time, for TTLs and rate-limit windows.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, for schedules.
+    fn utc_now(&self) -> DateTime<Utc>;
+}
+
+/// The real clock, backed by `Instant::now()` and `Utc::now()`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when [`advance`](FakeClock::advance) is called.
+#[derive(Debug)]
+pub struct FakeClock {
+    start: Instant,
+    start_utc: DateTime<Utc>,
+    elapsed: Mutex<Duration>,
+}
+
+impl FakeClock {
+    /// A fake clock frozen at the current time.
+    pub fn new() -> Self {
+        Self::at(Utc::now())
+    }
+
+    /// A fake clock whose wall-clock time starts at `utc`.
+    pub fn at(utc: DateTime<Utc>) -> Self {
+        Self {
+            start: Instant::now(),
+            start_utc: utc,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move both the monotonic and wall-clock time forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        self.start_utc + chrono::Duration::from_std(self.elapsed()).unwrap_or(chrono::Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_clock_only_moves_when_advanced() {
+        let clock = FakeClock::new();
+        let (t0, u0) = (clock.now(), clock.utc_now());
+        assert_eq!(clock.now(), t0);
+        assert_eq!(clock.utc_now(), u0);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now() - t0, Duration::from_secs(90));
+        assert_eq!(clock.utc_now() - u0, chrono::Duration::seconds(90));
+    }
+}
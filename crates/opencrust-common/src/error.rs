@@ -13,6 +13,14 @@ pub enum Error {
     #[error("agent error: {0}")]
     Agent(String),
 
+    /// A provider answered HTTP 429. `retry_after` is the wait it asked for
+    /// via `Retry-After` or rate-limit reset headers, when present.
+    #[error("rate limited: {message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<std::time::Duration>,
+    },
+
     #[error("database error: {0}")]
     Database(String),
 
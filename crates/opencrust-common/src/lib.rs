@@ -1,7 +1,9 @@
+pub mod clock;
 pub mod error;
 pub mod message;
 pub mod types;
 
+pub use clock::{Clock, FakeClock, SystemClock};
 pub use error::{Error, Result};
-pub use message::{Message, MessageContent, MessageDirection};
+pub use message::{MediaAttachment, MediaKind, Message, MessageContent, MessageDirection};
 pub use types::{ChannelId, SessionId, UserId};
@@ -49,6 +49,113 @@ pub enum MessageContent {
         target_message_id: String,
     },
     System(String),
+    /// Media carried inline as bytes, received from or sent to a channel.
+    Attachment(MediaAttachment),
+}
+
+/// What kind of media a [`MediaAttachment`] holds. Channels use it to pick
+/// the matching upload API (photo, voice note, video, file).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaKind {
+    Photo,
+    Voice,
+    Video,
+    Document,
+}
+
+impl MediaKind {
+    /// Guess the kind from a MIME type. Unknown or missing types are documents.
+    pub fn from_mime(mime_type: Option<&str>) -> Self {
+        match mime_type.and_then(|mime| mime.split('/').next()) {
+            Some("image") => Self::Photo,
+            Some("audio") => Self::Voice,
+            Some("video") => Self::Video,
+            _ => Self::Document,
+        }
+    }
+}
+
+/// A channel-agnostic media attachment with its bytes already downloaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaAttachment {
+    pub kind: MediaKind,
+    pub data: Vec<u8>,
+    /// Original filename, when the platform reports one.
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    pub caption: Option<String>,
+    /// Length in seconds of voice and video clips, when known.
+    pub duration_secs: Option<u32>,
+}
+
+impl MediaAttachment {
+    pub fn new(kind: MediaKind, data: Vec<u8>) -> Self {
+        Self {
+            kind,
+            data,
+            filename: None,
+            mime_type: None,
+            caption: None,
+            duration_secs: None,
+        }
+    }
+
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    pub fn with_mime_type(mut self, mime_type: Option<String>) -> Self {
+        self.mime_type = mime_type;
+        self
+    }
+
+    pub fn with_caption(mut self, caption: Option<String>) -> Self {
+        self.caption = caption;
+        self
+    }
+
+    pub fn with_duration(mut self, duration_secs: u32) -> Self {
+        self.duration_secs = Some(duration_secs);
+        self
+    }
+
+    /// The filename, or a generic one for the kind when the platform gave none.
+    pub fn filename_or_default(&self) -> String {
+        self.filename.clone().unwrap_or_else(|| {
+            match self.kind {
+                MediaKind::Photo => "photo.jpg",
+                MediaKind::Voice => "voice.ogg",
+                MediaKind::Video => "video.mp4",
+                MediaKind::Document => "file",
+            }
+            .to_string()
+        })
+    }
+
+    /// Text stand-in for channels that cannot upload media, e.g.
+    /// `"[photo: chart.png] Weekly numbers"`.
+    ///
+    /// Senders without an upload path for the kind (MQTT, WeChat videos and
+    /// files, or LINE without a `public_url`) send this in place of the file,
+    /// so the recipient still learns that a file was shared and gets its
+    /// caption.
+    pub fn describe(&self) -> String {
+        let kind = match self.kind {
+            MediaKind::Photo => "photo",
+            MediaKind::Voice => "voice message",
+            MediaKind::Video => "video",
+            MediaKind::Document => "file",
+        };
+        let label = match &self.filename {
+            Some(filename) => format!("[{kind}: {filename}]"),
+            None => format!("[{kind}]"),
+        };
+        match self.caption.as_deref().filter(|c| !c.is_empty()) {
+            Some(caption) => format!("{label} {caption}"),
+            None => label,
+        }
+    }
 }
 
 impl Message {
@@ -112,6 +219,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn media_kind_from_mime() {
+        assert_eq!(MediaKind::from_mime(Some("image/png")), MediaKind::Photo);
+        assert_eq!(MediaKind::from_mime(Some("audio/ogg")), MediaKind::Voice);
+        assert_eq!(MediaKind::from_mime(Some("video/mp4")), MediaKind::Video);
+        assert_eq!(
+            MediaKind::from_mime(Some("application/pdf")),
+            MediaKind::Document
+        );
+        assert_eq!(MediaKind::from_mime(None), MediaKind::Document);
+    }
+
+    #[test]
+    fn attachment_describes_itself_as_text() {
+        let photo = MediaAttachment::new(MediaKind::Photo, vec![1, 2])
+            .with_filename("chart.png")
+            .with_caption(Some("Weekly numbers".into()));
+        assert_eq!(photo.describe(), "[photo: chart.png] Weekly numbers");
+
+        let voice = MediaAttachment::new(MediaKind::Voice, vec![]);
+        assert_eq!(voice.describe(), "[voice message]");
+        assert_eq!(voice.filename_or_default(), "voice.ogg");
+    }
+
     #[test]
     fn message_direction_serializes() {
         let json = serde_json::to_string(&MessageDirection::Incoming).unwrap();
@@ -3,10 +3,13 @@ pub mod model;
 pub mod providers;
 pub mod watcher;
 
-pub use loader::{ConfigLoader, backup_file, backup_file_with_limit, try_backup_file};
+pub use loader::{
+    ConfigLoader, PROFILE_ENV, backup_file, backup_file_with_limit, profile_path, try_backup_file,
+};
 pub use model::{
-    AgentConfig, AppConfig, ChannelConfig, EmbeddingProviderConfig, GatewayConfig,
-    LlmProviderConfig, McpServerConfig, MemoryConfig, NamedAgentConfig, ToolsConfig,
-    WebSearchConfig,
+    AgentConfig, AppConfig, ChannelConfig, EmbeddingProviderConfig, GatewayConfig, ImageGenConfig,
+    LanguageConfig, LlmProviderConfig, McpServerConfig, MemoryConfig, MessagesConfig,
+    ModelAliasConfig, NamedAgentConfig, SecurityConfig, SessionsConfig, ThinkingConfig,
+    ToolsConfig, WebFetchConfig, WebSearchConfig,
 };
 pub use watcher::ConfigWatcher;
@@ -59,14 +59,127 @@ fn backup_path(original: &Path, n: u32) -> PathBuf {
     PathBuf::from(name)
 }
 
+/// Environment variable selecting a config profile when none is given explicitly.
+pub const PROFILE_ENV: &str = "OPENCRUST_PROFILE";
+
+/// Path of the overlay for `profile` next to `base`: `config.yml` with
+/// profile `prod` becomes `config.prod.yml`.
+pub fn profile_path(base: &Path, profile: &str) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    match base.extension() {
+        Some(ext) => base.with_file_name(format!("{stem}.{profile}.{}", ext.to_string_lossy())),
+        None => base.with_file_name(format!("{stem}.{profile}")),
+    }
+}
+
+/// Profile names become part of a file name, so keep them to a safe charset.
+fn validate_profile(profile: &str) -> Result<()> {
+    let valid = !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "invalid config profile '{profile}': use letters, digits, '-' or '_'"
+        )))
+    }
+}
+
+/// Parse a YAML or TOML config file into a generic value for merging.
+fn read_config_value(path: &Path) -> Result<serde_json::Value> {
+    let contents = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "toml" => toml::from_str(&contents)
+            .map_err(|e| Error::Config(format!("failed to parse TOML config: {e}"))),
+        _ => serde_yaml::from_str::<Option<serde_json::Value>>(&contents)
+            .map(|value| value.unwrap_or_else(|| serde_json::json!({})))
+            .map_err(|e| Error::Config(format!("failed to parse YAML config: {e}"))),
+    }
+}
+
+/// Deep-merge `overlay` into `base`. Maps merge key by key; any other value
+/// (including lists) in the overlay replaces the base value.
+fn merge_config_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Read `path` and, when `profile` is set and its overlay exists, deep-merge
+/// the overlay over it. A missing overlay falls back to the base config.
+pub(crate) fn read_config_file(path: &Path, profile: Option<&str>) -> Result<AppConfig> {
+    let overlay = match profile {
+        Some(profile) => {
+            validate_profile(profile)?;
+            let overlay = profile_path(path, profile);
+            if overlay.exists() {
+                Some(overlay)
+            } else {
+                warn!(
+                    "config profile '{profile}' has no {}; using base config",
+                    overlay.display()
+                );
+                None
+            }
+        }
+        None => None,
+    };
+
+    let mut config: AppConfig = match overlay {
+        Some(overlay) => {
+            info!("applying config profile overlay {}", overlay.display());
+            let mut value = read_config_value(path)?;
+            merge_config_values(&mut value, read_config_value(&overlay)?);
+            serde_json::from_value(value)
+                .map_err(|e| Error::Config(format!("invalid config after profile merge: {e}")))?
+        }
+        None => {
+            let contents = std::fs::read_to_string(path)?;
+            if path.extension().is_some_and(|e| e == "toml") {
+                toml::from_str(&contents)
+                    .map_err(|e| Error::Config(format!("failed to parse TOML config: {e}")))?
+            } else {
+                serde_yaml::from_str(&contents)
+                    .map_err(|e| Error::Config(format!("failed to parse YAML config: {e}")))?
+            }
+        }
+    };
+    config.profile = profile.map(str::to_string);
+    Ok(config)
+}
+
 pub struct ConfigLoader {
     config_dir: PathBuf,
+    profile: Option<String>,
 }
 
 impl ConfigLoader {
     pub fn new() -> Result<Self> {
         let config_dir = Self::default_config_dir();
-        Ok(Self { config_dir })
+        Ok(Self {
+            config_dir,
+            profile: Self::profile_from_env(),
+        })
+    }
+
+    /// Profile named by `OPENCRUST_PROFILE`, if set and non-empty.
+    pub fn profile_from_env() -> Option<String> {
+        std::env::var(PROFILE_ENV)
+            .ok()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
     }
 
     pub fn default_config_dir() -> PathBuf {
@@ -97,9 +210,23 @@ impl ConfigLoader {
     pub fn with_dir(config_dir: impl Into<PathBuf>) -> Self {
         Self {
             config_dir: config_dir.into(),
+            profile: None,
         }
     }
 
+    /// Select the profile whose overlay `load` merges over the base config.
+    /// `Some` overrides `OPENCRUST_PROFILE`; `None` keeps the current choice.
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        if profile.is_some() {
+            self.profile = profile;
+        }
+        self
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
     pub fn config_dir(&self) -> &Path {
         &self.config_dir
     }
@@ -109,24 +236,22 @@ impl ConfigLoader {
         self.config_dir.join("config.yml").exists() || self.config_dir.join("config.toml").exists()
     }
 
+    /// Load `config.yml` (or `config.toml`), deep-merging the active profile's
+    /// overlay (`config.<profile>.yml`) over it when one exists.
     pub fn load(&self) -> Result<AppConfig> {
         let yaml_path = self.config_dir.join("config.yml");
         let toml_path = self.config_dir.join("config.toml");
 
-        if yaml_path.exists() {
-            info!("loading config from {}", yaml_path.display());
-            let contents = std::fs::read_to_string(&yaml_path)?;
-            serde_yaml::from_str(&contents)
-                .map_err(|e| Error::Config(format!("failed to parse YAML config: {e}")))
+        let path = if yaml_path.exists() {
+            yaml_path
         } else if toml_path.exists() {
-            info!("loading config from {}", toml_path.display());
-            let contents = std::fs::read_to_string(&toml_path)?;
-            toml::from_str(&contents)
-                .map_err(|e| Error::Config(format!("failed to parse TOML config: {e}")))
+            toml_path
         } else {
             info!("no config file found, using defaults");
-            Ok(AppConfig::default())
-        }
+            return Ok(AppConfig::default());
+        };
+        info!("loading config from {}", path.display());
+        read_config_file(&path, self.profile.as_deref())
     }
 
     /// Load MCP server configs from `~/.opencrust/mcp.json` (Claude Desktop compatible format).
@@ -392,4 +517,100 @@ mod tests {
 
         let _ = fs::remove_dir_all(dir);
     }
+
+    #[test]
+    fn profile_overlay_overrides_base_and_keeps_the_rest() {
+        let dir = temp_dir("profile-overlay");
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        fs::write(
+            dir.join("config.yml"),
+            "gateway:\n  host: \"0.0.0.0\"\n  port: 4001\nagent:\n  system_prompt: base\n  max_tokens: 1000\n",
+        )
+        .expect("failed to write base config");
+        fs::write(
+            dir.join("config.prod.yml"),
+            "gateway:\n  port: 8080\nagent:\n  max_tokens: 4000\n",
+        )
+        .expect("failed to write profile config");
+
+        let config = ConfigLoader::with_dir(&dir)
+            .with_profile(Some("prod".to_string()))
+            .load()
+            .expect("load should succeed");
+
+        assert_eq!(config.gateway.port, 8080);
+        assert_eq!(config.gateway.host, "0.0.0.0");
+        assert_eq!(config.agent.max_tokens, Some(4000));
+        assert_eq!(config.agent.system_prompt.as_deref(), Some("base"));
+        assert_eq!(config.profile.as_deref(), Some("prod"));
+
+        let base = ConfigLoader::with_dir(&dir)
+            .load()
+            .expect("load should succeed");
+        assert_eq!(base.gateway.port, 4001);
+        assert!(base.profile.is_none());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn missing_profile_overlay_falls_back_to_base() {
+        let dir = temp_dir("profile-missing");
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        fs::write(dir.join("config.yml"), "gateway:\n  port: 4001\n")
+            .expect("failed to write base config");
+
+        let config = ConfigLoader::with_dir(&dir)
+            .with_profile(Some("staging".to_string()))
+            .load()
+            .expect("load should succeed");
+
+        assert_eq!(config.gateway.port, 4001);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn toml_profile_overlay_is_merged() {
+        let dir = temp_dir("profile-toml");
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        fs::write(
+            dir.join("config.toml"),
+            "[gateway]\nhost = \"127.0.0.2\"\nport = 4002\n",
+        )
+        .expect("failed to write base config");
+        fs::write(dir.join("config.dev.toml"), "[gateway]\nport = 4999\n")
+            .expect("failed to write profile config");
+
+        let config = ConfigLoader::with_dir(&dir)
+            .with_profile(Some("dev".to_string()))
+            .load()
+            .expect("load should succeed");
+
+        assert_eq!(config.gateway.host, "127.0.0.2");
+        assert_eq!(config.gateway.port, 4999);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn profile_names_cannot_escape_the_config_dir() {
+        let dir = temp_dir("profile-invalid");
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        fs::write(dir.join("config.yml"), "gateway:\n  port: 4001\n")
+            .expect("failed to write base config");
+
+        let err = ConfigLoader::with_dir(&dir)
+            .with_profile(Some("../prod".to_string()))
+            .load()
+            .expect_err("path-like profile should be rejected");
+        assert!(err.to_string().contains("invalid config profile"));
+
+        assert_eq!(
+            super::profile_path(&dir.join("config.yml"), "prod"),
+            dir.join("config.prod.yml")
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
 }
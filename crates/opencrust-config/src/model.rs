@@ -13,12 +13,19 @@ pub struct AppConfig {
     #[serde(default)]
     pub llm: HashMap<String, LlmProviderConfig>,
 
+    /// Model aliases users can switch a conversation to with `/model <alias>`.
+    #[serde(default)]
+    pub models: HashMap<String, ModelAliasConfig>,
+
     #[serde(default)]
     pub embeddings: HashMap<String, EmbeddingProviderConfig>,
 
     #[serde(default)]
     pub memory: MemoryConfig,
 
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+
     #[serde(default)]
     pub agent: AgentConfig,
 
@@ -46,8 +53,20 @@ pub struct AppConfig {
     #[serde(default)]
     pub guardrails: GuardrailsConfig,
 
+    #[serde(default)]
+    pub security: SecurityConfig,
+
     #[serde(default)]
     pub voice: VoiceConfig,
+
+    /// Onboarding replies shared by all channels (overridable per channel).
+    #[serde(default)]
+    pub messages: MessagesConfig,
+
+    /// Profile whose overlay (`config.<profile>.yml`) was merged over the base
+    /// config. Set by the loader, never read from or written to a file.
+    #[serde(skip)]
+    pub profile: Option<String>,
 }
 
 impl Default for AppConfig {
@@ -56,8 +75,10 @@ impl Default for AppConfig {
             gateway: GatewayConfig::default(),
             channels: HashMap::new(),
             llm: HashMap::new(),
+            models: HashMap::new(),
             embeddings: HashMap::new(),
             memory: MemoryConfig::default(),
+            sessions: SessionsConfig::default(),
             agent: AgentConfig::default(),
             data_dir: None,
             log_level: Some("info".to_string()),
@@ -66,7 +87,10 @@ impl Default for AppConfig {
             agents: HashMap::new(),
             tools: ToolsConfig::default(),
             guardrails: GuardrailsConfig::default(),
+            security: SecurityConfig::default(),
             voice: VoiceConfig::default(),
+            messages: MessagesConfig::default(),
+            profile: None,
         }
     }
 }
@@ -161,6 +185,12 @@ pub struct ChannelConfig {
 
     pub enabled: Option<bool>,
 
+    /// LLM provider key (from the `llm:` section) that answers this channel.
+    /// Default: the default provider.
+    pub provider: Option<String>,
+    /// Model name override for this channel (otherwise the provider's default).
+    pub model: Option<String>,
+
     #[serde(flatten)]
     pub settings: HashMap<String, serde_json::Value>,
 }
@@ -171,11 +201,23 @@ pub struct LlmProviderConfig {
     pub model: Option<String>,
     pub api_key: Option<String>,
     pub base_url: Option<String>,
+    /// Response token limit for this provider. Overrides `agent.max_tokens`
+    /// when this provider handles a request. Default: unset.
+    pub max_tokens: Option<u32>,
+    /// Whether the endpoint accepts tool definitions. Set to false for
+    /// OpenAI-compatible servers that reject the `tools` field; the agent
+    /// then answers with plain text. Default: true.
+    #[serde(default = "default_supports_tools")]
+    pub supports_tools: bool,
 
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+fn default_supports_tools() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingProviderConfig {
     pub provider: String,
@@ -206,6 +248,11 @@ pub struct MemoryConfig {
     /// Default: true when memory is enabled.
     #[serde(default)]
     pub summarization: Option<bool>,
+
+    /// Delete conversation memory older than this many days. Notes saved with
+    /// the `memory` tool are kept. Default: unset (kept forever).
+    #[serde(default)]
+    pub retention_days: Option<u32>,
 }
 
 impl Default for MemoryConfig {
@@ -216,16 +263,30 @@ impl Default for MemoryConfig {
             shared_continuity: false,
             recall_limit: None,
             summarization: None,
+            retention_days: None,
         }
     }
 }
 
+/// Persistent conversation history (`sessions.db`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionsConfig {
+    /// Delete stored conversation turns older than this many days, and sessions
+    /// left without any. Default: unset (kept forever).
+    #[serde(default)]
+    pub retention_days: Option<u32>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentConfig {
     pub system_prompt: Option<String>,
     pub default_provider: Option<String>,
     pub max_tokens: Option<u32>,
     pub max_context_tokens: Option<usize>,
+    /// Sampling seed sent with every request so repeated runs return the same
+    /// output. Only honoured by providers that support it (OpenAI-compatible).
+    /// Default: unset.
+    pub seed: Option<u64>,
     /// Allow the agent to persist skills it discovers during conversations.
     /// Registers the `create_skill` tool at startup. Default: true.
     pub self_learning: Option<bool>,
@@ -235,6 +296,61 @@ pub struct AgentConfig {
     /// Persist every tool call and result to a trajectory database for analysis and training.
     /// Default: false. Stored in `{data_dir}/trajectories.db`.
     pub collect_trajectories: Option<bool>,
+    /// Write every LLM request and response (including tool calls) to
+    /// `~/.opencrust/transcripts/{session_id}.jsonl` for debugging prompts.
+    /// Secrets are redacted. Default: false.
+    pub transcripts: Option<bool>,
+    /// Root directory for per-session tool workspaces. When set, each session gets
+    /// its own subdirectory and relative file-tool paths resolve inside it.
+    pub workspace_dir: Option<PathBuf>,
+    /// Detect the language of inbound messages and instruct the model to reply in it.
+    pub language: Option<LanguageConfig>,
+    /// Let the model reason before answering (Anthropic extended thinking).
+    pub thinking: Option<ThinkingConfig>,
+}
+
+/// Opt-in language detection for inbound messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageConfig {
+    /// Enable detection. Default: false.
+    #[serde(default)]
+    pub detect: bool,
+    /// Per-language system prompt additions keyed by ISO 639-3 code (`spa`, `tha`, ...).
+    /// Languages without an entry get a generic "respond in {lang}" instruction.
+    #[serde(default)]
+    pub prompts: HashMap<String, String>,
+}
+
+/// Extended thinking. Providers without a thinking mode ignore it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingConfig {
+    /// Tokens the model may spend thinking, on top of `max_tokens`.
+    /// Anthropic requires at least 1024. Default: 4096.
+    #[serde(default = "default_thinking_budget")]
+    pub budget_tokens: u32,
+    /// Show the reasoning under the reply on channels that can collapse it
+    /// (Discord spoilers, Slack context blocks). Default: false.
+    #[serde(default)]
+    pub show_reasoning: bool,
+}
+
+fn default_thinking_budget() -> u32 {
+    4096
+}
+
+/// Target of a `models:` alias: a bare model name (`fast: gpt-4o-mini`), run
+/// on the `llm:` provider configured with that model or else on the
+/// channel's provider, or an explicit provider key with an optional model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModelAliasConfig {
+    Model(String),
+    Target {
+        /// Which LLM provider key (from `llm:` section) to use.
+        provider: Option<String>,
+        /// Model name (otherwise uses the provider's default).
+        model: Option<String>,
+    },
 }
 
 /// A named agent configuration for multi-agent routing.
@@ -263,6 +379,89 @@ pub struct NamedAgentConfig {
 pub struct ToolsConfig {
     #[serde(default)]
     pub web_search: Option<WebSearchConfig>,
+
+    /// Preview destructive tool calls (bash, file_write, file_patch) and only
+    /// execute them after the user replies with a confirmation. Default: false.
+    #[serde(default)]
+    pub confirm_destructive: bool,
+
+    /// Stream a short status line ("Searching the web...") to channels that
+    /// support streaming whenever a tool starts. Default: false.
+    #[serde(default)]
+    pub status_messages: bool,
+
+    /// Per-tool status lines keyed by tool name, replacing the built-in ones.
+    #[serde(default)]
+    pub status_overrides: HashMap<String, String>,
+
+    #[serde(default)]
+    pub web_fetch: WebFetchConfig,
+
+    /// Image generation backend for the `generate_image` tool. Unset disables the tool.
+    #[serde(default)]
+    pub image_gen: Option<ImageGenConfig>,
+}
+
+/// Settings for the `generate_image` tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageGenConfig {
+    /// `"openai"`, `"stability"` or `"sdwebui"` (a local Stable Diffusion web UI).
+    pub provider: String,
+    pub api_key: Option<String>,
+    /// OpenAI: `"dall-e-3"` (default) or `"gpt-image-1"`; Stability: `"core"`
+    /// (default), `"ultra"` or `"sd3"`. Ignored by sdwebui.
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    /// Default image size as `WIDTHxHEIGHT`.
+    pub size: Option<String>,
+    /// Sampling steps for sdwebui. Default: 20.
+    pub steps: Option<u32>,
+}
+
+/// Settings for the `web_fetch` tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebFetchConfig {
+    /// User-Agent header sent with every fetch. Default: `OpenCrust/<version>`.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    /// Check the site's robots.txt and refuse disallowed paths. Default: false.
+    #[serde(default)]
+    pub respect_robots: bool,
+
+    /// How long a fetched page is reused before it is downloaded again. A
+    /// shorter `Cache-Control: max-age` wins; 0 disables caching. Default: 300.
+    #[serde(default = "default_web_fetch_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+
+    /// Maximum number of URLs kept in the cache. Default: 100.
+    #[serde(default = "default_web_fetch_cache_max_entries")]
+    pub cache_max_entries: usize,
+
+    /// Directory to persist cached pages in across restarts. Default: unset
+    /// (memory only).
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+impl Default for WebFetchConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            respect_robots: false,
+            cache_ttl_secs: default_web_fetch_cache_ttl_secs(),
+            cache_max_entries: default_web_fetch_cache_max_entries(),
+            cache_dir: None,
+        }
+    }
+}
+
+fn default_web_fetch_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_web_fetch_cache_max_entries() -> usize {
+    100
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -337,6 +536,38 @@ impl Default for GuardrailsConfig {
     }
 }
 
+/// Ownership and access hardening.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Let the first user to message the bot become its owner. Disable this
+    /// so a leaked bot token can't be used to claim the bot. Default: true.
+    #[serde(default = "default_auto_claim_owner")]
+    pub auto_claim_owner: bool,
+
+    /// User id allowed to claim ownership when `auto_claim_owner` is false.
+    #[serde(default)]
+    pub owner_id: Option<String>,
+
+    /// Reply "message too long" to text messages over this many characters
+    /// instead of running the agent. File uploads are exempt. None = no limit.
+    #[serde(default)]
+    pub max_inbound_chars: Option<usize>,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            auto_claim_owner: default_auto_claim_owner(),
+            owner_id: None,
+            max_inbound_chars: None,
+        }
+    }
+}
+
+fn default_auto_claim_owner() -> bool {
+    true
+}
+
 fn default_max_input_chars() -> usize {
     16_000
 }
@@ -380,20 +611,36 @@ pub struct McpServerConfig {
     pub timeout: Option<u64>,
 }
 
+/// Customizable onboarding replies. `{user_name}` is replaced with the
+/// sender's display name; unset messages use the built-in wording.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessagesConfig {
+    /// Sent when the first user is auto-paired as the bot owner.
+    pub welcome_owner: Option<String>,
+    /// Sent when a user gains access with a pairing code.
+    pub welcome_user: Option<String>,
+    /// Sent to unpaired users, prompting for a pairing code.
+    pub blocked: Option<String>,
+}
+
 /// Voice input/output configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VoiceConfig {
-    /// TTS provider: `"openai"` (cloud) or `"kokoro"` (self-hosted).
+    /// TTS provider: `"openai"` or `"elevenlabs"` (cloud), `"piper"` (local
+    /// binary) or `"kokoro"` (self-hosted).
     #[serde(default)]
     pub tts_provider: Option<String>,
 
     /// Voice ID (provider-specific).
     /// OpenAI: `"alloy"` | `"echo"` | `"fable"` | `"onyx"` | `"nova"` | `"shimmer"`
+    /// ElevenLabs: a voice ID from the voice library (e.g. `"21m00Tcm4TlvDq8ikWAM"`)
     /// Kokoro: `"af_heart"` | `"af_bella"` | … (see Kokoro docs)
     #[serde(default)]
     pub voice: Option<String>,
 
-    /// Model override (OpenAI: `"tts-1"` / `"tts-1-hd"`; ignored by Kokoro).
+    /// Model override (OpenAI: `"tts-1"` / `"tts-1-hd"`; ElevenLabs:
+    /// `"eleven_multilingual_v2"`; ignored by Kokoro). For Piper, the path to
+    /// the `.onnx` voice model (required).
     #[serde(default)]
     pub model: Option<String>,
 
@@ -416,19 +663,25 @@ pub struct VoiceConfig {
     #[serde(default)]
     pub stt_model: Option<String>,
 
+    /// Path to a ggml Whisper model for on-device transcription. Tried before
+    /// any server or API; requires the `whisper-local` build feature and `ffmpeg`.
+    #[serde(default)]
+    pub stt_local_model: Option<String>,
+
     /// Maximum characters sent to TTS synthesis (default: 4000).
     /// Protects against OpenAI's 4096-char limit and oversized Kokoro responses.
     #[serde(default)]
     pub tts_max_chars: Option<usize>,
 
-    /// When `true`, voice-message inputs receive a voice response.
+    /// When `true`, voice-message inputs receive a voice response. Channels
+    /// can override this with their `reply_with_voice` setting.
     #[serde(default)]
     pub auto_reply_voice: bool,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::AppConfig;
+    use super::{AppConfig, ModelAliasConfig};
 
     #[test]
     fn app_config_defaults_include_memory_block() {
@@ -473,4 +726,42 @@ embeddings:
         assert_eq!(cohere.model.as_deref(), Some("embed-english-v3.0"));
         assert_eq!(cohere.dimensions, Some(1024));
     }
+
+    #[test]
+    fn parses_model_aliases() {
+        let raw = r#"
+models:
+  fast: gpt-4o-mini
+  smart:
+    provider: claude
+    model: claude-sonnet-4-5-20250929
+"#;
+
+        let config: AppConfig = serde_yaml::from_str(raw).expect("yaml should parse");
+        assert_eq!(
+            config.models.get("fast"),
+            Some(&ModelAliasConfig::Model("gpt-4o-mini".to_string()))
+        );
+        assert_eq!(
+            config.models.get("smart"),
+            Some(&ModelAliasConfig::Target {
+                provider: Some("claude".to_string()),
+                model: Some("claude-sonnet-4-5-20250929".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn thinking_budget_defaults_when_omitted() {
+        let raw = r#"
+agent:
+  thinking:
+    show_reasoning: true
+"#;
+
+        let config: AppConfig = serde_yaml::from_str(raw).expect("yaml should parse");
+        let thinking = config.agent.thinking.expect("thinking should be set");
+        assert_eq!(thinking.budget_tokens, 4096);
+        assert!(thinking.show_reasoning);
+    }
 }
@@ -65,6 +65,24 @@ pub static KNOWN_PROVIDERS: &[KnownProvider] = &[
         requires_api_key: true,
         is_local: false,
     },
+    KnownProvider {
+        id: "groq",
+        display_name: "Groq",
+        env_var: "GROQ_API_KEY",
+        default_base_url: None,
+        default_model: None,
+        requires_api_key: true,
+        is_local: false,
+    },
+    KnownProvider {
+        id: "grok",
+        display_name: "xAI Grok",
+        env_var: "GROK_API_KEY",
+        default_base_url: None,
+        default_model: None,
+        requires_api_key: true,
+        is_local: false,
+    },
     KnownProvider {
         id: "gemini",
         display_name: "Gemini",
@@ -74,6 +92,24 @@ pub static KNOWN_PROVIDERS: &[KnownProvider] = &[
         requires_api_key: true,
         is_local: false,
     },
+    KnownProvider {
+        id: "openrouter",
+        display_name: "OpenRouter",
+        env_var: "OPENROUTER_API_KEY",
+        default_base_url: None,
+        default_model: None,
+        requires_api_key: true,
+        is_local: false,
+    },
+    KnownProvider {
+        id: "llamacpp",
+        display_name: "llama.cpp server (local GGUF)",
+        env_var: "LLAMACPP_API_KEY",
+        default_base_url: Some("http://localhost:8080"),
+        default_model: Some("local"),
+        requires_api_key: false,
+        is_local: true,
+    },
     KnownProvider {
         id: "vllm",
         display_name: "vLLM (self-hosted)",
@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::sync::watch;
-use tracing::{info, warn};
+use tracing::{error, info};
 
 use crate::model::AppConfig;
 
@@ -19,10 +19,18 @@ pub struct ConfigWatcher {
 impl ConfigWatcher {
     /// Start watching `config_path`. Returns a receiver that yields the latest
     /// config whenever the file changes. The initial value is `initial_config`.
+    ///
+    /// When `initial_config.profile` is set, the profile overlay is watched too
+    /// and merged over the base file on every reload.
     pub fn start(
         config_path: PathBuf,
         initial_config: AppConfig,
     ) -> Result<(Self, watch::Receiver<AppConfig>), notify::Error> {
+        let profile = initial_config.profile.clone();
+        let overlay_filename = profile
+            .as_deref()
+            .map(|p| crate::loader::profile_path(&config_path, p))
+            .and_then(|p| p.file_name().map(|f| f.to_os_string()));
         let (tx, rx) = watch::channel(initial_config);
 
         // We watch the parent directory because editors often write to a temp
@@ -35,14 +43,17 @@ impl ConfigWatcher {
         let mut watcher =
             notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
                 if let Ok(event) = event {
-                    let dominated =
-                        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                    let dominated = matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    );
                     if dominated {
                         // Only fire if this event touches our config file
-                        let touches_config = event
-                            .paths
-                            .iter()
-                            .any(|p| p.file_name().map(|f| f == target_filename).unwrap_or(false));
+                        let touches_config = event.paths.iter().any(|p| {
+                            p.file_name().is_some_and(|f| {
+                                f == target_filename || overlay_filename.as_deref() == Some(f)
+                            })
+                        });
                         if touches_config {
                             let _ = notify_tx.try_send(());
                         }
@@ -64,14 +75,24 @@ impl ConfigWatcher {
                 tokio::time::sleep(Duration::from_millis(DEBOUNCE_MS)).await;
                 while notify_rx.try_recv().is_ok() {}
 
+                // A deleted file is not a request to fall back to defaults:
+                // keep serving the last-good config until it reappears.
+                if !cfg_path.exists() {
+                    error!(
+                        "config file {} was removed; keeping last-good config",
+                        cfg_path.display()
+                    );
+                    continue;
+                }
+
                 // Re-read the config
-                match reload_config(&cfg_path) {
+                match reload_config(&cfg_path, profile.as_deref()) {
                     Ok(new_config) => {
                         info!("config reloaded from {}", cfg_path.display());
                         let _ = tx.send(new_config);
                     }
                     Err(e) => {
-                        warn!("config reload failed (keeping previous config): {e}");
+                        error!("config reload failed (keeping last-good config): {e}");
                     }
                 }
             }
@@ -82,15 +103,117 @@ impl ConfigWatcher {
     }
 }
 
-fn reload_config(path: &Path) -> Result<AppConfig, String> {
-    let contents = std::fs::read_to_string(path).map_err(|e| format!("read error: {e}"))?;
+fn reload_config(path: &Path, profile: Option<&str>) -> Result<AppConfig, String> {
+    // An empty file parses as an all-default config, which would drop every
+    // provider and channel. Treat it as a half-written file instead.
+    let overlay = profile.map(|p| crate::loader::profile_path(path, p));
+    for file in std::iter::once(path).chain(overlay.as_deref().filter(|p| p.exists())) {
+        let contents = std::fs::read_to_string(file).map_err(|e| format!("read error: {e}"))?;
+        if contents.trim().is_empty() {
+            return Err(format!("config file {} is empty", file.display()));
+        }
+    }
 
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-    match ext {
-        "yml" | "yaml" => {
-            serde_yaml::from_str(&contents).map_err(|e| format!("YAML parse error: {e}"))
-        }
-        "toml" => toml::from_str(&contents).map_err(|e| format!("TOML parse error: {e}")),
-        other => Err(format!("unsupported config extension: {other}")),
+    if !matches!(ext, "yml" | "yaml" | "toml") {
+        return Err(format!("unsupported config extension: {ext}"));
+    }
+    crate::loader::read_config_file(path, profile).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigWatcher;
+    use crate::model::AppConfig;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tokio::sync::watch;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock should be after unix epoch")
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "opencrust-watcher-test-{}-{}-{}",
+            label,
+            std::process::id(),
+            nanos
+        ))
+    }
+
+    fn start(dir: &PathBuf) -> (ConfigWatcher, watch::Receiver<AppConfig>, PathBuf) {
+        fs::create_dir_all(dir).expect("failed to create temp dir");
+        let path = dir.join("config.yml");
+        fs::write(&path, "gateway:\n  port: 4001\n").expect("failed to write config");
+        let initial: AppConfig = serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let (watcher, rx) = ConfigWatcher::start(path.clone(), initial).expect("watcher starts");
+        (watcher, rx, path)
+    }
+
+    /// Longer than the debounce window, so any reload would have happened.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(super::DEBOUNCE_MS * 3)).await;
+    }
+
+    #[tokio::test]
+    async fn deleted_config_keeps_last_good_and_recovers() {
+        let dir = temp_dir("delete");
+        let (_watcher, mut rx, path) = start(&dir);
+
+        fs::remove_file(&path).expect("failed to delete config");
+        settle().await;
+
+        assert!(!rx.has_changed().unwrap());
+        assert_eq!(rx.borrow().gateway.port, 4001);
+
+        // The watcher survives the deletion and picks the file back up.
+        fs::write(&path, "gateway:\n  port: 4002\n").expect("failed to rewrite config");
+        tokio::time::timeout(Duration::from_secs(10), rx.changed())
+            .await
+            .expect("reload after recreate")
+            .unwrap();
+        assert_eq!(rx.borrow().gateway.port, 4002);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn emptied_config_keeps_last_good() {
+        let dir = temp_dir("empty");
+        let (_watcher, rx, path) = start(&dir);
+
+        fs::write(&path, "").expect("failed to truncate config");
+        settle().await;
+
+        assert!(!rx.has_changed().unwrap());
+        assert_eq!(rx.borrow().gateway.port, 4001);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn profile_overlay_edits_trigger_merged_reload() {
+        let dir = temp_dir("profile");
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        let path = dir.join("config.yml");
+        let overlay = dir.join("config.prod.yml");
+        fs::write(&path, "gateway:\n  host: \"0.0.0.0\"\n  port: 4001\n").unwrap();
+        fs::write(&overlay, "gateway:\n  port: 5001\n").unwrap();
+        let initial = crate::loader::read_config_file(&path, Some("prod")).unwrap();
+        let (_watcher, mut rx) = ConfigWatcher::start(path, initial).expect("watcher starts");
+
+        fs::write(&overlay, "gateway:\n  port: 5002\n").unwrap();
+        tokio::time::timeout(Duration::from_secs(10), rx.changed())
+            .await
+            .expect("reload after overlay edit")
+            .unwrap();
+        let config = rx.borrow();
+        assert_eq!(config.gateway.port, 5002);
+        assert_eq!(config.gateway.host, "0.0.0.0");
+        assert_eq!(config.profile.as_deref(), Some("prod"));
+
+        let _ = fs::remove_dir_all(dir);
     }
 }
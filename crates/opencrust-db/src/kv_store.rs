@@ -0,0 +1,203 @@
+use opencrust_common::{Error, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Small persistent key-value store for plugins and skills.
+///
+/// Entries are scoped by a namespace (usually the plugin or skill name) and an
+/// optional user id, so one plugin can never read another's state and per-user
+/// values stay separate from shared ones.
+pub struct KvStore {
+    conn: Mutex<Connection>,
+}
+
+impl KvStore {
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let conn = Connection::open(db_path)
+            .map_err(|e| Error::Database(format!("failed to open kv db: {e}")))?;
+        conn.execute_batch("PRAGMA journal_mode=WAL;")
+            .map_err(|e| Error::Database(format!("failed to set pragmas: {e}")))?;
+        let store = Self {
+            conn: Mutex::new(conn),
+        };
+        store.run_migrations()?;
+        Ok(store)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| Error::Database(format!("failed to open in-memory kv db: {e}")))?;
+        let store = Self {
+            conn: Mutex::new(conn),
+        };
+        store.run_migrations()?;
+        Ok(store)
+    }
+
+    fn run_migrations(&self) -> Result<()> {
+        let conn = self.connection()?;
+        // Shared (non-user) entries store an empty user_id so the primary key
+        // stays unique; NULLs would never collide.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS kv_entries (
+                namespace   TEXT NOT NULL,
+                user_id     TEXT NOT NULL DEFAULT '',
+                key         TEXT NOT NULL,
+                value       TEXT NOT NULL,
+                updated_at  TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (namespace, user_id, key)
+            );",
+        )
+        .map_err(|e| Error::Database(format!("kv migration failed: {e}")))?;
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| Error::Database("kv store lock poisoned".into()))
+    }
+
+    /// Read a value, or `None` if the key is not set.
+    pub fn get(&self, namespace: &str, user_id: Option<&str>, key: &str) -> Result<Option<String>> {
+        let conn = self.connection()?;
+        conn.query_row(
+            "SELECT value FROM kv_entries WHERE namespace = ?1 AND user_id = ?2 AND key = ?3",
+            params![namespace, user_id.unwrap_or_default(), key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| Error::Database(format!("kv get failed: {e}")))
+    }
+
+    /// Insert or replace a value.
+    pub fn set(
+        &self,
+        namespace: &str,
+        user_id: Option<&str>,
+        key: &str,
+        value: &str,
+    ) -> Result<()> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT INTO kv_entries (namespace, user_id, key, value, updated_at)
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))
+             ON CONFLICT(namespace, user_id, key)
+             DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![namespace, user_id.unwrap_or_default(), key, value],
+        )
+        .map_err(|e| Error::Database(format!("kv set failed: {e}")))?;
+        Ok(())
+    }
+
+    /// Remove a key. Returns `true` if it existed.
+    pub fn delete(&self, namespace: &str, user_id: Option<&str>, key: &str) -> Result<bool> {
+        let conn = self.connection()?;
+        let removed = conn
+            .execute(
+                "DELETE FROM kv_entries WHERE namespace = ?1 AND user_id = ?2 AND key = ?3",
+                params![namespace, user_id.unwrap_or_default(), key],
+            )
+            .map_err(|e| Error::Database(format!("kv delete failed: {e}")))?;
+        Ok(removed > 0)
+    }
+
+    /// List keys in a namespace for one user (or the shared scope), sorted.
+    pub fn keys(&self, namespace: &str, user_id: Option<&str>) -> Result<Vec<String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT key FROM kv_entries WHERE namespace = ?1 AND user_id = ?2 ORDER BY key",
+            )
+            .map_err(|e| Error::Database(format!("kv keys failed: {e}")))?;
+        let rows = stmt
+            .query_map(params![namespace, user_id.unwrap_or_default()], |row| {
+                row.get(0)
+            })
+            .map_err(|e| Error::Database(format!("kv keys failed: {e}")))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Database(format!("kv keys failed: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_store() -> KvStore {
+        KvStore::in_memory().expect("in-memory store should open")
+    }
+
+    #[test]
+    fn set_get_delete_round_trip() {
+        let store = make_store();
+        assert_eq!(store.get("counter", None, "hits").unwrap(), None);
+
+        store.set("counter", None, "hits", "1").unwrap();
+        store.set("counter", None, "hits", "2").unwrap();
+        assert_eq!(
+            store.get("counter", None, "hits").unwrap().as_deref(),
+            Some("2")
+        );
+
+        assert!(store.delete("counter", None, "hits").unwrap());
+        assert!(!store.delete("counter", None, "hits").unwrap());
+        assert_eq!(store.get("counter", None, "hits").unwrap(), None);
+    }
+
+    #[test]
+    fn namespaces_and_users_are_isolated() {
+        let store = make_store();
+        store.set("weather", None, "unit", "celsius").unwrap();
+        store
+            .set("weather", Some("alice"), "unit", "fahrenheit")
+            .unwrap();
+        store.set("news", None, "unit", "headlines").unwrap();
+
+        assert_eq!(
+            store.get("weather", None, "unit").unwrap().as_deref(),
+            Some("celsius")
+        );
+        assert_eq!(
+            store
+                .get("weather", Some("alice"), "unit")
+                .unwrap()
+                .as_deref(),
+            Some("fahrenheit")
+        );
+        assert_eq!(store.get("weather", Some("bob"), "unit").unwrap(), None);
+        assert_eq!(
+            store.get("news", None, "unit").unwrap().as_deref(),
+            Some("headlines")
+        );
+
+        store.delete("weather", Some("alice"), "unit").unwrap();
+        assert_eq!(
+            store.get("weather", None, "unit").unwrap().as_deref(),
+            Some("celsius")
+        );
+        assert_eq!(store.keys("news", None).unwrap(), vec!["unit"]);
+        assert!(store.keys("weather", Some("alice")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn values_persist_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kv.db");
+
+        {
+            let store = KvStore::open(&path).unwrap();
+            store.set("counter", Some("alice"), "hits", "42").unwrap();
+        }
+
+        let store = KvStore::open(&path).unwrap();
+        assert_eq!(
+            store
+                .get("counter", Some("alice"), "hits")
+                .unwrap()
+                .as_deref(),
+            Some("42")
+        );
+    }
+}
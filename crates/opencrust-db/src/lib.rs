@@ -1,4 +1,5 @@
 pub mod document_store;
+pub mod kv_store;
 pub mod memory_store;
 pub mod migrations;
 pub mod session_store;
@@ -8,11 +9,15 @@ pub mod vector_store;
 pub use document_store::{
     ChunkEmbeddingUpdate, DocumentChunk, DocumentInfo, DocumentStore, NewDocumentChunk,
 };
+pub use kv_store::KvStore;
 pub use memory_store::{
-    CompactionReport, MemoryEntry, MemoryProvider, MemoryRole, MemoryStore, NewMemoryEntry,
-    RecallQuery, SessionContext,
+    CompactionReport, MemoryAttachment, MemoryEntry, MemoryProvider, MemoryRole, MemoryStore,
+    NewMemoryEntry, RecallQuery, SessionContext,
+};
+pub use session_store::{
+    HistoryPurge, MAX_TAG_LEN, ScheduledMessage, ScheduledTask, SessionStore, SessionSummary,
+    TASK_KIND_HEARTBEAT, TASK_KIND_REMINDER, UsageAttribution, UsageRecord, normalize_tag,
 };
-pub use session_store::{ScheduledTask, SessionStore, UsageAttribution, UsageRecord};
 pub use trajectory_store::{
     RepeatedToolSequence, SummarySkillCandidate, TrajectoryEvent, TrajectoryEventType,
     TrajectoryStore, TrajectorySummary,
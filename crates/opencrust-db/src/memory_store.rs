@@ -3,17 +3,20 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use opencrust_common::{Error, Result};
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::cmp::Ordering;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, MutexGuard};
 use tracing::info;
 use uuid::Uuid;
 
 use crate::VectorStore;
-use crate::migrations::MEMORY_SCHEMA_V1;
+use crate::migrations::{MEMORY_SCHEMA_V1, MEMORY_SCHEMA_V2_COLUMNS};
 
 const DEFAULT_RECALL_LIMIT: usize = 20;
 const MAX_RECALL_LIMIT: usize = 200;
+/// Leading characters compared when matching a reply to its memory entry.
+const FEEDBACK_MATCH_CHARS: usize = 200;
 
 /// Persisted memory entry used for retrieval and context assembly.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +33,9 @@ pub struct MemoryEntry {
     pub embedding_model: Option<String>,
     pub embedding_dimensions: Option<usize>,
     pub metadata: serde_json::Value,
+    /// Files shared alongside this entry, stored under content-addressed paths.
+    #[serde(default)]
+    pub attachments: Vec<MemoryAttachment>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -45,6 +51,54 @@ pub struct NewMemoryEntry {
     pub embedding: Option<Vec<f32>>,
     pub embedding_model: Option<String>,
     pub metadata: serde_json::Value,
+    #[serde(default)]
+    pub attachments: Vec<MemoryAttachment>,
+}
+
+/// Reference to a raw file stored alongside a memory entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MemoryAttachment {
+    pub filename: String,
+    pub mime_type: Option<String>,
+    /// Hex-encoded SHA-256 of the file contents.
+    pub sha256: String,
+    pub size_bytes: u64,
+    /// Content-addressed location: `{attachments_dir}/{sha256[..2]}/{sha256}`.
+    pub path: PathBuf,
+}
+
+impl MemoryAttachment {
+    /// Write `data` under `dir` at its content-addressed path and return a reference.
+    /// Identical content is stored once; later writes reuse the existing file.
+    pub fn store(
+        dir: &Path,
+        filename: &str,
+        mime_type: Option<String>,
+        data: &[u8],
+    ) -> Result<Self> {
+        let sha256: String = Sha256::digest(data)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let shard = dir.join(&sha256[..2]);
+        let path = shard.join(&sha256);
+
+        if !path.exists() {
+            std::fs::create_dir_all(&shard).map_err(|e| {
+                Error::Database(format!("failed to create attachment directory: {e}"))
+            })?;
+            std::fs::write(&path, data)
+                .map_err(|e| Error::Database(format!("failed to write attachment: {e}")))?;
+        }
+
+        Ok(Self {
+            filename: filename.to_string(),
+            mime_type,
+            sha256,
+            size_bytes: data.len() as u64,
+            path,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -82,6 +136,9 @@ pub struct RecallQuery {
     pub query_text: Option<String>,
     pub query_embedding: Option<Vec<f32>>,
     pub session_id: Option<String>,
+    /// Restrict recall to these sessions, e.g. the ones carrying a tag.
+    #[serde(default)]
+    pub session_ids: Option<Vec<String>>,
     pub continuity_key: Option<String>,
     pub limit: usize,
 }
@@ -109,7 +166,9 @@ pub trait MemoryProvider: Send + Sync {
         limit: usize,
     ) -> Result<Vec<MemoryEntry>>;
     async fn compact(&self, before: DateTime<Utc>) -> Result<CompactionReport>;
+    async fn delete_expired(&self, before: DateTime<Utc>) -> Result<usize>;
     async fn delete_session_memory(&self, session_id: &str) -> Result<usize>;
+    async fn record_feedback(&self, session_id: &str, reply: &str, delta: i64) -> Result<bool>;
 }
 
 /// Backing store for long-term and session-scoped memory data.
@@ -166,6 +225,15 @@ impl MemoryStore {
         conn.execute_batch(MEMORY_SCHEMA_V1.sql)
             .map_err(|e| Error::Database(format!("memory migration failed: {e}")))?;
 
+        for (col, col_type) in MEMORY_SCHEMA_V2_COLUMNS {
+            let sql = format!("ALTER TABLE memory_entries ADD COLUMN {col} {col_type}");
+            if let Err(e) = conn.execute(&sql, [])
+                && !e.to_string().contains("duplicate column")
+            {
+                return Err(Error::Database(format!("memory v2 migration failed: {e}")));
+            }
+        }
+
         Ok(())
     }
 
@@ -218,6 +286,19 @@ impl MemoryStore {
         })
     }
 
+    /// Delete conversation entries created before `before`. Notes saved
+    /// explicitly (`metadata.source == "explicit"`) are kept.
+    pub async fn delete_expired(&self, before: DateTime<Utc>) -> Result<usize> {
+        let conn = self.connection()?;
+        conn.execute(
+            "DELETE FROM memory_entries
+             WHERE datetime(created_at) < datetime(?1)
+               AND coalesce(json_extract(metadata, '$.source'), '') != 'explicit'",
+            params![before.to_rfc3339()],
+        )
+        .map_err(|e| Error::Database(format!("failed to delete expired memory entries: {e}")))
+    }
+
     pub async fn delete_session_memory(&self, session_id: &str) -> Result<usize> {
         let conn = self.connection()?;
         conn.execute(
@@ -227,6 +308,33 @@ impl MemoryStore {
         .map_err(|e| Error::Database(format!("failed to delete session memory: {e}")))
     }
 
+    /// Add `delta` to `metadata.feedback` of the session's most recent
+    /// assistant entry for `reply`. Entries are matched on their first
+    /// [`FEEDBACK_MATCH_CHARS`] characters, since stored replies may be
+    /// truncated. Returns whether an entry was found.
+    pub async fn record_feedback(&self, session_id: &str, reply: &str, delta: i64) -> Result<bool> {
+        let conn = self.connection()?;
+        let updated = conn
+            .execute(
+                "UPDATE memory_entries
+                 SET metadata = json_set(
+                     coalesce(metadata, '{}'),
+                     '$.feedback',
+                     coalesce(json_extract(metadata, '$.feedback'), 0) + ?3
+                 )
+                 WHERE id = (
+                     SELECT id FROM memory_entries
+                     WHERE session_id = ?1 AND role = 'assistant'
+                       AND substr(content, 1, ?4) = substr(?2, 1, ?4)
+                     ORDER BY datetime(created_at) DESC, rowid DESC
+                     LIMIT 1
+                 )",
+                params![session_id, reply, delta, FEEDBACK_MATCH_CHARS as i64],
+            )
+            .map_err(|e| Error::Database(format!("failed to record memory feedback: {e}")))?;
+        Ok(updated > 0)
+    }
+
     fn remember_sync(&self, entry: NewMemoryEntry) -> Result<String> {
         if entry.content.trim().is_empty() {
             return Err(Error::Database("memory content cannot be empty".into()));
@@ -238,13 +346,16 @@ impl MemoryStore {
         let embedding_dimensions = entry.embedding.as_ref().map(|e| e.len() as i64);
         let metadata_json = serde_json::to_string(&entry.metadata)
             .map_err(|e| Error::Database(format!("failed to serialize memory metadata: {e}")))?;
+        let attachments_json = serde_json::to_string(&entry.attachments)
+            .map_err(|e| Error::Database(format!("failed to serialize memory attachments: {e}")))?;
 
         let conn = self.connection()?;
         conn.execute(
             "INSERT INTO memory_entries (
                 id, session_id, channel_id, user_id, continuity_key, role, content,
-                embedding, embedding_model, embedding_dimensions, metadata, created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                embedding, embedding_model, embedding_dimensions, metadata, created_at,
+                attachments
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 id,
                 entry.session_id,
@@ -258,6 +369,7 @@ impl MemoryStore {
                 embedding_dimensions,
                 metadata_json,
                 created_at,
+                attachments_json,
             ],
         )
         .map_err(|e| Error::Database(format!("failed to insert memory entry: {e}")))?;
@@ -289,7 +401,10 @@ impl MemoryStore {
             {
                 let candidate_ids: Vec<&str> =
                     knn_results.iter().map(|(id, _)| id.as_str()).collect();
-                let candidates = self.fetch_entries_by_ids(&candidate_ids)?;
+                let mut candidates = self.fetch_entries_by_ids(&candidate_ids)?;
+                if let Some(ids) = &query.session_ids {
+                    candidates.retain(|entry| ids.contains(&entry.session_id));
+                }
 
                 if !candidates.is_empty() {
                     return self.score_and_rank(candidates, &query, limit);
@@ -300,6 +415,7 @@ impl MemoryStore {
 
         let candidates = self.query_candidates_sync(
             query.session_id.as_deref(),
+            query.session_ids.as_deref(),
             query.continuity_key.as_deref(),
             query.query_text.as_deref(),
             limit.saturating_mul(4),
@@ -358,7 +474,8 @@ impl MemoryStore {
         let placeholders: String = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let sql = format!(
             "SELECT id, session_id, channel_id, user_id, continuity_key, role, content,
-                    embedding, embedding_model, embedding_dimensions, metadata, created_at
+                    embedding, embedding_model, embedding_dimensions, metadata, created_at,
+                    attachments
              FROM memory_entries
              WHERE id IN ({placeholders})"
         );
@@ -381,27 +498,34 @@ impl MemoryStore {
         continuity_key: Option<&str>,
         limit: usize,
     ) -> Result<Vec<MemoryEntry>> {
-        self.query_candidates_sync(session_id, continuity_key, None, limit)
+        self.query_candidates_sync(session_id, None, continuity_key, None, limit)
     }
 
     fn query_candidates_sync(
         &self,
         session_id: Option<&str>,
+        session_ids: Option<&[String]>,
         continuity_key: Option<&str>,
         query_text: Option<&str>,
         limit: usize,
     ) -> Result<Vec<MemoryEntry>> {
         let query_limit = clamp_limit(limit).min(MAX_RECALL_LIMIT) as i64;
+        let session_ids = session_ids
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| Error::Database(format!("failed to encode session filter: {e}")))?;
         let conn = self.connection()?;
 
         let mut stmt = conn
             .prepare(
                 "SELECT id, session_id, channel_id, user_id, continuity_key, role, content,
-                        embedding, embedding_model, embedding_dimensions, metadata, created_at
+                        embedding, embedding_model, embedding_dimensions, metadata, created_at,
+                        attachments
                  FROM memory_entries
                  WHERE (?1 IS NULL OR session_id = ?1)
                    AND (?2 IS NULL OR continuity_key = ?2)
                    AND (?3 IS NULL OR lower(content) LIKE '%' || lower(?3) || '%')
+                   AND (?5 IS NULL OR session_id IN (SELECT value FROM json_each(?5)))
                  ORDER BY datetime(created_at) DESC
                  LIMIT ?4",
             )
@@ -409,7 +533,13 @@ impl MemoryStore {
 
         let rows = stmt
             .query_map(
-                params![session_id, continuity_key, query_text, query_limit],
+                params![
+                    session_id,
+                    continuity_key,
+                    query_text,
+                    query_limit,
+                    session_ids
+                ],
                 row_to_entry,
             )
             .map_err(|e| Error::Database(format!("failed to execute recall query: {e}")))?;
@@ -446,9 +576,17 @@ impl MemoryProvider for MemoryStore {
         self.compact(before).await
     }
 
+    async fn delete_expired(&self, before: DateTime<Utc>) -> Result<usize> {
+        self.delete_expired(before).await
+    }
+
     async fn delete_session_memory(&self, session_id: &str) -> Result<usize> {
         self.delete_session_memory(session_id).await
     }
+
+    async fn record_feedback(&self, session_id: &str, reply: &str, delta: i64) -> Result<bool> {
+        self.record_feedback(session_id, reply, delta).await
+    }
 }
 
 fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryEntry> {
@@ -474,6 +612,11 @@ fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryEntry> {
         rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::other(e.to_string())))
     })?;
 
+    let attachments_str: Option<String> = row.get(12)?;
+    let attachments = attachments_str
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
     Ok(MemoryEntry {
         id: row.get(0)?,
         session_id: row.get(1)?,
@@ -486,6 +629,7 @@ fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryEntry> {
         embedding_model: row.get(8)?,
         embedding_dimensions: row.get::<_, Option<i64>>(9)?.map(|d| d as usize),
         metadata,
+        attachments,
         created_at,
     })
 }
@@ -592,8 +736,12 @@ impl<T> Pipe for T {}
 
 #[cfg(test)]
 mod tests {
-    use super::{MemoryRole, MemoryStore, NewMemoryEntry, RecallQuery};
+    use super::{
+        FEEDBACK_MATCH_CHARS, MemoryAttachment, MemoryRole, MemoryStore, NewMemoryEntry,
+        RecallQuery,
+    };
     use chrono::{Duration, Utc};
+    use rusqlite::params;
 
     fn entry(
         session_id: &str,
@@ -612,6 +760,7 @@ mod tests {
             embedding,
             embedding_model: Some("unit-test".to_string()),
             metadata: serde_json::json!({}),
+            attachments: Vec::new(),
         }
     }
 
@@ -737,6 +886,7 @@ mod tests {
                 query_text: None,
                 query_embedding: Some(vec![0.95, 0.05, 0.0]),
                 session_id: Some("session-a".to_string()),
+                session_ids: None,
                 continuity_key: None,
                 limit: 1,
             })
@@ -784,4 +934,188 @@ mod tests {
             .expect("delete should succeed");
         assert_eq!(deleted, 1);
     }
+
+    #[test]
+    fn attachment_is_stored_at_content_addressed_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = MemoryAttachment::store(dir.path(), "budget.csv", None, b"a,b\n1,2\n")
+            .expect("store should succeed");
+        assert_eq!(first.sha256.len(), 64);
+        assert_eq!(
+            first.path,
+            dir.path().join(&first.sha256[..2]).join(&first.sha256)
+        );
+        assert_eq!(std::fs::read(&first.path).unwrap(), b"a,b\n1,2\n");
+        assert_eq!(first.size_bytes, 8);
+
+        // Same bytes under a different name share the stored file.
+        let second = MemoryAttachment::store(dir.path(), "copy.csv", None, b"a,b\n1,2\n")
+            .expect("store should succeed");
+        assert_eq!(second.path, first.path);
+        assert_eq!(second.filename, "copy.csv");
+    }
+
+    #[tokio::test]
+    async fn attachment_reference_is_persisted_and_recalled() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let attachment = MemoryAttachment::store(
+            dir.path(),
+            "q3-spreadsheet.xlsx",
+            Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".to_string()),
+            b"fake xlsx bytes",
+        )
+        .expect("store should succeed");
+
+        let store = MemoryStore::in_memory().expect("failed to create in-memory memory store");
+        let mut shared = entry(
+            "session-a",
+            Some("continuity-1"),
+            "User shared file: q3-spreadsheet.xlsx",
+            MemoryRole::User,
+            None,
+        );
+        shared.attachments = vec![attachment.clone()];
+        store
+            .remember(shared)
+            .await
+            .expect("remember should succeed");
+        store
+            .remember(entry(
+                "session-a",
+                Some("continuity-1"),
+                "unrelated chatter",
+                MemoryRole::User,
+                None,
+            ))
+            .await
+            .expect("remember should succeed");
+
+        let recalled = store
+            .recall(RecallQuery {
+                query_text: Some("spreadsheet".to_string()),
+                query_embedding: None,
+                session_id: None,
+                session_ids: None,
+                continuity_key: Some("continuity-1".to_string()),
+                limit: 5,
+            })
+            .await
+            .expect("recall should succeed");
+
+        assert_eq!(recalled.len(), 1);
+        assert_eq!(recalled[0].attachments, vec![attachment]);
+        assert!(recalled[0].attachments[0].path.exists());
+    }
+
+    #[tokio::test]
+    async fn recall_can_be_limited_to_a_set_of_sessions() {
+        let store = MemoryStore::in_memory().expect("store should open");
+        for (session, content) in [
+            ("s-work", "standup notes for the roadmap"),
+            ("s-recipes", "roadmap for the sourdough starter"),
+            ("s-other", "roadmap of the city"),
+        ] {
+            store
+                .remember(entry(session, None, content, MemoryRole::User, None))
+                .await
+                .expect("remember should succeed");
+        }
+
+        let recalled = store
+            .recall(RecallQuery {
+                query_text: Some("roadmap".to_string()),
+                query_embedding: None,
+                session_id: None,
+                session_ids: Some(vec!["s-work".to_string(), "s-recipes".to_string()]),
+                continuity_key: None,
+                limit: 10,
+            })
+            .await
+            .expect("recall should succeed");
+
+        let mut sessions: Vec<_> = recalled.iter().map(|e| e.session_id.as_str()).collect();
+        sessions.sort();
+        assert_eq!(sessions, vec!["s-recipes", "s-work"]);
+    }
+
+    #[tokio::test]
+    async fn delete_expired_keeps_recent_entries_and_explicit_notes() {
+        let store = MemoryStore::in_memory().expect("store should open");
+        let old_turn = store
+            .remember(entry("s1", None, "old turn", MemoryRole::User, None))
+            .await
+            .unwrap();
+        let mut note = entry("s1", None, "old note", MemoryRole::System, None);
+        note.metadata = serde_json::json!({ "source": "explicit" });
+        let old_note = store.remember(note).await.unwrap();
+        store
+            .remember(entry("s1", None, "new turn", MemoryRole::User, None))
+            .await
+            .unwrap();
+        {
+            let conn = store.connection().unwrap();
+            let backdated = (Utc::now() - Duration::days(40)).to_rfc3339();
+            for id in [&old_turn, &old_note] {
+                conn.execute(
+                    "UPDATE memory_entries SET created_at = ?1 WHERE id = ?2",
+                    params![backdated, id],
+                )
+                .unwrap();
+            }
+        }
+
+        let deleted = store
+            .delete_expired(Utc::now() - Duration::days(30))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        let mut remaining: Vec<String> = store
+            .get_session_context("s1", 10)
+            .await
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["new turn", "old note"]);
+    }
+
+    #[tokio::test]
+    async fn record_feedback_scores_latest_matching_reply() {
+        let store = MemoryStore::in_memory().expect("store should open");
+        store
+            .remember(entry("s1", None, "Paris.", MemoryRole::Assistant, None))
+            .await
+            .unwrap();
+        let long_reply = "x".repeat(FEEDBACK_MATCH_CHARS + 50);
+        store
+            .remember(entry(
+                "s1",
+                None,
+                &long_reply[..FEEDBACK_MATCH_CHARS + 10],
+                MemoryRole::Assistant,
+                None,
+            ))
+            .await
+            .unwrap();
+
+        assert!(store.record_feedback("s1", "Paris.", 1).await.unwrap());
+        assert!(store.record_feedback("s1", "Paris.", 1).await.unwrap());
+        // Matches the stored (truncated) copy of a longer reply.
+        assert!(store.record_feedback("s1", &long_reply, -1).await.unwrap());
+        assert!(!store.record_feedback("s1", "Berlin.", 1).await.unwrap());
+        assert!(!store.record_feedback("s2", "Paris.", 1).await.unwrap());
+
+        let entries = store.get_session_context("s1", 10).await.unwrap().entries;
+        let feedback = |content: &str| {
+            entries
+                .iter()
+                .find(|e| e.content.starts_with(content))
+                .and_then(|e| e.metadata.get("feedback").and_then(|v| v.as_i64()))
+        };
+        assert_eq!(feedback("Paris."), Some(2));
+        assert_eq!(feedback("xxx"), Some(-1));
+    }
 }
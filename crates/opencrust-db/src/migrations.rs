@@ -40,6 +40,9 @@ pub const MEMORY_SCHEMA_V1: Migration = Migration {
     sql: MEMORY_SCHEMA_V1_SQL,
 };
 
+/// Idempotent column additions to memory_entries for file attachment references.
+pub const MEMORY_SCHEMA_V2_COLUMNS: &[(&str, &str)] = &[("attachments", "TEXT DEFAULT '[]'")];
+
 pub const DOCUMENT_SCHEMA_V1_SQL: &str = "
 CREATE TABLE IF NOT EXISTS documents (
     id TEXT PRIMARY KEY,
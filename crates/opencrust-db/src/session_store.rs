@@ -16,6 +16,39 @@ pub struct StoredMessage {
     pub metadata: serde_json::Value,
 }
 
+/// A session row with its tags, as returned by [`SessionStore::list_sessions`].
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub channel_id: String,
+    pub user_id: String,
+    pub updated_at: String,
+    pub tags: Vec<String>,
+}
+
+/// Longest accepted session tag, in characters.
+pub const MAX_TAG_LEN: usize = 32;
+
+/// Normalize a user-supplied tag: trimmed, lowercased, without a leading `#`.
+/// Returns `None` unless the result is 1..=[`MAX_TAG_LEN`] characters of
+/// letters, digits, `-` or `_`.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let tag = raw.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(tag)
+}
+
+/// Rows removed by [`SessionStore::delete_history_before`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryPurge {
+    pub messages: usize,
+    pub sessions: usize,
+}
+
 /// Aggregated token usage statistics.
 #[derive(Debug, Clone, Default)]
 pub struct UsageRecord {
@@ -106,7 +139,41 @@ impl SessionStore {
                 );
 
                 CREATE INDEX IF NOT EXISTS idx_tasks_execute_at
-                    ON scheduled_tasks(execute_at) WHERE status = 'pending';",
+                    ON scheduled_tasks(execute_at) WHERE status = 'pending';
+
+                CREATE TABLE IF NOT EXISTS session_tags (
+                    session_id TEXT NOT NULL,
+                    tag TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    PRIMARY KEY (session_id, tag)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_session_tags_tag
+                    ON session_tags(tag);
+
+                CREATE TABLE IF NOT EXISTS scheduled_messages (
+                    id TEXT PRIMARY KEY,
+                    channel TEXT NOT NULL,
+                    recipient TEXT NOT NULL,
+                    send_at TEXT NOT NULL,
+                    text TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'pending',
+                    attempts INTEGER NOT NULL DEFAULT 0,
+                    next_attempt_at TEXT,
+                    session_id TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_scheduled_messages_send_at
+                    ON scheduled_messages(send_at) WHERE status = 'pending';
+
+                CREATE TABLE IF NOT EXISTS inbound_messages (
+                    key TEXT PRIMARY KEY,
+                    seen_at TEXT NOT NULL
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_inbound_messages_seen_at
+                    ON inbound_messages(seen_at);",
         )
         .map_err(|e| Error::Database(format!("migration failed: {e}")))?;
 
@@ -137,6 +204,7 @@ impl SessionStore {
             ("recurrence_end_at", "TEXT"),
             ("deliver_to_channel", "TEXT"),
             ("timezone", "TEXT"),
+            ("kind", "TEXT DEFAULT 'heartbeat'"),
         ];
         for (col, col_type) in &columns {
             let sql = format!("ALTER TABLE scheduled_tasks ADD COLUMN {col} {col_type}");
@@ -148,6 +216,14 @@ impl SessionStore {
                 }
             }
         }
+        // Session that scheduled the message through the agent, if any.
+        if let Err(e) = conn.execute(
+            "ALTER TABLE scheduled_messages ADD COLUMN session_id TEXT",
+            [],
+        ) && !e.to_string().contains("duplicate column")
+        {
+            return Err(Error::Database(format!("migration failed: {e}")));
+        }
 
         Ok(())
     }
@@ -275,26 +351,61 @@ impl SessionStore {
         Ok(task_id)
     }
 
+    /// Schedule a reminder: `text` is delivered verbatim to the session's
+    /// channel at `execute_at`, without running the agent.
+    pub fn schedule_reminder(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        execute_at: chrono::DateTime<chrono::Utc>,
+        text: &str,
+    ) -> Result<String> {
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO scheduled_tasks (id, session_id, user_id, execute_at, payload, status, kind)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6)",
+            params![
+                task_id,
+                session_id,
+                user_id,
+                execute_at.to_rfc3339(),
+                text,
+                TASK_KIND_REMINDER
+            ],
+        )
+        .map_err(|e| Error::Database(format!("failed to schedule reminder: {e}")))?;
+        Ok(task_id)
+    }
+
     /// Poll for pending tasks that are due for execution.
     pub fn poll_due_tasks(&self) -> Result<Vec<ScheduledTask>> {
+        self.poll_due_tasks_at(chrono::Utc::now())
+    }
+
+    /// Poll for pending tasks that are due as of `now`.
+    pub fn poll_due_tasks_at(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<ScheduledTask>> {
         let conn = self.conn()?;
         let mut stmt = conn
             .prepare(
                 "SELECT t.id, t.session_id, s.channel_id, t.user_id, t.execute_at, t.payload,
                         s.metadata, t.retry_count, t.max_retries, t.heartbeat_depth,
                         t.recurrence_type, t.recurrence_value, t.recurrence_end_at,
-                        t.deliver_to_channel, t.timezone
+                        t.deliver_to_channel, t.timezone, t.kind
                  FROM scheduled_tasks t
                  JOIN sessions s ON t.session_id = s.id
                  WHERE t.status = 'pending'
-                   AND datetime(COALESCE(t.next_retry_at, t.execute_at)) <= datetime('now')
+                   AND datetime(COALESCE(t.next_retry_at, t.execute_at)) <= datetime(?1)
                  ORDER BY t.execute_at ASC
                  LIMIT 10",
             )
             .map_err(|e| Error::Database(format!("failed to prepare poll query: {e}")))?;
 
         let rows = stmt
-            .query_map([], |row| {
+            .query_map(params![now.to_rfc3339()], |row| {
                 let execute_at_raw: String = row.get(4)?;
                 let metadata_raw: String = row.get(6)?;
                 let end_at_raw: Option<String> = row.get(12)?;
@@ -315,6 +426,9 @@ impl SessionStore {
                     recurrence_end_at: end_at_raw.map(|s| parse_timestamp(&s)),
                     deliver_to_channel: row.get(13)?,
                     timezone: row.get(14)?,
+                    kind: row
+                        .get::<_, Option<String>>(15)?
+                        .unwrap_or_else(|| TASK_KIND_HEARTBEAT.to_string()),
                 })
             })
             .map_err(|e| Error::Database(format!("failed to poll tasks: {e}")))?;
@@ -374,6 +488,92 @@ impl SessionStore {
         }
     }
 
+    /// Tag a session. Tags are kept apart from the session metadata, so they
+    /// survive metadata rewrites. Returns `false` if the tag was already set.
+    pub fn tag_session(&self, session_id: &str, tag: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let inserted = conn
+            .execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag) VALUES (?1, ?2)",
+                params![session_id, tag],
+            )
+            .map_err(|e| Error::Database(format!("failed to tag session: {e}")))?;
+        Ok(inserted > 0)
+    }
+
+    /// Tags on a session, sorted by name.
+    pub fn session_tags(&self, session_id: &str) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT tag FROM session_tags WHERE session_id = ?1 ORDER BY tag")
+            .map_err(|e| Error::Database(format!("failed to prepare tags query: {e}")))?;
+        let rows = stmt
+            .query_map(params![session_id], |row| row.get(0))
+            .map_err(|e| Error::Database(format!("failed to query session tags: {e}")))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Database(format!("failed to read session tags: {e}")))
+    }
+
+    /// IDs of the sessions carrying `tag`.
+    pub fn sessions_with_tag(&self, tag: &str) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT session_id FROM session_tags WHERE tag = ?1 ORDER BY session_id")
+            .map_err(|e| Error::Database(format!("failed to prepare tag query: {e}")))?;
+        let rows = stmt
+            .query_map(params![tag], |row| row.get(0))
+            .map_err(|e| Error::Database(format!("failed to query tagged sessions: {e}")))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Database(format!("failed to read tagged sessions: {e}")))
+    }
+
+    /// Distinct user IDs that have a session on `channel_id`.
+    pub fn channel_users(&self, channel_id: &str) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT user_id FROM sessions WHERE channel_id = ?1 ORDER BY user_id")
+            .map_err(|e| Error::Database(format!("failed to prepare channel users query: {e}")))?;
+        let rows = stmt
+            .query_map(params![channel_id], |row| row.get(0))
+            .map_err(|e| Error::Database(format!("failed to query channel users: {e}")))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Database(format!("failed to read channel users: {e}")))
+    }
+
+    /// List sessions, most recently active first. With `tag`, only sessions
+    /// carrying that tag are returned.
+    pub fn list_sessions(&self, tag: Option<&str>) -> Result<Vec<SessionSummary>> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.id, s.channel_id, s.user_id, s.updated_at,
+                        (SELECT group_concat(tag, ',') FROM session_tags WHERE session_id = s.id)
+                 FROM sessions s
+                 WHERE ?1 IS NULL
+                    OR EXISTS (SELECT 1 FROM session_tags t WHERE t.session_id = s.id AND t.tag = ?1)
+                 ORDER BY s.updated_at DESC, s.id",
+            )
+            .map_err(|e| Error::Database(format!("failed to prepare session list: {e}")))?;
+        let rows = stmt
+            .query_map(params![tag], |row| {
+                let tags: Option<String> = row.get(4)?;
+                let mut tags: Vec<String> = tags
+                    .map(|t| t.split(',').map(str::to_string).collect())
+                    .unwrap_or_default();
+                tags.sort();
+                Ok(SessionSummary {
+                    id: row.get(0)?,
+                    channel_id: row.get(1)?,
+                    user_id: row.get(2)?,
+                    updated_at: row.get(3)?,
+                    tags,
+                })
+            })
+            .map_err(|e| Error::Database(format!("failed to list sessions: {e}")))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Database(format!("failed to read session list: {e}")))
+    }
+
     /// Delete all but the most recent `keep` messages for a session.
     /// Returns the number of deleted rows.
     pub fn prune_old_messages(&self, session_id: &str, keep: usize) -> Result<usize> {
@@ -390,6 +590,22 @@ impl SessionStore {
         Ok(deleted)
     }
 
+    /// Delete the session's last user message and everything stored after it,
+    /// i.e. the most recent turn. Returns the number of deleted rows.
+    pub fn delete_last_turn(&self, session_id: &str) -> Result<usize> {
+        let conn = self.conn()?;
+        let deleted = conn
+            .execute(
+                "DELETE FROM messages WHERE session_id = ?1 AND rowid >= (
+                    SELECT MAX(rowid) FROM messages
+                    WHERE session_id = ?1 AND direction = 'user'
+                )",
+                params![session_id],
+            )
+            .map_err(|e| Error::Database(format!("failed to delete last turn: {e}")))?;
+        Ok(deleted)
+    }
+
     /// Count pending scheduled tasks for a given session.
     pub fn count_pending_tasks_for_session(&self, session_id: &str) -> Result<i64> {
         let conn = self.conn()?;
@@ -456,7 +672,7 @@ impl SessionStore {
                 "SELECT t.id, t.session_id, s.channel_id, t.user_id, t.execute_at, t.payload,
                         s.metadata, t.retry_count, t.max_retries, t.heartbeat_depth,
                         t.recurrence_type, t.recurrence_value, t.recurrence_end_at,
-                        t.deliver_to_channel, t.timezone
+                        t.deliver_to_channel, t.timezone, t.kind
                  FROM scheduled_tasks t
                  JOIN sessions s ON t.session_id = s.id
                  WHERE t.session_id = ?1 AND t.status = 'pending'
@@ -486,6 +702,9 @@ impl SessionStore {
                     recurrence_end_at: end_at_raw.map(|s| parse_timestamp(&s)),
                     deliver_to_channel: row.get(13)?,
                     timezone: row.get(14)?,
+                    kind: row
+                        .get::<_, Option<String>>(15)?
+                        .unwrap_or_else(|| TASK_KIND_HEARTBEAT.to_string()),
                 })
             })
             .map_err(|e| Error::Database(format!("failed to list tasks: {e}")))?;
@@ -509,6 +728,13 @@ impl SessionStore {
             params![interval],
         )
         .map_err(|e| Error::Database(format!("failed to cleanup session messages: {e}")))?;
+        conn.execute(
+            "DELETE FROM session_tags WHERE session_id IN (
+                     SELECT id FROM sessions WHERE updated_at < datetime('now', ?1)
+                 )",
+            params![interval],
+        )
+        .map_err(|e| Error::Database(format!("failed to cleanup session tags: {e}")))?;
         let deleted = conn
             .execute(
                 "DELETE FROM sessions WHERE updated_at < datetime('now', ?1)",
@@ -518,6 +744,37 @@ impl SessionStore {
         Ok(deleted)
     }
 
+    /// Delete messages stored before `before`, then sessions that have no
+    /// messages left and were last active before `before`, with their tags.
+    pub fn delete_history_before(
+        &self,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<HistoryPurge> {
+        let cutoff = before.to_rfc3339();
+        let conn = self.conn()?;
+        let messages = conn
+            .execute(
+                "DELETE FROM messages WHERE datetime(timestamp) < datetime(?1)",
+                params![cutoff],
+            )
+            .map_err(|e| Error::Database(format!("failed to delete old messages: {e}")))?;
+        let empty_sessions = "SELECT id FROM sessions s
+             WHERE datetime(s.updated_at) < datetime(?1)
+               AND NOT EXISTS (SELECT 1 FROM messages m WHERE m.session_id = s.id)";
+        conn.execute(
+            &format!("DELETE FROM session_tags WHERE session_id IN ({empty_sessions})"),
+            params![cutoff],
+        )
+        .map_err(|e| Error::Database(format!("failed to delete old session tags: {e}")))?;
+        let sessions = conn
+            .execute(
+                &format!("DELETE FROM sessions WHERE id IN ({empty_sessions})"),
+                params![cutoff],
+            )
+            .map_err(|e| Error::Database(format!("failed to delete old sessions: {e}")))?;
+        Ok(HistoryPurge { messages, sessions })
+    }
+
     /// Delete completed, failed, and cancelled tasks older than `older_than_days`.
     /// Returns the number of deleted rows.
     pub fn cleanup_completed_tasks(&self, older_than_days: i64) -> Result<usize> {
@@ -757,6 +1014,203 @@ impl SessionStore {
     }
 }
 
+impl SessionStore {
+    /// Queue `text` for delivery to `recipient` on the channel named
+    /// `channel` at `send_at`. Unlike reminders, the message needs no
+    /// existing session; `session_id` records the session that asked for it,
+    /// for [`Self::count_pending_messages_for_session`].
+    pub fn schedule_message(
+        &self,
+        channel: &str,
+        recipient: &str,
+        send_at: chrono::DateTime<chrono::Utc>,
+        text: &str,
+        session_id: Option<&str>,
+    ) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO scheduled_messages (id, channel, recipient, send_at, text, session_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                channel,
+                recipient,
+                send_at.to_rfc3339(),
+                text,
+                session_id
+            ],
+        )
+        .map_err(|e| Error::Database(format!("failed to schedule message: {e}")))?;
+        Ok(id)
+    }
+
+    /// Count pending scheduled messages queued by `session_id`.
+    pub fn count_pending_messages_for_session(&self, session_id: &str) -> Result<i64> {
+        let conn = self.conn()?;
+        conn.query_row(
+            "SELECT COUNT(*) FROM scheduled_messages WHERE session_id = ?1 AND status = 'pending'",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| Error::Database(format!("failed to count pending messages: {e}")))
+    }
+
+    /// Pending scheduled messages due for delivery as of `now`.
+    pub fn due_scheduled_messages_at(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<ScheduledMessage>> {
+        self.query_scheduled_messages(
+            "WHERE status = 'pending'
+               AND datetime(COALESCE(next_attempt_at, send_at)) <= datetime(?1)
+             ORDER BY send_at ASC
+             LIMIT 10",
+            params![now.to_rfc3339()],
+        )
+    }
+
+    /// All pending scheduled messages, soonest first.
+    pub fn list_scheduled_messages(&self) -> Result<Vec<ScheduledMessage>> {
+        self.query_scheduled_messages("WHERE status = 'pending' ORDER BY send_at ASC", params![])
+    }
+
+    fn query_scheduled_messages(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<ScheduledMessage>> {
+        let conn = self.conn()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, channel, recipient, send_at, text, attempts
+                 FROM scheduled_messages {filter}"
+            ))
+            .map_err(|e| {
+                Error::Database(format!("failed to prepare scheduled messages query: {e}"))
+            })?;
+        let rows = stmt
+            .query_map(params, |row| {
+                let send_at_raw: String = row.get(3)?;
+                Ok(ScheduledMessage {
+                    id: row.get(0)?,
+                    channel: row.get(1)?,
+                    recipient: row.get(2)?,
+                    send_at: parse_timestamp(&send_at_raw),
+                    text: row.get(4)?,
+                    attempts: row.get(5)?,
+                })
+            })
+            .map_err(|e| Error::Database(format!("failed to query scheduled messages: {e}")))?;
+        rows.collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Database(format!("failed to read scheduled message row: {e}")))
+    }
+
+    /// Mark a scheduled message as delivered.
+    pub fn complete_scheduled_message(&self, id: &str) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE scheduled_messages SET status = 'sent' WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| Error::Database(format!("failed to complete scheduled message: {e}")))?;
+        Ok(())
+    }
+
+    /// Record a failed delivery attempt. The message is retried with the same
+    /// backoff as tasks (30s, 60s, 120s) and marked failed after the third
+    /// retry. Returns whether another attempt is queued.
+    pub fn retry_or_fail_scheduled_message(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let attempts: i32 = conn
+            .query_row(
+                "SELECT attempts FROM scheduled_messages WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Database(format!("failed to read scheduled message: {e}")))?;
+
+        if attempts >= MAX_SCHEDULED_MESSAGE_RETRIES {
+            conn.execute(
+                "UPDATE scheduled_messages SET status = 'failed', attempts = ?1 WHERE id = ?2",
+                params![attempts + 1, id],
+            )
+            .map_err(|e| Error::Database(format!("failed to fail scheduled message: {e}")))?;
+            return Ok(false);
+        }
+
+        let next_attempt = chrono::Utc::now() + chrono::Duration::seconds(30i64 << attempts);
+        conn.execute(
+            "UPDATE scheduled_messages SET attempts = ?1, next_attempt_at = ?2 WHERE id = ?3",
+            params![attempts + 1, next_attempt.to_rfc3339(), id],
+        )
+        .map_err(|e| Error::Database(format!("failed to set scheduled message retry: {e}")))?;
+        Ok(true)
+    }
+
+    /// Cancel a pending scheduled message. Returns false when no pending
+    /// message has that ID.
+    pub fn cancel_scheduled_message(&self, id: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let rows = conn
+            .execute(
+                "UPDATE scheduled_messages SET status = 'cancelled' WHERE id = ?1 AND status = 'pending'",
+                params![id],
+            )
+            .map_err(|e| Error::Database(format!("failed to cancel scheduled message: {e}")))?;
+        Ok(rows > 0)
+    }
+
+    /// Record the inbound message `key` and return `true` unless it was
+    /// already recorded within `ttl`. Keys older than `ttl` are forgotten.
+    pub fn first_inbound_message(&self, key: &str, ttl: chrono::Duration) -> Result<bool> {
+        self.first_inbound_message_at(key, chrono::Utc::now(), ttl)
+    }
+
+    pub fn first_inbound_message_at(
+        &self,
+        key: &str,
+        now: chrono::DateTime<chrono::Utc>,
+        ttl: chrono::Duration,
+    ) -> Result<bool> {
+        let conn = self.conn()?;
+        conn.execute(
+            "DELETE FROM inbound_messages WHERE seen_at <= ?1",
+            params![(now - ttl).to_rfc3339()],
+        )
+        .map_err(|e| Error::Database(format!("failed to expire inbound messages: {e}")))?;
+        let rows = conn
+            .execute(
+                "INSERT OR IGNORE INTO inbound_messages (key, seen_at) VALUES (?1, ?2)",
+                params![key, now.to_rfc3339()],
+            )
+            .map_err(|e| Error::Database(format!("failed to record inbound message: {e}")))?;
+        Ok(rows > 0)
+    }
+}
+
+/// Retries of a failed scheduled message delivery before it is given up.
+const MAX_SCHEDULED_MESSAGE_RETRIES: i32 = 3;
+
+/// A message queued for delivery to a channel recipient at a set time.
+#[derive(Debug, Clone)]
+pub struct ScheduledMessage {
+    pub id: String,
+    /// Channel name from config (e.g. `telegram`).
+    pub channel: String,
+    /// Platform-native chat or user ID.
+    pub recipient: String,
+    pub send_at: chrono::DateTime<chrono::Utc>,
+    pub text: String,
+    /// Failed delivery attempts so far.
+    pub attempts: i32,
+}
+
+/// Task kind for agent wake-ups: the payload is a prompt for the agent.
+pub const TASK_KIND_HEARTBEAT: &str = "heartbeat";
+/// Task kind for reminders: the payload is sent to the user as-is.
+pub const TASK_KIND_REMINDER: &str = "reminder";
+
 /// Represents a scheduled background task.
 #[derive(Debug, Clone)]
 pub struct ScheduledTask {
@@ -775,6 +1229,14 @@ pub struct ScheduledTask {
     pub recurrence_end_at: Option<chrono::DateTime<chrono::Utc>>,
     pub deliver_to_channel: Option<String>,
     pub timezone: Option<String>,
+    /// [`TASK_KIND_HEARTBEAT`] or [`TASK_KIND_REMINDER`].
+    pub kind: String,
+}
+
+impl ScheduledTask {
+    pub fn is_reminder(&self) -> bool {
+        self.kind == TASK_KIND_REMINDER
+    }
 }
 
 fn parse_timestamp(value: &str) -> chrono::DateTime<chrono::Utc> {
@@ -791,7 +1253,7 @@ fn parse_timestamp(value: &str) -> chrono::DateTime<chrono::Utc> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ScheduledTask, SessionStore, UsageAttribution};
+    use super::{MAX_TAG_LEN, ScheduledTask, SessionStore, UsageAttribution, normalize_tag};
     use chrono::Duration;
 
     #[test]
@@ -970,6 +1432,37 @@ mod tests {
         assert_eq!(remaining[2].content, "msg-9");
     }
 
+    #[test]
+    fn delete_last_turn_removes_latest_exchange() {
+        let store = SessionStore::in_memory().expect("in-memory store should open");
+        store
+            .upsert_session("s1", "web", "u1", &serde_json::json!({}))
+            .unwrap();
+        for (direction, content) in [
+            ("user", "q1"),
+            ("assistant", "a1"),
+            ("user", "q2"),
+            ("assistant", "a2"),
+        ] {
+            store
+                .append_message(
+                    "s1",
+                    direction,
+                    content,
+                    chrono::Utc::now(),
+                    &serde_json::json!({}),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(store.delete_last_turn("s1").unwrap(), 2);
+        let remaining = store.load_recent_messages("s1", 100).unwrap();
+        let contents: Vec<_> = remaining.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["q1", "a1"]);
+
+        assert_eq!(store.delete_last_turn("missing").unwrap(), 0);
+    }
+
     #[test]
     fn count_pending_tasks_for_session() {
         let store = SessionStore::in_memory().expect("in-memory store should open");
@@ -1126,6 +1619,7 @@ mod tests {
             recurrence_end_at: None,
             deliver_to_channel: None,
             timezone: None,
+            kind: super::TASK_KIND_HEARTBEAT.to_string(),
         };
 
         let new_id = store
@@ -1160,6 +1654,7 @@ mod tests {
             recurrence_end_at: None,
             deliver_to_channel: None,
             timezone: None,
+            kind: super::TASK_KIND_HEARTBEAT.to_string(),
         };
 
         assert!(store.reschedule_recurring_task(&task).unwrap().is_none());
@@ -1189,6 +1684,7 @@ mod tests {
             recurrence_end_at: Some(chrono::Utc::now() - Duration::minutes(1)),
             deliver_to_channel: None,
             timezone: None,
+            kind: super::TASK_KIND_HEARTBEAT.to_string(),
         };
 
         assert!(store.reschedule_recurring_task(&task).unwrap().is_none());
@@ -1214,6 +1710,142 @@ mod tests {
         assert_eq!(store.poll_due_tasks().unwrap().len(), 0);
     }
 
+    #[test]
+    fn inbound_messages_are_seen_once_within_ttl() {
+        let store = SessionStore::in_memory().unwrap();
+        let ttl = Duration::hours(24);
+        let now = chrono::Utc::now();
+
+        assert!(
+            store
+                .first_inbound_message_at("telegram:1:7", now, ttl)
+                .unwrap()
+        );
+        assert!(
+            !store
+                .first_inbound_message_at("telegram:1:7", now, ttl)
+                .unwrap()
+        );
+        assert!(
+            store
+                .first_inbound_message_at("slack:1:7", now, ttl)
+                .unwrap()
+        );
+        assert!(
+            !store
+                .first_inbound_message_at("telegram:1:7", now + Duration::hours(23), ttl)
+                .unwrap()
+        );
+        assert!(
+            store
+                .first_inbound_message_at("telegram:1:7", now + Duration::hours(25), ttl)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn scheduled_messages_are_due_at_send_time_and_retry_then_fail() {
+        let store = SessionStore::in_memory().unwrap();
+        let send_at = chrono::Utc::now() + Duration::hours(3);
+        let id = store
+            .schedule_message("telegram", "42", send_at, "standup in 5", None)
+            .unwrap();
+        let other = store
+            .schedule_message(
+                "slack",
+                "C1",
+                send_at + Duration::hours(1),
+                "later",
+                Some("sess-1"),
+            )
+            .unwrap();
+        assert_eq!(
+            store.count_pending_messages_for_session("sess-1").unwrap(),
+            1
+        );
+
+        assert!(
+            store
+                .due_scheduled_messages_at(send_at - Duration::minutes(1))
+                .unwrap()
+                .is_empty()
+        );
+        let due = store.due_scheduled_messages_at(send_at).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, id);
+        assert_eq!(due[0].channel, "telegram");
+        assert_eq!(due[0].recipient, "42");
+        assert_eq!(due[0].text, "standup in 5");
+
+        for _ in 0..3 {
+            assert!(store.retry_or_fail_scheduled_message(&id).unwrap());
+        }
+        assert!(!store.retry_or_fail_scheduled_message(&id).unwrap());
+        let pending: Vec<String> = store
+            .list_scheduled_messages()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(pending, vec![other.clone()]);
+
+        assert!(store.cancel_scheduled_message(&other).unwrap());
+        assert!(!store.cancel_scheduled_message(&other).unwrap());
+        assert_eq!(
+            store.count_pending_messages_for_session("sess-1").unwrap(),
+            0
+        );
+        assert!(store.list_scheduled_messages().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reminder_is_polled_at_its_due_time_with_session_routing() {
+        let store = SessionStore::in_memory().unwrap();
+        store
+            .upsert_session(
+                "telegram-42",
+                "telegram",
+                "u1",
+                &serde_json::json!({ "chat_id": 42 }),
+            )
+            .unwrap();
+        let due = chrono::Utc::now() + Duration::hours(12);
+        let id = store
+            .schedule_reminder("telegram-42", "u1", due, "call mom")
+            .unwrap();
+
+        assert!(
+            store
+                .poll_due_tasks_at(due - Duration::minutes(1))
+                .unwrap()
+                .is_empty()
+        );
+
+        let tasks = store.poll_due_tasks_at(due).unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task = &tasks[0];
+        assert_eq!(task.id, id);
+        assert!(task.is_reminder());
+        assert_eq!(task.payload, "call mom");
+        assert_eq!(task.channel_id, "telegram");
+        assert_eq!(task.session_metadata["chat_id"], 42);
+    }
+
+    #[test]
+    fn plain_tasks_default_to_heartbeat_kind() {
+        let store = SessionStore::in_memory().unwrap();
+        store
+            .upsert_session("s1", "web", "u1", &serde_json::json!({}))
+            .unwrap();
+        store
+            .schedule_task("s1", "u1", chrono::Utc::now(), "wake up")
+            .unwrap();
+
+        let tasks = store.list_pending_tasks("s1").unwrap();
+        assert_eq!(tasks[0].kind, super::TASK_KIND_HEARTBEAT);
+        assert!(!tasks[0].is_reminder());
+    }
+
     #[test]
     fn cleanup_completed_tasks_deletes_old_rows() {
         let store = SessionStore::in_memory().expect("in-memory store should open");
@@ -1447,4 +2079,128 @@ mod tests {
             .expect("query nobody");
         assert_eq!(result.total_tokens, 0);
     }
+
+    #[test]
+    fn normalize_tag_accepts_simple_names_only() {
+        assert_eq!(normalize_tag(" #Work ").as_deref(), Some("work"));
+        assert_eq!(normalize_tag("meal-prep_2").as_deref(), Some("meal-prep_2"));
+        assert_eq!(normalize_tag(""), None);
+        assert_eq!(normalize_tag("two words"), None);
+        assert_eq!(normalize_tag("a,b"), None);
+        assert_eq!(normalize_tag(&"x".repeat(MAX_TAG_LEN + 1)), None);
+    }
+
+    #[test]
+    fn channel_users_lists_each_user_once() {
+        let store = SessionStore::in_memory().expect("in-memory store should open");
+        for (id, channel, user) in [
+            ("telegram-1", "telegram", "1"),
+            ("telegram-1-topic", "telegram", "1"),
+            ("telegram-2", "telegram", "2"),
+            ("discord-3", "discord", "3"),
+        ] {
+            store
+                .upsert_session(id, channel, user, &serde_json::json!({}))
+                .unwrap();
+        }
+        assert_eq!(store.channel_users("telegram").unwrap(), vec!["1", "2"]);
+        assert_eq!(store.channel_users("discord").unwrap(), vec!["3"]);
+        assert!(store.channel_users("slack").unwrap().is_empty());
+    }
+
+    #[test]
+    fn tagged_sessions_are_filtered_by_tag() {
+        let store = SessionStore::in_memory().expect("in-memory store should open");
+        for id in ["s-work", "s-recipes", "s-both"] {
+            store
+                .upsert_session(id, "web", "u1", &serde_json::json!({}))
+                .unwrap();
+        }
+        assert!(store.tag_session("s-work", "work").unwrap());
+        assert!(!store.tag_session("s-work", "work").unwrap());
+        store.tag_session("s-recipes", "recipes").unwrap();
+        store.tag_session("s-both", "work").unwrap();
+        store.tag_session("s-both", "recipes").unwrap();
+
+        // Tags survive a metadata rewrite of the session row.
+        store
+            .upsert_session("s-work", "web", "u1", &serde_json::json!({ "k": 1 }))
+            .unwrap();
+
+        let mut work: Vec<String> = store
+            .list_sessions(Some("work"))
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        work.sort();
+        assert_eq!(work, vec!["s-both", "s-work"]);
+        assert_eq!(store.sessions_with_tag("work").unwrap(), work);
+
+        assert_eq!(store.list_sessions(None).unwrap().len(), 3);
+        assert!(store.list_sessions(Some("travel")).unwrap().is_empty());
+        assert_eq!(
+            store.session_tags("s-both").unwrap(),
+            vec!["recipes", "work"]
+        );
+        let both = store
+            .list_sessions(Some("recipes"))
+            .unwrap()
+            .into_iter()
+            .find(|s| s.id == "s-both")
+            .unwrap();
+        assert_eq!(both.tags, vec!["recipes", "work"]);
+    }
+
+    #[test]
+    fn delete_history_before_drops_only_old_turns_and_empty_sessions() {
+        let store = SessionStore::in_memory().expect("in-memory store should open");
+        let now = chrono::Utc::now();
+        let old = now - Duration::days(40);
+        let meta = serde_json::json!({});
+        for id in ["old-session", "mixed-session"] {
+            store.upsert_session(id, "web", "u1", &meta).unwrap();
+        }
+        store.tag_session("old-session", "work").unwrap();
+        store
+            .append_message("old-session", "user", "ancient", old, &meta)
+            .unwrap();
+        store
+            .append_message("mixed-session", "user", "old question", old, &meta)
+            .unwrap();
+        store
+            .append_message("mixed-session", "assistant", "fresh answer", now, &meta)
+            .unwrap();
+        store
+            .connection()
+            .unwrap()
+            .execute(
+                "UPDATE sessions SET updated_at = datetime('now', '-40 days') WHERE id = 'old-session'",
+                [],
+            )
+            .unwrap();
+
+        let purge = store
+            .delete_history_before(now - Duration::days(30))
+            .unwrap();
+        assert_eq!(
+            purge,
+            super::HistoryPurge {
+                messages: 2,
+                sessions: 1
+            }
+        );
+
+        let remaining = store.load_recent_messages("mixed-session", 10).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].content, "fresh answer");
+        let ids: Vec<String> = store
+            .list_sessions(None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec!["mixed-session"]);
+        assert!(store.sessions_with_tag("work").unwrap().is_empty());
+    }
 }
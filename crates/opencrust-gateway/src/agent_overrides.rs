@@ -13,7 +13,7 @@ pub fn load_skills_flat_block(skills_dir: &str) -> Option<String> {
     }
     let block = skills
         .iter()
-        .map(|s| format!("### {}\n{}\n", s.frontmatter.name, s.body))
+        .map(|s| format!("### {}\n{}\n", s.frontmatter.name, s.prompt_body()))
         .collect::<Vec<_>>()
         .join("\n");
     Some(format!("## Agent Skills\n\n{block}"))
@@ -1,13 +1,13 @@
 use axum::Json;
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, Uri};
 use axum::response::IntoResponse;
-use opencrust_agents::{ContentBlock, MessagePart};
+use opencrust_agents::{ChatMessage, ChatRole, ContentBlock, MessagePart, TurnTrace};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use crate::agent_router;
-use crate::state::SharedState;
+use crate::state::{BroadcastError, SharedState};
 
 #[derive(Deserialize)]
 pub struct CreateSessionRequest {
@@ -30,10 +30,37 @@ pub struct SendMessageRequest {
     pub model: Option<String>,
 }
 
+#[derive(Deserialize, Default)]
+pub struct SendMessageQuery {
+    /// `debug=1` returns the turn's tool-call trace. Requires the gateway API key.
+    pub debug: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct SendMessageResponse {
     pub session_id: String,
     pub content: String,
+    /// Tool calls and iteration count for this turn, present only for `debug=1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TurnTrace>,
+}
+
+/// Maximum number of messages accepted by a single history import.
+pub const MAX_IMPORTED_MESSAGES: usize = 200;
+
+#[derive(Deserialize)]
+pub struct ImportedMessage {
+    /// `"user"` or `"assistant"`.
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportHistoryRequest {
+    pub messages: Vec<ImportedMessage>,
+    /// Also write the messages to the session store.
+    #[serde(default)]
+    pub persist: bool,
 }
 
 #[derive(Serialize)]
@@ -68,11 +95,37 @@ pub async fn create_session(
 }
 
 /// POST /api/sessions/:id/messages — send a message to a session.
+///
+/// With `?debug=1` the response also carries a `trace` of the tool calls made
+/// during the turn. Debug traces expose tool inputs and outputs, so they are
+/// only returned to callers presenting the gateway API key.
 pub async fn send_message(
     State(state): State<SharedState>,
     Path(session_id): Path<String>,
+    Query(query): Query<SendMessageQuery>,
+    headers: HeaderMap,
+    uri: Uri,
     Json(body): Json<SendMessageRequest>,
 ) -> impl IntoResponse {
+    let debug = matches!(query.debug.as_deref(), Some("1" | "true"));
+    if debug {
+        let authorized = state
+            .config
+            .gateway
+            .api_key
+            .as_deref()
+            .is_some_and(|key| crate::router::request_has_api_key(&headers, &uri, key));
+        if !authorized {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": "debug traces require the gateway API key"
+                })),
+            )
+                .into_response();
+        }
+    }
+
     // Look up session and extract any stored agent_id (set at session creation).
     let session_agent_id = match state.sessions.get(&session_id) {
         Some(s) => s
@@ -154,6 +207,10 @@ pub async fn send_message(
     let config = state.current_config();
     let agent_config = agent_router::resolve(&config, effective_agent_id.as_deref(), None);
 
+    if debug {
+        state.agents.begin_turn_trace(&session_id);
+    }
+
     let result = if let Some(ac) = agent_config {
         // Apply per-agent tool whitelist (#300)
         if !ac.tools.is_empty() {
@@ -218,6 +275,7 @@ pub async fn send_message(
             )
             .await
     };
+    let trace = state.agents.take_turn_trace(&session_id);
 
     match result {
         Ok(response_text) => {
@@ -247,6 +305,7 @@ pub async fn send_message(
                 Json(serde_json::json!(SendMessageResponse {
                     session_id,
                     content: response_text,
+                    trace,
                 })),
             )
                 .into_response()
@@ -262,6 +321,78 @@ pub async fn send_message(
     }
 }
 
+/// POST /api/sessions/:id/history — seed a session with an external conversation.
+/// Replaces the session's in-memory history so the next message continues from it.
+pub async fn import_session_history(
+    State(state): State<SharedState>,
+    Path(session_id): Path<String>,
+    Json(body): Json<ImportHistoryRequest>,
+) -> impl IntoResponse {
+    if body.messages.len() > MAX_IMPORTED_MESSAGES {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("history rejected: more than {MAX_IMPORTED_MESSAGES} messages")
+            })),
+        )
+            .into_response();
+    }
+
+    let guardrails = state.current_config().guardrails.clone();
+    let mut history = Vec::with_capacity(body.messages.len());
+    for (index, message) in body.messages.into_iter().enumerate() {
+        let (role, max_chars) = match message.role.as_str() {
+            "user" => (ChatRole::User, guardrails.max_input_chars),
+            "assistant" => (ChatRole::Assistant, guardrails.max_output_chars),
+            other => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": format!("message {index}: unsupported role '{other}' (expected user or assistant)")
+                    })),
+                )
+                    .into_response();
+            }
+        };
+        let content = opencrust_security::InputValidator::sanitize(&message.content);
+        if opencrust_security::InputValidator::exceeds_length(&content, max_chars) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("message {index}: exceeds {max_chars} character limit")
+                })),
+            )
+                .into_response();
+        }
+        if matches!(role, ChatRole::User)
+            && opencrust_security::InputValidator::check_prompt_injection(&content)
+        {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("message {index}: potential prompt injection detected")
+                })),
+            )
+                .into_response();
+        }
+        history.push(ChatMessage {
+            role,
+            content: MessagePart::Text(content),
+        });
+    }
+
+    let imported = history.len();
+    state
+        .import_session_history(&session_id, Some("api"), history, body.persist)
+        .await;
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "session_id": session_id, "imported": imported })),
+    )
+        .into_response()
+}
+
 /// GET /api/sessions/:id/history — get session history.
 /// Loads from persistent storage if the session is not in memory (e.g. after server restart).
 pub async fn session_history(
@@ -315,3 +446,320 @@ pub async fn list_sessions(State(state): State<SharedState>) -> impl IntoRespons
 
     Json(serde_json::json!({ "sessions": sessions }))
 }
+
+#[derive(Deserialize)]
+pub struct EmbeddingsRequest {
+    pub input: Vec<String>,
+}
+
+/// POST /api/embeddings — embed texts with the configured embedding provider.
+pub async fn embeddings(
+    State(state): State<SharedState>,
+    Json(body): Json<EmbeddingsRequest>,
+) -> impl IntoResponse {
+    let Some(provider) = state.agents.embedding_provider() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "no embedding provider configured" })),
+        )
+            .into_response();
+    };
+
+    if body.input.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "input must contain at least one text" })),
+        )
+            .into_response();
+    }
+
+    match provider.embed_documents(&body.input).await {
+        Ok(vectors) => {
+            let data: Vec<serde_json::Value> = vectors
+                .into_iter()
+                .enumerate()
+                .map(|(index, embedding)| {
+                    serde_json::json!({ "index": index, "embedding": embedding })
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "model": provider.model(),
+                    "data": data,
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            warn!("embeddings request failed: {e}");
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BroadcastRequest {
+    pub text: String,
+}
+
+/// POST /api/channels/:channel/broadcast — send an announcement to every
+/// allowed user who has talked to the bot on a channel.
+pub async fn broadcast(
+    State(state): State<SharedState>,
+    Path(channel): Path<String>,
+    Json(body): Json<BroadcastRequest>,
+) -> impl IntoResponse {
+    match state.broadcast(&channel, &body.text).await {
+        Ok(report) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "sent": report.sent,
+                "failed": report.failed,
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            let status = match e {
+                BroadcastError::UnknownChannel(_) => StatusCode::NOT_FOUND,
+                BroadcastError::EmptyText => StatusCode::BAD_REQUEST,
+            };
+            (status, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+        }
+    }
+}
+
+/// POST /api/broadcast — send an announcement to the owner on every
+/// connected channel.
+pub async fn broadcast_to_owners(
+    State(state): State<SharedState>,
+    Json(body): Json<BroadcastRequest>,
+) -> impl IntoResponse {
+    let text = body.text.trim();
+    if text.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "text must not be empty" })),
+        )
+            .into_response();
+    }
+
+    let report = state.broadcast_to_owners(text).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "sent": report.sent,
+            "failed": report.failed,
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize, Default)]
+pub struct MaintenanceRequest {
+    /// Reply sent instead of an agent turn; defaults to a generic notice.
+    pub message: Option<String>,
+    /// Hold incoming messages and answer them when maintenance ends.
+    #[serde(default)]
+    pub queue: bool,
+}
+
+fn unknown_channel(channel: &str) -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": format!("unknown channel: {channel}") })),
+    )
+        .into_response()
+}
+
+/// GET /api/channels/:channel/maintenance — whether a channel is in
+/// maintenance mode, and how many messages it holds.
+pub async fn channel_maintenance(
+    State(state): State<SharedState>,
+    Path(channel): Path<String>,
+) -> impl IntoResponse {
+    if !state.config.channels.contains_key(&channel) {
+        return unknown_channel(&channel);
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "channel": channel,
+            "maintenance": state.maintenance_status(&channel),
+        })),
+    )
+        .into_response()
+}
+
+/// PUT /api/channels/:channel/maintenance — put a channel into maintenance
+/// mode, or update its message and queueing.
+pub async fn start_channel_maintenance(
+    State(state): State<SharedState>,
+    Path(channel): Path<String>,
+    body: Option<Json<MaintenanceRequest>>,
+) -> impl IntoResponse {
+    if !state.config.channels.contains_key(&channel) {
+        return unknown_channel(&channel);
+    }
+    let Json(body) = body.unwrap_or_default();
+    let status = state.start_maintenance(&channel, body.message, body.queue);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "channel": channel,
+            "maintenance": status,
+        })),
+    )
+        .into_response()
+}
+
+/// DELETE /api/channels/:channel/maintenance — bring a channel back and
+/// answer the messages it held.
+pub async fn end_channel_maintenance(
+    State(state): State<SharedState>,
+    Path(channel): Path<String>,
+) -> impl IntoResponse {
+    match state.end_maintenance(&channel) {
+        Some(replayed) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "channel": channel, "replayed": replayed })),
+        )
+            .into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("{channel} is not in maintenance mode") })),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleMessageRequest {
+    /// Channel name from config (e.g. `telegram`).
+    pub channel: String,
+    /// Platform-native chat or user ID.
+    pub to: String,
+    /// RFC 3339 time to deliver at, e.g. `2026-03-01T17:00:00+01:00`.
+    pub send_at: String,
+    pub text: String,
+}
+
+fn scheduled_message_json(message: &opencrust_db::ScheduledMessage) -> serde_json::Value {
+    serde_json::json!({
+        "id": message.id,
+        "channel": message.channel,
+        "to": message.recipient,
+        "send_at": message.send_at.to_rfc3339(),
+        "text": message.text,
+        "attempts": message.attempts,
+    })
+}
+
+fn scheduling_unavailable() -> axum::response::Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({ "error": "scheduling requires the session store" })),
+    )
+        .into_response()
+}
+
+/// POST /api/scheduled-messages — queue a message for delivery to a channel
+/// recipient at a given time.
+pub async fn schedule_message(
+    State(state): State<SharedState>,
+    Json(body): Json<ScheduleMessageRequest>,
+) -> impl IntoResponse {
+    let Some(store) = &state.session_store else {
+        return scheduling_unavailable();
+    };
+    let bad_request = |error: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": error })),
+        )
+            .into_response()
+    };
+
+    let text = body.text.trim();
+    if text.is_empty() {
+        return bad_request("text must not be empty".to_string());
+    }
+    if body.to.trim().is_empty() {
+        return bad_request("to must not be empty".to_string());
+    }
+    if !state.config.channels.contains_key(&body.channel) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("unknown channel: {}", body.channel) })),
+        )
+            .into_response();
+    }
+    let send_at = match chrono::DateTime::parse_from_rfc3339(&body.send_at) {
+        Ok(t) => t.with_timezone(&chrono::Utc),
+        Err(e) => return bad_request(format!("send_at must be an RFC 3339 time: {e}")),
+    };
+    if send_at <= state.clock().utc_now() {
+        return bad_request("send_at must be in the future".to_string());
+    }
+
+    match store.schedule_message(&body.channel, body.to.trim(), send_at, text, None) {
+        Ok(id) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({ "id": id, "send_at": send_at.to_rfc3339() })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /api/scheduled-messages — pending scheduled messages, soonest first.
+pub async fn list_scheduled_messages(State(state): State<SharedState>) -> impl IntoResponse {
+    let Some(store) = &state.session_store else {
+        return scheduling_unavailable();
+    };
+    match store.list_scheduled_messages() {
+        Ok(messages) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "messages": messages.iter().map(scheduled_message_json).collect::<Vec<_>>(),
+            })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+/// DELETE /api/scheduled-messages/:id — cancel a pending scheduled message.
+pub async fn cancel_scheduled_message(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(store) = &state.session_store else {
+        return scheduling_unavailable();
+    };
+    match store.cancel_scheduled_message(&id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "no pending scheduled message with that id" })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
@@ -4,26 +4,47 @@ use std::sync::{Arc, Mutex};
 
 use opencrust_agents::tools::Tool;
 use opencrust_agents::{
-    AgentRuntime, AnthropicProvider, BashTool, ChatMessage, CohereEmbeddingProvider,
-    CreateSkillTool, DocSearchTool, FilePatchTool, FileReadTool, FileWriteTool, GoogleSearchTool,
-    ListDocumentsTool, McpManager, MemoryTool, OllamaEmbeddingProvider, OllamaProvider,
-    OpenAiProvider, SearchFilesTool, SendMessageHandle, SendMessageTool, WebFetchTool,
-    WebSearchTool,
+    AgentRuntime, AnthropicProvider, AskUserTool, BashTool, ChatMessage, CohereEmbeddingProvider,
+    CreateSkillTool, DocSearchTool, EmbeddingProvider, FilePatchTool, FileReadTool, FileWriteTool,
+    GeminiProvider, GoogleSearchTool, ImageGenTool, ImageProvider, ListDocumentsTool, LlamaCppMode,
+    LlamaCppProvider, McpManager, MemoryTool, MessagePart, OllamaEmbeddingProvider, OllamaProvider,
+    OpenAiEmbeddingProvider, OpenAiImageProvider, OpenAiProvider, SdWebUiImageProvider,
+    SearchFilesTool, SendMessageHandle, SendMessageTool, SessionModel, StabilityImageProvider,
+    WebFetchTool, WebSearchTool,
 };
 use opencrust_channels::{
-    ChannelResponse, MediaAttachment, MqttChannel, MqttOnMessageFn, SlackChannel, SlackGroupFilter,
-    SlackOnMessageFn, TelegramChannel, WhatsAppChannel, WhatsAppOnMessageFn, WhatsAppWebChannel,
-    WhatsAppWebGroupFilter,
+    ChannelResponse, MediaAttachment, MediaKind, MqttChannel, MqttOnMessageFn, RemoteChannel,
+    RemoteOnMessageFn, SignalChannel, SignalGroupFilter, SignalOnMessageFn, SlackChannel,
+    SlackGroupFilter, SlackOnMessageFn, TeamsChannel, TeamsGroupFilter, TeamsOnMessageFn,
+    TelegramChannel, WebChatChannel, WebChatOnMessageFn, WhatsAppChannel, WhatsAppOnMessageFn,
+    WhatsAppWebChannel, WhatsAppWebGroupFilter,
 };
 #[cfg(target_os = "macos")]
 use opencrust_channels::{IMessageChannel, IMessageGroupFilter, IMessageOnMessageFn};
-use opencrust_config::AppConfig;
+use opencrust_config::{
+    AppConfig, EmbeddingProviderConfig, ImageGenConfig, McpServerConfig, ModelAliasConfig,
+};
 use opencrust_db::{MemoryStore, TrajectoryStore, VectorStore};
-use opencrust_security::{Allowlist, ChannelPolicy, DmAuthResult, PairingManager, check_dm_auth};
+use opencrust_security::{ChannelMessages, ChannelPolicy, check_dm_auth};
 use tracing::{info, warn};
 
+use crate::pipeline::{InboundMessage, MessagePipeline};
+use crate::startup::StartupReport;
 use crate::state::SharedState;
 
+/// Default `HTTP-Referer` sent to OpenRouter when `site_url` is not configured.
+pub(crate) const OPENROUTER_DEFAULT_SITE_URL: &str = "https://github.com/opencrust-org/opencrust";
+/// Default `X-Title` sent to OpenRouter when `app_name` is not configured.
+pub(crate) const OPENROUTER_DEFAULT_APP_NAME: &str = "OpenCrust";
+/// OpenAI-compatible Groq endpoint, shared by chat and Whisper transcription.
+pub(crate) const GROQ_DEFAULT_BASE_URL: &str = "https://api.groq.com/openai/v1";
+/// Chat model used when a `groq` provider does not set `model`.
+pub(crate) const GROQ_DEFAULT_MODEL: &str = "llama-3.3-70b-versatile";
+/// xAI's OpenAI-compatible endpoint for `grok` providers.
+pub(crate) const GROK_DEFAULT_BASE_URL: &str = "https://api.x.ai/v1";
+/// Chat model used when a `grok` provider does not set `model`.
+pub(crate) const GROK_DEFAULT_MODEL: &str = "grok-3";
+
 /// Default vault path under the user's home directory.
 pub(crate) fn default_vault_path() -> Option<PathBuf> {
     Some(
@@ -57,8 +78,119 @@ pub(crate) fn resolve_api_key(
     std::env::var(env_var).ok()
 }
 
+/// Build the embedding provider described by an `embeddings:` entry. Returns
+/// `None` (with a warning) when the type is unknown or its API key is missing.
+pub(crate) fn build_embedding_provider(
+    name: &str,
+    config: &EmbeddingProviderConfig,
+) -> Option<Arc<dyn EmbeddingProvider>> {
+    let provider: Arc<dyn EmbeddingProvider> = match config.provider.as_str() {
+        "cohere" => {
+            let Some(key) = resolve_api_key(
+                config.api_key.as_deref(),
+                "COHERE_API_KEY",
+                "COHERE_API_KEY",
+            ) else {
+                warn!("skipping cohere embedding provider: no API key");
+                return None;
+            };
+            Arc::new(CohereEmbeddingProvider::new(
+                key,
+                config.model.clone(),
+                config.base_url.clone(),
+            ))
+        }
+        "openai" => {
+            let Some(key) = resolve_api_key(
+                config.api_key.as_deref(),
+                "OPENAI_API_KEY",
+                "OPENAI_API_KEY",
+            ) else {
+                warn!("skipping openai embedding provider: no API key");
+                return None;
+            };
+            Arc::new(OpenAiEmbeddingProvider::new(
+                key,
+                config.model.clone(),
+                config.base_url.clone(),
+                config.dimensions,
+            ))
+        }
+        "ollama" => Arc::new(OllamaEmbeddingProvider::new(
+            config.model.clone(),
+            config.base_url.clone(),
+        )),
+        other => {
+            warn!("unknown embedding provider type: {other}");
+            return None;
+        }
+    };
+    info!(
+        "configured {} embedding provider: {name}",
+        provider.provider_id()
+    );
+    Some(provider)
+}
+
+/// Build the image backend for the `generate_image` tool. Returns `None`
+/// (with a warning) when the type is unknown or its API key is missing.
+pub(crate) fn build_image_provider(config: &ImageGenConfig) -> Option<Arc<dyn ImageProvider>> {
+    match config.provider.as_str() {
+        "openai" => {
+            let Some(key) = resolve_api_key(
+                config.api_key.as_deref(),
+                "OPENAI_API_KEY",
+                "OPENAI_API_KEY",
+            ) else {
+                warn!("skipping openai image generation: no API key");
+                return None;
+            };
+            Some(Arc::new(OpenAiImageProvider::new(
+                key,
+                config.model.clone(),
+                config.base_url.clone(),
+                config.size.clone(),
+            )))
+        }
+        "stability" => {
+            let Some(key) = resolve_api_key(
+                config.api_key.as_deref(),
+                "STABILITY_API_KEY",
+                "STABILITY_API_KEY",
+            ) else {
+                warn!("skipping stability image generation: no API key");
+                return None;
+            };
+            Some(Arc::new(StabilityImageProvider::new(
+                key,
+                config.model.clone(),
+                config.base_url.clone(),
+                config.size.clone(),
+            )))
+        }
+        "sdwebui" => Some(Arc::new(SdWebUiImageProvider::new(
+            config.base_url.clone(),
+            config.size.clone(),
+            config.steps,
+        ))),
+        other => {
+            warn!("unknown image generation provider: {other}, skipping");
+            None
+        }
+    }
+}
+
 /// Build a fully-configured `AgentRuntime` from the application config.
 pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessageHandle) {
+    build_agent_runtime_with_report(config, &StartupReport::default()).await
+}
+
+/// [`build_agent_runtime`], recording providers that could not be configured
+/// in `report`.
+pub async fn build_agent_runtime_with_report(
+    config: &AppConfig,
+    report: &StartupReport,
+) -> (AgentRuntime, SendMessageHandle) {
     let mut runtime = AgentRuntime::new();
 
     // --- LLM Providers ---
@@ -72,17 +204,25 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                 );
 
                 if let Some(key) = api_key {
+                    let upload_images = llm_config
+                        .extra
+                        .get("upload_images")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
                     let provider = AnthropicProvider::new(
                         key,
                         llm_config.model.clone(),
                         llm_config.base_url.clone(),
                     )
-                    .with_name(name);
+                    .with_name(name)
+                    .with_image_uploads(upload_images);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured anthropic provider: {name}");
                 } else {
-                    warn!(
-                        "skipping anthropic provider {name}: no API key (set api_key in config or ANTHROPIC_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or ANTHROPIC_API_KEY env var)",
                     );
                 }
             }
@@ -99,15 +239,62 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         llm_config.model.clone(),
                         llm_config.base_url.clone(),
                     )
-                    .with_name(name);
+                    .with_name(name)
+                    .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured openai provider: {name}");
                 } else {
-                    warn!(
-                        "skipping openai provider {name}: no API key (set api_key in config or OPENAI_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or OPENAI_API_KEY env var)",
                     );
                 }
             }
+            "azure-openai" => {
+                let api_key = resolve_api_key(
+                    llm_config.api_key.as_deref(),
+                    "AZURE_OPENAI_API_KEY",
+                    "AZURE_OPENAI_API_KEY",
+                );
+                let extra = |key: &str| {
+                    llm_config
+                        .extra
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                };
+                // Deployments are often named after their model.
+                let deployment = extra("deployment").or_else(|| llm_config.model.clone());
+
+                match (api_key, &llm_config.base_url, deployment) {
+                    (Some(key), Some(base_url), Some(deployment)) => {
+                        let provider = OpenAiProvider::new(
+                            key,
+                            llm_config.model.clone(),
+                            Some(base_url.clone()),
+                        )
+                        .with_name(name)
+                        .with_azure_deployment(deployment, extra("api_version"))
+                        .with_supports_tools(llm_config.supports_tools);
+                        runtime.register_provider(Arc::new(provider));
+                        info!("configured azure-openai provider: {name}");
+                    }
+                    (None, _, _) => report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or AZURE_OPENAI_API_KEY env var)",
+                    ),
+                    (_, None, _) => report.fail(
+                        "provider",
+                        name,
+                        "no base_url (set it to https://<resource>.openai.azure.com)",
+                    ),
+                    (_, _, None) => {
+                        report.fail("provider", name, "no deployment (set deployment or model)")
+                    }
+                }
+            }
             "ollama" => {
                 let provider =
                     OllamaProvider::new(llm_config.model.clone(), llm_config.base_url.clone())
@@ -131,12 +318,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("sansa-auto".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured sansa provider: {name}");
                 } else {
-                    warn!(
-                        "skipping sansa provider {name}: no API key (set api_key in config or SANSA_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or SANSA_API_KEY env var)",
                     );
                 }
             }
@@ -156,12 +347,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("deepseek-chat".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured deepseek provider: {name}");
                 } else {
-                    warn!(
-                        "skipping deepseek provider {name}: no API key (set api_key in config or DEEPSEEK_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or DEEPSEEK_API_KEY env var)",
                     );
                 }
             }
@@ -181,36 +376,123 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("mistral-large-latest".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured mistral provider: {name}");
                 } else {
-                    warn!(
-                        "skipping mistral provider {name}: no API key (set api_key in config or MISTRAL_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or MISTRAL_API_KEY env var)",
                     );
                 }
             }
-            "gemini" => {
+            "groq" => {
                 let api_key = resolve_api_key(
                     llm_config.api_key.as_deref(),
-                    "GEMINI_API_KEY",
-                    "GEMINI_API_KEY",
+                    "GROQ_API_KEY",
+                    "GROQ_API_KEY",
                 );
 
                 if let Some(key) = api_key {
-                    let base_url = llm_config.base_url.clone().or_else(|| {
-                        Some("https://generativelanguage.googleapis.com/v1beta/openai/".to_string())
-                    });
+                    let base_url = llm_config
+                        .base_url
+                        .clone()
+                        .or_else(|| Some(GROQ_DEFAULT_BASE_URL.to_string()));
+                    let model = llm_config
+                        .model
+                        .clone()
+                        .or_else(|| Some(GROQ_DEFAULT_MODEL.to_string()));
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
+                    runtime.register_provider(Arc::new(provider));
+                    info!("configured groq provider: {name}");
+                } else {
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or GROQ_API_KEY env var)",
+                    );
+                }
+            }
+            "grok" => {
+                let api_key = resolve_api_key(
+                    llm_config.api_key.as_deref(),
+                    "GROK_API_KEY",
+                    "GROK_API_KEY",
+                );
+
+                if let Some(key) = api_key {
+                    let base_url = llm_config
+                        .base_url
+                        .clone()
+                        .or_else(|| Some(GROK_DEFAULT_BASE_URL.to_string()));
                     let model = llm_config
                         .model
                         .clone()
-                        .or_else(|| Some("gemini-2.5-flash".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                        .or_else(|| Some(GROK_DEFAULT_MODEL.to_string()));
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
+                    info!("configured grok provider: {name}");
+                } else {
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or GROK_API_KEY env var)",
+                    );
+                }
+            }
+            "gemini" => {
+                let api_key = resolve_api_key(
+                    llm_config.api_key.as_deref(),
+                    "GEMINI_API_KEY",
+                    "GEMINI_API_KEY",
+                );
+
+                if let Some(key) = api_key {
+                    if is_gemini_openai_url(llm_config.base_url.as_deref()) {
+                        let model = llm_config
+                            .model
+                            .clone()
+                            .or_else(|| Some("gemini-2.5-flash".to_string()));
+                        let provider = OpenAiProvider::new(key, model, llm_config.base_url.clone())
+                            .with_name(name)
+                            .with_supports_tools(llm_config.supports_tools);
+                        runtime.register_provider(Arc::new(provider));
+                    } else {
+                        let google_search = llm_config
+                            .extra
+                            .get("google_search")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false);
+                        let cache_ttl = llm_config
+                            .extra
+                            .get("cache_ttl_secs")
+                            .and_then(|v| v.as_u64())
+                            .filter(|secs| *secs > 0)
+                            .map(std::time::Duration::from_secs);
+                        let provider = GeminiProvider::new(
+                            key,
+                            llm_config.model.clone(),
+                            llm_config.base_url.clone(),
+                        )
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools)
+                        .with_google_search(google_search)
+                        .with_context_cache(cache_ttl);
+                        runtime.register_provider(Arc::new(provider));
+                    }
                     info!("configured gemini provider: {name}");
                 } else {
-                    warn!(
-                        "skipping gemini provider {name}: no API key (set api_key in config or GEMINI_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or GEMINI_API_KEY env var)",
                     );
                 }
             }
@@ -230,12 +512,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("tiiuae/falcon-180b-chat".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured falcon provider: {name}");
                 } else {
-                    warn!(
-                        "skipping falcon provider {name}: no API key (set api_key in config or FALCON_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or FALCON_API_KEY env var)",
                     );
                 }
             }
@@ -255,12 +541,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("jais-adapted-70b-chat".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured jais provider: {name}");
                 } else {
-                    warn!(
-                        "skipping jais provider {name}: no API key (set api_key in config or JAIS_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or JAIS_API_KEY env var)",
                     );
                 }
             }
@@ -279,12 +569,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("qwen-plus".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured qwen provider: {name}");
                 } else {
-                    warn!(
-                        "skipping qwen provider {name}: no API key (set api_key in config or QWEN_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or QWEN_API_KEY env var)",
                     );
                 }
             }
@@ -301,12 +595,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("yi-large".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured yi provider: {name}");
                 } else {
-                    warn!(
-                        "skipping yi provider {name}: no API key (set api_key in config or YI_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or YI_API_KEY env var)",
                     );
                 }
             }
@@ -326,12 +624,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("command-r-plus".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured cohere provider: {name}");
                 } else {
-                    warn!(
-                        "skipping cohere provider {name}: no API key (set api_key in config or COHERE_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or COHERE_API_KEY env var)",
                     );
                 }
             }
@@ -351,12 +653,16 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("MiniMax-Text-01".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured minimax provider: {name}");
                 } else {
-                    warn!(
-                        "skipping minimax provider {name}: no API key (set api_key in config or MINIMAX_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or MINIMAX_API_KEY env var)",
                     );
                 }
             }
@@ -376,12 +682,58 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                         .model
                         .clone()
                         .or_else(|| Some("kimi-k2-0711-preview".to_string()));
-                    let provider = OpenAiProvider::new(key, model, base_url).with_name(name);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools);
                     runtime.register_provider(Arc::new(provider));
                     info!("configured moonshot provider: {name}");
                 } else {
-                    warn!(
-                        "skipping moonshot provider {name}: no API key (set api_key in config or MOONSHOT_API_KEY env var)"
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or MOONSHOT_API_KEY env var)",
+                    );
+                }
+            }
+            "openrouter" => {
+                let api_key = resolve_api_key(
+                    llm_config.api_key.as_deref(),
+                    "OPENROUTER_API_KEY",
+                    "OPENROUTER_API_KEY",
+                );
+
+                if let Some(key) = api_key {
+                    let base_url = llm_config
+                        .base_url
+                        .clone()
+                        .or_else(|| Some("https://openrouter.ai/api/v1".to_string()));
+                    let model = llm_config
+                        .model
+                        .clone()
+                        .or_else(|| Some("openrouter/auto".to_string()));
+                    // OpenRouter requires attribution headers on every request.
+                    let site_url = llm_config
+                        .extra
+                        .get("site_url")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(OPENROUTER_DEFAULT_SITE_URL);
+                    let app_name = llm_config
+                        .extra
+                        .get("app_name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(OPENROUTER_DEFAULT_APP_NAME);
+                    let provider = OpenAiProvider::new(key, model, base_url)
+                        .with_name(name)
+                        .with_supports_tools(llm_config.supports_tools)
+                        .with_header("HTTP-Referer", site_url)
+                        .with_header("X-Title", app_name);
+                    runtime.register_provider(Arc::new(provider));
+                    info!("configured openrouter provider: {name}");
+                } else {
+                    report.fail(
+                        "provider",
+                        name,
+                        "no API key (set api_key in config or OPENROUTER_API_KEY env var)",
                     );
                 }
             }
@@ -399,12 +751,50 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
                     .clone()
                     .or_else(|| Some("http://localhost:8000".to_string()));
                 let model = llm_config.model.clone();
-                let provider = OpenAiProvider::new(api_key, model, base_url).with_name(name);
+                let provider = OpenAiProvider::new(api_key, model, base_url)
+                    .with_name(name)
+                    .with_supports_tools(llm_config.supports_tools);
                 runtime.register_provider(Arc::new(provider));
                 info!("configured vllm provider: {name}");
             }
+            "llamacpp" => {
+                // llama-server needs no API key unless started with --api-key.
+                let api_key = resolve_api_key(
+                    llm_config.api_key.as_deref(),
+                    "LLAMACPP_API_KEY",
+                    "LLAMACPP_API_KEY",
+                );
+                let mode = match llm_config.extra.get("mode").and_then(|v| v.as_str()) {
+                    None | Some("chat") => LlamaCppMode::Chat,
+                    Some("completion") => LlamaCppMode::Completion,
+                    Some(other) => {
+                        report.fail(
+                            "provider",
+                            name,
+                            format!("unknown llamacpp mode '{other}' (use chat or completion)"),
+                        );
+                        continue;
+                    }
+                };
+                let slot = llm_config
+                    .extra
+                    .get("slot")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|id| u32::try_from(id).ok());
+                let provider = LlamaCppProvider::new(
+                    llm_config.model.clone(),
+                    llm_config.base_url.clone(),
+                    api_key,
+                )
+                .with_name(name)
+                .with_supports_tools(llm_config.supports_tools)
+                .with_mode(mode)
+                .with_slot(slot);
+                runtime.register_provider(Arc::new(provider));
+                info!("configured llamacpp provider: {name}");
+            }
             other => {
-                warn!("unknown LLM provider type: {other}, skipping {name}");
+                report.fail("provider", name, format!("unknown provider type '{other}'"));
             }
         }
     }
@@ -415,7 +805,21 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
     runtime.register_tool(Box::new(FileWriteTool::new(None)));
     runtime.register_tool(Box::new(FilePatchTool::new(None)));
     runtime.register_tool(Box::new(SearchFilesTool::new()));
-    runtime.register_tool(Box::new(WebFetchTool::new(None)));
+    runtime.register_tool(Box::new(AskUserTool));
+    let web_fetch_config = &config.tools.web_fetch;
+    let mut web_fetch = WebFetchTool::new(None)
+        .with_respect_robots(web_fetch_config.respect_robots)
+        .with_cache(
+            std::time::Duration::from_secs(web_fetch_config.cache_ttl_secs),
+            web_fetch_config.cache_max_entries,
+        );
+    if let Some(dir) = &web_fetch_config.cache_dir {
+        web_fetch = web_fetch.with_cache_dir(dir);
+    }
+    if let Some(user_agent) = &web_fetch_config.user_agent {
+        web_fetch = web_fetch.with_user_agent(user_agent.clone());
+    }
+    runtime.register_tool(Box::new(web_fetch));
 
     // Self-learning: agent can save reusable skills discovered during conversations.
     // Enabled by default; set `agent.self_learning: false` in config.yml to disable.
@@ -475,6 +879,20 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
         }
     }
 
+    // Image generation (OpenAI, Stability or a local SD web UI)
+    if let Some(provider) = config
+        .tools
+        .image_gen
+        .as_ref()
+        .and_then(build_image_provider)
+    {
+        info!(
+            "image generation tool registered: {}",
+            provider.provider_id()
+        );
+        runtime.register_tool(Box::new(ImageGenTool::new(provider)));
+    }
+
     // --- Memory ---
     if config.memory.enabled {
         let data_dir = config
@@ -491,44 +909,15 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
             Ok(store) => {
                 let store = Arc::new(store);
                 runtime.set_memory_provider(store);
+                runtime.set_attachments_dir(data_dir.join("attachments"));
                 info!("memory store opened at {}", memory_db_path.display());
 
                 // Attach embedding provider if configured
                 if let Some(embed_name) = &config.memory.embedding_provider
                     && let Some(embed_config) = config.embeddings.get(embed_name)
+                    && let Some(provider) = build_embedding_provider(embed_name, embed_config)
                 {
-                    match embed_config.provider.as_str() {
-                        "cohere" => {
-                            let api_key = resolve_api_key(
-                                embed_config.api_key.as_deref(),
-                                "COHERE_API_KEY",
-                                "COHERE_API_KEY",
-                            );
-
-                            if let Some(key) = api_key {
-                                let provider = CohereEmbeddingProvider::new(
-                                    key,
-                                    embed_config.model.clone(),
-                                    embed_config.base_url.clone(),
-                                );
-                                runtime.set_embedding_provider(Arc::new(provider));
-                                info!("configured cohere embedding provider: {embed_name}");
-                            } else {
-                                warn!("skipping cohere embedding provider: no API key");
-                            }
-                        }
-                        "ollama" => {
-                            let provider = OllamaEmbeddingProvider::new(
-                                embed_config.model.clone(),
-                                embed_config.base_url.clone(),
-                            );
-                            runtime.set_embedding_provider(Arc::new(provider));
-                            info!("configured ollama embedding provider: {embed_name}");
-                        }
-                        other => {
-                            warn!("unknown embedding provider type: {other}");
-                        }
-                    }
+                    runtime.set_embedding_provider(provider);
                 }
             }
             Err(e) => {
@@ -576,7 +965,10 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
             info!("list_documents tool registered");
 
             // Explicit persistent memory tool — agent-initiated save/recall across sessions.
-            runtime.register_tool(Box::new(MemoryTool::new(memory_db_path.clone())));
+            runtime.register_tool(Box::new(
+                MemoryTool::new(memory_db_path.clone())
+                    .with_sessions_db(data_dir.join("sessions.db")),
+            ));
             info!("memory tool registered");
         }
     }
@@ -593,6 +985,43 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
     if let Some(max_tokens) = config.agent.max_tokens {
         runtime.set_max_tokens(max_tokens);
     }
+    if let Some(seed) = config.agent.seed {
+        runtime.set_seed(seed);
+    }
+    for (name, llm_config) in &config.llm {
+        if let Some(max_tokens) = llm_config.max_tokens {
+            runtime.set_provider_max_tokens(name.clone(), max_tokens);
+        }
+    }
+    let provider_ids = runtime.provider_ids();
+    for (alias, target) in &config.models {
+        let target = match target {
+            // A bare model runs on the provider configured with it, if any.
+            ModelAliasConfig::Model(model) => SessionModel {
+                provider: config
+                    .llm
+                    .iter()
+                    .find(|(_, c)| c.model.as_deref() == Some(model.as_str()))
+                    .map(|(name, _)| name.clone()),
+                model: Some(model.clone()),
+            },
+            ModelAliasConfig::Target { provider, model } => SessionModel {
+                provider: provider.clone(),
+                model: model.clone(),
+            },
+        };
+        if let Some(provider) = &target.provider
+            && !provider_ids.contains(provider)
+        {
+            report.fail(
+                "model alias",
+                alias,
+                format!("unknown provider '{provider}'"),
+            );
+            continue;
+        }
+        runtime.set_model_alias(alias.clone(), target);
+    }
     if let Some(max_context_tokens) = config.agent.max_context_tokens {
         runtime.set_max_context_tokens(max_context_tokens);
     }
@@ -609,6 +1038,32 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
     if let Some(limit) = config.agent.skill_recall_limit {
         runtime.set_skill_recall_limit(limit);
     }
+    if config.tools.confirm_destructive {
+        runtime.set_confirm_destructive(true);
+        info!("destructive tools require user confirmation");
+    }
+    if config.tools.status_messages {
+        runtime.set_tool_status_messages(config.tools.status_overrides.clone());
+        info!("tool status messages enabled");
+    }
+    if let Some(dir) = &config.agent.workspace_dir {
+        runtime.set_workspace_root(dir.clone());
+        info!("per-session tool workspaces under {}", dir.display());
+    }
+    if let Some(language) = config.agent.language.as_ref().filter(|l| l.detect) {
+        runtime.set_language_detection(language.prompts.clone());
+        info!(
+            "language detection enabled ({} per-language prompts)",
+            language.prompts.len()
+        );
+    }
+    if let Some(thinking) = &config.agent.thinking {
+        runtime.set_thinking(thinking.budget_tokens, thinking.show_reasoning);
+        info!(
+            "extended thinking enabled (budget {} tokens)",
+            thinking.budget_tokens
+        );
+    }
     if config.agent.collect_trajectories.unwrap_or(false) {
         let traj_dir = config
             .data_dir
@@ -623,18 +1078,19 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
             Err(e) => warn!("failed to open trajectory store: {e}"),
         }
     }
+    if config.agent.transcripts.unwrap_or(false) {
+        let dir = opencrust_config::ConfigLoader::default_config_dir().join("transcripts");
+        info!("writing LLM transcripts to {}", dir.display());
+        runtime.set_transcripts_dir(dir);
+    }
 
     // --- Skills ---
-    let skills_dir = opencrust_config::ConfigLoader::default_config_dir().join("skills");
-    let scanner = opencrust_skills::SkillScanner::new(&skills_dir);
-    match scanner.discover() {
-        Ok(skills) => {
-            let count = skills.len();
-            runtime.index_skills(skills).await;
-            if count > 0 {
-                info!("indexed {} skill(s) for semantic retrieval", count);
-            }
-        }
+    // Parsed skills are cached on the runtime; the skills watcher calls
+    // `reload_skills` so new or edited skills apply without a restart.
+    runtime.set_skills_dir(opencrust_config::ConfigLoader::default_config_dir().join("skills"));
+    match runtime.reload_skills().await {
+        Ok(count) if count > 0 => info!("indexed {} skill(s) for semantic retrieval", count),
+        Ok(_) => {}
         Err(e) => warn!("failed to scan skills directory: {e}"),
     }
 
@@ -653,6 +1109,12 @@ pub async fn build_agent_runtime(config: &AppConfig) -> (AgentRuntime, SendMessa
     (runtime, send_msg_handle)
 }
 
+/// True when a Gemini `base_url` points at Google's OpenAI-compatible
+/// endpoint, which configs written before the native provider still use.
+pub fn is_gemini_openai_url(base_url: Option<&str>) -> bool {
+    base_url.is_some_and(|url| url.trim_end_matches('/').ends_with("/openai"))
+}
+
 /// Resolve MCP server env vars through the vault. Empty values trigger a
 /// vault lookup with key `MCP_{SERVER}_{ENV_KEY}`, falling back to the
 /// process environment. Non-empty values pass through unchanged.
@@ -703,80 +1165,47 @@ pub fn build_skill_block(skills: &[opencrust_skills::SkillDefinition]) -> String
     block
 }
 
+/// Per-server MCP startup outcome: the number of tools registered, or why the
+/// server could not be started. Sorted by server name; disabled servers are
+/// left out.
+pub type McpStartupReport = Vec<(String, std::result::Result<usize, String>)>;
+
+/// Delay before a failed stdio MCP server gets its single retry.
+const MCP_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Build MCP tools from merged config (config.yml + mcp.json).
 ///
 /// Returns the Arc-wrapped manager, a flat list of bridged tools (including
-/// the resource tool), and an optional instructions string from server handshakes.
+/// the resource tool), an optional instructions string from server handshakes,
+/// and the per-server startup report.
 pub async fn build_mcp_tools(
     config: &AppConfig,
-) -> (Arc<McpManager>, Vec<Box<dyn Tool>>, Option<String>) {
+) -> (
+    Arc<McpManager>,
+    Vec<Box<dyn Tool>>,
+    Option<String>,
+    McpStartupReport,
+) {
     let loader = match opencrust_config::ConfigLoader::new() {
         Ok(l) => l,
         Err(e) => {
             warn!("failed to create config loader for MCP: {e}");
-            return (Arc::new(McpManager::new()), Vec::new(), None);
+            return (Arc::new(McpManager::new()), Vec::new(), None, Vec::new());
         }
     };
 
     let mcp_configs = loader.merged_mcp_config(config);
     if mcp_configs.is_empty() {
-        return (Arc::new(McpManager::new()), Vec::new(), None);
+        return (Arc::new(McpManager::new()), Vec::new(), None, Vec::new());
     }
 
     let manager = McpManager::new();
-    let mut all_tools: Vec<Box<dyn Tool>> = Vec::new();
-
-    for (name, server_config) in &mcp_configs {
-        let enabled = server_config.enabled.unwrap_or(true);
-        if !enabled {
-            info!("MCP server '{name}' is disabled, skipping");
-            continue;
-        }
-
-        let timeout_secs = server_config.timeout.unwrap_or(30);
-
-        let resolved_env = resolve_mcp_env(name, &server_config.env);
-
-        let connect_result = match server_config.transport.as_str() {
-            "stdio" => {
-                manager
-                    .connect(
-                        name,
-                        &server_config.command,
-                        &server_config.args,
-                        &resolved_env,
-                        timeout_secs,
-                    )
-                    .await
-            }
-            "http" => {
-                let Some(url) = &server_config.url else {
-                    warn!(
-                        "MCP server '{name}' uses HTTP transport but no 'url' configured, skipping"
-                    );
-                    continue;
-                };
-                manager.connect_http(name, url, timeout_secs).await
-            }
-            other => {
-                warn!("MCP server '{name}' uses unsupported transport '{other}', skipping");
-                continue;
-            }
-        };
-
-        match connect_result {
-            Ok(()) => {
-                let tools = manager
-                    .take_tools(name, std::time::Duration::from_secs(timeout_secs))
-                    .await;
-                info!("MCP server '{name}': registered {} tool(s)", tools.len());
-                all_tools.extend(tools);
-            }
-            Err(e) => {
-                warn!("failed to connect MCP server '{name}': {e}");
-            }
-        }
-    }
+    let (report, mut all_tools) =
+        start_mcp_servers(&mcp_configs, MCP_RETRY_DELAY, |name, server_config| {
+            let manager = &manager;
+            async move { connect_mcp_server(manager, &name, &server_config).await }
+        })
+        .await;
 
     // Collect server instructions
     let all_instructions = manager.get_all_instructions().await;
@@ -796,11 +1225,113 @@ pub async fn build_mcp_tools(
         Arc::clone(&manager),
     )));
 
-    (manager, all_tools, instructions_text)
+    (manager, all_tools, instructions_text, report)
+}
+
+/// Connect one MCP server and take its bridged tools.
+async fn connect_mcp_server(
+    manager: &McpManager,
+    name: &str,
+    server_config: &McpServerConfig,
+) -> std::result::Result<Vec<Box<dyn Tool>>, String> {
+    let timeout_secs = server_config.timeout.unwrap_or(30);
+    let connect_result = match server_config.transport.as_str() {
+        "stdio" => {
+            let resolved_env = resolve_mcp_env(name, &server_config.env);
+            manager
+                .connect(
+                    name,
+                    &server_config.command,
+                    &server_config.args,
+                    &resolved_env,
+                    timeout_secs,
+                )
+                .await
+        }
+        "http" => {
+            let Some(url) = &server_config.url else {
+                return Err("HTTP transport but no 'url' configured".to_string());
+            };
+            manager.connect_http(name, url, timeout_secs).await
+        }
+        other => return Err(format!("unsupported transport '{other}'")),
+    };
+    connect_result.map_err(|e| e.to_string())?;
+    Ok(manager
+        .take_tools(name, std::time::Duration::from_secs(timeout_secs))
+        .await)
+}
+
+/// Start every enabled MCP server with `start`, retrying a failed stdio server
+/// once after `retry_delay`. Logs a summary and returns the per-server report
+/// with the tools of the servers that came up.
+async fn start_mcp_servers<F, Fut>(
+    configs: &HashMap<String, McpServerConfig>,
+    retry_delay: std::time::Duration,
+    mut start: F,
+) -> (McpStartupReport, Vec<Box<dyn Tool>>)
+where
+    F: FnMut(String, McpServerConfig) -> Fut,
+    Fut: std::future::Future<Output = std::result::Result<Vec<Box<dyn Tool>>, String>>,
+{
+    let mut names: Vec<&String> = configs.keys().collect();
+    names.sort();
+
+    let mut report = McpStartupReport::new();
+    let mut all_tools: Vec<Box<dyn Tool>> = Vec::new();
+    for name in names {
+        let server_config = &configs[name];
+        if !server_config.enabled.unwrap_or(true) {
+            info!("MCP server '{name}' is disabled, skipping");
+            continue;
+        }
+
+        let mut result = start(name.clone(), server_config.clone()).await;
+        if let Err(e) = &result
+            && server_config.transport == "stdio"
+        {
+            warn!("failed to connect MCP server '{name}': {e}; retrying once");
+            tokio::time::sleep(retry_delay).await;
+            result = start(name.clone(), server_config.clone()).await;
+        }
+
+        match result {
+            Ok(tools) => {
+                info!("MCP server '{name}': registered {} tool(s)", tools.len());
+                report.push((name.clone(), Ok(tools.len())));
+                all_tools.extend(tools);
+            }
+            Err(e) => {
+                warn!("failed to connect MCP server '{name}': {e}");
+                report.push((name.clone(), Err(e)));
+            }
+        }
+    }
+
+    let failed: Vec<&str> = report
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    info!(
+        "MCP startup: {}/{} server(s) connected, {} tool(s)",
+        report.len() - failed.len(),
+        report.len(),
+        all_tools.len()
+    );
+    if !failed.is_empty() {
+        warn!("MCP servers failed to start: {}", failed.join(", "));
+    }
+
+    (report, all_tools)
 }
 
 /// Build configured channels that can be initialized before state is wrapped in Arc.
-pub async fn build_channels(config: &AppConfig) -> opencrust_channels::ChannelRegistry {
+/// Channels of an unknown or unsupported type are recorded in `report`.
+pub async fn build_channels(
+    config: &AppConfig,
+    report: &StartupReport,
+) -> opencrust_channels::ChannelRegistry {
     // Load .env file if present (idempotent, will not overwrite existing env vars)
     if let Err(e) = dotenvy::dotenv() {
         tracing::debug!("no .env file loaded: {e}");
@@ -833,14 +1364,21 @@ pub async fn build_channels(config: &AppConfig) -> opencrust_channels::ChannelRe
                 info!("whatsapp channel {name} will be started after state initialization");
             }
             "imessage" => {
-                // iMessage channels need SharedState for callbacks, so they are started later.
-                info!("imessage channel {name} will be started after state initialization");
+                if cfg!(target_os = "macos") {
+                    // iMessage channels need SharedState for callbacks, so they are started later.
+                    info!("imessage channel {name} will be started after state initialization");
+                } else {
+                    report.fail("channel", name, "imessage is only supported on macOS");
+                }
             }
-            "mqtt" => {
-                info!("mqtt channel {name} will be started after state initialization");
+            "line" | "wechat" | "mqtt" | "signal" | "teams" | "webchat" | "connector" => {
+                info!(
+                    "{} channel {name} will be started after state initialization",
+                    channel_config.channel_type
+                );
             }
             other => {
-                warn!("unknown channel type: {other} for channel {name}, skipping");
+                report.fail("channel", name, format!("unknown channel type '{other}'"));
             }
         }
     }
@@ -848,6 +1386,15 @@ pub async fn build_channels(config: &AppConfig) -> opencrust_channels::ChannelRe
     registry
 }
 
+/// Global onboarding messages from the `messages:` config section.
+fn channel_messages(config: &AppConfig) -> ChannelMessages {
+    ChannelMessages {
+        welcome_owner: config.messages.welcome_owner.clone(),
+        welcome_user: config.messages.welcome_user.clone(),
+        blocked: config.messages.blocked.clone(),
+    }
+}
+
 /// Build Discord channels from config. Must be called after state is
 /// wrapped in `Arc` so the message callback can capture a `SharedState`.
 pub fn build_discord_channels(
@@ -872,257 +1419,118 @@ pub fn build_discord_channels(
             settings.insert("application_id".to_string(), serde_json::json!(id));
         }
 
-        let allowlist = Arc::clone(&state.allowlist);
-        let pairing = Arc::clone(&state.pairing);
-
-        let policy = Arc::new(ChannelPolicy::from_settings(&settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         let group_filter: opencrust_channels::discord::DiscordGroupFilter = {
             let policy = Arc::clone(&policy);
             Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
         };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
-        let auto_reply_voice_discord = config.voice.auto_reply_voice;
-        let tts_provider_discord = state.tts_provider.clone();
-        let tts_max_chars_discord = config
-            .voice
-            .tts_max_chars
-            .unwrap_or(opencrust_media::TTS_DEFAULT_MAX_CHARS);
-        let data_dir_discord = config
-            .data_dir
-            .clone()
-            .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
-
-        let inject_user_name_discord = channel_config
-            .settings
-            .get("inject_user_name")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let pipeline = Arc::new(
+            MessagePipeline::new("discord", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config)
+                .with_voice_replies(config),
+        );
+        let stt_base_url: Option<String> = config.voice.stt_base_url.clone();
+        let stt_model: Option<String> = config.voice.stt_model.clone();
+        let stt_local_model: Option<String> = config.voice.stt_local_model.clone();
+        let stt_api_key: Option<String> = resolve_api_key(
+            config.voice.api_key.as_deref(),
+            "VOICE_API_KEY",
+            "VOICE_API_KEY",
+        );
 
+        let on_edit = pipeline.edit_hook(|channel_id, _| format!("discord-{channel_id}"));
+        let on_reaction = pipeline.feedback_hook(|channel_id, _| format!("discord-{channel_id}"));
         let on_message: opencrust_channels::discord::DiscordOnMessageFn = Arc::new(
             move |channel_id: String,
                   user_id: String,
                   user_name: String,
                   text: String,
                   is_group: bool,
-                  file: Option<opencrust_channels::discord::DiscordFile>,
+                  file: Option<opencrust_channels::MediaAttachment>,
                   delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
-                let tts = tts_provider_discord.clone();
-                let tts_max_chars = tts_max_chars_discord;
-                let data_dir = data_dir_discord.clone();
+                let pipeline = Arc::clone(&pipeline);
+                let stt_base_url = stt_base_url.clone();
+                let stt_model = stt_model.clone();
+                let stt_local_model = stt_local_model.clone();
+                let stt_api_key = stt_api_key.clone();
                 Box::pin(async move {
-                    let session_id = format!("discord-{channel_id}");
-
-                    // --- /ingest command ---
-                    if let Some(cmd) = text.strip_prefix('!').or_else(|| text.strip_prefix('/')) {
-                        let cmd_word = cmd.split_whitespace().next().unwrap_or("");
-                        if cmd_word == "ingest" {
-                            if let Some(pending) = state.take_pending_file(&session_id) {
-                                return crate::ingest::run_ingest(
-                                    &state,
-                                    &data_dir,
-                                    &text,
-                                    &pending.filename,
-                                    &pending.data,
-                                )
-                                .await;
-                            } else {
-                                return Ok(ChannelResponse::Text(
-                                    "No pending file. Send a document first, then use !ingest."
-                                        .to_string(),
-                                ));
-                            }
-                        }
-
-                        // All other slash commands are handled synchronously.
-                        return handle_discord_command(
-                            cmd_word,
-                            &user_id,
-                            &user_name,
-                            &channel_id,
-                            &allowlist,
-                            &pairing,
-                            &policy,
-                            &state,
-                        )
-                        .map(ChannelResponse::Text);
-                    }
-
-                    // --- File handling ---
-                    if let Some(discord_file) = file {
-                        let fname = discord_file.filename.clone();
-                        let caption = text.trim().to_lowercase();
-                        if caption.contains("ingest") {
-                            return crate::ingest::run_ingest(
-                                &state,
-                                &data_dir,
-                                &caption,
-                                &fname,
-                                &discord_file.data,
-                            )
-                            .await;
-                        } else if opencrust_media::is_supported_for_ingest(&fname) {
-                            state.set_pending_file(
-                                &session_id,
-                                crate::state::PendingFile {
-                                    filename: fname.clone(),
-                                    data: discord_file.data,
-                                    received_at: std::time::Instant::now(),
-                                },
-                            );
-                            return Ok(ChannelResponse::Text(format!(
-                                "Received {fname}. Use !ingest to store it for future reference."
-                            )));
-                        }
-                    }
-
-                    // Groups already filtered by channel handler - skip auth for groups
-                    if !is_group {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy, &mut list, &pairing, &user_id, &user_name, &text, "discord",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
+                    let session_id =
+                        pipeline.session_id(format!("discord-{channel_id}"), &user_id, is_group);
+                    let msg = InboundMessage::text(session_id, user_id, user_name, text)
+                        .with_group(is_group)
+                        .with_metadata(serde_json::json!({"discord_channel_id": channel_id}))
+                        .with_delta_tx(delta_tx)
+                        .with_voice_reply(true);
+
+                    // --- Commands ---
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
 
-                    state.check_user_rate_limit(&user_id, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &user_id, &guardrails_config)
+                    // --- Speech from a voice channel: answer the transcript ---
+                    if let Some(voice) = file.as_ref().filter(|f| f.kind == MediaKind::Voice) {
+                        let transcript = transcribe_voice(
+                            voice,
+                            stt_base_url.as_deref(),
+                            stt_model.as_deref(),
+                            stt_local_model.as_deref(),
+                            stt_api_key.as_deref(),
+                        )
                         .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
-                    );
-                    if inject_user_name_discord {
-                        state.agents.set_session_user_name(&session_id, &user_name);
-                    }
-
-                    let text = opencrust_security::InputValidator::sanitize(&text);
-                    if opencrust_security::InputValidator::exceeds_length(&text, max_input_chars) {
-                        return Err(format!(
-                            "message too long (max {max_input_chars} characters)"
-                        ));
-                    }
-                    if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                        return Err(
-                            "input rejected: potential prompt injection detected".to_string()
-                        );
+                        info!("discord voice transcribed: {} chars", transcript.len());
+                        return pipeline
+                            .handle(InboundMessage {
+                                text: transcript,
+                                ..msg
+                            })
+                            .await;
                     }
 
-                    state
-                        .hydrate_session_history(&session_id, Some("discord"), Some(&user_id))
-                        .await;
-                    let history: Vec<ChatMessage> = state.session_history(&session_id);
-                    let continuity_key = state.continuity_key(Some(&user_id));
-                    let summary = state.session_summary(&session_id);
-
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
+                    // --- File handling ---
+                    if let Some(discord_file) = file
+                        && let Some(result) = pipeline
+                            .receive_file(
+                                &msg.session_id,
+                                discord_file.filename_or_default(),
+                                discord_file.data,
+                                &msg.text,
                             )
                             .await
-                    }
-                    .map_err(|e| e.to_string())?;
-
-                    if let Some(s) = new_summary {
-                        state.update_session_summary(&session_id, &s);
-                    }
-
-                    let response = opencrust_security::InputValidator::truncate_output(
-                        &response,
-                        max_output_chars,
-                    );
-
-                    state
-                        .persist_turn(
-                            &session_id,
-                            Some("discord"),
-                            Some(&user_id),
-                            &text,
-                            &response,
-                            Some(serde_json::json!({"discord_channel_id": channel_id})),
-                        )
-                        .await;
-
-                    if let Some((input, output, provider, model)) =
-                        state.agents.take_session_usage(&session_id)
                     {
-                        state
-                            .persist_usage(&session_id, &provider, &model, input, output)
-                            .await;
+                        return result;
                     }
 
-                    // TTS: synthesize voice response if configured
-                    if auto_reply_voice_discord && let Some(ref provider) = tts {
-                        let tts_input = opencrust_media::truncate_for_tts(&response, tts_max_chars);
-                        match provider.synthesize(tts_input).await {
-                            Ok(audio) => {
-                                return Ok(ChannelResponse::Voice {
-                                    text: response,
-                                    audio,
-                                });
-                            }
-                            Err(e) => {
-                                warn!("tts synthesis failed, falling back to text: {e}");
-                            }
-                        }
-                    }
-                    Ok(ChannelResponse::Text(response))
+                    pipeline.handle(msg).await
                 })
             },
         );
 
         match opencrust_channels::discord::config::DiscordConfig::from_settings(&settings) {
             Ok(discord_config) => {
-                let channel = opencrust_channels::discord::DiscordChannel::with_group_filter(
+                let mut channel = opencrust_channels::discord::DiscordChannel::with_group_filter(
                     discord_config,
                     on_message,
                     group_filter,
                 )
-                .with_name(name.clone());
+                .with_name(name.clone())
+                .with_reaction_feedback(on_reaction);
+                if let Some(on_edit) = on_edit {
+                    channel = channel.with_edit_regeneration(on_edit);
+                }
+                if let Some(seen) = state.inbound_dedup(name) {
+                    channel = channel.with_dedup(seen);
+                }
                 channels.push(Box::new(channel) as Box<dyn opencrust_channels::Channel>);
                 info!("configured discord channel: {name}");
             }
             Err(e) => {
-                warn!("failed to configure discord channel {name}: {e}");
+                state.startup.fail("channel", name, e.to_string());
             }
         }
     }
@@ -1130,30 +1538,48 @@ pub fn build_discord_channels(
     channels
 }
 
-/// Transcribe voice audio using the Whisper API.
+/// Transcribe voice audio using the Whisper API. The attachment's filename
+/// and MIME type are passed on so servers can tell the audio format apart.
 ///
 /// Priority:
+/// 0. On-device Whisper model (`stt_local_model`, `whisper-local` feature);
+///    on failure the remaining sources are tried
 /// 1. Local Whisper server (`stt_base_url` in config) — no API key required
 /// 2. `voice.api_key` from config (used for OpenAI)
 /// 3. `OPENAI_API_KEY` env var
 /// 4. `GROQ_API_KEY` env var
 async fn transcribe_voice(
-    audio_bytes: &[u8],
+    audio: &MediaAttachment,
     stt_base_url: Option<&str>,
     stt_model: Option<&str>,
+    stt_local_model: Option<&str>,
     config_api_key: Option<&str>,
 ) -> std::result::Result<String, String> {
+    // 0. On-device model — no server or API key needed
+    if let Some(model_path) = stt_local_model {
+        #[cfg(feature = "whisper-local")]
+        match opencrust_media::transcribe_local(model_path, &audio.data).await {
+            Ok(transcript) => return Ok(transcript),
+            Err(e) => warn!("local whisper transcription failed, trying next source: {e}"),
+        }
+        #[cfg(not(feature = "whisper-local"))]
+        warn!(
+            "voice.stt_local_model '{model_path}' requires the `whisper-local` feature flag. \
+             Rebuild with: cargo build --features whisper-local"
+        );
+    }
+
     // 1. Local Whisper server — no API key needed
     if let Some(base_url) = stt_base_url {
         let endpoint = format!("{}/v1/audio/transcriptions", base_url.trim_end_matches('/'));
         let model = stt_model.unwrap_or("Systran/faster-whisper-large-v3");
-        return whisper_transcribe(audio_bytes, "", &endpoint, model).await;
+        return whisper_transcribe(audio, "", &endpoint, model).await;
     }
 
     // 2. API key from config
     if let Some(key) = config_api_key {
         return whisper_transcribe(
-            audio_bytes,
+            audio,
             key,
             "https://api.openai.com/v1/audio/transcriptions",
             "whisper-1",
@@ -1164,7 +1590,7 @@ async fn transcribe_voice(
     // 3. OpenAI env var
     if let Some(key) = resolve_api_key(None, "OPENAI_API_KEY", "OPENAI_API_KEY") {
         return whisper_transcribe(
-            audio_bytes,
+            audio,
             &key,
             "https://api.openai.com/v1/audio/transcriptions",
             "whisper-1",
@@ -1175,15 +1601,16 @@ async fn transcribe_voice(
     // 4. Groq env var
     if let Some(key) = resolve_api_key(None, "GROQ_API_KEY", "GROQ_API_KEY") {
         return whisper_transcribe(
-            audio_bytes,
+            audio,
             &key,
-            "https://api.groq.com/openai/v1/audio/transcriptions",
+            &format!("{GROQ_DEFAULT_BASE_URL}/audio/transcriptions"),
             "whisper-large-v3-turbo",
         )
         .await;
     }
 
     Err("Voice messages require a transcription source. Options:\n\
+         - Set voice.stt_local_model to a ggml Whisper model (whisper-local builds)\n\
          - Set voice.stt_base_url in config.yml for a local Whisper server\n\
          - Set voice.api_key for OpenAI Whisper\n\
          - Set GROQ_API_KEY env var for free Groq Whisper"
@@ -1191,17 +1618,20 @@ async fn transcribe_voice(
 }
 
 async fn whisper_transcribe(
-    audio_bytes: &[u8],
+    audio: &MediaAttachment,
     api_key: &str,
     endpoint: &str,
     model: &str,
 ) -> std::result::Result<String, String> {
     let client = reqwest::Client::new();
 
-    let file_part = reqwest::multipart::Part::bytes(audio_bytes.to_vec())
-        .file_name("voice.ogg")
-        .mime_str("audio/ogg")
-        .map_err(|e| format!("failed to build multipart: {e}"))?;
+    let mut file_part =
+        reqwest::multipart::Part::bytes(audio.data.clone()).file_name(audio.filename_or_default());
+    if let Some(mime_type) = audio.mime_type.as_deref() {
+        file_part = file_part
+            .mime_str(mime_type)
+            .map_err(|e| format!("failed to build multipart: {e}"))?;
+    }
 
     let form = reqwest::multipart::Form::new()
         .part("file", file_part)
@@ -1258,57 +1688,42 @@ pub fn build_telegram_channels(
             bot_token.or_else(|| resolve_api_key(None, "TELEGRAM_BOT_TOKEN", "TELEGRAM_BOT_TOKEN"));
 
         let Some(bot_token) = bot_token else {
-            warn!(
-                "telegram channel '{name}' has no bot_token, skipping \
-                 (set bot_token in config or TELEGRAM_BOT_TOKEN env var)"
+            state.startup.fail(
+                "channel",
+                name,
+                "no bot_token (set bot_token in config or TELEGRAM_BOT_TOKEN env var)",
             );
             continue;
         };
 
-        let allowlist = Arc::clone(&state.allowlist);
-
-        let pairing = Arc::clone(&state.pairing);
-
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         let group_filter: opencrust_channels::GroupFilter = {
             let policy = Arc::clone(&policy);
             Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
         };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
-        let auto_reply_voice = config.voice.auto_reply_voice;
-        let tts_provider = state.tts_provider.clone();
-        let tts_max_chars = config
-            .voice
-            .tts_max_chars
-            .unwrap_or(opencrust_media::TTS_DEFAULT_MAX_CHARS);
+        let pipeline = Arc::new(
+            MessagePipeline::new("telegram", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config)
+                .with_voice_replies(config),
+        );
         let stt_base_url: Option<String> = config.voice.stt_base_url.clone();
         let stt_model: Option<String> = config.voice.stt_model.clone();
+        let stt_local_model: Option<String> = config.voice.stt_local_model.clone();
         // Resolve STT API key via vault → config → env (same chain as all other keys).
         let stt_api_key: Option<String> = resolve_api_key(
             config.voice.api_key.as_deref(),
             "VOICE_API_KEY",
             "VOICE_API_KEY",
         );
-        let data_dir = config
-            .data_dir
-            .clone()
-            .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
-
-        let inject_user_name_tg = channel_config
-            .settings
-            .get("inject_user_name")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
 
+        let on_edit = pipeline.edit_hook(|chat_id, _| format!("telegram-{chat_id}"));
+        let on_reaction = pipeline.feedback_hook(|chat_id, _| format!("telegram-{chat_id}"));
         let on_message: opencrust_channels::OnMessageFn = Arc::new(
             move |chat_id: i64,
                   user_id: String,
@@ -1317,427 +1732,121 @@ pub fn build_telegram_channels(
                   is_group: bool,
                   attachment: Option<MediaAttachment>,
                   delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
-                let tts = tts_provider.clone();
-                let tts_max_chars = tts_max_chars;
+                let pipeline = Arc::clone(&pipeline);
                 let stt_base_url = stt_base_url.clone();
                 let stt_model = stt_model.clone();
+                let stt_local_model = stt_local_model.clone();
                 let stt_api_key = stt_api_key.clone();
-                let data_dir = data_dir.clone();
                 Box::pin(async move {
-                    // --- Command handling (text-only) ---
-                    if let Some(cmd) = text.strip_prefix('!').or_else(|| text.strip_prefix('/')) {
-                        let cmd = cmd.split_whitespace().next().unwrap_or("");
-
-                        // /ingest - async, needs data_dir and embedding provider
-                        if cmd == "ingest" {
-                            let session_id = format!("telegram-{chat_id}");
-                            if let Some(pending) = state.take_pending_file(&session_id) {
-                                return crate::ingest::run_ingest(
-                                    &state,
-                                    &data_dir,
-                                    &text,
-                                    &pending.filename,
-                                    &pending.data,
-                                )
-                                .await;
-                            } else {
-                                return Ok(ChannelResponse::Text(
-                                    "No pending file. Send a document first, then use !ingest."
-                                        .to_string(),
-                                ));
-                            }
-                        }
+                    let session_id =
+                        pipeline.session_id(format!("telegram-{chat_id}"), &user_id, is_group);
+                    let msg = InboundMessage::text(session_id, user_id, user_name, text)
+                        .with_group(is_group)
+                        .with_metadata(serde_json::json!({"telegram_chat_id": chat_id}))
+                        .with_delta_tx(delta_tx);
 
-                        return handle_command(
-                            cmd, &text, &user_id, &user_name, chat_id, &allowlist, &pairing,
-                            &policy, &state,
-                        )
-                        .map(ChannelResponse::Text);
-                    }
-
-                    // --- Auth / pairing (skip for groups - already filtered by channel handler) ---
-                    if !is_group {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy, &mut list, &pairing, &user_id, &user_name, &text, "telegram",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
+                    // --- Command handling (text-only) ---
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
 
-                    let session_id = format!("telegram-{chat_id}");
+                    // --- Group respond_mode (prefix) ---
+                    let Some(msg) = pipeline.addressed(msg) else {
+                        return Err("__blocked__".to_string());
+                    };
 
-                    state.check_user_rate_limit(&user_id, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &user_id, &guardrails_config)
-                        .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
-                    );
-                    if inject_user_name_tg {
-                        state.agents.set_session_user_name(&session_id, &user_name);
+                    // --- Auth / pairing, rate limits and budgets ---
+                    if let Some(reply) = pipeline.admit(&msg).await? {
+                        return Ok(reply);
                     }
 
                     // --- Handle media or text ---
                     match attachment {
-                        Some(MediaAttachment::Voice { data, duration }) => {
+                        Some(voice) if voice.kind == MediaKind::Voice => {
                             let transcript = transcribe_voice(
-                                &data,
+                                &voice,
                                 stt_base_url.as_deref(),
                                 stt_model.as_deref(),
+                                stt_local_model.as_deref(),
                                 stt_api_key.as_deref(),
                             )
                             .await?;
                             info!(
                                 "telegram voice transcribed: {} chars from {}s audio",
                                 transcript.len(),
-                                duration
-                            );
-
-                            let text = opencrust_security::InputValidator::sanitize(&transcript);
-                            if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                                return Err("input rejected: potential prompt injection detected"
-                                    .to_string());
-                            }
-                            if opencrust_security::InputValidator::exceeds_length(
-                                &text,
-                                max_input_chars,
-                            ) {
-                                return Err(format!(
-                                    "input rejected: message exceeds {max_input_chars} character limit"
-                                ));
-                            }
-
-                            state
-                                .hydrate_session_history(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                )
-                                .await;
-                            let history: Vec<ChatMessage> = state.session_history(&session_id);
-                            let continuity_key = state.continuity_key(Some(&user_id));
-                            let summary = state.session_summary(&session_id);
-
-                            let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                                state
-                                    .agents
-                                    .process_message_streaming_with_context_and_summary(
-                                        &session_id,
-                                        &text,
-                                        &history,
-                                        delta_sender,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            } else {
-                                state
-                                    .agents
-                                    .process_message_with_context_and_summary(
-                                        &session_id,
-                                        &text,
-                                        &history,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            }
-                            .map_err(|e| e.to_string())?;
-
-                            if let Some(s) = new_summary {
-                                state.update_session_summary(&session_id, &s);
-                            }
-
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
-                            );
-                            state
-                                .persist_turn(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                    &text,
-                                    &response,
-                                    Some(serde_json::json!({"telegram_chat_id": chat_id})),
-                                )
-                                .await;
-                            if let Some((input, output, provider, model)) =
-                                state.agents.take_session_usage(&session_id)
-                            {
-                                state
-                                    .persist_usage(&session_id, &provider, &model, input, output)
-                                    .await;
-                            }
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
-                            );
-                            // TTS: synthesize voice response if configured
-                            if auto_reply_voice && let Some(ref provider) = tts {
-                                let tts_input =
-                                    opencrust_media::truncate_for_tts(&response, tts_max_chars);
-                                match provider.synthesize(tts_input).await {
-                                    Ok(audio) => {
-                                        return Ok(ChannelResponse::Voice {
-                                            text: response,
-                                            audio,
-                                        });
-                                    }
-                                    Err(e) => {
-                                        warn!("tts synthesis failed, falling back to text: {e}");
-                                    }
-                                }
-                            }
-                            Ok(ChannelResponse::Text(response))
-                        }
-                        Some(MediaAttachment::Photo { data, caption }) => {
-                            use base64::Engine;
-                            let b64 = base64::engine::general_purpose::STANDARD.encode(&data);
-                            let data_url = format!("data:image/jpeg;base64,{b64}");
-                            let caption_text = opencrust_security::InputValidator::sanitize(
-                                &caption.unwrap_or_else(|| "Describe this image.".to_string()),
-                            );
-                            if opencrust_security::InputValidator::exceeds_length(
-                                &caption_text,
-                                max_input_chars,
-                            ) {
-                                return Err(format!(
-                                    "input rejected: message exceeds {max_input_chars} character limit"
-                                ));
-                            }
-                            if opencrust_security::InputValidator::check_prompt_injection(
-                                &caption_text,
-                            ) {
-                                return Err("input rejected: potential prompt injection detected"
-                                    .to_string());
-                            }
-
-                            let blocks = vec![
-                                opencrust_agents::ContentBlock::Image { url: data_url },
-                                opencrust_agents::ContentBlock::Text {
-                                    text: caption_text.clone(),
-                                },
-                            ];
-
-                            state
-                                .hydrate_session_history(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                )
-                                .await;
-                            let history: Vec<ChatMessage> = state.session_history(&session_id);
-                            let continuity_key = state.continuity_key(Some(&user_id));
-                            let summary = state.session_summary(&session_id);
-
-                            let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                                state
-                                    .agents
-                                    .process_message_streaming_with_blocks_and_summary(
-                                        &session_id,
-                                        blocks,
-                                        &caption_text,
-                                        &history,
-                                        delta_sender,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            } else {
-                                state
-                                    .agents
-                                    .process_message_with_blocks_and_summary(
-                                        &session_id,
-                                        blocks,
-                                        &caption_text,
-                                        &history,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            }
-                            .map_err(|e| e.to_string())?;
-
-                            if let Some(s) = new_summary {
-                                state.update_session_summary(&session_id, &s);
-                            }
-
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
-                            );
-                            state
-                                .persist_turn(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                    &caption_text,
-                                    &response,
-                                    Some(serde_json::json!({"telegram_chat_id": chat_id})),
-                                )
-                                .await;
-                            if let Some((input, output, provider, model)) =
-                                state.agents.take_session_usage(&session_id)
-                            {
-                                state
-                                    .persist_usage(&session_id, &provider, &model, input, output)
-                                    .await;
-                            }
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
+                                voice.duration_secs.unwrap_or(0)
                             );
-                            Ok(ChannelResponse::Text(response))
+                            let msg = InboundMessage {
+                                text: transcript,
+                                ..msg
+                            };
+                            pipeline.run_turn(msg.with_voice_reply(true)).await
                         }
-                        Some(MediaAttachment::Document {
+                        Some(MediaAttachment {
+                            kind: MediaKind::Photo,
                             data,
-                            filename,
-                            mime_type: _,
                             caption,
+                            ..
                         }) => {
-                            if data.len() > 10 * 1024 * 1024 {
+                            use base64::Engine;
+                            let b64 = base64::engine::general_purpose::STANDARD.encode(&data);
+                            let msg = InboundMessage {
+                                text: caption.unwrap_or_else(|| "Describe this image.".to_string()),
+                                ..msg
+                            };
+                            pipeline
+                                .run_turn(msg.with_image(format!("data:image/jpeg;base64,{b64}")))
+                                .await
+                        }
+                        // Documents and videos are handled as files.
+                        Some(attachment) => {
+                            if attachment.data.len() > 10 * 1024 * 1024 {
                                 return Err("File too large. Maximum size is 10MB.".to_string());
                             }
 
-                            let fname = filename.unwrap_or_else(|| "file".to_string());
-                            let caption_text = caption.unwrap_or_default().trim().to_lowercase();
-
-                            // If caption contains "ingest", ingest immediately
-                            if caption_text.contains("ingest") {
-                                return crate::ingest::run_ingest(
-                                    &state,
-                                    &data_dir,
-                                    &caption_text,
-                                    &fname,
-                                    &data,
-                                )
-                                .await;
-                            } else if opencrust_media::is_supported_for_ingest(&fname) {
-                                // Store as pending and prompt
-                                state.set_pending_file(
-                                    &session_id,
-                                    crate::state::PendingFile {
-                                        filename: fname.clone(),
-                                        data,
-                                        received_at: std::time::Instant::now(),
-                                    },
-                                );
-                                Ok(ChannelResponse::Text(format!(
-                                    "Received {fname}. Use !ingest to store it for future reference."
-                                )))
-                            } else {
-                                Ok(ChannelResponse::Text(String::new()))
+                            let fname = attachment.filename_or_default();
+                            let MediaAttachment {
+                                data,
+                                mime_type,
+                                caption,
+                                ..
+                            } = attachment;
+                            let caption = caption.unwrap_or_default();
+                            let mime = mime_type.unwrap_or_else(|| {
+                                opencrust_media::detect_mime_type(std::path::Path::new(&fname))
+                                    .to_string()
+                            });
+                            if pipeline.accepts_native_document(&caption, &mime) {
+                                let text =
+                                    crate::ingest::extract_text_from_bytes(&fname, &data).ok();
+                                let msg = InboundMessage {
+                                    text: caption,
+                                    ..msg
+                                };
+                                return pipeline
+                                    .run_turn(msg.with_document(&mime, &data, text))
+                                    .await;
                             }
+                            pipeline
+                                .receive_file(&msg.session_id, fname, data, &caption)
+                                .await
+                                .unwrap_or_else(|| Ok(ChannelResponse::Text(String::new())))
                         }
-                        None => {
-                            // Regular text-only path
-                            let text = opencrust_security::InputValidator::sanitize(&text);
-                            if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                                return Err("input rejected: potential prompt injection detected"
-                                    .to_string());
-                            }
-                            if opencrust_security::InputValidator::exceeds_length(
-                                &text,
-                                max_input_chars,
-                            ) {
-                                return Err(format!(
-                                    "input rejected: message exceeds {max_input_chars} character limit"
-                                ));
-                            }
+                        None => pipeline.run_turn(msg).await,
+                    }
+                })
+            },
+        );
 
-                            state
-                                .hydrate_session_history(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                )
-                                .await;
-                            let history: Vec<ChatMessage> = state.session_history(&session_id);
-                            let continuity_key = state.continuity_key(Some(&user_id));
-                            let summary = state.session_summary(&session_id);
-
-                            let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                                state
-                                    .agents
-                                    .process_message_streaming_with_context_and_summary(
-                                        &session_id,
-                                        &text,
-                                        &history,
-                                        delta_sender,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            } else {
-                                state
-                                    .agents
-                                    .process_message_with_context_and_summary(
-                                        &session_id,
-                                        &text,
-                                        &history,
-                                        summary.as_deref(),
-                                        continuity_key.as_deref(),
-                                        Some(&user_id),
-                                    )
-                                    .await
-                            }
-                            .map_err(|e| e.to_string())?;
-
-                            if let Some(s) = new_summary {
-                                state.update_session_summary(&session_id, &s);
-                            }
-
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
-                            );
-                            state
-                                .persist_turn(
-                                    &session_id,
-                                    Some("telegram"),
-                                    Some(&user_id),
-                                    &text,
-                                    &response,
-                                    Some(serde_json::json!({"telegram_chat_id": chat_id})),
-                                )
-                                .await;
-                            if let Some((input, output, provider, model)) =
-                                state.agents.take_session_usage(&session_id)
-                            {
-                                state
-                                    .persist_usage(&session_id, &provider, &model, input, output)
-                                    .await;
-                            }
-                            let response = opencrust_security::InputValidator::truncate_output(
-                                &response,
-                                max_output_chars,
-                            );
-                            Ok(ChannelResponse::Text(response))
-                        }
-                    }
-                })
-            },
-        );
-
-        let channel = TelegramChannel::with_group_filter(bot_token, on_message, group_filter)
-            .with_name(name.clone());
+        let mut channel = TelegramChannel::with_group_filter(bot_token, on_message, group_filter)
+            .with_name(name.clone())
+            .with_reaction_feedback(on_reaction);
+        if let Some(on_edit) = on_edit {
+            channel = channel.with_edit_regeneration(on_edit);
+        }
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
         channels.push(Box::new(channel) as Box<dyn opencrust_channels::Channel>);
         info!("configured telegram channel: {name}");
     }
@@ -1745,232 +1854,6 @@ pub fn build_telegram_channels(
     channels
 }
 
-#[allow(clippy::too_many_arguments)]
-fn handle_command(
-    cmd: &str,
-    _full_text: &str,
-    user_id: &str,
-    user_name: &str,
-    chat_id: i64,
-    allowlist: &Arc<Mutex<Allowlist>>,
-    pairing: &Arc<Mutex<PairingManager>>,
-    policy: &ChannelPolicy,
-    state: &SharedState,
-) -> std::result::Result<String, String> {
-    // If dm_policy is Open, skip all auth checks in commands
-    let dm_open = matches!(policy.authorize_dm(user_id), DmAuthResult::Allowed);
-    let list = allowlist.lock().unwrap();
-    let is_owner = dm_open || list.is_owner(user_id);
-    let is_allowed = dm_open || list.is_allowed(user_id);
-    drop(list);
-
-    match cmd {
-        "start" => {
-            if is_allowed {
-                Ok(
-                    "Welcome to OpenCrust! Send me a message and I will respond.\n\n\
-                    Commands:\n\
-                    /help - show this help\n\
-                    /clear - reset conversation history\n\
-                    /pair - generate invite code (owner only)"
-                        .to_string(),
-                )
-            } else {
-                let mut list = allowlist.lock().unwrap();
-                if list.needs_owner() {
-                    list.claim_owner(user_id);
-                    info!("telegram: auto-paired owner {} ({})", user_name, user_id);
-                    Ok(format!(
-                        "Welcome, {}! You are now the owner of this OpenCrust bot.\n\n\
-                         Use /pair to generate a code for adding other users.",
-                        user_name
-                    ))
-                } else {
-                    Ok("This bot is private. Send the 6-digit pairing code you received to get access.".to_string())
-                }
-            }
-        }
-        "help" => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            let mut help = "OpenCrust Commands:\n\
-                /help - show this help\n\
-                /clear - reset conversation history\n\
-                !ingest - store a sent document for future reference"
-                .to_string();
-            if is_owner {
-                help.push_str(
-                    "\n/pair - generate a 6-digit invite code\n/users - list allowed users",
-                );
-            }
-            Ok(help)
-        }
-        "clear" => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            let session_id = format!("telegram-{chat_id}");
-            if let Some(mut session) = state.sessions.get_mut(&session_id) {
-                session.history.clear();
-            }
-            state.update_session_summary(&session_id, "");
-            if let Some(store) = &state.session_store {
-                let _ = store.prune_old_messages(&session_id, 0);
-            }
-            Ok("Conversation history cleared.".to_string())
-        }
-        "pair" => {
-            if !is_owner {
-                if !is_allowed {
-                    return Err("__blocked__".to_string());
-                }
-                return Ok("Only the bot owner can generate pairing codes.".to_string());
-            }
-            let code = pairing.lock().unwrap().generate("telegram");
-            Ok(format!(
-                "Pairing code: {code}\n\n\
-                 Share this with the person you want to invite. \
-                 They should send this code to the bot within 5 minutes."
-            ))
-        }
-        "users" => {
-            if !is_owner {
-                if !is_allowed {
-                    return Err("__blocked__".to_string());
-                }
-                return Ok("Only the bot owner can list users.".to_string());
-            }
-            let list = allowlist.lock().unwrap();
-            let users = list.list_users();
-            let owner = list.owner().unwrap_or("none");
-            Ok(format!(
-                "Owner: {owner}\nAllowed users ({}):\n{}",
-                users.len(),
-                users.join("\n")
-            ))
-        }
-        _ => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            Ok(format!(
-                "Unknown command: /{cmd}\nUse /help for available commands."
-            ))
-        }
-    }
-}
-
-#[allow(clippy::too_many_arguments)]
-fn handle_discord_command(
-    cmd: &str,
-    user_id: &str,
-    user_name: &str,
-    channel_id: &str,
-    allowlist: &Arc<Mutex<Allowlist>>,
-    pairing: &Arc<Mutex<PairingManager>>,
-    policy: &ChannelPolicy,
-    state: &SharedState,
-) -> std::result::Result<String, String> {
-    let dm_open = matches!(policy.authorize_dm(user_id), DmAuthResult::Allowed);
-    let list = allowlist.lock().unwrap();
-    let is_owner = dm_open || list.is_owner(user_id);
-    let is_allowed = dm_open || list.is_allowed(user_id);
-    drop(list);
-
-    match cmd {
-        "start" => {
-            if is_allowed {
-                Ok(
-                    "Welcome to OpenCrust! Send me a message and I will respond.\n\n\
-                    Commands:\n\
-                    /help - show this help\n\
-                    /clear - reset conversation history\n\
-                    /pair - generate invite code (owner only)"
-                        .to_string(),
-                )
-            } else {
-                let mut list = allowlist.lock().unwrap();
-                if list.needs_owner() {
-                    list.claim_owner(user_id);
-                    info!("discord: auto-paired owner {} ({})", user_name, user_id);
-                    Ok(format!(
-                        "Welcome, {}! You are now the owner of this OpenCrust bot.\n\n\
-                         Use /pair to generate a code for adding other users.",
-                        user_name
-                    ))
-                } else {
-                    Ok("This bot is private. Send the 6-digit pairing code you received to get access.".to_string())
-                }
-            }
-        }
-        "help" => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            let mut help = "OpenCrust Commands:\n\
-                /help - show this help\n\
-                /clear - reset conversation history\n\
-                !ingest - store a sent document for future reference"
-                .to_string();
-            if is_owner {
-                help.push_str(
-                    "\n/pair - generate a 6-digit invite code\n/users - list allowed users",
-                );
-            }
-            Ok(help)
-        }
-        "clear" => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            let session_id = format!("discord-{channel_id}");
-            if let Some(mut session) = state.sessions.get_mut(&session_id) {
-                session.history.clear();
-            }
-            Ok("Conversation history cleared.".to_string())
-        }
-        "pair" => {
-            if !is_owner {
-                if !is_allowed {
-                    return Err("__blocked__".to_string());
-                }
-                return Ok("Only the bot owner can generate pairing codes.".to_string());
-            }
-            let code = pairing.lock().unwrap().generate("discord");
-            Ok(format!(
-                "Pairing code: {code}\n\n\
-                 Share this with the person you want to invite. \
-                 They should send this code to the bot within 5 minutes."
-            ))
-        }
-        "users" => {
-            if !is_owner {
-                if !is_allowed {
-                    return Err("__blocked__".to_string());
-                }
-                return Ok("Only the bot owner can list users.".to_string());
-            }
-            let list = allowlist.lock().unwrap();
-            let users = list.list_users();
-            let owner = list.owner().unwrap_or("none");
-            Ok(format!(
-                "Owner: {owner}\nAllowed users ({}):\n{}",
-                users.len(),
-                users.join("\n")
-            ))
-        }
-        _ => {
-            if !is_allowed {
-                return Err("__blocked__".to_string());
-            }
-            Ok(format!(
-                "Unknown command: /{cmd}\nUse /help for available commands."
-            ))
-        }
-    }
-}
-
 /// Build Slack channels from config. Must be called after state is
 /// wrapped in `Arc` so the message callback can capture a `SharedState`.
 pub fn build_slack_channels(
@@ -1994,9 +1877,10 @@ pub fn build_slack_channels(
             bot_token.or_else(|| resolve_api_key(None, "SLACK_BOT_TOKEN", "SLACK_BOT_TOKEN"));
 
         let Some(bot_token) = bot_token else {
-            warn!(
-                "slack channel '{name}' has no bot_token, skipping \
-                 (set bot_token in config or SLACK_BOT_TOKEN env var)"
+            state.startup.fail(
+                "channel",
+                name,
+                "no bot_token (set bot_token in config or SLACK_BOT_TOKEN env var)",
             );
             continue;
         };
@@ -2011,18 +1895,18 @@ pub fn build_slack_channels(
             app_token.or_else(|| resolve_api_key(None, "SLACK_APP_TOKEN", "SLACK_APP_TOKEN"));
 
         let Some(app_token) = app_token else {
-            warn!(
-                "slack channel '{name}' has no app_token, skipping \
-                 (set app_token in config or SLACK_APP_TOKEN env var)"
+            state.startup.fail(
+                "channel",
+                name,
+                "no app_token (set app_token in config or SLACK_APP_TOKEN env var)",
             );
             continue;
         };
 
-        let allowlist = Arc::clone(&state.allowlist);
-
-        let pairing = Arc::clone(&state.pairing);
-
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         let bot_user_id = channel_config
             .settings
@@ -2035,216 +1919,80 @@ pub fn build_slack_channels(
             Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
         };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
-        let auto_reply_voice_slack = config.voice.auto_reply_voice;
-        let tts_provider_slack = state.tts_provider.clone();
-        let tts_max_chars_slack = config
-            .voice
-            .tts_max_chars
-            .unwrap_or(opencrust_media::TTS_DEFAULT_MAX_CHARS);
-        let data_dir_slack = config
-            .data_dir
-            .clone()
-            .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
-
-        let inject_user_name_slack = channel_config
-            .settings
-            .get("inject_user_name")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        // Slack has no native audio API, so voice replies are never synthesized.
+        let pipeline = Arc::new(
+            MessagePipeline::new("slack", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config),
+        );
 
+        let on_edit = pipeline.edit_hook(slack_session_base);
+        let on_reaction = pipeline.feedback_hook(slack_session_base);
         let on_message: SlackOnMessageFn = Arc::new(
             move |channel_id: String,
+                  thread_ts: Option<String>,
                   user_id: String,
                   user_name: String,
                   text: String,
                   is_group: bool,
-                  file: Option<opencrust_channels::SlackFile>,
+                  file: Option<opencrust_channels::MediaAttachment>,
                   delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
-                let tts = tts_provider_slack.clone();
-                let tts_max_chars = tts_max_chars_slack;
-                let data_dir = data_dir_slack.clone();
+                let pipeline = Arc::clone(&pipeline);
                 Box::pin(async move {
-                    let session_id = format!("slack-{channel_id}");
-
-                    // --- /ingest command ---
-                    if let Some(cmd) = text.strip_prefix('!').or_else(|| text.strip_prefix('/')) {
-                        let cmd_word = cmd.split_whitespace().next().unwrap_or("");
-                        if cmd_word == "ingest" {
-                            if let Some(pending) = state.take_pending_file(&session_id) {
-                                return crate::ingest::run_ingest(
-                                    &state,
-                                    &data_dir,
-                                    &text,
-                                    &pending.filename,
-                                    &pending.data,
-                                )
-                                .await;
-                            } else {
-                                return Ok(ChannelResponse::Text(
-                                    "No pending file. Send a document first, then use !ingest."
-                                        .to_string(),
-                                ));
-                            }
-                        }
-                    }
-
-                    // --- File handling ---
-                    if let Some(slack_file) = file {
-                        let fname = slack_file.filename.clone();
-                        let caption = text.trim().to_lowercase();
-                        if caption.contains("ingest") {
-                            return crate::ingest::run_ingest(
-                                &state,
-                                &data_dir,
-                                &caption,
-                                &fname,
-                                &slack_file.data,
-                            )
-                            .await;
-                        } else if opencrust_media::is_supported_for_ingest(&fname) {
-                            state.set_pending_file(
-                                &session_id,
-                                crate::state::PendingFile {
-                                    filename: fname.clone(),
-                                    data: slack_file.data,
-                                    received_at: std::time::Instant::now(),
-                                },
-                            );
-                            return Ok(ChannelResponse::Text(format!(
-                                "Received {fname}. Use !ingest to store it for future reference."
-                            )));
-                        }
-                    }
-
-                    // Groups already filtered by channel handler - skip auth for groups
-                    if !is_group {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy, &mut list, &pairing, &user_id, &user_name, &text, "slack",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
-                    }
-
-                    state.check_user_rate_limit(&user_id, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &user_id, &guardrails_config)
-                        .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
+                    let session_id = pipeline.session_id(
+                        slack_session_base(&channel_id, thread_ts.as_deref()),
+                        &user_id,
+                        is_group,
                     );
-                    if inject_user_name_slack {
-                        state.agents.set_session_user_name(&session_id, &user_name);
-                    }
-
-                    let text = opencrust_security::InputValidator::sanitize(&text);
-                    if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                        return Err(
-                            "input rejected: potential prompt injection detected".to_string()
-                        );
+                    let mut metadata = serde_json::json!({"slack_channel_id": channel_id});
+                    if let Some(ts) = thread_ts {
+                        metadata["slack_thread_ts"] = serde_json::Value::String(ts);
                     }
-                    if opencrust_security::InputValidator::exceeds_length(&text, max_input_chars) {
-                        return Err(format!(
-                            "input rejected: message exceeds {max_input_chars} character limit"
-                        ));
+                    let msg = InboundMessage::text(session_id, user_id, user_name, text)
+                        .with_group(is_group)
+                        .with_metadata(metadata)
+                        .with_delta_tx(delta_tx);
+
+                    // --- Commands ---
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
 
-                    state
-                        .hydrate_session_history(&session_id, Some("slack"), Some(&user_id))
-                        .await;
-                    let history: Vec<ChatMessage> = state.session_history(&session_id);
-                    let continuity_key = state.continuity_key(Some(&user_id));
-                    let summary = state.session_summary(&session_id);
-
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
+                    // --- File handling ---
+                    if let Some(slack_file) = file
+                        && let Some(result) = pipeline
+                            .receive_file(
+                                &msg.session_id,
+                                slack_file.filename_or_default(),
+                                slack_file.data,
+                                &msg.text,
                             )
                             .await
-                    }
-                    .map_err(|e| e.to_string())?;
-
-                    if let Some(s) = new_summary {
-                        state.update_session_summary(&session_id, &s);
-                    }
-
-                    let response = opencrust_security::InputValidator::truncate_output(
-                        &response,
-                        max_output_chars,
-                    );
-                    state
-                        .persist_turn(
-                            &session_id,
-                            Some("slack"),
-                            Some(&user_id),
-                            &text,
-                            &response,
-                            Some(serde_json::json!({"slack_channel_id": channel_id})),
-                        )
-                        .await;
-
-                    if let Some((input, output, provider, model)) =
-                        state.agents.take_session_usage(&session_id)
                     {
-                        state
-                            .persist_usage(&session_id, &provider, &model, input, output)
-                            .await;
+                        return result;
                     }
 
-                    // Slack has no native audio API — always return text.
-                    // TTS is not attempted to avoid wasted synthesis.
-                    let _ = (auto_reply_voice_slack, tts, tts_max_chars);
-                    Ok(ChannelResponse::Text(response))
+                    pipeline.handle(msg).await
                 })
             },
         );
 
-        let channel = SlackChannel::with_group_filter(
+        let mut channel = SlackChannel::with_group_filter(
             bot_token,
             app_token,
             on_message,
             group_filter,
             bot_user_id,
         )
-        .with_name(name.clone());
+        .with_name(name.clone())
+        .with_reaction_feedback(on_reaction)
+        .with_read_receipts(read_receipts(&channel_config.settings).unwrap_or(false));
+        if let Some(on_edit) = on_edit {
+            channel = channel.with_edit_regeneration(on_edit);
+        }
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
         channels.push(Box::new(channel) as Box<dyn opencrust_channels::Channel>);
         info!("configured slack channel: {name}");
     }
@@ -2252,6 +2000,21 @@ pub fn build_slack_channels(
     channels
 }
 
+/// The channel's `read_receipts` setting: acknowledge messages when the bot
+/// starts working on them. Each channel picks its own default.
+fn read_receipts(settings: &HashMap<String, serde_json::Value>) -> Option<bool> {
+    settings.get("read_receipts").and_then(|v| v.as_bool())
+}
+
+/// Session key for a Slack conversation. Each thread is its own conversation,
+/// so parallel threads in one channel do not share history.
+fn slack_session_base(channel_id: &str, thread_ts: Option<&str>) -> String {
+    match thread_ts {
+        Some(ts) => format!("slack-{channel_id}-{ts}"),
+        None => format!("slack-{channel_id}"),
+    }
+}
+
 /// Build WhatsApp channels from config. Must be called after state is
 /// wrapped in `Arc` so the message callback can capture a `SharedState`.
 pub fn build_whatsapp_channels(
@@ -2292,9 +2055,10 @@ pub fn build_whatsapp_channels(
                      will try as whatsapp-web"
                 );
             } else {
-                warn!(
-                    "whatsapp channel '{name}' has no access_token, skipping \
-                     (set access_token in config or WHATSAPP_ACCESS_TOKEN env var)"
+                state.startup.fail(
+                    "channel",
+                    name,
+                    "no access_token (set access_token in config or WHATSAPP_ACCESS_TOKEN env var)",
                 );
             }
             continue;
@@ -2308,7 +2072,7 @@ pub fn build_whatsapp_channels(
             .to_string();
 
         if phone_number_id.is_empty() {
-            warn!("whatsapp channel '{name}' has no phone_number_id, skipping");
+            state.startup.fail("channel", name, "no phone_number_id");
             continue;
         }
 
@@ -2320,210 +2084,129 @@ pub fn build_whatsapp_channels(
             .or_else(|| std::env::var("WHATSAPP_VERIFY_TOKEN").ok())
             .unwrap_or_else(|| "opencrust-verify".to_string());
 
-        let allowlist = Arc::clone(&state.allowlist);
-
-        let pairing = Arc::clone(&state.pairing);
+        let app_secret = channel_config
+            .settings
+            .get("app_secret")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .or_else(|| resolve_api_key(None, "WHATSAPP_APP_SECRET", "WHATSAPP_APP_SECRET"));
 
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let Some(app_secret) = app_secret else {
+            state.startup.fail("channel", name, "no app_secret");
+            continue;
+        };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
-        let data_dir_wa = config
-            .data_dir
-            .clone()
-            .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
-        let inject_user_name_wa = channel_config
-            .settings
-            .get("inject_user_name")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let pipeline = Arc::new(
+            MessagePipeline::new("whatsapp", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config)
+                .with_voice_replies(config)
+                .with_bare_commands(),
+        );
+        let stt_base_url: Option<String> = config.voice.stt_base_url.clone();
+        let stt_model: Option<String> = config.voice.stt_model.clone();
+        let stt_local_model: Option<String> = config.voice.stt_local_model.clone();
+        let stt_api_key: Option<String> = resolve_api_key(
+            config.voice.api_key.as_deref(),
+            "VOICE_API_KEY",
+            "VOICE_API_KEY",
+        );
 
         let on_message: WhatsAppOnMessageFn = Arc::new(
             move |from_number: String,
                   user_name: String,
                   text: String,
                   _is_group: bool,
-                  file: Option<opencrust_channels::whatsapp::WhatsAppFile>,
+                  file: Option<opencrust_channels::MediaAttachment>,
                   delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
-                let data_dir = data_dir_wa.clone();
+                let pipeline = Arc::clone(&pipeline);
+                let stt_base_url = stt_base_url.clone();
+                let stt_model = stt_model.clone();
+                let stt_local_model = stt_local_model.clone();
+                let stt_api_key = stt_api_key.clone();
                 Box::pin(async move {
                     let session_id = format!("whatsapp-{from_number}");
 
-                    // WhatsApp Business is DM-only, always check auth
-                    {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy,
-                            &mut list,
-                            &pairing,
-                            &from_number,
-                            &user_name,
-                            &text,
-                            "whatsapp",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
-                    }
+                    let msg =
+                        InboundMessage::text(session_id, from_number.clone(), user_name, text)
+                            .with_metadata(serde_json::json!({"whatsapp_from": from_number}))
+                            .with_delta_tx(delta_tx);
 
-                    // --- /ingest command ---
-                    if let Some(cmd) = text.strip_prefix('!').or_else(|| text.strip_prefix('/')) {
-                        let cmd_word = cmd.split_whitespace().next().unwrap_or("");
-                        if cmd_word == "ingest" {
-                            if let Some(pending) = state.take_pending_file(&session_id) {
-                                return crate::ingest::run_ingest(
-                                    &state,
-                                    &data_dir,
-                                    &text,
-                                    &pending.filename,
-                                    &pending.data,
-                                )
-                                .await;
-                            } else {
-                                return Ok(ChannelResponse::Text(
-                                    "No pending file. Send a document first, then use !ingest."
-                                        .to_string(),
-                                ));
-                            }
-                        }
+                    // --- Commands ---
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
 
-                    // --- File handling ---
-                    if let Some(wa_file) = file {
-                        let fname = wa_file.filename.clone();
-                        let caption = text.trim().to_lowercase();
-                        if caption.contains("ingest") {
-                            return crate::ingest::run_ingest(
-                                &state,
-                                &data_dir,
-                                &caption,
-                                &fname,
-                                &wa_file.data,
-                            )
-                            .await;
-                        } else if opencrust_media::is_supported_for_ingest(&fname) {
-                            state.set_pending_file(
-                                &session_id,
-                                crate::state::PendingFile {
-                                    filename: fname.clone(),
-                                    data: wa_file.data,
-                                    received_at: std::time::Instant::now(),
-                                },
-                            );
-                            return Ok(ChannelResponse::Text(format!(
-                                "Received {fname}. Use !ingest to store it for future reference."
-                            )));
-                        }
+                    // WhatsApp Business is DM-only, always check auth
+                    if let Some(reply) = pipeline.admit(&msg).await? {
+                        return Ok(reply);
                     }
 
-                    state.check_user_rate_limit(&from_number, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &from_number, &guardrails_config)
+                    // --- Voice notes: answer the transcript ---
+                    if let Some(voice) = file.as_ref().filter(|f| f.kind == MediaKind::Voice) {
+                        let transcript = transcribe_voice(
+                            voice,
+                            stt_base_url.as_deref(),
+                            stt_model.as_deref(),
+                            stt_local_model.as_deref(),
+                            stt_api_key.as_deref(),
+                        )
                         .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
-                    );
-                    if inject_user_name_wa {
-                        state.agents.set_session_user_name(&session_id, &user_name);
+                        info!("whatsapp voice transcribed: {} chars", transcript.len());
+                        let msg = InboundMessage {
+                            text: transcript,
+                            ..msg
+                        };
+                        return pipeline.run_turn(msg.with_voice_reply(true)).await;
                     }
 
-                    let text = opencrust_security::InputValidator::sanitize(&text);
-                    if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                        return Err(
-                            "input rejected: potential prompt injection detected".to_string()
-                        );
-                    }
-                    if opencrust_security::InputValidator::exceeds_length(&text, max_input_chars) {
-                        return Err(format!(
-                            "input rejected: message exceeds {max_input_chars} character limit"
-                        ));
-                    }
-
-                    state
-                        .hydrate_session_history(&session_id, Some("whatsapp"), Some(&from_number))
-                        .await;
-                    let history: Vec<ChatMessage> = state.session_history(&session_id);
-                    let continuity_key = state.continuity_key(Some(&from_number));
-                    let summary = state.session_summary(&session_id);
-
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&from_number),
-                            )
-                            .await
-                    } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&from_number),
-                            )
+                    // --- File handling ---
+                    if let Some(wa_file) = file {
+                        let fname = wa_file.filename_or_default();
+                        let mime = wa_file.mime_type.clone().unwrap_or_else(|| {
+                            opencrust_media::detect_mime_type(std::path::Path::new(&fname))
+                                .to_string()
+                        });
+                        if pipeline.accepts_native_document(&msg.text, &mime) {
+                            let text =
+                                crate::ingest::extract_text_from_bytes(&fname, &wa_file.data).ok();
+                            return pipeline
+                                .run_turn(msg.with_document(&mime, &wa_file.data, text))
+                                .await;
+                        }
+                        if let Some(result) = pipeline
+                            .receive_file(&msg.session_id, fname, wa_file.data, &msg.text)
                             .await
-                    }
-                    .map_err(|e| e.to_string())?;
-
-                    if let Some(s) = new_summary {
-                        state.update_session_summary(&session_id, &s);
-                    }
-
-                    let response = opencrust_security::InputValidator::truncate_output(
-                        &response,
-                        max_output_chars,
-                    );
-                    state
-                        .persist_turn(
-                            &session_id,
-                            Some("whatsapp"),
-                            Some(&from_number),
-                            &text,
-                            &response,
-                            Some(serde_json::json!({"whatsapp_from": from_number})),
-                        )
-                        .await;
-
-                    if let Some((input, output, provider, model)) =
-                        state.agents.take_session_usage(&session_id)
-                    {
-                        state
-                            .persist_usage(&session_id, &provider, &model, input, output)
-                            .await;
+                        {
+                            return result;
+                        }
                     }
 
-                    Ok(ChannelResponse::Text(response))
+                    pipeline.run_turn(msg).await
                 })
             },
         );
 
-        let channel = WhatsAppChannel::new(access_token, phone_number_id, verify_token, on_message)
-            .with_name(name.clone());
+        let mut channel = WhatsAppChannel::new(
+            access_token,
+            phone_number_id,
+            verify_token,
+            app_secret,
+            on_message,
+        )
+        .with_name(name.clone())
+        .with_read_receipts(read_receipts(&channel_config.settings).unwrap_or(true));
+        if let Some(template) = whatsapp_template(&channel_config.settings) {
+            channel = channel.with_template(template);
+        }
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
         channels.push(Arc::new(channel));
         info!("configured whatsapp channel: {name}");
     }
@@ -2531,6 +2214,26 @@ pub fn build_whatsapp_channels(
     channels
 }
 
+/// Approved template for proactive WhatsApp messages, from the `template`
+/// (and optional `template_language`, default `en_US`) channel settings.
+fn whatsapp_template(
+    settings: &HashMap<String, serde_json::Value>,
+) -> Option<opencrust_channels::WhatsAppTemplate> {
+    let name = settings
+        .get("template")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())?;
+    let language = settings
+        .get("template_language")
+        .and_then(|v| v.as_str())
+        .unwrap_or("en_US");
+    Some(opencrust_channels::WhatsAppTemplate {
+        name: name.to_string(),
+        language: language.to_string(),
+    })
+}
+
 /// Build WhatsApp Web channels from config (sidecar-driven, QR code pairing).
 ///
 /// Picks up channels where `mode == "web"` or where no `access_token` is set
@@ -2567,11 +2270,10 @@ pub fn build_whatsapp_web_channels(
             continue;
         }
 
-        let allowlist = Arc::clone(&state.allowlist);
-
-        let pairing = Arc::clone(&state.pairing);
-
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         // Warn if group_policy: mention is set - WhatsApp has no mention detection
         if channel_config
@@ -2591,151 +2293,388 @@ pub fn build_whatsapp_web_channels(
             Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
         };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
-
-        let inject_user_name_waweb = channel_config
-            .settings
-            .get("inject_user_name")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let pipeline = Arc::new(
+            MessagePipeline::new("whatsapp-web", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config)
+                .with_bare_commands(),
+        );
 
         let on_message: WhatsAppOnMessageFn = Arc::new(
             move |from_jid: String,
                   user_name: String,
                   text: String,
                   is_group: bool,
-                  _file: Option<opencrust_channels::whatsapp::WhatsAppFile>,
+                  _file: Option<opencrust_channels::MediaAttachment>,
                   delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
+                let pipeline = Arc::clone(&pipeline);
                 Box::pin(async move {
-                    // Groups already filtered by channel handler - skip auth for groups
-                    if !is_group {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy,
-                            &mut list,
-                            &pairing,
-                            &from_jid,
-                            &user_name,
-                            &text,
-                            "whatsapp-web",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
+                    let session_id = format!("whatsapp-web-{from_jid}");
+                    let msg = InboundMessage::text(session_id, from_jid.clone(), user_name, text)
+                        .with_group(is_group)
+                        .with_metadata(serde_json::json!({"whatsapp_from": from_jid}))
+                        .with_delta_tx(delta_tx);
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
+                    pipeline.handle(msg).await
+                })
+            },
+        );
 
-                    let session_id = format!("whatsapp-web-{from_jid}");
+        let mut channel =
+            WhatsAppWebChannel::with_group_filter(on_message, group_filter).with_name(name.clone());
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
+        channels.push(channel);
+        info!("configured whatsapp-web channel: {name}");
+    }
 
-                    state.check_user_rate_limit(&from_jid, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &from_jid, &guardrails_config)
-                        .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
-                    );
-                    if inject_user_name_waweb {
-                        state.agents.set_session_user_name(&session_id, &user_name);
-                    }
+    channels
+}
 
-                    let text = opencrust_security::InputValidator::sanitize(&text);
-                    if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                        return Err(
-                            "input rejected: potential prompt injection detected".to_string()
-                        );
-                    }
-                    if opencrust_security::InputValidator::exceeds_length(&text, max_input_chars) {
-                        return Err(format!(
-                            "input rejected: message exceeds {max_input_chars} character limit"
-                        ));
+/// Build Signal channels from config. Each one talks to a signal-cli
+/// JSON-RPC daemon (`signal-cli -a <account> daemon --tcp`).
+///
+/// Must be called after state is wrapped in `Arc` so the message callback can capture a `SharedState`.
+pub fn build_signal_channels(config: &AppConfig, state: &SharedState) -> Vec<SignalChannel> {
+    let mut channels = Vec::new();
+
+    for (name, channel_config) in &config.channels {
+        if channel_config.channel_type != "signal" || channel_config.enabled == Some(false) {
+            continue;
+        }
+
+        let account = channel_config
+            .settings
+            .get("account")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .or_else(|| resolve_api_key(None, "SIGNAL_ACCOUNT", "SIGNAL_ACCOUNT"));
+
+        let Some(account) = account else {
+            state.startup.fail(
+                "channel",
+                name,
+                "no account (set account in config or SIGNAL_ACCOUNT env var)",
+            );
+            continue;
+        };
+
+        let rpc_addr = channel_config
+            .settings
+            .get("rpc_addr")
+            .and_then(|v| v.as_str())
+            .unwrap_or(opencrust_channels::signal::DEFAULT_RPC_ADDR)
+            .to_string();
+
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
+
+        let group_filter: SignalGroupFilter = {
+            let policy = Arc::clone(&policy);
+            Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
+        };
+
+        let pipeline = Arc::new(
+            MessagePipeline::new("signal", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config)
+                .with_bare_commands(),
+        );
+
+        let on_message: SignalOnMessageFn = Arc::new(
+            move |source: String, source_name: String, text: String, group_id: Option<String>| {
+                let pipeline = Arc::clone(&pipeline);
+                Box::pin(async move {
+                    let is_group = group_id.is_some();
+                    let (base, metadata) = match &group_id {
+                        Some(group_id) => (
+                            format!("signal-group-{group_id}"),
+                            serde_json::json!({"signal_group_id": group_id}),
+                        ),
+                        None => (
+                            format!("signal-{source}"),
+                            serde_json::json!({"signal_recipient": source}),
+                        ),
+                    };
+                    let session_id = pipeline.session_id(base, &source, is_group);
+                    let msg = InboundMessage::text(session_id, source, source_name, text)
+                        .with_group(is_group)
+                        .with_metadata(metadata);
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
+                    pipeline.handle(msg).await
+                })
+            },
+        );
 
-                    state
-                        .hydrate_session_history(&session_id, Some("whatsapp-web"), Some(&from_jid))
-                        .await;
-                    let history: Vec<ChatMessage> = state.session_history(&session_id);
-                    let continuity_key = state.continuity_key(Some(&from_jid));
-                    let summary = state.session_summary(&session_id);
+        let mut channel =
+            SignalChannel::with_group_filter(account, rpc_addr, on_message, group_filter)
+                .with_name(name.clone());
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
+        channels.push(channel);
+        info!("configured signal channel: {name}");
+    }
 
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&from_jid),
-                            )
-                            .await
+    channels
+}
+
+/// Build Microsoft Teams channels from config. Each one receives Bot
+/// Framework activities on `POST /webhooks/teams`.
+///
+/// Must be called after state is wrapped in `Arc` so the message callback can capture a `SharedState`.
+pub fn build_teams_channels(config: &AppConfig, state: &SharedState) -> Vec<TeamsChannel> {
+    let mut channels = Vec::new();
+
+    for (name, channel_config) in &config.channels {
+        if channel_config.channel_type != "teams" || channel_config.enabled == Some(false) {
+            continue;
+        }
+
+        let setting = |key: &str| {
+            channel_config
+                .settings
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+
+        let Some(app_id) =
+            setting("app_id").or_else(|| resolve_api_key(None, "TEAMS_APP_ID", "TEAMS_APP_ID"))
+        else {
+            state.startup.fail(
+                "channel",
+                name,
+                "no app_id (set app_id in config or TEAMS_APP_ID env var)",
+            );
+            continue;
+        };
+
+        let Some(app_password) = setting("app_password")
+            .or_else(|| resolve_api_key(None, "TEAMS_APP_PASSWORD", "TEAMS_APP_PASSWORD"))
+        else {
+            state.startup.fail(
+                "channel",
+                name,
+                "no app_password (set app_password in config or TEAMS_APP_PASSWORD env var)",
+            );
+            continue;
+        };
+
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
+
+        let group_filter: TeamsGroupFilter = {
+            let policy = Arc::clone(&policy);
+            Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
+        };
+
+        let pipeline = Arc::new(
+            MessagePipeline::new("teams", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config),
+        );
+
+        let on_message: TeamsOnMessageFn = Arc::new(
+            move |user_id: String,
+                  user_name: String,
+                  conversation_id: String,
+                  service_url: String,
+                  text: String,
+                  is_group: bool| {
+                let pipeline = Arc::clone(&pipeline);
+                Box::pin(async move {
+                    let base = if is_group {
+                        format!("teams-{conversation_id}")
                     } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&from_jid),
-                            )
-                            .await
+                        format!("teams-{user_id}")
+                    };
+                    let session_id = pipeline.session_id(base, &user_id, is_group);
+                    let msg = InboundMessage::text(session_id, user_id.clone(), user_name, text)
+                        .with_group(is_group)
+                        .with_metadata(serde_json::json!({
+                            "teams_user_id": user_id,
+                            "teams_conversation_id": conversation_id,
+                            "teams_service_url": service_url,
+                        }));
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
                     }
-                    .map_err(|e| e.to_string())?;
+                    pipeline.handle(msg).await
+                })
+            },
+        );
 
-                    if let Some(s) = new_summary {
-                        state.update_session_summary(&session_id, &s);
-                    }
+        let mut channel = TeamsChannel::with_group_filter(
+            app_id,
+            app_password,
+            setting("tenant_id"),
+            on_message,
+            group_filter,
+        )
+        .with_name(name.clone());
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
+        channels.push(channel);
+        info!("configured teams channel: {name}");
+    }
 
-                    let response = opencrust_security::InputValidator::truncate_output(
-                        &response,
-                        max_output_chars,
-                    );
-                    state
-                        .persist_turn(
-                            &session_id,
-                            Some("whatsapp-web"),
-                            Some(&from_jid),
-                            &text,
-                            &response,
-                            Some(serde_json::json!({"whatsapp_from": from_jid})),
-                        )
-                        .await;
+    channels
+}
 
-                    if let Some((input, output, provider, model)) =
-                        state.agents.take_session_usage(&session_id)
-                    {
-                        state
-                            .persist_usage(&session_id, &provider, &model, input, output)
-                            .await;
-                    }
+/// Build web chat channels from config. Each one is served by the gateway
+/// itself under `/webchat`.
+///
+/// Must be called after state is wrapped in `Arc` so the message callback can capture a `SharedState`.
+pub fn build_webchat_channels(config: &AppConfig, state: &SharedState) -> Vec<WebChatChannel> {
+    let mut channels = Vec::new();
 
-                    Ok(ChannelResponse::Text(response))
+    for (name, channel_config) in &config.channels {
+        if channel_config.channel_type != "webchat" || channel_config.enabled == Some(false) {
+            continue;
+        }
+
+        let allowed_origins: Vec<String> = channel_config
+            .settings
+            .get("allowed_origins")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
+
+        let pipeline = Arc::new(
+            MessagePipeline::new("webchat", state, config, policy)
+                .with_name(name)
+                .with_channel_settings(channel_config),
+        );
+
+        let on_message: WebChatOnMessageFn = Arc::new(
+            move |visitor_id: String,
+                  text: String,
+                  delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
+                let pipeline = Arc::clone(&pipeline);
+                Box::pin(async move {
+                    let session_id = format!("webchat-{visitor_id}");
+                    let msg = InboundMessage::text(
+                        session_id,
+                        visitor_id.clone(),
+                        "Visitor".to_string(),
+                        text,
+                    )
+                    .with_metadata(serde_json::json!({"webchat_visitor_id": visitor_id}))
+                    .with_delta_tx(delta_tx);
+                    if let Some(result) = pipeline.handle_command(&msg).await {
+                        return result;
+                    }
+                    pipeline.handle(msg).await
                 })
             },
         );
 
-        let channel =
-            WhatsAppWebChannel::with_group_filter(on_message, group_filter).with_name(name.clone());
+        let mut channel = WebChatChannel::new(on_message)
+            .with_name(name.clone())
+            .with_allowed_origins(allowed_origins);
+        if let Some(title) = channel_config
+            .settings
+            .get("title")
+            .and_then(|v| v.as_str())
+        {
+            channel = channel.with_title(title.to_string());
+        }
         channels.push(channel);
-        info!("configured whatsapp-web channel: {name}");
+        info!("configured webchat channel: {name}");
+    }
+
+    channels
+}
+
+/// Build channels served by external connector processes over `/connector`.
+/// Each needs a `token` the connector authenticates with; `channel_type`
+/// (default: the config key) is the type the connector must declare.
+pub fn build_connector_channels(config: &AppConfig, state: &SharedState) -> Vec<RemoteChannel> {
+    let mut channels = Vec::new();
+
+    for (name, channel_config) in &config.channels {
+        if channel_config.channel_type != "connector" || channel_config.enabled == Some(false) {
+            continue;
+        }
+
+        let Some(token) = channel_config
+            .settings
+            .get("token")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+        else {
+            state.startup.fail("channel", name, "no token");
+            continue;
+        };
+
+        let channel_type = channel_config
+            .settings
+            .get("channel_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or(name)
+            .to_string();
+
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
+
+        let pipeline = Arc::new(
+            MessagePipeline::new("connector", state, config, policy)
+                .with_name(name)
+                .with_channel_settings(channel_config),
+        );
+
+        let session_prefix = name.clone();
+        let on_message: RemoteOnMessageFn = Arc::new(move |message: opencrust_common::Message| {
+            let pipeline = Arc::clone(&pipeline);
+            let session_prefix = session_prefix.clone();
+            Box::pin(async move {
+                let Some(text) = opencrust_channels::connector::message_text(&message) else {
+                    return Err("unsupported message content".to_string());
+                };
+                let user_id = message.user_id.to_string();
+                let user_name = message
+                    .metadata
+                    .get("user_name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&user_id)
+                    .to_string();
+                // Keep connector sessions apart from other channels' sessions.
+                let session_id = format!("{session_prefix}-{}", message.session_id);
+                let msg = InboundMessage::text(session_id, user_id, user_name, text)
+                    .with_metadata(message.metadata);
+                if let Some(result) = pipeline.handle_command(&msg).await {
+                    return result;
+                }
+                pipeline.handle(msg).await
+            })
+        });
+
+        channels.push(RemoteChannel::new(channel_type, token, on_message).with_name(name.clone()));
+        info!("configured connector channel: {name}");
     }
 
     channels
@@ -2762,11 +2701,10 @@ pub fn build_imessage_channels(
             .and_then(|v| v.as_u64())
             .unwrap_or(2);
 
-        let allowlist = Arc::clone(&state.allowlist);
-
-        let pairing = Arc::clone(&state.pairing);
-
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         // Warn if group_policy: mention is set - iMessage has no mention concept
         if channel_config
@@ -2786,114 +2724,128 @@ pub fn build_imessage_channels(
             Arc::new(move |is_mentioned| policy.should_process_group(is_mentioned))
         };
 
-        let state_for_cb = Arc::clone(state);
-        let allowlist_for_cb = Arc::clone(&allowlist);
-        let pairing_for_cb = Arc::clone(&pairing);
-        let policy_for_cb = Arc::clone(&policy);
-        let max_input_chars = config.guardrails.max_input_chars;
-        let max_output_chars = config.guardrails.max_output_chars;
-        let rate_limit_config = Arc::new(config.gateway.rate_limit.clone());
-        let guardrails_config = Arc::new(config.guardrails.clone());
+        let pipeline = Arc::new(
+            MessagePipeline::new("imessage", state, config, Arc::clone(&policy))
+                .with_name(name)
+                .with_channel_settings(channel_config),
+        );
+        let stt_base_url: Option<String> = config.voice.stt_base_url.clone();
+        let stt_model: Option<String> = config.voice.stt_model.clone();
+        let stt_local_model: Option<String> = config.voice.stt_local_model.clone();
+        let stt_api_key: Option<String> = resolve_api_key(
+            config.voice.api_key.as_deref(),
+            "VOICE_API_KEY",
+            "VOICE_API_KEY",
+        );
 
         let on_message: IMessageOnMessageFn = Arc::new(
             move |session_key: String,
                   sender_id: String,
                   text: String,
                   is_group: bool,
+                  file: Option<MediaAttachment>,
                   _delta_tx: Option<tokio::sync::mpsc::Sender<String>>| {
-                let state = Arc::clone(&state_for_cb);
-                let allowlist = Arc::clone(&allowlist_for_cb);
-                let pairing = Arc::clone(&pairing_for_cb);
-                let policy = Arc::clone(&policy_for_cb);
-                let rate_limit_config = Arc::clone(&rate_limit_config);
-                let guardrails_config = Arc::clone(&guardrails_config);
+                let pipeline = Arc::clone(&pipeline);
+                let stt_base_url = stt_base_url.clone();
+                let stt_model = stt_model.clone();
+                let stt_local_model = stt_local_model.clone();
+                let stt_api_key = stt_api_key.clone();
                 Box::pin(async move {
-                    // Groups already filtered by channel handler - skip auth for groups
-                    if !is_group {
-                        let mut list = allowlist.lock().unwrap();
-                        match check_dm_auth(
-                            &policy, &mut list, &pairing, &sender_id, "", &text, "imessage",
-                        ) {
-                            Ok(None) => {}
-                            Ok(Some(welcome)) => return Ok(ChannelResponse::Text(welcome)),
-                            Err(e) => return Err(e),
-                        }
-                    }
-
                     // session_key is group_name for groups, sender handle for DMs
-                    let session_id = format!("imessage-{session_key}");
-
-                    state.check_user_rate_limit(&sender_id, &rate_limit_config)?;
-                    state
-                        .check_token_budget(&session_id, &sender_id, &guardrails_config)
-                        .await?;
-                    state.agents.set_session_tool_config(
-                        &session_id,
-                        guardrails_config.allowed_tools.clone(),
-                        guardrails_config.session_tool_call_budget,
+                    let session_id = pipeline.session_id(
+                        format!("imessage-{session_key}"),
+                        &sender_id,
+                        is_group,
                     );
+                    // Files the agent sends during the turn go back to the group.
+                    let metadata = if is_group {
+                        serde_json::json!({
+                            "imessage_sender": sender_id,
+                            "imessage_group": session_key,
+                        })
+                    } else {
+                        serde_json::json!({"imessage_sender": sender_id})
+                    };
+                    let msg = InboundMessage::text(session_id, sender_id.clone(), "", text)
+                        .with_group(is_group)
+                        .with_metadata(metadata);
 
-                    let text = opencrust_security::InputValidator::sanitize(&text);
-                    if opencrust_security::InputValidator::check_prompt_injection(&text) {
-                        return Err(
-                            "input rejected: potential prompt injection detected".to_string()
-                        );
-                    }
-                    if opencrust_security::InputValidator::exceeds_length(&text, max_input_chars) {
-                        return Err(format!(
-                            "input rejected: message exceeds {max_input_chars} character limit"
-                        ));
-                    }
-
-                    state
-                        .hydrate_session_history(&session_id, Some("imessage"), Some(&sender_id))
-                        .await;
-                    let history: Vec<opencrust_agents::ChatMessage> =
-                        state.session_history(&session_id);
-                    let continuity_key = state.continuity_key(Some(&sender_id));
-                    let summary = state.session_summary(&session_id);
-
-                    let (response, new_summary) = state
-                        .agents
-                        .process_message_with_context_and_summary(
-                            &session_id,
-                            &text,
-                            &history,
-                            summary.as_deref(),
-                            continuity_key.as_deref(),
-                            Some(&sender_id),
-                        )
-                        .await
-                        .map_err(|e| e.to_string())?;
+                    let Some(file) = file else {
+                        return pipeline.handle(msg).await;
+                    };
 
-                    if let Some(s) = new_summary {
-                        state.update_session_summary(&session_id, &s);
+                    let Some(msg) = pipeline.addressed(msg) else {
+                        return Err("__blocked__".to_string());
+                    };
+                    if let Some(reply) = pipeline.admit(&msg).await? {
+                        return Ok(reply);
                     }
 
-                    let response = opencrust_security::InputValidator::truncate_output(
-                        &response,
-                        max_output_chars,
-                    );
-                    state
-                        .persist_turn(
-                            &session_id,
-                            Some("imessage"),
-                            Some(&sender_id),
-                            &text,
-                            &response,
-                            Some(serde_json::json!({"imessage_sender": sender_id})),
-                        )
-                        .await;
-
-                    if let Some((input, output, provider, model)) =
-                        state.agents.take_session_usage(&session_id)
-                    {
-                        state
-                            .persist_usage(&session_id, &provider, &model, input, output)
-                            .await;
+                    match file.kind {
+                        // --- Audio messages: answer the transcript ---
+                        MediaKind::Voice => {
+                            let transcript = transcribe_voice(
+                                &file,
+                                stt_base_url.as_deref(),
+                                stt_model.as_deref(),
+                                stt_local_model.as_deref(),
+                                stt_api_key.as_deref(),
+                            )
+                            .await?;
+                            info!("imessage audio transcribed: {} chars", transcript.len());
+                            pipeline
+                                .run_turn(InboundMessage {
+                                    text: transcript,
+                                    ..msg
+                                })
+                                .await
+                        }
+                        // --- Photos go to the vision model ---
+                        MediaKind::Photo => {
+                            use base64::Engine;
+                            let b64 = base64::engine::general_purpose::STANDARD.encode(&file.data);
+                            let mime = file.mime_type.as_deref().unwrap_or("image/jpeg");
+                            let text = if msg.text.is_empty() {
+                                "Describe this image.".to_string()
+                            } else {
+                                msg.text.clone()
+                            };
+                            pipeline
+                                .run_turn(
+                                    InboundMessage { text, ..msg }
+                                        .with_image(format!("data:{mime};base64,{b64}")),
+                                )
+                                .await
+                        }
+                        // --- Documents and videos are handled as files ---
+                        MediaKind::Document | MediaKind::Video => {
+                            let fname = file.filename_or_default();
+                            let mime = file.mime_type.clone().unwrap_or_else(|| {
+                                opencrust_media::detect_mime_type(std::path::Path::new(&fname))
+                                    .to_string()
+                            });
+                            if pipeline.accepts_native_document(&msg.text, &mime) {
+                                let text =
+                                    crate::ingest::extract_text_from_bytes(&fname, &file.data).ok();
+                                return pipeline
+                                    .run_turn(msg.with_document(&mime, &file.data, text))
+                                    .await;
+                            }
+                            if let Some(result) = pipeline
+                                .receive_file(&msg.session_id, fname.clone(), file.data, &msg.text)
+                                .await
+                            {
+                                return result;
+                            }
+                            let text = format!("{}\n[Attachment: {fname}]", msg.text);
+                            pipeline
+                                .run_turn(InboundMessage {
+                                    text: text.trim_start().to_string(),
+                                    ..msg
+                                })
+                                .await
+                        }
                     }
-
-                    Ok(ChannelResponse::Text(response))
                 })
             },
         );
@@ -2937,7 +2889,9 @@ pub fn build_line_channels(
             });
 
         let Some(channel_access_token) = channel_access_token else {
-            warn!("line channel '{name}' has no channel_access_token, skipping");
+            state
+                .startup
+                .fail("channel", name, "no channel_access_token");
             continue;
         };
 
@@ -2949,9 +2903,14 @@ pub fn build_line_channels(
             .or_else(|| resolve_api_key(None, "LINE_CHANNEL_SECRET", "LINE_CHANNEL_SECRET"));
 
         let Some(channel_secret) = channel_secret else {
-            warn!("line channel '{name}' has no channel_secret, skipping");
+            state.startup.fail("channel", name, "no channel_secret");
             continue;
         };
+        let public_url = channel_config
+            .settings
+            .get("public_url")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
 
         let group_policy = channel_config
             .settings
@@ -2967,7 +2926,10 @@ pub fn build_line_channels(
 
         let allowlist = Arc::clone(&state.allowlist);
         let pairing = Arc::clone(&state.pairing);
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         let state_for_cb = Arc::clone(state);
         let allowlist_for_cb = Arc::clone(&allowlist);
@@ -2988,6 +2950,10 @@ pub fn build_line_channels(
             .clone()
             .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
 
+        let provider_line = channel_config.provider.clone();
+        let model_line = channel_config.model.clone();
+
+        let channel_name = name.clone();
         let on_message: LineOnMessageFn = Arc::new(
             move |user_id: String,
                   context_id: String,
@@ -3003,7 +2969,10 @@ pub fn build_line_channels(
                 let guardrails_config = Arc::clone(&guardrails_config);
                 let tts = tts_provider_line.clone();
                 let tts_max_chars = tts_max_chars_line;
+                let provider = provider_line.clone();
+                let model = model_line.clone();
                 let data_dir = data_dir_line.clone();
+                let channel_name = channel_name.clone();
                 Box::pin(async move {
                     if !is_group {
                         // Owner-only commands handled before auth so the owner can
@@ -3153,6 +3122,9 @@ pub fn build_line_channels(
                         }
                     }
 
+                    if let Some(reply) = state.maintenance_reply(&channel_name) {
+                        return Ok(ChannelResponse::Text(reply));
+                    }
                     state.check_user_rate_limit(&user_id, &rate_limit_config)?;
                     state
                         .check_token_budget(&session_id, &user_id, &guardrails_config)
@@ -3182,33 +3154,22 @@ pub fn build_line_channels(
                     let continuity_key = state.continuity_key(Some(&user_id));
                     let summary = state.session_summary(&session_id);
 
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    }
-                    .map_err(|e| e.to_string())?;
+                    let (response, new_summary) = state
+                        .agents
+                        .process_message_with_provider_and_summary(
+                            &session_id,
+                            MessagePart::Text(text.clone()),
+                            &text,
+                            &history,
+                            delta_tx,
+                            summary.as_deref(),
+                            continuity_key.as_deref(),
+                            Some(&user_id),
+                            provider.as_deref(),
+                            model.as_deref(),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
 
                     if let Some(s) = new_summary {
                         state.update_session_summary(&session_id, &s);
@@ -3275,30 +3236,13 @@ pub fn build_line_channels(
                 .unwrap_or("default");
             let embed_config = config.embeddings.get(embed_provider_name);
 
-            let embed_provider: Option<Arc<dyn opencrust_agents::EmbeddingProvider>> =
-                match embed_config.map(|c| c.provider.as_str()) {
-                    Some("cohere") => {
-                        let api_key = resolve_api_key(
-                            embed_config.and_then(|c| c.api_key.as_deref()),
-                            "COHERE_API_KEY",
-                            "COHERE_API_KEY",
-                        );
-                        api_key.map(|key| {
-                            Arc::new(CohereEmbeddingProvider::new(
-                                key,
-                                embed_config.and_then(|c| c.model.clone()),
-                                embed_config.and_then(|c| c.base_url.clone()),
-                            ))
-                                as Arc<dyn opencrust_agents::EmbeddingProvider>
-                        })
-                    }
-                    _ => {
-                        warn!(
-                            "line channel '{name}': group_rag_enabled=true but no valid embedding_provider configured, skipping RAG"
-                        );
-                        None
-                    }
-                };
+            let embed_provider =
+                embed_config.and_then(|c| build_embedding_provider(embed_provider_name, c));
+            if embed_provider.is_none() {
+                warn!(
+                    "line channel '{name}': group_rag_enabled=true but no valid embedding_provider configured, skipping RAG"
+                );
+            }
 
             if let Some(provider) = embed_provider {
                 let rag_top_k = channel_config
@@ -3508,7 +3452,7 @@ pub fn build_line_channels(
                             },
                         );
 
-                        let channel = LineChannel::with_group_filter(
+                        let mut channel = LineChannel::with_group_filter(
                             channel_access_token,
                             channel_secret,
                             rag_on_message,
@@ -3516,6 +3460,12 @@ pub fn build_line_channels(
                         )
                         .with_group_observe(observe_fn)
                         .with_name(name.clone());
+                        if let Some(seen) = state.inbound_dedup(name) {
+                            channel = channel.with_dedup(seen);
+                        }
+                        if let Some(public_url) = &public_url {
+                            channel = channel.with_public_url(public_url.clone());
+                        }
                         channels.push(channel);
                         info!("configured line channel: {name}");
                         continue;
@@ -3529,13 +3479,19 @@ pub fn build_line_channels(
             }
         }
 
-        let channel = LineChannel::with_group_filter(
+        let mut channel = LineChannel::with_group_filter(
             channel_access_token,
             channel_secret,
             on_message,
             group_filter,
         )
         .with_name(name.clone());
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
+        if let Some(public_url) = public_url {
+            channel = channel.with_public_url(public_url);
+        }
         channels.push(channel);
         info!("configured line channel: {name}");
     }
@@ -3566,7 +3522,7 @@ pub fn build_wechat_channels(
             .or_else(|| resolve_api_key(None, "WECHAT_APPID", "WECHAT_APPID"));
 
         let Some(appid) = appid else {
-            warn!("wechat channel '{name}' has no appid, skipping");
+            state.startup.fail("channel", name, "no appid");
             continue;
         };
 
@@ -3578,7 +3534,7 @@ pub fn build_wechat_channels(
             .or_else(|| resolve_api_key(None, "WECHAT_SECRET", "WECHAT_SECRET"));
 
         let Some(secret) = secret else {
-            warn!("wechat channel '{name}' has no secret, skipping");
+            state.startup.fail("channel", name, "no secret");
             continue;
         };
 
@@ -3590,7 +3546,7 @@ pub fn build_wechat_channels(
             .or_else(|| resolve_api_key(None, "WECHAT_TOKEN", "WECHAT_TOKEN"));
 
         let Some(token) = token else {
-            warn!("wechat channel '{name}' has no webhook token, skipping");
+            state.startup.fail("channel", name, "no webhook token");
             continue;
         };
 
@@ -3607,7 +3563,10 @@ pub fn build_wechat_channels(
 
         let allowlist = Arc::clone(&state.allowlist);
         let pairing = Arc::clone(&state.pairing);
-        let policy = Arc::new(ChannelPolicy::from_settings(&channel_config.settings));
+        let policy = Arc::new(
+            ChannelPolicy::from_settings(&channel_config.settings)
+                .with_default_messages(&channel_messages(config)),
+        );
 
         let state_for_cb = Arc::clone(state);
         let allowlist_for_cb = Arc::clone(&allowlist);
@@ -3628,6 +3587,10 @@ pub fn build_wechat_channels(
             .clone()
             .unwrap_or_else(|| opencrust_config::ConfigLoader::default_config_dir().join("data"));
 
+        let provider_wechat = channel_config.provider.clone();
+        let model_wechat = channel_config.model.clone();
+
+        let channel_name = name.clone();
         let on_message: WeChatOnMessageFn = Arc::new(
             move |user_id: String,
                   context_id: String,
@@ -3643,7 +3606,10 @@ pub fn build_wechat_channels(
                 let guardrails_config = Arc::clone(&guardrails_config);
                 let tts = tts_provider_wechat.clone();
                 let tts_max_chars = tts_max_chars_wechat;
+                let provider = provider_wechat.clone();
+                let model = model_wechat.clone();
                 let data_dir = data_dir_wechat.clone();
+                let channel_name = channel_name.clone();
                 Box::pin(async move {
                     {
                         let mut list = allowlist.lock().unwrap();
@@ -3701,6 +3667,9 @@ pub fn build_wechat_channels(
                         }
                     }
 
+                    if let Some(reply) = state.maintenance_reply(&channel_name) {
+                        return Ok(ChannelResponse::Text(reply));
+                    }
                     state.check_user_rate_limit(&user_id, &rate_limit_config)?;
                     state
                         .check_token_budget(&session_id, &user_id, &guardrails_config)
@@ -3730,33 +3699,22 @@ pub fn build_wechat_channels(
                     let continuity_key = state.continuity_key(Some(&user_id));
                     let summary = state.session_summary(&session_id);
 
-                    let (response, new_summary) = if let Some(delta_sender) = delta_tx {
-                        state
-                            .agents
-                            .process_message_streaming_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                delta_sender,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    } else {
-                        state
-                            .agents
-                            .process_message_with_context_and_summary(
-                                &session_id,
-                                &text,
-                                &history,
-                                summary.as_deref(),
-                                continuity_key.as_deref(),
-                                Some(&user_id),
-                            )
-                            .await
-                    }
-                    .map_err(|e| e.to_string())?;
+                    let (response, new_summary) = state
+                        .agents
+                        .process_message_with_provider_and_summary(
+                            &session_id,
+                            MessagePart::Text(text.clone()),
+                            &text,
+                            &history,
+                            delta_tx,
+                            summary.as_deref(),
+                            continuity_key.as_deref(),
+                            Some(&user_id),
+                            provider.as_deref(),
+                            model.as_deref(),
+                        )
+                        .await
+                        .map_err(|e| e.to_string())?;
 
                     if let Some(s) = new_summary {
                         state.update_session_summary(&session_id, &s);
@@ -3806,9 +3764,12 @@ pub fn build_wechat_channels(
             },
         );
 
-        let channel =
+        let mut channel =
             WeChatChannel::with_group_filter(appid, secret, token, on_message, group_filter)
                 .with_name(name.clone());
+        if let Some(seen) = state.inbound_dedup(name) {
+            channel = channel.with_dedup(seen);
+        }
         channels.push(Arc::new(channel));
         info!("configured wechat channel: {name}");
     }
@@ -3831,7 +3792,9 @@ pub fn build_mqtt_channels(config: &AppConfig, state: &SharedState) -> Vec<MqttC
         let mqtt_config = match MqttConfig::from_settings(name, &channel_config.settings) {
             Ok(c) => c,
             Err(e) => {
-                warn!("mqtt channel '{name}' config error: {e}, skipping");
+                state
+                    .startup
+                    .fail("channel", name, format!("config error: {e}"));
                 continue;
             }
         };
@@ -3843,6 +3806,8 @@ pub fn build_mqtt_channels(config: &AppConfig, state: &SharedState) -> Vec<MqttC
         let guardrails_config = Arc::new(config.guardrails.clone());
         let channel_name = name.clone();
         let _publish_topic = mqtt_config.publish_topic.clone();
+        let provider_mqtt = channel_config.provider.clone();
+        let model_mqtt = channel_config.model.clone();
 
         let on_message: MqttOnMessageFn = Arc::new(
             move |user_id: String,
@@ -3853,7 +3818,12 @@ pub fn build_mqtt_channels(config: &AppConfig, state: &SharedState) -> Vec<MqttC
                 let rate_limit_config = Arc::clone(&rate_limit_config);
                 let guardrails_config = Arc::clone(&guardrails_config);
                 let channel = channel_name.clone();
+                let provider = provider_mqtt.clone();
+                let model = model_mqtt.clone();
                 Box::pin(async move {
+                    if let Some(reply) = state.maintenance_reply(&channel) {
+                        return Ok(ChannelResponse::Text(reply));
+                    }
                     state.check_user_rate_limit(&user_id, &rate_limit_config)?;
                     state
                         .check_token_budget(&session_id, &user_id, &guardrails_config)
@@ -3885,13 +3855,17 @@ pub fn build_mqtt_channels(config: &AppConfig, state: &SharedState) -> Vec<MqttC
 
                     let (response, new_summary) = state
                         .agents
-                        .process_message_with_context_and_summary(
+                        .process_message_with_provider_and_summary(
                             &session_id,
+                            MessagePart::Text(text.clone()),
                             &text,
                             &history,
+                            None,
                             summary.as_deref(),
                             continuity_key.as_deref(),
                             Some(&user_id),
+                            provider.as_deref(),
+                            model.as_deref(),
                         )
                         .await
                         .map_err(|e| e.to_string())?;
@@ -3961,6 +3935,8 @@ mod tests {
                 model: None,
                 api_key: None,
                 base_url: None,
+                max_tokens: None,
+                supports_tools: true,
                 extra: std::collections::HashMap::new(),
             },
         );
@@ -3978,6 +3954,8 @@ mod tests {
                 model: Some("Qwen/Qwen2.5-7B-Instruct".to_string()),
                 api_key: None,
                 base_url: Some("http://localhost:8000".to_string()),
+                max_tokens: None,
+                supports_tools: true,
                 extra: std::collections::HashMap::new(),
             },
         );
@@ -3985,6 +3963,234 @@ mod tests {
         let _r = build_agent_runtime(&config).await;
     }
 
+    #[tokio::test]
+    async fn build_agent_runtime_openrouter_provider_registers() {
+        let mut config = AppConfig::default();
+        let mut extra = std::collections::HashMap::new();
+        extra.insert(
+            "app_name".to_string(),
+            serde_json::Value::String("Test Bot".to_string()),
+        );
+        config.llm.insert(
+            "openrouter".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "openrouter".to_string(),
+                model: Some("openai/gpt-4o-mini".to_string()),
+                api_key: Some("or-test-key".to_string()),
+                base_url: None,
+                max_tokens: None,
+                supports_tools: true,
+                extra,
+            },
+        );
+        let (runtime, _handle) = build_agent_runtime(&config).await;
+        let provider = runtime
+            .get_provider("openrouter")
+            .expect("provider registered");
+        assert_eq!(provider.configured_model(), Some("openai/gpt-4o-mini"));
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_groq_provider_defaults_model() {
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "groq".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "groq".to_string(),
+                model: None,
+                api_key: Some("gsk-test-key".to_string()),
+                base_url: None,
+                max_tokens: None,
+                supports_tools: true,
+                extra: std::collections::HashMap::new(),
+            },
+        );
+        let (runtime, _handle) = build_agent_runtime(&config).await;
+        let provider = runtime.get_provider("groq").expect("provider registered");
+        assert_eq!(provider.configured_model(), Some(GROQ_DEFAULT_MODEL));
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_grok_provider_defaults_model() {
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "grok".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "grok".to_string(),
+                model: None,
+                api_key: Some("xai-test-key".to_string()),
+                base_url: None,
+                max_tokens: None,
+                supports_tools: true,
+                extra: std::collections::HashMap::new(),
+            },
+        );
+        let (runtime, _handle) = build_agent_runtime(&config).await;
+        let provider = runtime.get_provider("grok").expect("provider registered");
+        assert_eq!(provider.configured_model(), Some(GROK_DEFAULT_MODEL));
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_azure_openai_needs_base_url_and_deployment() {
+        let azure = |base_url: Option<&str>, model: Option<&str>| {
+            let mut extra = std::collections::HashMap::new();
+            extra.insert("api_version".to_string(), serde_json::json!("2024-10-21"));
+            opencrust_config::LlmProviderConfig {
+                provider: "azure-openai".to_string(),
+                model: model.map(str::to_string),
+                api_key: Some("az-test-key".to_string()),
+                base_url: base_url.map(str::to_string),
+                max_tokens: None,
+                supports_tools: true,
+                extra,
+            }
+        };
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "azure".to_string(),
+            azure(Some("https://res.openai.azure.com"), Some("gpt-4o")),
+        );
+        config
+            .llm
+            .insert("no-url".to_string(), azure(None, Some("gpt-4o")));
+        config.llm.insert(
+            "no-deployment".to_string(),
+            azure(Some("https://res.openai.azure.com"), None),
+        );
+
+        let report = StartupReport::default();
+        let (runtime, _handle) = build_agent_runtime_with_report(&config, &report).await;
+        let provider = runtime.get_provider("azure").expect("provider registered");
+        assert_eq!(provider.configured_model(), Some("gpt-4o"));
+        assert!(runtime.get_provider("no-url").is_none());
+        assert!(runtime.get_provider("no-deployment").is_none());
+        assert_eq!(report.failures().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_llamacpp_rejects_unknown_mode() {
+        let llamacpp = |mode: &str| {
+            let mut extra = std::collections::HashMap::new();
+            extra.insert("mode".to_string(), serde_json::json!(mode));
+            extra.insert("slot".to_string(), serde_json::json!(0));
+            opencrust_config::LlmProviderConfig {
+                provider: "llamacpp".to_string(),
+                model: None,
+                api_key: None,
+                base_url: Some("http://localhost:8081".to_string()),
+                max_tokens: None,
+                supports_tools: true,
+                extra,
+            }
+        };
+        let mut config = AppConfig::default();
+        config
+            .llm
+            .insert("offline".to_string(), llamacpp("completion"));
+        config.llm.insert("typo".to_string(), llamacpp("instruct"));
+
+        let report = StartupReport::default();
+        let (runtime, _handle) = build_agent_runtime_with_report(&config, &report).await;
+        let provider = runtime
+            .get_provider("offline")
+            .expect("provider registered");
+        assert!(!provider.supports_tools());
+        assert!(runtime.get_provider("typo").is_none());
+        assert_eq!(report.failures().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_resolves_model_aliases() {
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "groq".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "groq".to_string(),
+                model: Some("llama-3.1-8b-instant".to_string()),
+                api_key: Some("gsk-test-key".to_string()),
+                base_url: None,
+                max_tokens: None,
+                supports_tools: true,
+                extra: std::collections::HashMap::new(),
+            },
+        );
+        config.models.insert(
+            "fast".to_string(),
+            ModelAliasConfig::Model("llama-3.1-8b-instant".to_string()),
+        );
+        config.models.insert(
+            "mini".to_string(),
+            ModelAliasConfig::Model("gpt-4o-mini".to_string()),
+        );
+        config.models.insert(
+            "smart".to_string(),
+            ModelAliasConfig::Target {
+                provider: Some("claude".to_string()),
+                model: None,
+            },
+        );
+
+        let report = StartupReport::default();
+        let (runtime, _handle) = build_agent_runtime_with_report(&config, &report).await;
+        let aliases = runtime.model_aliases();
+        assert_eq!(aliases["fast"].provider.as_deref(), Some("groq"));
+        assert_eq!(aliases["mini"].provider, None);
+        assert_eq!(aliases["mini"].model.as_deref(), Some("gpt-4o-mini"));
+        assert!(!aliases.contains_key("smart"));
+        assert_eq!(report.failures().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_gemini_provider_is_native() {
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "gemini".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "gemini".to_string(),
+                model: None,
+                api_key: Some("gm-test-key".to_string()),
+                base_url: None,
+                max_tokens: None,
+                supports_tools: true,
+                extra: Default::default(),
+            },
+        );
+        let (runtime, _handle) = build_agent_runtime(&config).await;
+        let provider = runtime.get_provider("gemini").expect("provider registered");
+        assert_eq!(provider.configured_model(), Some("gemini-2.5-flash"));
+        assert!(provider.supports_documents("text/csv"));
+
+        assert!(is_gemini_openai_url(Some(
+            "https://generativelanguage.googleapis.com/v1beta/openai/"
+        )));
+        assert!(!is_gemini_openai_url(Some(
+            "https://generativelanguage.googleapis.com/v1beta"
+        )));
+        assert!(!is_gemini_openai_url(None));
+    }
+
+    #[tokio::test]
+    async fn build_agent_runtime_legacy_gemini_keeps_default_model() {
+        let mut config = AppConfig::default();
+        config.llm.insert(
+            "gemini".to_string(),
+            opencrust_config::LlmProviderConfig {
+                provider: "gemini".to_string(),
+                model: None,
+                api_key: Some("gm-test-key".to_string()),
+                base_url: Some(
+                    "https://generativelanguage.googleapis.com/v1beta/openai/".to_string(),
+                ),
+                max_tokens: None,
+                supports_tools: true,
+                extra: Default::default(),
+            },
+        );
+        let (runtime, _handle) = build_agent_runtime(&config).await;
+        let provider = runtime.get_provider("gemini").expect("provider registered");
+        assert_eq!(provider.configured_model(), Some("gemini-2.5-flash"));
+    }
+
     #[test]
     fn resolve_api_key_prefers_config_over_env() {
         // Config value should win when present
@@ -4012,4 +4218,335 @@ mod tests {
         let result = resolve_api_key(None, "NONEXISTENT_VAULT_KEY", "NONEXISTENT_ENV_VAR_99999");
         assert_eq!(result, None);
     }
+
+    struct NamedTool(String);
+
+    #[async_trait::async_trait]
+    impl Tool for NamedTool {
+        fn name(&self) -> &str {
+            &self.0
+        }
+        fn description(&self) -> &str {
+            "mock MCP tool"
+        }
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+        async fn execute(
+            &self,
+            _context: &opencrust_agents::tools::ToolContext,
+            _input: serde_json::Value,
+        ) -> opencrust_common::Result<opencrust_agents::tools::ToolOutput> {
+            Ok(opencrust_agents::tools::ToolOutput::success("ok"))
+        }
+    }
+
+    fn mcp_server(transport: &str, enabled: bool) -> McpServerConfig {
+        McpServerConfig {
+            command: "mock".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            transport: transport.to_string(),
+            url: None,
+            enabled: Some(enabled),
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn mcp_startup_reports_each_server_and_retries_stdio_once() {
+        let configs = HashMap::from([
+            ("alpha".to_string(), mcp_server("stdio", true)),
+            ("broken".to_string(), mcp_server("stdio", true)),
+            ("flaky".to_string(), mcp_server("stdio", true)),
+            ("remote".to_string(), mcp_server("http", true)),
+            ("off".to_string(), mcp_server("stdio", false)),
+        ]);
+
+        let attempts = Mutex::new(HashMap::<String, usize>::new());
+        let (report, tools) =
+            start_mcp_servers(&configs, std::time::Duration::ZERO, |name, _config| {
+                let attempt = {
+                    let mut attempts = attempts.lock().unwrap();
+                    let count = attempts.entry(name.clone()).or_default();
+                    *count += 1;
+                    *count
+                };
+                async move {
+                    match (name.as_str(), attempt) {
+                        ("alpha", _) => Ok(vec![
+                            Box::new(NamedTool("alpha.a".to_string())) as Box<dyn Tool>,
+                            Box::new(NamedTool("alpha.b".to_string())),
+                        ]),
+                        ("flaky", 2) => Ok(vec![Box::new(NamedTool("flaky.a".to_string())) as _]),
+                        _ => Err(format!("{name} refused attempt {attempt}")),
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(
+            report,
+            vec![
+                ("alpha".to_string(), Ok(2)),
+                (
+                    "broken".to_string(),
+                    Err("broken refused attempt 2".to_string())
+                ),
+                ("flaky".to_string(), Ok(1)),
+                (
+                    "remote".to_string(),
+                    Err("remote refused attempt 1".to_string())
+                ),
+            ]
+        );
+        let names: Vec<&str> = tools.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["alpha.a", "alpha.b", "flaky.a"]);
+        let attempts = attempts.into_inner().unwrap();
+        assert_eq!(attempts["alpha"], 1);
+        assert_eq!(attempts["broken"], 2);
+        assert_eq!(attempts["remote"], 1);
+        assert!(!attempts.contains_key("off"));
+    }
+
+    #[tokio::test]
+    async fn connect_mcp_server_rejects_bad_transport_config() {
+        let manager = McpManager::new();
+        let err = connect_mcp_server(&manager, "web", &mcp_server("http", true))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.contains("no 'url' configured"));
+        let err = connect_mcp_server(&manager, "ws", &mcp_server("websocket", true))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.contains("unsupported transport 'websocket'"));
+    }
+
+    #[tokio::test]
+    async fn startup_report_lists_unknown_provider_and_keeps_valid_ones() {
+        let provider = |kind: &str| opencrust_config::LlmProviderConfig {
+            provider: kind.to_string(),
+            model: None,
+            api_key: None,
+            base_url: None,
+            max_tokens: None,
+            supports_tools: true,
+            extra: std::collections::HashMap::new(),
+        };
+        let mut config = AppConfig::default();
+        config.llm.insert("local".to_string(), provider("ollama"));
+        config
+            .llm
+            .insert("bad".to_string(), provider("nonexistent-provider"));
+
+        let report = StartupReport::default();
+        let (runtime, _handle) = build_agent_runtime_with_report(&config, &report).await;
+
+        assert_eq!(runtime.provider_ids(), vec!["local".to_string()]);
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].component, "provider");
+        assert_eq!(failures[0].name, "bad");
+        assert!(failures[0].error.contains("nonexistent-provider"));
+    }
+
+    #[tokio::test]
+    async fn broken_channel_is_reported_and_others_still_start() {
+        let channel = |kind: &str, settings: serde_json::Value| opencrust_config::ChannelConfig {
+            channel_type: kind.to_string(),
+            enabled: None,
+            provider: None,
+            model: None,
+            settings: serde_json::from_value(settings).unwrap(),
+        };
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "line-ok".to_string(),
+            channel(
+                "line",
+                serde_json::json!({ "channel_access_token": "tok", "channel_secret": "sec" }),
+            ),
+        );
+        config.channels.insert(
+            "line-broken".to_string(),
+            channel("line", serde_json::json!({ "channel_access_token": "tok" })),
+        );
+        config
+            .channels
+            .insert("fax".to_string(), channel("fax", serde_json::json!({})));
+
+        let state: SharedState = Arc::new(crate::state::AppState::new(
+            config.clone(),
+            Arc::new(AgentRuntime::new()),
+            opencrust_channels::ChannelRegistry::new(),
+        ));
+        build_channels(&config, &state.startup).await;
+        let lines = build_line_channels(&config, &state);
+
+        assert_eq!(lines.len(), 1);
+        let mut failures = state.startup.failures();
+        failures.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].name, "fax");
+        assert_eq!(failures[0].error, "unknown channel type 'fax'");
+        assert_eq!(failures[1].name, "line-broken");
+        assert_eq!(failures[1].error, "no channel_secret");
+    }
+
+    #[test]
+    fn build_signal_channels_requires_account() {
+        let channel = |settings: serde_json::Value| opencrust_config::ChannelConfig {
+            channel_type: "signal".to_string(),
+            enabled: None,
+            provider: None,
+            model: None,
+            settings: serde_json::from_value(settings).unwrap(),
+        };
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "signal-home".to_string(),
+            channel(serde_json::json!({ "account": "+15550000000", "rpc_addr": "127.0.0.1:7583" })),
+        );
+        config
+            .channels
+            .insert("signal-broken".to_string(), channel(serde_json::json!({})));
+
+        let state: SharedState = Arc::new(crate::state::AppState::new(
+            config.clone(),
+            Arc::new(AgentRuntime::new()),
+            opencrust_channels::ChannelRegistry::new(),
+        ));
+        let channels = build_signal_channels(&config, &state);
+
+        assert_eq!(channels.len(), 1);
+        assert_eq!(
+            opencrust_channels::ChannelSender::channel_name(&channels[0]),
+            "signal-home"
+        );
+        let failures = state.startup.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].name, "signal-broken");
+        assert!(failures[0].error.starts_with("no account"));
+    }
+
+    #[test]
+    fn build_teams_channels_requires_credentials() {
+        let channel = |settings: serde_json::Value| opencrust_config::ChannelConfig {
+            channel_type: "teams".to_string(),
+            enabled: None,
+            provider: None,
+            model: None,
+            settings: serde_json::from_value(settings).unwrap(),
+        };
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "teams-work".to_string(),
+            channel(serde_json::json!({
+                "app_id": "app-123",
+                "app_password": "secret",
+                "tenant_id": "contoso.onmicrosoft.com",
+            })),
+        );
+        config.channels.insert(
+            "teams-broken".to_string(),
+            channel(serde_json::json!({ "app_id": "app-456" })),
+        );
+
+        let state: SharedState = Arc::new(crate::state::AppState::new(
+            config.clone(),
+            Arc::new(AgentRuntime::new()),
+            opencrust_channels::ChannelRegistry::new(),
+        ));
+        let channels = build_teams_channels(&config, &state);
+
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].app_id(), "app-123");
+        assert_eq!(
+            opencrust_channels::ChannelSender::channel_name(&channels[0]),
+            "teams-work"
+        );
+        let failures = state.startup.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].name, "teams-broken");
+        assert!(failures[0].error.starts_with("no app_password"));
+    }
+
+    #[test]
+    fn build_webchat_channels_reads_settings() {
+        let channel =
+            |enabled: Option<bool>, settings: serde_json::Value| opencrust_config::ChannelConfig {
+                channel_type: "webchat".to_string(),
+                enabled,
+                provider: None,
+                model: None,
+                settings: serde_json::from_value(settings).unwrap(),
+            };
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "support".to_string(),
+            channel(
+                None,
+                serde_json::json!({
+                    "title": "Support",
+                    "allowed_origins": ["https://example.com"],
+                }),
+            ),
+        );
+        config.channels.insert(
+            "off".to_string(),
+            channel(Some(false), serde_json::json!({})),
+        );
+
+        let state: SharedState = Arc::new(crate::state::AppState::new(
+            config.clone(),
+            Arc::new(AgentRuntime::new()),
+            opencrust_channels::ChannelRegistry::new(),
+        ));
+        let channels = build_webchat_channels(&config, &state);
+
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].name(), "support");
+        assert_eq!(channels[0].title(), "Support");
+        assert!(channels[0].origin_allowed(Some("https://example.com")));
+        assert!(!channels[0].origin_allowed(Some("https://evil.example")));
+        assert!(state.startup.failures().is_empty());
+    }
+
+    #[test]
+    fn slack_sessions_are_scoped_to_threads() {
+        assert_eq!(slack_session_base("D1", None), "slack-D1");
+        assert_eq!(
+            slack_session_base("C1", Some("1700000000.000100")),
+            "slack-C1-1700000000.000100"
+        );
+        assert_ne!(
+            slack_session_base("C1", Some("1700000000.000100")),
+            slack_session_base("C1", Some("1700000000.000200"))
+        );
+    }
+
+    #[test]
+    fn whatsapp_template_from_settings() {
+        let settings = |v: serde_json::Value| -> HashMap<String, serde_json::Value> {
+            serde_json::from_value(v).unwrap()
+        };
+        let template = whatsapp_template(&settings(
+            serde_json::json!({ "template": "opencrust_update" }),
+        ))
+        .unwrap();
+        assert_eq!(template.name, "opencrust_update");
+        assert_eq!(template.language, "en_US");
+
+        let template = whatsapp_template(&settings(serde_json::json!({
+            "template": "aviso",
+            "template_language": "es",
+        })))
+        .unwrap();
+        assert_eq!(template.language, "es");
+
+        assert!(whatsapp_template(&settings(serde_json::json!({ "template": " " }))).is_none());
+        assert!(whatsapp_template(&settings(serde_json::json!({}))).is_none());
+    }
 }
@@ -99,6 +99,24 @@ pub async fn ingest_from_bytes(
     embedding_provider: Option<&dyn EmbeddingProvider>,
     replace: bool,
 ) -> Result<IngestResult> {
+    let text = extract_text_from_bytes(filename, data)?;
+
+    let mime = opencrust_media::detect_mime_type(Path::new(filename));
+    ingest_text(
+        filename,
+        &text,
+        None,
+        mime,
+        doc_store,
+        embedding_provider,
+        replace,
+    )
+    .await
+}
+
+/// Extract plain text from an uploaded file's bytes, using `filename`'s
+/// extension to pick the extractor.
+pub fn extract_text_from_bytes(filename: &str, data: &[u8]) -> Result<String> {
     // Write to temp file for extract_text (it needs a path with extension)
     let ext = Path::new(filename)
         .extension()
@@ -114,19 +132,7 @@ pub async fn ingest_from_bytes(
 
     let text = opencrust_media::extract_text(&temp_path);
     let _ = std::fs::remove_file(&temp_path);
-    let text = text?;
-
-    let mime = opencrust_media::detect_mime_type(Path::new(filename));
-    ingest_text(
-        filename,
-        &text,
-        None,
-        mime,
-        doc_store,
-        embedding_provider,
-        replace,
-    )
-    .await
+    text
 }
 
 /// Core ingestion: chunk text, embed, store.
@@ -5,9 +5,13 @@ pub mod api;
 pub mod bootstrap;
 pub mod google_secrets;
 pub mod ingest;
+pub mod pipeline;
 pub mod router;
 pub mod server;
+pub mod startup;
 pub mod state;
+#[cfg(feature = "web-ui")]
+pub mod web_ui;
 pub mod ws;
 
 pub use server::GatewayServer;
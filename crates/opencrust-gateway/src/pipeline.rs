@@ -0,0 +1,2091 @@
+//! Shared inbound message pipeline used by chat channel callbacks.
+//!
+//! Every channel runs the same steps for a user turn: DM auth/pairing, rate
+//! limits, token budget, input validation, history hydration, the agent call,
+//! and persistence. Channels keep their own command, file and media handling
+//! and hand the resulting text to [`MessagePipeline`].
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use opencrust_agents::{ChatMessage, ContentBlock, MessagePart, SessionModel};
+use opencrust_channels::{
+    ChannelResponse, Feedback, InlineButton, OnEditFn, OnReactionFn, SentReply, ToolResult,
+};
+use opencrust_common::{ChannelId, Message, MessageContent, MessageDirection, SessionId, UserId};
+use opencrust_config::model::{GuardrailsConfig, RateLimitConfig};
+use opencrust_config::{AppConfig, ChannelConfig};
+use opencrust_media::TtsProvider;
+use opencrust_security::{
+    ChannelPolicy, DmAuthResult, InputValidator, LINKED_REPLY, check_dm_auth,
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::state::{ReplayFn, SharedState};
+
+/// Commands recognised without a `/` or `!` prefix when bare commands are enabled.
+const BARE_COMMANDS: &[&str] = &["help", "clear", "pair", "users"];
+
+/// A single inbound user turn, normalised across channels.
+pub struct InboundMessage {
+    /// Session id, already namespaced by channel (e.g. `telegram-123`).
+    pub session_id: String,
+    /// Stable user id used for auth, rate limits and continuity.
+    pub user_id: String,
+    /// Display name; may be empty when the channel has none.
+    pub user_name: String,
+    /// Raw message text (or voice transcript / image caption).
+    pub text: String,
+    /// Group messages are filtered by the channel and skip DM auth.
+    pub is_group: bool,
+    /// Image and document blocks sent ahead of the text.
+    pub attachments: Vec<ContentBlock>,
+    /// Channel routing fields persisted with the session.
+    pub metadata: serde_json::Value,
+    /// Streaming sink, when the channel supports incremental delivery.
+    pub delta_tx: Option<mpsc::Sender<String>>,
+    /// Reply with synthesized audio when the pipeline has voice replies enabled.
+    pub voice_reply: bool,
+}
+
+impl InboundMessage {
+    pub fn text(
+        session_id: impl Into<String>,
+        user_id: impl Into<String>,
+        user_name: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            session_id: session_id.into(),
+            user_id: user_id.into(),
+            user_name: user_name.into(),
+            text: text.into(),
+            is_group: false,
+            attachments: Vec::new(),
+            metadata: serde_json::json!({}),
+            delta_tx: None,
+            voice_reply: false,
+        }
+    }
+
+    pub fn with_group(mut self, is_group: bool) -> Self {
+        self.is_group = is_group;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_delta_tx(mut self, delta_tx: Option<mpsc::Sender<String>>) -> Self {
+        self.delta_tx = delta_tx;
+        self
+    }
+
+    pub fn with_image(mut self, data_url: String) -> Self {
+        self.attachments.push(ContentBlock::Image { url: data_url });
+        self
+    }
+
+    /// Attach a file for the model to read natively. `text` is the extracted
+    /// content, sent instead by providers that cannot read the file itself.
+    pub fn with_document(mut self, mime: &str, data: &[u8], text: Option<String>) -> Self {
+        use base64::Engine;
+        let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+        self.attachments.push(ContentBlock::Document {
+            mime: mime.to_string(),
+            url: format!("data:{mime};base64,{b64}"),
+            text,
+        });
+        self
+    }
+
+    pub fn with_voice_reply(mut self, voice_reply: bool) -> Self {
+        self.voice_reply = voice_reply;
+        self
+    }
+}
+
+/// Per-channel message pipeline shared by all of a channel's callbacks.
+#[derive(Clone)]
+pub struct MessagePipeline {
+    state: SharedState,
+    channel: &'static str,
+    /// Configured channel name, used for maintenance mode and replies to
+    /// replayed messages.
+    name: String,
+    policy: Arc<ChannelPolicy>,
+    rate_limit: RateLimitConfig,
+    guardrails: GuardrailsConfig,
+    max_inbound_chars: Option<usize>,
+    data_dir: PathBuf,
+    inject_user_name: bool,
+    per_user_sessions: bool,
+    regenerate_on_edit: bool,
+    bare_commands: bool,
+    /// Channel override of `voice.auto_reply_voice`.
+    reply_with_voice: Option<bool>,
+    tts: Option<(Arc<dyn TtsProvider>, usize)>,
+    provider: Option<String>,
+    model: Option<String>,
+}
+
+impl MessagePipeline {
+    /// Create a pipeline for `channel` (used as the session channel id and log label).
+    pub fn new(
+        channel: &'static str,
+        state: &SharedState,
+        config: &AppConfig,
+        policy: Arc<ChannelPolicy>,
+    ) -> Self {
+        Self {
+            state: Arc::clone(state),
+            channel,
+            name: channel.to_string(),
+            policy,
+            rate_limit: config.gateway.rate_limit.clone(),
+            guardrails: config.guardrails.clone(),
+            max_inbound_chars: config.security.max_inbound_chars,
+            data_dir: config.data_dir.clone().unwrap_or_else(|| {
+                opencrust_config::ConfigLoader::default_config_dir().join("data")
+            }),
+            inject_user_name: false,
+            per_user_sessions: false,
+            regenerate_on_edit: false,
+            bare_commands: false,
+            reply_with_voice: None,
+            tts: None,
+            provider: None,
+            model: None,
+        }
+    }
+
+    /// Set the configured channel name (default: the channel type).
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Read the channel's `provider` and `model` overrides, its
+    /// `allowed_tools` list (replacing `guardrails.allowed_tools`) and its
+    /// `inject_user_name`, `per_user_sessions`, `regenerate_on_edit` and
+    /// `reply_with_voice` settings.
+    pub fn with_channel_settings(mut self, channel_config: &ChannelConfig) -> Self {
+        self.provider = channel_config.provider.clone();
+        self.model = channel_config.model.clone();
+        let settings = &channel_config.settings;
+        if let Some(tools) = settings.get("allowed_tools").and_then(|v| v.as_array()) {
+            self.guardrails.allowed_tools = Some(
+                tools
+                    .iter()
+                    .filter_map(|t| t.as_str())
+                    .map(str::to_string)
+                    .collect(),
+            );
+        }
+        self.inject_user_name = settings
+            .get("inject_user_name")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        self.per_user_sessions = settings
+            .get("per_user_sessions")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        self.regenerate_on_edit = settings
+            .get("regenerate_on_edit")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        self.reply_with_voice = settings.get("reply_with_voice").and_then(|v| v.as_bool());
+        self
+    }
+
+    /// The hook a channel runs before regenerating an edited message, or
+    /// `None` when `regenerate_on_edit` is off. It drops the last turn of the
+    /// session that `session_base(chat_id, thread_id)` names.
+    pub fn edit_hook(
+        self: &Arc<Self>,
+        session_base: fn(&str, Option<&str>) -> String,
+    ) -> Option<OnEditFn> {
+        if !self.regenerate_on_edit {
+            return None;
+        }
+        let pipeline = Arc::clone(self);
+        Some(Arc::new(
+            move |chat_id: String, thread_id: Option<String>, user_id: String, is_group: bool| {
+                let pipeline = Arc::clone(&pipeline);
+                Box::pin(async move {
+                    let base = session_base(&chat_id, thread_id.as_deref());
+                    let session_id = pipeline.session_id(base, &user_id, is_group);
+                    pipeline.state.rewind_last_turn(&session_id).await;
+                })
+            },
+        ))
+    }
+
+    /// The hook a channel runs when a user reacts 👍/👎 to one of its
+    /// replies. It scores the reply's memory entry in the session that
+    /// `session_base(chat_id, thread_id)` names, so recall can favour answers
+    /// that were well received.
+    pub fn feedback_hook(
+        self: &Arc<Self>,
+        session_base: fn(&str, Option<&str>) -> String,
+    ) -> OnReactionFn {
+        let pipeline = Arc::clone(self);
+        Arc::new(move |reply: SentReply, feedback: Feedback| {
+            let pipeline = Arc::clone(&pipeline);
+            Box::pin(async move {
+                let base = session_base(&reply.chat_id, reply.thread_id.as_deref());
+                let session_id = pipeline.session_id(base, &reply.user_id, reply.is_group);
+                match pipeline
+                    .state
+                    .agents
+                    .record_feedback(&session_id, &reply.text, feedback.score())
+                    .await
+                {
+                    Ok(true) => info!("{}: recorded {feedback:?} feedback", pipeline.channel),
+                    Ok(false) => {}
+                    Err(e) => warn!("{}: failed to record feedback: {e}", pipeline.channel),
+                }
+            })
+        })
+    }
+
+    /// Session id for a message in the chat whose shared session is `base`.
+    /// With `per_user_sessions`, each sender in a group chat gets their own
+    /// session; DMs are already per user and keep `base`.
+    pub fn session_id(&self, base: String, user_id: &str, is_group: bool) -> String {
+        if self.per_user_sessions && is_group {
+            format!("{base}-{user_id}")
+        } else {
+            base
+        }
+    }
+
+    /// Also accept commands sent as a lone keyword (`help`, `clear`, ...), for
+    /// channels without a slash-command UI.
+    pub fn with_bare_commands(mut self) -> Self {
+        self.bare_commands = true;
+        self
+    }
+
+    /// Synthesize voice replies when a TTS provider is configured and the
+    /// channel's `reply_with_voice` (default: `voice.auto_reply_voice`) is on.
+    /// Only for channels that can deliver audio; call after
+    /// [`Self::with_channel_settings`].
+    pub fn with_voice_replies(mut self, config: &AppConfig) -> Self {
+        if self
+            .reply_with_voice
+            .unwrap_or(config.voice.auto_reply_voice)
+            && let Some(provider) = self.state.tts_provider.clone()
+        {
+            let max_chars = config
+                .voice
+                .tts_max_chars
+                .unwrap_or(opencrust_media::TTS_DEFAULT_MAX_CHARS);
+            self.tts = Some((provider, max_chars));
+        }
+        self
+    }
+
+    pub fn state(&self) -> &SharedState {
+        &self.state
+    }
+
+    /// Run the full pipeline: admission checks followed by the agent turn.
+    pub async fn handle(&self, msg: InboundMessage) -> Result<ChannelResponse, String> {
+        let Some(msg) = self.addressed(msg) else {
+            return Err("__blocked__".to_string());
+        };
+        if let Some(reply) = self.admit(&msg).await? {
+            return Ok(reply);
+        }
+        self.run_turn(msg).await
+    }
+
+    /// Apply the channel's `respond_mode` to a group message. Returns `None`
+    /// when the bot should not answer, otherwise the message with any
+    /// `respond_prefix` removed. Direct messages pass through unchanged.
+    pub fn addressed(&self, mut msg: InboundMessage) -> Option<InboundMessage> {
+        if msg.is_group {
+            msg.text = self.policy.group_text(&msg.text)?;
+        }
+        Some(msg)
+    }
+
+    /// DM auth/pairing, maintenance mode, rate limit and token budget checks,
+    /// then per-session tool config. Returns `Some` when the user should get
+    /// a pairing, welcome or maintenance reply instead of an agent turn.
+    pub async fn admit(&self, msg: &InboundMessage) -> Result<Option<ChannelResponse>, String> {
+        if !msg.is_group {
+            let mut list = self.state.allowlist.lock().unwrap();
+            if let Some(welcome) = check_dm_auth(
+                &self.policy,
+                &mut list,
+                &self.state.pairing,
+                &msg.user_id,
+                &msg.user_name,
+                &msg.text,
+                self.channel,
+            )? {
+                return Ok(Some(ChannelResponse::Text(welcome)));
+            }
+        }
+
+        if let Some(reply) = self
+            .state
+            .hold_for_maintenance(&self.name, &msg.session_id, || self.replay(msg))
+        {
+            return Ok(Some(ChannelResponse::Text(reply)));
+        }
+
+        self.prepare_turn(msg).await?;
+        Ok(None)
+    }
+
+    /// The checks of [`Self::admit`] that run after maintenance mode: rate
+    /// limit and token budget, then per-session tool config. Held messages go
+    /// through these again when they are replayed.
+    async fn prepare_turn(&self, msg: &InboundMessage) -> Result<(), String> {
+        self.state
+            .check_user_rate_limit(&msg.user_id, &self.rate_limit)?;
+        self.state
+            .check_token_budget(&msg.session_id, &msg.user_id, &self.guardrails)
+            .await?;
+        self.state.agents.set_session_tool_config(
+            &msg.session_id,
+            self.guardrails.allowed_tools.clone(),
+            self.guardrails.session_tool_call_budget,
+        );
+        if self.inject_user_name {
+            self.state
+                .agents
+                .set_session_user_name(&msg.session_id, &msg.user_name);
+        }
+        Ok(())
+    }
+
+    /// Hold a copy of `msg` to run once the channel leaves maintenance mode.
+    /// The reply goes out through the channel's sender, routed by the
+    /// message metadata; streaming and voice replies are not replayed.
+    fn replay(&self, msg: &InboundMessage) -> ReplayFn {
+        let pipeline = self.clone();
+        let msg = InboundMessage {
+            session_id: msg.session_id.clone(),
+            user_id: msg.user_id.clone(),
+            user_name: msg.user_name.clone(),
+            text: msg.text.clone(),
+            is_group: msg.is_group,
+            attachments: msg.attachments.clone(),
+            metadata: msg.metadata.clone(),
+            delta_tx: None,
+            voice_reply: false,
+        };
+        Box::new(move || {
+            Box::pin(async move {
+                let name = pipeline.name.clone();
+                let (session_id, user_id, metadata) = (
+                    msg.session_id.clone(),
+                    msg.user_id.clone(),
+                    msg.metadata.clone(),
+                );
+                let turn = match pipeline.prepare_turn(&msg).await {
+                    Ok(()) => pipeline.run_turn(msg).await,
+                    Err(e) => Err(e),
+                };
+                let response = match turn {
+                    Ok(response) => response,
+                    Err(e) => {
+                        warn!("{name}: replayed message for {session_id} failed: {e}");
+                        return;
+                    }
+                };
+                let Some(sender) = pipeline
+                    .state
+                    .channel_senders
+                    .get(&name)
+                    .map(|s| Arc::clone(s.value()))
+                else {
+                    warn!("{name}: no sender to deliver replayed reply for {session_id}");
+                    return;
+                };
+                let mut message = Message::text(
+                    SessionId::from_string(&session_id),
+                    ChannelId::from_string(&name),
+                    UserId::from_string(&user_id),
+                    MessageDirection::Outgoing,
+                    response.text(),
+                );
+                message.metadata = metadata;
+                if let Err(e) = sender.send_message(&message).await {
+                    warn!("{name}: failed to deliver replayed reply for {session_id}: {e}");
+                }
+            })
+        })
+    }
+
+    /// Validate input, run the agent with session history, and persist the turn.
+    pub async fn run_turn(&self, msg: InboundMessage) -> Result<ChannelResponse, String> {
+        let state = &self.state;
+        let session_id = msg.session_id.as_str();
+        let user_id = msg.user_id.as_str();
+        let max_input_chars = self.guardrails.max_input_chars;
+
+        // File uploads carry their content in attachments and are exempt.
+        if let Some(limit) = self.max_inbound_chars
+            && msg.attachments.is_empty()
+            && InputValidator::exceeds_length(&msg.text, limit)
+        {
+            return Ok(ChannelResponse::Text(format!(
+                "Your message is too long ({} characters, the limit is {limit}). \
+                 Please shorten it and try again.",
+                msg.text.chars().count()
+            )));
+        }
+
+        let text = InputValidator::sanitize(&msg.text);
+        if InputValidator::check_prompt_injection(&text) {
+            return Err("input rejected: potential prompt injection detected".to_string());
+        }
+        if InputValidator::exceeds_length(&text, max_input_chars) {
+            return Err(format!(
+                "input rejected: message exceeds {max_input_chars} character limit"
+            ));
+        }
+
+        state
+            .hydrate_session_history(session_id, Some(self.channel), Some(user_id))
+            .await;
+        let history: Vec<ChatMessage> = state.session_history(session_id);
+        let continuity_key = state.continuity_key(Some(user_id));
+        let summary = state.session_summary(session_id);
+        let agents = &state.agents;
+        // Drop results left over from a turn that failed before they were taken.
+        agents.take_tool_results(session_id);
+        agents.take_tool_attachments(session_id);
+        agents.take_reasoning(session_id);
+        if state.channel_senders.contains_key(&self.name) {
+            agents.deliver_tool_attachments(session_id);
+        }
+
+        let content = if msg.attachments.is_empty() {
+            MessagePart::Text(text.clone())
+        } else {
+            let mut blocks = msg.attachments;
+            blocks.push(ContentBlock::Text { text: text.clone() });
+            MessagePart::Parts(blocks)
+        };
+        let (response, new_summary) = agents
+            .process_message_with_provider_and_summary(
+                session_id,
+                content,
+                &text,
+                &history,
+                msg.delta_tx,
+                summary.as_deref(),
+                continuity_key.as_deref(),
+                Some(user_id),
+                self.provider.as_deref(),
+                self.model.as_deref(),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Some(s) = new_summary {
+            state.update_session_summary(session_id, &s);
+        }
+
+        let response = InputValidator::truncate_output(&response, self.guardrails.max_output_chars);
+        self.send_tool_attachments(session_id, user_id, &msg.metadata)
+            .await;
+        state
+            .persist_turn(
+                session_id,
+                Some(self.channel),
+                Some(user_id),
+                &text,
+                &response,
+                Some(msg.metadata),
+            )
+            .await;
+
+        if let Some((input, output, provider, model)) = agents.take_session_usage(session_id) {
+            state
+                .persist_usage(session_id, &provider, &model, input, output)
+                .await;
+        }
+
+        if msg.voice_reply
+            && let Some((provider, max_chars)) = &self.tts
+        {
+            let tts_input = opencrust_media::truncate_for_tts(&response, *max_chars);
+            match provider.synthesize(tts_input).await {
+                Ok(audio) => {
+                    return Ok(ChannelResponse::Voice {
+                        text: response,
+                        audio,
+                    });
+                }
+                Err(e) => {
+                    warn!("tts synthesis failed, falling back to text: {e}");
+                }
+            }
+        }
+
+        // An `ask_user` question with suggested answers: offer them as buttons.
+        let options = agents.pending_question_options(session_id);
+        if !options.is_empty() {
+            return Ok(ChannelResponse::Buttons {
+                text: response,
+                buttons: options
+                    .into_iter()
+                    .map(|o| vec![InlineButton::new(o.clone(), o)])
+                    .collect(),
+            });
+        }
+
+        let results = agents.take_tool_results(session_id);
+        if !results.is_empty() {
+            return Ok(ChannelResponse::ToolResults {
+                text: response,
+                results: results
+                    .into_iter()
+                    .map(|(tool, data)| ToolResult { tool, data })
+                    .collect(),
+            });
+        }
+
+        // Only plain text replies carry the reasoning: the variants above
+        // return first, and their reasoning is dropped at the next turn.
+        if let Some(reasoning) = agents.take_reasoning(session_id) {
+            return Ok(ChannelResponse::Reasoning {
+                text: response,
+                reasoning,
+            });
+        }
+        Ok(ChannelResponse::Text(response))
+    }
+
+    /// Send media produced by tools this turn (generated images, ...) to the
+    /// chat `metadata` routes to, through the channel's sender, ahead of the
+    /// text reply.
+    async fn send_tool_attachments(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        metadata: &serde_json::Value,
+    ) {
+        let attachments = self.state.agents.take_tool_attachments(session_id);
+        if attachments.is_empty() {
+            return;
+        }
+        let name = &self.name;
+        let Some(sender) = self
+            .state
+            .channel_senders
+            .get(name)
+            .map(|s| Arc::clone(s.value()))
+        else {
+            warn!(
+                "{name}: no sender to deliver {} tool attachment(s) for {session_id}",
+                attachments.len()
+            );
+            return;
+        };
+        for attachment in attachments {
+            let mut message = Message::text(
+                SessionId::from_string(session_id),
+                ChannelId::from_string(name),
+                UserId::from_string(user_id),
+                MessageDirection::Outgoing,
+                "",
+            );
+            message.content = MessageContent::Attachment(attachment);
+            message.metadata = metadata.clone();
+            if let Err(e) = sender.send_message(&message).await {
+                warn!("{name}: failed to deliver tool attachment for {session_id}: {e}");
+            }
+        }
+    }
+
+    /// Handle a `/command` or `!command` (and, with bare commands enabled, a
+    /// lone keyword such as `help`). Returns `None` when `msg` is not a command.
+    ///
+    /// Commands run before admission checks and do their own auth.
+    pub async fn handle_command(
+        &self,
+        msg: &InboundMessage,
+    ) -> Option<Result<ChannelResponse, String>> {
+        let cmd = self.parse_command(msg)?;
+        if cmd == "ingest" {
+            return Some(self.ingest_pending(&msg.session_id, &msg.text).await);
+        }
+        if cmd == "summarize" {
+            return Some(self.summarize(msg).await.map(ChannelResponse::Text));
+        }
+        if cmd == "announce" {
+            return Some(self.announce(msg).await.map(ChannelResponse::Text));
+        }
+        Some(self.run_command(&cmd, msg).map(ChannelResponse::Text))
+    }
+
+    /// `(is_owner, is_allowed)` for `user_id`. An open DM policy grants both.
+    fn access(&self, user_id: &str) -> (bool, bool) {
+        let dm_open = matches!(self.policy.authorize_dm(user_id), DmAuthResult::Allowed);
+        let list = self.state.allowlist.lock().unwrap();
+        (
+            dm_open || list.is_owner(user_id),
+            dm_open || list.is_allowed(user_id),
+        )
+    }
+
+    /// `/summarize [save]`: summarize the session so far. With `save`, the
+    /// summary also replaces the session's running summary.
+    async fn summarize(&self, msg: &InboundMessage) -> Result<String, String> {
+        let (_, is_allowed) = self.access(&msg.user_id);
+        if !is_allowed {
+            return Err("__blocked__".to_string());
+        }
+        let session_id = msg.session_id.as_str();
+        let history = self.state.session_history(session_id);
+        if history.is_empty() {
+            return Ok("Nothing to summarize yet.".to_string());
+        }
+        let existing = self.state.session_summary(session_id);
+        let summary = self
+            .state
+            .agents
+            .summarize_conversation(&history, existing.as_deref())
+            .await
+            .map_err(|e| {
+                warn!("{}: summarize failed for {session_id}: {e}", self.channel);
+                "Sorry, I couldn't summarize this conversation right now.".to_string()
+            })?;
+        let save = msg.text.split_whitespace().nth(1) == Some("save");
+        if save {
+            self.state.update_session_summary(session_id, &summary);
+        }
+        Ok(summary)
+    }
+
+    /// `/announce <text>`: owner-only broadcast of `text` to every allowed
+    /// user on this channel.
+    async fn announce(&self, msg: &InboundMessage) -> Result<String, String> {
+        let (is_owner, is_allowed) = self.access(&msg.user_id);
+        if !is_owner {
+            if !is_allowed {
+                return Err("__blocked__".to_string());
+            }
+            return Ok("Only the bot owner can send announcements.".to_string());
+        }
+        let text = msg
+            .text
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .map(|(_, rest)| rest.trim())
+            .unwrap_or_default();
+        if text.is_empty() {
+            return Ok("Usage: /announce <message>".to_string());
+        }
+        let report = self
+            .state
+            .broadcast(&self.name, text)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(format!(
+            "Announcement sent to {} user(s), {} failed.",
+            report.sent, report.failed
+        ))
+    }
+
+    /// The command `msg` invokes, if any. Bare keywords only count for
+    /// allowed users: from anyone else they are ordinary messages, so
+    /// admission answers them (e.g. with pairing instructions) instead of the
+    /// command being silently blocked.
+    fn parse_command(&self, msg: &InboundMessage) -> Option<String> {
+        let text = msg.text.as_str();
+        if let Some(cmd) = text.strip_prefix('!').or_else(|| text.strip_prefix('/')) {
+            return Some(cmd.split_whitespace().next().unwrap_or("").to_string());
+        }
+        let word = text.trim().to_lowercase();
+        (self.bare_commands
+            && BARE_COMMANDS.contains(&word.as_str())
+            && self.access(&msg.user_id).1)
+            .then_some(word)
+    }
+
+    async fn ingest_pending(
+        &self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<ChannelResponse, String> {
+        match self.state.take_pending_file(session_id) {
+            Some(pending) => {
+                crate::ingest::run_ingest(
+                    &self.state,
+                    &self.data_dir,
+                    text,
+                    &pending.filename,
+                    &pending.data,
+                )
+                .await
+            }
+            None => Ok(ChannelResponse::Text(
+                "No pending file. Send a document first, then use !ingest.".to_string(),
+            )),
+        }
+    }
+
+    fn run_command(&self, cmd: &str, msg: &InboundMessage) -> Result<String, String> {
+        let state = &self.state;
+        let user_id = msg.user_id.as_str();
+        let user_name = msg.user_name.as_str();
+
+        let (is_owner, is_allowed) = self.access(user_id);
+
+        match cmd {
+            "start" => {
+                if is_allowed {
+                    Ok(
+                        "Welcome to OpenCrust! Send me a message and I will respond.\n\n\
+                        Commands:\n\
+                        /help - show this help\n\
+                        /clear - reset conversation history\n\
+                        /pair - generate invite code (owner only)"
+                            .to_string(),
+                    )
+                } else {
+                    let mut list = state.allowlist.lock().unwrap();
+                    if list.needs_owner() && list.claim_owner(user_id) {
+                        info!(
+                            "{}: auto-paired owner {user_name} ({user_id})",
+                            self.channel
+                        );
+                        Ok(self.policy.messages.welcome_owner(user_name))
+                    } else {
+                        Ok(self.policy.messages.blocked(user_name))
+                    }
+                }
+            }
+            "help" => {
+                if !is_allowed {
+                    return Err("__blocked__".to_string());
+                }
+                let mut help = "OpenCrust Commands:\n\
+                    /help - show this help\n\
+                    /clear - reset conversation history\n\
+                    /summarize - summarize this conversation\n\
+                    /tag <name> - tag this conversation for search\n\
+                    /model <alias> - switch this conversation's model\n\
+                    !ingest - store a sent document for future reference"
+                    .to_string();
+                if is_owner {
+                    help.push_str(
+                        "\n/pair - generate a 6-digit invite code\n/users - list allowed users\n\
+                         /announce - message all allowed users on this channel\n\
+                         /link - link your accounts on other channels",
+                    );
+                }
+                Ok(help)
+            }
+            "clear" => {
+                if !is_allowed {
+                    return Err("__blocked__".to_string());
+                }
+                let session_id = msg.session_id.as_str();
+                if let Some(mut session) = state.sessions.get_mut(session_id) {
+                    session.history.clear();
+                }
+                state.update_session_summary(session_id, "");
+                if let Some(store) = &state.session_store {
+                    let _ = store.prune_old_messages(session_id, 0);
+                }
+                Ok("Conversation history cleared.".to_string())
+            }
+            "tag" => {
+                if !is_allowed {
+                    return Err("__blocked__".to_string());
+                }
+                let Some(store) = &state.session_store else {
+                    return Ok("Tags need the session store, which is not enabled.".to_string());
+                };
+                let session_id = msg.session_id.as_str();
+                let Some(raw) = msg.text.split_whitespace().nth(1) else {
+                    let tags = store.session_tags(session_id).map_err(|e| e.to_string())?;
+                    return Ok(if tags.is_empty() {
+                        "This conversation has no tags. Usage: /tag <name>".to_string()
+                    } else {
+                        format!("Tags: {}", tags.join(", "))
+                    });
+                };
+                let Some(tag) = opencrust_db::normalize_tag(raw) else {
+                    return Ok(format!(
+                        "Invalid tag {raw:?}: use up to {} letters, digits, '-' or '_'.",
+                        opencrust_db::MAX_TAG_LEN
+                    ));
+                };
+                let added = store
+                    .tag_session(session_id, &tag)
+                    .map_err(|e| e.to_string())?;
+                Ok(if added {
+                    format!("Tagged this conversation \"{tag}\".")
+                } else {
+                    format!("This conversation is already tagged \"{tag}\".")
+                })
+            }
+            "model" => {
+                if !is_allowed {
+                    return Err("__blocked__".to_string());
+                }
+                Ok(self.model_command(msg))
+            }
+            "pair" => {
+                if !is_owner {
+                    if !is_allowed {
+                        return Err("__blocked__".to_string());
+                    }
+                    return Ok("Only the bot owner can generate pairing codes.".to_string());
+                }
+                let code = state.pairing.lock().unwrap().generate(self.channel);
+                Ok(format!(
+                    "Pairing code: {code}\n\n\
+                     Share this with the person you want to invite. \
+                     They should send this code to the bot within 5 minutes."
+                ))
+            }
+            "link" => {
+                if let Some(code) = msg.text.split_whitespace().nth(1) {
+                    let target = state.pairing.lock().unwrap().claim_link(code, user_id);
+                    let Some(target) = target else {
+                        if !is_allowed {
+                            return Err("__blocked__".to_string());
+                        }
+                        return Ok("Invalid or expired link code.".to_string());
+                    };
+                    state.allowlist.lock().unwrap().link(user_id, &target);
+                    info!(
+                        "{}: linked {user_name} ({user_id}) to {target}",
+                        self.channel
+                    );
+                    return Ok(LINKED_REPLY.to_string());
+                }
+                if !is_owner {
+                    if !is_allowed {
+                        return Err("__blocked__".to_string());
+                    }
+                    return Ok("Only the bot owner can link accounts.".to_string());
+                }
+                let identity = state
+                    .allowlist
+                    .lock()
+                    .unwrap()
+                    .identity(user_id)
+                    .to_string();
+                let code = state.pairing.lock().unwrap().generate_link(&identity);
+                Ok(format!(
+                    "Link code: {code}\n\n\
+                     Send /link {code} to the bot from your account on another channel \
+                     within 5 minutes. Both accounts will then share memory."
+                ))
+            }
+            "users" => {
+                if !is_owner {
+                    if !is_allowed {
+                        return Err("__blocked__".to_string());
+                    }
+                    return Ok("Only the bot owner can list users.".to_string());
+                }
+                let list = state.allowlist.lock().unwrap();
+                let users = list.list_users();
+                let owner = list.owner().unwrap_or("none");
+                Ok(format!(
+                    "Owner: {owner}\nAllowed users ({}):\n{}",
+                    users.len(),
+                    users.join("\n")
+                ))
+            }
+            _ => {
+                if !is_allowed {
+                    return Err("__blocked__".to_string());
+                }
+                Ok(format!(
+                    "Unknown command: /{cmd}\nUse /help for available commands."
+                ))
+            }
+        }
+    }
+
+    /// `/model` shows the current model and aliases, `/model <alias>` switches
+    /// this session to an alias from `models:`, `/model default` switches back.
+    fn model_command(&self, msg: &InboundMessage) -> String {
+        let agents = &self.state.agents;
+        let session_id = msg.session_id.as_str();
+        match msg.text.split_whitespace().nth(1) {
+            Some("default") => {
+                agents.clear_session_model(session_id);
+                format!("Switched back to {}.", self.describe_model(None))
+            }
+            Some(alias) => match agents.switch_session_model(session_id, alias) {
+                Ok(target) => format!(
+                    "Switched to {alias} ({}).",
+                    self.describe_model(Some(&target))
+                ),
+                Err(e) => e.to_string(),
+            },
+            None => {
+                let current = agents.session_model(session_id);
+                let mut aliases: Vec<&str> =
+                    agents.model_aliases().keys().map(String::as_str).collect();
+                aliases.sort_unstable();
+                let mut reply = format!("Current model: {}", self.describe_model(current.as_ref()));
+                if aliases.is_empty() {
+                    reply.push_str("\nNo model aliases configured (add them under models:).");
+                } else {
+                    reply.push_str(&format!(
+                        "\nAvailable: {}\nUsage: /model <alias> or /model default",
+                        aliases.join(", ")
+                    ));
+                }
+                reply
+            }
+        }
+    }
+
+    /// `provider/model` for a session selection layered over this channel's
+    /// provider and model, falling back to the default provider.
+    fn describe_model(&self, selection: Option<&SessionModel>) -> String {
+        let (provider_id, model) = match selection {
+            Some(SessionModel {
+                provider: Some(provider),
+                model,
+            }) => (Some(provider.clone()), model.clone()),
+            Some(SessionModel {
+                provider: None,
+                model: Some(model),
+            }) => (self.provider.clone(), Some(model.clone())),
+            _ => (self.provider.clone(), self.model.clone()),
+        };
+        let provider = match provider_id {
+            Some(id) => self.state.agents.get_provider(&id),
+            None => self.state.agents.default_provider(),
+        };
+        let provider_name = provider
+            .as_ref()
+            .map(|p| p.provider_id().to_string())
+            .unwrap_or_else(|| "no provider".to_string());
+        let model = model.or_else(|| {
+            provider
+                .as_ref()
+                .and_then(|p| p.configured_model().map(str::to_string))
+        });
+        match model {
+            Some(model) => format!("{provider_name}/{model}"),
+            None => provider_name,
+        }
+    }
+
+    /// Whether a file sent with `caption` should go straight to the model as a
+    /// document: the caption asks something (rather than requesting ingest)
+    /// and the default provider reads `mime` natively. Otherwise the file
+    /// takes the `receive_file` path.
+    pub fn accepts_native_document(&self, caption: &str, mime: &str) -> bool {
+        let caption = caption.trim();
+        !caption.is_empty()
+            && !caption.to_lowercase().contains("ingest")
+            && self.state.agents.supports_documents(mime)
+    }
+
+    /// Handle an uploaded file: ingest immediately when the caption asks for
+    /// it, otherwise hold supported documents as pending for `!ingest`.
+    /// Returns `None` for files the pipeline does not handle.
+    pub async fn receive_file(
+        &self,
+        session_id: &str,
+        filename: String,
+        data: Vec<u8>,
+        caption: &str,
+    ) -> Option<Result<ChannelResponse, String>> {
+        let caption = caption.trim().to_lowercase();
+        if caption.contains("ingest") {
+            return Some(
+                crate::ingest::run_ingest(&self.state, &self.data_dir, &caption, &filename, &data)
+                    .await,
+            );
+        }
+        if !opencrust_media::is_supported_for_ingest(&filename) {
+            return None;
+        }
+        let reply = format!("Received {filename}. Use !ingest to store it for future reference.");
+        self.state.set_pending_file(
+            session_id,
+            crate::state::PendingFile {
+                filename,
+                data,
+                received_at: std::time::Instant::now(),
+            },
+        );
+        Some(Ok(ChannelResponse::Text(reply)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencrust_agents::{AgentRuntime, LlmProvider, LlmRequest, LlmResponse, MessagePart};
+    use opencrust_channels::ChannelRegistry;
+    use opencrust_security::{Allowlist, DmPolicy, RespondMode};
+    use std::sync::Mutex;
+
+    struct EchoProvider;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for EchoProvider {
+        fn provider_id(&self) -> &str {
+            "echo"
+        }
+
+        async fn complete(&self, _request: &LlmRequest) -> opencrust_common::Result<LlmResponse> {
+            Ok(LlmResponse {
+                content: vec![ContentBlock::Text {
+                    text: "pong".to_string(),
+                }],
+                model: "echo-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime should build")
+            .block_on(future)
+    }
+
+    fn test_pipeline(policy: ChannelPolicy) -> MessagePipeline {
+        channel_pipeline("test", policy)
+    }
+
+    fn channel_pipeline(channel: &'static str, policy: ChannelPolicy) -> MessagePipeline {
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        let mut state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        state.allowlist = Arc::new(Mutex::new(Allowlist::restricted(Vec::new())));
+        MessagePipeline::new(channel, &Arc::new(state), &config, Arc::new(policy))
+    }
+
+    #[test]
+    fn text_turn_runs_agent_and_persists_history() {
+        let pipeline = test_pipeline(ChannelPolicy {
+            dm_policy: Some(DmPolicy::Open),
+            ..Default::default()
+        });
+        let msg = InboundMessage::text("test-chat", "alice", "Alice", "ping");
+
+        let response = block_on(pipeline.handle(msg)).expect("turn should succeed");
+
+        assert!(matches!(response, ChannelResponse::Text(ref t) if t == "pong"));
+        let history = pipeline.state().session_history("test-chat");
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn blocked_user_is_rejected_before_agent_runs() {
+        let pipeline = test_pipeline(ChannelPolicy {
+            dm_policy: Some(DmPolicy::Allowlist),
+            channel_allowlist: ["bob".to_string()].into_iter().collect(),
+            ..Default::default()
+        });
+        let msg = InboundMessage::text("test-chat", "mallory", "Mallory", "ping");
+
+        let err = block_on(pipeline.handle(msg)).expect_err("user should be blocked");
+
+        assert_eq!(err, "__blocked__");
+        assert!(pipeline.state().session_history("test-chat").is_empty());
+    }
+
+    fn open_policy() -> ChannelPolicy {
+        ChannelPolicy {
+            dm_policy: Some(DmPolicy::Open),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn slack_clear_command_resets_session() {
+        let pipeline = channel_pipeline("slack", open_policy());
+        let turn = InboundMessage::text("slack-C1", "U1", "Alice", "ping");
+        block_on(pipeline.handle(turn)).expect("turn should succeed");
+        assert_eq!(pipeline.state().session_history("slack-C1").len(), 2);
+
+        let clear = InboundMessage::text("slack-C1", "U1", "Alice", "/clear");
+        let response = block_on(pipeline.handle_command(&clear))
+            .expect("/clear should be a command")
+            .expect("/clear should succeed");
+
+        assert!(
+            matches!(response, ChannelResponse::Text(ref t) if t == "Conversation history cleared.")
+        );
+        assert!(pipeline.state().session_history("slack-C1").is_empty());
+    }
+
+    #[test]
+    fn prefix_respond_mode_gates_group_messages() {
+        let pipeline = channel_pipeline(
+            "telegram",
+            ChannelPolicy {
+                respond_mode: Some(RespondMode::Prefix),
+                ..open_policy()
+            },
+        );
+        let group =
+            |text: &str| InboundMessage::text("telegram--1", "U1", "Alice", text).with_group(true);
+
+        let ignored = block_on(pipeline.handle(group("just chatting")));
+        assert_eq!(ignored.unwrap_err(), "__blocked__");
+        assert!(pipeline.state().session_history("telegram--1").is_empty());
+
+        block_on(pipeline.handle(group("!ai ping"))).expect("turn should succeed");
+        let history = pipeline.state().session_history("telegram--1");
+        assert!(matches!(&history[0].content, MessagePart::Text(t) if t == "ping"));
+
+        // DMs never need the prefix.
+        let dm = InboundMessage::text("telegram-1", "U1", "Alice", "hello");
+        assert_eq!(pipeline.addressed(dm).unwrap().text, "hello");
+    }
+
+    #[test]
+    fn feedback_hook_scores_reply_in_memory() {
+        let config = AppConfig::default();
+        let memory = Arc::new(opencrust_db::MemoryStore::in_memory().unwrap());
+        let mut agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        agents.set_memory_provider(memory.clone());
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let pipeline = Arc::new(MessagePipeline::new(
+            "telegram",
+            &Arc::new(state),
+            &config,
+            Arc::new(open_policy()),
+        ));
+
+        block_on(async {
+            let turn = InboundMessage::text("telegram-42", "U1", "Alice", "ping");
+            pipeline.handle(turn).await.expect("turn should succeed");
+
+            let on_reaction = pipeline.feedback_hook(|chat_id, _| format!("telegram-{chat_id}"));
+            let reply = SentReply {
+                chat_id: "42".to_string(),
+                thread_id: None,
+                user_id: "U1".to_string(),
+                is_group: false,
+                text: "pong".to_string(),
+            };
+            on_reaction(reply, Feedback::Positive).await;
+
+            let entries = memory
+                .get_session_context("telegram-42", 10)
+                .await
+                .unwrap()
+                .entries;
+            let pong = entries.iter().find(|e| e.content == "pong").unwrap();
+            assert_eq!(pong.metadata["feedback"], 1);
+        });
+    }
+
+    /// Provider that answers with the model it was asked for.
+    struct ModelEchoProvider;
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ModelEchoProvider {
+        fn provider_id(&self) -> &str {
+            "cheap"
+        }
+
+        async fn complete(&self, request: &LlmRequest) -> opencrust_common::Result<LlmResponse> {
+            Ok(LlmResponse {
+                content: vec![ContentBlock::Text {
+                    text: format!("model={}", request.model),
+                }],
+                model: request.model.clone(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn channel_provider_and_model_override_default() {
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        agents.register_provider(Arc::new(ModelEchoProvider));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let state = Arc::new(state);
+        let channel_config: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "type": "whatsapp",
+            "provider": "cheap",
+            "model": "mini-1",
+        }))
+        .unwrap();
+        let pipeline = MessagePipeline::new("whatsapp", &state, &config, Arc::new(open_policy()))
+            .with_channel_settings(&channel_config);
+        let default = MessagePipeline::new("slack", &state, &config, Arc::new(open_policy()));
+
+        let reply = block_on(pipeline.handle(InboundMessage::text("wa-1", "u1", "", "hi")));
+        assert!(matches!(reply, Ok(ChannelResponse::Text(ref t)) if t == "model=mini-1"));
+        let reply = block_on(default.handle(InboundMessage::text("slack-1", "u1", "", "hi")));
+        assert!(matches!(reply, Ok(ChannelResponse::Text(ref t)) if t == "pong"));
+    }
+
+    #[tokio::test]
+    async fn generated_images_are_sent_to_the_chat() {
+        struct DrawingProvider(std::sync::atomic::AtomicUsize);
+
+        #[async_trait::async_trait]
+        impl LlmProvider for DrawingProvider {
+            fn provider_id(&self) -> &str {
+                "drawing"
+            }
+
+            async fn complete(
+                &self,
+                _request: &LlmRequest,
+            ) -> opencrust_common::Result<LlmResponse> {
+                let call = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let block = if call == 0 {
+                    ContentBlock::ToolUse {
+                        id: "call-1".to_string(),
+                        name: "generate_image".to_string(),
+                        input: serde_json::json!({"prompt": "a fox"}),
+                    }
+                } else {
+                    ContentBlock::Text {
+                        text: "Here is your fox.".to_string(),
+                    }
+                };
+                Ok(LlmResponse {
+                    content: vec![block],
+                    model: "drawing-1".to_string(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+
+            async fn health_check(&self) -> opencrust_common::Result<bool> {
+                Ok(true)
+            }
+        }
+
+        struct FakeImages;
+
+        #[async_trait::async_trait]
+        impl opencrust_agents::ImageProvider for FakeImages {
+            fn provider_id(&self) -> &str {
+                "fake"
+            }
+
+            async fn generate(
+                &self,
+                _prompt: &str,
+                _size: Option<&str>,
+            ) -> opencrust_common::Result<opencrust_agents::GeneratedImage> {
+                Ok(opencrust_agents::GeneratedImage {
+                    data: b"PNG".to_vec(),
+                    mime_type: "image/png".to_string(),
+                    revised_prompt: None,
+                })
+            }
+        }
+
+        struct RecordingSender(Arc<Mutex<Vec<Message>>>);
+
+        #[async_trait::async_trait]
+        impl opencrust_channels::ChannelSender for RecordingSender {
+            fn channel_type(&self) -> &str {
+                "telegram"
+            }
+
+            async fn send_message(&self, message: &Message) -> opencrust_common::Result<()> {
+                self.0.lock().unwrap().push(message.clone());
+                Ok(())
+            }
+        }
+
+        let config = AppConfig::default();
+        let mut agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(DrawingProvider(Default::default())));
+        agents.register_tool(Box::new(opencrust_agents::ImageGenTool::new(Arc::new(
+            FakeImages,
+        ))));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender(Arc::clone(&sent))),
+        );
+        let pipeline = MessagePipeline::new(
+            "telegram",
+            &Arc::new(state),
+            &config,
+            Arc::new(open_policy()),
+        );
+
+        let msg = InboundMessage::text("telegram-7", "7", "Ann", "draw me a fox")
+            .with_metadata(serde_json::json!({ "telegram_chat_id": 7 }));
+        let reply = pipeline.handle(msg).await.unwrap();
+
+        assert_eq!(reply.text(), "Here is your fox.");
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].metadata,
+            serde_json::json!({ "telegram_chat_id": 7 })
+        );
+        let MessageContent::Attachment(image) = &sent[0].content else {
+            panic!("expected an attachment");
+        };
+        assert_eq!(image.kind, opencrust_common::MediaKind::Photo);
+        assert_eq!(image.data, b"PNG");
+    }
+
+    #[test]
+    fn reasoning_is_returned_with_the_reply() {
+        struct ThinkingProvider;
+
+        #[async_trait::async_trait]
+        impl LlmProvider for ThinkingProvider {
+            fn provider_id(&self) -> &str {
+                "thinking"
+            }
+
+            async fn complete(
+                &self,
+                request: &LlmRequest,
+            ) -> opencrust_common::Result<LlmResponse> {
+                assert_eq!(request.thinking, Some(2048));
+                Ok(LlmResponse {
+                    content: vec![
+                        ContentBlock::Thinking {
+                            thinking: "Six times seven.".to_string(),
+                            signature: "sig".to_string(),
+                        },
+                        ContentBlock::Text {
+                            text: "42".to_string(),
+                        },
+                    ],
+                    model: "thinking-1".to_string(),
+                    usage: None,
+                    stop_reason: None,
+                })
+            }
+
+            async fn health_check(&self) -> opencrust_common::Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let config = AppConfig::default();
+        let mut agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(ThinkingProvider));
+        agents.set_thinking(2048, true);
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let pipeline = MessagePipeline::new(
+            "discord",
+            &Arc::new(state),
+            &config,
+            Arc::new(open_policy()),
+        );
+
+        let reply = block_on(pipeline.handle(InboundMessage::text("d-1", "u1", "", "6*7?")));
+        match reply {
+            Ok(ChannelResponse::Reasoning { text, reasoning }) => {
+                assert_eq!(text, "42");
+                assert_eq!(reasoning, "Six times seven.");
+            }
+            other => panic!("expected reasoning, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn model_command_switches_the_session_model() {
+        let config = AppConfig::default();
+        let mut agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        agents.register_provider(Arc::new(ModelEchoProvider));
+        agents.set_model_alias(
+            "smart",
+            SessionModel {
+                provider: Some("cheap".to_string()),
+                model: Some("big-1".to_string()),
+            },
+        );
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let pipeline =
+            MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()));
+        let send = |text: &str| {
+            let msg = InboundMessage::text("chat-1", "u1", "", text);
+            let reply = match block_on(pipeline.handle_command(&msg)) {
+                Some(reply) => reply,
+                None => block_on(pipeline.handle(msg)),
+            };
+            match reply {
+                Ok(ChannelResponse::Text(t)) => t,
+                other => panic!("unexpected reply: {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            send("/model"),
+            "Current model: echo\nAvailable: smart\nUsage: /model <alias> or /model default"
+        );
+        assert_eq!(send("/model smart"), "Switched to smart (cheap/big-1).");
+        assert_eq!(send("hi"), "model=big-1");
+        assert!(send("/model huge").contains("unknown model alias 'huge'"));
+        assert_eq!(send("/model default"), "Switched back to echo.");
+        assert_eq!(send("hi"), "pong");
+    }
+
+    #[test]
+    fn reply_with_voice_overrides_auto_reply_voice() {
+        struct FakeTts;
+        #[async_trait::async_trait]
+        impl TtsProvider for FakeTts {
+            async fn synthesize(&self, _text: &str) -> Result<Vec<u8>, String> {
+                Ok(b"OggS".to_vec())
+            }
+            fn name(&self) -> &'static str {
+                "fake"
+            }
+        }
+
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        let mut state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        state.set_tts_provider(Arc::new(FakeTts));
+        let state = Arc::new(state);
+        let settings = |on: bool| -> ChannelConfig {
+            serde_json::from_value(serde_json::json!({
+                "type": "whatsapp",
+                "reply_with_voice": on,
+            }))
+            .unwrap()
+        };
+        let voice_msg = || InboundMessage::text("wa-1", "u1", "", "hi").with_voice_reply(true);
+
+        // `voice.auto_reply_voice` is off, but the channel turns voice on.
+        let pipeline = MessagePipeline::new("whatsapp", &state, &config, Arc::new(open_policy()))
+            .with_channel_settings(&settings(true))
+            .with_voice_replies(&config);
+        let reply = block_on(pipeline.handle(voice_msg()));
+        assert!(
+            matches!(reply, Ok(ChannelResponse::Voice { ref text, ref audio }) if text == "pong" && audio == b"OggS")
+        );
+
+        let mut config_on = config.clone();
+        config_on.voice.auto_reply_voice = true;
+        let pipeline = MessagePipeline::new("whatsapp", &state, &config, Arc::new(open_policy()))
+            .with_channel_settings(&settings(false))
+            .with_voice_replies(&config_on);
+        let reply = block_on(pipeline.handle(voice_msg()));
+        assert!(matches!(reply, Ok(ChannelResponse::Text(ref t)) if t == "pong"));
+    }
+
+    #[test]
+    fn channel_allowed_tools_replace_the_global_list() {
+        let channel_config: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "type": "webchat",
+            "allowed_tools": ["web_search"],
+        }))
+        .unwrap();
+        let pipeline = channel_pipeline("webchat", open_policy());
+        assert_eq!(pipeline.guardrails.allowed_tools, None);
+
+        let pipeline = pipeline.with_channel_settings(&channel_config);
+        assert_eq!(
+            pipeline.guardrails.allowed_tools,
+            Some(vec!["web_search".to_string()])
+        );
+    }
+
+    #[test]
+    fn per_user_sessions_split_group_chats_by_sender() {
+        let channel_config: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "type": "slack",
+            "per_user_sessions": true,
+        }))
+        .unwrap();
+        let pipeline =
+            channel_pipeline("slack", open_policy()).with_channel_settings(&channel_config);
+
+        let alice = pipeline.session_id("slack-C1".to_string(), "U1", true);
+        let bob = pipeline.session_id("slack-C1".to_string(), "U2", true);
+        assert_eq!(alice, "slack-C1-U1");
+        assert_eq!(bob, "slack-C1-U2");
+        // DMs are already per user and keep the chat's session.
+        assert_eq!(
+            pipeline.session_id("slack-D1".to_string(), "U1", false),
+            "slack-D1"
+        );
+
+        block_on(pipeline.handle(InboundMessage::text(
+            alice.clone(),
+            "U1",
+            "Alice",
+            "from alice",
+        )))
+        .expect("turn should succeed");
+        block_on(pipeline.handle(InboundMessage::text(bob.clone(), "U2", "Bob", "from bob")))
+            .expect("turn should succeed");
+
+        let alice_history = pipeline.state().session_history(&alice);
+        let bob_history = pipeline.state().session_history(&bob);
+        assert_eq!(alice_history.len(), 2);
+        assert_eq!(bob_history.len(), 2);
+        assert!(matches!(&alice_history[0].content, MessagePart::Text(t) if t == "from alice"));
+        assert!(matches!(&bob_history[0].content, MessagePart::Text(t) if t == "from bob"));
+    }
+
+    #[test]
+    fn group_chats_share_a_session_by_default() {
+        let pipeline = channel_pipeline("slack", open_policy());
+        assert_eq!(
+            pipeline.session_id("slack-C1".to_string(), "U1", true),
+            "slack-C1"
+        );
+        assert_eq!(
+            pipeline.session_id("slack-C1".to_string(), "U2", true),
+            "slack-C1"
+        );
+    }
+
+    #[test]
+    fn whatsapp_accepts_bare_help_keyword() {
+        let pipeline = channel_pipeline("whatsapp", open_policy()).with_bare_commands();
+        let msg = InboundMessage::text("whatsapp-15550001", "15550001", "Alice", "Help");
+
+        let response = block_on(pipeline.handle_command(&msg))
+            .expect("bare keyword should be a command")
+            .expect("help should succeed");
+
+        assert!(matches!(response, ChannelResponse::Text(ref t) if t.contains("/clear")));
+    }
+
+    #[test]
+    fn bare_keywords_from_unpaired_users_go_to_admission() {
+        let pipeline = channel_pipeline(
+            "whatsapp",
+            ChannelPolicy {
+                dm_policy: Some(DmPolicy::Pairing),
+                ..Default::default()
+            },
+        )
+        .with_bare_commands();
+        let msg = InboundMessage::text("whatsapp-15550001", "15550001", "Alice", "help");
+
+        assert!(block_on(pipeline.handle_command(&msg)).is_none());
+        let slash = InboundMessage {
+            text: "/help".to_string(),
+            ..msg
+        };
+        assert!(block_on(pipeline.handle_command(&slash)).is_some());
+    }
+
+    #[test]
+    fn bare_keywords_are_chat_without_opt_in() {
+        let pipeline = channel_pipeline("slack", open_policy());
+        let msg = InboundMessage::text("slack-C1", "U1", "Alice", "help");
+
+        assert!(block_on(pipeline.handle_command(&msg)).is_none());
+    }
+
+    /// Provider that reads PDFs natively and records the blocks it was sent.
+    struct PdfProvider {
+        seen: Arc<Mutex<Vec<ContentBlock>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for PdfProvider {
+        fn provider_id(&self) -> &str {
+            "pdf"
+        }
+
+        fn supports_documents(&self, mime: &str) -> bool {
+            mime == "application/pdf"
+        }
+
+        async fn complete(&self, request: &LlmRequest) -> opencrust_common::Result<LlmResponse> {
+            if let Some(MessagePart::Parts(blocks)) =
+                request.messages.last().map(|m| m.content.clone())
+            {
+                *self.seen.lock().unwrap() = blocks;
+            }
+            Ok(LlmResponse {
+                content: vec![ContentBlock::Text {
+                    text: "read it".to_string(),
+                }],
+                model: "pdf-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn native_documents_only_for_supported_types_with_a_question() {
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(PdfProvider {
+            seen: Arc::new(Mutex::new(Vec::new())),
+        }));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let pipeline =
+            MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()));
+
+        assert!(pipeline.accepts_native_document("What is the total?", "application/pdf"));
+        assert!(!pipeline.accepts_native_document("", "application/pdf"));
+        assert!(!pipeline.accepts_native_document("ingest this", "application/pdf"));
+        assert!(!pipeline.accepts_native_document("What is the total?", "text/csv"));
+
+        // The echo provider has no native document support.
+        let echo = test_pipeline(open_policy());
+        assert!(!echo.accepts_native_document("What is the total?", "application/pdf"));
+    }
+
+    #[test]
+    fn document_turn_sends_document_block() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(PdfProvider { seen: seen.clone() }));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        let pipeline =
+            MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()));
+
+        let msg = InboundMessage::text("doc-chat", "alice", "Alice", "What is the total?")
+            .with_document("application/pdf", b"%PDF-", Some("Total: 42".to_string()));
+        let response = block_on(pipeline.run_turn(msg)).expect("turn should succeed");
+
+        assert!(matches!(response, ChannelResponse::Text(ref t) if t == "read it"));
+        let seen = seen.lock().unwrap();
+        assert!(matches!(
+            &seen[0],
+            ContentBlock::Document { mime, url, text }
+                if mime == "application/pdf"
+                    && url == "data:application/pdf;base64,JVBERi0="
+                    && text.as_deref() == Some("Total: 42")
+        ));
+        assert!(matches!(&seen[1], ContentBlock::Text { text } if text == "What is the total?"));
+    }
+
+    /// Provider that answers with a fixed summary and records the prompts it saw.
+    struct SummaryProvider {
+        seen: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for SummaryProvider {
+        fn provider_id(&self) -> &str {
+            "summary"
+        }
+
+        async fn complete(&self, request: &LlmRequest) -> opencrust_common::Result<LlmResponse> {
+            if let Some(MessagePart::Text(text)) = request.messages.last().map(|m| &m.content) {
+                self.seen.lock().unwrap().push(text.clone());
+            }
+            Ok(LlmResponse {
+                content: vec![ContentBlock::Text {
+                    text: "- Alice is planning a trip to Lisbon".to_string(),
+                }],
+                model: "summary-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn summary_pipeline(seen: Arc<Mutex<Vec<String>>>) -> MessagePipeline {
+        let config = AppConfig::default();
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(SummaryProvider { seen }));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()))
+    }
+
+    fn seeded_history() -> Vec<opencrust_agents::ChatMessage> {
+        use opencrust_agents::{ChatMessage, ChatRole};
+        vec![
+            ChatMessage {
+                role: ChatRole::User,
+                content: MessagePart::Text("I want to visit Lisbon in May".to_string()),
+            },
+            ChatMessage {
+                role: ChatRole::Assistant,
+                content: MessagePart::Text("May is a great time for Lisbon.".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn summarize_command_summarizes_seeded_history() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let pipeline = summary_pipeline(seen.clone());
+        block_on(pipeline.state().import_session_history(
+            "trip-chat",
+            None,
+            seeded_history(),
+            false,
+        ));
+
+        let msg = InboundMessage::text("trip-chat", "alice", "Alice", "/summarize");
+        let response = block_on(pipeline.handle_command(&msg))
+            .expect("/summarize should be a command")
+            .expect("summary should succeed");
+
+        assert!(
+            matches!(response, ChannelResponse::Text(ref t) if t == "- Alice is planning a trip to Lisbon")
+        );
+        let prompts = seen.lock().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains("User: I want to visit Lisbon in May"));
+        assert!(prompts[0].contains("Assistant: May is a great time for Lisbon."));
+        // Without `save`, the running summary is untouched.
+        assert!(pipeline.state().session_summary("trip-chat").is_none());
+    }
+
+    #[test]
+    fn summarize_save_stores_running_summary() {
+        let pipeline = summary_pipeline(Arc::new(Mutex::new(Vec::new())));
+        block_on(pipeline.state().import_session_history(
+            "trip-chat",
+            None,
+            seeded_history(),
+            false,
+        ));
+
+        let msg = InboundMessage::text("trip-chat", "alice", "Alice", "/summarize save");
+        block_on(pipeline.handle_command(&msg))
+            .expect("/summarize should be a command")
+            .expect("summary should succeed");
+
+        assert_eq!(
+            pipeline.state().session_summary("trip-chat").as_deref(),
+            Some("- Alice is planning a trip to Lisbon")
+        );
+    }
+
+    #[test]
+    fn summarize_empty_session_skips_the_model() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let pipeline = summary_pipeline(seen.clone());
+
+        let msg = InboundMessage::text("new-chat", "alice", "Alice", "/summarize");
+        let response = block_on(pipeline.handle_command(&msg))
+            .expect("/summarize should be a command")
+            .expect("empty summary should succeed");
+
+        assert!(
+            matches!(response, ChannelResponse::Text(ref t) if t == "Nothing to summarize yet.")
+        );
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    fn inbound_limit_pipeline(limit: usize) -> MessagePipeline {
+        let mut config = AppConfig::default();
+        config.security.max_inbound_chars = Some(limit);
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(EchoProvider));
+        let state =
+            crate::state::AppState::new(config.clone(), Arc::new(agents), ChannelRegistry::new());
+        MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()))
+    }
+
+    #[test]
+    fn over_limit_message_gets_too_long_reply() {
+        let pipeline = inbound_limit_pipeline(10);
+        let msg = InboundMessage::text("chat", "alice", "Alice", "a".repeat(11));
+
+        let response = block_on(pipeline.handle(msg)).expect("should reply, not fail");
+
+        assert!(
+            matches!(response, ChannelResponse::Text(ref t) if t.contains("too long") && t.contains("limit is 10"))
+        );
+        assert!(pipeline.state().session_history("chat").is_empty());
+    }
+
+    #[test]
+    fn at_limit_message_runs_agent() {
+        let pipeline = inbound_limit_pipeline(10);
+        let msg = InboundMessage::text("chat", "alice", "Alice", "a".repeat(10));
+
+        let response = block_on(pipeline.handle(msg)).expect("turn should succeed");
+
+        assert!(matches!(response, ChannelResponse::Text(ref t) if t == "pong"));
+        assert_eq!(pipeline.state().session_history("chat").len(), 2);
+    }
+
+    #[test]
+    fn file_upload_is_exempt_from_inbound_limit() {
+        let pipeline = inbound_limit_pipeline(10);
+        let mut msg = InboundMessage::text("chat", "alice", "Alice", "a".repeat(50));
+        msg.attachments.push(ContentBlock::Text {
+            text: "document contents".to_string(),
+        });
+
+        let response = block_on(pipeline.handle(msg)).expect("turn should succeed");
+
+        assert!(matches!(response, ChannelResponse::Text(ref t) if t == "pong"));
+    }
+
+    #[test]
+    fn link_command_joins_accounts_across_channels() {
+        let pipeline = channel_pipeline("discord", ChannelPolicy::default());
+        let state = pipeline.state();
+        state.allowlist.lock().unwrap().claim_owner("tg-1");
+
+        let stranger = InboundMessage::text("discord-9", "dc-9", "Eve", "/link");
+        assert!(
+            block_on(pipeline.handle_command(&stranger))
+                .unwrap()
+                .is_err()
+        );
+
+        let owner = InboundMessage::text("telegram-1", "tg-1", "Alice", "/link");
+        let reply = block_on(pipeline.handle_command(&owner)).unwrap().unwrap();
+        let code = reply.text()["Link code: ".len()..][..6].to_string();
+
+        let guess = InboundMessage::text("discord-9", "dc-9", "Eve", "/link 000000");
+        let reply = block_on(pipeline.handle_command(&guess)).unwrap();
+        assert_eq!(reply.unwrap_err(), "__blocked__");
+
+        let other = InboundMessage::text("discord-1", "dc-1", "Alice", format!("/link {code}"));
+        let reply = block_on(pipeline.handle_command(&other)).unwrap().unwrap();
+        assert_eq!(reply.text(), LINKED_REPLY);
+        assert!(state.allowlist.lock().unwrap().is_owner("dc-1"));
+        assert_eq!(
+            state.continuity_key(Some("dc-1")),
+            Some("bus:user:tg-1".to_string())
+        );
+        assert_eq!(
+            state.continuity_key(Some("tg-1")),
+            state.continuity_key(Some("dc-1"))
+        );
+    }
+
+    #[test]
+    fn announce_broadcasts_to_allowed_users_for_owner_only() {
+        struct CountingSender(Arc<Mutex<usize>>);
+
+        #[async_trait::async_trait]
+        impl opencrust_channels::ChannelSender for CountingSender {
+            fn channel_type(&self) -> &str {
+                "telegram"
+            }
+
+            async fn send_message(
+                &self,
+                _message: &opencrust_common::Message,
+            ) -> opencrust_common::Result<()> {
+                *self.0.lock().unwrap() += 1;
+                Ok(())
+            }
+        }
+
+        let pipeline = channel_pipeline("telegram", ChannelPolicy::default());
+        let sent = Arc::new(Mutex::new(0));
+        pipeline.state().channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(CountingSender(Arc::clone(&sent))),
+        );
+        {
+            let mut list = pipeline.state().allowlist.lock().unwrap();
+            list.claim_owner("1");
+            list.add("2");
+            list.add("3");
+        }
+        // Only users seen on this channel are messaged; "3" never used it.
+        for user in ["1", "2"] {
+            block_on(pipeline.state().persist_turn(
+                &format!("telegram-{user}"),
+                Some("telegram"),
+                Some(user),
+                "hi",
+                "hello",
+                None,
+            ));
+        }
+
+        let guest = InboundMessage::text("telegram-2", "2", "Bob", "/announce hi");
+        let reply = block_on(pipeline.handle_command(&guest)).unwrap().unwrap();
+        assert!(reply.text().contains("Only the bot owner"));
+        assert_eq!(*sent.lock().unwrap(), 0);
+
+        let owner = InboundMessage::text("telegram-1", "1", "Alice", "/announce Back at 5pm");
+        let reply = block_on(pipeline.handle_command(&owner)).unwrap().unwrap();
+        assert_eq!(reply.text(), "Announcement sent to 2 user(s), 0 failed.");
+        assert_eq!(*sent.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn maintenance_replies_and_replays_held_messages() {
+        struct RecordingSender(Arc<Mutex<Vec<(String, serde_json::Value)>>>);
+
+        #[async_trait::async_trait]
+        impl opencrust_channels::ChannelSender for RecordingSender {
+            fn channel_type(&self) -> &str {
+                "telegram"
+            }
+
+            async fn send_message(
+                &self,
+                message: &opencrust_common::Message,
+            ) -> opencrust_common::Result<()> {
+                let opencrust_common::MessageContent::Text(text) = &message.content else {
+                    panic!("expected a text reply");
+                };
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((text.clone(), message.metadata.clone()));
+                Ok(())
+            }
+        }
+
+        let pipeline = channel_pipeline("telegram", open_policy()).with_name("support");
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        pipeline.state().channel_senders.insert(
+            "support".to_string(),
+            Arc::new(RecordingSender(Arc::clone(&sent))),
+        );
+        let msg = || {
+            InboundMessage::text("telegram-7", "7", "Ann", "ping")
+                .with_metadata(serde_json::json!({ "telegram_chat_id": 7 }))
+        };
+
+        pipeline.state().start_maintenance("support", None, false);
+        let reply = pipeline.handle(msg()).await.unwrap();
+        assert_eq!(reply.text(), crate::state::DEFAULT_MAINTENANCE_MESSAGE);
+        assert_eq!(pipeline.state().end_maintenance("support"), Some(0));
+
+        pipeline
+            .state()
+            .start_maintenance("support", Some("Back soon".to_string()), true);
+        let reply = pipeline.handle(msg()).await.unwrap();
+        assert_eq!(reply.text(), "Back soon");
+        assert!(pipeline.state().session_history("telegram-7").is_empty());
+        assert_eq!(
+            pipeline
+                .state()
+                .maintenance_status("support")
+                .unwrap()
+                .queued,
+            1
+        );
+
+        // A flood from one session is held only up to the per-session cap.
+        for _ in 0..crate::state::MAX_HELD_PER_SESSION + 2 {
+            pipeline.handle(msg()).await.unwrap();
+        }
+        assert_eq!(
+            pipeline
+                .state()
+                .maintenance_status("support")
+                .unwrap()
+                .queued,
+            crate::state::MAX_HELD_PER_SESSION
+        );
+        pipeline.state().end_maintenance("support");
+        let sent_count = || sent.lock().unwrap().len();
+        for _ in 0..100 {
+            if sent_count() == crate::state::MAX_HELD_PER_SESSION {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        sent.lock().unwrap().clear();
+
+        pipeline
+            .state()
+            .start_maintenance("support", Some("Back soon".to_string()), true);
+        pipeline.handle(msg()).await.unwrap();
+        assert_eq!(pipeline.state().end_maintenance("support"), Some(1));
+        for _ in 0..100 {
+            if !sent.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let sent = sent.lock().unwrap();
+        assert_eq!(
+            *sent,
+            [(
+                "pong".to_string(),
+                serde_json::json!({ "telegram_chat_id": 7 })
+            )]
+        );
+        assert!(pipeline.state().maintenance_status("support").is_none());
+    }
+
+    #[test]
+    fn tag_command_tags_the_session() {
+        let config = AppConfig::default();
+        let store = Arc::new(opencrust_db::SessionStore::in_memory().unwrap());
+        let mut state = crate::state::AppState::new(
+            config.clone(),
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        state.set_session_store(Arc::clone(&store));
+        let pipeline =
+            MessagePipeline::new("test", &Arc::new(state), &config, Arc::new(open_policy()));
+        let tag = |text: &str| {
+            let msg = InboundMessage::text("chat-1", "alice", "Alice", text);
+            block_on(pipeline.handle_command(&msg))
+                .unwrap()
+                .unwrap()
+                .text()
+                .to_string()
+        };
+
+        assert_eq!(tag("/tag #Work"), "Tagged this conversation \"work\".");
+        assert_eq!(
+            tag("/tag work"),
+            "This conversation is already tagged \"work\"."
+        );
+        assert!(tag("/tag no,commas").starts_with("Invalid tag"));
+        tag("/tag recipes");
+        assert_eq!(tag("/tag"), "Tags: recipes, work");
+        assert_eq!(store.sessions_with_tag("work").unwrap(), vec!["chat-1"]);
+    }
+}
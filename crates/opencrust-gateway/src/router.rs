@@ -7,11 +7,10 @@ use axum::extract::Query;
 use axum::http::{Request, StatusCode};
 use axum::middleware::Next;
 use axum::response::{Html, IntoResponse, Redirect};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use opencrust_security::credentials::vault_passphrase_available;
 use tower_governor::GovernorLayer;
 use tower_governor::governor::GovernorConfigBuilder;
-use tower_http::services::ServeDir;
 use url::form_urlencoded;
 
 use crate::a2a;
@@ -25,6 +24,9 @@ pub fn build_router(
     whatsapp_state: opencrust_channels::whatsapp::webhook::WhatsAppState,
     line_state: opencrust_channels::line::webhook::LineWebhookState,
     wechat_state: opencrust_channels::wechat::webhook::WeChatWebhookState,
+    teams_state: opencrust_channels::teams::webhook::TeamsWebhookState,
+    webchat_state: opencrust_channels::webchat::socket::WebChatState,
+    connector_state: opencrust_channels::ConnectorState,
 ) -> Router {
     // Per-IP rate limit from config (default: 1 req/sec, burst 60).
     let rl = &state.config.gateway.rate_limit;
@@ -58,6 +60,10 @@ pub fn build_router(
             "/webhooks/line",
             post(opencrust_channels::line::webhook::line_webhook),
         )
+        .route(
+            "/webhooks/line/media/{id}",
+            get(opencrust_channels::line::webhook::line_media),
+        )
         .with_state(line_state);
 
     let wechat_routes = Router::new()
@@ -68,6 +74,35 @@ pub fn build_router(
         )
         .with_state(wechat_state);
 
+    let teams_routes = Router::new()
+        .route(
+            "/webhooks/teams",
+            post(opencrust_channels::teams::webhook::teams_webhook),
+        )
+        .with_state(teams_state);
+
+    let webchat_routes = Router::new()
+        .route(
+            "/webchat",
+            get(opencrust_channels::webchat::socket::webchat_page),
+        )
+        .route(
+            "/webchat/widget.js",
+            get(opencrust_channels::webchat::socket::webchat_widget),
+        )
+        .route(
+            "/webchat/ws",
+            get(opencrust_channels::webchat::socket::webchat_socket),
+        )
+        .with_state(webchat_state);
+
+    let connector_routes = Router::new()
+        .route(
+            "/connector",
+            get(opencrust_channels::connector::socket::connector_socket),
+        )
+        .with_state(connector_state);
+
     let protected_integration_routes = Router::new()
         .route(
             "/api/integrations/google",
@@ -94,15 +129,34 @@ pub fn build_router(
             post(disconnect_google_integration),
         )
         .route("/api/security/vault", get(get_vault_status))
-        .route("/api/sessions/{id}/history", get(api::session_history))
+        .route(
+            "/api/sessions/{id}/history",
+            get(api::session_history).post(api::import_session_history),
+        )
         .route("/api/sessions/{id}/upload", post(upload_file))
+        .route("/api/embeddings", post(api::embeddings))
+        .route("/api/channels/{channel}/broadcast", post(api::broadcast))
+        .route("/api/broadcast", post(api::broadcast_to_owners))
+        .route(
+            "/api/channels/{channel}/maintenance",
+            get(api::channel_maintenance)
+                .put(api::start_channel_maintenance)
+                .delete(api::end_channel_maintenance),
+        )
+        .route(
+            "/api/scheduled-messages",
+            get(api::list_scheduled_messages).post(api::schedule_message),
+        )
+        .route(
+            "/api/scheduled-messages/{id}",
+            delete(api::cancel_scheduled_message),
+        )
         .route_layer(axum::middleware::from_fn_with_state(
             state.clone(),
             require_gateway_api_key,
         ));
 
-    Router::new()
-        .route("/", get(web_chat))
+    let router = Router::new()
         .route("/health", get(health))
         .route("/ws", get(ws::ws_handler))
         .route("/api/status", get(status))
@@ -124,44 +178,24 @@ pub fn build_router(
         .route("/a2a/tasks", post(a2a::create_task))
         .route("/a2a/tasks/{id}", get(a2a::get_task))
         .route("/a2a/tasks/{id}/cancel", post(a2a::cancel_task))
-        .nest_service("/assets", ServeDir::new("assets"))
         .merge(protected_integration_routes)
         .merge(whatsapp_routes)
         .merge(line_routes)
         .merge(wechat_routes)
-        .with_state(state)
-        .layer(governor_layer)
+        .merge(teams_routes)
+        .merge(webchat_routes)
+        .merge(connector_routes);
+
+    #[cfg(feature = "web-ui")]
+    let router = router.merge(crate::web_ui::routes());
+
+    router.with_state(state).layer(governor_layer)
 }
 
 async fn health() -> &'static str {
     "ok"
 }
 
-async fn web_chat(axum::extract::State(state): axum::extract::State<SharedState>) -> Html<String> {
-    let html =
-        if let Ok(content) = std::fs::read_to_string("crates/opencrust-gateway/src/webchat.html") {
-            content
-        } else {
-            include_str!("webchat.html").to_string()
-        };
-
-    // Issue a short-lived webchat token and inject it instead of the real
-    // gateway API key.  The token is validated server-side on WebSocket
-    // upgrade so the real key is never exposed in the page source.
-    let inject = if state.config.gateway.api_key.is_some() {
-        let token = state.issue_webchat_token();
-        format!(
-            "<script>window.__OPENCRUST_GATEWAY_KEY__={:?};</script>",
-            token
-        )
-    } else {
-        String::new()
-    };
-
-    let html = html.replacen("</head>", &format!("{inject}</head>"), 1);
-    Html(html)
-}
-
 async fn status(
     axum::extract::State(state): axum::extract::State<SharedState>,
 ) -> axum::Json<serde_json::Value> {
@@ -172,13 +206,16 @@ async fn status(
         .map(|s| s.to_string())
         .collect();
 
-    // Include channels registered via sender handles (the primary source).
-    for entry in state.channel_senders.iter() {
-        let ct = entry.key().clone();
-        if !channels.contains(&ct) {
-            channels.push(ct);
-        }
-    }
+    // Include channels registered via sender handles (the primary source),
+    // sorted so the listing is stable.
+    let mut senders: Vec<String> = state
+        .channel_senders
+        .iter()
+        .map(|entry| entry.key().clone())
+        .filter(|name| !channels.contains(name))
+        .collect();
+    senders.sort();
+    channels.extend(senders);
 
     // Gather LLM provider info from config
     let llm: serde_json::Value = state
@@ -205,6 +242,27 @@ async fn status(
         "sessions": state.sessions.len(),
         "llm": llm,
     });
+    if !state.mcp_startup.is_empty() {
+        resp["mcp"] = state
+            .mcp_startup
+            .iter()
+            .map(|(server, result)| match result {
+                Ok(tools) => serde_json::json!({
+                    "server": server,
+                    "status": "connected",
+                    "tools": tools,
+                }),
+                Err(error) => serde_json::json!({
+                    "server": server,
+                    "status": "failed",
+                    "error": error,
+                }),
+            })
+            .collect();
+    }
+    if !state.startup.is_empty() {
+        resp["startup_failures"] = serde_json::json!(state.startup.failures());
+    }
     if let Some(latest) = latest_version {
         let current = env!("CARGO_PKG_VERSION");
         if latest.trim_start_matches('v') != current {
@@ -239,36 +297,43 @@ async fn require_gateway_api_key(
             .into_response();
     };
 
-    let token_from_header = req
-        .headers()
+    if request_has_api_key(req.headers(), req.uri(), configured_key) {
+        next.run(req).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({
+                "status": "error",
+                "message": "Invalid or missing gateway API key.",
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// True when the request presents `configured_key`, either as a bearer token
+/// or as a `token` / `api_key` query parameter.
+pub(crate) fn request_has_api_key(
+    headers: &axum::http::HeaderMap,
+    uri: &axum::http::Uri,
+    configured_key: &str,
+) -> bool {
+    let token_from_header = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|value| value.to_str().ok())
         .map(|value| value.strip_prefix("Bearer ").unwrap_or(value).to_string());
 
-    let token_from_query = req.uri().query().and_then(|query| {
+    let token_from_query = uri.query().and_then(|query| {
         form_urlencoded::parse(query.as_bytes())
             .find(|(key, _)| key == "token" || key == "api_key")
             .map(|(_, value)| value.into_owned())
     });
 
-    let valid = token_from_header
+    token_from_header
         .or(token_from_query)
         .as_deref()
         .map(|token| constant_time_token_eq(token, configured_key))
-        .unwrap_or(false);
-
-    if valid {
-        next.run(req).await
-    } else {
-        (
-            StatusCode::UNAUTHORIZED,
-            axum::Json(serde_json::json!({
-                "status": "error",
-                "message": "Invalid or missing gateway API key.",
-            })),
-        )
-            .into_response()
-    }
+        .unwrap_or(false)
 }
 
 fn constant_time_token_eq(left: &str, right: &str) -> bool {
@@ -1076,6 +1141,8 @@ const KNOWN_PROVIDERS: &[(&str, &str, bool)] = &[
     ("openai", "OpenAI", true),
     ("deepseek", "DeepSeek", true),
     ("mistral", "Mistral", true),
+    ("groq", "Groq", true),
+    ("grok", "xAI Grok", true),
     ("sansa", "Sansa", true),
     ("gemini", "Google Gemini", true),
     ("falcon", "Falcon", true),
@@ -1085,8 +1152,10 @@ const KNOWN_PROVIDERS: &[(&str, &str, bool)] = &[
     ("cohere", "Cohere", true),
     ("minimax", "MiniMax", true),
     ("moonshot", "Moonshot K2", true),
+    ("openrouter", "OpenRouter", true),
     ("ollama", "Ollama", false),
     ("vllm", "vLLM", false),
+    ("llamacpp", "llama.cpp", false),
 ];
 
 /// GET /api/providers — list known provider types with activation status.
@@ -1283,26 +1352,83 @@ async fn add_provider(
             state.agents.register_provider(Arc::new(provider));
             persist_api_key("MISTRAL_API_KEY", key);
         }
-        "gemini" => {
+        "groq" => {
             let Some(key) = &body.api_key else {
                 return (
                     axum::http::StatusCode::BAD_REQUEST,
                     axum::Json(serde_json::json!({
                         "status": "error",
-                        "message": "api_key is required for gemini",
+                        "message": "api_key is required for groq",
                     })),
                 );
             };
-            let base_url = body.base_url.clone().or_else(|| {
-                Some("https://generativelanguage.googleapis.com/v1beta/openai/".to_string())
-            });
+            let base_url = body
+                .base_url
+                .clone()
+                .or_else(|| Some(crate::bootstrap::GROQ_DEFAULT_BASE_URL.to_string()));
             let model = body
                 .model
                 .clone()
-                .or_else(|| Some("gemini-2.5-flash".to_string()));
+                .or_else(|| Some(crate::bootstrap::GROQ_DEFAULT_MODEL.to_string()));
             let provider = opencrust_agents::OpenAiProvider::new(key.clone(), model, base_url)
-                .with_name("gemini");
+                .with_name("groq");
             state.agents.register_provider(Arc::new(provider));
+            persist_api_key("GROQ_API_KEY", key);
+        }
+        "grok" => {
+            let Some(key) = &body.api_key else {
+                return (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    axum::Json(serde_json::json!({
+                        "status": "error",
+                        "message": "api_key is required for grok",
+                    })),
+                );
+            };
+            let base_url = body
+                .base_url
+                .clone()
+                .or_else(|| Some(crate::bootstrap::GROK_DEFAULT_BASE_URL.to_string()));
+            let model = body
+                .model
+                .clone()
+                .or_else(|| Some(crate::bootstrap::GROK_DEFAULT_MODEL.to_string()));
+            let provider = opencrust_agents::OpenAiProvider::new(key.clone(), model, base_url)
+                .with_name("grok");
+            state.agents.register_provider(Arc::new(provider));
+            persist_api_key("GROK_API_KEY", key);
+        }
+        "gemini" => {
+            let Some(key) = &body.api_key else {
+                return (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    axum::Json(serde_json::json!({
+                        "status": "error",
+                        "message": "api_key is required for gemini",
+                    })),
+                );
+            };
+            if crate::bootstrap::is_gemini_openai_url(body.base_url.as_deref()) {
+                let model = body
+                    .model
+                    .clone()
+                    .or_else(|| Some("gemini-2.5-flash".to_string()));
+                let provider = opencrust_agents::OpenAiProvider::new(
+                    key.clone(),
+                    model,
+                    body.base_url.clone(),
+                )
+                .with_name("gemini");
+                state.agents.register_provider(Arc::new(provider));
+            } else {
+                let provider = opencrust_agents::GeminiProvider::new(
+                    key.clone(),
+                    body.model.clone(),
+                    body.base_url.clone(),
+                )
+                .with_name("gemini");
+                state.agents.register_provider(Arc::new(provider));
+            }
             persist_api_key("GEMINI_API_KEY", key);
         }
         "falcon" => {
@@ -1459,11 +1585,47 @@ async fn add_provider(
             state.agents.register_provider(Arc::new(provider));
             persist_api_key("MOONSHOT_API_KEY", key);
         }
+        "openrouter" => {
+            let Some(key) = &body.api_key else {
+                return (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    axum::Json(serde_json::json!({
+                        "status": "error",
+                        "message": "api_key is required for openrouter",
+                    })),
+                );
+            };
+            let base_url = body
+                .base_url
+                .clone()
+                .or_else(|| Some("https://openrouter.ai/api/v1".to_string()));
+            let model = body
+                .model
+                .clone()
+                .or_else(|| Some("openrouter/auto".to_string()));
+            let provider = opencrust_agents::OpenAiProvider::new(key.clone(), model, base_url)
+                .with_name("openrouter")
+                .with_header(
+                    "HTTP-Referer",
+                    crate::bootstrap::OPENROUTER_DEFAULT_SITE_URL,
+                )
+                .with_header("X-Title", crate::bootstrap::OPENROUTER_DEFAULT_APP_NAME);
+            state.agents.register_provider(Arc::new(provider));
+            persist_api_key("OPENROUTER_API_KEY", key);
+        }
         "ollama" => {
             let provider =
                 opencrust_agents::OllamaProvider::new(body.model.clone(), body.base_url.clone());
             state.agents.register_provider(Arc::new(provider));
         }
+        "llamacpp" => {
+            let provider = opencrust_agents::LlamaCppProvider::new(
+                body.model.clone(),
+                body.base_url.clone(),
+                body.api_key.clone(),
+            );
+            state.agents.register_provider(Arc::new(provider));
+        }
         other => {
             return (
                 axum::http::StatusCode::BAD_REQUEST,
@@ -1912,4 +2074,591 @@ mod tests {
                 || html.contains("<p>&lt;script&gt;alert('xss')&lt;/script&gt;</p>")
         );
     }
+
+    struct MockEmbeddings;
+
+    #[async_trait::async_trait]
+    impl opencrust_agents::EmbeddingProvider for MockEmbeddings {
+        fn provider_id(&self) -> &str {
+            "mock"
+        }
+
+        fn model(&self) -> &str {
+            "mock-embed"
+        }
+
+        async fn embed_documents(
+            &self,
+            texts: &[String],
+        ) -> opencrust_common::Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| vec![t.len() as f32; 4]).collect())
+        }
+
+        async fn embed_query(&self, text: &str) -> opencrust_common::Result<Vec<f32>> {
+            Ok(vec![text.len() as f32; 4])
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn embeddings_router(state: SharedState) -> Router {
+        Router::new()
+            .route("/api/embeddings", post(api::embeddings))
+            .route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                require_gateway_api_key,
+            ))
+            .with_state(state)
+    }
+
+    fn embeddings_request(token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/api/embeddings")
+            .header(axum::http::header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header(axum::http::header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        builder
+            .body(Body::from(r#"{"input":["hello","world!"]}"#))
+            .unwrap()
+    }
+
+    #[test]
+    fn embeddings_returns_vectors_from_provider() {
+        let mut agents = AgentRuntime::new();
+        agents.set_embedding_provider(Arc::new(MockEmbeddings));
+        let mut config = AppConfig::default();
+        config.gateway.api_key = Some("secret-token".to_string());
+        let state = Arc::new(crate::state::AppState::new(
+            config,
+            Arc::new(agents),
+            ChannelRegistry::new(),
+        ));
+        let router = embeddings_router(state);
+
+        let unauthorized = block_on(router.clone().oneshot(embeddings_request(None)))
+            .expect("request should complete");
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = block_on(router.oneshot(embeddings_request(Some("secret-token"))))
+            .expect("request should complete");
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = block_on(axum::body::to_bytes(resp.into_body(), usize::MAX)).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["model"], "mock-embed");
+        let data = json["data"].as_array().unwrap();
+        assert_eq!(data.len(), 2);
+        for (i, item) in data.iter().enumerate() {
+            assert_eq!(item["index"], i);
+            assert_eq!(item["embedding"].as_array().unwrap().len(), 4);
+        }
+        assert_eq!(data[1]["embedding"][0], 6.0);
+    }
+
+    #[test]
+    fn embeddings_unavailable_without_provider() {
+        let router = embeddings_router(test_state(Some("secret-token")));
+        let resp = block_on(router.oneshot(embeddings_request(Some("secret-token"))))
+            .expect("request should complete");
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    /// Provider that calls `lookup` on the first round and answers on the next.
+    struct LookupCallingProvider {
+        rounds: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl opencrust_agents::LlmProvider for LookupCallingProvider {
+        fn provider_id(&self) -> &str {
+            "lookup-caller"
+        }
+
+        async fn complete(
+            &self,
+            _request: &opencrust_agents::LlmRequest,
+        ) -> opencrust_common::Result<opencrust_agents::LlmResponse> {
+            use opencrust_agents::ContentBlock;
+            let round = self
+                .rounds
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let content = if round.is_multiple_of(2) {
+                vec![ContentBlock::ToolUse {
+                    id: format!("tu_{round}"),
+                    name: "lookup".to_string(),
+                    input: serde_json::json!({ "key": "answer" }),
+                }]
+            } else {
+                vec![ContentBlock::Text {
+                    text: "42".to_string(),
+                }]
+            };
+            Ok(opencrust_agents::LlmResponse {
+                content,
+                model: "lookup-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    struct LookupTool;
+
+    #[async_trait::async_trait]
+    impl opencrust_agents::Tool for LookupTool {
+        fn name(&self) -> &str {
+            "lookup"
+        }
+
+        fn description(&self) -> &str {
+            "looks up a key"
+        }
+
+        fn input_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object" })
+        }
+
+        async fn execute(
+            &self,
+            _context: &opencrust_agents::ToolContext,
+            _input: serde_json::Value,
+        ) -> opencrust_common::Result<opencrust_agents::ToolOutput> {
+            Ok(opencrust_agents::ToolOutput::success("answer=42"))
+        }
+    }
+
+    fn chat_state() -> (SharedState, String) {
+        let mut agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(LookupCallingProvider {
+            rounds: std::sync::atomic::AtomicUsize::new(0),
+        }));
+        agents.register_tool(Box::new(LookupTool));
+        let mut config = AppConfig::default();
+        config.gateway.api_key = Some("secret-token".to_string());
+        let state = Arc::new(crate::state::AppState::new(
+            config,
+            Arc::new(agents),
+            ChannelRegistry::new(),
+        ));
+        let session_id = state.create_session();
+        (state, session_id)
+    }
+
+    fn chat_request(session_id: &str, query: &str, token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri(format!("/api/sessions/{session_id}/messages{query}"))
+            .header(axum::http::header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header(axum::http::header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        builder
+            .body(Body::from(r#"{"content":"what is the answer?"}"#))
+            .unwrap()
+    }
+
+    fn chat_json(router: Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let resp = block_on(router.oneshot(request)).expect("request should complete");
+        let status = resp.status();
+        let body = block_on(axum::body::to_bytes(resp.into_body(), usize::MAX)).unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn chat_router(state: SharedState) -> Router {
+        Router::new()
+            .route("/api/sessions/{id}/messages", post(api::send_message))
+            .with_state(state)
+    }
+
+    #[test]
+    fn send_message_debug_includes_tool_call_trace() {
+        let (state, session_id) = chat_state();
+        let (status, json) = chat_json(
+            chat_router(state),
+            chat_request(&session_id, "?debug=1", Some("secret-token")),
+        );
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["content"], "42");
+        assert_eq!(json["trace"]["iterations"], 2);
+        let calls = json["trace"]["tool_calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0]["name"], "lookup");
+        assert_eq!(calls[0]["input"], r#"{"key":"answer"}"#);
+        assert_eq!(calls[0]["output"], "answer=42");
+        assert_eq!(calls[0]["is_error"], false);
+    }
+
+    #[test]
+    fn send_message_omits_trace_without_debug() {
+        let (state, session_id) = chat_state();
+        let (status, json) = chat_json(chat_router(state), chat_request(&session_id, "", None));
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["content"], "42");
+        assert!(json.get("trace").is_none());
+    }
+
+    #[test]
+    fn send_message_debug_requires_api_key() {
+        let (state, session_id) = chat_state();
+        let router = chat_router(state);
+
+        let (status, _) = chat_json(router.clone(), chat_request(&session_id, "?debug=1", None));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = chat_json(router, chat_request(&session_id, "?debug=1", Some("wrong")));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    /// Provider that records the conversation it was sent and replies "ok".
+    struct RecordingProvider {
+        seen: Arc<Mutex<Vec<opencrust_agents::ChatMessage>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl opencrust_agents::LlmProvider for RecordingProvider {
+        fn provider_id(&self) -> &str {
+            "recording"
+        }
+
+        async fn complete(
+            &self,
+            request: &opencrust_agents::LlmRequest,
+        ) -> opencrust_common::Result<opencrust_agents::LlmResponse> {
+            *self.seen.lock().unwrap() = request.messages.clone();
+            Ok(opencrust_agents::LlmResponse {
+                content: vec![opencrust_agents::ContentBlock::Text {
+                    text: "ok".to_string(),
+                }],
+                model: "recording-1".to_string(),
+                usage: None,
+                stop_reason: None,
+            })
+        }
+
+        async fn health_check(&self) -> opencrust_common::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn history_router(state: SharedState) -> Router {
+        Router::new()
+            .route(
+                "/api/sessions/{id}/history",
+                get(api::session_history).post(api::import_session_history),
+            )
+            .route("/api/sessions/{id}/messages", post(api::send_message))
+            .with_state(state)
+    }
+
+    fn json_post(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(axum::http::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn message_text(message: &opencrust_agents::ChatMessage) -> &str {
+        match &message.content {
+            opencrust_agents::MessagePart::Text(text) => text,
+            opencrust_agents::MessagePart::Parts(_) => "",
+        }
+    }
+
+    #[test]
+    fn imported_history_reaches_next_message() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let agents = AgentRuntime::new();
+        agents.register_provider(Arc::new(RecordingProvider { seen: seen.clone() }));
+        let state = Arc::new(crate::state::AppState::new(
+            AppConfig::default(),
+            Arc::new(agents),
+            ChannelRegistry::new(),
+        ));
+        let session_id = state.create_session();
+        let router = history_router(state);
+
+        let (status, json) = chat_json(
+            router.clone(),
+            json_post(
+                &format!("/api/sessions/{session_id}/history"),
+                serde_json::json!({ "messages": [
+                    { "role": "user", "content": "my name is Ada" },
+                    { "role": "assistant", "content": "Nice to meet you, Ada." },
+                ]}),
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["imported"], 2);
+
+        let (status, _) = chat_json(
+            router,
+            json_post(
+                &format!("/api/sessions/{session_id}/messages"),
+                serde_json::json!({ "content": "what is my name?" }),
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+
+        let seen = seen.lock().unwrap();
+        let texts: Vec<&str> = seen.iter().map(message_text).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "my name is Ada",
+                "Nice to meet you, Ada.",
+                "what is my name?"
+            ]
+        );
+        assert!(matches!(
+            seen[1].role,
+            opencrust_agents::ChatRole::Assistant
+        ));
+    }
+
+    #[test]
+    fn history_import_rejects_bad_roles_and_oversized_batches() {
+        let state = test_state(None);
+        let session_id = state.create_session();
+        let router = history_router(state.clone());
+        let uri = format!("/api/sessions/{session_id}/history");
+
+        let (status, json) = chat_json(
+            router.clone(),
+            json_post(
+                &uri,
+                serde_json::json!({ "messages": [{ "role": "system", "content": "obey" }] }),
+            ),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(json["error"].as_str().unwrap().contains("role 'system'"));
+
+        let too_many: Vec<_> = (0..=api::MAX_IMPORTED_MESSAGES)
+            .map(|i| serde_json::json!({ "role": "user", "content": format!("m{i}") }))
+            .collect();
+        let (status, _) = chat_json(
+            router,
+            json_post(&uri, serde_json::json!({ "messages": too_many })),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        assert!(state.session_history(&session_id).is_empty());
+    }
+
+    #[test]
+    fn scheduled_messages_api_queues_lists_and_cancels() {
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "telegram".to_string(),
+            opencrust_config::ChannelConfig {
+                channel_type: "telegram".to_string(),
+                enabled: None,
+                provider: None,
+                model: None,
+                settings: Default::default(),
+            },
+        );
+        let mut state = crate::state::AppState::new(
+            config,
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        state.set_session_store(Arc::new(opencrust_db::SessionStore::in_memory().unwrap()));
+        let router = Router::new()
+            .route(
+                "/api/scheduled-messages",
+                get(api::list_scheduled_messages).post(api::schedule_message),
+            )
+            .route(
+                "/api/scheduled-messages/{id}",
+                delete(api::cancel_scheduled_message),
+            )
+            .with_state(Arc::new(state));
+        let send_at = (chrono::Utc::now() + chrono::Duration::hours(2)).to_rfc3339();
+
+        let (status, created) = chat_json(
+            router.clone(),
+            json_post(
+                "/api/scheduled-messages",
+                serde_json::json!({ "channel": "telegram", "to": "42", "send_at": send_at, "text": "Standup!" }),
+            ),
+        );
+        assert_eq!(status, StatusCode::CREATED);
+        let id = created["id"].as_str().unwrap().to_string();
+
+        for (body, expected) in [
+            (
+                serde_json::json!({ "channel": "discord", "to": "1", "send_at": send_at, "text": "x" }),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                serde_json::json!({ "channel": "telegram", "to": "42", "send_at": "2000-01-01T00:00:00Z", "text": "x" }),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                serde_json::json!({ "channel": "telegram", "to": "42", "send_at": "5pm", "text": "x" }),
+                StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            let (status, _) = chat_json(router.clone(), json_post("/api/scheduled-messages", body));
+            assert_eq!(status, expected);
+        }
+
+        let list = Request::builder()
+            .uri("/api/scheduled-messages")
+            .body(Body::empty())
+            .unwrap();
+        let (status, listed) = chat_json(router.clone(), list);
+        assert_eq!(status, StatusCode::OK);
+        let messages = listed["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["id"], id.as_str());
+        assert_eq!(messages[0]["to"], "42");
+        assert_eq!(messages[0]["text"], "Standup!");
+
+        let cancel = |id: &str| {
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/scheduled-messages/{id}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let resp = block_on(router.clone().oneshot(cancel(&id))).unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = block_on(router.oneshot(cancel(&id))).unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn channel_maintenance_api_toggles_maintenance_mode() {
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "telegram".to_string(),
+            opencrust_config::ChannelConfig {
+                channel_type: "telegram".to_string(),
+                enabled: None,
+                provider: None,
+                model: None,
+                settings: Default::default(),
+            },
+        );
+        let state = Arc::new(crate::state::AppState::new(
+            config,
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        ));
+        let router = Router::new()
+            .route(
+                "/api/channels/{channel}/maintenance",
+                get(api::channel_maintenance)
+                    .put(api::start_channel_maintenance)
+                    .delete(api::end_channel_maintenance),
+            )
+            .with_state(Arc::clone(&state));
+        let request = |method: &str, channel: &str, body: Option<serde_json::Value>| {
+            let builder = Request::builder()
+                .method(method)
+                .uri(format!("/api/channels/{channel}/maintenance"));
+            match body {
+                Some(body) => builder
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+                None => builder.body(Body::empty()).unwrap(),
+            }
+        };
+
+        let (status, body) = chat_json(router.clone(), request("PUT", "telegram", None));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["maintenance"]["message"],
+            crate::state::DEFAULT_MAINTENANCE_MESSAGE
+        );
+        assert_eq!(body["maintenance"]["queue"], false);
+
+        let update = serde_json::json!({ "message": "Upgrading, back at 5pm", "queue": true });
+        let (status, _) = chat_json(router.clone(), request("PUT", "telegram", Some(update)));
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = chat_json(router.clone(), request("GET", "telegram", None));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["maintenance"]["message"], "Upgrading, back at 5pm");
+        assert_eq!(body["maintenance"]["queued"], 0);
+        assert_eq!(
+            state.maintenance_reply("telegram").as_deref(),
+            Some("Upgrading, back at 5pm")
+        );
+
+        let (status, _) = chat_json(router.clone(), request("PUT", "discord", None));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = chat_json(router.clone(), request("DELETE", "telegram", None));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["replayed"], 0);
+        let (_, body) = chat_json(router.clone(), request("GET", "telegram", None));
+        assert!(body["maintenance"].is_null());
+        let (status, _) = chat_json(router, request("DELETE", "telegram", None));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn persisted_history_import_survives_restart() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(opencrust_db::SessionStore::open(&dir.path().join("s.db")).unwrap());
+        let new_state = || {
+            let mut state = crate::state::AppState::new(
+                AppConfig::default(),
+                Arc::new(AgentRuntime::new()),
+                ChannelRegistry::new(),
+            );
+            state.set_session_store(store.clone());
+            Arc::new(state)
+        };
+
+        let (status, _) = chat_json(
+            history_router(new_state()),
+            json_post(
+                "/api/sessions/restored/history",
+                serde_json::json!({ "persist": true, "messages": [
+                    { "role": "user", "content": "hello" },
+                    { "role": "assistant", "content": "hi there" },
+                ]}),
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+
+        let restarted = new_state();
+        block_on(restarted.hydrate_session_history("restored", Some("api"), None));
+        let history = restarted.session_history("restored");
+        let texts: Vec<&str> = history.iter().map(message_text).collect();
+        assert_eq!(texts, vec!["hello", "hi there"]);
+
+        // Re-importing replaces the stored history rather than adding to it.
+        let (status, _) = chat_json(
+            history_router(restarted),
+            json_post(
+                "/api/sessions/restored/history",
+                serde_json::json!({ "persist": true, "messages": [
+                    { "role": "user", "content": "hello" },
+                    { "role": "assistant", "content": "hi there" },
+                    { "role": "user", "content": "still there?" },
+                ]}),
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+        let restarted = new_state();
+        block_on(restarted.hydrate_session_history("restored", Some("api"), None));
+        let history = restarted.session_history("restored");
+        let texts: Vec<&str> = history.iter().map(message_text).collect();
+        assert_eq!(texts, vec!["hello", "hi there", "still there?"]);
+    }
 }
@@ -15,12 +15,15 @@ use tracing::{info, warn};
 #[cfg(target_os = "macos")]
 use crate::bootstrap::build_imessage_channels;
 use crate::bootstrap::{
-    build_agent_runtime, build_channels, build_discord_channels, build_line_channels,
-    build_mcp_tools, build_mqtt_channels, build_slack_channels, build_telegram_channels,
-    build_wechat_channels, build_whatsapp_channels, build_whatsapp_web_channels, resolve_api_key,
+    build_agent_runtime_with_report, build_channels, build_connector_channels,
+    build_discord_channels, build_line_channels, build_mcp_tools, build_mqtt_channels,
+    build_signal_channels, build_slack_channels, build_teams_channels, build_telegram_channels,
+    build_webchat_channels, build_wechat_channels, build_whatsapp_channels,
+    build_whatsapp_web_channels, resolve_api_key,
 };
 use crate::router::build_router;
-use crate::state::AppState;
+use crate::startup::StartupReport;
+use crate::state::{AppState, SharedState};
 
 /// The main gateway server that binds to a port and serves the API + WebSocket.
 pub struct GatewayServer {
@@ -32,13 +35,71 @@ impl GatewayServer {
         Self { config }
     }
 
+    /// Build the agent runtime and state without binding a port or starting
+    /// channels, for running turns in-process (`opencrust chat --local`).
+    ///
+    /// MCP tools and the session store are set up as in [`run`](Self::run);
+    /// scheduling tools are left out because no scheduler runs.
+    pub async fn local_state(self) -> SharedState {
+        let (mut agents, _send_msg_handle) =
+            build_agent_runtime_with_report(&self.config, &StartupReport::default()).await;
+
+        let (mcp_manager_arc, mcp_tools, mcp_instructions, mcp_startup) =
+            build_mcp_tools(&self.config).await;
+        for tool in mcp_tools {
+            agents.register_tool(tool);
+        }
+        if let Some(instructions) = &mcp_instructions {
+            agents.append_system_prompt(instructions);
+        }
+
+        let shared_config = Arc::new(RwLock::new(self.config.clone()));
+        let (handoff_tool, handoff_handle) =
+            opencrust_agents::HandoffTool::new(Arc::clone(&shared_config));
+        agents.register_tool(Box::new(handoff_tool));
+
+        let agents = Arc::new(agents);
+        handoff_handle.wire(&agents);
+
+        let data_dir =
+            self.config.data_dir.clone().unwrap_or_else(|| {
+                opencrust_config::ConfigLoader::default_config_dir().join("data")
+            });
+        let session_store = std::fs::create_dir_all(&data_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|()| {
+                SessionStore::open(&data_dir.join("sessions.db")).map_err(|e| e.to_string())
+            });
+
+        let mut state = AppState::new(
+            self.config,
+            agents,
+            opencrust_channels::ChannelRegistry::new(),
+        );
+        state.mcp_manager_arc = Some(mcp_manager_arc);
+        state.mcp_startup = mcp_startup;
+        match session_store {
+            Ok(store) => state.set_session_store(Arc::new(store)),
+            Err(e) => warn!("failed to open session store: {e}"),
+        }
+        Arc::new(state)
+    }
+
     pub async fn run(self) -> Result<()> {
         let addr = format!("{}:{}", self.config.gateway.host, self.config.gateway.port);
 
-        let (mut agents, send_msg_handle) = build_agent_runtime(&self.config).await;
+        let startup = StartupReport::default();
+        let (mut agents, send_msg_handle) =
+            build_agent_runtime_with_report(&self.config, &startup).await;
 
         // Connect MCP servers and register their tools
-        let (mcp_manager_arc, mcp_tools, mcp_instructions) = build_mcp_tools(&self.config).await;
+        let (mcp_manager_arc, mcp_tools, mcp_instructions, mcp_startup) =
+            build_mcp_tools(&self.config).await;
+        for (server, result) in &mcp_startup {
+            if let Err(e) = result {
+                startup.record("mcp", server, e.clone());
+            }
+        }
         for tool in mcp_tools {
             agents.register_tool(tool);
         }
@@ -55,7 +116,7 @@ impl GatewayServer {
             opencrust_agents::HandoffTool::new(Arc::clone(&shared_config));
         agents.register_tool(Box::new(handoff_tool));
 
-        let channels = build_channels(&self.config).await;
+        let channels = build_channels(&self.config, &startup).await;
 
         // Open session store and register session-dependent tools on the mutable
         // runtime BEFORE wrapping in Arc (register_tool requires &mut self).
@@ -67,12 +128,17 @@ impl GatewayServer {
             warn!("failed to create data directory: {e}");
         }
         let sessions_db = data_dir.join("sessions.db");
+        let mut schedule_message_handle = None;
         let session_store_arc = match SessionStore::open(&sessions_db) {
             Ok(store) => {
                 let store = Arc::new(store);
                 agents.register_tool(Box::new(opencrust_agents::ScheduleHeartbeat::new(
                     Arc::clone(&store),
                 )));
+                let (schedule_message, handle) =
+                    opencrust_agents::ScheduleMessage::new(Arc::clone(&store));
+                agents.register_tool(Box::new(schedule_message));
+                schedule_message_handle = Some(handle);
                 agents.register_tool(Box::new(opencrust_agents::CancelHeartbeat::new(
                     Arc::clone(&store),
                 )));
@@ -122,20 +188,27 @@ impl GatewayServer {
         send_msg_handle.wire(send_tx);
         let mut state = AppState::new(self.config, Arc::clone(&agents), channels);
         state.mcp_manager_arc = Some(Arc::clone(&mcp_manager_arc));
+        state.mcp_startup = mcp_startup;
+        state.startup = startup;
 
         if let Some(store) = session_store_arc {
             state.set_session_store(store);
         }
 
         // Wire TTS provider from voice config.
-        // Key resolution: vault → voice.api_key → VOICE_API_KEY env → openai provider key.
+        // Key resolution: vault → voice.api_key → VOICE_API_KEY env → provider fallback
+        // (ELEVENLABS_API_KEY for ElevenLabs, the openai provider key otherwise).
         let voice_cfg = &state.config.voice;
+        let elevenlabs = voice_cfg.tts_provider.as_deref() == Some("elevenlabs");
         let voice_api_key = resolve_api_key(
             voice_cfg.api_key.as_deref(),
             "VOICE_API_KEY",
             "VOICE_API_KEY",
         )
         .or_else(|| {
+            if elevenlabs {
+                return resolve_api_key(None, "ELEVENLABS_API_KEY", "ELEVENLABS_API_KEY");
+            }
             // Fall back to the explicitly-named "openai" LLM provider key so we
             // don't accidentally send an Anthropic key to an OpenAI endpoint.
             state
@@ -188,8 +261,21 @@ impl GatewayServer {
 
         let state = Arc::new(state);
 
+        // Messages to other chats are checked against the connected channels
+        // and the allowlist owner, which only the gateway state knows.
+        if let Some(handle) = schedule_message_handle {
+            let weak = Arc::downgrade(&state);
+            handle.wire(Arc::new(
+                move |context: &opencrust_agents::ToolContext, channel: &str| match weak.upgrade() {
+                    Some(state) => state.check_schedule_target(context.user_id.as_deref(), channel),
+                    None => Err("the gateway is shutting down".to_string()),
+                },
+            ));
+        }
+
         // Spawn background tasks
         state.spawn_session_cleanup();
+        state.spawn_retention();
         state.spawn_config_applier();
 
         // Watch dna.md and skills directory for hot-reload
@@ -205,17 +291,20 @@ impl GatewayServer {
         // Start configured Discord channels
         let discord_channels = build_discord_channels(&state.config, &state);
         for mut channel in discord_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
-            state
-                .channel_senders
-                .insert(sender.channel_name().to_string(), sender);
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
             tokio::spawn(async move {
-                if let Err(e) = channel.connect().await {
-                    warn!("discord channel failed to connect: {e}");
-                    return;
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(channel.as_mut(), shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
                 }
-                shutdown_signal().await;
-                channel.disconnect().await.ok();
             });
         }
 
@@ -251,34 +340,40 @@ impl GatewayServer {
         // Start configured Telegram channels
         let telegram_channels = build_telegram_channels(&state.config, &state);
         for mut channel in telegram_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
-            state
-                .channel_senders
-                .insert(sender.channel_name().to_string(), sender);
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
             tokio::spawn(async move {
-                if let Err(e) = channel.connect().await {
-                    warn!("telegram channel failed to connect: {e}");
-                    return;
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(channel.as_mut(), shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
                 }
-                shutdown_signal().await;
-                channel.disconnect().await.ok();
             });
         }
 
         // Start configured Slack channels
         let slack_channels = build_slack_channels(&state.config, &state);
         for mut channel in slack_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
-            state
-                .channel_senders
-                .insert(sender.channel_name().to_string(), sender);
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
             tokio::spawn(async move {
-                if let Err(e) = channel.connect().await {
-                    warn!("slack channel failed to connect: {e}");
-                    return;
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(channel.as_mut(), shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
                 }
-                shutdown_signal().await;
-                channel.disconnect().await.ok();
             });
         }
 
@@ -287,17 +382,22 @@ impl GatewayServer {
         {
             let imessage_channels = build_imessage_channels(&state.config, &state);
             for mut channel in imessage_channels {
-                let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
-                state
-                    .channel_senders
-                    .insert(sender.channel_name().to_string(), sender);
+                let sender = state.channels.queued_sender(channel.create_sender());
+                let name = sender.channel_name().to_string();
+                state.channel_senders.insert(name.clone(), sender);
+                let task_state = Arc::clone(&state);
                 tokio::spawn(async move {
-                    if let Err(e) = channel.connect().await {
-                        warn!("imessage channel failed to connect: {e}");
-                        return;
+                    if let Err(e) = task_state
+                        .channels
+                        .supervise(channel.as_mut(), shutdown_signal())
+                        .await
+                    {
+                        task_state.startup.fail(
+                            "channel",
+                            &name,
+                            format!("failed to connect: {e}"),
+                        );
                     }
-                    shutdown_signal().await;
-                    channel.disconnect().await.ok();
                 });
             }
         }
@@ -305,7 +405,7 @@ impl GatewayServer {
         // Build WhatsApp Business channels (webhook-driven - no persistent connection)
         let whatsapp_channels = build_whatsapp_channels(&state.config, &state);
         for channel in &whatsapp_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
+            let sender = state.channels.queued_sender(channel.create_sender());
             state
                 .channel_senders
                 .insert(sender.channel_name().to_string(), sender);
@@ -320,17 +420,20 @@ impl GatewayServer {
         // Start WhatsApp Web channels (sidecar-driven, QR code pairing)
         let whatsapp_web_channels = build_whatsapp_web_channels(&state.config, &state);
         for mut channel in whatsapp_web_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
-            state
-                .channel_senders
-                .insert(sender.channel_name().to_string(), sender);
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
             tokio::spawn(async move {
-                if let Err(e) = channel.connect().await {
-                    warn!("whatsapp-web channel failed to connect: {e}");
-                    return;
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(&mut channel, shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
                 }
-                shutdown_signal().await;
-                channel.disconnect().await.ok();
             });
         }
 
@@ -338,13 +441,15 @@ impl GatewayServer {
         let mut line_channels_raw = build_line_channels(&state.config, &state);
         for channel in &mut line_channels_raw {
             if let Err(e) = channel.connect().await {
-                warn!("line channel failed to connect: {e}");
+                state
+                    .startup
+                    .fail("channel", "line", format!("failed to connect: {e}"));
             }
         }
         let line_channels: Vec<Arc<opencrust_channels::line::LineChannel>> =
             line_channels_raw.into_iter().map(Arc::new).collect();
         for channel in &line_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
+            let sender = state.channels.queued_sender(channel.create_sender());
             state
                 .channel_senders
                 .insert(sender.channel_name().to_string(), sender);
@@ -355,7 +460,7 @@ impl GatewayServer {
 
         let wechat_channels = build_wechat_channels(&state.config, &state);
         for channel in &wechat_channels {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
+            let sender = state.channels.queued_sender(channel.create_sender());
             state
                 .channel_senders
                 .insert(sender.channel_name().to_string(), sender);
@@ -364,20 +469,108 @@ impl GatewayServer {
         let wechat_state: opencrust_channels::wechat::webhook::WeChatWebhookState =
             Arc::new(wechat_channels);
 
-        // Start MQTT channels (persistent TCP connection to broker)
-        let mut mqtt_channels = build_mqtt_channels(&state.config, &state);
-        for mut channel in mqtt_channels.drain(..) {
-            let sender: Arc<dyn ChannelSender> = Arc::from(channel.create_sender());
+        // Start Teams channels (webhook mode)
+        let mut teams_channels_raw = build_teams_channels(&state.config, &state);
+        for channel in &mut teams_channels_raw {
+            if let Err(e) = channel.connect().await {
+                let name = channel.channel_name().to_string();
+                state
+                    .startup
+                    .fail("channel", &name, format!("failed to connect: {e}"));
+            }
+        }
+        let teams_channels: Vec<Arc<opencrust_channels::TeamsChannel>> =
+            teams_channels_raw.into_iter().map(Arc::new).collect();
+        for channel in &teams_channels {
+            let sender = state.channels.queued_sender(channel.create_sender());
+            state
+                .channel_senders
+                .insert(sender.channel_name().to_string(), sender);
+            info!("teams channel ready (webhook mode)");
+        }
+        let teams_state: opencrust_channels::teams::webhook::TeamsWebhookState =
+            Arc::new(teams_channels);
+
+        // Start web chat channels (served under /webchat)
+        let mut webchat_channels_raw = build_webchat_channels(&state.config, &state);
+        for channel in &mut webchat_channels_raw {
+            if let Err(e) = channel.connect().await {
+                let name = channel.channel_name().to_string();
+                state
+                    .startup
+                    .fail("channel", &name, format!("failed to connect: {e}"));
+            }
+        }
+        let webchat_channels: Vec<Arc<opencrust_channels::WebChatChannel>> =
+            webchat_channels_raw.into_iter().map(Arc::new).collect();
+        for channel in &webchat_channels {
+            let sender = state.channels.queued_sender(channel.create_sender());
+            state
+                .channel_senders
+                .insert(sender.channel_name().to_string(), sender);
+            info!("webchat channel ready at /webchat");
+        }
+        let webchat_state: opencrust_channels::webchat::socket::WebChatState =
+            Arc::new(webchat_channels);
+
+        // Start external connector channels (connectors dial in to /connector)
+        let mut connector_channels_raw = build_connector_channels(&state.config, &state);
+        for channel in &mut connector_channels_raw {
+            if let Err(e) = channel.connect().await {
+                let name = channel.channel_name().to_string();
+                state
+                    .startup
+                    .fail("channel", &name, format!("failed to connect: {e}"));
+            }
+        }
+        let connector_channels: Vec<Arc<opencrust_channels::RemoteChannel>> =
+            connector_channels_raw.into_iter().map(Arc::new).collect();
+        for channel in &connector_channels {
+            let sender = state.channels.queued_sender(channel.create_sender());
             state
                 .channel_senders
                 .insert(sender.channel_name().to_string(), sender);
+            info!("connector channel '{}' ready at /connector", channel.name());
+        }
+        let connector_state: opencrust_channels::ConnectorState = Arc::new(connector_channels);
+
+        // Start MQTT channels (persistent TCP connection to broker)
+        let mut mqtt_channels = build_mqtt_channels(&state.config, &state);
+        for mut channel in mqtt_channels.drain(..) {
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
             tokio::spawn(async move {
-                if let Err(e) = channel.connect().await {
-                    warn!("mqtt channel failed to connect: {e}");
-                    return;
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(&mut channel, shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
+                }
+            });
+        }
+
+        // Start Signal channels (persistent TCP connection to signal-cli)
+        let signal_channels = build_signal_channels(&state.config, &state);
+        for mut channel in signal_channels {
+            let sender = state.channels.queued_sender(channel.create_sender());
+            let name = sender.channel_name().to_string();
+            state.channel_senders.insert(name.clone(), sender);
+            let task_state = Arc::clone(&state);
+            tokio::spawn(async move {
+                if let Err(e) = task_state
+                    .channels
+                    .supervise(&mut channel, shutdown_signal())
+                    .await
+                {
+                    task_state
+                        .startup
+                        .fail("channel", &name, format!("failed to connect: {e}"));
                 }
-                shutdown_signal().await;
-                channel.disconnect().await.ok();
             });
         }
 
@@ -399,14 +592,8 @@ impl GatewayServer {
                         );
                         continue;
                     };
-                    let metadata = match msg.channel_id.as_str() {
-                        "telegram" => {
-                            let chat_id: i64 = msg.recipient_id.parse().unwrap_or(0);
-                            serde_json::json!({ "telegram_chat_id": chat_id })
-                        }
-                        "line" => serde_json::json!({ "line_user_id": msg.recipient_id }),
-                        _ => serde_json::json!({ "recipient_id": msg.recipient_id }),
-                    };
+                    let metadata =
+                        crate::state::recipient_metadata(sender.channel_type(), &msg.recipient_id);
                     let mut message = Message::text(
                         SessionId::new(),
                         ChannelId::from_string(msg.channel_id.clone()),
@@ -432,8 +619,24 @@ impl GatewayServer {
             });
         }
 
+        // Channels that fail to connect later are added to the report as they
+        // happen; everything known at this point is summarised once here.
+        state.startup.log_summary(
+            state.agents.provider_ids().len(),
+            state.channel_senders.len(),
+            state.mcp_startup.iter().filter(|(_, r)| r.is_ok()).count(),
+        );
+
         let state_for_shutdown = Arc::clone(&state);
-        let app = build_router(state, whatsapp_state, line_state, wechat_state);
+        let app = build_router(
+            state,
+            whatsapp_state,
+            line_state,
+            wechat_state,
+            teams_state,
+            webchat_state,
+            connector_state,
+        );
 
         let listener = TcpListener::bind(&addr).await?;
         info!("OpenCrust gateway listening on {}", addr);
@@ -570,10 +773,12 @@ fn spawn_skills_watcher(state: Arc<AppState>, config_dir: PathBuf) {
                     EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
                 );
                 if dominated {
-                    let touches_skill = event
-                        .paths
-                        .iter()
-                        .any(|p| p.extension().and_then(|e| e.to_str()) == Some("md"));
+                    // Removing a folder-layout skill reports only the directory path.
+                    let touches_skill = matches!(event.kind, EventKind::Remove(_))
+                        || event
+                            .paths
+                            .iter()
+                            .any(|p| p.extension().and_then(|e| e.to_str()) == Some("md"));
                     if touches_skill {
                         let _ = notify_tx.try_send(());
                     }
@@ -596,7 +801,8 @@ fn spawn_skills_watcher(state: Arc<AppState>, config_dir: PathBuf) {
         return;
     }
 
-    if let Err(e) = watcher.watch(&skills_dir, RecursiveMode::NonRecursive) {
+    // Recursive so edits to folder-layout skills (`skills/<name>/SKILL.md`) are seen.
+    if let Err(e) = watcher.watch(&skills_dir, RecursiveMode::Recursive) {
         warn!("failed to watch skills dir: {e}");
         return;
     }
@@ -613,16 +819,12 @@ fn spawn_skills_watcher(state: Arc<AppState>, config_dir: PathBuf) {
             tokio::time::sleep(std::time::Duration::from_millis(500)).await;
             while notify_rx.try_recv().is_ok() {}
 
-            let scanner = opencrust_skills::SkillScanner::new(&skills_dir);
-            match scanner.discover() {
-                Ok(skills) => {
-                    let count = skills.len();
-                    state.agents.index_skills(skills).await;
-                    if count > 0 {
-                        info!("skills reloaded ({} skill(s))", count);
-                    } else {
-                        info!("skills directory empty, cleared skills");
-                    }
+            match state.agents.reload_skills().await {
+                Ok(count) if count > 0 => {
+                    info!("skills reloaded ({} skill(s))", count);
+                }
+                Ok(_) => {
+                    info!("skills directory empty, cleared skills");
                 }
                 Err(e) => {
                     warn!("failed to re-scan skills directory: {e}");
@@ -633,12 +835,19 @@ fn spawn_skills_watcher(state: Arc<AppState>, config_dir: PathBuf) {
 }
 
 async fn run_scheduler(state: &AppState) -> Result<()> {
+    run_scheduler_at(state, state.clock().utc_now()).await
+}
+
+/// Execute every task due as of `now`.
+async fn run_scheduler_at(state: &AppState, now: chrono::DateTime<chrono::Utc>) -> Result<()> {
     let store = match &state.session_store {
         Some(s) => s,
         None => return Ok(()),
     };
 
-    let tasks = store.poll_due_tasks()?;
+    deliver_scheduled_messages(state, store, now).await?;
+
+    let tasks = store.poll_due_tasks_at(now)?;
 
     if tasks.is_empty() {
         return Ok(());
@@ -669,6 +878,108 @@ async fn run_scheduler(state: &AppState) -> Result<()> {
     Ok(())
 }
 
+/// Send every scheduled message due as of `now` to its recipient. Failed
+/// deliveries (including channels that are not up yet) are retried with
+/// backoff.
+async fn deliver_scheduled_messages(
+    state: &AppState,
+    store: &Arc<SessionStore>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    for scheduled in store.due_scheduled_messages_at(now)? {
+        let result = match state
+            .channel_senders
+            .get(&scheduled.channel)
+            .map(|s| Arc::clone(s.value()))
+        {
+            Some(sender) => {
+                let mut message = Message::text(
+                    SessionId::new(),
+                    ChannelId::from_string(&scheduled.channel),
+                    UserId::from_string(&scheduled.recipient),
+                    MessageDirection::Outgoing,
+                    &scheduled.text,
+                );
+                message.metadata =
+                    crate::state::recipient_metadata(sender.channel_type(), &scheduled.recipient);
+                sender.send_message(&message).await.map(|()| {
+                    crate::state::dm_session_id(sender.channel_type(), &scheduled.recipient).map(
+                        |session_id| {
+                            message.session_id = SessionId::from_string(session_id);
+                            message
+                        },
+                    )
+                })
+            }
+            None => Err(opencrust_common::Error::Channel(format!(
+                "no channel sender registered for scheduled message: {}",
+                scheduled.channel
+            ))),
+        };
+
+        match result {
+            Ok(persisted) => {
+                // Record the message so the agent sees it in later turns.
+                if let Some(message) = persisted {
+                    state.persist_message(&message).await;
+                }
+                store.complete_scheduled_message(&scheduled.id)?;
+                info!(
+                    "delivered scheduled message {} to {} on {}",
+                    scheduled.id, scheduled.recipient, scheduled.channel
+                );
+            }
+            Err(e) => {
+                warn!("scheduled message {} failed: {e}", scheduled.id);
+                if !store.retry_or_fail_scheduled_message(&scheduled.id)? {
+                    tracing::error!(
+                        "scheduled message {} permanently failed after max retries",
+                        scheduled.id
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Send a reminder's text to its channel as-is; the agent is not involved.
+async fn deliver_reminder(
+    state: &AppState,
+    store: &Arc<SessionStore>,
+    task: &opencrust_db::ScheduledTask,
+    delivery_channel: &str,
+) -> Result<()> {
+    let Some(sender) = state
+        .channel_senders
+        .get(delivery_channel)
+        .map(|s| Arc::clone(s.value()))
+    else {
+        // Fail so the retry logic tries again once the channel is up.
+        return Err(opencrust_common::Error::Channel(format!(
+            "no channel sender registered for reminder delivery: {delivery_channel}"
+        )));
+    };
+
+    let reminder = Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: SessionId::from_string(&task.session_id),
+        channel_id: ChannelId::from_string(delivery_channel),
+        user_id: UserId::from_string("genesis"),
+        direction: MessageDirection::Outgoing,
+        content: MessageContent::Text(task.payload.clone()),
+        timestamp: chrono::Utc::now(),
+        metadata: task.session_metadata.clone(),
+    };
+    sender.send_message(&reminder).await?;
+
+    // Record the reminder so the agent sees it in later turns.
+    state.persist_message(&reminder).await;
+    store.complete_task(&task.id)?;
+    info!("delivered reminder {} to {delivery_channel}", task.id);
+    Ok(())
+}
+
 async fn execute_scheduled_task(
     state: &AppState,
     store: &Arc<SessionStore>,
@@ -691,6 +1002,10 @@ async fn execute_scheduled_task(
         &task.channel_id
     };
 
+    if task.is_reminder() {
+        return deliver_reminder(state, store, task, delivery_channel).await;
+    }
+
     let message = Message {
         id: uuid::Uuid::new_v4().to_string(),
         session_id: SessionId::from_string(&task.session_id),
@@ -802,3 +1117,168 @@ async fn execute_scheduled_task(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencrust_agents::AgentRuntime;
+    use opencrust_channels::ChannelRegistry;
+    use opencrust_common::{Clock, FakeClock};
+    use std::sync::Mutex;
+
+    /// Sender that records every outbound message.
+    struct RecordingSender {
+        sent: Arc<Mutex<Vec<Message>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChannelSender for RecordingSender {
+        fn channel_type(&self) -> &str {
+            "telegram"
+        }
+
+        async fn send_message(&self, message: &Message) -> Result<()> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduler_dispatches_reminder_at_due_time() {
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        store
+            .upsert_session(
+                "telegram-42",
+                "telegram",
+                "u1",
+                &serde_json::json!({ "chat_id": 42 }),
+            )
+            .unwrap();
+        let due = chrono::Utc::now() + chrono::Duration::hours(9);
+        store
+            .schedule_reminder("telegram-42", "u1", due, "Time to call mom!")
+            .unwrap();
+
+        let mut state = AppState::new(
+            AppConfig::default(),
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        state.session_store = Some(Arc::clone(&store));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender {
+                sent: Arc::clone(&sent),
+            }),
+        );
+
+        run_scheduler_at(&state, due - chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+
+        run_scheduler_at(&state, due).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert!(
+                matches!(&sent[0].content, MessageContent::Text(t) if t == "Time to call mom!")
+            );
+            assert_eq!(sent[0].metadata["chat_id"], 42);
+            assert_eq!(sent[0].session_id.as_str(), "telegram-42");
+        }
+
+        // Completed reminders are not sent again.
+        run_scheduler_at(&state, due + chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(store.list_pending_tasks("telegram-42").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn scheduler_delivers_scheduled_message_to_recipient() {
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        let send_at = chrono::Utc::now() + chrono::Duration::hours(5);
+        let id = store
+            .schedule_message("telegram", "42", send_at, "Standup in 5", None)
+            .unwrap();
+        store
+            .schedule_message("slack", "C1", send_at, "no sender", None)
+            .unwrap();
+
+        let mut state = AppState::new(
+            AppConfig::default(),
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        state.session_store = Some(Arc::clone(&store));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender {
+                sent: Arc::clone(&sent),
+            }),
+        );
+
+        run_scheduler_at(&state, send_at - chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+
+        run_scheduler_at(&state, send_at).await.unwrap();
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert!(matches!(&sent[0].content, MessageContent::Text(t) if t == "Standup in 5"));
+            assert_eq!(sent[0].metadata["telegram_chat_id"], 42);
+        }
+
+        // Delivered messages are done; the one without a sender waits for a retry.
+        let pending = store.list_scheduled_messages().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_ne!(pending[0].id, id);
+        assert_eq!(pending[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn scheduler_uses_state_clock() {
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        store
+            .upsert_session(
+                "telegram-7",
+                "telegram",
+                "u1",
+                &serde_json::json!({ "chat_id": 7 }),
+            )
+            .unwrap();
+        let clock = Arc::new(FakeClock::new());
+        let due = clock.utc_now() + chrono::Duration::hours(2);
+        store
+            .schedule_reminder("telegram-7", "u1", due, "Stretch")
+            .unwrap();
+
+        let mut state = AppState::new(
+            AppConfig::default(),
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        state.set_clock(clock.clone());
+        state.session_store = Some(Arc::clone(&store));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender {
+                sent: Arc::clone(&sent),
+            }),
+        );
+
+        run_scheduler(&state).await.unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+
+        clock.advance(std::time::Duration::from_secs(2 * 3600));
+        run_scheduler(&state).await.unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,96 @@
+//! Startup report: which configured components did not start, and why.
+//!
+//! A broken provider, channel or MCP server is skipped rather than aborting
+//! startup. Each skip is recorded here so the failures can be logged as one
+//! summary once the gateway is up and returned from `/api/status`.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// One configured component that failed to start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StartupFailure {
+    /// `"provider"`, `"channel"` or `"mcp"`.
+    pub component: &'static str,
+    /// Config key of the component, or its channel type for connect failures.
+    pub name: String,
+    pub error: String,
+}
+
+/// Failures collected while the gateway starts.
+#[derive(Debug, Default)]
+pub struct StartupReport {
+    failures: Mutex<Vec<StartupFailure>>,
+}
+
+impl StartupReport {
+    /// Log a warning for a component that will not run and record it.
+    pub fn fail(&self, component: &'static str, name: &str, error: impl Into<String>) {
+        let error = error.into();
+        warn!("{component} '{name}' not started: {error}");
+        self.record(component, name, error);
+    }
+
+    /// Record a failure that has already been logged.
+    pub fn record(&self, component: &'static str, name: &str, error: impl Into<String>) {
+        self.failures.lock().unwrap().push(StartupFailure {
+            component,
+            name: name.to_string(),
+            error: error.into(),
+        });
+    }
+
+    /// Failures recorded so far, in the order they happened.
+    pub fn failures(&self) -> Vec<StartupFailure> {
+        self.failures.lock().unwrap().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failures.lock().unwrap().is_empty()
+    }
+
+    /// Log one line summarising what came up, then one line per failure.
+    pub fn log_summary(&self, providers: usize, channels: usize, mcp_servers: usize) {
+        let failures = self.failures();
+        info!(
+            "startup complete: {providers} provider(s), {channels} channel(s), \
+             {mcp_servers} MCP server(s) running; {} component(s) failed",
+            failures.len()
+        );
+        for f in &failures {
+            warn!("  {} '{}': {}", f.component, f.name, f.error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_failures_in_order() {
+        let report = StartupReport::default();
+        assert!(report.is_empty());
+
+        report.fail("channel", "tg", "no bot_token");
+        report.record("mcp", "files", "spawn failed");
+
+        assert_eq!(
+            report.failures(),
+            vec![
+                StartupFailure {
+                    component: "channel",
+                    name: "tg".into(),
+                    error: "no bot_token".into(),
+                },
+                StartupFailure {
+                    component: "mcp",
+                    name: "files".into(),
+                    error: "spawn failed".into(),
+                },
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::RwLock;
@@ -7,14 +9,17 @@ use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 use opencrust_agents::{AgentRuntime, ChatMessage};
-use opencrust_channels::ChannelRegistry;
+use opencrust_channels::{ChannelRegistry, DEFAULT_DEDUP_TTL, SeenFn};
+use opencrust_common::{
+    ChannelId, Clock, Message, MessageDirection, SessionId, SystemClock, UserId,
+};
 use opencrust_config::{
     AppConfig,
     model::{GuardrailsConfig, RateLimitConfig},
 };
 use opencrust_db::SessionStore;
 use opencrust_media::TtsProvider;
-use opencrust_security::{Allowlist, PairingManager};
+use opencrust_security::{Allowlist, ClaimPolicy, PairingManager};
 use tokio::sync::watch;
 use tracing::{info, warn};
 use uuid::Uuid;
@@ -25,10 +30,51 @@ const SESSION_TTL: Duration = Duration::from_secs(3600); // 1 hour
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(300); // 5 minutes
 /// Sliding window for per-user rate limiting.
 const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// How long a pairing code stays claimable.
+const PAIRING_CODE_TTL: Duration = Duration::from_secs(300); // 5 minutes
 /// How long an unconfirmed pending file is kept before being dropped.
 const PENDING_FILE_TTL: Duration = Duration::from_secs(300); // 5 minutes
 /// How long a webchat session token remains valid after issuance.
 const WEBCHAT_TOKEN_TTL: Duration = Duration::from_secs(86400); // 24 hours
+/// Pause between messages of a broadcast, to stay under platform send limits.
+const BROADCAST_INTERVAL: Duration = Duration::from_millis(100);
+/// How often `sessions.retention_days` and `memory.retention_days` are enforced.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600); // 1 hour
+
+/// Reply sent while a channel is in maintenance mode and no message was given.
+pub const DEFAULT_MAINTENANCE_MESSAGE: &str =
+    "I'm temporarily offline for maintenance. Please try again later.";
+
+/// Messages held per session while a channel is in maintenance mode. Further
+/// messages from that session get the maintenance reply but are not queued.
+pub const MAX_HELD_PER_SESSION: usize = 5;
+
+/// Runs one message held during maintenance mode and delivers the reply.
+pub type ReplayFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// A channel put into maintenance mode through the API.
+struct ChannelMaintenance {
+    message: String,
+    queue: bool,
+    /// Held messages with the session each came from, oldest first.
+    queued: Vec<(String, ReplayFn)>,
+}
+
+/// The maintenance mode of one channel, as reported by the API.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MaintenanceStatus {
+    pub message: String,
+    pub queue: bool,
+    pub queued: usize,
+}
+
+/// Rows deleted by one [`AppState::enforce_retention`] pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub messages: usize,
+    pub sessions: usize,
+    pub memory_entries: usize,
+}
 
 /// Per-user rate limit tracking entry.
 struct UserRateLimitEntry {
@@ -51,6 +97,11 @@ pub struct AppState {
     pub a2a_tasks: DashMap<String, opencrust_agents::a2a::A2ATask>,
     /// MCP manager wrapped in Arc for health monitoring and resource access.
     pub mcp_manager_arc: Option<Arc<opencrust_agents::McpManager>>,
+    /// Outcome of connecting each MCP server at startup, shown in `/api/status`.
+    pub mcp_startup: crate::bootstrap::McpStartupReport,
+    /// Providers, channels and MCP servers that failed to start, shown in
+    /// `/api/status`.
+    pub startup: crate::startup::StartupReport,
     pub session_store: Option<Arc<SessionStore>>,
     /// TTS provider for voice responses (set from `voice.tts_provider` config).
     pub tts_provider: Option<Arc<dyn TtsProvider>>,
@@ -83,6 +134,66 @@ pub struct AppState {
     /// A user authorized on any channel is authorized on all channels,
     /// matching the multi-agent cross-channel identity model.
     pub allowlist: Arc<Mutex<Allowlist>>,
+    /// Channels in maintenance mode, keyed by channel name.
+    maintenance: DashMap<String, ChannelMaintenance>,
+    /// Time source for pairing codes, rate limiting and the scheduler.
+    clock: Arc<dyn Clock>,
+}
+
+/// Delivery counts for a [`AppState::broadcast`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastReport {
+    pub sent: usize,
+    pub failed: usize,
+}
+
+/// Why an [`AppState::broadcast`] was not sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastError {
+    /// No sender is registered under this channel name.
+    UnknownChannel(String),
+    /// The announcement text is empty.
+    EmptyText,
+}
+
+impl std::fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownChannel(channel) => {
+                write!(f, "no sender registered for channel: {channel}")
+            }
+            Self::EmptyText => f.write_str("text must not be empty"),
+        }
+    }
+}
+
+/// Routing metadata a channel sender needs to message `recipient_id` outside
+/// of an existing conversation, keyed by the sender's channel type.
+pub fn recipient_metadata(channel_type: &str, recipient_id: &str) -> serde_json::Value {
+    match channel_type {
+        "telegram" => {
+            let chat_id: i64 = recipient_id.parse().unwrap_or(0);
+            serde_json::json!({ "telegram_chat_id": chat_id })
+        }
+        "line" => serde_json::json!({ "line_user_id": recipient_id }),
+        "whatsapp" | "whatsapp-web" => serde_json::json!({ "whatsapp_from": recipient_id }),
+        "imessage" => serde_json::json!({ "imessage_sender": recipient_id }),
+        "wechat" => serde_json::json!({ "wechat_openid": recipient_id }),
+        "signal" => serde_json::json!({ "signal_recipient": recipient_id }),
+        "teams" => serde_json::json!({ "teams_user_id": recipient_id }),
+        "webchat" => serde_json::json!({ "webchat_visitor_id": recipient_id }),
+        _ => serde_json::json!({ "recipient_id": recipient_id }),
+    }
+}
+
+/// Session ID of the direct conversation with `recipient_id`, for channels
+/// whose DM sessions are keyed by the sender's ID.
+pub fn dm_session_id(channel_type: &str, recipient_id: &str) -> Option<String> {
+    match channel_type {
+        "telegram" | "line" | "whatsapp" | "whatsapp-web" | "imessage" | "signal" | "teams"
+        | "webchat" => Some(format!("{channel_type}-{recipient_id}")),
+        _ => None,
+    }
 }
 
 /// A file received in chat waiting for the user to confirm ingestion.
@@ -116,6 +227,19 @@ pub struct SessionState {
 
 impl AppState {
     pub fn new(config: AppConfig, agents: Arc<AgentRuntime>, channels: ChannelRegistry) -> Self {
+        let mut allowlist = Allowlist::load_or_create(
+            &opencrust_config::ConfigLoader::default_config_dir().join("allowlist.json"),
+        );
+        if !config.security.auto_claim_owner {
+            if config.security.owner_id.is_none() && allowlist.needs_owner() {
+                warn!(
+                    "security.auto_claim_owner is false but no security.owner_id is set; \
+                     nobody can claim ownership"
+                );
+            }
+            allowlist.set_claim_policy(ClaimPolicy::Configured(config.security.owner_id.clone()));
+        }
+
         Self {
             config,
             channels,
@@ -124,6 +248,8 @@ impl AppState {
             channel_senders: DashMap::new(),
             a2a_tasks: DashMap::new(),
             mcp_manager_arc: None,
+            mcp_startup: Vec::new(),
+            startup: crate::startup::StartupReport::default(),
             session_store: None,
             tts_provider: None,
             session_summaries: DashMap::new(),
@@ -136,15 +262,40 @@ impl AppState {
             session_token_counts: DashMap::new(),
             pending_files: DashMap::new(),
             webchat_tokens: DashMap::new(),
-            pairing: Arc::new(Mutex::new(PairingManager::new(Duration::from_secs(300)))),
-            allowlist: Arc::new(Mutex::new(Allowlist::load_or_create(
-                &opencrust_config::ConfigLoader::default_config_dir().join("allowlist.json"),
-            ))),
+            pairing: Arc::new(Mutex::new(PairingManager::new(PAIRING_CODE_TTL))),
+            allowlist: Arc::new(Mutex::new(allowlist)),
+            maintenance: DashMap::new(),
+            clock: Arc::new(SystemClock),
         }
     }
 
     /// Store a file pending ingestion confirmation for a session.
+    /// The raw file is also recorded in memory in the background so the agent
+    /// can recall it later, whether or not the user goes on to ingest it.
     pub fn set_pending_file(&self, session_id: &str, file: PendingFile) {
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let agents = Arc::clone(&self.agents);
+            let session_id = session_id.to_string();
+            let continuity_key = self.continuity_key(None);
+            let filename = file.filename.clone();
+            let data = file.data.clone();
+            handle.spawn(async move {
+                let mime = opencrust_media::detect_mime_type(std::path::Path::new(&filename));
+                if let Err(e) = agents
+                    .remember_attachment(
+                        &session_id,
+                        continuity_key.as_deref(),
+                        None,
+                        &filename,
+                        Some(mime),
+                        &data,
+                    )
+                    .await
+                {
+                    warn!("failed to remember attachment {filename}: {e}");
+                }
+            });
+        }
         self.pending_files.insert(session_id.to_string(), file);
     }
 
@@ -153,14 +304,14 @@ impl AppState {
         self.pending_files
             .remove(session_id)
             .map(|(_, f)| f)
-            .filter(|f| f.received_at.elapsed() < Duration::from_secs(300)) // 5 min expiry
+            .filter(|f| self.clock.now().duration_since(f.received_at) < Duration::from_secs(300)) // 5 min expiry
     }
 
     /// Check if a session has a pending file.
     pub fn has_pending_file(&self, session_id: &str) -> bool {
         self.pending_files
             .get(session_id)
-            .map(|f| f.received_at.elapsed() < Duration::from_secs(300))
+            .map(|f| self.clock.now().duration_since(f.received_at) < Duration::from_secs(300))
             .unwrap_or(false)
     }
 
@@ -169,6 +320,113 @@ impl AppState {
         self.session_store = Some(store);
     }
 
+    /// Inbound de-duplication for the channel named `channel`, recorded in
+    /// the session store so redeliveries are also caught across restarts.
+    /// `None` without a store; channels then remember message ids in memory.
+    pub fn inbound_dedup(&self, channel: &str) -> Option<SeenFn> {
+        let store = Arc::clone(self.session_store.as_ref()?);
+        let channel = channel.to_string();
+        let ttl = chrono::Duration::from_std(DEFAULT_DEDUP_TTL).unwrap_or_default();
+        Some(Arc::new(move |key: &str| {
+            store
+                .first_inbound_message(&format!("{channel}:{key}"), ttl)
+                .unwrap_or_else(|e| {
+                    warn!("{channel}: failed to check inbound message {key}: {e}");
+                    true
+                })
+        }))
+    }
+
+    /// Put the channel named `channel` into maintenance mode. Its users get
+    /// `message` (default [`DEFAULT_MAINTENANCE_MESSAGE`]) instead of an
+    /// agent turn and, with `queue`, their messages are held for replay by
+    /// [`Self::end_maintenance`]. Messages already held are kept.
+    pub fn start_maintenance(
+        &self,
+        channel: &str,
+        message: Option<String>,
+        queue: bool,
+    ) -> MaintenanceStatus {
+        let message = message
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_MAINTENANCE_MESSAGE.to_string());
+        let mut entry = self
+            .maintenance
+            .entry(channel.to_string())
+            .or_insert_with(|| ChannelMaintenance {
+                message: String::new(),
+                queue,
+                queued: Vec::new(),
+            });
+        entry.message = message;
+        entry.queue = queue;
+        info!("channel {channel} entered maintenance mode");
+        MaintenanceStatus {
+            message: entry.message.clone(),
+            queue: entry.queue,
+            queued: entry.queued.len(),
+        }
+    }
+
+    /// The maintenance mode of `channel`, or `None` when it is live.
+    pub fn maintenance_status(&self, channel: &str) -> Option<MaintenanceStatus> {
+        self.maintenance.get(channel).map(|m| MaintenanceStatus {
+            message: m.message.clone(),
+            queue: m.queue,
+            queued: m.queued.len(),
+        })
+    }
+
+    /// The reply for a message on `channel` while it is in maintenance mode,
+    /// or `None` when the message should be processed. `replay` is called to
+    /// hold the message when the channel queues messages and `session_id` has
+    /// fewer than [`MAX_HELD_PER_SESSION`] messages held.
+    pub fn hold_for_maintenance(
+        &self,
+        channel: &str,
+        session_id: &str,
+        replay: impl FnOnce() -> ReplayFn,
+    ) -> Option<String> {
+        let mut maintenance = self.maintenance.get_mut(channel)?;
+        if maintenance.queue {
+            let held = maintenance
+                .queued
+                .iter()
+                .filter(|(session, _)| session == session_id)
+                .count();
+            if held < MAX_HELD_PER_SESSION {
+                maintenance.queued.push((session_id.to_string(), replay()));
+            } else {
+                warn!("{channel}: session {session_id} already has {held} held messages, dropping");
+            }
+        }
+        Some(maintenance.message.clone())
+    }
+
+    /// The maintenance reply for channels that cannot reply to a held message
+    /// later, such as LINE whose reply tokens expire. Nothing is queued.
+    pub fn maintenance_reply(&self, channel: &str) -> Option<String> {
+        self.maintenance.get(channel).map(|m| m.message.clone())
+    }
+
+    /// Take `channel` out of maintenance mode and replay its held messages,
+    /// oldest first, in a background task. Returns how many were held, or
+    /// `None` when the channel was not in maintenance mode.
+    pub fn end_maintenance(&self, channel: &str) -> Option<usize> {
+        let (_, maintenance) = self.maintenance.remove(channel)?;
+        let queued = maintenance.queued;
+        let count = queued.len();
+        info!("channel {channel} left maintenance mode, replaying {count} message(s)");
+        if count > 0 {
+            tokio::spawn(async move {
+                for (_, replay) in queued {
+                    replay().await;
+                }
+            });
+        }
+        Some(count)
+    }
+
     /// Attach a TTS provider for voice responses.
     pub fn set_tts_provider(&mut self, provider: Arc<dyn TtsProvider>) {
         self.tts_provider = Some(provider);
@@ -179,6 +437,21 @@ impl AppState {
         self.config_rx = Some(rx);
     }
 
+    /// Replace the time source. Resets the pairing manager so its codes use
+    /// the same clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.pairing = Arc::new(Mutex::new(PairingManager::with_clock(
+            PAIRING_CODE_TTL,
+            Arc::clone(&clock),
+        )));
+        self.clock = clock;
+    }
+
+    /// The current time source.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
     /// Get the latest config, preferring the hot-reloaded version if available.
     pub fn current_config(&self) -> AppConfig {
         if let Some(rx) = &self.config_rx {
@@ -227,7 +500,7 @@ impl AppState {
     /// key so the real key is never visible in the page source.
     pub fn issue_webchat_token(&self) -> String {
         let token = Uuid::new_v4().simple().to_string();
-        self.webchat_tokens.insert(token.clone(), Instant::now());
+        self.webchat_tokens.insert(token.clone(), self.clock.now());
         token
     }
 
@@ -236,8 +509,9 @@ impl AppState {
     /// Expired tokens are pruned from the map on each call.
     pub fn validate_webchat_token(&self, token: &str) -> bool {
         // Prune all expired tokens while we have the map open.
+        let now = self.clock.now();
         self.webchat_tokens
-            .retain(|_, issued_at| issued_at.elapsed() < WEBCHAT_TOKEN_TTL);
+            .retain(|_, issued_at| now.duration_since(*issued_at) < WEBCHAT_TOKEN_TTL);
         self.webchat_tokens.contains_key(token)
     }
 
@@ -245,7 +519,7 @@ impl AppState {
     pub fn issue_google_oauth_state(&self) -> String {
         let state = Uuid::new_v4().to_string();
         self.google_oauth_states
-            .insert(state.clone(), Instant::now());
+            .insert(state.clone(), self.clock.now());
         state
     }
 
@@ -253,7 +527,7 @@ impl AppState {
     pub fn consume_google_oauth_state(&self, state: &str, max_age: Duration) -> bool {
         self.google_oauth_states
             .remove(state)
-            .map(|(_, created_at)| created_at.elapsed() <= max_age)
+            .map(|(_, created_at)| self.clock.now().duration_since(created_at) <= max_age)
             .unwrap_or(false)
     }
 
@@ -289,7 +563,7 @@ impl AppState {
         user_id: &str,
         config: &RateLimitConfig,
     ) -> std::result::Result<(), String> {
-        let now = Instant::now();
+        let now = self.clock.now();
 
         let mut entry = self
             .user_rate_limits
@@ -355,7 +629,7 @@ impl AppState {
     /// Create a session with a specific ID (used by channels like Telegram
     /// where the external chat ID determines the session key).
     pub fn create_session_with_id(&self, id: String) {
-        let now = Instant::now();
+        let now = self.clock.now();
         self.session_summaries.remove(&id);
         self.sessions.insert(
             id.clone(),
@@ -372,13 +646,35 @@ impl AppState {
     }
 
     /// Resolve the continuity key used by the cross-channel memory bus.
-    /// When disabled, returns `None` and memory remains session-scoped.
-    pub fn continuity_key(&self, _user_id: Option<&str>) -> Option<String> {
+    /// With `shared_continuity` everyone shares one bus. Otherwise accounts
+    /// linked with `/link` share a bus per identity, and memory of unlinked
+    /// users remains session-scoped (`None`).
+    pub fn continuity_key(&self, user_id: Option<&str>) -> Option<String> {
         if self.config.memory.shared_continuity {
-            Some("bus:shared-global".to_string())
-        } else {
-            None
+            return Some("bus:shared-global".to_string());
+        }
+        let user_id = user_id?;
+        let list = self.allowlist.lock().unwrap();
+        list.is_linked(user_id)
+            .then(|| format!("bus:user:{}", list.identity(user_id)))
+    }
+
+    /// Whether `user_id` may have the agent schedule a message to another
+    /// chat on `channel`: the channel must be connected, and only the owner
+    /// (or an account linked to it) may message chats other than their own.
+    pub fn check_schedule_target(
+        &self,
+        user_id: Option<&str>,
+        channel: &str,
+    ) -> Result<(), String> {
+        if !self.channel_senders.contains_key(channel) {
+            return Err(format!("unknown channel '{channel}'"));
         }
+        let is_owner = user_id.is_some_and(|id| self.allowlist.lock().unwrap().is_owner(id));
+        if !is_owner {
+            return Err("only the owner can schedule messages to other chats".to_string());
+        }
+        Ok(())
     }
 
     /// Return a cloned history snapshot for a session.
@@ -425,7 +721,7 @@ impl AppState {
                 session.user_id = Some(user.to_string());
             }
             session.connected = true;
-            session.last_active = Instant::now();
+            session.last_active = self.clock.now();
         }
 
         let Some(store) = &self.session_store else {
@@ -484,6 +780,72 @@ impl AppState {
         }
     }
 
+    /// Replace a session's in-memory history with imported messages, creating the
+    /// session if needed. With `persist`, the stored messages are replaced as
+    /// well, so the imported history is what gets hydrated after a restart.
+    pub async fn import_session_history(
+        &self,
+        session_id: &str,
+        channel_id: Option<&str>,
+        messages: Vec<ChatMessage>,
+        persist: bool,
+    ) {
+        if !self.sessions.contains_key(session_id) {
+            self.create_session_with_id(session_id.to_string());
+        }
+
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            if let Some(channel) = channel_id {
+                session.channel_id = Some(channel.to_string());
+            }
+            session.last_active = self.clock.now();
+            session.history = messages.clone();
+        }
+
+        if !persist {
+            return;
+        }
+        let Some(store) = &self.session_store else {
+            return;
+        };
+
+        let channel = channel_id.unwrap_or("web");
+        let metadata = self
+            .continuity_key(None)
+            .map(|k| serde_json::json!({ "continuity_key": k }))
+            .unwrap_or_else(|| serde_json::json!({}));
+        if let Err(e) = store.upsert_session(session_id, channel, "anonymous", &metadata) {
+            warn!("failed to upsert session {session_id}: {e}");
+            return;
+        }
+        if let Err(e) = store.prune_old_messages(session_id, 0) {
+            warn!("failed to clear stored history for {session_id}: {e}");
+            return;
+        }
+        let imported = messages.iter().filter_map(|message| {
+            let direction = match message.role {
+                opencrust_agents::ChatRole::User => "user",
+                opencrust_agents::ChatRole::Assistant => "assistant",
+                _ => return None,
+            };
+            match &message.content {
+                opencrust_agents::MessagePart::Text(text) => Some((direction, text)),
+                _ => None,
+            }
+        });
+        for (direction, text) in imported {
+            if let Err(e) = store.append_message(
+                session_id,
+                direction,
+                text,
+                chrono::Utc::now(),
+                &serde_json::json!({ "channel_id": channel, "imported": true }),
+            ) {
+                warn!("failed to persist imported message for {session_id}: {e}");
+            }
+        }
+    }
+
     /// Append a user/assistant turn to in-memory state and persistent session storage.
     ///
     /// `channel_metadata` is an optional JSON object with channel-specific routing
@@ -509,7 +871,7 @@ impl AppState {
             if let Some(user) = user_id {
                 session.user_id = Some(user.to_string());
             }
-            session.last_active = Instant::now();
+            session.last_active = self.clock.now();
             session.history.push(ChatMessage {
                 role: opencrust_agents::ChatRole::User,
                 content: opencrust_agents::MessagePart::Text(user_text.to_string()),
@@ -563,6 +925,80 @@ impl AppState {
         }
     }
 
+    /// Drop the most recent turn (the last user message and the replies after
+    /// it) from memory and the session store, so an edited message can be run
+    /// again in its place.
+    pub async fn rewind_last_turn(&self, session_id: &str) {
+        if let Some(mut session) = self.sessions.get_mut(session_id)
+            && let Some(last_user) = session
+                .history
+                .iter()
+                .rposition(|m| matches!(m.role, opencrust_agents::ChatRole::User))
+        {
+            session.history.truncate(last_user);
+        }
+
+        if let Some(store) = &self.session_store
+            && let Err(e) = store.delete_last_turn(session_id)
+        {
+            warn!("failed to delete last turn for {session_id}: {e}");
+        }
+    }
+
+    /// Record a single message on its own, without the user/assistant pair of
+    /// [`Self::persist_turn`]. Outgoing messages (reminders, broadcasts) are
+    /// stored as assistant turns and incoming ones as user turns, so proactive
+    /// messages show up in the history the agent sees next.
+    ///
+    /// The message is appended to the in-memory history when it is already
+    /// loaded; otherwise the next hydration picks it up from the store.
+    pub async fn persist_message(&self, message: &Message) {
+        let opencrust_common::MessageContent::Text(text) = &message.content else {
+            return;
+        };
+        let session_id = message.session_id.as_str();
+        let channel = message.channel_id.as_str();
+        let (role, direction) = match message.direction {
+            MessageDirection::Outgoing => (opencrust_agents::ChatRole::Assistant, "assistant"),
+            MessageDirection::Incoming => (opencrust_agents::ChatRole::User, "user"),
+        };
+
+        if let Some(mut session) = self.sessions.get_mut(session_id)
+            && (!session.history.is_empty() || self.session_store.is_none())
+        {
+            session.history.push(ChatMessage {
+                role,
+                content: opencrust_agents::MessagePart::Text(text.clone()),
+            });
+        }
+
+        let Some(store) = &self.session_store else {
+            return;
+        };
+        // Only create the session row when missing, so an existing row keeps
+        // its channel routing metadata.
+        if let Ok(None) = store.load_session_metadata(session_id)
+            && let Err(e) = store.upsert_session(
+                session_id,
+                channel,
+                message.user_id.as_str(),
+                &message.metadata,
+            )
+        {
+            warn!("failed to upsert session {session_id}: {e}");
+            return;
+        }
+        if let Err(e) = store.append_message(
+            session_id,
+            direction,
+            text,
+            message.timestamp,
+            &serde_json::json!({ "channel_id": channel, "proactive": true }),
+        ) {
+            warn!("failed to persist {direction} message for {session_id}: {e}");
+        }
+    }
+
     /// Persist token usage for a completed agent turn to the session store.
     ///
     /// Also increments the in-memory session token counter used for budget checks.
@@ -685,7 +1121,7 @@ impl AppState {
     pub fn disconnect_session(&self, session_id: &str) {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
             session.connected = false;
-            session.last_active = Instant::now();
+            session.last_active = self.clock.now();
         }
     }
 
@@ -693,7 +1129,7 @@ impl AppState {
     pub fn resume_session(&self, session_id: &str) -> bool {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
             session.connected = true;
-            session.last_active = Instant::now();
+            session.last_active = self.clock.now();
             true
         } else {
             false
@@ -702,7 +1138,7 @@ impl AppState {
 
     /// Remove sessions that have been disconnected longer than the TTL.
     pub fn cleanup_expired_sessions(&self) -> usize {
-        let now = Instant::now();
+        let now = self.clock.now();
         let mut removed = 0;
 
         self.sessions.retain(|_id, session| {
@@ -727,14 +1163,24 @@ impl AppState {
             .retain_session_dna_overrides(|session_id| self.sessions.contains_key(session_id));
         self.agents
             .retain_session_skills_overrides(|session_id| self.sessions.contains_key(session_id));
+        self.agents
+            .retain_session_models(|session_id| self.sessions.contains_key(session_id));
+        self.agents
+            .retain_tool_results(|session_id| self.sessions.contains_key(session_id));
+        self.agents
+            .retain_session_reasoning(|session_id| self.sessions.contains_key(session_id));
+        self.agents
+            .retain_tool_attachments(|session_id| self.sessions.contains_key(session_id));
+        self.agents
+            .retain_pending_questions(|session_id| self.sessions.contains_key(session_id));
 
         // Drop pending files that were never confirmed within PENDING_FILE_TTL.
         self.pending_files
-            .retain(|_, f| f.received_at.elapsed() < PENDING_FILE_TTL);
+            .retain(|_, f| now.duration_since(f.received_at) < PENDING_FILE_TTL);
 
         // Evict expired webchat tokens.
         self.webchat_tokens
-            .retain(|_, issued_at| issued_at.elapsed() < WEBCHAT_TOKEN_TTL);
+            .retain(|_, issued_at| now.duration_since(*issued_at) < WEBCHAT_TOKEN_TTL);
 
         // Evict rate-limit entries whose sliding window has fully expired and
         // whose cooldown (if any) has also elapsed. Without this, the DashMap
@@ -758,6 +1204,159 @@ impl AppState {
         removed
     }
 
+    /// Send `text` to every allowlisted user who has talked to the bot on the
+    /// channel registered as `channel`. The allowlist is shared by all
+    /// channels, so users are matched against the sessions of the sender's
+    /// channel type. Messages go out one at a time, [`BROADCAST_INTERVAL`]
+    /// apart, so large allowlists do not trip platform rate limits.
+    pub async fn broadcast(
+        &self,
+        channel: &str,
+        text: &str,
+    ) -> Result<BroadcastReport, BroadcastError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(BroadcastError::EmptyText);
+        }
+        let sender = self
+            .channel_senders
+            .get(channel)
+            .map(|s| Arc::clone(s.value()))
+            .ok_or_else(|| BroadcastError::UnknownChannel(channel.to_string()))?;
+        let channel_users = self.channel_users(sender.channel_type());
+        let mut users: Vec<String> = self
+            .allowlist
+            .lock()
+            .unwrap()
+            .list_users()
+            .into_iter()
+            .filter(|user| channel_users.contains(*user))
+            .map(str::to_string)
+            .collect();
+        users.sort();
+
+        let mut report = BroadcastReport::default();
+        for (i, user_id) in users.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(BROADCAST_INTERVAL).await;
+            }
+            let mut message = Message::text(
+                SessionId::new(),
+                ChannelId::from_string(channel),
+                UserId::from_string(user_id),
+                MessageDirection::Outgoing,
+                text,
+            );
+            message.metadata = recipient_metadata(sender.channel_type(), user_id);
+            match sender.send_message(&message).await {
+                Ok(()) => {
+                    report.sent += 1;
+                    if let Some(session_id) = dm_session_id(sender.channel_type(), user_id) {
+                        message.session_id = SessionId::from_string(session_id);
+                        self.persist_message(&message).await;
+                    }
+                }
+                Err(e) => {
+                    warn!("broadcast to {user_id} on {channel} failed: {e}");
+                    report.failed += 1;
+                }
+            }
+        }
+        info!(
+            "broadcast on {channel}: {} sent, {} failed",
+            report.sent, report.failed
+        );
+        Ok(report)
+    }
+
+    /// IDs of users with a session on `channel_type`, live or stored.
+    fn channel_users(&self, channel_type: &str) -> HashSet<String> {
+        let mut users: HashSet<String> = self
+            .sessions
+            .iter()
+            .filter(|s| s.channel_id.as_deref() == Some(channel_type))
+            .filter_map(|s| s.user_id.clone())
+            .collect();
+        if let Some(store) = &self.session_store {
+            match store.channel_users(channel_type) {
+                Ok(stored) => users.extend(stored),
+                Err(e) => warn!("failed to list {channel_type} users: {e}"),
+            }
+        }
+        users
+    }
+
+    /// Send `text` to the owner's conversation on every connected channel.
+    /// A channel's `owner_chat` setting names that conversation; otherwise
+    /// the owner's account on that channel is messaged directly: the
+    /// allowlist owner or an account linked to it with `/link`, whichever has
+    /// a session on the channel. Channels with neither are skipped.
+    pub async fn broadcast_to_owners(&self, text: &str) -> BroadcastReport {
+        let owner_accounts: Vec<String> = self
+            .allowlist
+            .lock()
+            .unwrap()
+            .owner_accounts()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let messages = Mutex::new(Vec::new());
+        let results = self
+            .channels
+            .broadcast(|sender| {
+                let recipient = self
+                    .config
+                    .channels
+                    .get(sender.channel_name())
+                    .and_then(|c| c.settings.get("owner_chat"))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .or_else(|| {
+                        let channel_users = self.channel_users(sender.channel_type());
+                        owner_accounts
+                            .iter()
+                            .find(|account| channel_users.contains(*account))
+                            .cloned()
+                    })?;
+                let mut message = Message::text(
+                    SessionId::new(),
+                    ChannelId::from_string(sender.channel_name()),
+                    UserId::from_string(&recipient),
+                    MessageDirection::Outgoing,
+                    text,
+                );
+                message.metadata = recipient_metadata(sender.channel_type(), &recipient);
+                if let Some(session_id) = dm_session_id(sender.channel_type(), &recipient) {
+                    let mut persisted = message.clone();
+                    persisted.session_id = SessionId::from_string(session_id);
+                    messages
+                        .lock()
+                        .unwrap()
+                        .push((sender.channel_name().to_string(), persisted));
+                }
+                Some(message)
+            })
+            .await;
+
+        let mut report = BroadcastReport::default();
+        let messages = messages.into_inner().unwrap();
+        for (channel, result) in results {
+            if result.is_err() {
+                report.failed += 1;
+                continue;
+            }
+            report.sent += 1;
+            if let Some((_, message)) = messages.iter().find(|(name, _)| *name == channel) {
+                self.persist_message(message).await;
+            }
+        }
+        info!(
+            "broadcast to owners: {} sent, {} failed",
+            report.sent, report.failed
+        );
+        report
+    }
+
     /// Spawn a background task that periodically cleans up expired sessions.
     pub fn spawn_session_cleanup(self: &Arc<Self>) {
         let state = Arc::clone(self);
@@ -770,6 +1369,57 @@ impl AppState {
         });
     }
 
+    /// Delete stored turns and memory entries older than the configured
+    /// retention windows. Unset windows are skipped.
+    pub async fn enforce_retention(&self) -> RetentionReport {
+        let config = self.current_config();
+        let now = chrono::Utc::now();
+        let mut report = RetentionReport::default();
+
+        if let Some(days) = config.sessions.retention_days
+            && let Some(store) = &self.session_store
+        {
+            match store.delete_history_before(now - chrono::Duration::days(days.into())) {
+                Ok(purge) => {
+                    report.messages = purge.messages;
+                    report.sessions = purge.sessions;
+                }
+                Err(e) => warn!("session retention failed: {e}"),
+            }
+        }
+        if let Some(days) = config.memory.retention_days {
+            match self
+                .agents
+                .delete_expired_memory(now - chrono::Duration::days(days.into()))
+                .await
+            {
+                Ok(deleted) => report.memory_entries = deleted,
+                Err(e) => warn!("memory retention failed: {e}"),
+            }
+        }
+
+        if report != RetentionReport::default() {
+            info!(
+                "retention: deleted {} message(s), {} session(s), {} memory entry(ies)",
+                report.messages, report.sessions, report.memory_entries
+            );
+        }
+        report
+    }
+
+    /// Spawn a background task that enforces retention windows every
+    /// [`RETENTION_INTERVAL`], starting at launch.
+    pub fn spawn_retention(self: &Arc<Self>) {
+        let state = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                state.enforce_retention().await;
+            }
+        });
+    }
+
     /// Spawn a background task that logs hot-reloaded config changes.
     /// Note: Agent-level settings (system_prompt, max_tokens) will take effect
     /// on next restart. Provider and channel changes also require restart.
@@ -962,6 +1612,95 @@ mod tests {
         assert!(state.check_user_rate_limit("user1", &cfg).is_err());
     }
 
+    #[test]
+    fn rate_limit_cooldown_expires_when_clock_advances() {
+        let clock = Arc::new(opencrust_common::FakeClock::new());
+        let mut state = test_state();
+        state.set_clock(clock.clone());
+        let cfg = rate_limit_config(20, 2, 30);
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+        assert!(state.check_user_rate_limit("user1", &cfg).is_err());
+
+        clock.advance(Duration::from_secs(29));
+        assert!(state.check_user_rate_limit("user1", &cfg).is_err());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+    }
+
+    #[test]
+    fn rate_limit_window_slides_with_clock() {
+        let clock = Arc::new(opencrust_common::FakeClock::new());
+        let mut state = test_state();
+        state.set_clock(clock.clone());
+        let cfg = rate_limit_config(2, 10, 0);
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+        assert!(state.check_user_rate_limit("user1", &cfg).is_err());
+
+        clock.advance(RATE_LIMIT_WINDOW + Duration::from_secs(1));
+        assert!(state.check_user_rate_limit("user1", &cfg).is_ok());
+    }
+
+    #[test]
+    fn pairing_codes_expire_on_state_clock() {
+        let clock = Arc::new(opencrust_common::FakeClock::new());
+        let mut state = test_state();
+        state.set_clock(clock.clone());
+        let code = state.pairing.lock().unwrap().generate("telegram");
+
+        clock.advance(PAIRING_CODE_TTL);
+        assert!(
+            state
+                .pairing
+                .lock()
+                .unwrap()
+                .claim(&code, "user1")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn sessions_and_tokens_expire_on_state_clock() {
+        let clock = Arc::new(opencrust_common::FakeClock::new());
+        let mut state = test_state();
+        state.set_clock(clock.clone());
+        state.create_session_with_id("s1".to_string());
+        state.disconnect_session("s1");
+        let token = state.issue_webchat_token();
+
+        clock.advance(SESSION_TTL);
+        assert_eq!(state.cleanup_expired_sessions(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(state.cleanup_expired_sessions(), 1);
+        assert!(state.validate_webchat_token(&token));
+
+        clock.advance(WEBCHAT_TOKEN_TTL);
+        assert!(!state.validate_webchat_token(&token));
+    }
+
+    #[test]
+    fn continuity_key_follows_linked_identity() {
+        let state = test_state();
+        state
+            .allowlist
+            .lock()
+            .unwrap()
+            .link("discord-1", "telegram-1");
+
+        assert_eq!(
+            state.continuity_key(Some("discord-1")),
+            Some("bus:user:telegram-1".to_string())
+        );
+        assert_eq!(
+            state.continuity_key(Some("telegram-1")),
+            state.continuity_key(Some("discord-1"))
+        );
+        assert_eq!(state.continuity_key(Some("stranger")), None);
+    }
+
     #[test]
     fn continuity_key_with_shared_continuity_disabled() {
         let mut config = AppConfig::default();
@@ -1250,4 +1989,364 @@ mod tests {
             "expired session skills should be evicted"
         );
     }
+
+    /// Sender that records every outbound message.
+    struct RecordingSender {
+        sent: Arc<Mutex<Vec<Message>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl opencrust_channels::ChannelSender for RecordingSender {
+        fn channel_type(&self) -> &str {
+            "telegram"
+        }
+
+        async fn send_message(&self, message: &Message) -> opencrust_common::Result<()> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn broadcast_sends_to_each_allowed_user() {
+        let mut state = test_state();
+        state.allowlist = Arc::new(Mutex::new(Allowlist::restricted(vec![
+            "200".to_string(),
+            "100".to_string(),
+            "300".to_string(),
+        ])));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender {
+                sent: Arc::clone(&sent),
+            }),
+        );
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+        // "300" is allowed but only ever talked to the bot on Discord.
+        for (channel, user) in [("telegram", "100"), ("telegram", "200"), ("discord", "300")] {
+            store
+                .upsert_session(
+                    &format!("{channel}-{user}"),
+                    channel,
+                    user,
+                    &serde_json::json!({}),
+                )
+                .unwrap();
+        }
+
+        let report = state
+            .broadcast("telegram", "Down for maintenance")
+            .await
+            .unwrap();
+
+        assert_eq!(report, BroadcastReport { sent: 2, failed: 0 });
+        // Each recipient's DM history records the announcement.
+        let stored = store.load_recent_messages("telegram-100", 10).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].direction, "assistant");
+        let sent = sent.lock().unwrap();
+        let chat_ids: Vec<i64> = sent
+            .iter()
+            .map(|m| m.metadata["telegram_chat_id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(chat_ids, vec![100, 200]);
+        assert!(sent.iter().all(|m| matches!(
+            &m.content,
+            opencrust_common::MessageContent::Text(t) if t == "Down for maintenance"
+        )));
+    }
+
+    #[tokio::test]
+    async fn persisted_import_replaces_stored_history() {
+        let mut state = test_state();
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+        store
+            .upsert_session("s1", "web", "anonymous", &serde_json::json!({}))
+            .unwrap();
+        for (direction, text) in [("user", "A"), ("assistant", "B")] {
+            store
+                .append_message(
+                    "s1",
+                    direction,
+                    text,
+                    chrono::Utc::now(),
+                    &serde_json::json!({}),
+                )
+                .unwrap();
+        }
+
+        let messages = vec![
+            ChatMessage {
+                role: opencrust_agents::ChatRole::User,
+                content: opencrust_agents::MessagePart::Text("X".into()),
+            },
+            ChatMessage {
+                role: opencrust_agents::ChatRole::Assistant,
+                content: opencrust_agents::MessagePart::Text("Y".into()),
+            },
+        ];
+        state
+            .import_session_history("s1", Some("web"), messages, true)
+            .await;
+
+        let stored: Vec<(String, String)> = store
+            .load_recent_messages("s1", 10)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.direction, m.content))
+            .collect();
+        assert_eq!(
+            stored,
+            vec![
+                ("user".to_string(), "X".to_string()),
+                ("assistant".to_string(), "Y".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_unknown_channel_fails() {
+        let state = test_state();
+        let err = state.broadcast("discord", "hello").await.unwrap_err();
+        assert_eq!(err, BroadcastError::UnknownChannel("discord".to_string()));
+        let err = state.broadcast("discord", "  ").await.unwrap_err();
+        assert_eq!(err, BroadcastError::EmptyText);
+    }
+
+    #[test]
+    fn schedule_target_requires_a_connected_channel_and_the_owner() {
+        let mut state = test_state();
+        let mut allowlist = Allowlist::restricted(Vec::new());
+        allowlist.claim_owner("100");
+        state.allowlist = Arc::new(Mutex::new(allowlist));
+        state.channel_senders.insert(
+            "telegram".to_string(),
+            Arc::new(RecordingSender {
+                sent: Arc::new(Mutex::new(Vec::new())),
+            }),
+        );
+
+        assert!(state.check_schedule_target(Some("100"), "telegram").is_ok());
+        let err = state
+            .check_schedule_target(Some("100"), "discord")
+            .unwrap_err();
+        assert!(err.contains("unknown channel"));
+        let err = state
+            .check_schedule_target(Some("200"), "telegram")
+            .unwrap_err();
+        assert!(err.contains("only the owner"));
+        assert!(state.check_schedule_target(None, "telegram").is_err());
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_owners_prefers_owner_chat() {
+        let mut config = AppConfig::default();
+        config.channels.insert(
+            "telegram".to_string(),
+            serde_json::from_value(serde_json::json!({
+                "type": "telegram",
+                "owner_chat": "-100555",
+            }))
+            .unwrap(),
+        );
+        let mut state = AppState::new(
+            config,
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        );
+        let mut allowlist = Allowlist::restricted(Vec::new());
+        allowlist.claim_owner("100");
+        state.allowlist = Arc::new(Mutex::new(allowlist));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channels.queued_sender(Box::new(RecordingSender {
+            sent: Arc::clone(&sent),
+        }));
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        let report = state.broadcast_to_owners("Down for maintenance").await;
+
+        assert_eq!(report, BroadcastReport { sent: 1, failed: 0 });
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent[0].metadata["telegram_chat_id"], -100555);
+        let stored = store.load_recent_messages("telegram--100555", 10).unwrap();
+        assert_eq!(stored.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_owners_uses_the_owner_account_on_each_channel() {
+        let mut state = test_state();
+        let mut allowlist = Allowlist::restricted(Vec::new());
+        allowlist.claim_owner("dc-100");
+        state.allowlist = Arc::new(Mutex::new(allowlist));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        state.channels.queued_sender(Box::new(RecordingSender {
+            sent: Arc::clone(&sent),
+        }));
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        // The owner has never used Telegram, so there is nobody to message.
+        let report = state.broadcast_to_owners("Down for maintenance").await;
+        assert_eq!(report, BroadcastReport::default());
+
+        // Their linked Telegram account is messaged instead.
+        state.allowlist.lock().unwrap().link("555", "dc-100");
+        store
+            .upsert_session("telegram-555", "telegram", "555", &serde_json::json!({}))
+            .unwrap();
+        let report = state.broadcast_to_owners("Down for maintenance").await;
+        assert_eq!(report, BroadcastReport { sent: 1, failed: 0 });
+        assert_eq!(sent.lock().unwrap()[0].metadata["telegram_chat_id"], 555);
+    }
+
+    #[tokio::test]
+    async fn enforce_retention_deletes_only_expired_history_and_memory() {
+        let memory = Arc::new(opencrust_db::MemoryStore::in_memory().unwrap());
+        let mut agents = AgentRuntime::new();
+        agents.set_memory_provider(memory.clone());
+        let mut config = AppConfig::default();
+        config.sessions.retention_days = Some(30);
+        config.memory.retention_days = Some(30);
+        let mut state = AppState::new(config, Arc::new(agents), ChannelRegistry::new());
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        let now = chrono::Utc::now();
+        let meta = serde_json::json!({});
+        store.upsert_session("s1", "web", "u1", &meta).unwrap();
+        store
+            .append_message("s1", "user", "old", now - chrono::Duration::days(31), &meta)
+            .unwrap();
+        store
+            .append_message("s1", "user", "new", now - chrono::Duration::days(29), &meta)
+            .unwrap();
+        memory
+            .remember(opencrust_db::NewMemoryEntry {
+                session_id: "s1".into(),
+                channel_id: None,
+                user_id: None,
+                continuity_key: None,
+                role: opencrust_db::MemoryRole::User,
+                content: "recent memory".into(),
+                embedding: None,
+                embedding_model: None,
+                metadata: serde_json::json!({}),
+                attachments: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let report = state.enforce_retention().await;
+        assert_eq!(
+            report,
+            RetentionReport {
+                messages: 1,
+                sessions: 0,
+                memory_entries: 0,
+            }
+        );
+        let recalled = memory.get_session_context("s1", 10).await.unwrap();
+        assert_eq!(recalled.entries.len(), 1);
+        let left = store.load_recent_messages("s1", 10).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].content, "new");
+    }
+
+    #[tokio::test]
+    async fn proactive_message_appears_in_hydrated_history() {
+        let mut state = test_state();
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        let reminder = Message::text(
+            SessionId::from_string("telegram-42"),
+            ChannelId::from_string("telegram"),
+            UserId::from_string("42"),
+            MessageDirection::Outgoing,
+            "Time to call mom!",
+        );
+        state.persist_message(&reminder).await;
+
+        // A later turn loads the reminder from the store as an assistant turn.
+        state
+            .hydrate_session_history("telegram-42", Some("telegram"), Some("42"))
+            .await;
+        let history = state.session_history("telegram-42");
+        assert_eq!(history.len(), 1);
+        assert!(matches!(
+            history[0].role,
+            opencrust_agents::ChatRole::Assistant
+        ));
+        assert!(matches!(
+            &history[0].content,
+            opencrust_agents::MessagePart::Text(t) if t == "Time to call mom!"
+        ));
+
+        // With history already in memory, the message is appended there too.
+        state
+            .persist_turn(
+                "telegram-42",
+                Some("telegram"),
+                Some("42"),
+                "hi",
+                "hello",
+                None,
+            )
+            .await;
+        let follow_up = Message::text(
+            SessionId::from_string("telegram-42"),
+            ChannelId::from_string("telegram"),
+            UserId::from_string("42"),
+            MessageDirection::Outgoing,
+            "Did you call?",
+        );
+        state.persist_message(&follow_up).await;
+        let history = state.session_history("telegram-42");
+        assert_eq!(history.len(), 4);
+        assert_eq!(
+            store.load_recent_messages("telegram-42", 10).unwrap().len(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn rewind_last_turn_drops_latest_exchange() {
+        let mut state = test_state();
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        for (user, assistant) in [("q1", "a1"), ("q2", "a2")] {
+            state
+                .persist_turn("slack-C1", Some("slack"), Some("U1"), user, assistant, None)
+                .await;
+        }
+        state.rewind_last_turn("slack-C1").await;
+
+        let history = state.session_history("slack-C1");
+        assert_eq!(history.len(), 2);
+        assert!(matches!(
+            &history[0].content,
+            opencrust_agents::MessagePart::Text(t) if t == "q1"
+        ));
+        assert_eq!(store.load_recent_messages("slack-C1", 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn inbound_dedup_is_namespaced_by_channel_and_survives_restarts() {
+        let mut state = test_state();
+        assert!(state.inbound_dedup("telegram").is_none());
+        let store = Arc::new(SessionStore::in_memory().unwrap());
+        state.set_session_store(Arc::clone(&store));
+
+        let telegram = state.inbound_dedup("telegram").unwrap();
+        assert!(telegram("42:7:0"));
+        assert!(!telegram("42:7:0"));
+        assert!(state.inbound_dedup("slack").unwrap()("42:7:0"));
+        // A fresh hook over the same store (e.g. after a restart) still knows the key.
+        assert!(!state.inbound_dedup("telegram").unwrap()("42:7:0"));
+    }
 }
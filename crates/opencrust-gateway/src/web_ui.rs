@@ -0,0 +1,146 @@
+//! Bundled web chat UI, compiled in with the `web-ui` feature.
+//!
+//! The page talks to the gateway over `/ws`. Its scripts, styles and images
+//! are embedded in the binary with `rust-embed`, so the gateway serves the UI
+//! without an `assets/` directory next to it.
+
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use rust_embed::RustEmbed;
+
+use crate::state::SharedState;
+
+/// Static files under the repository `assets/` directory. README-only media is
+/// left out to keep the binary small.
+#[derive(RustEmbed)]
+#[folder = "../../assets"]
+#[exclude = "demo.*"]
+#[exclude = "logo.png"]
+struct Assets;
+
+const INDEX_HTML: &str = include_str!("webchat.html");
+
+/// Routes for the chat page at `/` and its files under `/assets/`.
+pub fn routes() -> Router<SharedState> {
+    Router::new()
+        .route("/", get(index))
+        .route("/assets/{*path}", get(asset))
+}
+
+async fn index(State(state): State<SharedState>) -> Html<String> {
+    // Issue a short-lived webchat token and inject it instead of the real
+    // gateway API key.  The token is validated server-side on WebSocket
+    // upgrade so the real key is never exposed in the page source.
+    let inject = if state.config.gateway.api_key.is_some() {
+        let token = state.issue_webchat_token();
+        format!(
+            "<script>window.__OPENCRUST_GATEWAY_KEY__={:?};</script>",
+            token
+        )
+    } else {
+        String::new()
+    };
+
+    Html(INDEX_HTML.replacen("</head>", &format!("{inject}</head>"), 1))
+}
+
+async fn asset(Path(path): Path<String>) -> Response {
+    match Assets::get(&path) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use opencrust_agents::AgentRuntime;
+    use opencrust_channels::ChannelRegistry;
+    use opencrust_config::AppConfig;
+    use tower::ServiceExt;
+
+    fn router(api_key: Option<&str>) -> Router {
+        let mut config = AppConfig::default();
+        config.gateway.api_key = api_key.map(ToString::to_string);
+        let state = Arc::new(crate::state::AppState::new(
+            config,
+            Arc::new(AgentRuntime::new()),
+            ChannelRegistry::new(),
+        ));
+        routes().with_state(state)
+    }
+
+    async fn get(router: Router, uri: &str) -> Response {
+        router
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body_text(resp: Response) -> String {
+        let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    #[tokio::test]
+    async fn index_returns_embedded_html() {
+        let resp = get(router(None), "/").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            resp.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+        let html = body_text(resp).await;
+        assert!(html.contains("/assets/webchat/main.js"));
+        assert!(!html.contains("__OPENCRUST_GATEWAY_KEY__"));
+    }
+
+    #[tokio::test]
+    async fn index_injects_webchat_token_instead_of_api_key() {
+        let html = body_text(get(router(Some("secret-key")), "/").await).await;
+        assert!(html.contains("window.__OPENCRUST_GATEWAY_KEY__="));
+        assert!(!html.contains("secret-key"));
+    }
+
+    #[tokio::test]
+    async fn serves_embedded_assets_with_content_type() {
+        let resp = get(router(None), "/assets/webchat/main.js").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            resp.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .contains("javascript")
+        );
+        assert!(body_text(resp).await.contains("initIntegrationsView"));
+
+        let resp = get(router(None), "/assets/webchat/integrations/styles.css").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/css");
+
+        let resp = get(router(None), "/assets/UI%20Crab.png").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "image/png");
+    }
+
+    #[tokio::test]
+    async fn unknown_and_excluded_assets_are_not_found() {
+        for uri in ["/assets/webchat/missing.js", "/assets/demo.gif"] {
+            let resp = get(router(None), uri).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+}
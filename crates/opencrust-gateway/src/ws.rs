@@ -307,6 +307,7 @@ async fn process_text_message(
     sender: &mut futures::stream::SplitSink<WebSocket, Message>,
 ) -> Option<serde_json::Value> {
     let (user_text, provider_id, model_override) = parse_user_message(text);
+    let (agent_id, stream) = parse_turn_options(text);
 
     // Input validation
     let user_text = opencrust_security::InputValidator::sanitize(&user_text);
@@ -437,7 +438,7 @@ async fn process_text_message(
 
     // Resolve named agent config for the web channel (#301)
     let config = state.current_config();
-    let agent_config = agent_router::resolve(&config, None, Some("web"));
+    let agent_config = agent_router::resolve(&config, agent_id.as_deref(), Some("web"));
 
     // Apply per-agent overrides (#300 tools, #303 dna/skills) and resolve call params
     let (effective_provider, effective_system_prompt, effective_max_tokens, effective_max_ctx) =
@@ -474,24 +475,42 @@ async fn process_text_message(
             (provider_id.clone(), None, None, None)
         };
 
+    // Streaming has no agent-config variant, so turns with per-agent or
+    // per-message overrides always reply in one piece.
+    let streamable =
+        stream && agent_config.is_none() && provider_id.is_none() && model_override.is_none();
+
     // Route through agent runtime (with optional provider override)
-    let reply = match state
-        .agents
-        .process_message_with_agent_config_and_summary(
+    let result = if streamable {
+        stream_turn(
+            state,
             session_id,
             &user_text,
             &history,
             continuity_key.as_deref(),
-            None,
-            effective_provider.as_deref(),
-            model_override.as_deref(),
-            effective_system_prompt.as_deref(),
-            effective_max_tokens,
-            effective_max_ctx, // #302
             summary.as_deref(),
+            sender,
         )
         .await
-    {
+    } else {
+        state
+            .agents
+            .process_message_with_agent_config_and_summary(
+                session_id,
+                &user_text,
+                &history,
+                continuity_key.as_deref(),
+                None,
+                effective_provider.as_deref(),
+                model_override.as_deref(),
+                effective_system_prompt.as_deref(),
+                effective_max_tokens,
+                effective_max_ctx, // #302
+                summary.as_deref(),
+            )
+            .await
+    };
+    let reply = match result {
         Ok((response_text, new_summary)) => {
             if let Some(s) = new_summary {
                 state.update_session_summary(session_id, &s);
@@ -547,6 +566,53 @@ async fn process_text_message(
     Some(reply)
 }
 
+/// Run a streaming turn, forwarding text deltas to the client as
+/// `{"type": "delta", "content": "..."}` frames ahead of the final message.
+async fn stream_turn(
+    state: &SharedState,
+    session_id: &str,
+    user_text: &str,
+    history: &[ChatMessage],
+    continuity_key: Option<&str>,
+    summary: Option<&str>,
+    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+) -> opencrust_common::Result<(String, Option<String>)> {
+    let (delta_tx, mut delta_rx) = tokio::sync::mpsc::channel::<String>(64);
+    let turn = state
+        .agents
+        .process_message_streaming_with_context_and_summary(
+            session_id,
+            user_text,
+            history,
+            delta_tx,
+            summary,
+            continuity_key,
+            None,
+        );
+    tokio::pin!(turn);
+
+    let delta_frame = |delta: &str| {
+        serde_json::json!({
+            "type": "delta",
+            "session_id": session_id,
+            "content": delta,
+        })
+        .to_string()
+    };
+    let result = loop {
+        tokio::select! {
+            result = &mut turn => break result,
+            Some(delta) = delta_rx.recv() => {
+                let _ = sender.send(Message::Text(delta_frame(&delta).into())).await;
+            }
+        }
+    };
+    while let Ok(delta) = delta_rx.try_recv() {
+        let _ = sender.send(Message::Text(delta_frame(&delta).into())).await;
+    }
+    result
+}
+
 /// Try to parse a resume request: `{"type": "resume", "session_id": "..."}`.
 fn is_init_message(raw: &str) -> bool {
     serde_json::from_str::<serde_json::Value>(raw)
@@ -568,6 +634,20 @@ fn text_message_too_large(len: usize) -> bool {
     len > MAX_WS_TEXT_BYTES
 }
 
+/// Extract the optional `"agent_id"` and `"stream"` fields of a JSON chat message.
+fn parse_turn_options(raw: &str) -> (Option<String>, bool) {
+    let Ok(v) = serde_json::from_str::<serde_json::Value>(raw) else {
+        return (None, false);
+    };
+    let agent_id = v
+        .get("agent_id")
+        .and_then(|a| a.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+    let stream = v.get("stream").and_then(|s| s.as_bool()).unwrap_or(false);
+    (agent_id, stream)
+}
+
 /// Try to extract `"content"` plus optional `"provider"` and `"model"` from JSON,
 /// otherwise use the raw text as content with no overrides.
 fn parse_user_message(raw: &str) -> (String, Option<String>, Option<String>) {
@@ -592,7 +672,10 @@ fn parse_user_message(raw: &str) -> (String, Option<String>, Option<String>) {
 
 #[cfg(test)]
 mod tests {
-    use super::{MAX_WS_TEXT_BYTES, parse_user_message, text_message_too_large, try_parse_resume};
+    use super::{
+        MAX_WS_TEXT_BYTES, parse_turn_options, parse_user_message, text_message_too_large,
+        try_parse_resume,
+    };
 
     #[test]
     fn text_message_size_guard_uses_strict_upper_bound() {
@@ -651,4 +734,12 @@ mod tests {
         assert_eq!(provider, None);
         assert_eq!(model, None);
     }
+
+    #[test]
+    fn parse_turn_options_reads_agent_and_stream() {
+        let json = r#"{"content": "hi", "agent_id": "coder", "stream": true}"#;
+        assert_eq!(parse_turn_options(json), (Some("coder".to_string()), true));
+        assert_eq!(parse_turn_options(r#"{"content": "hi"}"#), (None, false));
+        assert_eq!(parse_turn_options("plain text"), (None, false));
+    }
 }
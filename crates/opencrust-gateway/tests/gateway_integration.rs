@@ -28,6 +28,8 @@ fn test_config(port: u16, mock_url: &str) -> AppConfig {
             model: Some("claude-test".to_string()),
             api_key: Some("sk-test-key".to_string()),
             base_url: Some(mock_url.to_string()),
+            max_tokens: None,
+            supports_tools: true,
             extra: Default::default(),
         },
     );
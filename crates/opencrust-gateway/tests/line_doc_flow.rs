@@ -132,6 +132,8 @@ async fn after_ingest_doc_search_finds_content() {
         user_id: None,
         heartbeat_depth: 0,
         allowed_tools: None,
+        continuity_key: None,
+        workspace_dir: None,
     };
 
     // Ask about document content
@@ -301,6 +303,8 @@ async fn doc_search_returns_empty_when_no_matching_content() {
         user_id: None,
         heartbeat_depth: 0,
         allowed_tools: None,
+        continuity_key: None,
+        workspace_dir: None,
     };
 
     // Query about something completely unrelated
@@ -1,12 +1,17 @@
 pub mod document;
 pub mod processing;
+#[cfg(feature = "whisper-local")]
+pub mod stt;
 pub mod tts;
 pub mod types;
 
 pub use document::{
     ChunkOptions, TextChunk, chunk_text, detect_mime_type, extract_text, is_supported_for_ingest,
 };
+#[cfg(feature = "whisper-local")]
+pub use stt::{LocalWhisper, transcribe_local};
 pub use tts::{
-    AudioBytes, TTS_DEFAULT_MAX_CHARS, TtsProvider, build_tts_provider, truncate_for_tts,
+    AudioBytes, ElevenLabsTts, OpenAiTts, PiperTts, TTS_DEFAULT_MAX_CHARS, TtsProvider,
+    build_tts_provider, truncate_for_tts,
 };
 pub use types::{MediaFormat, MediaType};
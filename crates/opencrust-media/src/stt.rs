@@ -0,0 +1,151 @@
+//! On-device speech-to-text with whisper.cpp (via `whisper-rs`).
+//!
+//! Enable with:  cargo build --features whisper-local
+//!
+//! Needs a ggml Whisper model (e.g. `ggml-base.bin` from
+//! https://huggingface.co/ggerganov/whisper.cpp) and `ffmpeg` on PATH to
+//! decode voice notes (OGG/Opus, M4A, WAV, ...) to 16 kHz mono PCM.
+//!
+//! Config example:
+//!   voice:
+//!     stt_local_model: /home/me/.opencrust/models/ggml-base.bin
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tracing::info;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use crate::tts::run_piped;
+
+/// Sample rate Whisper models expect.
+const WHISPER_SAMPLE_RATE: &str = "16000";
+
+/// A loaded Whisper model. Loading is slow, so models are cached per path
+/// and shared by every transcription (see [`transcribe_local`]).
+pub struct LocalWhisper {
+    context: WhisperContext,
+}
+
+impl LocalWhisper {
+    /// Load a ggml model from `model_path`. Blocking.
+    pub fn load(model_path: &str) -> Result<Self, String> {
+        let context =
+            WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
+                .map_err(|e| format!("failed to load whisper model {model_path}: {e}"))?;
+        info!("whisper-local: loaded model {model_path}");
+        Ok(Self { context })
+    }
+
+    /// Transcribe 16 kHz mono samples in `[-1.0, 1.0]`. Blocking and CPU-bound.
+    pub fn transcribe_samples(&self, samples: &[f32]) -> Result<String, String> {
+        let mut state = self
+            .context
+            .create_state()
+            .map_err(|e| format!("whisper state: {e}"))?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some("auto"));
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        if let Ok(threads) = std::thread::available_parallelism() {
+            params.set_n_threads(threads.get().min(8) as i32);
+        }
+
+        state
+            .full(params, samples)
+            .map_err(|e| format!("whisper transcription failed: {e}"))?;
+
+        let segments = state
+            .full_n_segments()
+            .map_err(|e| format!("whisper segments: {e}"))?;
+        let mut text = String::new();
+        for i in 0..segments {
+            let segment = state
+                .full_get_segment_text(i)
+                .map_err(|e| format!("whisper segment {i}: {e}"))?;
+            text.push_str(&segment);
+        }
+        Ok(text.trim().to_string())
+    }
+}
+
+/// Decode any audio ffmpeg understands to 16 kHz mono f32 samples.
+pub async fn decode_to_pcm(audio: &[u8]) -> Result<Vec<f32>, String> {
+    let raw = run_piped(
+        "ffmpeg",
+        &[
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-i",
+            "pipe:0",
+            "-f",
+            "s16le",
+            "-ar",
+            WHISPER_SAMPLE_RATE,
+            "-ac",
+            "1",
+            "pipe:1",
+        ],
+        audio.to_vec(),
+    )
+    .await?;
+    Ok(pcm_s16le_to_f32(&raw))
+}
+
+fn pcm_s16le_to_f32(raw: &[u8]) -> Vec<f32> {
+    raw.chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect()
+}
+
+/// The cached model for `model_path`, loading it on first use.
+fn cached_model(model_path: &str) -> Result<Arc<LocalWhisper>, String> {
+    static MODELS: OnceLock<Mutex<HashMap<String, Arc<LocalWhisper>>>> = OnceLock::new();
+    let mut models = MODELS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(model) = models.get(model_path) {
+        return Ok(Arc::clone(model));
+    }
+    let model = Arc::new(LocalWhisper::load(model_path)?);
+    models.insert(model_path.to_string(), Arc::clone(&model));
+    Ok(model)
+}
+
+/// Transcribe a voice note with the model at `model_path`.
+pub async fn transcribe_local(model_path: &str, audio: &[u8]) -> Result<String, String> {
+    let samples = decode_to_pcm(audio).await?;
+    if samples.is_empty() {
+        return Err("voice note contains no audio".to_string());
+    }
+    let model_path = model_path.to_string();
+    tokio::task::spawn_blocking(move || cached_model(&model_path)?.transcribe_samples(&samples))
+        .await
+        .map_err(|e| format!("whisper task failed: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_s16le_to_unit_range() {
+        let raw = [0x00, 0x00, 0xff, 0x7f, 0x00, 0x80, 0x01];
+        let samples = pcm_s16le_to_f32(&raw);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[1] - 32767.0 / 32768.0).abs() < f32::EPSILON);
+        assert_eq!(samples[2], -1.0);
+    }
+
+    #[test]
+    fn missing_model_is_an_error() {
+        let err = cached_model("/nonexistent/ggml-base.bin").err().unwrap();
+        assert!(err.contains("failed to load whisper model"));
+    }
+}
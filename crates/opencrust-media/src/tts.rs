@@ -111,6 +111,202 @@ impl TtsProvider for OpenAiTts {
     }
 }
 
+// ---------------------------------------------------------------------------
+// ElevenLabs TTS
+// ---------------------------------------------------------------------------
+
+/// Calls the ElevenLabs `/v1/text-to-speech/{voice_id}` endpoint.
+pub struct ElevenLabsTts {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    /// ElevenLabs voice ID (not the display name).
+    voice: String,
+    base_url: String,
+}
+
+impl ElevenLabsTts {
+    pub fn new(api_key: String, model: Option<String>, voice: Option<String>) -> Self {
+        Self::with_base_url(api_key, model, voice, None)
+    }
+
+    pub fn with_base_url(
+        api_key: String,
+        model: Option<String>,
+        voice: Option<String>,
+        base_url: Option<String>,
+    ) -> Self {
+        Self {
+            client: tts_http_client(),
+            api_key,
+            model: model.unwrap_or_else(|| "eleven_multilingual_v2".to_string()),
+            // "Rachel", one of the default voices every account has.
+            voice: voice.unwrap_or_else(|| "21m00Tcm4TlvDq8ikWAM".to_string()),
+            base_url: base_url
+                .unwrap_or_else(|| "https://api.elevenlabs.io".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl TtsProvider for ElevenLabsTts {
+    fn name(&self) -> &'static str {
+        "elevenlabs"
+    }
+
+    async fn synthesize(&self, text: &str) -> Result<AudioBytes, String> {
+        info!("elevenlabs tts: synthesizing {} chars", text.len());
+        let url = format!("{}/v1/text-to-speech/{}", self.base_url, self.voice);
+        let resp = self
+            .client
+            .post(&url)
+            .header("xi-api-key", &self.api_key)
+            .query(&[("output_format", "opus_48000_64")])
+            .json(&serde_json::json!({
+                "text": text,
+                "model_id": self.model,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("elevenlabs tts request failed: {e}"))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("elevenlabs tts error {status}: {body}"));
+        }
+
+        resp.bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|e| format!("elevenlabs tts read body failed: {e}"))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Piper TTS (local)
+//
+// Runs the `piper` binary on the voice model given as `model`, then encodes
+// its raw PCM output to OGG/Opus with `ffmpeg`. Both must be on PATH.
+//
+// Config example:
+//   voice:
+//     tts_provider: piper
+//     model: /opt/piper/en_US-lessac-medium.onnx
+//     auto_reply_voice: true
+// ---------------------------------------------------------------------------
+
+/// Sample rate piper voices use unless their config says otherwise.
+const PIPER_DEFAULT_SAMPLE_RATE: u64 = 22050;
+
+pub struct PiperTts {
+    /// Path to the `.onnx` voice model.
+    model: String,
+    sample_rate: u64,
+}
+
+impl PiperTts {
+    pub fn new(model: String) -> Self {
+        let sample_rate = piper_sample_rate(&model);
+        Self { model, sample_rate }
+    }
+}
+
+/// The sample rate from the voice's `<model>.json` config, which piper
+/// ships next to every model.
+fn piper_sample_rate(model: &str) -> u64 {
+    std::fs::read_to_string(format!("{model}.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v["audio"]["sample_rate"].as_u64())
+        .unwrap_or(PIPER_DEFAULT_SAMPLE_RATE)
+}
+
+/// Run `program` with `input` on stdin and return its stdout.
+pub(crate) async fn run_piped(
+    program: &str,
+    args: &[&str],
+    input: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+
+    // Feed stdin from a task so a full stdout pipe cannot deadlock us.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&input).await;
+    });
+    let output = tokio::time::timeout(TTS_HTTP_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{program} timed out"))?
+        .map_err(|e| format!("{program} failed: {e}"))?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{program} exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[async_trait]
+impl TtsProvider for PiperTts {
+    fn name(&self) -> &'static str {
+        "piper"
+    }
+
+    async fn synthesize(&self, text: &str) -> Result<AudioBytes, String> {
+        info!("piper tts: synthesizing {} chars", text.len());
+        let pcm = run_piped(
+            "piper",
+            &["--model", &self.model, "--output_raw"],
+            text.as_bytes().to_vec(),
+        )
+        .await?;
+        let rate = self.sample_rate.to_string();
+        run_piped(
+            "ffmpeg",
+            &[
+                "-hide_banner",
+                "-loglevel",
+                "error",
+                "-f",
+                "s16le",
+                "-ar",
+                &rate,
+                "-ac",
+                "1",
+                "-i",
+                "pipe:0",
+                "-c:a",
+                "libopus",
+                "-b:a",
+                "32k",
+                "-f",
+                "ogg",
+                "pipe:1",
+            ],
+            pcm,
+        )
+        .await
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Kokoro TTS (self-hosted via kokoro-fastapi)
 //
@@ -208,6 +404,22 @@ pub fn build_tts_provider(
                 tts_base_url,
             )))
         }
+        "elevenlabs" => {
+            let key = api_key?;
+            Some(Arc::new(ElevenLabsTts::with_base_url(
+                key,
+                model,
+                voice,
+                tts_base_url,
+            )))
+        }
+        "piper" => {
+            let Some(model) = model else {
+                tracing::warn!("tts_provider 'piper' needs `model` set to a voice model path");
+                return None;
+            };
+            Some(Arc::new(PiperTts::new(model)))
+        }
         #[cfg(feature = "tts-kokoro")]
         "kokoro" => Some(Arc::new(KokoroTts::new(tts_base_url, voice))),
         #[cfg(not(feature = "tts-kokoro"))]
@@ -252,7 +464,7 @@ pub fn truncate_for_tts(text: &str, max_chars: usize) -> &str {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{header, method, path};
+    use wiremock::matchers::{header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     // Fake OGG/Opus header (4 bytes) — just enough for the "non-empty bytes" check.
@@ -281,7 +493,32 @@ mod tests {
 
     #[test]
     fn build_tts_provider_unknown_returns_none() {
+        assert!(build_tts_provider(Some("festival"), None, None, None, None).is_none());
+    }
+
+    #[test]
+    fn build_tts_provider_elevenlabs_needs_key() {
         assert!(build_tts_provider(Some("elevenlabs"), None, None, None, None).is_none());
+        let p = build_tts_provider(Some("elevenlabs"), Some("xi-test".into()), None, None, None);
+        assert_eq!(p.unwrap().name(), "elevenlabs");
+    }
+
+    #[test]
+    fn build_tts_provider_piper_needs_model() {
+        assert!(build_tts_provider(Some("piper"), None, None, None, None).is_none());
+        let p = build_tts_provider(
+            Some("piper"),
+            None,
+            Some("/nonexistent/voice.onnx".into()),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(p.name(), "piper");
+        assert_eq!(
+            piper_sample_rate("/nonexistent/voice.onnx"),
+            PIPER_DEFAULT_SAMPLE_RATE
+        );
     }
 
     // -----------------------------------------------------------------------
@@ -374,6 +611,50 @@ mod tests {
         assert_eq!(audio, FAKE_AUDIO);
     }
 
+    // -----------------------------------------------------------------------
+    // ElevenLabsTts
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn elevenlabs_tts_success() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/text-to-speech/voice-1"))
+            .and(header("xi-api-key", "xi-test"))
+            .and(query_param("output_format", "opus_48000_64"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(FAKE_AUDIO)
+                    .insert_header("content-type", "audio/ogg"),
+            )
+            .mount(&server)
+            .await;
+
+        let tts = ElevenLabsTts::with_base_url(
+            "xi-test".into(),
+            None,
+            Some("voice-1".into()),
+            Some(server.uri()),
+        );
+        let audio = tts.synthesize("hello world").await.unwrap();
+        assert_eq!(audio, FAKE_AUDIO);
+    }
+
+    #[tokio::test]
+    async fn elevenlabs_tts_error_response_returns_err() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+            .mount(&server)
+            .await;
+
+        let tts = ElevenLabsTts::with_base_url("bad".into(), None, None, Some(server.uri()));
+        let err = tts.synthesize("hello").await.unwrap_err();
+        assert!(err.contains("401"), "expected 401 in error: {err}");
+    }
+
     // -----------------------------------------------------------------------
     // KokoroTts (only compiled when feature flag is on)
     // -----------------------------------------------------------------------
@@ -0,0 +1,152 @@
+//! Host functions exposed to WASM plugins under the `opencrust` import module.
+//!
+//! Key-value storage ABI (all pointers and lengths are `i32` offsets into the
+//! plugin's exported `memory`):
+//!
+//! - `kv_get(key_ptr, key_len, out_ptr, out_cap) -> i32`: returns the value
+//!   length. The value is written to `out_ptr` only when it fits in `out_cap`,
+//!   so a plugin can retry with a larger buffer.
+//! - `kv_set(key_ptr, key_len, val_ptr, val_len) -> i32`: returns `0`.
+//! - `kv_delete(key_ptr, key_len) -> i32`: returns `1` if the key existed, else `0`.
+//!
+//! Negative results are errors: [`KV_NOT_FOUND`], [`KV_ERROR`], [`KV_DENIED`].
+
+use opencrust_db::KvStore;
+use std::sync::Arc;
+use tracing::warn;
+use wasmtime::{Caller, Extern, Linker};
+
+/// Host import module name.
+pub const HOST_MODULE: &str = "opencrust";
+
+/// `kv_get` found no value for the key.
+pub const KV_NOT_FOUND: i32 = -1;
+/// Bad pointers, oversized input, or a storage failure.
+pub const KV_ERROR: i32 = -2;
+/// The plugin lacks the `kv` permission or no store is attached.
+pub const KV_DENIED: i32 = -3;
+
+const MAX_KEY_BYTES: usize = 256;
+const MAX_VALUE_BYTES: usize = 64 * 1024;
+
+/// A plugin's view of the KV store: its own namespace and the calling user.
+pub(crate) struct KvScope {
+    pub store: Arc<KvStore>,
+    pub namespace: String,
+    pub user_id: Option<String>,
+}
+
+/// Implemented by the store data so host functions can find the KV scope.
+pub(crate) trait HostState {
+    fn kv(&self) -> Option<&KvScope>;
+}
+
+pub(crate) fn add_to_linker<T: HostState + 'static>(linker: &mut Linker<T>) -> anyhow::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_get",
+        |mut caller: Caller<'_, T>, key_ptr: i32, key_len: i32, out_ptr: i32, out_cap: i32| {
+            let Some(key) = read_string(&mut caller, key_ptr, key_len, MAX_KEY_BYTES) else {
+                return KV_ERROR;
+            };
+            let value = {
+                let Some(scope) = caller.data().kv() else {
+                    return KV_DENIED;
+                };
+                match scope
+                    .store
+                    .get(&scope.namespace, scope.user_id.as_deref(), &key)
+                {
+                    Ok(Some(value)) => value,
+                    Ok(None) => return KV_NOT_FOUND,
+                    Err(e) => {
+                        warn!("plugin kv_get failed: {e}");
+                        return KV_ERROR;
+                    }
+                }
+            };
+            let Ok(len) = i32::try_from(value.len()) else {
+                return KV_ERROR;
+            };
+            if value.len() <= out_cap.max(0) as usize
+                && !write_bytes(&mut caller, out_ptr, value.as_bytes())
+            {
+                return KV_ERROR;
+            }
+            len
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_set",
+        |mut caller: Caller<'_, T>, key_ptr: i32, key_len: i32, val_ptr: i32, val_len: i32| {
+            let Some(key) = read_string(&mut caller, key_ptr, key_len, MAX_KEY_BYTES) else {
+                return KV_ERROR;
+            };
+            let Some(value) = read_string(&mut caller, val_ptr, val_len, MAX_VALUE_BYTES) else {
+                return KV_ERROR;
+            };
+            let Some(scope) = caller.data().kv() else {
+                return KV_DENIED;
+            };
+            match scope
+                .store
+                .set(&scope.namespace, scope.user_id.as_deref(), &key, &value)
+            {
+                Ok(()) => 0,
+                Err(e) => {
+                    warn!("plugin kv_set failed: {e}");
+                    KV_ERROR
+                }
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "kv_delete",
+        |mut caller: Caller<'_, T>, key_ptr: i32, key_len: i32| {
+            let Some(key) = read_string(&mut caller, key_ptr, key_len, MAX_KEY_BYTES) else {
+                return KV_ERROR;
+            };
+            let Some(scope) = caller.data().kv() else {
+                return KV_DENIED;
+            };
+            match scope
+                .store
+                .delete(&scope.namespace, scope.user_id.as_deref(), &key)
+            {
+                Ok(removed) => i32::from(removed),
+                Err(e) => {
+                    warn!("plugin kv_delete failed: {e}");
+                    KV_ERROR
+                }
+            }
+        },
+    )?;
+
+    Ok(())
+}
+
+fn read_string<T>(caller: &mut Caller<'_, T>, ptr: i32, len: i32, max: usize) -> Option<String> {
+    let (start, len) = (usize::try_from(ptr).ok()?, usize::try_from(len).ok()?);
+    if len > max {
+        return None;
+    }
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return None;
+    };
+    let bytes = memory.data(&caller).get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+fn write_bytes<T>(caller: &mut Caller<'_, T>, ptr: i32, bytes: &[u8]) -> bool {
+    let Ok(start) = usize::try_from(ptr) else {
+        return false;
+    };
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return false;
+    };
+    memory.write(caller, start, bytes).is_ok()
+}
@@ -1,3 +1,4 @@
+pub mod host;
 pub mod loader;
 pub mod manifest;
 pub mod runtime;
@@ -3,6 +3,7 @@ use crate::runtime::WasmRuntime;
 use crate::traits::Plugin;
 use anyhow::{Context, Result};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use opencrust_db::KvStore;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -12,15 +13,23 @@ use tracing::{error, info, warn};
 #[derive(Clone)]
 pub struct PluginLoader {
     plugins_dir: PathBuf,
+    kv_store: Option<Arc<KvStore>>,
 }
 
 impl PluginLoader {
     pub fn new(plugins_dir: impl Into<PathBuf>) -> Self {
         Self {
             plugins_dir: plugins_dir.into(),
+            kv_store: None,
         }
     }
 
+    /// Attach key-value storage to every plugin this loader creates.
+    pub fn with_kv_store(mut self, store: Arc<KvStore>) -> Self {
+        self.kv_store = Some(store);
+        self
+    }
+
     /// Scan the plugins directory and return all valid plugins.
     pub fn discover(&self) -> Result<Vec<Arc<dyn Plugin>>> {
         if !self.plugins_dir.exists() {
@@ -80,7 +89,10 @@ impl PluginLoader {
             );
         };
 
-        let runtime = WasmRuntime::new(manifest, wasm_path)?;
+        let mut runtime = WasmRuntime::new(manifest, wasm_path)?;
+        if let Some(store) = &self.kv_store {
+            runtime = runtime.with_kv_store(Arc::clone(store));
+        }
         Ok(Arc::new(runtime))
     }
 
@@ -207,3 +219,65 @@ impl Drop for PluginRegistry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::PluginInput;
+
+    /// Plugin that stores "visits" = "1" and exits.
+    const KV_PLUGIN_WAT: &str = r#"
+(module
+  (import "opencrust" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "visits")
+  (data (i32.const 16) "1")
+  (func (export "_start")
+    (drop (call $kv_set (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 1)))))
+"#;
+
+    #[tokio::test]
+    async fn discovered_plugins_use_the_kv_store_for_the_calling_user() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "opencrust-loader-test-{}-{nanos}",
+            std::process::id()
+        ));
+        let plugin_dir = root.join("counter");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+        std::fs::write(
+            plugin_dir.join("plugin.toml"),
+            "[plugin]\nname = \"counter\"\nversion = \"0.1.0\"\ndescription = \"kv test\"\n\n[permissions]\nkv = true\n",
+        )
+        .unwrap();
+        std::fs::write(plugin_dir.join("plugin.wasm"), KV_PLUGIN_WAT).unwrap();
+
+        let store = Arc::new(KvStore::in_memory().unwrap());
+        let plugins = PluginLoader::new(&root)
+            .with_kv_store(Arc::clone(&store))
+            .discover()
+            .unwrap();
+        assert_eq!(plugins.len(), 1);
+
+        let output = plugins[0]
+            .execute(PluginInput {
+                user_id: Some("bob".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(output.status, 0);
+        assert_eq!(
+            store
+                .get("counter", Some("bob"), "visits")
+                .unwrap()
+                .as_deref(),
+            Some("1")
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
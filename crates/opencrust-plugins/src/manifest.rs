@@ -38,6 +38,9 @@ pub struct Permissions {
     /// Environment variables that can be passed through from PluginInput.
     #[serde(default)]
     pub env_vars: Vec<String>,
+    /// Enable the `opencrust.kv_*` host functions for persistent storage.
+    #[serde(default)]
+    pub kv: bool,
 }
 
 /// Resource limits.
@@ -103,6 +106,7 @@ filesystem_read_paths = ["./fixtures/read"]
 filesystem_write_paths = ["./fixtures/write"]
 network = ["example.com"]
 env_vars = ["TEST_VAR"]
+kv = true
 
 [limits]
 timeout_secs = 10
@@ -121,6 +125,7 @@ max_output_bytes = 2048
             vec!["./fixtures/write"]
         );
         assert_eq!(manifest.permissions.network, vec!["example.com"]);
+        assert!(manifest.permissions.kv);
         assert_eq!(manifest.limits.timeout_secs, 10);
         assert_eq!(manifest.limits.max_memory_mb, 128);
         assert_eq!(manifest.limits.max_output_bytes, 2048);
@@ -137,6 +142,7 @@ description = "minimal"
         let manifest: PluginManifest = toml::from_str(toml).unwrap();
         assert!(!manifest.permissions.filesystem);
         assert!(manifest.permissions.network.is_empty());
+        assert!(!manifest.permissions.kv);
         assert!(manifest.permissions.filesystem_read_paths.is_empty());
         assert!(manifest.permissions.filesystem_write_paths.is_empty());
         assert_eq!(manifest.limits.timeout_secs, 30);
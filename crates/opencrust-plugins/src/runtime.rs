@@ -1,7 +1,9 @@
+use crate::host::{self, HostState, KvScope};
 use crate::manifest::PluginManifest;
 use crate::traits::{Capability, Plugin, PluginInput, PluginOutput};
 use async_trait::async_trait;
 use opencrust_common::{Error, Result};
+use opencrust_db::KvStore;
 use std::collections::{BTreeMap, HashSet};
 use std::net::{IpAddr, ToSocketAddrs};
 use std::path::{Path, PathBuf};
@@ -17,12 +19,20 @@ pub struct WasmRuntime {
     engine: Engine,
     module: Module,
     plugin_root: PathBuf,
+    kv_store: Option<Arc<KvStore>>,
     ticker_handle: tokio::task::JoinHandle<()>,
 }
 
 struct WasmState {
     ctx: WasiP1Ctx,
     limits: StoreLimits,
+    kv: Option<KvScope>,
+}
+
+impl HostState for WasmState {
+    fn kv(&self) -> Option<&KvScope> {
+        self.kv.as_ref()
+    }
 }
 
 impl Drop for WasmRuntime {
@@ -63,10 +73,18 @@ impl WasmRuntime {
             engine,
             module,
             plugin_root,
+            kv_store: None,
             ticker_handle,
         })
     }
 
+    /// Give the plugin key-value storage, namespaced by its name. Only used
+    /// when the manifest grants the `kv` permission.
+    pub fn with_kv_store(mut self, store: Arc<KvStore>) -> Self {
+        self.kv_store = Some(store);
+        self
+    }
+
     fn configure_filesystem(&self, builder: &mut WasiCtxBuilder) -> Result<()> {
         let read_paths = &self.manifest.permissions.filesystem_read_paths;
         let write_paths = &self.manifest.permissions.filesystem_write_paths;
@@ -178,6 +196,9 @@ impl Plugin for WasmRuntime {
                 self.manifest.permissions.env_vars.clone(),
             ));
         }
+        if self.manifest.permissions.kv {
+            caps.push(Capability::KeyValue);
+        }
         caps
     }
 
@@ -185,6 +206,8 @@ impl Plugin for WasmRuntime {
         let mut linker = Linker::new(&self.engine);
         p1::add_to_linker_async(&mut linker, |s: &mut WasmState| &mut s.ctx)
             .map_err(|e| Error::Plugin(format!("linker error: {e}")))?;
+        host::add_to_linker(&mut linker)
+            .map_err(|e| Error::Plugin(format!("linker error: {e}")))?;
 
         let mut builder = WasiCtxBuilder::new();
         builder.args(&input.args);
@@ -221,7 +244,17 @@ impl Plugin for WasmRuntime {
             .memory_size(max_memory_bytes)
             .build();
 
-        let state = WasmState { ctx, limits };
+        let kv = self
+            .kv_store
+            .as_ref()
+            .filter(|_| self.manifest.permissions.kv)
+            .map(|store| KvScope {
+                store: Arc::clone(store),
+                namespace: self.manifest.plugin.name.clone(),
+                user_id: input.user_id.clone(),
+            });
+
+        let state = WasmState { ctx, limits, kv };
         let mut store = Store::new(&self.engine, state);
         store.limiter(|s| &mut s.limits);
 
@@ -482,4 +515,77 @@ mod tests {
             &"2001:4860:4860::8888".parse::<IpAddr>().unwrap()
         ));
     }
+
+    /// Plugin that stores "theme" = "dark", reads it back and exits with the
+    /// `kv_get` result plus 10 (WASI exit codes can't be negative).
+    const KV_PLUGIN_WAT: &str = r#"
+(module
+  (import "opencrust" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+  (import "opencrust" "kv_get" (func $kv_get (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "theme")
+  (data (i32.const 16) "dark")
+  (func (export "_start")
+    (drop (call $kv_set (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 4)))
+    (call $proc_exit
+      (i32.add (i32.const 10)
+        (call $kv_get (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 32))))))
+"#;
+
+    fn kv_plugin(label: &str, kv_permission: bool) -> super::WasmRuntime {
+        let root = temp_root(label);
+        let wasm_path = root.join("plugin.wasm");
+        std::fs::write(&wasm_path, KV_PLUGIN_WAT).unwrap();
+        let manifest: crate::manifest::PluginManifest = toml::from_str(&format!(
+            "[plugin]\nname = \"prefs\"\nversion = \"0.1.0\"\ndescription = \"kv test\"\n\n[permissions]\nkv = {kv_permission}\n"
+        ))
+        .unwrap();
+        super::WasmRuntime::new(manifest, wasm_path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn kv_host_functions_scope_by_plugin_and_user() {
+        use crate::traits::{Plugin, PluginInput};
+        use opencrust_db::KvStore;
+        use std::sync::Arc;
+
+        let store = Arc::new(KvStore::in_memory().unwrap());
+        let plugin = kv_plugin("kv-scope", true).with_kv_store(Arc::clone(&store));
+
+        let output = plugin
+            .execute(PluginInput {
+                user_id: Some("alice".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(output.status, 10 + 4);
+        assert_eq!(
+            store
+                .get("prefs", Some("alice"), "theme")
+                .unwrap()
+                .as_deref(),
+            Some("dark")
+        );
+        assert_eq!(store.get("prefs", None, "theme").unwrap(), None);
+        assert_eq!(store.get("other", Some("alice"), "theme").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn kv_host_functions_denied_without_permission() {
+        use crate::host::KV_DENIED;
+        use crate::traits::{Plugin, PluginInput};
+        use opencrust_db::KvStore;
+        use std::sync::Arc;
+
+        let store = Arc::new(KvStore::in_memory().unwrap());
+        let plugin = kv_plugin("kv-denied", false).with_kv_store(Arc::clone(&store));
+
+        let output = plugin.execute(PluginInput::default()).await.unwrap();
+
+        assert_eq!(output.status, 10 + KV_DENIED);
+        assert!(store.keys("prefs", None).unwrap().is_empty());
+    }
 }
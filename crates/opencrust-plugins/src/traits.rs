@@ -15,6 +15,8 @@ pub enum Capability {
     Network(Vec<String>),
     /// Environment variables. List of allowed variable names.
     EnvVars(Vec<String>),
+    /// Persistent key-value storage scoped to the plugin.
+    KeyValue,
 }
 
 /// Input passed to a plugin execution.
@@ -26,6 +28,9 @@ pub struct PluginInput {
     pub env: HashMap<String, String>,
     /// Standard input data.
     pub stdin: Vec<u8>,
+    /// User the plugin runs on behalf of; scopes key-value storage.
+    #[serde(default)]
+    pub user_id: Option<String>,
 }
 
 /// Output returned from a plugin execution.
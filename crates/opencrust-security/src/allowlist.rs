@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -10,14 +10,30 @@ struct AllowlistData {
     mode: String,
     owner: Option<String>,
     users: Vec<String>,
+    #[serde(default)]
+    links: HashMap<String, String>,
 }
 
 /// Manages which users are allowed to interact with the assistant per channel.
 pub struct Allowlist {
     allowed_users: HashSet<String>,
     owner: Option<String>,
+    /// Linked user ids, mapped to the id of the identity they belong to.
+    links: HashMap<String, String>,
     mode: AllowlistMode,
     path: Option<PathBuf>,
+    claim_policy: ClaimPolicy,
+}
+
+/// Who may claim ownership of an allowlist without an owner.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ClaimPolicy {
+    /// The first user to message the bot becomes owner.
+    #[default]
+    FirstUser,
+    /// Only this pre-configured user id may claim ownership. `None` means
+    /// nobody can; an owner must already be recorded.
+    Configured(Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,8 +49,10 @@ impl Allowlist {
         Self {
             allowed_users: HashSet::new(),
             owner: None,
+            links: HashMap::new(),
             mode: AllowlistMode::Open,
             path: None,
+            claim_policy: ClaimPolicy::default(),
         }
     }
 
@@ -42,8 +60,10 @@ impl Allowlist {
         Self {
             allowed_users: users.into_iter().collect(),
             owner: None,
+            links: HashMap::new(),
             mode: AllowlistMode::Restricted,
             path: None,
+            claim_policy: ClaimPolicy::default(),
         }
     }
 
@@ -67,8 +87,10 @@ impl Allowlist {
                         return Self {
                             allowed_users: data.users.into_iter().collect(),
                             owner: data.owner,
+                            links: data.links,
                             mode,
                             path: Some(path.to_path_buf()),
+                            claim_policy: ClaimPolicy::default(),
                         };
                     }
                     Err(e) => warn!("invalid allowlist file, creating new: {e}"),
@@ -95,12 +117,24 @@ impl Allowlist {
         self.mode == AllowlistMode::Restricted && self.owner.is_none()
     }
 
-    /// Set the owner and add them to the allowlist. Returns false if owner already set.
+    /// Restrict who may claim ownership. See [`ClaimPolicy`].
+    pub fn set_claim_policy(&mut self, policy: ClaimPolicy) {
+        self.claim_policy = policy;
+    }
+
+    /// Set the owner and add them to the allowlist. Returns false if owner
+    /// already set or the claim policy doesn't allow `user_id` to claim.
     pub fn claim_owner(&mut self, user_id: impl Into<String>) -> bool {
         if self.owner.is_some() {
             return false;
         }
         let uid = user_id.into();
+        if let ClaimPolicy::Configured(expected) = &self.claim_policy
+            && expected.as_deref() != Some(uid.as_str())
+        {
+            warn!("rejected owner claim from {uid}: auto-claim is disabled");
+            return false;
+        }
         self.owner = Some(uid.clone());
         self.allowed_users.insert(uid);
         self.save();
@@ -111,8 +145,49 @@ impl Allowlist {
         self.owner.as_deref()
     }
 
+    /// Whether `user_id` is the owner or one of the owner's linked accounts.
     pub fn is_owner(&self, user_id: &str) -> bool {
-        self.owner.as_deref() == Some(user_id)
+        self.owner.as_deref() == Some(self.identity(user_id))
+    }
+
+    /// The owner's account followed by every account linked to it, sorted.
+    /// Empty when there is no owner.
+    pub fn owner_accounts(&self) -> Vec<&str> {
+        let Some(owner) = self.owner.as_deref() else {
+            return Vec::new();
+        };
+        let mut linked: Vec<&str> = self
+            .links
+            .iter()
+            .filter(|(_, identity)| identity.as_str() == owner)
+            .map(|(uid, _)| uid.as_str())
+            .collect();
+        linked.sort_unstable();
+        linked.insert(0, owner);
+        linked
+    }
+
+    /// The identity `user_id` belongs to: the account it was linked to, or
+    /// `user_id` itself.
+    pub fn identity<'a>(&'a self, user_id: &'a str) -> &'a str {
+        self.links.get(user_id).map_or(user_id, String::as_str)
+    }
+
+    /// Whether `user_id` is part of an identity spanning several accounts.
+    pub fn is_linked(&self, user_id: &str) -> bool {
+        self.links.contains_key(user_id) || self.links.values().any(|id| id == user_id)
+    }
+
+    /// Link `user_id` (e.g. a Discord account) to the identity of `to` (e.g.
+    /// the same person's Telegram account) and allow it.
+    pub fn link(&mut self, user_id: impl Into<String>, to: &str) {
+        let uid = user_id.into();
+        let identity = self.identity(to).to_string();
+        if uid != identity {
+            self.links.insert(uid.clone(), identity);
+        }
+        self.allowed_users.insert(uid);
+        self.save();
     }
 
     pub fn add(&mut self, user_id: impl Into<String>) {
@@ -146,6 +221,7 @@ impl Allowlist {
             },
             owner: self.owner.clone(),
             users: self.allowed_users.iter().cloned().collect(),
+            links: self.links.clone(),
         };
 
         if let Some(parent) = path.parent()
@@ -213,6 +289,24 @@ mod tests {
         assert!(!allowlist.is_owner("user-2"));
     }
 
+    #[test]
+    fn linked_accounts_share_identity_and_ownership() {
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.claim_owner("tg-1");
+        assert!(!allowlist.is_linked("tg-1"));
+
+        allowlist.link("dc-1", "tg-1");
+        allowlist.link("wa-1", "dc-1");
+
+        assert_eq!(allowlist.identity("dc-1"), "tg-1");
+        assert_eq!(allowlist.identity("wa-1"), "tg-1");
+        assert_eq!(allowlist.identity("stranger"), "stranger");
+        assert!(allowlist.is_linked("tg-1") && allowlist.is_linked("wa-1"));
+        assert!(allowlist.is_allowed("wa-1"));
+        assert!(allowlist.is_owner("dc-1"));
+        assert_eq!(allowlist.owner_accounts(), vec!["tg-1", "dc-1", "wa-1"]);
+    }
+
     #[test]
     fn persistence_round_trip() {
         let dir =
@@ -223,14 +317,38 @@ mod tests {
             let mut list = Allowlist::load_or_create(&path);
             list.claim_owner("owner-1");
             list.add("friend-1");
+            list.link("owner-2", "owner-1");
         }
 
         let list = Allowlist::load_or_create(&path);
         assert!(list.is_owner("owner-1"));
+        assert_eq!(list.identity("owner-2"), "owner-1");
         assert!(list.is_allowed("owner-1"));
         assert!(list.is_allowed("friend-1"));
         assert!(!list.is_allowed("stranger"));
 
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn configured_policy_rejects_unknown_claimant() {
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.set_claim_policy(ClaimPolicy::Configured(Some("owner-1".to_string())));
+
+        assert!(!allowlist.claim_owner("intruder"));
+        assert!(allowlist.needs_owner());
+        assert!(!allowlist.is_allowed("intruder"));
+
+        assert!(allowlist.claim_owner("owner-1"));
+        assert!(allowlist.is_owner("owner-1"));
+    }
+
+    #[test]
+    fn configured_policy_without_owner_rejects_everyone() {
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.set_claim_policy(ClaimPolicy::Configured(None));
+
+        assert!(!allowlist.claim_owner("user-1"));
+        assert!(allowlist.owner().is_none());
+    }
 }
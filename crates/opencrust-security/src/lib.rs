@@ -5,11 +5,14 @@ pub mod policy;
 pub mod redaction;
 pub mod validation;
 
-pub use allowlist::{Allowlist, AllowlistMode};
+pub use allowlist::{Allowlist, AllowlistMode, ClaimPolicy};
 pub use credentials::{
     CredentialError, CredentialVault, try_vault_get, try_vault_remove, try_vault_set,
 };
 pub use pairing::PairingManager;
-pub use policy::{ChannelPolicy, DmAuthResult, DmPolicy, GroupPolicy, check_dm_auth};
+pub use policy::{
+    ChannelMessages, ChannelPolicy, DEFAULT_RESPOND_PREFIX, DmAuthResult, DmPolicy, GroupPolicy,
+    LINKED_REPLY, RespondMode, check_dm_auth,
+};
 pub use redaction::{RedactingWriter, redact_secrets};
 pub use validation::InputValidator;
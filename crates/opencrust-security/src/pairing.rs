@@ -1,11 +1,17 @@
+use opencrust_common::{Clock, SystemClock};
 use rand::Rng;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Key prefix of codes from [`PairingManager::generate_link`].
+const LINK_PREFIX: &str = "link:";
+
 /// Manages pairing codes for device and channel authentication.
 pub struct PairingManager {
     codes: HashMap<String, PairingCode>,
     code_ttl: Duration,
+    clock: Arc<dyn Clock>,
 }
 
 struct PairingCode {
@@ -16,9 +22,15 @@ struct PairingCode {
 
 impl PairingManager {
     pub fn new(code_ttl: Duration) -> Self {
+        Self::with_clock(code_ttl, Arc::new(SystemClock))
+    }
+
+    /// Create a manager that reads the time from `clock`.
+    pub fn with_clock(code_ttl: Duration, clock: Arc<dyn Clock>) -> Self {
         Self {
             codes: HashMap::new(),
             code_ttl,
+            clock,
         }
     }
 
@@ -35,7 +47,7 @@ impl PairingManager {
             channel_id.to_string(),
             PairingCode {
                 code: code.clone(),
-                created_at: Instant::now(),
+                created_at: self.clock.now(),
                 claimed_by: None,
             },
         );
@@ -43,14 +55,42 @@ impl PairingManager {
         code
     }
 
+    /// Generate a code that links the account claiming it to `user_id`'s
+    /// identity. See [`link_target`](Self::link_target).
+    pub fn generate_link(&mut self, user_id: &str) -> String {
+        self.generate(&format!("{LINK_PREFIX}{user_id}"))
+    }
+
+    /// The user id a claimed code links to, if it came from
+    /// [`generate_link`](Self::generate_link).
+    pub fn link_target(claimed: &str) -> Option<&str> {
+        claimed.strip_prefix(LINK_PREFIX)
+    }
+
     /// Attempt to claim a pairing code. Returns the channel ID if valid.
     pub fn claim(&mut self, code: &str, user_id: &str) -> Option<String> {
+        self.claim_where(code, user_id, |_| true)
+    }
+
+    /// Attempt to claim a code from [`generate_link`](Self::generate_link).
+    /// Returns the user id to link to. Invite codes are left unclaimed.
+    pub fn claim_link(&mut self, code: &str, user_id: &str) -> Option<String> {
+        self.claim_where(code, user_id, |key| key.starts_with(LINK_PREFIX))
+            .and_then(|key| Self::link_target(&key).map(str::to_string))
+    }
+
+    fn claim_where(
+        &mut self,
+        code: &str,
+        user_id: &str,
+        accept: impl Fn(&str) -> bool,
+    ) -> Option<String> {
         self.cleanup_expired();
 
         let entry = self
             .codes
             .iter_mut()
-            .find(|(_, pc)| pc.code == code && pc.claimed_by.is_none());
+            .find(|(key, pc)| pc.code == code && pc.claimed_by.is_none() && accept(key));
 
         if let Some((channel_id, pairing_code)) = entry {
             pairing_code.claimed_by = Some(user_id.to_string());
@@ -61,15 +101,17 @@ impl PairingManager {
     }
 
     fn cleanup_expired(&mut self) {
+        let now = self.clock.now();
         self.codes
-            .retain(|_, pc| pc.created_at.elapsed() < self.code_ttl);
+            .retain(|_, pc| now.duration_since(pc.created_at) < self.code_ttl);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::PairingManager;
-    use std::thread::sleep;
+    use opencrust_common::FakeClock;
+    use std::sync::Arc;
     use std::time::Duration;
 
     #[test]
@@ -94,11 +136,39 @@ mod tests {
 
     #[test]
     fn expired_codes_cannot_be_claimed() {
-        let mut manager = PairingManager::new(Duration::from_millis(5));
+        let clock = Arc::new(FakeClock::new());
+        let mut manager = PairingManager::with_clock(Duration::from_secs(60), clock.clone());
         let code = manager.generate("channel-expire");
 
-        sleep(Duration::from_millis(15));
+        clock.advance(Duration::from_secs(60));
         let claim = manager.claim(&code, "user-1");
         assert!(claim.is_none());
     }
+
+    #[test]
+    fn codes_remain_claimable_until_ttl_elapses() {
+        let clock = Arc::new(FakeClock::new());
+        let mut manager = PairingManager::with_clock(Duration::from_secs(60), clock.clone());
+        let code = manager.generate("channel-ttl");
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(
+            manager.claim(&code, "user-1").as_deref(),
+            Some("channel-ttl")
+        );
+    }
+
+    #[test]
+    fn link_codes_are_separate_from_invites() {
+        let mut manager = PairingManager::new(Duration::from_secs(60));
+        let invite = manager.generate("telegram");
+        let link = manager.generate_link("tg-1");
+
+        assert!(manager.claim_link(&invite, "dc-1").is_none());
+        assert_eq!(manager.claim_link(&link, "dc-1").as_deref(), Some("tg-1"));
+        assert!(manager.claim_link(&link, "wa-1").is_none());
+        assert_eq!(manager.claim(&invite, "dc-2").as_deref(), Some("telegram"));
+        assert_eq!(PairingManager::link_target("link:tg-1"), Some("tg-1"));
+        assert_eq!(PairingManager::link_target("telegram"), None);
+    }
 }
@@ -31,6 +31,25 @@ pub enum GroupPolicy {
     Disabled,
 }
 
+/// Which group messages the bot answers, among those `group_policy` lets in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RespondMode {
+    /// Answer every message.
+    All,
+    /// Answer only when @mentioned or replied to.
+    Mention,
+    /// Answer only messages starting with `respond_prefix`.
+    Prefix,
+}
+
+/// Prefix used by [`RespondMode::Prefix`] when `respond_prefix` is unset.
+pub const DEFAULT_RESPOND_PREFIX: &str = "!ai";
+
+/// Reply to an account that claimed a link code.
+pub const LINKED_REPLY: &str = "This account is now linked to your other accounts. \
+     Conversations here share memory with them.";
+
 /// Result of a DM authorization check.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DmAuthResult {
@@ -42,12 +61,83 @@ pub enum DmAuthResult {
     UseGlobalAllowlist,
 }
 
+/// Operator-customizable replies sent during DM onboarding.
+///
+/// Templates may contain `{user_name}`, which is replaced with the sender's
+/// display name. Unset templates fall back to the built-in wording.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMessages {
+    /// Sent to the first user, who is auto-paired as the bot owner.
+    #[serde(default)]
+    pub welcome_owner: Option<String>,
+    /// Sent to a user who just gained access with a pairing code.
+    #[serde(default)]
+    pub welcome_user: Option<String>,
+    /// Sent to an unpaired user, prompting for a pairing code.
+    #[serde(default)]
+    pub blocked: Option<String>,
+}
+
+impl ChannelMessages {
+    pub fn welcome_owner(&self, user_name: &str) -> String {
+        match &self.welcome_owner {
+            Some(template) => render_message(template, user_name),
+            None if user_name.is_empty() => {
+                "Welcome! You are now the owner of this OpenCrust bot.\n\n\
+                 Send /pair to generate a code for adding other users.\n\
+                 Send /help for available commands."
+                    .to_string()
+            }
+            None => format!(
+                "Welcome, {user_name}! You are now the owner of this OpenCrust bot.\n\n\
+                 Use /pair to generate a code for adding other users.\n\
+                 Use /help for available commands."
+            ),
+        }
+    }
+
+    pub fn welcome_user(&self, user_name: &str) -> String {
+        match &self.welcome_user {
+            Some(template) => render_message(template, user_name),
+            None if user_name.is_empty() => "Welcome! You now have access to this bot.".to_string(),
+            None => format!("Welcome, {user_name}! You now have access to this bot."),
+        }
+    }
+
+    pub fn blocked(&self, user_name: &str) -> String {
+        match &self.blocked {
+            Some(template) => render_message(template, user_name),
+            None => {
+                "This bot is private. Send your 6-digit pairing code to get access.".to_string()
+            }
+        }
+    }
+
+    /// Fill any unset templates from `defaults`.
+    pub fn or(self, defaults: &ChannelMessages) -> Self {
+        Self {
+            welcome_owner: self
+                .welcome_owner
+                .or_else(|| defaults.welcome_owner.clone()),
+            welcome_user: self.welcome_user.or_else(|| defaults.welcome_user.clone()),
+            blocked: self.blocked.or_else(|| defaults.blocked.clone()),
+        }
+    }
+}
+
+fn render_message(template: &str, user_name: &str) -> String {
+    template.replace("{user_name}", user_name)
+}
+
 /// Per-channel authorization policy.
 #[derive(Debug, Clone, Default)]
 pub struct ChannelPolicy {
     pub dm_policy: Option<DmPolicy>,
     pub group_policy: Option<GroupPolicy>,
+    pub respond_mode: Option<RespondMode>,
+    pub respond_prefix: Option<String>,
     pub channel_allowlist: HashSet<String>,
+    pub messages: ChannelMessages,
 }
 
 impl ChannelPolicy {
@@ -73,6 +163,21 @@ impl ChannelPolicy {
             }
         }
 
+        if let Some(val) = settings.get("respond_mode")
+            && let Some(s) = val.as_str()
+        {
+            match serde_json::from_value::<RespondMode>(serde_json::Value::String(s.to_string())) {
+                Ok(m) => policy.respond_mode = Some(m),
+                Err(_) => warn!("unknown respond_mode value: {s:?}"),
+            }
+        }
+
+        if let Some(prefix) = settings.get("respond_prefix").and_then(|v| v.as_str())
+            && !prefix.trim().is_empty()
+        {
+            policy.respond_prefix = Some(prefix.trim().to_string());
+        }
+
         if let Some(val) = settings.get("allowlist")
             && let Some(arr) = val.as_array()
         {
@@ -83,18 +188,61 @@ impl ChannelPolicy {
             }
         }
 
+        if let Some(val) = settings.get("messages") {
+            match serde_json::from_value::<ChannelMessages>(val.clone()) {
+                Ok(m) => policy.messages = m,
+                Err(e) => warn!("invalid messages setting: {e}"),
+            }
+        }
+
         policy
     }
 
+    /// Use `defaults` (typically the global `messages:` config) for any
+    /// onboarding message this channel does not override.
+    pub fn with_default_messages(mut self, defaults: &ChannelMessages) -> Self {
+        self.messages = self.messages.or(defaults);
+        self
+    }
+
     /// Check whether a group message should be processed.
     /// Returns `true` if the message should be processed.
+    ///
+    /// `is_mentioned` should also be `true` for replies to the bot's messages.
+    /// In [`RespondMode::Prefix`] the text is checked later by
+    /// [`group_text`](Self::group_text).
     pub fn should_process_group(&self, is_mentioned: bool) -> bool {
-        match &self.group_policy {
+        let allowed = match &self.group_policy {
             None => true,
             Some(GroupPolicy::Open) => true,
             Some(GroupPolicy::Mention) => is_mentioned,
             Some(GroupPolicy::Disabled) => false,
+        };
+        allowed && (is_mentioned || self.respond_mode != Some(RespondMode::Mention))
+    }
+
+    /// The text to pass to the agent for a group message, or `None` when the
+    /// bot should stay quiet. In [`RespondMode::Prefix`] the message must start
+    /// with the prefix, which is removed; other modes return the text as is.
+    pub fn group_text(&self, text: &str) -> Option<String> {
+        if self.respond_mode != Some(RespondMode::Prefix) {
+            return Some(text.to_string());
+        }
+        let prefix = self
+            .respond_prefix
+            .as_deref()
+            .unwrap_or(DEFAULT_RESPOND_PREFIX);
+        let rest = text.trim_start();
+        let head = rest.get(..prefix.len())?;
+        if !head.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        let rest = &rest[prefix.len()..];
+        // "!ai" must not match "!aim".
+        if rest.starts_with(|c: char| c.is_alphanumeric()) {
+            return None;
         }
+        Some(rest.trim_start_matches([':', ',']).trim().to_string())
     }
 
     /// Check DM authorization against the per-channel policy.
@@ -141,23 +289,11 @@ pub fn check_dm_auth(
         }
     }
 
-    // Owner auto-claim (first user to message becomes owner)
-    if allowlist.needs_owner() {
-        allowlist.claim_owner(user_id);
+    // Owner auto-claim (first user to message becomes owner, unless the
+    // claim policy pins the owner id)
+    if allowlist.needs_owner() && allowlist.claim_owner(user_id) {
         info!("{label}: auto-paired owner {user_name} ({user_id})");
-        let welcome = if user_name.is_empty() {
-            "Welcome! You are now the owner of this OpenCrust bot.\n\n\
-             Send /pair to generate a code for adding other users.\n\
-             Send /help for available commands."
-                .to_string()
-        } else {
-            format!(
-                "Welcome, {user_name}! You are now the owner of this OpenCrust bot.\n\n\
-                 Use /pair to generate a code for adding other users.\n\
-                 Use /help for available commands."
-            )
-        };
-        return Ok(Some(welcome));
+        return Ok(Some(policy.messages.welcome_owner(user_name)));
     }
 
     // Check global allowlist
@@ -169,15 +305,15 @@ pub fn check_dm_auth(
     let trimmed = text.trim();
     if trimmed.len() == 6 && trimmed.chars().all(|c| c.is_ascii_digit()) {
         let claimed = pairing.lock().unwrap().claim(trimmed, user_id);
+        if let Some(target) = claimed.as_deref().and_then(PairingManager::link_target) {
+            allowlist.link(user_id, target);
+            info!("{label}: linked {user_name} ({user_id}) to {target} via code");
+            return Ok(Some(LINKED_REPLY.to_string()));
+        }
         if claimed.is_some() {
             allowlist.add(user_id);
             info!("{label}: paired user {user_name} ({user_id}) via code");
-            let welcome = if user_name.is_empty() {
-                "Welcome! You now have access to this bot.".to_string()
-            } else {
-                format!("Welcome, {user_name}! You now have access to this bot.")
-            };
-            return Ok(Some(welcome));
+            return Ok(Some(policy.messages.welcome_user(user_name)));
         }
         // Looks like a code but claim failed — invalid or expired
         warn!("{label}: invalid pairing code from {user_name} ({user_id})");
@@ -188,14 +324,13 @@ pub fn check_dm_auth(
 
     // Regular message from unknown user — prompt for pairing code
     warn!("{label}: unauthorized user {user_name} ({user_id})");
-    Ok(Some(
-        "Send your 6-digit pairing code to get access.".to_string(),
-    ))
+    Ok(Some(policy.messages.blocked(user_name)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::allowlist::ClaimPolicy;
     use std::time::Duration;
 
     #[test]
@@ -308,6 +443,54 @@ mod tests {
         assert!(!policy.should_process_group(true));
     }
 
+    fn settings(v: serde_json::Value) -> std::collections::HashMap<String, serde_json::Value> {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn respond_mode_mention_requires_mention() {
+        let policy = ChannelPolicy::from_settings(&settings(serde_json::json!({
+            "respond_mode": "mention"
+        })));
+        assert_eq!(policy.respond_mode, Some(RespondMode::Mention));
+        assert!(!policy.should_process_group(false));
+        assert!(policy.should_process_group(true));
+
+        // group_policy still applies on top.
+        let policy = ChannelPolicy::from_settings(&settings(serde_json::json!({
+            "respond_mode": "mention",
+            "group_policy": "disabled"
+        })));
+        assert!(!policy.should_process_group(true));
+    }
+
+    #[test]
+    fn respond_mode_prefix_strips_prefix() {
+        let policy = ChannelPolicy::from_settings(&settings(serde_json::json!({
+            "respond_mode": "prefix"
+        })));
+        assert!(policy.should_process_group(false));
+        assert_eq!(
+            policy.group_text("!ai what time is it").as_deref(),
+            Some("what time is it")
+        );
+        assert_eq!(policy.group_text("  !AI: hello").as_deref(), Some("hello"));
+        assert_eq!(policy.group_text("!aim high"), None);
+        assert_eq!(policy.group_text("just chatting"), None);
+
+        let policy = ChannelPolicy::from_settings(&settings(serde_json::json!({
+            "respond_mode": "prefix",
+            "respond_prefix": "bot,"
+        })));
+        assert_eq!(policy.group_text("bot, hi").as_deref(), Some("hi"));
+        assert_eq!(policy.group_text("!ai hi"), None);
+
+        assert_eq!(
+            ChannelPolicy::default().group_text("anything").as_deref(),
+            Some("anything")
+        );
+    }
+
     #[test]
     fn authorize_dm_variants() {
         // None -> UseGlobalAllowlist
@@ -387,6 +570,47 @@ mod tests {
         assert!(allowlist.is_owner("user1"));
     }
 
+    #[test]
+    fn check_dm_auth_disabled_auto_claim_rejects_unknown_claimant() {
+        let policy = ChannelPolicy::default();
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.set_claim_policy(ClaimPolicy::Configured(Some("owner1".to_string())));
+        let pairing = Mutex::new(PairingManager::new(Duration::from_secs(300)));
+
+        let result = check_dm_auth(
+            &policy,
+            &mut allowlist,
+            &pairing,
+            "intruder",
+            "Mallory",
+            "hi",
+            "test",
+        );
+        assert_eq!(result, Ok(Some(policy.messages.blocked("Mallory"))));
+        assert!(allowlist.owner().is_none());
+        assert!(!allowlist.is_allowed("intruder"));
+    }
+
+    #[test]
+    fn check_dm_auth_disabled_auto_claim_accepts_configured_owner() {
+        let policy = ChannelPolicy::default();
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.set_claim_policy(ClaimPolicy::Configured(Some("owner1".to_string())));
+        let pairing = Mutex::new(PairingManager::new(Duration::from_secs(300)));
+
+        let result = check_dm_auth(
+            &policy,
+            &mut allowlist,
+            &pairing,
+            "owner1",
+            "Alice",
+            "hi",
+            "test",
+        );
+        assert_eq!(result, Ok(Some(policy.messages.welcome_owner("Alice"))));
+        assert!(allowlist.is_owner("owner1"));
+    }
+
     #[test]
     fn check_dm_auth_pairing_code_claim() {
         let policy = ChannelPolicy::default();
@@ -412,6 +636,28 @@ mod tests {
         assert!(allowlist.is_allowed("user2"));
     }
 
+    #[test]
+    fn check_dm_auth_link_code_links_identity() {
+        let policy = ChannelPolicy::default();
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        allowlist.claim_owner("owner1");
+        let pairing = Mutex::new(PairingManager::new(Duration::from_secs(300)));
+        let code = pairing.lock().unwrap().generate_link("owner1");
+
+        let reply = check_dm_auth(
+            &policy,
+            &mut allowlist,
+            &pairing,
+            "owner1-discord",
+            "Owner",
+            &code,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(reply.as_deref(), Some(LINKED_REPLY));
+        assert!(allowlist.is_owner("owner1-discord"));
+    }
+
     #[test]
     fn check_dm_auth_unknown_user_gets_pairing_prompt() {
         let policy = ChannelPolicy::default();
@@ -534,4 +780,87 @@ mod tests {
         // None group_policy -> process all
         assert!(policy.should_process_group(false));
     }
+
+    #[test]
+    fn messages_substitute_user_name_placeholder() {
+        let messages = ChannelMessages {
+            welcome_owner: Some("Hi {user_name}, this bot is yours.".to_string()),
+            welcome_user: Some("{user_name} is in! Welcome, {user_name}.".to_string()),
+            blocked: Some("Sorry {user_name}, ask the owner for a code.".to_string()),
+        };
+        assert_eq!(
+            messages.welcome_owner("Alice"),
+            "Hi Alice, this bot is yours."
+        );
+        assert_eq!(messages.welcome_user("Bob"), "Bob is in! Welcome, Bob.");
+        assert_eq!(
+            messages.blocked("Eve"),
+            "Sorry Eve, ask the owner for a code."
+        );
+        // Templates without the placeholder are sent verbatim.
+        let plain = ChannelMessages {
+            blocked: Some("Private bot.".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(plain.blocked("Eve"), "Private bot.");
+    }
+
+    #[test]
+    fn check_dm_auth_uses_custom_messages() {
+        let policy = ChannelPolicy {
+            messages: ChannelMessages {
+                welcome_owner: Some("Owner: {user_name}".to_string()),
+                blocked: Some("Go away, {user_name}".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut allowlist = Allowlist::restricted(Vec::<String>::new());
+        let pairing = Mutex::new(PairingManager::new(Duration::from_secs(300)));
+
+        let owner = check_dm_auth(
+            &policy,
+            &mut allowlist,
+            &pairing,
+            "user1",
+            "Alice",
+            "hi",
+            "test",
+        );
+        assert_eq!(owner, Ok(Some("Owner: Alice".to_string())));
+
+        let stranger = check_dm_auth(
+            &policy,
+            &mut allowlist,
+            &pairing,
+            "user2",
+            "Mallory",
+            "hello",
+            "test",
+        );
+        assert_eq!(stranger, Ok(Some("Go away, Mallory".to_string())));
+    }
+
+    #[test]
+    fn channel_messages_override_global_defaults() {
+        let mut settings = std::collections::HashMap::new();
+        settings.insert(
+            "messages".to_string(),
+            serde_json::json!({ "welcome_user": "Bienvenue {user_name}" }),
+        );
+        let global = ChannelMessages {
+            welcome_user: Some("Welcome {user_name}".to_string()),
+            blocked: Some("Private.".to_string()),
+            ..Default::default()
+        };
+        let policy = ChannelPolicy::from_settings(&settings).with_default_messages(&global);
+        assert_eq!(policy.messages.welcome_user("Ana"), "Bienvenue Ana");
+        assert_eq!(policy.messages.blocked("Ana"), "Private.");
+        assert!(
+            policy
+                .messages
+                .welcome_owner("Ana")
+                .starts_with("Welcome, Ana!")
+        );
+    }
 }
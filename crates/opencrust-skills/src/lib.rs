@@ -2,8 +2,10 @@ pub mod installer;
 pub mod parser;
 pub mod scanner;
 pub mod security;
+pub mod template;
 
 pub use installer::SkillInstaller;
 pub use parser::{SkillDefinition, SkillFrontmatter, parse_skill, validate_skill};
 pub use scanner::SkillScanner;
 pub use security::scan_skill;
+pub use template::{SimpleTemplateEngine, TemplateContext, TemplateEngine};
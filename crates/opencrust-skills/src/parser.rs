@@ -15,6 +15,9 @@ pub struct SkillFrontmatter {
     pub triggers: Vec<String>,
     #[serde(default)]
     pub dependencies: Vec<String>,
+    /// Render the body as a template (see [`crate::template`]) when injected.
+    #[serde(default)]
+    pub template: bool,
 
     // ── agentskills.io compatible fields ────────────────────────────────
     /// Skill version string (e.g. "1.0.0"). Optional.
@@ -39,6 +42,18 @@ pub struct SkillDefinition {
     pub source_path: Option<PathBuf>,
 }
 
+impl SkillDefinition {
+    /// Body as it should appear in a prompt block that is later rendered:
+    /// template skills are kept as-is, all others are escaped to stay literal.
+    pub fn prompt_body(&self) -> String {
+        if self.frontmatter.template {
+            self.body.clone()
+        } else {
+            crate::template::escape(&self.body)
+        }
+    }
+}
+
 /// Parse a SKILL.md file: YAML frontmatter between `---` delimiters, followed by markdown body.
 pub fn parse_skill(content: &str) -> Result<SkillDefinition> {
     let trimmed = content.trim_start();
@@ -106,6 +121,9 @@ pub fn validate_skill(skill: &SkillDefinition) -> Result<()> {
             "compatibility field exceeds 500 characters".into(),
         ));
     }
+    if skill.frontmatter.template {
+        crate::template::validate(&skill.body)?;
+    }
     Ok(())
 }
 
@@ -241,4 +259,18 @@ Follow the four phases systematically.
         assert!(skill.frontmatter.metadata.is_none());
         validate_skill(&skill).unwrap();
     }
+
+    #[test]
+    fn template_skill_with_unknown_directive_fails_validation() {
+        let content =
+            "---\nname: t\ndescription: d\ntemplate: true\n---\n{{#each facts}}x{{/each}}";
+        let skill = parse_skill(content).unwrap();
+        let err = validate_skill(&skill).unwrap_err().to_string();
+        assert!(err.contains("unknown template directive"));
+
+        // The same body is fine when the skill is not a template.
+        let plain = parse_skill(&content.replace("template: true\n", "")).unwrap();
+        validate_skill(&plain).unwrap();
+        assert_eq!(plain.prompt_body(), "\\{{#each facts}}x\\{{/each}}");
+    }
 }
@@ -60,6 +60,7 @@ mod tests {
                 rationale: None,
                 triggers: vec![],
                 dependencies: vec![],
+                template: false,
                 version: None,
                 license: None,
                 compatibility: None,
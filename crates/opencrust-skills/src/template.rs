@@ -0,0 +1,298 @@
+//! Minimal template syntax for skill bodies, rendered at injection time.
+//!
+//! Only skills with `template: true` in their frontmatter are rendered; other
+//! bodies are passed through [`escape`] so they stay literal.
+//!
+//! Syntax:
+//! - `{{ name }}` inserts a variable (empty when it is not set).
+//! - `{{#if name}} ... {{else}} ... {{/if}}` keeps a section when the variable
+//!   is set and non-empty. `{{else}}` is optional and blocks may nest.
+//! - `\{{` produces a literal `{{`.
+//!
+//! Values are inserted as-is and never re-parsed, so recalled text cannot
+//! inject directives. Any other `{{#...}}` or `{{/...}}` tag is an error.
+
+use opencrust_common::{Error, Result};
+use std::collections::HashMap;
+
+/// Renders a template against a context. Implement this to swap in another
+/// template language for skills.
+pub trait TemplateEngine: Send + Sync {
+    fn render(&self, template: &str, ctx: &TemplateContext) -> Result<String>;
+}
+
+/// The built-in engine implementing the syntax described in the module docs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimpleTemplateEngine;
+
+impl TemplateEngine for SimpleTemplateEngine {
+    fn render(&self, template: &str, ctx: &TemplateContext) -> Result<String> {
+        render(template, ctx)
+    }
+}
+
+/// Variables available to a template.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateContext {
+    vars: HashMap<String, String>,
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+}
+
+/// Render `template` with the built-in syntax.
+pub fn render(template: &str, ctx: &TemplateContext) -> Result<String> {
+    let nodes = parse(template)?;
+    let mut out = String::with_capacity(template.len());
+    render_nodes(&nodes, ctx, &mut out);
+    Ok(out)
+}
+
+/// Check that `template` parses, without rendering it.
+pub fn validate(template: &str) -> Result<()> {
+    parse(template).map(|_| ())
+}
+
+/// Escape `text` so that rendering it returns it unchanged.
+pub fn escape(text: &str) -> String {
+    text.replace("{{", "\\{{")
+}
+
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+enum Node {
+    Text(String),
+    Var(String),
+    If {
+        name: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+struct IfFrame {
+    name: String,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+impl IfFrame {
+    fn current(&mut self) -> &mut Vec<Node> {
+        self.otherwise.as_mut().unwrap_or(&mut self.then)
+    }
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("\\{{") {
+            text.push_str("{{");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{{") {
+            let end = after
+                .find("}}")
+                .ok_or_else(|| Error::Skill("unclosed `{{` in skill template".into()))?;
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(Token::Tag(after[..end].trim().to_string()));
+            rest = &after[end + 2..];
+        } else {
+            let ch = rest.chars().next().unwrap_or_default();
+            text.push(ch);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+fn parse(template: &str) -> Result<Vec<Node>> {
+    let mut root = Vec::new();
+    let mut stack: Vec<IfFrame> = Vec::new();
+
+    for token in tokenize(template)? {
+        let tag = match token {
+            Token::Text(text) => {
+                current(&mut root, &mut stack).push(Node::Text(text));
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+
+        if let Some(directive) = tag.strip_prefix('#') {
+            let mut parts = directive.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("if"), Some(name), None) if is_variable_name(name) => {
+                    stack.push(IfFrame {
+                        name: name.to_string(),
+                        then: Vec::new(),
+                        otherwise: None,
+                    });
+                }
+                _ => {
+                    return Err(Error::Skill(format!(
+                        "unknown template directive `{{{{{tag}}}}}`"
+                    )));
+                }
+            }
+        } else if tag == "else" {
+            match stack.last_mut() {
+                Some(frame) if frame.otherwise.is_none() => frame.otherwise = Some(Vec::new()),
+                _ => {
+                    return Err(Error::Skill(
+                        "`{{else}}` outside of an `{{#if}}` block".into(),
+                    ));
+                }
+            }
+        } else if tag == "/if" {
+            let frame = stack
+                .pop()
+                .ok_or_else(|| Error::Skill("`{{/if}}` without a matching `{{#if}}`".into()))?;
+            current(&mut root, &mut stack).push(Node::If {
+                name: frame.name,
+                then: frame.then,
+                otherwise: frame.otherwise.unwrap_or_default(),
+            });
+        } else if is_variable_name(&tag) {
+            current(&mut root, &mut stack).push(Node::Var(tag));
+        } else {
+            return Err(Error::Skill(format!(
+                "unknown template directive `{{{{{tag}}}}}`"
+            )));
+        }
+    }
+
+    if let Some(frame) = stack.last() {
+        return Err(Error::Skill(format!(
+            "unclosed `{{{{#if {}}}}}` in skill template",
+            frame.name
+        )));
+    }
+    Ok(root)
+}
+
+fn current<'a>(root: &'a mut Vec<Node>, stack: &'a mut [IfFrame]) -> &'a mut Vec<Node> {
+    match stack.last_mut() {
+        Some(frame) => frame.current(),
+        None => root,
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn render_nodes(nodes: &[Node], ctx: &TemplateContext, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var(name) => out.push_str(ctx.get(name).unwrap_or_default()),
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                let truthy = ctx.get(name).is_some_and(|v| !v.trim().is_empty());
+                render_nodes(if truthy { then } else { otherwise }, ctx, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> TemplateContext {
+        let mut ctx = TemplateContext::new();
+        ctx.set("user_name", "Alice").set("date", "2026-10-15");
+        ctx
+    }
+
+    #[test]
+    fn renders_variables_and_conditionals() {
+        let template = "Hi {{ user_name }}, today is {{date}}.\n\
+                        {{#if memories}}Recall: {{memories}}{{else}}No memories.{{/if}}";
+        assert_eq!(
+            render(template, &ctx()).unwrap(),
+            "Hi Alice, today is 2026-10-15.\nNo memories."
+        );
+
+        let mut with_memories = ctx();
+        with_memories.set("memories", "likes tea");
+        assert_eq!(
+            render(template, &with_memories).unwrap(),
+            "Hi Alice, today is 2026-10-15.\nRecall: likes tea"
+        );
+    }
+
+    #[test]
+    fn missing_variable_renders_empty() {
+        assert_eq!(
+            render("[{{ nickname }}]", &TemplateContext::new()).unwrap(),
+            "[]"
+        );
+    }
+
+    #[test]
+    fn nested_if_blocks() {
+        let template = "{{#if user_name}}A{{#if missing}}B{{else}}C{{/if}}{{/if}}";
+        assert_eq!(render(template, &ctx()).unwrap(), "AC");
+    }
+
+    #[test]
+    fn values_are_not_reparsed() {
+        let mut ctx = TemplateContext::new();
+        ctx.set("memories", "{{#each}} {{ date }}");
+        assert_eq!(
+            render("{{memories}}", &ctx).unwrap(),
+            "{{#each}} {{ date }}"
+        );
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        let literal = "use ${{ secrets.TOKEN }} and \\n";
+        assert_eq!(render(&escape(literal), &ctx()).unwrap(), literal);
+    }
+
+    #[test]
+    fn unknown_directive_is_an_error() {
+        let err = render("{{#each items}}x{{/each}}", &ctx()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown template directive `{{#each items}}`")
+        );
+    }
+
+    #[test]
+    fn unbalanced_blocks_are_errors() {
+        assert!(render("{{#if a}}x", &ctx()).is_err());
+        assert!(render("x{{/if}}", &ctx()).is_err());
+        assert!(render("{{else}}", &ctx()).is_err());
+        assert!(render("{{ user_name", &ctx()).is_err());
+    }
+}
@@ -12,6 +12,8 @@
  *
  * Stdin commands:
  *   {"type":"send","to":"<jid>","text":"<body>"}
+ *   {"type":"send_media","to":"<jid>","kind":"image|audio|video|document",
+ *    "data":"<base64>","mimetype":"<mime>","fileName":"<name>","caption":"<text>"}
  *   {"type":"ping"} -> responds {"type":"pong"}
  */
 
@@ -136,6 +138,30 @@ async function start() {
         process.stderr.write(`send error: ${err.message}\n`);
       }
     }
+
+    if (cmd.type === "send_media" && cmd.to && cmd.data) {
+      const media = Buffer.from(cmd.data, "base64");
+      const caption = cmd.caption || undefined;
+      const content =
+        cmd.kind === "image" ? { image: media, caption, mimetype: cmd.mimetype || undefined }
+        : cmd.kind === "audio" ? { audio: media, mimetype: cmd.mimetype || "audio/ogg; codecs=opus" }
+        : cmd.kind === "video" ? { video: media, caption, mimetype: cmd.mimetype || undefined }
+        : {
+            document: media,
+            caption,
+            mimetype: cmd.mimetype || "application/octet-stream",
+            fileName: cmd.fileName,
+          };
+      try {
+        await sock.sendMessage(cmd.to, content);
+        // Audio messages carry no caption, so send it separately.
+        if (cmd.kind === "audio" && caption) {
+          await sock.sendMessage(cmd.to, { text: caption });
+        }
+      } catch (err) {
+        process.stderr.write(`send error: ${err.message}\n`);
+      }
+    }
   });
 
   rl.on("close", () => {